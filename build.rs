@@ -0,0 +1,59 @@
+//! Embeds the build information `stacks --version` prints: commit, build date and target triple
+//!
+//! Packagers building from a source tarball (no .git) can set STACKS_GIT_SHA; SOURCE_DATE_EPOCH
+//! pins the build date for reproducible builds.
+
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn main() {
+    println!("cargo:rustc-env=STACKS_GIT_SHA={}", git_sha());
+    println!("cargo:rustc-env=STACKS_BUILD_DATE={}", build_date());
+    println!("cargo:rustc-env=STACKS_TARGET={}", std::env::var("TARGET").unwrap_or_else(|_| "unknown".to_string()));
+
+    println!("cargo:rerun-if-env-changed=STACKS_GIT_SHA");
+    println!("cargo:rerun-if-env-changed=SOURCE_DATE_EPOCH");
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    // HEAD only changes when switching branches; commits move the branch it points at
+    if let Some(reference) = std::fs::read_to_string(".git/HEAD").ok().and_then(|head| head.strip_prefix("ref: ").map(|r| r.trim().to_string())) {
+        println!("cargo:rerun-if-changed=.git/{}", reference);
+    }
+}
+
+fn git_sha() -> String {
+    if let Ok(sha) = std::env::var("STACKS_GIT_SHA") {
+        return sha;
+    }
+    Command::new("git")
+        .args(["rev-parse", "--short=12", "HEAD"])
+        .output()
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).trim().to_string())
+        .filter(|sha| !sha.is_empty())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+/// UTC date of the build as YYYY-MM-DD
+fn build_date() -> String {
+    let seconds = std::env::var("SOURCE_DATE_EPOCH")
+        .ok()
+        .and_then(|epoch| epoch.parse::<i64>().ok())
+        .unwrap_or_else(|| SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs() as i64).unwrap_or(0));
+    let (year, month, day) = civil_from_days(seconds.div_euclid(86_400));
+    format!("{:04}-{:02}-{:02}", year, month, day)
+}
+
+/// Proleptic Gregorian date of a day count since 1970-01-01 (Howard Hinnant's algorithm)
+fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1_460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
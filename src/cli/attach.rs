@@ -0,0 +1,76 @@
+use std::process::Command;
+use anyhow::{Result, Context};
+
+use crate::core::sessions::{live_targets, SessionRegistry};
+use crate::error::StacksError;
+use crate::utils::dependency_check::{require, Dependency};
+use crate::utils::fuzzy::Picker;
+use crate::utils::process::Process;
+
+/// Fuzzy-pick a tmux session created by stacks (or any other live window) and jump to it
+pub async fn run(query: Option<String>) -> Result<()> {
+    require(Dependency::Tmux)?;
+
+    let live = live_targets();
+    let mut registry = SessionRegistry::load()?;
+    if registry.retain_live(&live) > 0 {
+        registry.save()?;
+    }
+
+    // Sessions stacks created come first; other windows remain reachable too
+    let mut lines: Vec<String> = registry.sorted()
+        .iter()
+        .map(|session| format!(
+            "{}\t{}\t{}\t{}",
+            session.target,
+            session.task,
+            session.worktree.display(),
+            session.created_at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M")
+        ))
+        .collect();
+    let mut windows: Vec<&String> = live.iter()
+        .filter(|target| target.contains(':') && !registry.sessions.iter().any(|session| &session.target == *target))
+        .collect();
+    windows.sort();
+    lines.extend(windows.into_iter().map(|target| format!("{}\t(untracked)", target)));
+
+    if lines.is_empty() {
+        return Err(StacksError::tmux("No tmux sessions to attach to")
+            .with_hint("Create one with `stacks worktree`")
+            .into());
+    }
+
+    let selected = Picker::new("Attach to: ")
+        .with_query(query)
+        .with_fallback_hint("Name one session, e.g. 'stacks attach <task>', or jump with 'stacks open <task>'")
+        .pick(&lines)?;
+    let target = match selected.first().and_then(|line| line.split('\t').next()) {
+        Some(target) => target.to_string(),
+        None => return Ok(()),
+    };
+
+    registry.mark_attached(&target);
+    registry.save()?;
+    jump_to(&target)
+}
+
+/// Switch the current client to `target`, or attach to it from outside tmux
+pub fn jump_to(target: &str) -> Result<()> {
+    if std::env::var_os("TMUX").is_some() {
+        Process::tmux()
+            .args(["switch-client", "-t", target])
+            .run()
+            .with_context(|| format!("Failed to switch to {}", target))?;
+        return Ok(());
+    }
+
+    // Attaching takes over the terminal, so it can't go through the captured-output runner
+    let status = Command::new("tmux")
+        .args(["attach-session", "-t", target])
+        .status()
+        .context("Failed to run tmux attach-session")?;
+    if !status.success() {
+        return Err(StacksError::tmux(format!("Failed to attach to {}", target)).into());
+    }
+    Ok(())
+}
@@ -0,0 +1,78 @@
+use anyhow::Result;
+
+use crate::config::load_config;
+use crate::core::auth::{self, DEFAULT_SCOPES};
+use crate::core::remote_stack_manager::github_token;
+use crate::error::StacksError;
+use crate::utils::terminal;
+
+/// Sign in to GitHub with the device flow and keep the token in the system keyring
+pub async fn login(client_id: Option<String>, scopes: Option<String>) -> Result<()> {
+    let client_id = match client_id.or(load_config()?.github_client_id) {
+        Some(client_id) => client_id,
+        None => {
+            return Err(StacksError::validation("No GitHub OAuth app configured for login")
+                .with_hint("Register an OAuth app with device flow enabled, then run 'stacks config set github-client-id <client id>'")
+                .into())
+        }
+    };
+    let scopes = scopes.unwrap_or_else(|| DEFAULT_SCOPES.to_string());
+    let client = reqwest::Client::new();
+
+    let code = auth::request_device_code(&client, &client_id, &scopes).await?;
+    outln!("🔑 Open {} and enter the code: {}", code.verification_uri, code.user_code);
+    // Best-effort; the URL is printed anyway
+    terminal::open_browser(&code.verification_uri);
+    outln!("⏳ Waiting for you to authorize stacks (scopes: {})...", scopes);
+
+    let token = auth::wait_for_token(&client, &client_id, &code).await?;
+    auth::store_token(&token)?;
+    match auth::token_login(&client, &token).await {
+        Ok(login) => outln!("✅ Logged in to GitHub as {}", login),
+        Err(error) => {
+            tracing::debug!("failed to look up the GitHub user: {:#}", error);
+            outln!("✅ Logged in to GitHub");
+        }
+    }
+    outln!("💡 The token is stored in the system keyring; GITHUB_TOKEN and GH_TOKEN still take precedence");
+    Ok(())
+}
+
+/// Forget the token stored by `stacks auth login`
+pub async fn logout() -> Result<()> {
+    match auth::delete_token()? {
+        true => outln!("👋 Removed the GitHub token from the system keyring"),
+        false => outln!("📭 No GitHub token stored by 'stacks auth login'"),
+    }
+    Ok(())
+}
+
+/// Show which GitHub token stacks would use and whose it is
+pub async fn status() -> Result<()> {
+    let source = token_source();
+    let token = match github_token() {
+        Some(token) => token,
+        None => {
+            outln!("🔓 Not logged in; private registries and stacks won't be reachable");
+            outln!("💡 Run 'stacks auth login', or export GITHUB_TOKEN");
+            return Ok(());
+        }
+    };
+    match auth::token_login(&reqwest::Client::new(), &token).await {
+        Ok(login) => outln!("✅ Logged in to GitHub as {} (token from {})", login, source),
+        Err(error) => outln!("❌ The token from {} doesn't work: {:#}", source, error),
+    }
+    Ok(())
+}
+
+/// Where the token `github_token` finds comes from, in the order it looks
+pub(crate) fn token_source() -> &'static str {
+    if ["GITHUB_TOKEN", "GH_TOKEN"].iter().any(|var| std::env::var(var).is_ok_and(|token| !token.trim().is_empty())) {
+        "environment (GITHUB_TOKEN / GH_TOKEN)"
+    } else if auth::stored_token().is_some() {
+        "system keyring (stacks auth login)"
+    } else {
+        "gh CLI"
+    }
+}
+
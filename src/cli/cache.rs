@@ -0,0 +1,77 @@
+use std::time::SystemTime;
+use anyhow::Result;
+
+use crate::core::cache::{self, CacheKind};
+
+/// Show where the cache is, how big it is, and which registries are indexed
+pub async fn info() -> Result<()> {
+    let root = cache::cache_root()?;
+    let entries = cache::entries(&root);
+    let now = SystemTime::now();
+    let total: u64 = entries.iter().map(|entry| entry.size).sum();
+
+    outln!("📁 Cache: {}", root.display());
+    outln!("   Size: {} of {} cap", cache::format_size(total), cache::format_size(cache::max_bytes()));
+
+    for kind in CacheKind::all() {
+        let of_kind: Vec<_> = entries.iter().filter(|entry| entry.kind == kind).collect();
+        if of_kind.is_empty() {
+            continue;
+        }
+        let size: u64 = of_kind.iter().map(|entry| entry.size).sum();
+        outln!("   {}: {} file(s), {}", kind.as_str(), of_kind.len(), cache::format_size(size));
+    }
+
+    let indexes: Vec<_> = entries.iter().filter(|entry| entry.kind == CacheKind::RegistryIndex).collect();
+    if !indexes.is_empty() {
+        outln!("\n📚 Registry indexes:");
+        for entry in indexes {
+            let index = std::fs::read_to_string(&entry.path)
+                .ok()
+                .and_then(|content| serde_json::from_str::<cache::RegistryIndex>(&content).ok());
+            let stale = if entry.is_stale(now) { " (stale)" } else { "" };
+            match index {
+                Some(index) => outln!(
+                    "   {} - {} stack(s), fetched {}{}",
+                    index.registry,
+                    index.stacks.len(),
+                    index.fetched_at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M"),
+                    stale
+                ),
+                None => outln!("   {} (unreadable){}", entry.path.display(), stale),
+            }
+        }
+    }
+
+    let stale = entries.iter().filter(|entry| entry.is_stale(now)).count();
+    if stale > 0 {
+        outln!("\n💡 {} stale file(s); run 'stacks cache prune' to remove them", stale);
+    }
+
+    Ok(())
+}
+
+/// Remove everything in the cache
+pub async fn clear() -> Result<()> {
+    let root = cache::cache_root()?;
+    let freed = cache::clear(&root)?;
+    outln!("🧹 Cleared {} ({} freed)", root.display(), cache::format_size(freed));
+    Ok(())
+}
+
+/// Remove stale entries and enforce the size cap
+pub async fn prune() -> Result<()> {
+    let root = cache::cache_root()?;
+    let removed = cache::prune(&root, SystemTime::now(), cache::max_bytes())?;
+    if removed.is_empty() {
+        outln!("✅ Nothing to prune in {}", root.display());
+        return Ok(());
+    }
+
+    for entry in &removed {
+        outln!("  🗑️ {}", entry.path.strip_prefix(&root).unwrap_or(&entry.path).display());
+    }
+    let freed: u64 = removed.iter().map(|entry| entry.size).sum();
+    outln!("🧹 Pruned {} file(s), {} freed", removed.len(), cache::format_size(freed));
+    Ok(())
+}
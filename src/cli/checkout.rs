@@ -1,27 +1,51 @@
 use anyhow::{Result, Context};
 use dialoguer::{Confirm, Input};
-use skim::prelude::*;
-use std::io::Cursor;
-use std::process::Command;
-use std::path::PathBuf;
-
+use std::path::{Path, PathBuf};
+
+use crate::cli::worktree::{describe_worktree_scope, get_current_branch, select_branch_strategy, BranchStrategy};
+use crate::core::policy::{self, Policy};
+use crate::core::review::{Quarantined, StackReview};
+use crate::core::signatures;
+use crate::core::hooks::{self, HookContext, Operation, Phase};
+use crate::core::journal::{Journal, JournalEntry, StackChange};
+use crate::core::prompts::{self, PromptSource};
+use crate::core::sessions::{label_target, record_session, task_label};
+use crate::core::session_manifest::{SessionManifest, MANIFEST_FILE};
+use crate::core::git::Repo;
 use crate::core::stack_manager::Stack;
-use crate::core::remote_stack_manager::RemoteStackManager;
+use crate::core::remote_stack_manager::{RemoteStackManager, StackSource};
 use crate::core::symlink_manager::SymlinkManager;
 use crate::core::settings_merger::SettingsMerger;
 use crate::core::mcp_validator::McpValidator;
-use crate::core::permission_generator::PermissionGenerator;
+use crate::core::permission_generator::{protected_paths, PermissionGenerator, PermissionTemplate};
 use crate::utils::claude_md_updater::ClaudeMdUpdater;
-use crate::utils::dependency_check::check_dependencies;
-
-pub async fn run() -> Result<()> {
-    run_worktree_stack_session().await
+use crate::utils::dependency_check::{require, require_all, Dependency};
+use crate::utils::fuzzy::Picker;
+use crate::utils::logging;
+use crate::error::{not_a_repository, StacksError};
+use crate::utils::process::Process;
+use crate::utils::task_name;
+use crate::utils::terminal::{is_interactive, require_interactive};
+use crate::utils::files;
+
+/// Run the worktree wizard; with a branch strategy it isn't asked for each worktree
+pub async fn run(prompt: Option<PromptSource>, branch_strategy: Option<BranchStrategy>) -> Result<()> {
+    // Load the template up front so a missing file or name fails before anything is created
+    let template = match prompt {
+        Some(source) => Some(source.load(&crate::config::load_config()?.prompts)?),
+        None => None,
+    };
+    run_worktree_stack_session(template.as_deref(), branch_strategy.as_ref()).await
 }
 
 /// Main function implementing the new worktree + tmux + stacks paradigm
-async fn run_worktree_stack_session() -> Result<()> {
-    println!("Setting up worktree-based stack session...");
-    check_dependencies().context("Dependency check failed")?;
+async fn run_worktree_stack_session(prompt_template: Option<&str>, branch_strategy: Option<&BranchStrategy>) -> Result<()> {
+    outln!("Setting up worktree-based stack session...");
+    require_all(&[Dependency::Git, Dependency::Tmux, Dependency::Claude])?;
+    require_interactive(
+        "The worktree wizard",
+        "Create a worktree with 'stacks worktree --task <name>', or install stacks here with 'stacks checkout <stack>...'",
+    )?;
     
     // Get current directory name for tmux window naming
     let cwd = std::env::current_dir()?;
@@ -31,13 +55,8 @@ async fn run_worktree_stack_session() -> Result<()> {
     let tmux_window_name = format!("{}-stacks", cwd_stem);
     
     // Check if we're in a git repository
-    let git_status = Command::new("git")
-        .args(&["status", "--porcelain"])
-        .output()
-        .context("Failed to check git status")?;
-
-    if !git_status.status.success() {
-        anyhow::bail!("Not in a git repository. Please run this command from a git repository.");
+    if Repo::current().is_err() {
+        return Err(not_a_repository().into());
     }
 
     // Create or attach to tmux session
@@ -45,7 +64,7 @@ async fn run_worktree_stack_session() -> Result<()> {
     
     // Main loop - keep adding worktrees until user is done
     loop {
-        if !create_stack_worktree(&tmux_window_name).await? {
+        if !create_stack_worktree(&tmux_window_name, prompt_template, branch_strategy).await? {
             break;
         }
         
@@ -59,9 +78,9 @@ async fn run_worktree_stack_session() -> Result<()> {
         }
     }
     
-    println!("\nStack session setup complete!");
-    println!("Attach to tmux session: tmux attach -t {}", tmux_window_name);
-    println!("Run 'stacks cleanup' when ready to clean up worktrees and merge back");
+    outln!("\nStack session setup complete!");
+    outln!("Attach to tmux session: tmux attach -t {}", tmux_window_name);
+    outln!("Run 'stacks cleanup' when ready to clean up worktrees and merge back");
     
     Ok(())
 }
@@ -69,158 +88,190 @@ async fn run_worktree_stack_session() -> Result<()> {
 /// Set up tmux window for the stack session
 async fn setup_tmux_window(window_name: &str) -> Result<()> {
     // Check if tmux session already exists
-    let session_exists = Command::new("tmux")
-        .args(&["has-session", "-t", window_name])
-        .output()
-        .map(|output| output.status.success())
-        .unwrap_or(false);
+    let session_exists = Process::tmux()
+        .args(["has-session", "-t", window_name])
+        .read_only()
+        .succeeds();
 
     if !session_exists {
         // Create new tmux session
-        println!("Creating tmux session: {}", window_name);
-        Command::new("tmux")
-            .args(&["new-session", "-d", "-s", window_name])
-            .output()
+        outln!("Creating tmux session: {}", window_name);
+        Process::tmux()
+            .args(["new-session", "-d", "-s", window_name])
+            .run()
             .context("Failed to create tmux session")?;
     } else {
-        println!("Using existing tmux session: {}", window_name);
+        outln!("Using existing tmux session: {}", window_name);
     }
     
     Ok(())
 }
 
 /// Create a single worktree with selected stacks and launch Claude
-async fn create_stack_worktree(tmux_session: &str) -> Result<bool> {
+///
+/// With a prompt template, every worktree starts from it instead of asking for a prompt.
+async fn create_stack_worktree(tmux_session: &str, prompt_template: Option<&str>, branch_strategy: Option<&BranchStrategy>) -> Result<bool> {
+    // Discover while the task name and prompt are typed, so the picker opens right away
+    let discovery = tokio::spawn(discover_stacks());
+
     // Get feature/task name from user
-    let feature_name: String = Input::new()
+    let raw_feature_name: String = Input::new()
         .with_prompt("Feature/task name")
         .interact_text()?;
 
-    if feature_name.trim().is_empty() {
+    if raw_feature_name.trim().is_empty() {
         return Ok(false);
     }
+    let feature_name = task_name::sanitize_reporting(&raw_feature_name)?;
+
+    let branch_strategy = match branch_strategy {
+        Some(strategy) => strategy.clone(),
+        None => select_branch_strategy(&get_current_branch()?)?,
+    };
 
     // Get Claude prompt (optional)
-    let claude_prompt: String = Input::new()
-        .with_prompt("Claude prompt (or press Enter for default 'claude')")
-        .default("claude".to_string())
-        .interact_text()?;
+    let claude_prompt: String = match prompt_template {
+        Some(template) => template.to_string(),
+        None => Input::new()
+            .with_prompt("Claude prompt (or press Enter for default 'claude'; {task} and {stacks} are filled in)")
+            .default("claude".to_string())
+            .interact_text()?,
+    };
 
-    // Select stacks using skim
-    let selected_stacks = select_stacks_with_skim().await?;
+    let stacks = discovery.await.context("Stack discovery was interrupted")??;
+    let selected_stacks = select_stacks(&stacks).await?;
+    let stack_names: Vec<&str> = selected_stacks.iter().map(|stack| stack.name.as_str()).collect();
+    let claude_prompt = prompts::render(&claude_prompt, &[
+        ("task", &feature_name),
+        ("stacks", &stack_names.join(", ")),
+    ]);
     
     if selected_stacks.is_empty() {
         // Allow Claude to work without stacks in current directory
-        println!("No stacks selected - Claude will work in current directory without stack configuration");
-        
-        // Create worktree anyway but without stacks
-        let worktree_path = create_worktree_for_feature(&feature_name).await?;
-        
-        // Create new tmux pane and launch Claude with the prompt
-        create_tmux_pane_with_claude(tmux_session, &worktree_path, &claude_prompt).await?;
-        
-        println!("Created worktree '{}' with no stacks (vanilla Claude)", feature_name);
-        return Ok(true);
+        outln!("No stacks selected - Claude will work in current directory without stack configuration");
     }
 
-    // Create worktree
-    let worktree_path = create_worktree_for_feature(&feature_name).await?;
-    
+    // Create worktree, even without stacks
+    let (worktree_path, branch_name) = create_worktree_for_feature(&feature_name, &branch_strategy).await?;
+    let mut manifest = SessionManifest::new(
+        &feature_name,
+        &branch_name,
+        selected_stacks.iter().map(|stack| stack.name.clone()).collect(),
+    );
+
     // Add selected stacks to the worktree
-    add_stacks_to_worktree(&worktree_path, &selected_stacks).await?;
-    
+    if !selected_stacks.is_empty() {
+        add_stacks_to_worktree(&worktree_path, &selected_stacks).await?;
+    }
+
     // Create new tmux pane and launch Claude with the prompt
-    create_tmux_pane_with_claude(tmux_session, &worktree_path, &claude_prompt).await?;
-    
-    println!("Created worktree '{}' with {} stack(s)", feature_name, selected_stacks.len());
-    
+    let pane_id = create_tmux_pane_with_claude(tmux_session, &feature_name, &worktree_path, &claude_prompt).await?;
+    manifest.tmux_target = Some(pane_id);
+    manifest.started_at = Some(chrono::Utc::now());
+    if claude_prompt.trim() != "claude" && !claude_prompt.trim().is_empty() {
+        manifest.prompt = Some(claude_prompt);
+    }
+
+    // The worktree is usable without the manifest, so a failed write only warns
+    if let Err(error) = manifest.save(&worktree_path) {
+        outln!("⚠️ Could not write {}: {:#}", MANIFEST_FILE, error);
+    }
+    outln!("\n{}\n", manifest.summary(&worktree_path));
+
     Ok(true)
 }
 
-/// Use skim to let user select stacks from remote
-async fn select_stacks_with_skim() -> Result<Vec<Stack>> {
-    println!("Discovering remote stacks...");
-    
-    // Discover available stacks from remote
-    let remote_manager = RemoteStackManager::new().context("Failed to initialize remote stack manager")?;
-    let stacks = remote_manager.discover_remote_stacks().await.context("Failed to discover remote stacks")?;
-    
+/// The configured registry's stacks
+async fn discover_stacks() -> Result<Vec<Stack>> {
+    registry_manager()
+        .context("Failed to initialize remote stack manager")?
+        .discover_remote_stacks()
+        .await
+        .context("Failed to discover remote stacks")
+}
+
+/// Let the user fuzzy-pick among the registry's `stacks`
+async fn select_stacks(stacks: &[Stack]) -> Result<Vec<Stack>> {
     if stacks.is_empty() {
-        anyhow::bail!("No stacks found in remote repository");
+        return Err(StacksError::validation("No stacks found in remote repository")
+            .with_hint("Point `stacks config set registry` at a repository with a `stacks/` directory")
+            .into());
     }
 
-    // Prepare items for skim, with option to continue without stacks
+    // Prepare items for the picker, with option to continue without stacks; groups come before single stacks
+    let groups = crate::config::stack_groups().unwrap_or_default();
     let mut items: Vec<String> = vec!["[NONE] - Continue without any stacks (Claude will work in current directory)".to_string()];
+    items.extend(groups.iter().map(|(name, members)| format!("@{} - group: {}", name, members.join(", "))));
     items.extend(stacks.iter().map(|stack| {
         format!("{} - {}", stack.name, stack.description.as_ref().unwrap_or(&"No description".to_string()))
     }));
     
-    let options = SkimOptionsBuilder::default()
-        .height(Some("50%"))
-        .multi(true)
-        .prompt(Some("Select stacks (Tab for multi-select, or choose [NONE] to work without stacks): "))
-        .build()
-        .unwrap();
-
-    let item_reader = SkimItemReader::default();
-    let items = item_reader.of_bufread(Cursor::new(items.join("\n")));
-
-    if let Some(out) = Skim::run_with(&options, Some(items)) {
-        if out.is_abort {
-            return Ok(vec![]);
-        }
-
-        let selected_stacks: Vec<Stack> = out.selected_items
-            .iter()
-            .filter_map(|item| {
-                let item_output = item.output();
-                let item_text = item_output.as_ref();
-                // Find the stack name (everything before the first " - ")
-                let stack_name = item_text.split(" - ").next()?;
-                
-                // Skip the "[NONE]" option
-                if stack_name == "[NONE]" {
-                    return None;
-                }
-                
-                stacks.iter().find(|s| s.name == stack_name).cloned()
-            })
-            .collect();
+    tracing::debug!(elapsed_ms = logging::since_start().as_millis() as u64, stacks = stacks.len(), "opening the stack picker");
+    let picked = Picker::new("Select stacks (Tab for multi-select, or choose [NONE] to work without stacks): ")
+        .with_height("50%")
+        .with_multi(true)
+        .with_fallback_hint("Name the stacks instead: 'stacks checkout <stack>...' or 'stacks checkout --group <group>'")
+        .pick(&items)?;
+
+    let mut selected_stacks: Vec<Stack> = Vec::new();
+    for item_output in &picked {
+        // Find the stack name (everything before the first " - ")
+        let stack_name = match item_output.split(" - ").next() {
+            Some(name) => name.to_string(),
+            None => continue,
+        };
 
-        Ok(selected_stacks)
-    } else {
-        Ok(vec![])
+        // Skip the "[NONE]" option; a group stands for its member stacks
+        let names = match stack_name.strip_prefix('@') {
+            _ if stack_name == "[NONE]" => continue,
+            Some(group) => groups.get(group).cloned().unwrap_or_default(),
+            None => vec![stack_name],
+        };
+        for name in names {
+            match stacks.iter().find(|s| s.name == name) {
+                Some(stack) if !selected_stacks.iter().any(|s| s.name == name) => selected_stacks.push(stack.clone()),
+                Some(_) => {}
+                None => outln!("⚠️ Stack '{}' from the group is not in the registry; skipping", name),
+            }
+        }
     }
+
+    Ok(selected_stacks)
 }
 
 /// Create git worktree for the feature
-async fn create_worktree_for_feature(feature_name: &str) -> Result<PathBuf> {
-    let branch_name = format!("feature-{}", feature_name);
+/// Returns the worktree path and the branch checked out in it
+async fn create_worktree_for_feature(feature_name: &str, branch_strategy: &BranchStrategy) -> Result<(PathBuf, String)> {
+    let (branch_name, start_point) = branch_strategy.resolve(feature_name)?;
     let worktree_path = PathBuf::from(format!("../{}-{}", 
         std::env::current_dir()?.file_stem().unwrap().to_str().unwrap(), 
         feature_name
     ));
 
     // Create branch and worktree
-    println!("Creating worktree at {}", worktree_path.display());
+    outln!("Creating worktree at {}", worktree_path.display());
     
-    let output = Command::new("git")
-        .args(&["worktree", "add", "-b", &branch_name, worktree_path.to_str().unwrap()])
-        .output()
-        .context("Failed to create git worktree")?;
-
-    if !output.status.success() {
-        let error = String::from_utf8_lossy(&output.stderr);
-        anyhow::bail!("Failed to create worktree: {}", error);
+    let worktree_add = match &start_point {
+        Some(start_point) => Process::git().args(["worktree", "add", "-b", &branch_name, worktree_path.to_str().unwrap(), start_point]),
+        None => Process::git().args(["worktree", "add", worktree_path.to_str().unwrap(), &branch_name]),
+    };
+    let output = worktree_add.output().context("Failed to create git worktree")?;
+
+    if !output.success() {
+        return Err(StacksError::git("Failed to create worktree")
+            .with_command(worktree_add.describe(), output.stderr)
+            .with_hint("Pick another branch name, or remove the stale worktree with `git worktree prune`")
+            .into());
     }
 
     // Set up automatic permissions for the feature branch
     setup_feature_permissions(&worktree_path).await?;
+    describe_worktree_scope(feature_name, &branch_name, &worktree_path);
 
-    Ok(worktree_path)
+    Ok((worktree_path, branch_name))
 }
 
-/// Add selected stacks to the worktree using subtree operations
+/// Add selected stacks to the worktree using subtree operations, reusing those the branch already has
 async fn add_stacks_to_worktree(worktree_path: &PathBuf, stacks: &[Stack]) -> Result<()> {
     // Store the original directory
     let original_dir = std::env::current_dir()?;
@@ -228,26 +279,39 @@ async fn add_stacks_to_worktree(worktree_path: &PathBuf, stacks: &[Stack]) -> Re
     // Change to worktree directory
     std::env::set_current_dir(worktree_path)?;
     
-    let remote_manager = RemoteStackManager::new().context("Failed to initialize remote manager")?;
+    let remote_manager = registry_manager().context("Failed to initialize remote manager")?;
+    let signature_config = crate::config::load_config()?.signatures;
     
     for stack in stacks {
-        println!("Adding stack: {}", stack.name);
-        remote_manager.add_stack_subtree(&stack.name).await?;
-        
         // Create a Stack object with the correct worktree-relative path
         let worktree_stack_path = PathBuf::from(format!("stacks/{}", stack.name));
-        let worktree_stack = Stack::new(stack.name.clone(), worktree_stack_path);
-        
+        let worktree_stack = Stack::new(stack.name.clone(), worktree_stack_path.clone());
+
+        // A stack committed on the base branch is already checked out in the worktree;
+        // adding its subtree again would download it a second time and fail on the existing prefix
+        if worktree_stack_path.is_dir() {
+            outln!("♻️ Reusing stack {} from the base branch", stack.name);
+            policy::enforce_stack(&worktree_stack).await?;
+        } else {
+            outln!("Adding stack: {}", stack.name);
+            let (url, branch) = remote_manager.stack_source(&stack.name).await?;
+            let verified = signatures::check(&stack.name, &url, branch.as_deref(), &signature_config)?;
+            remote_manager.add_stack_subtree(&stack.name, verified.as_deref()).await?;
+        }
+
         // Create symlinks and merge settings using the worktree-relative stack
         let symlink_manager = SymlinkManager::new();
         symlink_manager.create_symlinks_for_stack(&worktree_stack).await?;
         
-        let settings_merger = SettingsMerger::new();
+        let config = crate::config::load_config()?;
+        let settings_merger = SettingsMerger::new()
+            .with_scoped_grants(config.permissions.scope_stack_grants)
+            .with_auto_push(config.auto_push);
         settings_merger.merge_stack_settings(&worktree_stack).await?;
         
-        // Add stack import to CLAUDE.md with demarcation
-        let claude_updater = ClaudeMdUpdater::new();
-        claude_updater.add_stack_import_with_demarcation(&stack.name).await?;
+        // Add the stack's section to CLAUDE.md
+        let claude_updater = ClaudeMdUpdater::for_project(&std::env::current_dir()?)?;
+        claude_updater.add_stack_import(&stack.name).await?;
     }
     
     // Return to original directory
@@ -256,184 +320,448 @@ async fn add_stacks_to_worktree(worktree_path: &PathBuf, stacks: &[Stack]) -> Re
     Ok(())
 }
 
-/// Create tmux pane and launch Claude with the given prompt
-async fn create_tmux_pane_with_claude(session: &str, worktree_path: &PathBuf, prompt: &str) -> Result<()> {
+/// Claude panes per tmux window before the checkout wizard opens a new window
+const DEFAULT_MAX_PANES: u32 = 4;
+
+/// Create tmux pane and launch Claude with the given prompt; returns the pane id
+async fn create_tmux_pane_with_claude(session: &str, task: &str, worktree_path: &Path, prompt: &str) -> Result<String> {
     let worktree_abs_path = worktree_path.canonicalize()?;
+    let config = crate::config::load_config()?;
     
-    // Create new pane in the session
-    Command::new("tmux")
-        .args(&[
-            "split-window", "-t", session,
-            "-c", worktree_abs_path.to_str().unwrap()
+    // Splitting one window over and over leaves slivers, so a full window moves on to a new one
+    let max_panes = config.max_panes_per_window.unwrap_or(DEFAULT_MAX_PANES) as usize;
+    let panes = match Process::tmux().args(["list-panes", "-t", session, "-F", "#{pane_id}"]).read_only().output() {
+        Ok(output) if output.success() => output.stdout.lines().count(),
+        _ => 0,
+    };
+    let placement = match panes >= max_panes {
+        true => "new-window",
+        false => "split-window",
+    };
+    let pane = Process::tmux()
+        .args([
+            placement, "-t", session,
+            "-c", worktree_abs_path.to_str().unwrap(),
+            "-P", "-F", "#{pane_id}"
         ])
-        .output()
+        .run()
         .context("Failed to create tmux pane")?;
+    let pane_id = pane.stdout.trim().to_string();
+    if placement == "split-window" {
+        Process::tmux()
+            .args(["select-layout", "-t", session, config.tmux_strategy.layout()])
+            .run()
+            .context("Failed to rebalance tmux panes")?;
+    }
+    label_target(&pane_id, &task_label(task, &worktree_abs_path));
+    record_session(task, &pane_id, &worktree_abs_path);
     
     // Send the Claude command to the new pane
-    let claude_cmd = if prompt == "claude" {
-        "claude".to_string()
-    } else {
-        format!("claude \"{}\"", prompt)
-    };
+    let claude_cmd = config.launch.command_for_pane(&prompts::claude_command(prompt, task)?, 0)?;
     
-    Command::new("tmux")
-        .args(&[
-            "send-keys", "-t", session,
+    Process::tmux()
+        .args([
+            "send-keys", "-t", &pane_id,
             &claude_cmd, "Enter"
         ])
-        .output()
+        .run()
         .context("Failed to send Claude command to tmux pane")?;
     
-    Ok(())
+    Ok(pane_id)
 }
 
-pub async fn run_with_stack(direct_stack: Option<String>) -> Result<()> {
-    use is_terminal::IsTerminal;
-    
-    println!("🔍 Checking dependencies...");
-    check_dependencies().context("Dependency check failed")?;
-    
-    println!("📦 Discovering available stacks...");
-    
-    // Discover available stacks from remote (GitHub)
-    let remote_manager = RemoteStackManager::new().context("Failed to initialize remote stack manager")?;
-    let stacks = remote_manager.discover_remote_stacks().await.context("Failed to discover remote stacks")?;
-    
-    println!("  🌐 Found {} remote stack(s) from GitHub", stacks.len());
-    
-    if stacks.is_empty() {
-        println!("No stacks found in the stacks/ directory.");
-        return Ok(());
+/// A stack chosen for checkout: from the registry, or from its own repository
+enum Selection {
+    Registry(Stack),
+    Source(StackSource),
+}
+
+impl Selection {
+    fn name(&self) -> String {
+        match self {
+            Selection::Registry(stack) => stack.name.clone(),
+            Selection::Source(source) => source.stack_name(),
+        }
     }
 
-    let selected_stacks = if let Some(direct_stack_name) = direct_stack {
-        // Direct stack specified - validate it exists
-        if stacks.iter().any(|s| s.name == direct_stack_name) {
-            println!("🎯 Direct checkout: {}", direct_stack_name);
-            vec![direct_stack_name]
-        } else {
-            println!("❌ Stack '{}' not found. Available stacks:", direct_stack_name);
-            for stack in &stacks {
-                println!("  • {} - {}", stack.name, stack.description.as_ref().unwrap_or(&"No description".to_string()));
+    fn describe(&self) -> String {
+        match self {
+            Selection::Registry(stack) => stack.description.clone().unwrap_or_else(|| "No description".to_string()),
+            Selection::Source(source) => format!("from {}", source.describe()),
+        }
+    }
+}
+
+/// Check out the given stacks and groups, or pick them interactively when none are given
+///
+/// With `review`, each stack is downloaded to a quarantine directory and shown first; only the
+/// approved ones are added to the project.
+pub async fn run_with_stack(mut direct_stacks: Vec<String>, groups: Vec<String>, review: bool) -> Result<()> {
+    if review {
+        require_interactive("--review", "Approving each stack needs a terminal; review the stack repository before a plain checkout instead")?;
+    }
+
+    if !groups.is_empty() {
+        let members = crate::config::expand_groups(&crate::config::stack_groups()?, &groups)?;
+        outln!("👥 Group {}: {}", groups.join(", "), members.join(", "));
+        for member in members {
+            if !direct_stacks.contains(&member) {
+                direct_stacks.push(member);
+            }
+        }
+    }
+
+    // GitHub URLs and owner/repo specs come straight from their repository, without the registry
+    let mut selected: Vec<Selection> = Vec::new();
+    let mut names: Vec<String> = Vec::new();
+    for spec in &direct_stacks {
+        match StackSource::parse(spec) {
+            Some(source) => {
+                policy::enforce_registry(&source.owner, &source.repo).await?;
+                selected.push(Selection::Source(source));
             }
+            None => names.push(spec.clone()),
+        }
+    }
+
+    if direct_stacks.is_empty() || !names.is_empty() {
+        outln!("📦 Discovering available stacks...");
+        
+        // Discover available stacks from remote (GitHub)
+        let remote_manager = registry_manager().context("Failed to initialize remote stack manager")?;
+        let mut stacks = remote_manager.discover_remote_stacks().await.context("Failed to discover remote stacks")?;
+        // The listing may be a recent cached one from before a named stack was added to the registry
+        if names.iter().any(|name| !stacks.iter().any(|stack| &stack.name == name)) {
+            stacks = remote_manager.refresh_remote_stacks().await.context("Failed to discover remote stacks")?;
+        }
+        
+        outln!("  🌐 Found {} remote stack(s) from GitHub", stacks.len());
+        
+        if stacks.is_empty() {
+            outln!("No stacks found in the stacks/ directory.");
             return Ok(());
         }
-    } else {
-        println!("🎯 Select stacks to checkout (use Tab for multi-select, or choose [NONE] to work without stacks):");
-        let selected_stack_objects = select_stacks_with_skim().await?;
-        selected_stack_objects.iter().map(|s| s.name.clone()).collect()
-    };
+
+        let selected_names = if !names.is_empty() {
+            // Stacks named on the command line - validate they all exist before touching anything
+            let unknown: Vec<&str> = names.iter()
+                .filter(|name| !stacks.iter().any(|s| &s.name == *name))
+                .map(|name| name.as_str())
+                .collect();
+            if !unknown.is_empty() {
+                outln!("❌ Stack(s) not found: {}. Available stacks:", unknown.join(", "));
+                for stack in &stacks {
+                    outln!("  • {} - {}", stack.name, stack.description.as_ref().unwrap_or(&"No description".to_string()));
+                }
+                return Ok(());
+            }
+            outln!("🎯 Direct checkout: {}", names.join(", "));
+            names
+        } else {
+            outln!("🎯 Select stacks to checkout (use Tab for multi-select, or choose [NONE] to work without stacks):");
+            let selected_stack_objects = select_stacks(&stacks).await?;
+            selected_stack_objects.iter().map(|s| s.name.clone()).collect()
+        };
+
+        // Keep the order stacks were named in
+        for name in &selected_names {
+            if let Some(stack) = stacks.iter().find(|stack| &stack.name == name) {
+                selected.push(Selection::Registry(stack.clone()));
+            }
+        }
+    }
     
-    if selected_stacks.is_empty() {
-        println!("No stacks selected - Claude will work in the current directory without stack configuration.");
-        println!("💡 Claude Code is ready to use in this directory with default settings.");
+    if selected.is_empty() {
+        outln!("No stacks selected - Claude will work in the current directory without stack configuration.");
+        outln!("💡 Claude Code is ready to use in this directory with default settings.");
         return Ok(());
     }
 
-    // Find selected stack objects
-    let selected_stack_objects: Vec<_> = stacks
-        .iter()
-        .filter(|stack| selected_stacks.contains(&stack.name))
-        .collect();
-
     // Show what will be done
-    println!("\n📋 Selected stacks:");
-    for stack in &selected_stack_objects {
-        println!("  • {} - {}", stack.name, stack.description.as_ref().unwrap_or(&"No description".to_string()));
+    outln!("\n📋 Selected stacks:");
+    for selection in &selected {
+        outln!("  • {} - {}", selection.name(), selection.describe());
     }
 
-    let should_proceed = if std::io::stdin().is_terminal() {
+    let should_proceed = if is_interactive() {
         Confirm::new()
             .with_prompt("Proceed with checkout?")
             .default(true)
             .interact()?
     } else {
-        println!("Auto-proceeding with checkout in non-interactive mode...");
+        outln!("Auto-proceeding with checkout in non-interactive mode...");
         true
     };
 
     if !should_proceed {
-        println!("Checkout cancelled.");
+        outln!("Checkout cancelled.");
         return Ok(());
     }
 
+    // Stacks are added as git subtrees
+    require(Dependency::Git)?;
+
+    let hook_context = HookContext::new(Operation::Checkout)
+        .with_stacks(selected.iter().map(Selection::name).collect());
+    hooks::run(Phase::Pre, &hook_context)?;
+
     // Initialize remote manager for downloading  
-    let remote_manager = RemoteStackManager::new().context("Failed to initialize remote stack manager for processing")?;
+    let remote_manager = registry_manager().context("Failed to initialize remote stack manager for processing")?;
+
+    let signature_config = crate::config::load_config()?.signatures;
+
+    // Recorded after each stack, so `stacks undo` can revert even a checkout that failed halfway
+    let project_root = std::env::current_dir()?;
+    let mut journal_entry = JournalEntry::begin(&project_root, "checkout")?;
 
     // Process each selected stack
-    for stack in selected_stack_objects {
-        println!("\n🔧 Processing stack: {}", stack.name);
+    for selection in &selected {
+        let name = selection.name();
+        outln!("\n🔧 Processing stack: {}", name);
+
+        let already_present = matches!(selection, Selection::Registry(stack) if stack.path.exists());
+        let verified = match already_present {
+            true => None,
+            false => {
+                let (url, branch) = match selection {
+                    Selection::Registry(stack) => remote_manager.stack_source(&stack.name).await?,
+                    Selection::Source(source) => (source.url.clone(), source.branch.clone()),
+                };
+                signatures::check(&name, &url, branch.as_deref(), &signature_config)?
+            }
+        };
+        let reviewed_commit = match review && !already_present {
+            true => match review_selection(&remote_manager, selection).await? {
+                Some(commit) => Some(commit),
+                None => {
+                    outln!("  ⏭️ Not approved; {} was not installed", name);
+                    continue;
+                }
+            },
+            false => None,
+        };
+        // Install exactly the approved commit; upstream moving between the two checks is refused
+        let pinned = match (verified, reviewed_commit) {
+            (Some(verified), Some(reviewed)) if verified != reviewed => {
+                return Err(StacksError::validation(format!(
+                    "{} moved upstream between its signature check and the review (checked {}, reviewed {})",
+                    name, short_commit(&verified), short_commit(&reviewed)
+                ))
+                .with_hint("Run the checkout again to review the new commit")
+                .into());
+            }
+            (verified, reviewed) => reviewed.or(verified),
+        };
         
-        // Add stack as subtree if not already present
-        let stack_path = stack.path.clone();
-        if !stack.path.exists() {
-            // Add stack as git subtree
-            remote_manager.add_stack_subtree(&stack.name).await
-                .with_context(|| format!("Failed to add stack {} as subtree", stack.name))?;
-        } else {
-            println!("  📁 Stack already present: {}", stack.name);
+        let stack = match selection {
+            Selection::Registry(stack) => {
+                // Add stack as subtree if not already present
+                if !stack.path.exists() {
+                    remote_manager.add_stack_subtree(&stack.name, pinned.as_deref()).await
+                        .with_context(|| format!("Failed to add stack {} as subtree", stack.name))?;
+                } else {
+                    outln!("  📁 Stack already present: {}", stack.name);
+                }
+                stack.clone()
+            }
+            Selection::Source(source) => {
+                let stack_path = remote_manager.add_stack_from_source(source, pinned.as_deref()).await
+                    .with_context(|| format!("Failed to add stack {} as subtree", name))?;
+                Stack::new(name.clone(), stack_path)
+            }
+        };
+
+        let mut change = integrate_stack(&stack).await?;
+        change.added_subtree = !already_present;
+        journal_entry.stacks.push(change);
+        Journal::record(&project_root, &journal_entry)?;
+        outln!("  ✅ Stack {} checkout complete", name);
+    }
+
+    report_mcp_servers().await?;
+    report_required_stacks().await?;
+
+    outln!("\n🎉 All selected stacks have been checked out successfully!");
+    outln!("💡 You can now use the agents and commands from the selected stacks.");
+    
+    hooks::run(Phase::Post, &hook_context)
+}
+
+/// Download a selection to quarantine, show what it would add and ask for approval
+///
+/// Returns the reviewed commit when approved. The downloaded copy is removed either way.
+async fn review_selection(remote_manager: &RemoteStackManager, selection: &Selection) -> Result<Option<String>> {
+    let name = selection.name();
+    outln!("  🔒 Downloading {} to quarantine for review...", name);
+    let quarantined = match selection {
+        Selection::Registry(stack) => {
+            let (url, branch) = remote_manager.stack_source(&stack.name).await?;
+            Quarantined::download(&name, &url, branch.as_deref(), None)?
+        }
+        Selection::Source(source) => {
+            Quarantined::download(&name, &source.url, source.branch.as_deref(), source.path.as_deref())?
         }
+    };
 
-        // Update stack with the correct path
-        let cached_stack = if stack_path != stack.path {
-            crate::core::stack_manager::Stack::new(stack.name.clone(), stack_path)
-        } else {
-            stack.clone()
-        };
+    let approved = match StackReview::inspect(&quarantined.stack) {
+        Ok(review) => {
+            let forbidden = match Policy::current().await? {
+                Some(policy) => policy.check_grants("", &review.grants).len(),
+                None => 0,
+            };
+            print_review(&name, &quarantined.commit, &review);
+            if forbidden > 0 {
+                outln!("  ⛔ {} grant(s) are forbidden by the organization policy", forbidden);
+            }
+            Confirm::new()
+                .with_prompt(format!("Install {} into this project?", name))
+                .default(false)
+                .interact()
+        }
+        Err(error) => {
+            quarantined.discard();
+            return Err(error);
+        }
+    };
+    let commit = quarantined.commit.clone();
+    quarantined.discard();
+    Ok(approved?.then_some(commit))
+}
 
-        // Create symlinks for .claude files
-        let symlink_manager = SymlinkManager::new();
-        symlink_manager.create_symlinks_for_stack(&cached_stack).await
-            .with_context(|| format!("Failed to create symlinks for stack {}", cached_stack.name))?;
+fn print_review(name: &str, commit: &str, review: &StackReview) {
+    outln!("\n🔍 Review of {} at {}", name, short_commit(commit));
+    let list = |items: &[String]| match items.is_empty() {
+        true => "none".to_string(),
+        false => items.join(", "),
+    };
+    outln!("  🤖 Agents ({}): {}", review.agents.len(), list(&review.agents));
+    outln!("  ⌨️ Commands ({}): {}", review.commands.len(), list(&review.commands));
+
+    match review.grants.is_empty() {
+        true => outln!("  🔑 Permission grants: none"),
+        false => {
+            outln!("  🔑 Permission grants ({}) - claude may do these without asking:", review.grants.len());
+            for grant in &review.grants {
+                outln!("     ⚠️ {}", grant);
+            }
+        }
+    }
+    match review.hooks.is_empty() {
+        true => outln!("  🪝 Hooks: none"),
+        false => {
+            outln!("  🪝 Hooks ({}) - commands claude runs automatically:", review.hooks.len());
+            for hook in &review.hooks {
+                outln!("     ⚠️ {}", hook);
+            }
+        }
+    }
+    if !review.other_settings.is_empty() {
+        outln!("  ⚙️ Other settings: {}", review.other_settings.join(", "));
+    }
+    if !review.executables.is_empty() {
+        outln!("  📜 Executable files: {}", review.executables.join(", "));
+    }
+    outln!();
+}
 
-        // Merge settings
-        let settings_merger = SettingsMerger::new();
-        settings_merger.merge_stack_settings(&cached_stack).await
-            .with_context(|| format!("Failed to merge settings for stack {}", cached_stack.name))?;
+fn short_commit(commit: &str) -> &str {
+    &commit[..commit.len().min(7)]
+}
 
-        // Update CLAUDE.md
-        let md_updater = ClaudeMdUpdater::new();
-        md_updater.add_stack_import(&cached_stack.name).await
-            .with_context(|| format!("Failed to update CLAUDE.md for stack {}", cached_stack.name))?;
+/// Link a checked-out stack's agents and commands, merge its settings and add it to CLAUDE.md
+///
+/// Returns the links and import it added, for `stacks undo`.
+pub(crate) async fn integrate_stack(stack: &Stack) -> Result<StackChange> {
+    // Refuse stacks whose settings grant what the organization policy forbids; stacks added just
+    // now were checked before their subtree was, this catches those that were already present
+    policy::enforce_stack(stack).await?;
+
+    let mut change = StackChange::new(&stack.name);
+
+    // Create symlinks for .claude files
+    let symlink_manager = SymlinkManager::new();
+    change.links = symlink_manager.create_symlinks_for_stack(stack).await
+        .with_context(|| format!("Failed to create symlinks for stack {}", stack.name))?;
+
+    // Merge settings
+    let config = crate::config::load_config()?;
+    let settings_merger = SettingsMerger::new()
+        .with_scoped_grants(config.permissions.scope_stack_grants)
+        .with_auto_push(config.auto_push);
+    settings_merger.merge_stack_settings(stack).await
+        .with_context(|| format!("Failed to merge settings for stack {}", stack.name))?;
+
+    // Update CLAUDE.md
+    let md_updater = ClaudeMdUpdater::for_project(&std::env::current_dir()?)?;
+    change.added_import = !md_updater.has_stack_import(&stack.name).await?;
+    md_updater.add_stack_import(&stack.name).await
+        .with_context(|| format!("Failed to update CLAUDE.md for stack {}", stack.name))?;
+
+    Ok(change)
+}
 
-        println!("  ✅ Stack {} checkout complete", cached_stack.name);
+/// Mention stacks the organization policy requires that the project still lacks
+///
+/// Only `stacks ci verify` fails on them; a checkout of other stacks isn't the place to refuse.
+async fn report_required_stacks() -> Result<()> {
+    let policy = match Policy::current().await? {
+        Some(policy) => policy,
+        None => return Ok(()),
+    };
+    let installed: Vec<String> = match std::fs::read_dir("stacks") {
+        Ok(entries) => entries
+            .filter_map(|entry| entry.ok())
+            .map(|entry| entry.file_name().to_string_lossy().to_string())
+            .collect(),
+        Err(_) => Vec::new(),
+    };
+    for violation in policy.check_required(&installed) {
+        outln!("⚠️ Policy: {}", violation.message);
     }
+    Ok(())
+}
 
+/// Print install commands for MCP servers the checked-out stacks need but aren't configured
+async fn report_mcp_servers() -> Result<()> {
     // Check for missing MCP servers
-    println!("\n🔍 Checking MCP server requirements...");
+    outln!("\n🔍 Checking MCP server requirements...");
     let mcp_validator = McpValidator::new();
     let missing_servers = mcp_validator.validate_mcp_servers().await
         .context("Failed to validate MCP servers")?;
 
     if !missing_servers.is_empty() {
-        println!("\n⚠️ Missing MCP servers detected:");
+        outln!("\n⚠️ Missing MCP servers detected:");
         let install_commands = mcp_validator.generate_installation_commands(&missing_servers);
         
         for (server, command) in missing_servers.iter().zip(install_commands.iter()) {
-            println!("  • {} ({})", server.name, server.transport);
-            println!("    {}", command);
+            outln!("  • {} ({})", server.name, server.transport);
+            outln!("    {}", command);
         }
-        println!("\nRun the above commands to install missing MCP servers.");
+        outln!("\nRun the above commands to install missing MCP servers.");
     } else {
-        println!("  ✅ All required MCP servers are available");
+        outln!("  ✅ All required MCP servers are available");
     }
 
-    println!("\n🎉 All selected stacks have been checked out successfully!");
-    println!("💡 You can now use the agents and commands from the selected stacks.");
-    
     Ok(())
 }
 
 /// Set up automatic permissions that protect the main directory while allowing full access to the feature directory
 async fn setup_feature_permissions(worktree_path: &PathBuf) -> Result<()> {
-    println!("🛡️ Setting up automatic permissions for feature branch...");
+    outln!("🛡️ Setting up automatic permissions for feature branch...");
     
     // Get the current working directory (main project directory)
     let current_dir = std::env::current_dir()
         .context("Failed to get current working directory")?;
     
+    let app_config = crate::config::load_config()?;
+
+    // Rules come from .stacks/permissions.toml, the configured template, or the built-in default
+    let permission_template = PermissionTemplate::resolve(&current_dir, app_config.permissions.template.clone())?;
+    
     // Create permission generator
-    let permission_generator = PermissionGenerator::new(current_dir.clone(), worktree_path.clone());
+    let permission_generator = PermissionGenerator::new(current_dir.clone(), worktree_path.clone())
+        .with_template(permission_template)
+        .with_protected_paths(protected_paths(&app_config.permissions.protected_paths));
     
     // Apply permissions to the feature directory's .claude/settings.local.json
     let feature_settings_path = worktree_path.join(".claude").join("settings.local.json");
@@ -441,18 +769,23 @@ async fn setup_feature_permissions(worktree_path: &PathBuf) -> Result<()> {
     // Ensure the .claude directory exists in the feature directory
     let claude_dir = worktree_path.join(".claude");
     if !claude_dir.exists() {
-        tokio::fs::create_dir_all(&claude_dir).await
+        files::create_dir_all(&claude_dir)
             .with_context(|| format!("Failed to create .claude directory in {}", worktree_path.display()))?;
     }
     
     permission_generator.apply_to_local_settings(&feature_settings_path).await
         .context("Failed to apply feature permissions")?;
     
-    println!("  ✅ Permissions configured:");
-    println!("    • Full access to: {}", worktree_path.display());
-    println!("    • Read-only access to: {}", current_dir.display());
-    println!("    • Settings saved to: {}", feature_settings_path.display());
+    outln!("  ✅ Permissions configured:");
+    outln!("    • Full access to: {}", worktree_path.display());
+    outln!("    • Read-only access to: {}", current_dir.display());
+    outln!("    • Settings saved to: {}", feature_settings_path.display());
     
     Ok(())
 }
 
+/// Remote stack manager for the configured registry (profile-aware)
+pub(crate) fn registry_manager() -> Result<RemoteStackManager> {
+    let config = crate::config::load_config()?;
+    RemoteStackManager::for_registry(config.registry.as_deref())
+}
@@ -0,0 +1,264 @@
+use std::path::Path;
+use anyhow::{Result, Context};
+use walkdir::WalkDir;
+
+use crate::core::lockfile::{StacksLock, LOCKFILE_NAME};
+use crate::core::policy::{self, Policy, Violation};
+use crate::core::remote_stack_manager::RemoteStackManager;
+use crate::core::schema::{self, Problem, Severity, VerifyOutput, SCHEMA_VERSION};
+use crate::core::stack_manager::Stack;
+use crate::core::symlink_manager::SymlinkManager;
+use crate::core::upstream::{compare_upstream, installed_commit};
+use crate::utils::claude_md_updater::ClaudeMdUpdater;
+use crate::utils::output;
+
+/// GitHub Actions workflow command that shows the problem on the run and the PR diff
+fn annotation(problem: &Problem) -> String {
+    let level = match problem.severity {
+        Severity::Error => "error",
+        Severity::Warning => "warning",
+    };
+    let file = match &problem.file {
+        Some(file) => format!(" file={},", escape_property(file)),
+        None => " ".to_string(),
+    };
+    format!("::{}{}title=stacks::{}", level, file, escape_data(&problem.message))
+}
+
+fn escape_data(value: &str) -> String {
+    value.replace('%', "%25").replace('\r', "%0D").replace('\n', "%0A")
+}
+
+fn escape_property(value: &str) -> String {
+    escape_data(value).replace(':', "%3A").replace(',', "%2C")
+}
+
+/// Fail when the committed stacks are inconsistent: stacks.lock vs stacks/ and upstream,
+/// agent/command symlinks, stack settings and CLAUDE.md
+///
+/// Outdated stacks are warnings unless `strict`; `offline` skips the upstream comparison.
+/// `json` prints the problems as a `VerifyOutput` instead, with progress on stderr.
+pub async fn verify(offline: bool, strict: bool, json: bool) -> Result<()> {
+    if json {
+        output::redirect_to_stderr();
+    }
+    let root = std::env::current_dir()?;
+    let installed = installed_stacks(&root);
+    let mut problems = Vec::new();
+
+    problems.extend(check_lockfile(&root, &installed, offline, strict).await?);
+    problems.extend(check_symlinks(&root, &installed));
+    problems.extend(check_settings(&root, &installed));
+    problems.extend(check_claude_md(&root, &installed).await?);
+    problems.extend(check_policy(&root, &installed).await?);
+
+    let errors = problems.iter().filter(|problem| problem.severity == Severity::Error).count();
+    if json {
+        schema::print(&VerifyOutput { schema_version: SCHEMA_VERSION, stacks: installed, problems, passed: errors == 0 })?;
+        if errors > 0 {
+            anyhow::bail!("Stack verification failed with {} error(s)", errors);
+        }
+        return Ok(());
+    }
+
+    let github = std::env::var_os("GITHUB_ACTIONS").is_some();
+    for problem in &problems {
+        if github {
+            println!("{}", annotation(problem));
+        }
+        let icon = match problem.severity {
+            Severity::Error => "❌",
+            Severity::Warning => "⚠️",
+        };
+        match &problem.file {
+            Some(file) => outln!("{} {}: {}", icon, file, problem.message),
+            None => outln!("{} {}", icon, problem.message),
+        }
+    }
+
+    if errors > 0 {
+        anyhow::bail!("Stack verification failed with {} error(s)", errors);
+    }
+    outln!("✅ {} stack(s) verified{}", installed.len(), if problems.is_empty() { "" } else { " with warnings" });
+    Ok(())
+}
+
+fn installed_stacks(root: &Path) -> Vec<String> {
+    WalkDir::new(root.join("stacks"))
+        .min_depth(1)
+        .max_depth(1)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_dir())
+        .map(|e| e.file_name().to_string_lossy().to_string())
+        .collect()
+}
+
+async fn check_lockfile(root: &Path, installed: &[String], offline: bool, strict: bool) -> Result<Vec<Problem>> {
+    outln!("🔍 Checking {}...", LOCKFILE_NAME);
+    let lock = StacksLock::load_from(&root.join(LOCKFILE_NAME))?;
+    let mut problems = Vec::new();
+
+    for name in installed {
+        if lock.get(name).is_none() {
+            problems.push(Problem::error(
+                Some(LOCKFILE_NAME),
+                format!("Stack {} is in stacks/ but not in {}; run 'stacks checkout {}' to record it", name, LOCKFILE_NAME, name),
+            ));
+        }
+    }
+
+    let manager = match offline {
+        true => None,
+        false => Some(RemoteStackManager::new().context("Failed to initialize remote stack manager")?),
+    };
+    for metadata in &lock.stacks {
+        let name = &metadata.stack_name;
+        if !installed.contains(name) {
+            problems.push(Problem::error(
+                Some(LOCKFILE_NAME),
+                format!("{} lists {}, but stacks/{} does not exist", LOCKFILE_NAME, name, name),
+            ));
+            continue;
+        }
+
+        let subtree_commit = installed_commit(name)?;
+        if let (Some(locked), Some(subtree)) = (&metadata.commit, &subtree_commit) {
+            if locked != subtree {
+                problems.push(Problem::error(
+                    Some(LOCKFILE_NAME),
+                    format!("{} locks {} at {}, but the subtree was last merged from {}", LOCKFILE_NAME, name, short(locked), short(subtree)),
+                ));
+            }
+        }
+
+        let manager = match &manager {
+            Some(manager) => manager,
+            None => continue,
+        };
+        match compare_upstream(manager, metadata, subtree_commit.or_else(|| metadata.commit.clone())).await {
+            Ok(status) if status.is_outdated() => {
+                let message = format!("Stack {} is {}", name, status.describe());
+                problems.push(match strict {
+                    true => Problem::error(Some(LOCKFILE_NAME), message),
+                    false => Problem::warning(Some(LOCKFILE_NAME), message),
+                });
+            }
+            Ok(_) => {}
+            Err(error) => problems.push(Problem::warning(
+                Some(LOCKFILE_NAME),
+                format!("Could not compare {} with {}: {}", name, metadata.source_repo, error),
+            )),
+        }
+    }
+    Ok(problems)
+}
+
+fn check_symlinks(root: &Path, installed: &[String]) -> Vec<Problem> {
+    outln!("🔍 Checking symlinks...");
+    let manager = SymlinkManager::with_claude_dir(root.join(".claude"));
+    let mut problems = Vec::new();
+
+    for name in installed {
+        let health = manager.check_stack_symlinks(&Stack::new(name.clone(), root.join("stacks").join(name)));
+        for link in &health.broken {
+            problems.push(Problem::error(
+                Some(&relative(root, link)),
+                format!("Symlink for stack {} points to a file that no longer exists", name),
+            ));
+        }
+        // Links are local to each checkout; a fresh clone only lacks them until 'stacks repair'
+        if !health.missing.is_empty() {
+            problems.push(Problem::warning(
+                None,
+                format!("Stack {} has {} unlinked agent(s)/command(s); run 'stacks repair {}'", name, health.missing.len(), name),
+            ));
+        }
+    }
+    problems
+}
+
+fn check_settings(root: &Path, installed: &[String]) -> Vec<Problem> {
+    outln!("🔍 Checking settings...");
+    let mut files = vec![root.join(".claude").join("settings.json")];
+    files.extend(installed.iter().map(|name| root.join("stacks").join(name).join(".claude").join("settings.json")));
+
+    let mut problems = Vec::new();
+    for file in files.iter().filter(|file| file.exists()) {
+        let result = std::fs::read_to_string(file)
+            .map_err(|error| error.to_string())
+            .and_then(|content| serde_json::from_str::<serde_json::Value>(&content).map_err(|error| error.to_string()));
+        if let Err(error) = result {
+            problems.push(Problem::error(Some(&relative(root, file)), format!("Invalid settings JSON: {}", error)));
+        }
+    }
+    problems
+}
+
+async fn check_claude_md(root: &Path, installed: &[String]) -> Result<Vec<Problem>> {
+    outln!("🔍 Checking CLAUDE.md...");
+    let issues = ClaudeMdUpdater::for_project(root)?.check(installed).await?;
+    Ok(issues
+        .iter()
+        .map(|issue| Problem::error(Some("CLAUDE.md"), issue.describe()))
+        .collect())
+}
+
+async fn check_policy(root: &Path, installed: &[String]) -> Result<Vec<Problem>> {
+    let policy = match Policy::current().await? {
+        Some(policy) => policy,
+        None => return Ok(Vec::new()),
+    };
+    outln!("🔍 Checking organization policy...");
+    let problem = |file: Option<&str>, violation: Violation| match violation.severity {
+        policy::Severity::Error => Problem::error(file, violation.message),
+        policy::Severity::Warn => Problem::warning(file, violation.message),
+    };
+
+    let mut problems = Vec::new();
+    let lock = StacksLock::load_from(&root.join(LOCKFILE_NAME))?;
+    for metadata in &lock.stacks {
+        if let Some(violation) = policy.check_registry(&metadata.source_owner, &metadata.source_name) {
+            problems.push(problem(Some(LOCKFILE_NAME), violation));
+        }
+    }
+    for file in [".claude/settings.json", ".claude/.local-settings.json"] {
+        let grants = policy::allowed_rules(&root.join(file))?;
+        for violation in policy.check_grants(file, &grants) {
+            problems.push(problem(Some(file), violation));
+        }
+    }
+    for name in installed {
+        let stack = Stack::new(name.clone(), root.join("stacks").join(name));
+        for violation in policy.check_stack(&stack)? {
+            problems.push(problem(Some(&relative(root, &stack.claude_dir.join(".local-settings.json"))), violation));
+        }
+    }
+    for violation in policy.check_required(installed) {
+        problems.push(problem(None, violation));
+    }
+    Ok(problems)
+}
+
+fn relative(root: &Path, path: &Path) -> String {
+    path.strip_prefix(root).unwrap_or(path).display().to_string()
+}
+
+fn short(commit: &str) -> &str {
+    &commit[..commit.len().min(7)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_annotation() {
+        let problem = Problem::error(Some("stacks.lock"), "Stack lint is 2 commits behind upstream");
+        assert_eq!(annotation(&problem), "::error file=stacks.lock,title=stacks::Stack lint is 2 commits behind upstream");
+
+        let problem = Problem::warning(None, "50% done\nnext line");
+        assert_eq!(annotation(&problem), "::warning title=stacks::50%25 done%0Anext line");
+    }
+}
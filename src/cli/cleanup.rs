@@ -1,98 +1,81 @@
 use anyhow::{Result, Context};
 use dialoguer::Confirm;
-use std::process::Command;
 use walkdir::WalkDir;
 use std::path::PathBuf;
 
+use crate::core::hooks::{self, HookContext, Operation, Phase};
+use crate::core::lockfile::resolve_stack_metadata;
+use crate::core::permission_generator::revoke_generated_permissions;
 use crate::core::symlink_manager::SymlinkManager;
+use crate::core::worktrees;
 use crate::utils::claude_md_updater::ClaudeMdUpdater;
+use crate::error::not_a_repository;
+use crate::utils::process::Process;
+use crate::utils::files;
+use crate::utils::terminal::require_interactive;
 
 /// Main cleanup command - push stacks, remove symlinks, clean CLAUDE.md
 pub async fn run() -> Result<()> {
-    println!("Starting stacks cleanup process...");
+    outln!("Starting stacks cleanup process...");
     
     // Check if we're in a git repository
-    let git_status = Command::new("git")
-        .args(&["status", "--porcelain"])
-        .output()
-        .context("Failed to check git status")?;
-
-    if !git_status.status.success() {
-        anyhow::bail!("Not in a git repository. Please run this command from a git repository.");
+    if !Process::git().args(["status", "--porcelain"]).read_only().succeeds() {
+        return Err(not_a_repository().into());
     }
 
     // Find all worktrees that might contain stacks
     let worktrees = find_project_worktrees().await?;
     
     if worktrees.is_empty() {
-        println!("No project worktrees found to clean up.");
+        outln!("No project worktrees found to clean up.");
         return Ok(());
     }
 
-    println!("Found {} worktree(s) to process:", worktrees.len());
+    outln!("Found {} worktree(s) to process:", worktrees.len());
     for worktree in &worktrees {
-        println!("  - {}", worktree.display());
+        outln!("  - {}", worktree.display());
     }
 
+    require_interactive("stacks cleanup", "Run it in a terminal to confirm; it pushes stacks and removes their symlinks from each worktree")?;
     let should_proceed = Confirm::new()
-        .with_prompt("Proceed with cleanup? This will push stack changes, remove symlinks, and clean CLAUDE.md")
+        .with_prompt("Proceed with cleanup? This will push stack changes, remove symlinks, clean CLAUDE.md and revoke generated permissions")
         .default(false)
         .interact()?;
 
     if !should_proceed {
-        println!("Cleanup cancelled.");
+        outln!("Cleanup cancelled.");
         return Ok(());
     }
 
+    let worktree_list: Vec<String> = worktrees.iter().map(|worktree| worktree.display().to_string()).collect();
+    let hook_context = HookContext::new(Operation::Cleanup).with_env("worktrees", worktree_list.join(","));
+    hooks::run(Phase::Pre, &hook_context)?;
+
     // Process each worktree
     for worktree_path in worktrees {
         cleanup_worktree(&worktree_path).await?;
     }
 
-    println!("Cleanup complete! Worktrees are ready for merging back to main.");
+    hooks::run(Phase::Post, &hook_context)?;
+
+    outln!("Cleanup complete! Worktrees are ready for merging back to main.");
     
     Ok(())
 }
 
 /// Find worktrees that belong to this project
 async fn find_project_worktrees() -> Result<Vec<PathBuf>> {
-    let mut worktrees = Vec::new();
-    let current_dir = std::env::current_dir()?;
-    let project_name = current_dir.file_stem()
-        .and_then(|s| s.to_str())
-        .unwrap_or("project");
-
-    // Look for worktrees in parent directory with pattern: {project-name}-{feature}
-    let parent_dir = current_dir.parent().unwrap_or(&current_dir);
-    
-    for entry in WalkDir::new(parent_dir)
-        .max_depth(1)
+    // The main worktree is where feature worktrees get merged back, so it is never cleaned up
+    Ok(worktrees::other_worktrees_with_stacks()?
         .into_iter()
-        .filter_map(|e| e.ok())
-        .filter(|e| e.file_type().is_dir())
-    {
-        let dir_name = entry.file_name().to_string_lossy();
-        
-        // Skip the current directory
-        if entry.path() == current_dir {
-            continue;
-        }
-        
-        // Check if this looks like a project worktree
-        if dir_name.starts_with(&format!("{}-", project_name)) && entry.path().join(".git").exists() {
-            // Verify it's actually a worktree by checking if it has stacks
-            if entry.path().join("stacks").exists() {
-                worktrees.push(entry.path().to_path_buf());
-            }
-        }
-    }
-
-    Ok(worktrees)
+        .filter(|worktree| !worktree.is_main)
+        .map(|worktree| worktree.path)
+        .collect())
 }
 
 /// Clean up a specific worktree
 async fn cleanup_worktree(worktree_path: &PathBuf) -> Result<()> {
-    println!("\nProcessing worktree: {}", worktree_path.display());
+    outln!("\nProcessing worktree: {}", worktree_path.display());
     
     // Change to worktree directory
     let original_dir = std::env::current_dir()?;
@@ -101,7 +84,7 @@ async fn cleanup_worktree(worktree_path: &PathBuf) -> Result<()> {
     // Find all stacks in this worktree
     let stacks_dir = PathBuf::from("stacks");
     if !stacks_dir.exists() {
-        println!("  No stacks directory found, skipping");
+        outln!("  No stacks directory found, skipping");
         std::env::set_current_dir(original_dir)?;
         return Ok(());
     }
@@ -109,12 +92,12 @@ async fn cleanup_worktree(worktree_path: &PathBuf) -> Result<()> {
     let stack_names = find_stack_names(&stacks_dir)?;
     
     if stack_names.is_empty() {
-        println!("  No stacks found, skipping");
+        outln!("  No stacks found, skipping");
         std::env::set_current_dir(original_dir)?;
         return Ok(());
     }
 
-    println!("  Found {} stack(s): {}", stack_names.len(), stack_names.join(", "));
+    outln!("  Found {} stack(s): {}", stack_names.len(), stack_names.join(", "));
 
     // Push any changes in stacks back to their repositories
     push_stack_changes(&stack_names).await?;
@@ -125,13 +108,19 @@ async fn cleanup_worktree(worktree_path: &PathBuf) -> Result<()> {
     // Remove stacks directories 
     remove_stacks_directories(&stack_names).await?;
     
-    // Clean CLAUDE.md below demarcation line
+    // Remove stack sections from CLAUDE.md
     clean_claude_md().await?;
+
+    // Remove the permission rules generated when the worktree was created
+    let removed = revoke_generated_permissions(&PathBuf::from(".claude").join("settings.local.json")).await?;
+    if removed > 0 {
+        outln!("  Removed {} generated permission rule(s)", removed);
+    }
     
     // Return to original directory
     std::env::set_current_dir(original_dir)?;
     
-    println!("  ✅ Cleaned up worktree: {}", worktree_path.display());
+    outln!("  ✅ Cleaned up worktree: {}", worktree_path.display());
     
     Ok(())
 }
@@ -164,48 +153,48 @@ fn find_stack_names(stacks_dir: &PathBuf) -> Result<Vec<String>> {
 
 /// Push any uncommitted changes in stacks back to their repositories
 async fn push_stack_changes(stack_names: &[String]) -> Result<()> {
-    println!("  📤 Pushing stack changes...");
+    outln!("  📤 Pushing stack changes...");
     
     for stack_name in stack_names {
         // Check if there are changes in this stack
         let stack_path = format!("stacks/{}", stack_name);
-        let status_output = Command::new("git")
-            .args(&["status", "--porcelain", &stack_path])
+        let status_output = Process::git()
+            .args(["status", "--porcelain", &stack_path])
+            .read_only()
             .output()
             .context("Failed to check git status for stack")?;
 
-        if !String::from_utf8_lossy(&status_output.stdout).trim().is_empty() {
-            println!("    Pushing changes for stack: {}", stack_name);
+        if !status_output.stdout.trim().is_empty() {
+            outln!("    Pushing changes for stack: {}", stack_name);
             
             // Stage and commit stack changes
-            Command::new("git")
-                .args(&["add", &stack_path])
+            Process::git()
+                .args(["add", &stack_path])
                 .output()
                 .context("Failed to stage stack changes")?;
             
             let commit_message = format!("feat({}): update stack from worktree", stack_name);
-            Command::new("git")
-                .args(&["commit", "-m", &commit_message])
+            Process::git()
+                .args(["commit", "-m", &commit_message])
                 .output()
                 .context("Failed to commit stack changes")?;
             
             // Push using subtree
-            let repo_url = get_stack_repo_url(stack_name);
-            let push_output = Command::new("git")
+            let metadata = resolve_stack_metadata(stack_name);
+            let push_output = Process::git()
                 .args([
                     "subtree", "push",
                     "--prefix", &stack_path,
-                    &repo_url,
-                    "main"
+                    &metadata.source_repo,
+                    &metadata.source_branch
                 ])
                 .output()
                 .context("Failed to push subtree")?;
             
-            if !push_output.status.success() {
-                let error = String::from_utf8_lossy(&push_output.stderr);
-                println!("    Warning: Failed to push {}: {}", stack_name, error);
+            if !push_output.success() {
+                outln!("    Warning: Failed to push {}: {}", stack_name, push_output.stderr);
             } else {
-                println!("    ✅ Pushed stack: {}", stack_name);
+                outln!("    ✅ Pushed stack: {}", stack_name);
             }
         }
     }
@@ -213,28 +202,18 @@ async fn push_stack_changes(stack_names: &[String]) -> Result<()> {
     Ok(())
 }
 
-/// Get the repository URL for a stack
-fn get_stack_repo_url(stack_name: &str) -> String {
-    if stack_name == "ts-lint-stack" {
-        "git@github.com:csaben/ts-lint-stack.git".to_string()
-    } else {
-        // Default pattern - assume separate repo per stack
-        format!("git@github.com:csaben/{}.git", stack_name)
-    }
-}
-
 /// Remove symlinks created for stacks
 async fn remove_stack_symlinks(stack_names: &[String]) -> Result<()> {
-    println!("  🔗 Removing symlinks...");
+    outln!("  🔗 Removing symlinks...");
     
     let symlink_manager = SymlinkManager::new();
     
     for stack_name in stack_names {
         // Remove symlinks for this stack
         if let Err(e) = symlink_manager.remove_stack_symlinks(stack_name).await {
-            println!("    Warning: Failed to remove symlinks for {}: {}", stack_name, e);
+            outln!("    Warning: Failed to remove symlinks for {}: {}", stack_name, e);
         } else {
-            println!("    ✅ Removed symlinks for: {}", stack_name);
+            outln!("    ✅ Removed symlinks for: {}", stack_name);
         }
     }
     
@@ -243,16 +222,16 @@ async fn remove_stack_symlinks(stack_names: &[String]) -> Result<()> {
 
 /// Remove stacks directories
 async fn remove_stacks_directories(stack_names: &[String]) -> Result<()> {
-    println!("  📁 Removing stack directories...");
+    outln!("  📁 Removing stack directories...");
     
     for stack_name in stack_names {
         let stack_path = PathBuf::from(format!("stacks/{}", stack_name));
         
         if stack_path.exists() {
-            if let Err(e) = tokio::fs::remove_dir_all(&stack_path).await {
-                println!("    Warning: Failed to remove {}: {}", stack_path.display(), e);
+            if let Err(e) = files::remove_dir_all(&stack_path) {
+                outln!("    Warning: Failed to remove {}: {}", stack_path.display(), e);
             } else {
-                println!("    ✅ Removed directory: {}", stack_path.display());
+                outln!("    ✅ Removed directory: {}", stack_path.display());
             }
         }
     }
@@ -268,10 +247,10 @@ async fn remove_stacks_directories(stack_names: &[String]) -> Result<()> {
             }
             
             if count == 0 {
-                if let Err(e) = tokio::fs::remove_dir(&stacks_dir).await {
-                    println!("    Warning: Failed to remove empty stacks directory: {}", e);
+                if let Err(e) = files::remove_dir(&stacks_dir) {
+                    outln!("    Warning: Failed to remove empty stacks directory: {}", e);
                 } else {
-                    println!("    ✅ Removed empty stacks directory");
+                    outln!("    ✅ Removed empty stacks directory");
                 }
             }
         }
@@ -280,14 +259,16 @@ async fn remove_stacks_directories(stack_names: &[String]) -> Result<()> {
     Ok(())
 }
 
-/// Clean CLAUDE.md by removing everything below the demarcation line
+/// Clean CLAUDE.md by removing the stack sections, keeping the user's own content
 async fn clean_claude_md() -> Result<()> {
-    println!("  📝 Cleaning CLAUDE.md...");
+    outln!("  📝 Cleaning CLAUDE.md...");
     
-    let claude_updater = ClaudeMdUpdater::new();
-    claude_updater.cleanup_demarcated_imports().await?;
+    // Sections live in CLAUDE.local.md in worktrees; older versions wrote them to CLAUDE.md
+    let current_dir = std::env::current_dir()?;
+    ClaudeMdUpdater::for_checkout(&current_dir).remove_all_stack_sections().await?;
+    ClaudeMdUpdater::with_path(current_dir.join("CLAUDE.md")).remove_all_stack_sections().await?;
     
-    println!("    ✅ Cleaned CLAUDE.md");
+    outln!("    ✅ Cleaned CLAUDE.md");
     
     Ok(())
 }
\ No newline at end of file
@@ -0,0 +1,265 @@
+use anyhow::Result;
+use dialoguer::{Confirm, Select};
+
+use crate::config::{self, InTmuxBehavior, StacksConfig, TmuxStrategy};
+use crate::utils::terminal;
+
+/// Every setting with its value, description and allowed values, then aliases and profiles
+pub async fn show() -> Result<()> {
+    let config = config::load_config()?;
+    let config_path = config::get_config_path()?;
+    
+    outln!("📋 Current Configuration");
+    outln!("═══════════════════════");
+    if let Some(profile) = config::active_profile() {
+        outln!("\n👤 Profile: {} (unset keys fall back to the top-level settings)", profile);
+    }
+    
+    for key in config::CONFIG_KEYS {
+        let value = key.get(&config);
+        outln!("\n🔑 {} = {}", key.name, if value.is_empty() { "(default)" } else { &value });
+        outln!("   {}", key.description);
+        match &key.kind {
+            config::ConfigValueKind::Choice(options) => {
+                for (option, description) in options() {
+                    let marker = if option == value { "→" } else { " " };
+                    outln!("   {} {}: {}", marker, option, description);
+                }
+            }
+            _ => outln!("   Values: {}", key.allowed_values()),
+        }
+    }
+    
+    if !config.aliases.is_empty() {
+        outln!("\n🔤 Aliases:");
+        for (name, expansion) in &config.aliases {
+            outln!("   {} = {}", name, expansion);
+        }
+    }
+
+    if !config.profile.is_empty() {
+        outln!("\n👥 Profiles: {}", config.profile.keys().cloned().collect::<Vec<_>>().join(", "));
+        outln!("   Select one with --profile <name> or STACKS_PROFILE=<name>");
+    }
+    
+    outln!("\n📁 Config file: {}", config_path.display());
+    
+    Ok(())
+}
+
+/// Print one setting's value, e.g. for scripts
+pub fn get(key: &str) -> Result<()> {
+    let key = config::find_key(key)?;
+    println!("{}", key.get(&config::load_config()?));
+    Ok(())
+}
+
+pub fn set(key: &str, value: &str) -> Result<()> {
+    let key = config::find_key(key)?;
+    let updated = config::update_config(|config| key.set(config, value))?;
+    outln!("Set {} to: {}", key.name, key.get(&updated));
+    Ok(())
+}
+
+/// Print the config file to share as team defaults
+pub fn export() -> Result<()> {
+    print!("{}", config::export_config()?);
+    Ok(())
+}
+
+/// Merge settings from a file or URL into the config after showing what changes, keeping a backup
+pub async fn import(source: &str, yes: bool) -> Result<()> {
+    let content = config::read_import_source(source).await?;
+    let current = config::load_raw_config()?;
+    let (merged, changed) = config::merge_imported_config(&current, &content)?;
+    
+    if changed.is_empty() {
+        outln!("✅ Configuration already matches {}", source);
+        return Ok(());
+    }
+    
+    outln!("📥 Importing from {} changes:", source);
+    for key in &changed {
+        outln!("  • {}", key);
+    }
+    
+    let should_apply = if yes {
+        true
+    } else if terminal::is_interactive() {
+        Confirm::new()
+            .with_prompt("Apply these settings?")
+            .default(true)
+            .interact()?
+    } else {
+        anyhow::bail!("Refusing to import without confirmation in non-interactive mode; pass --yes");
+    };
+    
+    if !should_apply {
+        outln!("Import cancelled. No changes made.");
+        return Ok(());
+    }
+    
+    let config_path = config::get_config_path()?;
+    if config_path.exists() {
+        let backup_path = config_path.with_extension("toml.bak");
+        std::fs::copy(&config_path, &backup_path)?;
+        outln!("💾 Previous configuration saved to {}", backup_path.display());
+    }
+    
+    config::save_config(&merged)?;
+    outln!("✅ Imported {} setting(s)", changed.len());
+    Ok(())
+}
+
+/// Reset the active profile to the top-level settings, or the whole config to defaults
+pub fn reset() -> Result<()> {
+    match config::active_profile() {
+        Some(name) => {
+            let mut raw_config = config::load_raw_config()?;
+            raw_config.profile.remove(&name);
+            config::save_config(&raw_config)?;
+            outln!("Profile '{}' reset to the top-level settings", name);
+        }
+        None => {
+            config::save_config(&StacksConfig::default())?;
+            outln!("Configuration reset to defaults");
+        }
+    }
+    Ok(())
+}
+
+/// Menu for the tmux settings, saved on request
+pub async fn edit() -> Result<()> {
+    terminal::require_interactive("stacks config edit", "Change settings with 'stacks config set <key> <value>'")?;
+    
+    let mut config = config::load_config()?;
+    let mut changes_made = false;
+    
+    loop {
+        outln!("\n📋 Interactive Configuration Editor");
+        outln!("══════════════════════════════════");
+        
+        let options = vec![
+            format!("Tmux Strategy: {} ({})", config.tmux_strategy.as_str(), config.tmux_strategy.description()),
+            format!("Prompt Strategy: {}", if config.prompt_for_strategy { "enabled" } else { "disabled" }),
+            format!("In-Tmux Behavior: {} ({})", config.in_tmux_behavior.as_str(), config.in_tmux_behavior.description()),
+            "💾 Save and Exit".to_string(),
+            "❌ Exit without Saving".to_string(),
+        ];
+        
+        let selection = Select::new()
+            .with_prompt("Select setting to modify")
+            .items(&options)
+            .default(0)
+            .interact()?;
+            
+        match selection {
+            0 => {
+                // Tmux Strategy
+                let strategies = vec![
+                    TmuxStrategy::SeparateSessions,
+                    TmuxStrategy::QuadSplit,
+                    TmuxStrategy::HorizontalSplit,
+                    TmuxStrategy::MultipleWindows,
+                ];
+                
+                let strategy_options: Vec<String> = strategies.iter()
+                    .map(|s| format!("{}: {}", s.as_str(), s.description()))
+                    .collect();
+                
+                let current_index = strategies.iter().position(|s| s.as_str() == config.tmux_strategy.as_str()).unwrap_or(0);
+                
+                let selected_strategy = Select::new()
+                    .with_prompt("Choose tmux strategy")
+                    .items(&strategy_options)
+                    .default(current_index)
+                    .interact()?;
+                    
+                if strategies[selected_strategy].as_str() != config.tmux_strategy.as_str() {
+                    config.tmux_strategy = strategies[selected_strategy].clone();
+                    changes_made = true;
+                    outln!("✅ Updated tmux strategy to: {}", config.tmux_strategy.as_str());
+                }
+            }
+            1 => {
+                // Prompt Strategy
+                let prompt_options = vec![
+                    "disabled: Use default strategy without asking",
+                    "enabled: Ask which strategy to use each time",
+                ];
+                
+                let current_index = if config.prompt_for_strategy { 1 } else { 0 };
+                
+                let selected = Select::new()
+                    .with_prompt("Choose prompt behavior")
+                    .items(&prompt_options)
+                    .default(current_index)
+                    .interact()?;
+                    
+                let new_value = selected == 1;
+                if new_value != config.prompt_for_strategy {
+                    config.prompt_for_strategy = new_value;
+                    changes_made = true;
+                    outln!("✅ Updated prompt strategy to: {}", if new_value { "enabled" } else { "disabled" });
+                }
+            }
+            2 => {
+                // In-Tmux Behavior
+                let behaviors = vec![
+                    InTmuxBehavior::NewWindows,
+                    InTmuxBehavior::NewSession,
+                    InTmuxBehavior::Ask,
+                ];
+                
+                let behavior_options: Vec<String> = behaviors.iter()
+                    .map(|b| format!("{}: {}", b.as_str(), b.description()))
+                    .collect();
+                
+                let current_index = behaviors.iter().position(|b| b.as_str() == config.in_tmux_behavior.as_str()).unwrap_or(0);
+                
+                let selected_behavior = Select::new()
+                    .with_prompt("Choose in-tmux behavior")
+                    .items(&behavior_options)
+                    .default(current_index)
+                    .interact()?;
+                    
+                if behaviors[selected_behavior].as_str() != config.in_tmux_behavior.as_str() {
+                    config.in_tmux_behavior = behaviors[selected_behavior].clone();
+                    changes_made = true;
+                    outln!("✅ Updated in-tmux behavior to: {}", config.in_tmux_behavior.as_str());
+                }
+            }
+            3 => {
+                // Save and Exit
+                if changes_made {
+                    let edited = config.clone();
+                    config::update_config(|current| {
+                        *current = edited;
+                        Ok(())
+                    })?;
+                    outln!("💾 Configuration saved successfully!");
+                } else {
+                    outln!("No changes to save.");
+                }
+                break;
+            }
+            4 => {
+                // Exit without Saving
+                if changes_made {
+                    let confirm_exit = Confirm::new()
+                        .with_prompt("You have unsaved changes. Exit anyway?")
+                        .default(false)
+                        .interact()?;
+                    if !confirm_exit {
+                        continue;
+                    }
+                }
+                outln!("Configuration editor exited without saving.");
+                break;
+            }
+            _ => unreachable!(),
+        }
+    }
+    
+    Ok(())
+}
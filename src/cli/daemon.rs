@@ -0,0 +1,148 @@
+use std::time::Duration;
+use anyhow::{Result, Context};
+use walkdir::WalkDir;
+
+use crate::cli::repair::refresh_stack_integration;
+use crate::core::daemon::{self, DaemonStatus, StackHealth};
+use crate::core::git::Repo;
+use crate::core::lockfile::resolve_stack_metadata;
+use crate::core::notifications;
+use crate::core::remote_stack_manager::RemoteStackManager;
+use crate::core::stack_manager::Stack;
+use crate::core::symlink_manager::SymlinkManager;
+use crate::core::upstream::check_upstream;
+use crate::error::not_a_repository;
+use crate::utils::dependency_check::{require, Dependency};
+use crate::utils::process::Process;
+
+/// tmux window the daemon runs in with `--tmux`
+const TMUX_WINDOW: &str = "stacks-daemon";
+
+/// Check stacks every `interval` seconds until interrupted
+///
+/// Each pass compares stacks with upstream and checks their symlinks, writes the result to the
+/// status file `stacks status` reads, and announces newly available updates.
+pub async fn run(interval: u64, once: bool, notify: bool, repair: bool, tmux: bool) -> Result<()> {
+    if Repo::current().is_err() {
+        return Err(not_a_repository().into());
+    }
+    if tmux {
+        return start_in_tmux(interval, notify, repair);
+    }
+
+    let interval = Duration::from_secs(interval.max(60));
+    let manager = RemoteStackManager::new().context("Failed to initialize remote stack manager")?;
+
+    outln!("🛰️ Watching stacks every {}s (Ctrl-C to stop)", interval.as_secs());
+    loop {
+        let previous = daemon::load_status();
+        match check_stacks(&manager, interval, repair).await {
+            Ok(status) => {
+                daemon::save_status(&status)?;
+                report(&status, previous.as_ref(), notify);
+            }
+            Err(error) => outln!("❌ Check failed: {:#}", error),
+        }
+
+        if once {
+            return Ok(());
+        }
+        tokio::time::sleep(interval).await;
+    }
+}
+
+async fn check_stacks(manager: &RemoteStackManager, interval: Duration, repair: bool) -> Result<DaemonStatus> {
+    let stacks_dir = std::env::current_dir()?.join("stacks");
+    let names: Vec<String> = if stacks_dir.exists() {
+        WalkDir::new(&stacks_dir)
+            .min_depth(1)
+            .max_depth(1)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_dir())
+            .map(|e| e.file_name().to_string_lossy().to_string())
+            .collect()
+    } else {
+        Vec::new()
+    };
+
+    let symlink_manager = SymlinkManager::new();
+    let mut stacks = Vec::new();
+    for name in names {
+        let upstream = check_upstream(manager, &resolve_stack_metadata(&name)).await;
+
+        let stack = Stack::new(name.clone(), stacks_dir.join(&name));
+        let mut drifted = !symlink_manager.check_stack_symlinks(&stack).is_healthy();
+        if drifted && repair {
+            outln!("🔧 Repairing {}", name);
+            match refresh_stack_integration(&name).await {
+                Ok(()) => drifted = !symlink_manager.check_stack_symlinks(&stack).is_healthy(),
+                Err(error) => outln!("  ❌ Repair failed: {:#}", error),
+            }
+        }
+
+        stacks.push(StackHealth {
+            upstream: match &upstream {
+                Ok(status) => status.describe(),
+                Err(error) => format!("unknown ({})", error),
+            },
+            outdated: upstream.map(|status| status.is_outdated()).unwrap_or(false),
+            drifted,
+            name,
+        });
+    }
+
+    Ok(DaemonStatus {
+        pid: std::process::id(),
+        checked_at: chrono::Utc::now(),
+        interval_secs: interval.as_secs(),
+        stacks,
+    })
+}
+
+fn report(status: &DaemonStatus, previous: Option<&DaemonStatus>, notify: bool) {
+    let time = status.checked_at.with_timezone(&chrono::Local).format("%H:%M:%S");
+    let outdated = status.outdated();
+    let drifted = status.drifted();
+    if outdated.is_empty() && drifted.is_empty() {
+        outln!("[{}] ✅ {} stack(s) up to date", time, status.stacks.len());
+    }
+    for stack in &outdated {
+        outln!("[{}] ⬇️ {} is {}", time, stack.name, stack.upstream);
+    }
+    for stack in &drifted {
+        outln!("[{}] ⚠️ {} has missing or broken symlinks; run 'stacks repair {}'", time, stack.name, stack.name);
+    }
+
+    let fresh = status.newly_outdated(previous);
+    if notify && !fresh.is_empty() {
+        let names: Vec<&str> = fresh.iter().map(|stack| stack.name.as_str()).collect();
+        notifications::desktop("Stack updates available", &format!("{} - run 'stacks pull' to update", names.join(", ")));
+    }
+}
+
+/// Run the daemon in a detached tmux window of the current session, or a new session outside tmux
+fn start_in_tmux(interval: u64, notify: bool, repair: bool) -> Result<()> {
+    require(Dependency::Tmux)?;
+
+    let executable = std::env::current_exe().context("Failed to locate the stacks executable")?;
+    let mut command = format!("'{}' daemon --interval {}", executable.display(), interval);
+    if notify {
+        command.push_str(" --notify");
+    }
+    if repair {
+        command.push_str(" --repair");
+    }
+    let dir = std::env::current_dir()?;
+
+    let process = if std::env::var_os("TMUX").is_some() {
+        Process::tmux().args(["new-window", "-d", "-n", TMUX_WINDOW, "-c"]).arg(&dir).arg(&command)
+    } else {
+        Process::tmux().args(["new-session", "-d", "-s", TMUX_WINDOW, "-c"]).arg(&dir).arg(&command)
+    };
+    process.run().context("Failed to start the daemon in tmux")?;
+
+    outln!("🛰️ Daemon started in tmux window '{}'", TMUX_WINDOW);
+    Ok(())
+}
@@ -0,0 +1,108 @@
+use anyhow::{Result, bail};
+use walkdir::WalkDir;
+
+use crate::core::schema::{self, DoctorOutput, SCHEMA_VERSION};
+use crate::utils::claude_md_updater::ClaudeMdUpdater;
+use crate::utils::dependency_check::{self, Dependency, DependencyReport};
+
+/// Check the project for inconsistencies; `--fix` repairs what it can
+///
+/// `json` prints the dependency checks as a `DoctorOutput` instead, for scripts and bug reports.
+pub async fn run(claude_md: bool, dependencies: bool, fix: bool, json: bool) -> Result<()> {
+    if json {
+        let dependencies: Vec<DependencyReport> = Dependency::all().into_iter().map(dependency_check::check).collect();
+        let failed = dependencies.iter().filter(|report| !report.ok).count();
+        schema::print(&DoctorOutput { schema_version: SCHEMA_VERSION, dependencies })?;
+        if failed > 0 {
+            bail!("{} dependency check(s) failed", failed);
+        }
+        return Ok(());
+    }
+
+    // Running doctor without a check flag runs all of them
+    let check_all = !claude_md && !dependencies;
+
+    let mut problems = 0;
+    if dependencies || check_all {
+        problems += check_dependencies();
+    }
+    if claude_md || check_all {
+        problems += check_claude_md(fix).await?;
+    }
+
+    if problems > 0 {
+        bail!("Found {} problem(s). Run 'stacks doctor --fix' to repair them.", problems);
+    }
+
+    Ok(())
+}
+
+/// Returns the number of missing or too old dependencies; outdated ones only warn
+fn check_dependencies() -> usize {
+    outln!("🩺 Checking dependencies...");
+
+    let mut problems = 0;
+    for report in Dependency::all().into_iter().map(dependency_check::check) {
+        let version = report.version.clone().unwrap_or_else(|| "unknown version".to_string());
+        match (report.path.is_some(), report.ok, report.outdated) {
+            (false, _, _) => outln!("  ❌ {} not found", report.name),
+            (true, false, _) => outln!("  ❌ {} {} is older than {}", report.name, version, report.minimum_version.as_deref().unwrap_or_default()),
+            (true, true, true) => outln!("  ⚠️ {} {}: {} or newer is recommended", report.name, version, report.recommended_version.as_deref().unwrap_or_default()),
+            (true, true, false) => outln!("  ✅ {} {}", report.name, version),
+        }
+        if let Some(install) = &report.install {
+            outln!("    💡 {}", install);
+        }
+        if !report.ok {
+            problems += 1;
+        }
+    }
+    problems
+}
+
+/// Installed stacks, sorted by name
+fn installed_stacks() -> Result<Vec<String>> {
+    let stacks_dir = std::env::current_dir()?.join("stacks");
+    if !stacks_dir.exists() {
+        return Ok(Vec::new());
+    }
+
+    Ok(WalkDir::new(&stacks_dir)
+        .min_depth(1)
+        .max_depth(1)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_dir())
+        .map(|e| e.file_name().to_string_lossy().to_string())
+        .collect())
+}
+
+/// Returns the number of problems left after an optional fix
+async fn check_claude_md(fix: bool) -> Result<usize> {
+    outln!("🩺 Checking CLAUDE.md...");
+
+    let installed = installed_stacks()?;
+    let md_updater = ClaudeMdUpdater::for_project(&std::env::current_dir()?)?;
+    let issues = md_updater.check(&installed).await?;
+
+    if issues.is_empty() {
+        outln!("  ✅ {} stack(s), each referenced exactly once", installed.len());
+        return Ok(0);
+    }
+
+    for issue in &issues {
+        outln!("  ❌ {}", issue.describe());
+    }
+
+    if !fix {
+        return Ok(issues.len());
+    }
+
+    md_updater.rebuild(&installed).await?;
+    let remaining = md_updater.check(&installed).await?;
+    if remaining.is_empty() {
+        outln!("  🔧 Rebuilt stack sections for {} installed stack(s)", installed.len());
+    }
+    Ok(remaining.len())
+}
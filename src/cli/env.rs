@@ -0,0 +1,130 @@
+use anyhow::Result;
+
+use crate::cli::auth::token_source;
+use crate::config::{self, CONFIG_KEYS, PROJECT_CONFIG_FILE};
+use crate::core::cache;
+use crate::core::git::Repo;
+use crate::core::remote_stack_manager::{github_token, StackRepository};
+use crate::core::session_manifest::SessionManifest;
+use crate::core::sessions::SessionRegistry;
+use crate::utils::build_info;
+use crate::utils::dependency_check::{self, Dependency};
+
+/// Environment variables that change what stacks does; secrets are only reported as set
+const ENVIRONMENT: [(&str, bool); 7] = [
+    (config::PROFILE_ENV, false),
+    ("CLAUDE_CONFIG_DIR", false),
+    ("TMUX", false),
+    ("GITHUB_TOKEN", true),
+    ("GH_TOKEN", true),
+    ("STACKS_MIRROR_TOKEN", true),
+    ("RUST_LOG", false),
+];
+
+/// Print everything stacks resolved about this machine and project, for pasting into bug reports
+///
+/// Tokens are never printed, only whether and where one was found.
+pub async fn run() -> Result<()> {
+    outln!("🧭 stacks {}", build_info::LONG_VERSION);
+
+    let config = config::load_config()?;
+    let project_config = std::path::Path::new(PROJECT_CONFIG_FILE).is_file();
+    outln!("\n⚙️ Configuration");
+    outln!("  📄 {}", config::get_config_path()?.display());
+    if project_config {
+        outln!("  📄 {} (project overrides)", PROJECT_CONFIG_FILE);
+    }
+    outln!("  👤 Profile: {}", config::active_profile().unwrap_or_else(|| "(none)".to_string()));
+    for key in CONFIG_KEYS {
+        let value = key.get(&config);
+        outln!("  {} = {}", key.name, if value.is_empty() { "(default)" } else { &value });
+    }
+    if project_config {
+        outln!("  claude-md-mode (effective) = {}", config::claude_md_mode()?.as_str());
+    }
+    let groups = config::stack_groups()?;
+    if !groups.is_empty() {
+        outln!("  groups: {}", groups.keys().cloned().collect::<Vec<_>>().join(", "));
+    }
+
+    outln!("\n📦 Dependencies");
+    for report in Dependency::all().into_iter().map(dependency_check::check) {
+        match &report.path {
+            Some(path) => outln!(
+                "  {} {} {} ({})",
+                match report.ok { true => "✅", false => "❌" },
+                report.name,
+                report.version.as_deref().unwrap_or("unknown version"),
+                path.display()
+            ),
+            None => outln!("  ❌ {} not found", report.name),
+        }
+    }
+
+    outln!("\n🌐 Registry");
+    let repository = match &config.registry {
+        Some(spec) => StackRepository::from_spec(spec)?,
+        None => StackRepository::default(),
+    };
+    outln!("  📚 {}/{}@{}{}", repository.owner, repository.repo, repository.branch, match config.registry {
+        Some(_) => "",
+        None => " (default)",
+    });
+    if let Some(mirror) = &config.registry_mirror {
+        outln!("  🪞 Mirror: {}", mirror);
+    }
+    if let Some(policy) = &config.policy_url {
+        outln!("  📜 Policy: {}", policy);
+    }
+    match github_token() {
+        Some(_) => outln!("  🔑 GitHub token from {}", token_source()),
+        None => outln!("  🔓 No GitHub token"),
+    }
+
+    outln!("\n📁 Paths");
+    let cache_root = cache::cache_root()?;
+    let cache_size: u64 = cache::entries(&cache_root).iter().map(|entry| entry.size).sum();
+    outln!("  🗄️ Cache: {} ({})", cache_root.display(), cache::format_size(cache_size));
+    outln!("  🖥️ Session records: {}", SessionRegistry::path()?.display());
+
+    outln!("\n📂 Project");
+    let cwd = std::env::current_dir()?;
+    outln!("  📍 {}", cwd.display());
+    match Repo::discover(&cwd) {
+        Ok(repo) => {
+            if let Some(root) = repo.workdir() {
+                outln!("  🌳 Repository: {}", root.display());
+                if let Ok(Some(manifest)) = SessionManifest::load(root) {
+                    outln!("  🏷️ Task: {}", manifest.task);
+                }
+            }
+            if let Some(branch) = repo.symbolic_target("HEAD") {
+                outln!("  ⎇ Branch: {}", branch.trim_start_matches("refs/heads/"));
+            }
+            outln!("  🔗 Git directory: {}", repo.common_dir().display());
+        }
+        Err(_) => outln!("  ⚠️ Not in a git repository"),
+    }
+    let mut stacks: Vec<String> = std::fs::read_dir(cwd.join("stacks"))
+        .map(|entries| entries
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().is_dir())
+            .map(|entry| entry.file_name().to_string_lossy().to_string())
+            .collect())
+        .unwrap_or_default();
+    stacks.sort();
+    match stacks.is_empty() {
+        true => outln!("  📦 No stacks installed"),
+        false => outln!("  📦 Stacks: {}", stacks.join(", ")),
+    }
+
+    outln!("\n🌱 Environment");
+    for (name, secret) in ENVIRONMENT {
+        match (std::env::var(name), secret) {
+            (Ok(_), true) => outln!("  {}=(set)", name),
+            (Ok(value), false) => outln!("  {}={}", name, value),
+            (Err(_), _) => outln!("  {} is not set", name),
+        }
+    }
+    Ok(())
+}
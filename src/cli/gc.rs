@@ -0,0 +1,135 @@
+use std::path::Path;
+use anyhow::{Result, Context};
+use chrono::Utc;
+use dialoguer::Confirm;
+
+use crate::core::git::Repo;
+use crate::core::sessions::{last_activity, live_targets, pane_activity, SessionRecord, SessionRegistry};
+use crate::utils::dependency_check::{require, Dependency};
+use crate::utils::process::Process;
+use crate::utils::terminal::is_interactive;
+
+/// Hours without activity before a session counts as idle, unless configured
+const DEFAULT_IDLE_HOURS: u32 = 24;
+
+/// Find tmux sessions stacks created that have been idle for a while and whose worktrees are
+/// clean, and offer to close them and remove their worktrees
+///
+/// Sessions with uncommitted work are only reported. Without a terminal nothing is closed
+/// unless `yes` is given.
+pub async fn run(idle_hours: Option<u32>, yes: bool) -> Result<()> {
+    require(Dependency::Tmux)?;
+
+    let hours = match idle_hours {
+        Some(hours) => hours,
+        None => crate::config::load_config()?.idle_session_hours.unwrap_or(DEFAULT_IDLE_HOURS),
+    };
+    let mut registry = SessionRegistry::load()?;
+    if registry.retain_live(&live_targets()) > 0 {
+        registry.save()?;
+    }
+
+    let panes = pane_activity();
+    let now = Utc::now();
+    let mut idle: Vec<SessionRecord> = Vec::new();
+    for session in registry.sorted() {
+        let last = match last_activity(&session.target, &panes) {
+            Some(last) => last,
+            None => continue,
+        };
+        let idle_for = (now - last).num_hours();
+        if idle_for < hours as i64 {
+            continue;
+        }
+        match worktree_is_clean(&session.worktree) {
+            true => {
+                outln!("💤 {} ({}) idle for {}h, worktree clean", session.task, session.target, idle_for);
+                idle.push(session.clone());
+            }
+            false => outln!("✏️ {} ({}) idle for {}h, kept: {} has uncommitted changes", session.task, session.target, idle_for, session.worktree.display()),
+        }
+    }
+
+    if idle.is_empty() {
+        outln!("✅ No idle sessions with clean worktrees (idle means {}h without activity)", hours);
+        return Ok(());
+    }
+    let interactive = !yes && is_interactive();
+    if !yes && !interactive {
+        outln!("💡 Run 'stacks gc --yes' to close them and remove their worktrees");
+        return Ok(());
+    }
+
+    let mut closed = 0;
+    for session in &idle {
+        let close = match interactive {
+            true => Confirm::new()
+                .with_prompt(format!("Close {} ({})?", session.task, session.target))
+                .default(true)
+                .interact()?,
+            false => true,
+        };
+        if !close {
+            continue;
+        }
+        kill_target(&session.target)?;
+        registry.sessions.retain(|record| record.target != session.target);
+        closed += 1;
+        outln!("  🗑️ Closed {}", session.target);
+
+        // Other sessions may still work in the same worktree
+        if registry.sessions.iter().any(|record| record.worktree == session.worktree) || !session.worktree.is_dir() {
+            continue;
+        }
+        let remove = match interactive {
+            true => Confirm::new()
+                .with_prompt(format!("Also remove the worktree {}?", session.worktree.display()))
+                .default(false)
+                .interact()?,
+            false => true,
+        };
+        if remove {
+            remove_worktree(&session.worktree)?;
+            outln!("  🗑️ Removed worktree {}", session.worktree.display());
+        }
+    }
+    registry.save()?;
+
+    outln!("\n✅ Closed {} of {} idle session(s)", closed, idle.len());
+    Ok(())
+}
+
+/// Whether the worktree exists and has no uncommitted or untracked changes
+fn worktree_is_clean(worktree: &Path) -> bool {
+    match Repo::discover(worktree).and_then(|repo| repo.changes_in(worktree)) {
+        Ok(changes) => changes.is_empty(),
+        Err(_) => false,
+    }
+}
+
+/// Kill a session, window (`session:index`) or pane (`%id`)
+fn kill_target(target: &str) -> Result<()> {
+    let command = match (target.starts_with('%'), target.contains(':')) {
+        (true, _) => "kill-pane",
+        (false, true) => "kill-window",
+        (false, false) => "kill-session",
+    };
+    Process::tmux()
+        .args([command, "-t", target])
+        .run()
+        .with_context(|| format!("Failed to close {}", target))?;
+    Ok(())
+}
+
+fn remove_worktree(worktree: &Path) -> Result<()> {
+    let repo = Repo::discover(worktree)?;
+    // Run from the shared git directory; git won't remove the worktree it's running in
+    Process::git()
+        .arg("worktree")
+        .arg("remove")
+        .arg(worktree)
+        .current_dir(repo.common_dir())
+        .run()
+        .with_context(|| format!("Failed to remove worktree {}", worktree.display()))?;
+    Ok(())
+}
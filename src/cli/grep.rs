@@ -0,0 +1,63 @@
+use anyhow::Result;
+
+use crate::cli::checkout::registry_manager;
+use crate::cli::query::stack_names;
+use crate::core::search::{self, Match, SourceKind};
+use crate::error::ExitStatus;
+
+/// Search the installed stacks' CLAUDE.md files, agents and commands for `pattern`, grouped by
+/// the stack that provides each match
+///
+/// With `remote`, the registry's other stacks are searched too, through GitHub code search.
+/// Exits 1 when nothing matches, like grep.
+pub async fn run(pattern: String, ignore_case: bool, fixed_strings: bool, remote: bool) -> Result<()> {
+    let regex = search::pattern(&pattern, fixed_strings, ignore_case)?;
+    let root = std::env::current_dir()?;
+
+    let installed = search::search_installed(&root, &regex);
+    print_matches(&installed);
+
+    let mut registry = Vec::new();
+    if remote {
+        let manager = registry_manager()?;
+        let results = manager.search_code(&pattern).await?;
+        registry = search::registry_matches(&results, &regex, &stack_names(&root));
+        if !registry.is_empty() {
+            outln!("\n🌐 Registry stacks that aren't installed ({}):", manager.registry_spec());
+            print_matches(&registry);
+        }
+    }
+
+    if installed.is_empty() && registry.is_empty() {
+        let searched = match remote {
+            true => "installed or registry stacks",
+            false => "installed stacks",
+        };
+        eoutln!("No matches for {} in {}", pattern, searched);
+        return Err(ExitStatus(1).into());
+    }
+    Ok(())
+}
+
+/// Matches by stack and file, with the file's path in the project (or in the registry)
+fn print_matches(matches: &[Match]) {
+    let mut current: Option<(&str, &str)> = None;
+    for found in matches {
+        if current.map(|(stack, _)| stack) != Some(found.stack.as_str()) {
+            outln!("📦 {}", found.stack);
+        }
+        if current != Some((found.stack.as_str(), found.file.as_str())) {
+            let icon = match found.kind {
+                SourceKind::Instructions => "📘",
+                SourceKind::Agent => "🤖",
+                SourceKind::Command => "⚡",
+            };
+            outln!("  {} {} stacks/{}/{}", icon, found.kind.as_str(), found.stack, found.file);
+        }
+        match found.line {
+            Some(line) => outln!("    {}: {}", line, found.text),
+            None => outln!("    {}", found.text),
+        }
+        current = Some((found.stack.as_str(), found.file.as_str()));
+    }
+}
@@ -0,0 +1,94 @@
+use anyhow::Result;
+
+use crate::core::definitions::{self, Definition, DefinitionKind, Frontmatter, Scope};
+
+/// `stacks agents`: every subagent claude can use in this project
+pub async fn agents() -> Result<()> {
+    run(DefinitionKind::Agent)
+}
+
+/// `stacks commands`: every slash command claude offers in this project
+pub async fn commands() -> Result<()> {
+    run(DefinitionKind::Command)
+}
+
+/// One entry of the inventory, as claude shows it
+struct Entry {
+    /// Agent name, or the command with its leading slash
+    name: String,
+    description: Option<String>,
+    /// A user definition hidden by a project one with the same name
+    shadowed: bool,
+}
+
+/// List the agents or commands claude sees here, grouped by the stack they come from, then the
+/// project's own and the user's
+fn run(kind: DefinitionKind) -> Result<()> {
+    let root = std::env::current_dir()?;
+    let user_dir = definitions::user_claude_dir();
+    let visible: Vec<Definition> = definitions::visible(&root, user_dir.as_deref())
+        .into_iter()
+        .filter(|definition| definition.kind == kind)
+        .collect();
+    let broken = visible.iter().filter(|definition| definition.is_broken()).count();
+
+    let mut groups: Vec<(String, Vec<Entry>)> = Vec::new();
+    let mut project_names: Vec<String> = Vec::new();
+    for definition in visible.iter().filter(|definition| !definition.is_broken()) {
+        let content = std::fs::read_to_string(&definition.path).unwrap_or_default();
+        let name = match kind {
+            DefinitionKind::Agent => Frontmatter::parse(&content).name.unwrap_or_else(|| definition.name.clone()),
+            DefinitionKind::Command => format!("/{}", definition.name),
+        };
+        // Project definitions win over user ones with the same name
+        let shadowed = definition.scope == Scope::User && project_names.contains(&name);
+        if definition.scope == Scope::Project {
+            project_names.push(name.clone());
+        }
+
+        let group = match (&definition.stack, definition.scope) {
+            (Some(stack), _) => format!("📦 {}", stack),
+            (None, Scope::Project) => "📁 Project (.claude)".to_string(),
+            (None, _) => "👤 User (~/.claude)".to_string(),
+        };
+        let entry = Entry { name, description: definitions::description(&content), shadowed };
+        match groups.iter_mut().find(|(existing, _)| *existing == group) {
+            Some((_, entries)) => entries.push(entry),
+            None => groups.push((group, vec![entry])),
+        }
+    }
+
+    let plural = match kind {
+        DefinitionKind::Agent => "agents",
+        DefinitionKind::Command => "commands",
+    };
+    let icon = match kind {
+        DefinitionKind::Agent => "🤖",
+        DefinitionKind::Command => "⚡",
+    };
+    if groups.is_empty() {
+        outln!("No {} available in this project", plural);
+        outln!("💡 Check out a stack with 'stacks checkout' to add some");
+    }
+    // Stacks first, by name, then the project's own and the user's
+    groups.sort_by_key(|(group, _)| (!group.starts_with("📦"), group.starts_with("👤"), group.clone()));
+
+    let count: usize = groups.iter().map(|(_, entries)| entries.iter().filter(|entry| !entry.shadowed).count()).sum();
+    if count > 0 {
+        outln!("{} {} {}(s) available to claude", icon, count, kind.as_str());
+    }
+    for (group, entries) in &groups {
+        outln!("\n{}", group);
+        for entry in entries {
+            let description = entry.description.as_deref().unwrap_or("No description");
+            match entry.shadowed {
+                true => outln!("  {} - {} (hidden by the project's {})", entry.name, description, entry.name),
+                false => outln!("  {} - {}", entry.name, description),
+            }
+        }
+    }
+    if broken > 0 {
+        outln!("\n⚠️ {} {} link(s) are broken and unavailable; run 'stacks repair'", broken, kind.as_str());
+    }
+    Ok(())
+}
@@ -0,0 +1,76 @@
+use std::process::Command;
+use anyhow::{Result, Context};
+
+use crate::core::transcripts::{self, strip_ansi};
+use crate::error::StacksError;
+
+/// Print or search the transcripts recorded for a task, or list tasks with transcripts
+pub async fn run(task: Option<String>, follow: bool, grep: Option<String>, lines: usize) -> Result<()> {
+    let project_root = std::env::current_dir()?;
+
+    let task = match task {
+        Some(task) => task,
+        None => {
+            let tasks = transcripts::tasks(&project_root);
+            if tasks.is_empty() {
+                outln!("📭 No transcripts recorded yet");
+                outln!("💡 Record one with 'stacks worktree --record' or 'stacks config set record-transcripts true'");
+                return Ok(());
+            }
+            outln!("📼 Recorded transcripts:");
+            for task in tasks {
+                let panes = transcripts::transcript_files(&project_root, &task).len();
+                outln!("  {} ({} pane(s))", task, panes);
+            }
+            return Ok(());
+        }
+    };
+
+    let files = transcripts::transcript_files(&project_root, &task);
+    if files.is_empty() {
+        return Err(StacksError::validation(format!("No transcripts recorded for task '{}'", task))
+            .with_hint("Run 'stacks logs' to list recorded tasks")
+            .into());
+    }
+
+    if follow {
+        // tail owns the terminal until interrupted, so it can't go through the captured-output runner
+        let status = Command::new("tail")
+            .arg("-f")
+            .args(&files)
+            .status()
+            .context("Failed to run tail")?;
+        if !status.success() {
+            anyhow::bail!("tail exited with {}", status);
+        }
+        return Ok(());
+    }
+
+    let needle = grep.as_deref().map(str::to_lowercase);
+    for file in &files {
+        let content = std::fs::read(file)
+            .with_context(|| format!("Failed to read {}", file.display()))?;
+        let content = String::from_utf8_lossy(&content);
+        let pane = file.file_stem().map(|stem| stem.to_string_lossy().to_string()).unwrap_or_default();
+
+        match &needle {
+            Some(needle) => {
+                for (number, line) in content.lines().enumerate() {
+                    let line = strip_ansi(line);
+                    if line.to_lowercase().contains(needle) {
+                        outln!("{}:{}: {}", pane, number + 1, line);
+                    }
+                }
+            }
+            None => {
+                outln!("📄 {}", pane);
+                let all: Vec<&str> = content.lines().collect();
+                for line in &all[all.len().saturating_sub(lines)..] {
+                    outln!("{}", strip_ansi(line));
+                }
+                outln!();
+            }
+        }
+    }
+    Ok(())
+}
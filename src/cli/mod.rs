@@ -4,4 +4,34 @@ pub mod status;
 pub mod pull;
 pub mod worktree;
 pub mod sync;
-pub mod cleanup;
\ No newline at end of file
+pub mod cleanup;
+pub mod outdated;
+pub mod repair;
+pub mod permissions;
+pub mod remove;
+pub mod doctor;
+pub mod cache;
+pub mod daemon;
+pub mod attach;
+pub mod logs;
+pub mod usage;
+pub mod run;
+pub mod ci;
+pub mod stack_set;
+pub mod auth;
+pub mod registry;
+pub mod which;
+pub mod workspace;
+pub mod open;
+pub mod statusline;
+pub mod gc;
+pub mod remote;
+pub mod env;
+pub mod undo;
+pub mod schema;
+pub mod serve;
+pub mod query;
+pub mod telemetry;
+pub mod grep;
+pub mod inventory;
+pub mod config;
\ No newline at end of file
@@ -0,0 +1,144 @@
+use std::path::{Path, PathBuf};
+use anyhow::{Result, Context};
+
+use crate::cli::attach::jump_to;
+use crate::cli::worktree::{get_repo_name, CLAUDE_COMMAND};
+use crate::core::session_manifest::SessionManifest;
+use crate::core::sessions::{label_target, live_targets, record_session, task_label, SessionRegistry};
+use crate::core::worktrees::list_worktrees;
+use crate::error::StacksError;
+use crate::utils::dependency_check::{require, Dependency};
+use crate::utils::process::Process;
+
+/// Jump into the tmux session of an existing task, recreating it if it was closed
+///
+/// With `relaunch`, claude is restarted in panes where it exited (kept by tmux's remain-on-exit).
+pub async fn run(task: String, relaunch: bool) -> Result<()> {
+    require(Dependency::Tmux)?;
+
+    let mut registry = SessionRegistry::load()?;
+    let worktree = find_worktree(&task, &registry)?;
+
+    let live = live_targets();
+    if registry.retain_live(&live) > 0 {
+        registry.save()?;
+    }
+    let target = registry.sorted()
+        .into_iter()
+        .find(|session| session.task == task)
+        .map(|session| session.target.clone());
+
+    let target = match target {
+        Some(target) => {
+            outln!("🔗 Opening {} ({})", task, target);
+            restart_dead_panes(&task, &target, &worktree, relaunch)?;
+            target
+        }
+        None => {
+            let session = recreate_session(&task, &worktree)?;
+            registry = SessionRegistry::load()?;
+            session
+        }
+    };
+
+    registry.mark_attached(&target);
+    registry.save()?;
+    jump_to(&target)
+}
+
+/// Worktree of `task`: the one recorded with its tmux session, else the worktree whose
+/// session manifest, branch or directory names the task
+fn find_worktree(task: &str, registry: &SessionRegistry) -> Result<PathBuf> {
+    let recorded = registry.sorted()
+        .into_iter()
+        .find(|session| session.task == task && session.worktree.is_dir())
+        .map(|session| session.worktree.clone());
+    if let Some(worktree) = recorded {
+        return Ok(worktree);
+    }
+
+    let branch = format!("feature-{}", task);
+    let found = list_worktrees()?
+        .into_iter()
+        .filter(|worktree| !worktree.is_bare && !worktree.is_prunable)
+        .find(|worktree| {
+            let manifest_task = SessionManifest::load(&worktree.path).ok().flatten().map(|manifest| manifest.task);
+            manifest_task.as_deref() == Some(task)
+                || worktree.branch.as_deref() == Some(branch.as_str())
+                || worktree.path.file_name().is_some_and(|name| name.to_string_lossy().ends_with(task))
+        });
+    match found {
+        Some(worktree) => Ok(worktree.path),
+        None => Err(StacksError::validation(format!("No worktree found for task {}", task))
+            .with_hint("List worktrees with 'git worktree list', or create one with 'stacks worktree'")
+            .into()),
+    }
+}
+
+/// Start a session for the task again: a shell on the left and claude on the right
+fn recreate_session(task: &str, worktree: &Path) -> Result<String> {
+    let session = format!("{}-{}", get_repo_name()?, task);
+    let dir = worktree.to_string_lossy().to_string();
+    outln!("🆕 The tmux session of {} is gone; starting {} in {}", task, session, dir);
+
+    Process::tmux()
+        .args(["new-session", "-d", "-s", &session, "-c", &dir])
+        .run()
+        .with_context(|| format!("Failed to create tmux session {}", session))?;
+    let command = crate::config::load_config()?.launch.command_for_pane(CLAUDE_COMMAND, 0)?;
+    Process::tmux()
+        .args(["split-window", "-h", "-t", &format!("{}:0", session), "-c", &dir, &command])
+        .run()
+        .context("Failed to split tmux window and start Claude Code")?;
+    Process::tmux()
+        .args(["select-pane", "-t", &format!("{}:0.0", session)])
+        .run()
+        .context("Failed to select tmux pane")?;
+
+    label_target(&session, &task_label(task, worktree));
+    record_session(task, &session, worktree);
+    Ok(session)
+}
+
+/// Respawn claude in the target's dead panes, or point at --relaunch when not asked to
+fn restart_dead_panes(task: &str, target: &str, worktree: &Path, relaunch: bool) -> Result<()> {
+    // A bare session name lists the panes of every window; windows and pane ids just their window
+    let scope = match target.contains(':') || target.starts_with('%') {
+        true => None,
+        false => Some("-s"),
+    };
+    let mut list = Process::tmux().arg("list-panes");
+    if let Some(flag) = scope {
+        list = list.arg(flag);
+    }
+    let output = list
+        .args(["-t", target, "-F", "#{pane_id}\t#{pane_dead}"])
+        .read_only()
+        .run()
+        .with_context(|| format!("Failed to list the panes of {}", target))?;
+    let dead: Vec<&str> = output.stdout
+        .lines()
+        .filter_map(|line| line.split_once('\t'))
+        .filter(|(_, dead)| *dead == "1")
+        .map(|(pane, _)| pane)
+        .collect();
+
+    if dead.is_empty() {
+        return Ok(());
+    }
+    if !relaunch {
+        outln!("  💡 Claude exited in {} pane(s); run 'stacks open {} --relaunch' to restart it", dead.len(), task);
+        return Ok(());
+    }
+
+    let command = crate::config::load_config()?.launch.command_for_pane(CLAUDE_COMMAND, 0)?;
+    let dir = worktree.to_string_lossy().to_string();
+    for pane in dead {
+        Process::tmux()
+            .args(["respawn-pane", "-k", "-t", pane, "-c", &dir, &command])
+            .run()
+            .with_context(|| format!("Failed to restart claude in pane {}", pane))?;
+        outln!("  🔄 Restarted claude in pane {}", pane);
+    }
+    Ok(())
+}
@@ -0,0 +1,98 @@
+use anyhow::{Result, Context};
+use walkdir::WalkDir;
+
+use crate::core::lockfile::resolve_stack_metadata;
+use crate::core::remote_stack_manager::RemoteStackManager;
+use crate::core::schema::{self, OutdatedOutput, StackUpdate, SCHEMA_VERSION};
+use crate::core::upstream::{check_upstream, UpstreamStatus};
+use crate::utils::output;
+
+/// Read-only report of installed stacks that have upstream updates
+///
+/// `json` prints every stack as an `OutdatedOutput` instead, with progress on stderr.
+pub async fn run(json: bool) -> Result<()> {
+    if json {
+        output::redirect_to_stderr();
+    }
+    outln!("📦 Checking stacks for upstream updates...");
+
+    let stacks_dir = std::env::current_dir()?.join("stacks");
+
+    if !stacks_dir.exists() {
+        if json {
+            return schema::print(&OutdatedOutput { schema_version: SCHEMA_VERSION, stacks: Vec::new() });
+        }
+        outln!("No stacks directory found. Run 'stacks checkout <stack-name>' to check out a stack.");
+        return Ok(());
+    }
+
+    let manager = RemoteStackManager::new().context("Failed to initialize remote stack manager")?;
+    let mut rows: Vec<(String, UpstreamStatus)> = Vec::new();
+
+    for entry in WalkDir::new(&stacks_dir)
+        .min_depth(1)
+        .max_depth(1)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_dir())
+    {
+        let stack_name = entry.file_name().to_string_lossy().to_string();
+        let metadata = resolve_stack_metadata(&stack_name);
+
+        let status = check_upstream(&manager, &metadata).await
+            .with_context(|| format!("Failed to check upstream for stack {}", stack_name))?;
+        rows.push((stack_name, status));
+    }
+
+    rows.sort_by(|a, b| a.0.cmp(&b.0));
+    if json {
+        let stacks = rows.iter().map(|(name, status)| StackUpdate::new(name, status)).collect();
+        return schema::print(&OutdatedOutput { schema_version: SCHEMA_VERSION, stacks });
+    }
+
+    if rows.is_empty() {
+        outln!("No stacks found in the stacks directory.");
+        return Ok(());
+    }
+
+    print_report(&rows);
+
+    let outdated = rows.iter().filter(|(_, status)| status.is_outdated()).count();
+    if outdated == 0 {
+        outln!("\n✅ All stacks are up to date");
+    } else {
+        outln!("\n💡 {} stack(s) have updates. Run 'stacks pull <stack-name>' to update.", outdated);
+    }
+
+    Ok(())
+}
+
+fn print_report(rows: &[(String, UpstreamStatus)]) {
+    let name_width = rows.iter()
+        .map(|(name, _)| name.len())
+        .max()
+        .unwrap_or(0)
+        .max("STACK".len());
+
+    outln!("\n  {:<width$}  {:<9}  {:<9}  STATUS", "STACK", "INSTALLED", "LATEST", width = name_width);
+    for (name, status) in rows {
+        outln!(
+            "  {:<width$}  {:<9}  {:<9}  {}",
+            name,
+            short_sha(status.installed_commit.as_deref()),
+            short_sha(status.remote_commit.as_deref()),
+            status.describe(),
+            width = name_width
+        );
+        if status.is_outdated() {
+            if let Some(message) = &status.latest_message {
+                outln!("  {:<width$}  └─ latest: {}", "", message, width = name_width);
+            }
+        }
+    }
+}
+
+fn short_sha(sha: Option<&str>) -> String {
+    sha.map(|sha| sha.chars().take(7).collect())
+        .unwrap_or_else(|| "-".to_string())
+}
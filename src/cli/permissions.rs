@@ -0,0 +1,22 @@
+use std::path::PathBuf;
+use anyhow::Result;
+
+use crate::core::permission_generator::revoke_generated_permissions;
+
+/// Remove the generated feature-branch permissions from a worktree's settings.local.json
+pub async fn revoke(worktree: Option<PathBuf>) -> Result<()> {
+    let worktree = match worktree {
+        Some(path) => path,
+        None => std::env::current_dir()?,
+    };
+    let settings_path = worktree.join(".claude").join("settings.local.json");
+
+    let removed = revoke_generated_permissions(&settings_path).await?;
+    if removed == 0 {
+        outln!("No generated permissions found in {}", settings_path.display());
+    } else {
+        outln!("🛡️ Removed {} generated permission rule(s) from {}", removed, settings_path.display());
+    }
+
+    Ok(())
+}
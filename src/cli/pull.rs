@@ -1,95 +1,229 @@
-use std::path::Path;
-use std::process::Command;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 use anyhow::{Result, Context, bail};
-use dialoguer::Confirm;
-use is_terminal::IsTerminal;
+use dialoguer::{Confirm, Select};
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use serde::{Deserialize, Serialize};
 use walkdir::WalkDir;
 
+use crate::cli::repair::refresh_stack_integration;
+use crate::core::auth;
+use crate::core::hooks::{self, HookContext, Operation, Phase};
+use crate::core::lockfile::{self, resolve_stack_metadata};
 use crate::core::remote_stack_manager::StackMetadata;
+use crate::core::daemon;
+use crate::core::policy;
+use crate::core::git::Repo;
+use crate::core::upstream;
+use crate::utils::process::{is_dry_run, Process, ProcessOutput};
+use crate::utils::retry::{is_transient_git_process, RetryPolicy};
+use crate::utils::files;
+use crate::utils::fuzzy;
+use crate::utils::output::colors_enabled;
+use crate::utils::terminal::is_interactive;
+
+/// Pull interrupted by merge conflicts, persisted so --continue/--abort can finish it
+#[derive(Debug, Serialize, Deserialize)]
+struct PullState {
+    stack_name: String,
+    stashed: bool,
+}
+
+/// Pull one stack, or pick from all of them; with `preview`, only show what pulling the named stack brings in
+pub async fn run(stack_name: Option<String>, continue_pull: bool, abort: bool, preview: bool) -> Result<()> {
+    if continue_pull {
+        return continue_interrupted_pull().await;
+    }
+    if abort {
+        return abort_interrupted_pull();
+    }
+    // A preview only fetches, so it neither waits for a pending pull nor runs the pull hooks
+    if let (Some(name), true) = (&stack_name, preview) {
+        return pull_single_stack(name.clone(), true).await;
+    }
+    
+    if let Some(state) = load_pull_state()? {
+        bail!(
+            "A pull of stack '{}' is waiting for conflict resolution. Run 'stacks pull --continue' or 'stacks pull --abort' first.",
+            state.stack_name
+        );
+    }
+    
+    let hook_context = HookContext::new(Operation::Pull).with_stacks(stack_name.iter().cloned().collect());
+    hooks::run(Phase::Pre, &hook_context)?;
 
-pub async fn run(stack_name: Option<String>) -> Result<()> {
     match stack_name {
         Some(name) => {
             // Pull specific stack
-            pull_single_stack(name).await
+            pull_single_stack(name, false).await?;
         }
         None => {
             // Pull all stacks
-            pull_all_stacks().await
+            pull_all_stacks().await?;
         }
     }
+
+    hooks::run(Phase::Post, &hook_context)
 }
 
 async fn pull_all_stacks() -> Result<()> {
-    println!("🔄 Pulling updates for all stacks...");
+    outln!("🔄 Pulling updates for all stacks...");
     
     let stacks_dir = std::env::current_dir()?.join("stacks");
     
     if !stacks_dir.exists() {
-        println!("No stacks directory found. Run 'stacks checkout <stack-name>' to check out a stack.");
+        outln!("No stacks directory found. Run 'stacks checkout <stack-name>' to check out a stack.");
         return Ok(());
     }
     
-    let mut found_stacks = Vec::new();
-    
-    // Find all stack directories with metadata
-    for entry in WalkDir::new(&stacks_dir)
+    // Every directory under stacks/ is a subtree; its source comes from stacks.lock
+    let mut found_stacks: Vec<String> = WalkDir::new(&stacks_dir)
         .min_depth(1)
         .max_depth(1)
         .into_iter()
         .filter_map(|e| e.ok())
         .filter(|e| e.file_type().is_dir())
-    {
-        let stack_name = entry.file_name().to_string_lossy().to_string();
-        let stack_path = entry.path().to_path_buf();
-        
-        // Check if it has metadata (indicating it's a managed stack)
-        if stack_path.join(".stack-metadata.json").exists() {
-            found_stacks.push(stack_name);
-        }
-    }
+        .map(|e| e.file_name().to_string_lossy().to_string())
+        .collect();
     
     if found_stacks.is_empty() {
-        println!("  ℹ️ No managed stacks found to update.");
+        outln!("  ℹ️ No managed stacks found to update.");
         return Ok(());
     }
+    found_stacks.sort();
     
-    println!("  📝 Found {} managed stack(s):", found_stacks.len());
-    for name in &found_stacks {
-        println!("    • {}", name);
+    let details: Vec<(String, String)> = found_stacks.iter()
+        .map(|name| (name.clone(), stack_details(name)))
+        .collect();
+    outln!("  📝 Found {} managed stack(s):", found_stacks.len());
+    for (name, details) in &details {
+        outln!("    • {} ({})", name, details);
     }
     
-    // Confirm pull all
-    let should_proceed = if std::io::stdin().is_terminal() {
-        Confirm::new()
-            .with_prompt("Pull updates for all these stacks?")
-            .default(true)
-            .interact()?
+    // Pick which stacks to pull
+    let selected: Vec<String> = if is_interactive() {
+        fuzzy::pick_subset("Pull which stacks?", &details)?
     } else {
-        println!("Auto-proceeding with pull in non-interactive mode...");
-        true
+        outln!("Auto-proceeding with pull in non-interactive mode...");
+        found_stacks
     };
     
-    if !should_proceed {
-        println!("Pull cancelled.");
+    if selected.is_empty() {
+        outln!("Pull cancelled.");
         return Ok(());
     }
     
-    // Pull each stack
-    for stack_name in found_stacks {
-        println!("\n{}", "=".repeat(50));
-        match pull_single_stack(stack_name.clone()).await {
-            Ok(_) => println!("  ✅ Successfully updated {}", stack_name),
-            Err(e) => println!("  ❌ Failed to update {}: {}", stack_name, e),
+    // Fetch every stack's upstream concurrently, showing per-stack progress
+    let fetched = fetch_all_upstreams(&selected).await?;
+    
+    // Merges touch the shared index, so apply them one at a time
+    let mut failed = Vec::new();
+    let mut stopped = false;
+    for (stack_name, fetch_result) in fetched {
+        outln!("\n{}", "=".repeat(50));
+        outln!("🔄 Updating stack: {}", stack_name);
+        
+        let result = match fetch_result {
+            Ok((upstream_ref, metadata)) => match record_source_branch(&stack_name, &metadata) {
+                Ok(()) => merge_stack_upstream(&stack_name, &upstream_ref).await,
+                Err(e) => Err(e),
+            },
+            Err(e) => Err(e),
+        };
+        
+        match result {
+            Ok(_) => outln!("  ✅ Successfully updated {}", stack_name),
+            Err(e) => {
+                outln!("  ❌ Failed to update {}: {}", stack_name, e);
+                failed.push(stack_name);
+            }
+        }
+        
+        if load_pull_state()?.is_some() {
+            outln!("\n⏸️ Stopping: resolve the conflicts above before pulling the remaining stacks.");
+            stopped = true;
+            break;
         }
     }
     
-    println!("\n🎉 Finished updating all stacks!");
+    if !failed.is_empty() {
+        bail!("{} of {} stack(s) failed to update: {}", failed.len(), selected.len(), failed.join(", "));
+    }
+    if !stopped {
+        outln!("\n🎉 Finished updating all stacks!");
+    }
     Ok(())
 }
 
-async fn pull_single_stack(stack_name: String) -> Result<()> {
-    println!("🔄 Pulling updates for stack: {}", stack_name);
+/// Upstream drift from a fresh `stacks daemon` status, and the stack's local changes
+fn stack_details(stack_name: &str) -> String {
+    let upstream = daemon::load_status()
+        .filter(|status| status.is_fresh(chrono::Utc::now()))
+        .and_then(|status| status.stacks.into_iter().find(|stack| stack.name == stack_name))
+        .map(|stack| stack.upstream)
+        .unwrap_or_else(|| "upstream not checked".to_string());
+    let changed = Repo::current()
+        .and_then(|repo| repo.changes(&format!("stacks/{}", stack_name)))
+        .map(|changes| changes.len())
+        .unwrap_or(0);
+    match changed {
+        0 => upstream,
+        changed => format!("{}, {} local change(s)", upstream, changed),
+    }
+}
+
+/// Fetch all stacks' upstream branches in parallel with a multi-progress display
+///
+/// Each successful fetch comes with the source it was fetched from.
+async fn fetch_all_upstreams(stack_names: &[String]) -> Result<Vec<(String, Result<(String, StackMetadata)>)>> {
+    let multi = MultiProgress::new();
+    let template = match colors_enabled() {
+        true => "  {spinner} {prefix:.bold} {msg}",
+        false => "  {spinner} {prefix} {msg}",
+    };
+    let style = ProgressStyle::with_template(template)
+        .context("Invalid progress template")?;
+    
+    let mut handles = Vec::new();
+    for stack_name in stack_names {
+        let bar = multi.add(ProgressBar::new_spinner());
+        bar.set_style(style.clone());
+        bar.set_prefix(stack_name.clone());
+        bar.set_message("fetching...");
+        bar.enable_steady_tick(Duration::from_millis(120));
+        
+        let name = stack_name.clone();
+        let handle = tokio::task::spawn_blocking(move || {
+            let mut metadata = resolve_stack_metadata(&name);
+            let result = fetch_stack_upstream(&name, &mut metadata).map(|upstream_ref| (upstream_ref, metadata));
+            match &result {
+                Ok(_) => bar.finish_with_message("✅ fetched"),
+                Err(e) => bar.finish_with_message(format!("❌ {}", e.to_string().lines().next().unwrap_or(""))),
+            }
+            result
+        });
+        handles.push((stack_name.clone(), handle));
+    }
+    
+    let mut results = Vec::new();
+    for (stack_name, handle) in handles {
+        let result = handle.await
+            .context("Fetch task panicked")?;
+        results.push((stack_name, result));
+    }
+    
+    multi.clear().ok();
+    Ok(results)
+}
+
+/// Fetch one stack, show what the update brings and merge it once confirmed
+///
+/// With `preview`, stop after showing the changes.
+async fn pull_single_stack(stack_name: String, preview: bool) -> Result<()> {
+    match preview {
+        true => outln!("🔍 Previewing updates for stack: {}", stack_name),
+        false => outln!("🔄 Pulling updates for stack: {}", stack_name),
+    }
     
     let stack_path = std::env::current_dir()?.join("stacks").join(&stack_name);
     
@@ -98,114 +232,533 @@ async fn pull_single_stack(stack_name: String) -> Result<()> {
         bail!("Stack '{}' not found. Run 'stacks checkout {}' first.", stack_name, stack_name);
     }
     
-    // Load stack metadata
-    let metadata = load_stack_metadata(&stack_path)?;
-    println!("  📋 Source: {}", metadata.source_repo);
+    // Resolve the stack's source from stacks.lock (or the naming convention)
+    let mut metadata = resolve_stack_metadata(&stack_name);
+    outln!("  📋 Source: {}", metadata.source_repo);
     
-    // Check for uncommitted changes in the stack directory
-    let status_output = Command::new("git")
-        .current_dir(&stack_path)
-        .args(["status", "--porcelain"])
-        .output()
-        .context("Failed to check git status")?;
+    outln!("  📡 Fetching updates from {}...", metadata.source_repo);
+    let upstream_ref = fetch_stack_upstream(&stack_name, &mut metadata)?;
+    
+    if !show_incoming_changes(&stack_name, &upstream_ref)? {
+        return Ok(());
+    }
+    if preview {
+        outln!("  💡 Run 'stacks pull {}' to apply these changes", stack_name);
+        return Ok(());
+    }
+    if is_interactive() && !is_dry_run() {
+        let proceed = Confirm::new()
+            .with_prompt(format!("Pull these changes into stacks/{}?", stack_name))
+            .default(true)
+            .interact()?;
+        if !proceed {
+            outln!("Pull cancelled.");
+            return Ok(());
+        }
+    }
     
-    let has_changes = !status_output.stdout.is_empty();
+    record_source_branch(&stack_name, &metadata)?;
+    merge_stack_upstream(&stack_name, &upstream_ref).await
+}
+
+/// Print the upstream commits and CHANGELOG.md additions since the installed revision
+///
+/// Returns whether there is anything to pull.
+fn show_incoming_changes(stack_name: &str, upstream_ref: &str) -> Result<bool> {
+    let repo = Repo::current()?;
+    let installed = upstream::installed_commit(stack_name)?;
+    if installed.as_deref() == Some(repo.resolve_commit(upstream_ref)?.as_str()) {
+        outln!("  ✅ Subtree is already up to date!");
+        return Ok(false);
+    }
     
-    if has_changes {
-        println!("  ⚠️ Warning: Stack has uncommitted changes:");
-        let status_output = Command::new("git")
-            .current_dir(&stack_path)
-            .args(["status", "--short"])
-            .output()
-            .context("Failed to show git status")?;
-        
-        println!("{}", String::from_utf8_lossy(&status_output.stdout));
+    let changes = upstream::incoming_changes(Path::new("."), installed.as_deref(), upstream_ref)?;
+    match changes.partial {
+        true => outln!("  📝 Latest upstream commits (the installed revision isn't in the upstream history):"),
+        false => outln!("  📝 {} new upstream commit(s):", changes.commits.len()),
+    }
+    for (sha, subject) in &changes.commits {
+        outln!("    {} {}", sha, subject);
+    }
+    if !changes.changelog.is_empty() {
+        outln!("  📰 CHANGELOG.md:");
+        for line in &changes.changelog {
+            outln!("    {}", line);
+        }
+    }
+    Ok(true)
+}
+
+/// Local ref that holds a stack's fetched upstream branch
+fn upstream_ref(stack_name: &str) -> String {
+    format!("refs/stacks/upstream/{}", stack_name)
+}
+
+/// Fetch a stack's upstream branch into a private ref; safe to run concurrently
+///
+/// For a stack living in a directory of its repository, the ref ends up at that directory's
+/// split history, which is what the subtree was added from. When the branch had to fall back to
+/// the remote's default branch, `metadata` is updated to the branch that was fetched.
+fn fetch_stack_upstream(stack_name: &str, metadata: &mut StackMetadata) -> Result<String> {
+    let target_ref = fetch_upstream_branch(stack_name, metadata)?;
+    if let Some(path) = &metadata.source_path {
+        let split = Process::git()
+            .args(["subtree", "split", "--prefix", path, &target_ref])
+            .run()
+            .with_context(|| format!("Failed to split {} out of {}", path, metadata.source_repo))?;
+        Process::git()
+            .args(["update-ref", &target_ref, split.stdout.trim()])
+            .run()
+            .context("Failed to update the upstream ref")?;
+    }
+    Ok(target_ref)
+}
+
+fn fetch_upstream_branch(stack_name: &str, metadata: &mut StackMetadata) -> Result<String> {
+    let target_ref = upstream_ref(stack_name);
+    let output = fetch_ref(&metadata.source_repo, &metadata.source_ref(), &target_ref)?;
+    
+    if output.success() {
+        return Ok(target_ref);
+    }
+    
+    // Only a branch guessed from the naming convention may be wrong; recorded branches and pinned tags never fall back
+    let error = output.stderr;
+    let guessed = metadata.source_tag.is_none() && lockfile::recorded_stack_metadata(stack_name).is_none();
+    if guessed {
+        if let Some(default_branch) = upstream::missing_branch_fallback(&metadata.source_repo, &metadata.source_branch) {
+            let retry = fetch_ref(&metadata.source_repo, &format!("refs/heads/{}", default_branch), &target_ref)?;
+            if retry.success() {
+                outln!("  ℹ️ Branch '{}' not found for {}, using default branch '{}'", metadata.source_branch, stack_name, default_branch);
+                metadata.source_branch = default_branch;
+                return Ok(target_ref);
+            }
+        }
+    }
+    
+    bail!("Failed to fetch {} ({}): {}", metadata.source_repo, metadata.describe_ref(), error.trim());
+}
+
+/// Fetch `source_ref` (a full ref name, e.g. refs/tags/v1.2.0) of a repository into `target_ref`
+///
+/// Also runs with --dry-run: it only writes the private upstream ref, which previews read.
+fn fetch_ref(repo_url: &str, source_ref: &str, target_ref: &str) -> Result<ProcessOutput> {
+    RetryPolicy::from_config()
+        .run_blocking("Fetching stack upstream", || {
+            Process::git()
+                .env("LC_ALL", "C")
+                .envs(auth::git_auth_env(repo_url))
+                .args([
+                    "fetch", "--quiet", "--no-tags", "--no-write-fetch-head",
+                    repo_url,
+                    &format!("+{}:{}", source_ref, target_ref)
+                ])
+                .read_only()
+                .output()
+        }, is_transient_git_process)
+        .context("Failed to fetch stack upstream")
+}
+
+/// Record the branch a guessed stack was actually fetched from, so later pulls and pushes use it
+fn record_source_branch(stack_name: &str, metadata: &StackMetadata) -> Result<()> {
+    if resolve_stack_metadata(stack_name).source_branch != metadata.source_branch {
+        lockfile::record_stack(metadata.clone())
+            .context("Failed to record the stack's branch in stacks.lock")?;
+    }
+    Ok(())
+}
+
+/// Squash-merge a fetched upstream ref into the stack's subtree
+async fn merge_stack_upstream(stack_name: &str, upstream_ref: &str) -> Result<()> {
+    // Check for uncommitted changes in the stack directory
+    let local_changes = stack_changes(stack_name)?;
+    let mut stashed = false;
+    
+    if !local_changes.is_empty() {
+        outln!("  ⚠️ Stack has uncommitted changes:");
+        for change in &local_changes {
+            outln!("    {}", change.replace(&format!("stacks/{}/", stack_name), ""));
+        }
         
-        let should_continue = if std::io::stdin().is_terminal() {
+        let should_continue = if is_interactive() {
             Confirm::new()
-                .with_prompt("Continue with pull? (commit changes first)")
-                .default(false)
+                .with_prompt("Stash these changes, pull, then reapply them?")
+                .default(true)
                 .interact()?
         } else {
-            println!("Auto-proceeding with pull in non-interactive mode...");
+            outln!("Auto-stashing local changes in non-interactive mode...");
             true
         };
         
         if !should_continue {
-            println!("Pull cancelled.");
+            outln!("Pull cancelled.");
+            outln!("  💡 Tip: Run 'stacks push {}' to commit and push your changes first", stack_name);
             return Ok(());
         }
         
-        println!("  💡 Tip: Run 'stacks push {}' to commit and push your changes first", stack_name);
+        stashed = stash_stack_changes(stack_name)?;
+    }
+    
+    // Nothing to merge when the subtree already contains the fetched commit
+    let repo = Repo::current()?;
+    let upstream_commit = repo.resolve_commit(upstream_ref)?;
+    if upstream::installed_commit(stack_name)?.as_deref() == Some(upstream_commit.as_str()) {
+        if stashed {
+            restore_stashed_changes(stack_name)?;
+        }
+        outln!("  ✅ Subtree is already up to date!");
+        return Ok(());
+    }
+    let head_before = repo.head_commit();
+    
+    // Refuse an update whose settings grant what the organization policy forbids, before it lands
+    if let Err(error) = policy::enforce_commit(stack_name, upstream_ref).await {
+        if stashed {
+            restore_stashed_changes(stack_name)?;
+        }
+        return Err(error);
     }
     
-    // Pull updates using git subtree
-    println!("  📡 Pulling subtree updates from {}...", metadata.source_repo);
-    let pull_output = Command::new("git")
+    // Merge the fetched upstream using git subtree
+    outln!("  🔀 Merging upstream changes into stacks/{}...", stack_name);
+    let merge_output = Process::git()
         .args([
-            "subtree", "pull",
+            "subtree", "merge",
             "--prefix", &format!("stacks/{}", stack_name),
-            &metadata.source_repo,
-            "main",
+            upstream_ref,
             "--squash"
         ])
         .output()
-        .context("Failed to pull subtree updates")?;
+        .context("Failed to merge subtree updates")?;
+    if is_dry_run() {
+        return Ok(());
+    }
     
-    if !pull_output.status.success() {
-        let error = String::from_utf8_lossy(&pull_output.stderr);
-        
-        // Check if it's already up to date
-        if error.contains("Already up to date") || error.contains("up-to-date") {
-            println!("  ✅ Subtree is already up to date!");
-            return Ok(());
+    let stderr = merge_output.stderr.clone();
+    let up_to_date = merge_output.success() && repo.head_commit() == head_before;
+    
+    if !merge_output.success() {
+        let conflicts = conflicted_files()?;
+        if !conflicts.is_empty() {
+            return handle_pull_conflicts(stack_name, stashed, &conflicts).await;
         }
         
-        bail!("Failed to pull subtree updates: {}", error);
+        if stashed {
+            restore_stashed_changes(stack_name)?;
+        }
+        bail!("Failed to pull subtree updates: {}", stderr);
+    }
+    
+    if stashed {
+        restore_stashed_changes(stack_name)?;
     }
     
-    let output_str = String::from_utf8_lossy(&pull_output.stdout);
-    if output_str.contains("Already up to date") {
-        println!("  ✅ Subtree is already up to date!");
+    if up_to_date {
+        outln!("  ✅ Subtree is already up to date!");
         return Ok(());
     }
     
-    println!("  ✅ Successfully updated stack!");
+    outln!("  ✅ Successfully updated stack!");
+    
+    record_installed_commit(stack_name);
+    refresh_stack_integration(stack_name).await?;
     
-    // Show recent changes
-    let log_output = Command::new("git")
-        .current_dir(&stack_path)
-        .args(["log", "--oneline", "-5", "HEAD~5..HEAD"])
+    // Show recent upstream changes
+    let log_output = Process::git()
+        .args(["log", "--oneline", "-3", upstream_ref])
+        .read_only()
         .output()
         .context("Failed to show recent changes")?;
     
-    if log_output.status.success() && !log_output.stdout.is_empty() {
-        println!("  📝 Recent changes:");
-        for line in String::from_utf8_lossy(&log_output.stdout).lines().take(3) {
+    if log_output.success() && !log_output.stdout.is_empty() {
+        outln!("  📝 Recent changes:");
+        for line in log_output.stdout.lines() {
             if !line.trim().is_empty() {
-                println!("    {}", line);
+                outln!("    {}", line);
             }
         }
     }
     
-    println!("  🎉 Stack '{}' updated successfully!", stack_name);
+    outln!("  🎉 Stack '{}' updated successfully!", stack_name);
+    
+    Ok(())
+}
+
+/// Walk the user through conflicts left behind by `git subtree pull`
+async fn handle_pull_conflicts(stack_name: &str, stashed: bool, conflicts: &[String]) -> Result<()> {
+    outln!("  ⚠️ Upstream changes conflict with this project's copy of '{}':", stack_name);
+    for file in conflicts {
+        outln!("    • {}", file);
+    }
+    
+    save_pull_state(&PullState {
+        stack_name: stack_name.to_string(),
+        stashed,
+    })?;
+    
+    let strategies = vec![
+        "Keep ours (project version) for all conflicted files",
+        "Take theirs (upstream version) for all conflicted files",
+        "Resolve manually",
+    ];
+    
+    let selection = if is_interactive() {
+        Select::new()
+            .with_prompt("How do you want to resolve these conflicts?")
+            .items(&strategies)
+            .default(2)
+            .interact()?
+    } else {
+        2
+    };
+    
+    match selection {
+        0 => {
+            checkout_conflict_side("--ours", conflicts)?;
+            continue_interrupted_pull().await
+        }
+        1 => {
+            checkout_conflict_side("--theirs", conflicts)?;
+            continue_interrupted_pull().await
+        }
+        _ => {
+            outln!("  💡 Next steps:");
+            outln!("    1. Edit the files above and resolve the <<<<<<< / >>>>>>> markers");
+            outln!("    2. Mark them resolved: git add <file>");
+            outln!("    3. Finish the pull: stacks pull --continue");
+            outln!("    (or undo it entirely: stacks pull --abort)");
+            Ok(())
+        }
+    }
+}
+
+/// Resolve every conflicted file by taking one side of the merge
+fn checkout_conflict_side(side: &str, files: &[String]) -> Result<()> {
+    let output = Process::git()
+        .arg("checkout")
+        .arg(side)
+        .arg("--")
+        .args(files)
+        .output()
+        .context("Failed to check out conflict resolution")?;
+    
+    if !output.success() {
+        bail!("Failed to resolve conflicts with {}: {}", side, output.stderr);
+    }
+    
+    let add_output = Process::git()
+        .arg("add")
+        .arg("--")
+        .args(files)
+        .output()
+        .context("Failed to stage resolved files")?;
+    
+    if !add_output.success() {
+        bail!("Failed to stage resolved files: {}", add_output.stderr);
+    }
+    
+    Ok(())
+}
+
+/// Commit a resolved subtree merge and reapply any stashed stack changes
+async fn continue_interrupted_pull() -> Result<()> {
+    let state = load_pull_state()?
+        .context("No interrupted stack pull to continue")?;
+    
+    let remaining = conflicted_files()?;
+    if !remaining.is_empty() {
+        outln!("  ⚠️ These files still have unresolved conflicts:");
+        for file in &remaining {
+            outln!("    • {}", file);
+        }
+        bail!("Resolve the conflicts and 'git add' the files, then run 'stacks pull --continue' again");
+    }
+    
+    if merge_in_progress() {
+        let output = Process::git()
+            .args(["commit", "--no-edit"])
+            .output()
+            .context("Failed to commit resolved merge")?;
+        
+        if !output.success() {
+            bail!("Failed to commit resolved merge: {}", output.stderr);
+        }
+    }
+    
+    clear_pull_state()?;
+    
+    if state.stashed {
+        restore_stashed_changes(&state.stack_name)?;
+    }
+    
+    record_installed_commit(&state.stack_name);
+    refresh_stack_integration(&state.stack_name).await?;
+    
+    outln!("  🎉 Stack '{}' updated successfully!", state.stack_name);
+    Ok(())
+}
+
+/// Update stacks.lock after a merge; failures only warn since the pull itself succeeded
+fn record_installed_commit(stack_name: &str) {
+    if let Err(e) = lockfile::update_installed_commit(stack_name) {
+        outln!("  ⚠️ Could not update stacks.lock: {}", e);
+    }
+}
+
+/// Abandon an interrupted pull, restoring the pre-pull state
+fn abort_interrupted_pull() -> Result<()> {
+    let state = load_pull_state()?
+        .context("No interrupted stack pull to abort")?;
+    
+    if merge_in_progress() {
+        let output = Process::git()
+            .args(["merge", "--abort"])
+            .output()
+            .context("Failed to abort merge")?;
+        
+        if !output.success() {
+            bail!("Failed to abort merge: {}", output.stderr);
+        }
+    }
+    
+    clear_pull_state()?;
     
+    if state.stashed {
+        restore_stashed_changes(&state.stack_name)?;
+    }
+    
+    outln!("  ↩️ Pull of stack '{}' aborted", state.stack_name);
     Ok(())
 }
 
-fn load_stack_metadata(stack_path: &Path) -> Result<StackMetadata> {
-    let metadata_file = stack_path.join(".stack-metadata.json");
+fn merge_in_progress() -> bool {
+    Repo::current()
+        .map(|repo| repo.merge_in_progress())
+        .unwrap_or(false)
+}
+
+fn pull_state_path() -> Result<PathBuf> {
+    Ok(Repo::current()?.git_dir().join("stacks-pull-state.json"))
+}
+
+fn load_pull_state() -> Result<Option<PullState>> {
+    let path = pull_state_path()?;
+    if !path.exists() {
+        return Ok(None);
+    }
     
-    if !metadata_file.exists() {
-        bail!("Stack metadata not found. This stack may have been created with an older version or manually.");
+    let content = std::fs::read_to_string(&path)
+        .context("Failed to read pull state")?;
+    let state = serde_json::from_str(&content)
+        .context("Failed to parse pull state")?;
+    Ok(Some(state))
+}
+
+fn save_pull_state(state: &PullState) -> Result<()> {
+    let content = serde_json::to_string_pretty(state)
+        .context("Failed to serialize pull state")?;
+    files::write(pull_state_path()?, content)
+        .context("Failed to write pull state")?;
+    Ok(())
+}
+
+fn clear_pull_state() -> Result<()> {
+    let path = pull_state_path()?;
+    if path.exists() {
+        files::remove_file(&path)
+            .context("Failed to remove pull state")?;
+    }
+    Ok(())
+}
+
+/// Label used to find the stash created for a stack pull
+fn stash_label(stack_name: &str) -> String {
+    format!("stacks-pull:{}", stack_name)
+}
+
+/// List uncommitted changes (including untracked files) inside a stack subtree
+fn stack_changes(stack_name: &str) -> Result<Vec<String>> {
+    Ok(Repo::current()?
+        .changes(&format!("stacks/{}", stack_name))?
+        .into_iter()
+        .map(|(_, path)| path)
+        .collect())
+}
+
+/// Stash only the stack's own modifications so the subtree pull starts from a clean prefix
+fn stash_stack_changes(stack_name: &str) -> Result<bool> {
+    outln!("  📦 Stashing local changes to stacks/{}...", stack_name);
+    let output = Process::git()
+        .args([
+            "stash", "push", "--include-untracked",
+            "-m", &stash_label(stack_name),
+            "--", &format!("stacks/{}", stack_name)
+        ])
+        .output()
+        .context("Failed to stash stack changes")?;
+    
+    if !output.success() {
+        bail!("Failed to stash stack changes: {}", output.stderr);
+    }
+    
+    Ok(find_stack_stash(stack_name)?.is_some())
+}
+
+/// Find the stash entry created for this stack (e.g. "stash@{0}")
+fn find_stack_stash(stack_name: &str) -> Result<Option<String>> {
+    let output = Process::git()
+        .args(["stash", "list", "--format=%gd%x09%s"])
+        .read_only()
+        .output()
+        .context("Failed to list stashes")?;
+    
+    let label = stash_label(stack_name);
+    Ok(output.stdout
+        .lines()
+        .find(|line| line.ends_with(&label))
+        .and_then(|line| line.split('\t').next())
+        .map(|stash_ref| stash_ref.to_string()))
+}
+
+/// Reapply stashed stack changes, leaving conflict markers and guidance when they collide
+fn restore_stashed_changes(stack_name: &str) -> Result<()> {
+    let stash_ref = match find_stack_stash(stack_name)? {
+        Some(stash_ref) => stash_ref,
+        None => return Ok(()),
+    };
+    
+    outln!("  📦 Reapplying local changes...");
+    let output = Process::git()
+        .args(["stash", "pop", &stash_ref])
+        .output()
+        .context("Failed to reapply stashed changes")?;
+    
+    if output.success() {
+        outln!("  ✅ Local changes reapplied");
+        return Ok(());
     }
     
-    let metadata_content = std::fs::read_to_string(metadata_file)
-        .context("Failed to read stack metadata")?;
+    let conflicts = conflicted_files()?;
+    if conflicts.is_empty() {
+        bail!(
+            "Failed to reapply local changes (they are kept in {}): {}",
+            stash_ref, output.stderr
+        );
+    }
     
-    let metadata: StackMetadata = serde_json::from_str(&metadata_content)
-        .context("Failed to parse stack metadata")?;
+    outln!("  ⚠️ Your local changes conflict with the upstream update:");
+    for file in &conflicts {
+        outln!("    • {}", file);
+    }
+    outln!("  💡 Next steps:");
+    outln!("    1. Edit the files above and resolve the <<<<<<< / >>>>>>> markers");
+    outln!("    2. Mark them resolved: git add <file>");
+    outln!("    3. Drop the saved copy once you're happy: git stash drop {}", stash_ref);
     
-    Ok(metadata)
+    Ok(())
 }
 
+/// Files currently in an unmerged (conflicted) state
+fn conflicted_files() -> Result<Vec<String>> {
+    Ok(Repo::current()?.conflicted_files()?)
+}
@@ -1,36 +1,75 @@
 use std::path::Path;
-use std::process::Command;
 use anyhow::{Result, Context, bail};
 use dialoguer::{Confirm, Input};
-use is_terminal::IsTerminal;
 use walkdir::WalkDir;
 
-use crate::core::remote_stack_manager::StackMetadata;
+use crate::core::auth;
+use crate::core::hooks::{self, HookContext, Operation, Phase};
+use crate::core::git::Repo;
+use crate::core::remote_stack_manager::{RemoteStackManager, StackMetadata, github_token, parse_github_repo};
+use crate::core::lockfile::resolve_stack_metadata;
+use crate::core::stack_manifest::{self, ReleaseLevel};
+use crate::error::StacksError;
+use crate::utils::files;
+use crate::utils::process::Process;
+use crate::utils::fuzzy;
+use crate::utils::terminal;
+
+/// Result of pushing a single stack
+#[derive(Debug, Clone)]
+enum PushOutcome {
+    Pushed,
+    Forked { pr_url: String },
+    NoChanges,
+    Cancelled,
+}
+
+impl PushOutcome {
+    fn describe(&self) -> String {
+        match self {
+            PushOutcome::Pushed => "✅ pushed".to_string(),
+            PushOutcome::Forked { pr_url } => format!("🍴 pushed to fork ({})", pr_url),
+            PushOutcome::NoChanges => "ℹ️ no changes".to_string(),
+            PushOutcome::Cancelled => "⏭️ cancelled".to_string(),
+        }
+    }
+}
+
+pub async fn run(stack_name: Option<String>, message: Option<String>, fork: bool, release: Option<String>, quiet: bool) -> Result<()> {
+    let release = release.map(|level| level.parse::<ReleaseLevel>()).transpose()?;
+    if stack_name.is_none() && release.is_some() {
+        bail!("--release requires a stack name (e.g. 'stacks push my-stack --release patch')");
+    }
+    
+    let hook_context = HookContext::new(Operation::Push).with_stacks(stack_name.iter().cloned().collect());
+    hooks::run(Phase::Pre, &hook_context)?;
 
-pub async fn run(stack_name: Option<String>, message: Option<String>) -> Result<()> {
     match stack_name {
         Some(name) => {
             // Push specific stack
-            push_single_stack(name, message.clone()).await
+            push_single_stack(name, message.clone(), fork, release, quiet).await?;
         }
         None => {
             // Push all stacks with changes
-            push_all_stacks(message).await
+            push_all_stacks(message, fork, quiet).await?;
         }
     }
+
+    hooks::run(Phase::Post, &hook_context)
 }
 
-async fn push_all_stacks(message: Option<String>) -> Result<()> {
-    println!("🔄 Pushing changes for all stacks with modifications...");
-    
+async fn push_all_stacks(message: Option<String>, fork: bool, quiet: bool) -> Result<()> {
     let stacks_dir = std::env::current_dir()?.join("stacks");
     
     if !stacks_dir.exists() {
-        println!("No stacks directory found. Run 'stacks checkout <stack-name>' to check out a stack.");
+        if !quiet {
+            outln!("No stacks directory found. Run 'stacks checkout <stack-name>' to check out a stack.");
+        }
         return Ok(());
     }
     
-    let mut stacks_with_changes = Vec::new();
+    // Stacks with changes and how many files changed in each
+    let mut stacks_with_changes: Vec<(String, usize)> = Vec::new();
     
     // Find all stack directories with changes
     for entry in WalkDir::new(&stacks_dir)
@@ -41,74 +80,103 @@ async fn push_all_stacks(message: Option<String>) -> Result<()> {
         .filter(|e| e.file_type().is_dir())
     {
         let stack_name = entry.file_name().to_string_lossy().to_string();
-        let stack_path = entry.path().to_path_buf();
         
         // Check if this subtree has changes in the main repository
-        if has_subtree_changes(&stack_name)? {
-            stacks_with_changes.push(stack_name);
+        let changed = subtree_changes(&stack_name)?;
+        if changed > 0 {
+            stacks_with_changes.push((stack_name, changed));
         }
     }
-    
+    stacks_with_changes.sort();
+
     if stacks_with_changes.is_empty() {
-        println!("  ✅ No stacks have uncommitted changes.");
+        if !quiet {
+            outln!("🔄 Pushing changes for all stacks with modifications...");
+            outln!("  ✅ No stacks have uncommitted changes.");
+        }
         return Ok(());
     }
     
-    println!("  📝 Found {} stack(s) with changes:", stacks_with_changes.len());
-    for name in &stacks_with_changes {
-        println!("    • {}", name);
+    outln!("🔄 Pushing changes for all stacks with modifications...");
+    outln!("  📝 Found {} stack(s) with changes:", stacks_with_changes.len());
+    for (name, changed) in &stacks_with_changes {
+        outln!("    • {} ({} changed file(s))", name, changed);
     }
     
-    // Confirm push all
-    let should_proceed = if std::io::stdin().is_terminal() {
-        Confirm::new()
-            .with_prompt("Push changes for all these stacks?")
-            .default(true)
-            .interact()?
+    // Pick which stacks to push
+    let selected: Vec<String> = if is_interactive(quiet) {
+        let items: Vec<(String, String)> = stacks_with_changes.iter()
+            .map(|(name, changed)| (name.clone(), format!("{} changed file(s)", changed)))
+            .collect();
+        fuzzy::pick_subset("Push which stacks?", &items)?
     } else {
-        println!("Auto-proceeding with push in non-interactive mode...");
-        true
+        outln!("Auto-proceeding with push in non-interactive mode...");
+        stacks_with_changes.into_iter().map(|(name, _)| name).collect()
     };
     
-    if !should_proceed {
-        println!("Push cancelled.");
+    if selected.is_empty() {
+        outln!("Push cancelled.");
         return Ok(());
     }
     
-    // Push each stack
-    for stack_name in stacks_with_changes {
-        println!("\n{}", "=".repeat(50));
-        match push_single_stack(stack_name.clone(), message.clone()).await {
-            Ok(_) => println!("  ✅ Successfully pushed {}", stack_name),
-            Err(e) => println!("  ❌ Failed to push {}: {}", stack_name, e),
+    // Push each stack, collecting results for the summary
+    let mut results: Vec<(String, Result<PushOutcome>)> = Vec::new();
+    for stack_name in selected {
+        outln!("\n{}", "=".repeat(50));
+        let result = push_single_stack(stack_name.clone(), message.clone(), fork, None, quiet).await;
+        match &result {
+            Ok(outcome) => outln!("  {}: {}", stack_name, outcome.describe()),
+            Err(e) => outln!("  ❌ Failed to push {}: {}", stack_name, e),
         }
+        results.push((stack_name, result));
+    }
+    
+    print_push_summary(&results);
+    
+    let failed = results.iter().filter(|(_, result)| result.is_err()).count();
+    if failed > 0 {
+        bail!("{} of {} stack push(es) failed", failed, results.len());
     }
     
-    println!("\n🎉 Finished pushing all stacks!");
+    outln!("\n🎉 Finished pushing all stacks!");
     Ok(())
 }
 
-fn has_uncommitted_changes(stack_path: &Path) -> Result<bool> {
-    let status_output = Command::new("git")
-        .current_dir(stack_path)
-        .args(["status", "--porcelain"])
-        .output()
-        .context("Failed to check git status")?;
-    
-    Ok(!status_output.stdout.is_empty())
+/// Whether to prompt; `--quiet` pushes run from a claude hook, where nobody answers
+fn is_interactive(quiet: bool) -> bool {
+    !quiet && terminal::is_interactive()
 }
 
-fn has_subtree_changes(stack_name: &str) -> Result<bool> {
-    let status_output = Command::new("git")
-        .args(["status", "--porcelain", &format!("stacks/{}", stack_name)])
-        .output()
-        .context("Failed to check subtree git status")?;
+fn print_push_summary(results: &[(String, Result<PushOutcome>)]) {
+    let name_width = results.iter()
+        .map(|(name, _)| name.len())
+        .max()
+        .unwrap_or(0)
+        .max("STACK".len());
     
-    Ok(!status_output.stdout.is_empty())
+    outln!("\n📊 Push Summary");
+    outln!("═══════════════════════");
+    outln!("  {:<width$}  RESULT", "STACK", width = name_width);
+    for (name, result) in results {
+        let description = match result {
+            Ok(outcome) => outcome.describe(),
+            Err(e) => format!("❌ failed: {}", e.to_string().lines().next().unwrap_or("")),
+        };
+        outln!("  {:<width$}  {}", name, description, width = name_width);
+    }
 }
 
-async fn push_single_stack(stack_name: String, message: Option<String>) -> Result<()> {
-    println!("🔄 Pushing changes for stack: {}", stack_name);
+fn has_uncommitted_changes(stack_path: &Path) -> Result<bool> {
+    Ok(!Repo::discover(stack_path)?.changes_in(stack_path)?.is_empty())
+}
+
+/// Number of changed files in the stack's subtree
+fn subtree_changes(stack_name: &str) -> Result<usize> {
+    Ok(Repo::current()?.changes(&format!("stacks/{}", stack_name))?.len())
+}
+
+async fn push_single_stack(stack_name: String, message: Option<String>, fork: bool, release: Option<ReleaseLevel>, quiet: bool) -> Result<PushOutcome> {
+    outln!("🔄 Pushing changes for stack: {}", stack_name);
     
     let stack_path = std::env::current_dir()?.join("stacks").join(&stack_name);
     
@@ -117,42 +185,130 @@ async fn push_single_stack(stack_name: String, message: Option<String>) -> Resul
         bail!("Stack '{}' not found. Run 'stacks checkout {}' first.", stack_name, stack_name);
     }
     
-    // For subtrees, determine the repository URL based on stack name
-    let repo_url = match stack_name.as_str() {
-        "ts-lint-stack" => "git@github.com:csaben/ts-lint-stack.git".to_string(),
-        "stackstack" => "git@github.com:csaben/stackstack.git".to_string(),
-        _ => format!("git@github.com:csaben/{}.git", stack_name)
+    // For subtrees, the repository and branch come from stacks.lock (or the naming convention)
+    let metadata = resolve_stack_metadata(&stack_name);
+    let repo_url = metadata.source_repo.clone();
+    if let Some(path) = &metadata.source_path {
+        // A subtree push would replace the whole source branch with the stack's directory
+        return Err(StacksError::validation(format!("Stack {} is the {} directory of {}; it can't be pushed back", stack_name, path, repo_url))
+            .with_hint("Commit the change to the source repository directly, then run 'stacks pull' here")
+            .into());
+    }
+    outln!("  📋 Target: {}", repo_url);
+    
+    // Bump the stack version before collecting changes so the bump is part of the commit
+    let release = match release {
+        Some(level) => {
+            let manifest_before = std::fs::read_to_string(stack_path.join(stack_manifest::MANIFEST_FILE)).ok();
+            let current = stack_manifest::read_version(&stack_path)?
+                .unwrap_or_else(|| "0.0.0".to_string());
+            let next = stack_manifest::bump_version(&current, level)?;
+            stack_manifest::write_version(&stack_path, &stack_name, &next)?;
+            outln!("  🏷️ Bumping {} version: {} → {} ({})", stack_name, current, next, level.as_str());
+            Some(ReleaseBump { version: next, manifest_before })
+        }
+        None => None,
+    };
+    
+    let head_before = Repo::current()?.head_commit();
+    let result = commit_and_push(&stack_name, &metadata, message, fork, release.as_ref(), quiet).await;
+    let release = match release {
+        Some(release) => release,
+        None => return result,
     };
-    println!("  📋 Target: {}", repo_url);
+    match result {
+        Ok(PushOutcome::Pushed) => {
+            tag_release(&stack_name, &repo_url, &release.version)?;
+            Ok(PushOutcome::Pushed)
+        }
+        // A bump that wasn't pushed would be bumped again by the next release
+        Ok(PushOutcome::Cancelled) | Err(_) => {
+            undo_release_bump(&stack_path, &release, head_before.as_deref())?;
+            result
+        }
+        // Also after falling back to a fork for lack of write access; the pull request carries the bump
+        Ok(PushOutcome::Forked { pr_url }) => {
+            outln!("  ⚠️ Release {} went to a fork and wasn't tagged; the commit with its version bump is still on your branch", release.version);
+            Ok(PushOutcome::Forked { pr_url })
+        }
+        result => result,
+    }
+}
+
+/// Version bump of a `push --release`, with the manifest as it was before
+struct ReleaseBump {
+    version: String,
+    /// None when the stack had no stack.toml
+    manifest_before: Option<String>,
+}
+
+/// Put stack.toml back after a release that wasn't pushed, undoing its local commit if it was made
+fn undo_release_bump(stack_path: &Path, release: &ReleaseBump, head_before: Option<&str>) -> Result<()> {
+    let manifest = stack_path.join(stack_manifest::MANIFEST_FILE);
+    let committed = head_before.is_some() && Repo::current()?.head_commit().as_deref() != head_before;
+    if let (true, Some(head)) = (committed, head_before) {
+        // Keeps the commit's changes staged, as they were before committing
+        Process::git()
+            .args(["reset", "--soft", head])
+            .run()
+            .context("Failed to undo the release commit")?;
+    }
+    match &release.manifest_before {
+        Some(content) => files::write(&manifest, content),
+        None => files::remove_file(&manifest),
+    }
+    .with_context(|| format!("Failed to restore {}", manifest.display()))?;
+    if committed {
+        Process::git()
+            .args(["add", "-A", "--", &manifest.to_string_lossy()])
+            .run()
+            .context("Failed to stage the restored stack.toml")?;
+    }
+    outln!("  ↩️ Release {} wasn't pushed; restored the previous version in stack.toml", release.version);
+    Ok(())
+}
+
+/// Commit the stack's changes and push them to its repository, or to a fork without write access
+async fn commit_and_push(
+    stack_name: &str,
+    metadata: &StackMetadata,
+    message: Option<String>,
+    fork: bool,
+    release: Option<&ReleaseBump>,
+    quiet: bool,
+) -> Result<PushOutcome> {
+    let repo_url = metadata.source_repo.clone();
     
     // Check for changes in the subtree
-    let has_changes = has_subtree_changes(&stack_name)?;
+    let has_changes = subtree_changes(stack_name)? > 0;
     
     if !has_changes {
-        println!("  ℹ️ No changes detected in stack '{}'", stack_name);
-        return Ok(());
+        outln!("  ℹ️ No changes detected in stack '{}'", stack_name);
+        return Ok(PushOutcome::NoChanges);
     }
     
     // Show the changes in the subtree
-    println!("  📝 Changes detected in subtree:");
-    let status_output = Command::new("git")
+    outln!("  📝 Changes detected in subtree:");
+    let status_output = Process::git()
         .args(["status", "--short", &format!("stacks/{}", stack_name)])
+        .read_only()
         .output()
         .context("Failed to show subtree git status")?;
     
-    let output_str = String::from_utf8_lossy(&status_output.stdout);
     // Clean up the output to remove the stacks/stack-name/ prefix for better readability
-    for line in output_str.lines() {
+    for line in status_output.stdout.lines() {
         if !line.trim().is_empty() {
             let clean_line = line.replace(&format!("stacks/{}/", stack_name), "");
-            println!("    {}", clean_line);
+            outln!("    {}", clean_line);
         }
     }
     
     // Get commit message
     let commit_message = if let Some(msg) = message {
         msg
-    } else if std::io::stdin().is_terminal() {
+    } else if let Some(release) = release {
+        format!("release {} v{}", stack_name, release.version)
+    } else if is_interactive(quiet) {
         Input::<String>::new()
             .with_prompt("Enter commit message")
             .with_initial_text(format!("feat: update {} stack", stack_name))
@@ -162,66 +318,164 @@ async fn push_single_stack(stack_name: String, message: Option<String>) -> Resul
     };
     
     // Confirm the push
-    let should_proceed = if std::io::stdin().is_terminal() {
+    let should_proceed = if is_interactive(quiet) {
         Confirm::new()
             .with_prompt(format!("Push subtree changes to {}?", repo_url))
             .default(true)
             .interact()?
     } else {
-        println!("Auto-proceeding with push in non-interactive mode...");
+        outln!("Auto-proceeding with push in non-interactive mode...");
         true
     };
     
     if !should_proceed {
-        println!("Push cancelled.");
-        return Ok(());
+        outln!("Push cancelled.");
+        return Ok(PushOutcome::Cancelled);
     }
     
     // Stage changes in main repository (subtree changes)
-    println!("  📋 Staging subtree changes...");
-    let add_output = Command::new("git")
+    outln!("  📋 Staging subtree changes...");
+    Process::git()
         .args(["add", &format!("stacks/{}", stack_name)])
-        .output()
+        .run()
         .context("Failed to stage subtree changes")?;
     
-    if !add_output.status.success() {
-        let error = String::from_utf8_lossy(&add_output.stderr);
-        bail!("Failed to stage subtree changes: {}", error);
-    }
-    
     // Commit the changes in main repository
-    println!("  💾 Committing subtree changes...");
-    let commit_output = Command::new("git")
+    outln!("  💾 Committing subtree changes...");
+    Process::git()
         .args(["commit", "-m", &format!("feat({}): {}", stack_name, commit_message)])
-        .output()
+        .run()
         .context("Failed to commit subtree changes")?;
     
-    if !commit_output.status.success() {
-        let error = String::from_utf8_lossy(&commit_output.stderr);
-        bail!("Failed to commit subtree changes: {}", error);
+    if fork {
+        return push_to_fork(stack_name, &repo_url, quiet).await;
     }
     
     // Push subtree changes back to the stack's repository
-    println!("  🚀 Pushing subtree to {}...", repo_url);
-    let push_output = Command::new("git")
+    outln!("  🚀 Pushing subtree to {}...", repo_url);
+    let push_output = Process::git()
+        .envs(auth::git_auth_env(&repo_url))
         .args([
             "subtree", "push",
             "--prefix", &format!("stacks/{}", stack_name),
             &repo_url,
-            "main"
+            &metadata.source_branch
         ])
         .output()
         .context("Failed to push subtree")?;
     
-    if !push_output.status.success() {
-        let error = String::from_utf8_lossy(&push_output.stderr);
-        bail!("Failed to push subtree: {}", error);
+    if !push_output.success() {
+        if is_permission_error(&push_output.stderr) {
+            outln!("  🔒 No write access to {}, pushing to a fork instead...", repo_url);
+            return push_to_fork(stack_name, &repo_url, quiet).await;
+        }
+        
+        bail!("Failed to push subtree: {}", push_output.stderr);
     }
     
-    println!("  ✅ Successfully pushed subtree changes!");
-    println!("  📝 Changes pushed to {} via git subtree", repo_url);
+    outln!("  ✅ Successfully pushed subtree changes!");
+    outln!("  📝 Changes pushed to {} via git subtree", repo_url);
+    
+    Ok(PushOutcome::Pushed)
+}
+
+/// Tag the pushed subtree split in the stack repository and push the tag
+fn tag_release(stack_name: &str, repo_url: &str, version: &str) -> Result<()> {
+    let tag = stack_manifest::release_tag(version);
+    outln!("  🏷️ Tagging release {}...", tag);
     
+    let split_output = Process::git()
+        .args(["subtree", "split", "--prefix", &format!("stacks/{}", stack_name)])
+        .run()
+        .context("Failed to split subtree for tagging")?;
+    
+    let split_commit = split_output.stdout.trim().to_string();
+    
+    Process::git()
+        .envs(auth::git_auth_env(repo_url))
+        .args(["push", repo_url, &format!("{}:refs/tags/{}", split_commit, tag)])
+        .run()
+        .with_context(|| format!("Failed to push release tag {}", tag))?;
+    
+    outln!("  ✅ Released {} {} (pin with ref '{}')", stack_name, version, tag);
     Ok(())
 }
 
+/// Check whether a failed push was rejected for lack of write access
+///
+/// An SSH key git can't authenticate with ("Permission denied (publickey)") is not one: a fork
+/// would be pushed with the same key.
+fn is_permission_error(stderr: &str) -> bool {
+    let denied_to_user = stderr.lines().any(|line| {
+        line.split_once("Permission to ").is_some_and(|(_, rest)| rest.contains(" denied to "))
+    });
+    denied_to_user
+        || stderr.contains("The requested URL returned error: 403")
+        || stderr.contains("Write access to repository not granted")
+}
+
+/// Fork the stack repository and push the subtree split to a branch on the fork
+async fn push_to_fork(stack_name: &str, repo_url: &str, quiet: bool) -> Result<PushOutcome> {
+    let (owner, repo) = parse_github_repo(repo_url)
+        .with_context(|| format!("Cannot fork {}: not a GitHub repository URL", repo_url))?;
+    
+    let token = github_token()
+        .context("Forking requires a GitHub token. Set GITHUB_TOKEN or run 'gh auth login'")?;
+    
+    outln!("  🍴 Forking {}/{}...", owner, repo);
+    let remote_manager = RemoteStackManager::new()?;
+    let fork = remote_manager.fork_repository(&owner, &repo, &token).await?;
+    outln!("  📋 Fork: {}", fork.html_url);
+    
+    let branch = format!("stacks/{}-{}", stack_name, chrono::Local::now().format("%Y%m%d-%H%M%S"));
+    
+    // Over HTTPS with the token that created the fork, so no SSH key is needed
+    outln!("  🚀 Pushing subtree to {} ({})...", fork.clone_url, branch);
+    Process::git()
+        .envs(auth::git_token_env(&token))
+        .args([
+            "subtree", "push",
+            "--prefix", &format!("stacks/{}", stack_name),
+            &fork.clone_url,
+            &branch
+        ])
+        .run()
+        .context("Failed to push subtree to fork")?;
+    
+    let pr_url = format!(
+        "https://github.com/{}/{}/compare/{}...{}:{}?expand=1",
+        owner, repo, fork.default_branch, fork.owner.login, branch
+    );
+    
+    outln!("  ✅ Pushed changes to fork {}/{}", fork.owner.login, fork.name);
+    outln!("  🔗 Open a pull request: {}", pr_url);
+    
+    if is_interactive(quiet) {
+        let open_now = Confirm::new()
+            .with_prompt("Open the pull request page in your browser?")
+            .default(true)
+            .interact()?;
+        
+        if open_now && !terminal::open_browser(&pr_url) {
+            outln!("  ⚠️ Could not launch a browser, open the URL above manually");
+        }
+    }
+    
+    Ok(PushOutcome::Forked { pr_url })
+}
+
 // Metadata loading no longer needed for subtree-based stacks
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_permission_error() {
+        assert!(is_permission_error("remote: Permission to acme/lint-stack.git denied to octocat."));
+        assert!(is_permission_error("fatal: unable to access 'https://github.com/acme/lint-stack.git/': The requested URL returned error: 403"));
+        assert!(is_permission_error("ERROR: Write access to repository not granted."));
+        assert!(!is_permission_error(" ! [rejected]        main -> main (non-fast-forward)\nhint: Updates were rejected (4031a2b..9e8f7c6), 4030 bytes"));
+        assert!(!is_permission_error("git@github.com: Permission denied (publickey).\nfatal: Could not read from remote repository."));
+    }
+}
@@ -0,0 +1,116 @@
+use std::path::{Path, PathBuf};
+use anyhow::Result;
+
+use crate::core::daemon;
+use crate::core::git::Repo;
+use crate::core::session_manifest::SessionManifest;
+use crate::error::ExitStatus;
+
+/// What `stacks query` answers, from local state only
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Query {
+    /// Task the worktree was created for by `stacks worktree`
+    CurrentTask,
+    /// Stacks checked out under stacks/
+    Stacks,
+    /// Stacks with uncommitted changes
+    DirtyStacks,
+    /// Stacks behind upstream, as of the last check of `stacks daemon`
+    OutdatedStacks,
+}
+
+impl Query {
+    pub fn all() -> [Query; 4] {
+        [Query::CurrentTask, Query::Stacks, Query::DirtyStacks, Query::OutdatedStacks]
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Query::CurrentTask => "current-task",
+            Query::Stacks => "stacks",
+            Query::DirtyStacks => "dirty-stacks",
+            Query::OutdatedStacks => "outdated-stacks",
+        }
+    }
+}
+
+impl std::str::FromStr for Query {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::all()
+            .into_iter()
+            .find(|query| query.as_str() == s)
+            .ok_or_else(|| anyhow::anyhow!("Unknown query: {}", s))
+    }
+}
+
+/// Answer a question about the worktree at `path` (the current directory by default) for shell
+/// prompts and tmux status lines
+///
+/// Only reads local files and git state; outdated stacks come from the status `stacks daemon`
+/// keeps, so nothing here waits on the network. Prints one name per line, or their number with
+/// `count`. When there's no answer (outside a repository, not a task worktree, no fresh daemon
+/// status) it prints nothing and exits 1.
+pub async fn run(query: String, count: bool, path: Option<PathBuf>) -> Result<()> {
+    let query: Query = query.parse()?;
+    let dir = match path {
+        Some(path) => path,
+        None => std::env::current_dir()?,
+    };
+
+    let answer = match answer(query, &dir) {
+        Some(answer) => answer,
+        None => return Err(ExitStatus(1).into()),
+    };
+    match count {
+        true => outln!("{}", answer.len()),
+        false => {
+            for line in answer {
+                outln!("{}", line);
+            }
+        }
+    }
+    Ok(())
+}
+
+fn answer(query: Query, dir: &Path) -> Option<Vec<String>> {
+    let repo = Repo::discover(dir).ok()?;
+    let root = repo.workdir()?.to_path_buf();
+
+    match query {
+        Query::CurrentTask => match SessionManifest::load(&root) {
+            Ok(Some(manifest)) => Some(vec![manifest.task]),
+            _ => None,
+        },
+        Query::Stacks => Some(stack_names(&root)),
+        Query::DirtyStacks => Some(dirty_stacks(&repo, &root)),
+        Query::OutdatedStacks => {
+            // The daemon status is looked up from the current directory's repository
+            std::env::set_current_dir(&root).ok()?;
+            let status = daemon::load_status().filter(|status| status.is_fresh(chrono::Utc::now()))?;
+            Some(status.outdated().into_iter().map(|stack| stack.name.clone()).collect())
+        }
+    }
+}
+
+/// Stacks checked out in the worktree at `root`, sorted
+pub(crate) fn stack_names(root: &Path) -> Vec<String> {
+    let mut names: Vec<String> = std::fs::read_dir(root.join("stacks"))
+        .map(|entries| entries
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().is_dir())
+            .map(|entry| entry.file_name().to_string_lossy().to_string())
+            .collect())
+        .unwrap_or_default();
+    names.sort();
+    names
+}
+
+/// Stacks of the worktree at `root` with uncommitted changes
+pub(crate) fn dirty_stacks(repo: &Repo, root: &Path) -> Vec<String> {
+    stack_names(root)
+        .into_iter()
+        .filter(|name| repo.has_changes(&format!("stacks/{}", name)).unwrap_or(false))
+        .collect()
+}
@@ -0,0 +1,51 @@
+use anyhow::{Result, Context};
+
+use crate::config::load_config;
+use crate::core::cache;
+use crate::core::mirror::{self, MirrorIndex, MirrorLocation, MirroredStack, INDEX_FILE};
+use crate::core::remote_stack_manager::RemoteStackManager;
+use crate::utils::dependency_check::{require, Dependency};
+
+/// Snapshot the configured registry's index and every stack into a directory or artifact store
+///
+/// Stacks are stored as git bundles; the index is written last, so a failed run never leaves
+/// a mirror pointing at missing bundles.
+pub async fn mirror(dest: String) -> Result<()> {
+    require(Dependency::Git)?;
+    let location = MirrorLocation::parse(&dest);
+    let config = load_config()?;
+    let manager = RemoteStackManager::for_registry(config.registry.as_deref())?.without_mirror();
+
+    outln!("🔍 Discovering stacks in {}...", manager.registry_spec());
+    let stacks = manager.refresh_remote_stacks().await?;
+    let staging = cache::cache_root()?.join("mirror").join("staging");
+    std::fs::create_dir_all(&staging).context("Failed to create the mirror staging directory")?;
+
+    let mut mirrored = Vec::new();
+    for stack in &stacks {
+        outln!("📦 Mirroring {}...", stack.name);
+        let bundle_file = staging.join(format!("{}.bundle", stack.name));
+        let (branch, commit) = match mirror::create_bundle(&manager.stack_repo_url(&stack.name), None, &bundle_file) {
+            Ok(bundled) => bundled,
+            Err(error) => {
+                outln!("  ❌ Skipping {}: {:#}", stack.name, error);
+                continue;
+            }
+        };
+        let bundle = MirroredStack::bundle_path(&stack.name);
+        let content = std::fs::read(&bundle_file).with_context(|| format!("Failed to read {}", bundle_file.display()))?;
+        location.write(&bundle, content).await?;
+        std::fs::remove_file(&bundle_file).ok();
+        outln!("  ✅ {} at {} ({})", stack.name, &commit[..commit.len().min(7)], branch);
+        mirrored.push(MirroredStack { name: stack.name.clone(), description: stack.description.clone(), branch, commit, bundle });
+    }
+
+    let index = MirrorIndex { registry: manager.registry_spec(), created_at: chrono::Utc::now(), stacks: mirrored };
+    location.write(INDEX_FILE, serde_json::to_vec_pretty(&index)?).await?;
+    outln!("\n🪞 Mirrored {} of {} stack(s) to {}", index.stacks.len(), stacks.len(), location.describe());
+    if index.stacks.len() < stacks.len() {
+        outln!("⚠️ Some stacks could not be mirrored; run the command again to retry them");
+    }
+    outln!("💡 Point other machines at it with 'stacks config set registry-mirror {}'", dest);
+    Ok(())
+}
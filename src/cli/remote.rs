@@ -0,0 +1,103 @@
+use std::process::Command;
+use anyhow::{Result, Context};
+use dialoguer::{Confirm, Input};
+
+use crate::cli::worktree::{get_repo_name, CLAUDE_COMMAND};
+use crate::error::StacksError;
+use crate::utils::dependency_check::{require, Dependency};
+use crate::utils::process::{shell_quote, Process};
+use crate::utils::task_name;
+use crate::utils::terminal::{is_interactive, require_interactive};
+
+/// Create a feature worktree and a tmux session running claude on another machine
+///
+/// Every git and tmux command is the local one run over ssh, so the remote host only needs a
+/// clone of the repository (at `remote_dir`, `~/<repo>` by default), git, tmux and claude.
+pub async fn create_worktree(host: String, remote_dir: Option<String>, task: Option<String>) -> Result<()> {
+    require(Dependency::Ssh)?;
+    if task.is_none() {
+        require_interactive("stacks worktree --remote", "Pass the task with --task <name>")?;
+    }
+
+    let repo_name = get_repo_name()?;
+    let repo_dir = remote_dir.unwrap_or_else(|| format!("~/{}", repo_name));
+    let is_repository = Process::git()
+        .args(["rev-parse", "--is-inside-work-tree"])
+        .current_dir(&repo_dir)
+        .read_only()
+        .over_ssh(&host)
+        .succeeds();
+    if !is_repository {
+        return Err(StacksError::validation(format!("{} on {} is not a git repository", repo_dir, host))
+            .with_hint(format!("Clone the repository there, or point at the clone with --remote-dir (e.g. --remote-dir ~/src/{})", repo_name))
+            .into());
+    }
+
+    let raw_task_name: String = match task {
+        Some(task) => task,
+        None => Input::new()
+            .with_prompt("Task name")
+            .interact_text()?,
+    };
+    let task = task_name::sanitize_reporting(&raw_task_name)?;
+    let branch = format!("feature-{}", task);
+    let worktree = format!("{}-{}", repo_dir.trim_end_matches('/'), task);
+    let session = format!("{}-{}", repo_name, task);
+
+    outln!("🏗️ Creating worktree {} on {}...", worktree, host);
+    let branch_exists = Process::git()
+        .args(["rev-parse", "--verify", "--quiet", &format!("refs/heads/{}", branch)])
+        .current_dir(&repo_dir)
+        .read_only()
+        .over_ssh(&host)
+        .succeeds();
+    let add = match branch_exists {
+        true => Process::git().args(["worktree", "add", &worktree, &branch]),
+        false => Process::git().args(["worktree", "add", "-b", &branch, &worktree]),
+    };
+    add.current_dir(&repo_dir)
+        .over_ssh(&host)
+        .run()
+        .with_context(|| format!("Failed to create worktree {} on {}", worktree, host))?;
+
+    outln!("🖥️ Starting tmux session {} on {}...", session, host);
+    let command = crate::config::load_config()?.launch.command_for_pane(CLAUDE_COMMAND, 0)?;
+    Process::tmux()
+        .args(["new-session", "-d", "-s", &session])
+        .current_dir(&worktree)
+        .over_ssh(&host)
+        .run()
+        .context("Failed to create the remote tmux session")?;
+    Process::tmux()
+        .args(["split-window", "-h", "-t", &format!("{}:0", session), &command])
+        .current_dir(&worktree)
+        .over_ssh(&host)
+        .run()
+        .context("Failed to start claude in the remote tmux session")?;
+    Process::tmux()
+        .args(["select-pane", "-t", &format!("{}:0.0", session)])
+        .over_ssh(&host)
+        .run()
+        .context("Failed to select the remote tmux pane")?;
+
+    let attach = format!("ssh -t {} tmux attach -t {}", host, shell_quote(&session));
+    outln!("\n🎉 Remote worktree ready: {} ({}) on {}", worktree, branch, host);
+    outln!("💡 Attach with: {}", attach);
+
+    let attach_now = is_interactive()
+        && Confirm::new()
+            .with_prompt("Attach now?")
+            .default(true)
+            .interact()?;
+    if attach_now {
+        // Attaching takes over the terminal, so it can't go through the captured-output runner
+        let status = Command::new("ssh")
+            .args(["-t", &host, "tmux", "attach", "-t", &session])
+            .status()
+            .context("Failed to run ssh")?;
+        if !status.success() {
+            return Err(StacksError::tmux(format!("Failed to attach to {} on {}", session, host)).into());
+        }
+    }
+    Ok(())
+}
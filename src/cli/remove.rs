@@ -0,0 +1,79 @@
+use anyhow::{Result, Context, bail};
+use dialoguer::Confirm;
+
+use crate::core::lockfile::forget_stack;
+use crate::core::settings_merger::SettingsMerger;
+use crate::core::symlink_manager::SymlinkManager;
+use crate::utils::claude_md_updater::ClaudeMdUpdater;
+use crate::utils::process::Process;
+use crate::utils::terminal::is_interactive;
+
+/// Remove a checked-out stack: its symlinks, permission grants, CLAUDE.md import, subtree and lock entry
+pub async fn run(stack_name: String, yes: bool) -> Result<()> {
+    let stack_path = std::env::current_dir()?.join("stacks").join(&stack_name);
+    if !stack_path.is_dir() {
+        bail!("Stack '{}' is not checked out in this project", stack_name);
+    }
+
+    if !yes {
+        if !is_interactive() {
+            bail!("Refusing to remove stack '{}' without confirmation; pass --yes", stack_name);
+        }
+
+        let confirmed = Confirm::new()
+            .with_prompt(format!("Remove stack '{}'? Unpushed changes in stacks/{} will be lost", stack_name, stack_name))
+            .default(false)
+            .interact()?;
+
+        if !confirmed {
+            outln!("Remove cancelled.");
+            return Ok(());
+        }
+    }
+
+    outln!("🗑️ Removing stack: {}", stack_name);
+
+    SymlinkManager::new().remove_stack_symlinks(&stack_name).await
+        .with_context(|| format!("Failed to remove symlinks for stack {}", stack_name))?;
+
+    let retracted = SettingsMerger::new().retract_stack_grants(&stack_name).await
+        .with_context(|| format!("Failed to retract permissions for stack {}", stack_name))?;
+    if retracted > 0 {
+        outln!("  🛡️ Retracted {} permission rule(s) granted by {}", retracted, stack_name);
+    } else if !crate::config::load_config()?.permissions.scope_stack_grants {
+        outln!("  ℹ️ Permissions merged from this stack are not tracked; enable 'scope-stack-permissions' to retract them on removal");
+    }
+
+    ClaudeMdUpdater::for_checkout(&std::env::current_dir()?).remove_stack_import(&stack_name).await
+        .with_context(|| format!("Failed to update CLAUDE.md for stack {}", stack_name))?;
+
+    remove_subtree(&stack_name)?;
+    forget_stack(&stack_name)?;
+
+    outln!("✅ Removed stack: {}", stack_name);
+    Ok(())
+}
+
+/// Delete the stack's subtree from the repository and commit the removal
+pub(crate) fn remove_subtree(stack_name: &str) -> Result<()> {
+    let prefix = format!("stacks/{}", stack_name);
+
+    Process::git()
+        .args(["rm", "-r", "-q", "--", &prefix])
+        .run()
+        .with_context(|| format!("Failed to remove {}", prefix))?;
+
+    Process::git()
+        .args(["commit", "-m", &format!("chore(stacks): remove {}", stack_name), "--", &prefix])
+        .run()
+        .with_context(|| format!("Failed to commit removal of {}", prefix))?;
+
+    // git rm leaves untracked files behind
+    let stack_path = std::path::PathBuf::from(&prefix);
+    if stack_path.exists() {
+        std::fs::remove_dir_all(&stack_path)
+            .with_context(|| format!("Failed to remove {}", stack_path.display()))?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,72 @@
+use anyhow::{Result, Context, bail};
+use walkdir::WalkDir;
+
+use crate::core::policy;
+use crate::core::settings_merger::SettingsMerger;
+use crate::core::stack_manager::Stack;
+use crate::core::symlink_manager::SymlinkManager;
+use crate::utils::claude_md_updater::ClaudeMdUpdater;
+
+/// Restore symlinks, settings and CLAUDE.md imports for checked-out stacks
+pub async fn run(stack_name: Option<String>) -> Result<()> {
+    let stacks_dir = std::env::current_dir()?.join("stacks");
+    
+    if !stacks_dir.exists() {
+        outln!("No stacks directory found. Run 'stacks checkout <stack-name>' to check out a stack.");
+        return Ok(());
+    }
+    
+    let stack_names = match stack_name {
+        Some(name) => {
+            if !stacks_dir.join(&name).is_dir() {
+                bail!("Stack '{}' not found. Run 'stacks checkout {}' first.", name, name);
+            }
+            vec![name]
+        }
+        None => WalkDir::new(&stacks_dir)
+            .min_depth(1)
+            .max_depth(1)
+            .sort_by_file_name()
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_dir())
+            .map(|e| e.file_name().to_string_lossy().to_string())
+            .collect(),
+    };
+    
+    for name in &stack_names {
+        outln!("\n🔧 Repairing stack: {}", name);
+        refresh_stack_integration(name).await?;
+    }
+    
+    outln!("\n✅ Repaired {} stack(s)", stack_names.len());
+    Ok(())
+}
+
+/// Re-link agents/commands, re-merge settings and ensure the CLAUDE.md import after an update
+pub async fn refresh_stack_integration(stack_name: &str) -> Result<()> {
+    outln!("  🔗 Refreshing symlinks, settings and CLAUDE.md...");
+    
+    let stack_path = std::env::current_dir()?.join("stacks").join(stack_name);
+    let stack = Stack::new(stack_name.to_string(), stack_path);
+    policy::enforce_stack(&stack).await?;
+    
+    let symlink_manager = SymlinkManager::new();
+    symlink_manager.prune_stale_symlinks(stack_name).await
+        .with_context(|| format!("Failed to prune stale symlinks for stack {}", stack_name))?;
+    symlink_manager.create_symlinks_for_stack(&stack).await
+        .with_context(|| format!("Failed to create symlinks for stack {}", stack_name))?;
+    
+    let config = crate::config::load_config()?;
+    let settings_merger = SettingsMerger::new()
+        .with_scoped_grants(config.permissions.scope_stack_grants)
+        .with_auto_push(config.auto_push);
+    settings_merger.merge_stack_settings(&stack).await
+        .with_context(|| format!("Failed to merge settings for stack {}", stack_name))?;
+    
+    let md_updater = ClaudeMdUpdater::for_project(&std::env::current_dir()?)?;
+    md_updater.add_stack_import(stack_name).await
+        .with_context(|| format!("Failed to update CLAUDE.md for stack {}", stack_name))?;
+    
+    Ok(())
+}
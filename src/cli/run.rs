@@ -0,0 +1,199 @@
+use std::path::{Path, PathBuf};
+use std::time::Instant;
+use anyhow::{Result, Context};
+use futures::stream::{self, StreamExt};
+
+use crate::cli::worktree::{describe_worktree_scope, get_repo_name, setup_feature_permissions, BranchStrategy, WorktreeConfig, CLAUDE_COMMAND};
+use crate::config::load_config;
+use crate::core::prompts;
+use crate::core::transcripts;
+use crate::core::permission_generator::PermissionPreset;
+use crate::error::{not_a_repository, StacksError};
+use crate::utils::dependency_check::{require_all, Dependency};
+use crate::utils::process::Process;
+use crate::utils::task_name;
+
+/// A task whose worktree is ready for a headless claude run
+struct Job {
+    task: String,
+    worktree: PathBuf,
+    log_file: PathBuf,
+}
+
+/// How a headless run ended
+struct Outcome {
+    task: String,
+    code: Option<i32>,
+    seconds: u64,
+    log_file: PathBuf,
+}
+
+/// Create a worktree per task and run `claude -p` in each, at most `jobs` at a time
+///
+/// Worktrees are prepared one after another (branch, permissions, stack symlinks); only the
+/// claude runs overlap. Each run's output goes to `.stacks/logs/<task>/run.log`.
+pub async fn run(
+    tasks: Vec<String>,
+    prompt_file: PathBuf,
+    jobs: usize,
+    base: String,
+    permissions: Option<String>,
+) -> Result<()> {
+    require_all(&[Dependency::Git, Dependency::Claude])?;
+    if !Process::git().args(["rev-parse", "--git-dir"]).read_only().succeeds() {
+        return Err(not_a_repository().into());
+    }
+    let permission_preset = permissions.as_deref().map(str::parse::<PermissionPreset>).transpose()?;
+    let tasks = tasks.iter().map(|task| task_name::sanitize_reporting(task)).collect::<Result<Vec<_>>>()?;
+
+    let prompt = std::fs::read_to_string(&prompt_file)
+        .with_context(|| format!("Failed to read prompt file {}", prompt_file.display()))?;
+    if prompt.trim().is_empty() {
+        return Err(StacksError::validation(format!("Prompt file {} is empty", prompt_file.display())).into());
+    }
+
+    let project_root = std::env::current_dir()?;
+    let repo_name = get_repo_name()?;
+    let app_config = load_config()?;
+
+    let mut queue = Vec::new();
+    for task in &tasks {
+        outln!("🏗️ Preparing {}...", task);
+        let config = WorktreeConfig {
+            task_name: task.clone(),
+            branch_strategy: BranchStrategy::NewFromCurrent,
+            location: PathBuf::from(format!("../{}-{}", repo_name, task)),
+            tmux_session: String::new(),
+            tmux_strategy: app_config.tmux_strategy.clone(),
+            navigation_command: None,
+            permission_preset,
+            sandbox: false,
+            container: false,
+            record: false,
+            claude_command: CLAUDE_COMMAND.to_string(),
+            pane_commands: Vec::new(),
+        };
+        match prepare(&config, &base).await {
+            Ok(()) => queue.push(Job {
+                task: task.clone(),
+                worktree: config.location.clone(),
+                log_file: transcripts::create_task_dir(&project_root, task)?.join("run.log"),
+            }),
+            Err(error) => outln!("  ❌ Skipping {}: {:#}", task, error),
+        }
+    }
+    if queue.is_empty() {
+        anyhow::bail!("No task could be prepared");
+    }
+
+    let jobs = jobs.max(1);
+    outln!("\n🚀 Running {} task(s), {} at a time...", queue.len(), jobs);
+    let prompt = prompt.as_str();
+    let mut outcomes: Vec<Outcome> = stream::iter(queue)
+        .map(|job| async move {
+            let prompt = prompts::render(prompt, &[("task", &job.task)]);
+            let task = job.task.clone();
+            let log_file = job.log_file.clone();
+            match tokio::task::spawn_blocking(move || execute(&job, &prompt)).await {
+                Ok(Ok(outcome)) => outcome,
+                Ok(Err(error)) => {
+                    outln!("  ❌ {}: {:#}", task, error);
+                    Outcome { task, code: None, seconds: 0, log_file }
+                }
+                Err(error) => {
+                    outln!("  ❌ {}: {}", task, error);
+                    Outcome { task, code: None, seconds: 0, log_file }
+                }
+            }
+        })
+        .buffer_unordered(jobs)
+        .collect()
+        .await;
+
+    outcomes.sort_by(|a, b| a.task.cmp(&b.task));
+    outln!("\n📋 Results:");
+    for outcome in &outcomes {
+        let status = match outcome.code {
+            Some(0) => "✅ done".to_string(),
+            Some(code) => format!("❌ exited with {}", code),
+            None => "❌ did not finish".to_string(),
+        };
+        outln!("  {:<24} {:<20} {:>5}s  {}", outcome.task, status, outcome.seconds, outcome.log_file.display());
+    }
+
+    let failed = outcomes.iter().filter(|outcome| outcome.code != Some(0)).count();
+    if failed > 0 {
+        anyhow::bail!("{} of {} task(s) failed", failed, outcomes.len());
+    }
+    outln!("\n💡 Review the results with 'stacks logs <task>' and the worktrees' git diff");
+    Ok(())
+}
+
+/// Create the task's worktree (or reuse it), apply permissions and link its stacks
+async fn prepare(config: &WorktreeConfig, base: &str) -> Result<()> {
+    let branch = format!("feature-{}", config.task_name);
+    let location = config.location.to_string_lossy().to_string();
+
+    if config.location.exists() {
+        outln!("  ♻️ Reusing worktree at {}", location);
+    } else {
+        let branch_exists = Process::git()
+            .args(["rev-parse", "--verify", "--quiet", &format!("refs/heads/{}", branch)])
+            .read_only()
+            .succeeds();
+        let add = if branch_exists {
+            Process::git().args(["worktree", "add", &location, &branch])
+        } else {
+            Process::git().args(["worktree", "add", "-b", &branch, &location, base])
+        };
+        add.run().context("Failed to create git worktree")?;
+        outln!("  🌱 Created worktree at {} on {}", location, branch);
+    }
+
+    setup_feature_permissions(config).await?;
+    describe_worktree_scope(&config.task_name, &branch, &config.location);
+
+    // Symlinks, settings and CLAUDE.md imports are resolved relative to the current directory,
+    // so let the worktree's own `stacks repair` do it
+    if config.location.join("stacks").is_dir() {
+        let executable = std::env::current_exe().context("Failed to locate the stacks executable")?;
+        Process::new(executable)
+            .arg("repair")
+            .current_dir(&config.location)
+            .run()
+            .context("Failed to link stacks in the worktree")?;
+        outln!("  🔗 Linked stacks");
+    }
+    Ok(())
+}
+
+fn execute(job: &Job, prompt: &str) -> Result<Outcome> {
+    outln!("  ▶️ {} started", job.task);
+    let started = Instant::now();
+    let output = Process::claude()
+        .args(["-p", prompt, "--permission-mode", "acceptEdits"])
+        .current_dir(&job.worktree)
+        .output()?;
+    let seconds = started.elapsed().as_secs();
+
+    write_log(&job.log_file, &output.stdout, &output.stderr, output.code)?;
+    match output.code {
+        Some(0) => outln!("  ✅ {} finished in {}s", job.task, seconds),
+        _ => outln!("  ❌ {} failed after {}s", job.task, seconds),
+    }
+    Ok(Outcome { task: job.task.clone(), code: output.code, seconds, log_file: job.log_file.clone() })
+}
+
+fn write_log(path: &Path, stdout: &str, stderr: &str, code: Option<i32>) -> Result<()> {
+    let mut content = stdout.to_string();
+    if !stderr.is_empty() {
+        content.push_str("\n--- stderr ---\n");
+        content.push_str(stderr);
+    }
+    let status = match code {
+        Some(code) => code.to_string(),
+        None => "signal".to_string(),
+    };
+    content.push_str(&format!("\n--- claude exited with {} ---\n", status));
+    std::fs::write(path, content).with_context(|| format!("Failed to write {}", path.display()))
+}
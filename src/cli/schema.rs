@@ -0,0 +1,18 @@
+use std::collections::BTreeMap;
+use anyhow::Result;
+
+use crate::core::schema::{self, Output};
+
+/// Print the JSON Schema of one machine-readable output, or of all of them keyed by name
+pub fn run(name: Option<String>) -> Result<()> {
+    match name {
+        Some(name) => schema::print(&name.parse::<Output>()?.schema()),
+        None => {
+            let schemas: BTreeMap<&str, _> = Output::all()
+                .into_iter()
+                .map(|output| (output.as_str(), output.schema()))
+                .collect();
+            schema::print(&schemas)
+        }
+    }
+}
@@ -0,0 +1,172 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use tokio::io::{AsyncBufReadExt, BufReader};
+
+use crate::cli::{checkout, status};
+use crate::core::events;
+use crate::core::rpc::{self, Request, RpcError, RpcReporter, INVALID_PARAMS, METHOD_NOT_FOUND};
+use crate::core::schema::SCHEMA_VERSION;
+use crate::core::sessions::{live_targets, SessionRegistry};
+use crate::core::worktrees;
+use crate::utils::output::{self, Stream};
+
+/// Methods a client can call, as `initialize` lists them
+const METHODS: [&str; 5] = ["initialize", "stacks/list", "stacks/checkout", "stacks/status", "stacks/attachInfo"];
+
+/// A registry stack and whether this project has it
+#[derive(Debug, Serialize)]
+struct ListedStack {
+    name: String,
+    description: Option<String>,
+    installed: bool,
+}
+
+#[derive(Debug, Deserialize)]
+struct InitializeParams {
+    /// Project to work in; the directory the server was started in when omitted
+    root: Option<PathBuf>,
+}
+
+#[derive(Debug, Deserialize)]
+struct CheckoutParams {
+    #[serde(default)]
+    stacks: Vec<String>,
+    #[serde(default)]
+    groups: Vec<String>,
+}
+
+/// A tmux session of one of the project's tasks, and how to attach to it from a terminal
+#[derive(Debug, Serialize)]
+struct SessionInfo {
+    task: String,
+    target: String,
+    worktree: PathBuf,
+    created_at: DateTime<Utc>,
+    live: bool,
+    attach_command: Vec<String>,
+}
+
+/// Serve the stacks API over JSON-RPC 2.0 on stdin/stdout, one message per line, for editor extensions
+///
+/// Events of the running operation arrive as `stacks/event` notifications and every other line of
+/// output as `stacks/output`, so stdout only ever carries protocol messages. Requests are handled
+/// one at a time; `exit` (or the end of input) stops the server.
+pub async fn run() -> Result<()> {
+    events::set_reporter(Arc::new(RpcReporter));
+    output::set_sink(|stream, text| {
+        let stream = match stream {
+            Stream::Stdout => "stdout",
+            Stream::Stderr => "stderr",
+        };
+        rpc::send(&rpc::notification("stacks/output", json!({ "stream": stream, "text": text })));
+    });
+
+    let mut lines = BufReader::new(tokio::io::stdin()).lines();
+    while let Some(line) = lines.next_line().await? {
+        if line.trim().is_empty() {
+            continue;
+        }
+        let request = match rpc::parse_request(&line) {
+            Ok(request) => request,
+            Err(response) => {
+                rpc::send(&response);
+                continue;
+            }
+        };
+        if request.method == "exit" {
+            break;
+        }
+
+        let result = handle(&request).await;
+        // Notifications get no response, even when they fail
+        if let Some(id) = request.id {
+            rpc::send(&match result {
+                Ok(value) => rpc::result_response(id, value),
+                Err(error) => rpc::error_response(id, error),
+            });
+        }
+    }
+    Ok(())
+}
+
+async fn handle(request: &Request) -> Result<Value, RpcError> {
+    let result = match request.method.as_str() {
+        "initialize" => initialize(request.params()?),
+        "stacks/list" => list_stacks().await,
+        "stacks/checkout" => {
+            let params: CheckoutParams = request.params()?;
+            if params.stacks.is_empty() && params.groups.is_empty() {
+                return Err(RpcError::new(INVALID_PARAMS, "stacks/checkout needs stacks or groups"));
+            }
+            checkout_stacks(params).await
+        }
+        "stacks/status" => project_status().await,
+        "stacks/attachInfo" => attach_info(),
+        method => return Err(RpcError::new(METHOD_NOT_FOUND, format!("Unknown method: {}", method))),
+    };
+    result.map_err(|error| RpcError::from_error(&error))
+}
+
+fn initialize(params: InitializeParams) -> Result<Value> {
+    if let Some(root) = &params.root {
+        std::env::set_current_dir(root)?;
+    }
+    Ok(json!({
+        "name": "stacks",
+        "version": env!("CARGO_PKG_VERSION"),
+        "schema_version": SCHEMA_VERSION,
+        "root": std::env::current_dir()?,
+        "methods": METHODS,
+    }))
+}
+
+async fn list_stacks() -> Result<Value> {
+    let stacks: Vec<ListedStack> = checkout::registry_manager()?
+        .discover_remote_stacks()
+        .await?
+        .into_iter()
+        .map(|stack| ListedStack { installed: stack.path.exists(), name: stack.name, description: stack.description })
+        .collect();
+    Ok(serde_json::to_value(stacks)?)
+}
+
+/// Check stacks out the way `stacks checkout` does without a terminal, then report the status
+///
+/// Progress arrives as notifications while it runs.
+async fn checkout_stacks(params: CheckoutParams) -> Result<Value> {
+    checkout::run_with_stack(params.stacks, params.groups, false).await?;
+    project_status().await
+}
+
+/// The `StatusOutput` of `stacks status --json`
+async fn project_status() -> Result<Value> {
+    Ok(serde_json::to_value(status::collect_status().await?)?)
+}
+
+/// The project's task sessions, live ones first
+fn attach_info() -> Result<Value> {
+    let project_worktrees: Vec<PathBuf> = worktrees::list_worktrees()
+        .map(|worktrees| worktrees.into_iter().map(|worktree| worktree.path).collect())
+        .unwrap_or_default();
+    let live = live_targets();
+
+    let mut sessions: Vec<SessionInfo> = SessionRegistry::load()?
+        .sessions
+        .into_iter()
+        .filter(|session| project_worktrees.is_empty() || project_worktrees.contains(&session.worktree))
+        .map(|session| SessionInfo {
+            live: live.contains(&session.target),
+            attach_command: vec!["tmux".to_string(), "attach-session".to_string(), "-t".to_string(), session.target.clone()],
+            task: session.task,
+            target: session.target,
+            worktree: session.worktree,
+            created_at: session.created_at,
+        })
+        .collect();
+    sessions.sort_by(|a, b| b.live.cmp(&a.live).then(b.created_at.cmp(&a.created_at)));
+    Ok(serde_json::to_value(sessions)?)
+}
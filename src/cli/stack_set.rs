@@ -0,0 +1,145 @@
+use std::path::PathBuf;
+use anyhow::{Result, Context};
+
+use crate::cli::checkout::integrate_stack;
+use crate::core::lockfile::{StacksLock, LOCKFILE_NAME};
+use crate::core::mcp_validator::McpValidator;
+use crate::core::policy;
+use crate::core::remote_stack_manager::{parse_github_repo, RemoteStackManager};
+use crate::core::signatures;
+use crate::core::stack_manager::Stack;
+use crate::core::stack_set::StackSet;
+use crate::core::upstream::installed_commit;
+use crate::error::{not_a_repository, StacksError};
+use crate::utils::dependency_check::{require, Dependency};
+use crate::utils::process::Process;
+
+/// Write the project's stacks, MCP servers and settings to a manifest, or print it
+pub async fn export(output: Option<PathBuf>) -> Result<()> {
+    let root = std::env::current_dir()?;
+    let lock = StacksLock::load()?;
+    if lock.stacks.is_empty() {
+        return Err(StacksError::validation(format!("No stacks recorded in {}", LOCKFILE_NAME))
+            .with_hint("Check out stacks with 'stacks checkout' first")
+            .into());
+    }
+    let mcp_servers = match McpValidator::new().required_servers().await {
+        Ok(servers) => servers,
+        Err(error) => {
+            tracing::debug!("failed to read MCP servers from settings: {:#}", error);
+            Vec::new()
+        }
+    };
+
+    let set = StackSet::collect(&root, &lock, &mcp_servers)?;
+    let content = set.to_toml()?;
+    match output {
+        Some(path) => {
+            std::fs::write(&path, content).with_context(|| format!("Failed to write {}", path.display()))?;
+            eoutln!(
+                "📤 Exported {} stack(s) and {} MCP server(s) to {}",
+                set.stacks.len(),
+                set.mcp_servers.len(),
+                path.display()
+            );
+            eoutln!("💡 Teammates reproduce it with 'stacks apply {}'", path.display());
+        }
+        None => print!("{}", content),
+    }
+    Ok(())
+}
+
+/// Check out the stacks of an exported manifest, merge its settings and add its MCP servers
+pub async fn apply(file: PathBuf) -> Result<()> {
+    require(Dependency::Git)?;
+    if !Process::git().args(["rev-parse", "--git-dir"]).read_only().succeeds() {
+        return Err(not_a_repository().into());
+    }
+    let set = StackSet::load(&file)?;
+    let root = std::env::current_dir()?;
+
+    outln!("📥 Applying {} ({} stack(s))", file.display(), set.stacks.len());
+    let manager = RemoteStackManager::new().context("Failed to initialize remote stack manager")?;
+    let signature_config = crate::config::load_config()?.signatures;
+    for entry in &set.stacks {
+        let stack_path = root.join("stacks").join(&entry.name);
+        if stack_path.exists() {
+            outln!("  ✅ {} is already installed", entry.name);
+        } else {
+            if let Some((owner, repo)) = parse_github_repo(&entry.source) {
+                policy::enforce_registry(&owner, &repo).await?;
+            }
+            let verified = signatures::check(&entry.name, &entry.source, Some(&entry.branch), &signature_config)?;
+            manager.add_stack_from_repo(&entry.name, &entry.source, &entry.branch, entry.path.as_deref(), verified.as_deref()).await?;
+            integrate_stack(&Stack::new(entry.name.clone(), stack_path)).await?;
+        }
+
+        // Subtrees follow the branch, so the installed commit can be newer than the exported one
+        let installed = installed_commit(&entry.name)?;
+        if let (Some(exported), Some(installed)) = (&entry.commit, &installed) {
+            if exported != installed {
+                outln!(
+                    "  ⚠️ {} is at {}, the manifest was exported at {}",
+                    entry.name,
+                    &installed[..installed.len().min(7)],
+                    &exported[..exported.len().min(7)]
+                );
+            }
+        }
+    }
+
+    if let Some(settings) = &set.settings {
+        merge_settings(&root.join(".claude").join("settings.json"), settings)?;
+        outln!("⚙️ Merged the manifest's settings into .claude/settings.json");
+    }
+
+    if !set.mcp_servers.is_empty() {
+        add_mcp_servers(&set).await?;
+    }
+
+    outln!("🎉 Applied {}", file.display());
+    Ok(())
+}
+
+fn merge_settings(path: &std::path::Path, settings: &toml::Table) -> Result<()> {
+    let mut merged = match path.exists() {
+        true => {
+            let content = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+            serde_json::from_str(&content).with_context(|| format!("Failed to parse {}", path.display()))?
+        }
+        false => serde_json::Value::Object(serde_json::Map::new()),
+    };
+    McpValidator::merge_json_static(&mut merged, serde_json::to_value(settings)?);
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent).with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    std::fs::write(path, serde_json::to_string_pretty(&merged)?)
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+async fn add_mcp_servers(set: &StackSet) -> Result<()> {
+    require(Dependency::Claude)?;
+    let installed = McpValidator::new().list_installed_servers().await?;
+
+    outln!("🔌 MCP servers:");
+    for server in &set.mcp_servers {
+        if installed.iter().any(|installed| installed.name == server.name) {
+            outln!("  ✅ {} is already configured", server.name);
+            continue;
+        }
+        let args = match server.add_args() {
+            Some(args) => args,
+            None => {
+                outln!("  ⚠️ {} needs manual configuration ({} transport without a command or URL)", server.name, server.transport);
+                continue;
+            }
+        };
+        let output = Process::claude().args(&args).output()?;
+        match output.success() {
+            true => outln!("  ➕ Added {}", server.name),
+            false => outln!("  ❌ Could not add {}: {}", server.name, output.stderr.trim()),
+        }
+    }
+    Ok(())
+}
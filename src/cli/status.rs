@@ -1,207 +1,589 @@
+use std::collections::{BTreeMap, HashMap};
 use std::path::{Path, PathBuf};
-use std::process::Command;
+use std::time::{Duration, Instant};
 use anyhow::{Result, Context};
 use walkdir::WalkDir;
 
-use crate::core::remote_stack_manager::StackMetadata;
+use crate::cli::sync;
+use crate::core::daemon;
+use crate::core::git::{self, Repo};
+use crate::core::lockfile::resolve_stack_metadata_in;
+use crate::core::mcp_validator::McpValidator;
+use crate::core::remote_stack_manager::{RemoteStackManager, StackMetadata};
+use crate::core::schema::{self, ChangeState, StackStatus, StatusOutput, WorktreeStatus, SCHEMA_VERSION};
+use crate::core::stack_manager::Stack;
+use crate::core::symlink_manager::SymlinkManager;
+use crate::core::upstream::{self, UpstreamStatus};
+use crate::core::worktrees::{self, WorktreeInfo};
+use crate::utils::claude_md_updater::ClaudeMdUpdater;
+use crate::utils::process::Process;
+use crate::error::ExitStatus;
 
-pub async fn run() -> Result<()> {
-    println!("📊 Stack Status Report");
-    println!("═══════════════════════");
+/// How often watch mode forgets upstream results, so new upstream commits show up
+const UPSTREAM_REFRESH: Duration = Duration::from_secs(300);
+
+/// Exit codes for `status --porcelain` and `--json`
+const EXIT_CLEAN: i32 = 0;
+const EXIT_CHANGES: i32 = 1;
+const EXIT_ERROR: i32 = 2;
+
+pub async fn run(watch: bool, interval: u64, porcelain: bool, json: bool) -> Result<()> {
+    if porcelain || json {
+        let code = match machine_report(json).await {
+            Ok(code) => code,
+            Err(e) => {
+                eoutln!("error: {:#}", e);
+                EXIT_ERROR
+            }
+        };
+        return match code {
+            EXIT_CLEAN => Ok(()),
+            code => Err(ExitStatus(code).into()),
+        };
+    }
     
-    let stacks_dir = std::env::current_dir()?.join("stacks");
+    let mut upstream_cache = UpstreamCache::new()?;
     
-    if !stacks_dir.exists() {
-        println!("No stacks directory found. Run 'stacks checkout <stack-name>' to check out a stack.");
+    if !watch {
+        report(&mut upstream_cache).await?;
         return Ok(());
     }
     
-    let mut found_stacks = false;
+    let interval = Duration::from_secs(interval.max(1));
+    let mut upstream_checked = Instant::now();
+    
+    loop {
+        if upstream_checked.elapsed() >= UPSTREAM_REFRESH {
+            upstream_cache.results.clear();
+            upstream_checked = Instant::now();
+        }
+        
+        // Clear the screen and move the cursor home before redrawing
+        print!("\x1B[2J\x1B[H");
+        if let Err(e) = report(&mut upstream_cache).await {
+            outln!("❌ Failed to build status report: {}", e);
+        }
+        outln!(
+            "\n👀 Updated {} - refreshing every {}s or on changes (Ctrl-C to exit)",
+            chrono::Local::now().format("%H:%M:%S"),
+            interval.as_secs()
+        );
+        
+        wait_for_changes(interval).await;
+    }
+}
+
+/// Sleep until the interval elapses or the git state of any worktree changes
+async fn wait_for_changes(interval: Duration) {
+    let started = Instant::now();
+    let initial = worktree_fingerprint();
+    
+    while started.elapsed() < interval {
+        tokio::time::sleep(Duration::from_secs(1)).await;
+        if worktree_fingerprint() != initial {
+            return;
+        }
+    }
+}
+
+/// Cheap snapshot of HEAD and working tree state across worktrees, used to detect edits
+fn worktree_fingerprint() -> String {
+    let roots: Vec<PathBuf> = match worktrees::list_worktrees() {
+        Ok(worktrees) if !worktrees.is_empty() => worktrees.into_iter().map(|worktree| worktree.path).collect(),
+        _ => vec![PathBuf::from(".")],
+    };
+    
+    let mut fingerprint = String::new();
+    for root in roots {
+        for args in [&["rev-parse", "HEAD"][..], &["status", "--porcelain"][..]] {
+            if let Ok(output) = Process::git().current_dir(&root).args(args).read_only().output() {
+                fingerprint.push_str(&output.stdout);
+            }
+        }
+    }
+    fingerprint
+}
+
+async fn report(upstream_cache: &mut UpstreamCache) -> Result<StatusSummary> {
+    outln!("📊 Stack Status Report");
+    outln!("═══════════════════════");
+    
+    let current_dir = std::env::current_dir()?;
+    
+    // Outside a git repository there are no worktrees; report the current directory only
+    let worktrees: Vec<WorktreeInfo> = worktrees::list_worktrees()
+        .unwrap_or_default()
+        .into_iter()
+        .filter(|worktree| !worktree.is_bare && !worktree.is_prunable)
+        .collect();
+    
+    let mut summary = StatusSummary::default();
+    
+    if worktrees.is_empty() {
+        report_stacks(&current_dir, &mut summary, upstream_cache).await?;
+    } else {
+        for worktree in &worktrees {
+            let marker = if worktree.contains(&current_dir) { " (current)" } else { "" };
+            outln!("\n🌳 Worktree: {}{}", worktree.path.display(), marker);
+            outln!("  🌿 Branch: {}", worktree.branch_label());
+            
+            match check_git_status(&worktree.path) {
+                Ok(status_info) if status_info.has_changes => {
+                    outln!("  📝 Working tree: {} uncommitted change(s)", status_info.changes_count);
+                    summary.dirty_worktrees += 1;
+                }
+                Ok(_) => outln!("  ✅ Working tree: Clean"),
+                Err(e) => outln!("  ❌ Working tree: Failed to get status: {}", e),
+            }
+            
+            report_stacks(&worktree.path, &mut summary, upstream_cache).await?;
+        }
+    }
+    
+    report_mcp_servers(&current_dir).await;
+    
+    if summary.stacks == 0 {
+        outln!("\nNo stacks found.");
+        outln!("Run 'stacks checkout <stack-name>' to check out a stack.");
+        return Ok(summary);
+    }
+    
+    outln!("\n═══════════════════════");
+    outln!(
+        "📋 {} worktree(s), {} stack(s): {} with uncommitted stack changes, {} worktree(s) dirty",
+        worktrees.len().max(1),
+        summary.stacks,
+        summary.changed_stacks,
+        summary.dirty_worktrees
+    );
+    if summary.changed_stacks > 0 {
+        outln!("💡 Run 'stacks push' in a worktree to send its stack changes upstream");
+    }
+    if summary.behind_stacks > 0 {
+        outln!("⬇️ {} stack(s) behind upstream. Run 'stacks pull' to update.", summary.behind_stacks);
+    }
+    if summary.drifted_stacks > 0 {
+        outln!("⚠️ {} stack(s) have missing or broken symlinks or CLAUDE.md imports", summary.drifted_stacks);
+    }
+    if let Some(daemon_status) = daemon::load_status().filter(|status| status.is_fresh(chrono::Utc::now())) {
+        outln!(
+            "🛰️ Daemon checked at {}: {} update(s) available",
+            daemon_status.checked_at.with_timezone(&chrono::Local).format("%H:%M"),
+            daemon_status.outdated().len()
+        );
+    }
+    
+    Ok(summary)
+}
+
+/// Line-oriented, tab-separated status for scripts, or the `StatusOutput` JSON; returns the exit code
+///
+/// worktree <TAB> path <TAB> branch <TAB> clean|dirty <TAB> changes
+/// stack <TAB> worktree path <TAB> name <TAB> clean|modified|error <TAB> changes <TAB> local commits <TAB> ok|drift
+///
+/// Upstream comparisons need the network and are left to `stacks outdated`.
+async fn machine_report(json: bool) -> Result<i32> {
+    let status = collect_status().await?;
+    match json {
+        true => schema::print(&status)?,
+        false => print_porcelain(&status),
+    }
+    Ok(exit_code(&status))
+}
+
+/// Every worktree with its stacks, as `status --porcelain` and `--json` report them
+pub async fn collect_status() -> Result<StatusOutput> {
+    let roots: Vec<(PathBuf, Option<String>, Option<String>)> = match worktrees::list_worktrees() {
+        Ok(worktrees) if !worktrees.is_empty() => worktrees
+            .into_iter()
+            .filter(|worktree| !worktree.is_bare && !worktree.is_prunable)
+            .map(|worktree| (worktree.path, worktree.branch, worktree.head))
+            .collect(),
+        _ => vec![(std::env::current_dir()?, None, None)],
+    };
+    
+    let mut reports = Vec::new();
+    for (root, branch, head) in roots {
+        let (state, changes) = change_state(check_git_status(&root));
+        let mut stacks = Vec::new();
+        
+        let stacks_dir = root.join("stacks");
+        if stacks_dir.exists() {
+            for entry in WalkDir::new(&stacks_dir)
+                .min_depth(1)
+                .max_depth(1)
+                .sort_by_file_name()
+                .into_iter()
+                .filter_map(|e| e.ok())
+                .filter(|e| e.file_type().is_dir())
+            {
+                let name = entry.file_name().to_string_lossy().to_string();
+                let (state, changes) = change_state(check_subtree_status(&root, &name));
+                stacks.push(StackStatus {
+                    local_commits: upstream::local_commits_in(&root, &name, upstream::PROMPT_HISTORY_DEPTH).unwrap_or(0),
+                    healthy: stack_is_healthy(&root, &name).await.unwrap_or(false),
+                    name,
+                    state,
+                    changes,
+                });
+            }
+        }
+        
+        reports.push(WorktreeStatus { path: root, branch, head, state, changes, stacks });
+    }
+    
+    Ok(StatusOutput { schema_version: SCHEMA_VERSION, worktrees: reports })
+}
+
+fn change_state(status: Result<GitStatusInfo>) -> (ChangeState, Option<usize>) {
+    match status {
+        Ok(info) if info.has_changes => (ChangeState::Changed, Some(info.changes_count)),
+        Ok(_) => (ChangeState::Clean, Some(0)),
+        Err(_) => (ChangeState::Error, None),
+    }
+}
+
+/// 2 when anything couldn't be read, else 1 when a stack has changes, else 0
+fn exit_code(status: &StatusOutput) -> i32 {
+    let states = status.worktrees.iter().flat_map(|worktree| {
+        std::iter::once((worktree.state, false)).chain(worktree.stacks.iter().map(|stack| (stack.state, true)))
+    });
+    let mut code = EXIT_CLEAN;
+    for (state, is_stack) in states {
+        match (state, is_stack) {
+            (ChangeState::Error, _) => return EXIT_ERROR,
+            (ChangeState::Changed, true) => code = EXIT_CHANGES,
+            _ => {}
+        }
+    }
+    code
+}
+
+fn print_porcelain(status: &StatusOutput) {
+    let count = |changes: Option<usize>| changes.map(|changes| changes.to_string()).unwrap_or_else(|| "-".to_string());
+    for worktree in &status.worktrees {
+        let branch = match (&worktree.branch, &worktree.head) {
+            (Some(branch), _) => branch.clone(),
+            (None, Some(head)) => format!("detached at {}", head.chars().take(7).collect::<String>()),
+            (None, None) => "-".to_string(),
+        };
+        let state = match worktree.state {
+            ChangeState::Clean => "clean",
+            ChangeState::Changed => "dirty",
+            ChangeState::Error => "error",
+        };
+        println!("worktree\t{}\t{}\t{}\t{}", worktree.path.display(), branch, state, count(worktree.changes));
+        
+        for stack in &worktree.stacks {
+            let state = match stack.state {
+                ChangeState::Clean => "clean",
+                ChangeState::Changed => "modified",
+                ChangeState::Error => "error",
+            };
+            println!(
+                "stack\t{}\t{}\t{}\t{}\t{}\t{}",
+                worktree.path.display(),
+                stack.name,
+                state,
+                count(stack.changes),
+                stack.local_commits,
+                if stack.healthy { "ok" } else { "drift" }
+            );
+        }
+    }
+}
+
+/// Whether a stack's symlinks resolve and its CLAUDE.md import is present
+async fn stack_is_healthy(root: &Path, stack_name: &str) -> Result<bool> {
+    let stack = Stack::new(stack_name.to_string(), root.join("stacks").join(stack_name));
+    
+    if !SymlinkManager::with_claude_dir(root.join(".claude")).check_stack_symlinks(&stack).is_healthy() {
+        return Ok(false);
+    }
+    if stack.claude_md_path().is_some() {
+        return ClaudeMdUpdater::for_checkout(root).has_stack_import(stack_name).await;
+    }
+    Ok(true)
+}
+
+#[derive(Default)]
+struct StatusSummary {
+    stacks: usize,
+    changed_stacks: usize,
+    dirty_worktrees: usize,
+    drifted_stacks: usize,
+    behind_stacks: usize,
+}
+
+/// Upstream comparisons, cached so stacks shared between worktrees are only checked once
+struct UpstreamCache {
+    manager: RemoteStackManager,
+    results: HashMap<(String, String, Option<String>), UpstreamStatus>,
+}
+
+impl UpstreamCache {
+    fn new() -> Result<Self> {
+        Ok(Self {
+            manager: RemoteStackManager::new().context("Failed to initialize remote stack manager")?,
+            results: HashMap::new(),
+        })
+    }
+
+    async fn check(&mut self, metadata: &StackMetadata, installed: Option<String>) -> UpstreamStatus {
+        let key = (metadata.source_repo.clone(), metadata.source_ref(), installed.clone());
+        if let Some(status) = self.results.get(&key) {
+            return status.clone();
+        }
+        
+        // An unreachable upstream is reported as unknown rather than failing the whole report
+        let status = upstream::compare_upstream(&self.manager, metadata, installed.clone())
+            .await
+            .unwrap_or_else(|_| UpstreamStatus { installed_commit: installed, ..Default::default() });
+        self.results.insert(key, status.clone());
+        status
+    }
+}
+
+/// Print the status of every stack checked out under `root`
+async fn report_stacks(root: &Path, summary: &mut StatusSummary, upstream_cache: &mut UpstreamCache) -> Result<()> {
+    let stacks_dir = root.join("stacks");
+    
+    if !stacks_dir.exists() {
+        outln!("  📭 No stacks directory");
+        return Ok(());
+    }
     
     // Find all stack directories
     for entry in WalkDir::new(&stacks_dir)
         .min_depth(1)
         .max_depth(1)
+        .sort_by_file_name()
         .into_iter()
         .filter_map(|e| e.ok())
         .filter(|e| e.file_type().is_dir())
     {
-        found_stacks = true;
-        let stack_path = entry.path().to_path_buf();
+        summary.stacks += 1;
         let stack_name = entry.file_name().to_string_lossy().to_string();
         
-        println!("\n📦 Stack: {}", stack_name);
+        outln!("\n  📦 Stack: {}", stack_name);
         
         // For subtrees, check if this is a valid stack directory
-        println!("  📂 Type: Subtree (part of main repository)");
+        outln!("    📂 Type: Subtree (part of main repository)");
+        
+        let metadata = resolve_stack_metadata_in(root, &stack_name);
+        outln!("    📋 Source: {} ({})", metadata.source_repo, metadata.describe_ref());
         
-        // Check for subtree changes in main repository
-        match check_subtree_status(&stack_name) {
+        // Check for subtree changes in the worktree
+        match check_subtree_status(root, &stack_name) {
             Ok(status_info) => {
                 if status_info.has_changes {
-                    println!("  📝 Status: {} changes in subtree", status_info.changes_count);
+                    summary.changed_stacks += 1;
+                    outln!("    📝 Status: {} changes in subtree", status_info.changes_count);
                     if !status_info.changes.is_empty() {
                         for change in status_info.changes.iter().take(5) {
                             // Remove the stacks/stack-name/ prefix for cleaner display
                             let clean_change = change.replace(&format!("stacks/{}/", stack_name), "");
-                            println!("    {}", clean_change);
+                            outln!("      {}", clean_change);
                         }
                         if status_info.changes.len() > 5 {
-                            println!("    ... and {} more", status_info.changes.len() - 5);
+                            outln!("      ... and {} more", status_info.changes.len() - 5);
                         }
                     }
                 } else {
-                    println!("  ✅ Status: Clean (no changes in subtree)");
+                    outln!("    ✅ Status: Clean (no changes in subtree)");
                 }
             }
             Err(e) => {
-                println!("  ❌ Status: Failed to get subtree status: {}", e);
+                outln!("    ❌ Status: Failed to get subtree status: {}", e);
             }
         }
         
+        // Compare with the upstream branch; the lockfile commit covers history without a subtree squash
+        let installed = upstream::installed_commit_in(root, &stack_name)
+            .ok()
+            .flatten()
+            .or_else(|| metadata.commit.clone());
+        let upstream_status = upstream_cache.check(&metadata, installed).await;
+        if upstream_status.is_outdated() {
+            summary.behind_stacks += 1;
+            outln!("    ⬇️ Upstream: {}", upstream_status.describe());
+        } else {
+            outln!("    🔄 Upstream: {}", upstream_status.describe());
+        }
+        
+        let local_commits = upstream::local_commits_in(root, &stack_name, usize::MAX).unwrap_or(0);
+        if local_commits > 0 {
+            outln!("    ⬆️ {} local commit(s) since the last update from upstream", local_commits);
+        }
+        
         // Show last commit info for the subtree
-        if let Ok(commit_info) = get_subtree_last_commit(&stack_name) {
-            println!("  🕒 Last subtree change: {}", commit_info);
+        if let Ok(commit_info) = get_subtree_last_commit(root, &stack_name) {
+            outln!("    🕒 Last subtree change: {}", commit_info);
+        }
+        
+        if !report_stack_health(root, &stack_name).await? {
+            summary.drifted_stacks += 1;
+            outln!("    💡 Run 'stacks repair {}' in {} to restore them", stack_name, root.display());
         }
-    }
-    
-    if !found_stacks {
-        println!("No stacks found in the stacks directory.");
-        println!("Run 'stacks checkout <stack-name>' to check out a stack.");
     }
     
     Ok(())
 }
 
-struct GitStatusInfo {
-    has_changes: bool,
-    changes_count: usize,
-    changes: Vec<String>,
-}
-
-fn load_stack_metadata(stack_path: &Path) -> Result<StackMetadata> {
-    let metadata_file = stack_path.join(".stack-metadata.json");
+/// Print the MCP servers of the current project: who requires each and whether it is healthy
+async fn report_mcp_servers(root: &Path) {
+    let validator = McpValidator::new();
+    let mut required: BTreeMap<String, Vec<String>> = BTreeMap::new();
     
-    let metadata_content = std::fs::read_to_string(metadata_file)
-        .context("Failed to read stack metadata")?;
+    let stacks_dir = root.join("stacks");
+    if stacks_dir.exists() {
+        for entry in WalkDir::new(&stacks_dir)
+            .min_depth(1)
+            .max_depth(1)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_dir())
+        {
+            let stack_name = entry.file_name().to_string_lossy().to_string();
+            let stack = Stack::new(stack_name.clone(), entry.path().to_path_buf());
+            for server in validator.stack_required_servers(&stack).await.unwrap_or_default() {
+                required.entry(server.name).or_default().push(format!("stack {}", stack_name));
+            }
+        }
+    }
+    for (compose_file, name) in sync::compose_mcp_servers().await.unwrap_or_default() {
+        required.entry(name).or_default().push(format!("sync ({})", compose_file.display()));
+    }
     
-    let metadata: StackMetadata = serde_json::from_str(&metadata_content)
-        .context("Failed to parse stack metadata")?;
+    let installed = match validator.list_installed_servers().await {
+        Ok(installed) => installed,
+        Err(_) => {
+            outln!("\n🔌 MCP Servers: unknown ('claude' CLI not available)");
+            return;
+        }
+    };
     
-    Ok(metadata)
-}
-
-fn get_current_branch(stack_path: &PathBuf) -> Result<String> {
-    let output = Command::new("git")
-        .current_dir(stack_path)
-        .args(["branch", "--show-current"])
-        .output()
-        .context("Failed to get current branch")?;
+    let mut names: Vec<&String> = required.keys().chain(installed.iter().map(|server| &server.name)).collect();
+    names.sort();
+    names.dedup();
     
-    if output.status.success() {
-        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
-    } else {
-        Ok("unknown".to_string())
+    if names.is_empty() {
+        outln!("\n🔌 MCP Servers: none configured or required");
+        return;
     }
-}
-
-fn check_git_status(stack_path: &PathBuf) -> Result<GitStatusInfo> {
-    let output = Command::new("git")
-        .current_dir(stack_path)
-        .args(["status", "--porcelain"])
-        .output()
-        .context("Failed to check git status")?;
     
-    let status_lines = String::from_utf8_lossy(&output.stdout);
-    let changes: Vec<String> = status_lines
-        .lines()
-        .filter(|line| !line.trim().is_empty())
-        .map(|line| line.to_string())
-        .collect();
+    outln!("\n🔌 MCP Servers");
+    let mut missing = 0;
+    let mut unhealthy = 0;
+    for name in names {
+        let state = match installed.iter().find(|server| &server.name == name) {
+            Some(server) => match server.healthy {
+                Some(true) => "✅ connected",
+                Some(false) => {
+                    unhealthy += 1;
+                    "❌ failed health check"
+                }
+                None => "✔️ installed",
+            },
+            None => {
+                missing += 1;
+                "⚠️ not installed"
+            }
+        };
+        let sources = required.get(name)
+            .map(|sources| format!("required by {}", sources.join(", ")))
+            .unwrap_or_else(|| "added manually".to_string());
+        outln!("  {} {} ({})", state, name, sources);
+    }
     
-    Ok(GitStatusInfo {
-        has_changes: !changes.is_empty(),
-        changes_count: changes.len(),
-        changes,
-    })
+    if missing > 0 {
+        outln!("  💡 Run 'stacks sync' or 'stacks checkout' to install missing servers");
+    }
+    if unhealthy > 0 {
+        outln!("  💡 Run 'claude mcp list' for details on failing servers");
+    }
 }
 
-fn get_remote_status(stack_path: &PathBuf) -> Result<String> {
-    // Fetch from origin first (quietly)
-    let _fetch_output = Command::new("git")
-        .current_dir(stack_path)
-        .args(["fetch", "origin", "--quiet"])
-        .output();
+/// Print symlink and CLAUDE.md import health for a stack; returns false on drift
+async fn report_stack_health(root: &Path, stack_name: &str) -> Result<bool> {
+    let stack = Stack::new(stack_name.to_string(), root.join("stacks").join(stack_name));
+    let mut healthy = true;
     
-    // Check if ahead/behind
-    let output = Command::new("git")
-        .current_dir(stack_path)
-        .args(["status", "-b", "--porcelain"])
-        .output()
-        .context("Failed to check remote status")?;
-    
-    if output.status.success() {
-        let status_output = String::from_utf8_lossy(&output.stdout);
-        for line in status_output.lines() {
-            if line.starts_with("##") {
-                if line.contains("[ahead") || line.contains("[behind") {
-                    // Extract the ahead/behind information
-                    if let Some(bracket_start) = line.find('[') {
-                        if let Some(bracket_end) = line.find(']') {
-                            return Ok(line[bracket_start..=bracket_end].to_string());
-                        }
-                    }
-                }
-                break;
-            }
+    let symlink_health = SymlinkManager::with_claude_dir(root.join(".claude")).check_stack_symlinks(&stack);
+    if symlink_health.is_healthy() {
+        if symlink_health.linked > 0 {
+            outln!("    🔗 Symlinks: {} linked", symlink_health.linked);
+        }
+    } else {
+        healthy = false;
+        outln!(
+            "    ⚠️ Symlinks: {} linked, {} missing, {} broken",
+            symlink_health.linked,
+            symlink_health.missing.len(),
+            symlink_health.broken.len()
+        );
+        for link in symlink_health.missing.iter().take(5) {
+            outln!("      missing: {}", link.strip_prefix(root).unwrap_or(link.as_path()).display());
+        }
+        for link in symlink_health.broken.iter().take(5) {
+            outln!("      broken:  {}", link.strip_prefix(root).unwrap_or(link.as_path()).display());
+        }
+    }
+    
+    if stack.claude_md_path().is_some() {
+        let md_updater = ClaudeMdUpdater::for_checkout(root);
+        if md_updater.has_stack_import(stack_name).await? {
+            outln!("    📄 CLAUDE.md import: present");
+        } else {
+            healthy = false;
+            outln!("    ⚠️ CLAUDE.md import: missing (@stacks/{}/CLAUDE.md)", stack_name);
         }
     }
     
-    Ok(String::new())
+    Ok(healthy)
 }
 
-fn get_last_commit_info(stack_path: &PathBuf) -> Result<String> {
-    let output = Command::new("git")
-        .current_dir(stack_path)
-        .args(["log", "-1", "--format=%h - %s (%cr)"])
-        .output()
-        .context("Failed to get last commit info")?;
-    
-    if output.status.success() {
-        Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
-    } else {
-        Ok("No commits found".to_string())
+struct GitStatusInfo {
+    has_changes: bool,
+    changes_count: usize,
+    changes: Vec<String>,
+}
+
+impl GitStatusInfo {
+    fn from_changes(changes: Vec<(git2::Status, String)>) -> Self {
+        let changes: Vec<String> = changes
+            .into_iter()
+            .map(|(status, path)| format!("{} {}", git::porcelain_code(status), path))
+            .collect();
+
+        Self {
+            has_changes: !changes.is_empty(),
+            changes_count: changes.len(),
+            changes,
+        }
     }
 }
 
-fn check_subtree_status(stack_name: &str) -> Result<GitStatusInfo> {
-    let output = Command::new("git")
-        .args(["status", "--porcelain", &format!("stacks/{}", stack_name)])
-        .output()
-        .context("Failed to check subtree git status")?;
-    
-    let status_lines = String::from_utf8_lossy(&output.stdout);
-    let changes: Vec<String> = status_lines
-        .lines()
-        .filter(|line| !line.trim().is_empty())
-        .map(|line| line.to_string())
-        .collect();
-    
-    Ok(GitStatusInfo {
-        has_changes: !changes.is_empty(),
-        changes_count: changes.len(),
-        changes,
-    })
+fn check_git_status(stack_path: &Path) -> Result<GitStatusInfo> {
+    let changes = Repo::discover(stack_path)?.changes_in(stack_path)?;
+    Ok(GitStatusInfo::from_changes(changes))
+}
+
+fn check_subtree_status(root: &Path, stack_name: &str) -> Result<GitStatusInfo> {
+    let changes = Repo::discover(root)?.changes(&format!("stacks/{}", stack_name))?;
+    Ok(GitStatusInfo::from_changes(changes))
 }
 
-fn get_subtree_last_commit(stack_name: &str) -> Result<String> {
-    let output = Command::new("git")
+fn get_subtree_last_commit(root: &Path, stack_name: &str) -> Result<String> {
+    let output = Process::git()
+        .current_dir(root)
         .args(["log", "-1", "--format=%h - %s (%cr)", "--", &format!("stacks/{}", stack_name)])
+        .read_only()
         .output()
         .context("Failed to get last commit info for subtree")?;
     
-    if output.status.success() {
-        let commit_info = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if output.success() {
+        let commit_info = output.stdout.trim().to_string();
         if commit_info.is_empty() {
             Ok("No commits found for subtree".to_string())
         } else {
@@ -0,0 +1,51 @@
+use std::path::PathBuf;
+use anyhow::Result;
+
+use crate::cli::query::dirty_stacks;
+use crate::core::daemon;
+use crate::core::git::Repo;
+use crate::core::session_manifest::SessionManifest;
+
+/// Print a one-line summary for tmux's status-right: worktree, branch, dirty stacks and upstream drift
+///
+/// Meant for `#(stacks statusline --path '#{pane_current_path}')`; outside a repository it
+/// prints nothing, and drift only shows while `stacks daemon` keeps its status fresh.
+pub async fn run(path: Option<PathBuf>) -> Result<()> {
+    let dir = match path {
+        Some(path) => path,
+        None => std::env::current_dir()?,
+    };
+    if let Some(line) = statusline(dir) {
+        outln!("{}", line);
+    }
+    Ok(())
+}
+
+fn statusline(dir: PathBuf) -> Option<String> {
+    let repo = Repo::discover(&dir).ok()?;
+    let root = repo.workdir()?.to_path_buf();
+    // The daemon status is looked up from the current directory's repository
+    std::env::set_current_dir(&root).ok()?;
+
+    let name = match SessionManifest::load(&root) {
+        Ok(Some(manifest)) => manifest.task,
+        _ => root.file_name()?.to_string_lossy().to_string(),
+    };
+    let mut parts = vec![format!("📂 {}", name)];
+    if let Some(branch) = repo.symbolic_target("HEAD") {
+        parts.push(format!("⎇ {}", branch.trim_start_matches("refs/heads/")));
+    }
+
+    let dirty = dirty_stacks(&repo, &root).len();
+    if dirty > 0 {
+        parts.push(format!("✏️ {} dirty", dirty));
+    }
+
+    if let Some(status) = daemon::load_status() {
+        let outdated = status.outdated().len();
+        if status.is_fresh(chrono::Utc::now()) && outdated > 0 {
+            parts.push(format!("⬇️ {} behind", outdated));
+        }
+    }
+    Some(parts.join(" │ "))
+}
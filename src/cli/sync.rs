@@ -1,9 +1,12 @@
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use std::collections::HashMap;
 use anyhow::{Result, Context};
 use serde_yaml::Value as YamlValue;
 use dialoguer::Confirm;
 
+use crate::utils::process::Process;
+use crate::utils::terminal::require_interactive;
+
 #[derive(Debug, Clone)]
 pub struct DockerService {
     pub name: String,
@@ -24,17 +27,17 @@ pub enum ServiceType {
 }
 
 pub async fn run() -> Result<()> {
-    println!("🔍 Discovering services in docker-compose files...");
+    outln!("🔍 Discovering services in docker-compose files...");
     
     let compose_files = find_docker_compose_files()?;
     if compose_files.is_empty() {
-        println!("No docker-compose files found. Nothing to sync.");
+        outln!("No docker-compose files found. Nothing to sync.");
         return Ok(());
     }
 
-    println!("📁 Found {} docker-compose file(s):", compose_files.len());
+    outln!("📁 Found {} docker-compose file(s):", compose_files.len());
     for file in &compose_files {
-        println!("  • {}", file.display());
+        outln!("  • {}", file.display());
     }
 
     let mut all_services = Vec::new();
@@ -47,43 +50,70 @@ pub async fn run() -> Result<()> {
     }
 
     if all_services.is_empty() {
-        println!("No MCP-compatible services found in docker-compose files.");
+        outln!("No MCP-compatible services found in docker-compose files.");
         return Ok(());
     }
 
     // Show discovered services
-    println!("\n🎯 MCP-compatible services discovered:");
+    outln!("\n🎯 MCP-compatible services discovered:");
     for service in &all_services {
-        println!("  • {} ({:?})", service.name, service.service_type);
+        outln!("  • {} ({:?})", service.name, service.service_type);
     }
 
     // Generate MCP commands
     let mcp_commands = generate_mcp_commands(&all_services);
     
-    println!("\n📋 Generated MCP server commands:");
+    outln!("\n📋 Generated MCP server commands:");
     for (service, command) in all_services.iter().zip(mcp_commands.iter()) {
-        println!("  • {}: {}", service.name, command);
+        outln!("  • {}: {}", service.name, command);
     }
 
+    require_interactive("stacks sync", "Add the servers yourself with the commands listed above")?;
     let should_apply = Confirm::new()
         .with_prompt("Apply these MCP server configurations?")
         .default(true)
         .interact()?;
 
     if !should_apply {
-        println!("Sync cancelled. No changes made.");
+        outln!("Sync cancelled. No changes made.");
         return Ok(());
     }
 
     // Execute MCP server additions
     execute_mcp_commands(&mcp_commands).await?;
 
-    println!("\n🎉 MCP sync completed successfully!");
-    println!("💡 All discovered services have been configured as MCP servers.");
+    outln!("\n🎉 MCP sync completed successfully!");
+    outln!("💡 All discovered services have been configured as MCP servers.");
 
     Ok(())
 }
 
+/// MCP servers `stacks sync` would configure, with the compose file each comes from
+pub async fn compose_mcp_servers() -> Result<Vec<(PathBuf, String)>> {
+    let mut servers = Vec::new();
+    
+    for compose_file in find_docker_compose_files()? {
+        let services = parse_docker_compose(&compose_file).await
+            .with_context(|| format!("Failed to parse {}", compose_file.display()))?;
+        for service in services {
+            if let Some(name) = mcp_server_name(&service.service_type) {
+                servers.push((compose_file.clone(), name.to_string()));
+            }
+        }
+    }
+    
+    Ok(servers)
+}
+
+/// Name of the MCP server generated for a service type, if sync can generate one
+fn mcp_server_name(service_type: &ServiceType) -> Option<&'static str> {
+    match service_type {
+        ServiceType::Postgres => Some("postgres"),
+        ServiceType::Redis => Some("redis"),
+        _ => None,
+    }
+}
+
 fn find_docker_compose_files() -> Result<Vec<std::path::PathBuf>> {
     let compose_filenames = [
         "docker-compose.yml",
@@ -303,33 +333,32 @@ fn extract_host_port(ports: &[String]) -> Option<&str> {
 }
 
 async fn execute_mcp_commands(commands: &[String]) -> Result<()> {
-    println!("\n🚀 Executing MCP server configurations...");
+    outln!("\n🚀 Executing MCP server configurations...");
     
     for (i, command) in commands.iter().enumerate() {
         if command.starts_with('#') {
-            println!("  ℹ️ Skipping manual configuration: {}", command);
+            outln!("  ℹ️ Skipping manual configuration: {}", command);
             continue;
         }
 
-        println!("  {} Executing: {}", i + 1, command);
+        outln!("  {} Executing: {}", i + 1, command);
         
         // Parse the command to extract arguments
         let parts: Vec<&str> = command.split_whitespace().collect();
         if parts.len() < 4 || parts[0] != "claude" || parts[1] != "mcp" || parts[2] != "add" {
-            println!("    ⚠️ Invalid command format, skipping");
+            outln!("    ⚠️ Invalid command format, skipping");
             continue;
         }
 
-        let output = std::process::Command::new("claude")
+        let output = Process::claude()
             .args(&parts[1..])
             .output()
             .with_context(|| format!("Failed to execute command: {}", command))?;
 
-        if output.status.success() {
-            println!("    ✅ Success");
+        if output.success() {
+            outln!("    ✅ Success");
         } else {
-            let error = String::from_utf8_lossy(&output.stderr);
-            println!("    ❌ Failed: {}", error);
+            outln!("    ❌ Failed: {}", output.stderr);
         }
     }
 
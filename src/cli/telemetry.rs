@@ -0,0 +1,58 @@
+use anyhow::Result;
+
+use crate::config;
+use crate::core::policy::Policy;
+use crate::core::telemetry;
+
+/// Show whether telemetry is on, where records go, and the failure rates recorded so far
+pub async fn status() -> Result<()> {
+    let enabled = config::load_config()?.telemetry;
+    let path = telemetry::records_path()?;
+    let records = telemetry::load(&path);
+
+    match enabled {
+        true => outln!("📊 Telemetry is on"),
+        false => outln!("📊 Telemetry is off (turn it on with 'stacks telemetry on')"),
+    }
+    outln!("  📄 {} ({} record(s))", path.display(), records.len());
+    outln!("  🔒 Only command names, durations, error classes, the stacks version and the OS are recorded");
+    match Policy::current().await?.and_then(|policy| policy.telemetry.endpoint.as_ref()) {
+        Some(endpoint) => outln!("  📤 Uploaded daily to {} (set by the organization policy)", endpoint),
+        None => outln!("  💾 Kept on this machine; no upload endpoint is configured"),
+    }
+
+    let stats = telemetry::summarize(&records);
+    if !stats.is_empty() {
+        outln!("\n{:<20} {:>6} {:>8} {:>10}", "COMMAND", "RUNS", "FAILED", "AVERAGE");
+        for command in stats {
+            outln!(
+                "{:<20} {:>6} {:>7.0}% {:>9.1}s",
+                command.command,
+                command.runs,
+                command.failure_rate() * 100.0,
+                command.average().as_secs_f64()
+            );
+        }
+    }
+    Ok(())
+}
+
+pub async fn on() -> Result<()> {
+    config::update_config(|config| {
+        config.telemetry = true;
+        Ok(())
+    })?;
+    outln!("📊 Telemetry turned on. See what is recorded with 'stacks telemetry status'.");
+    Ok(())
+}
+
+/// Turn telemetry off and delete what was recorded on this machine
+pub async fn off() -> Result<()> {
+    config::update_config(|config| {
+        config.telemetry = false;
+        Ok(())
+    })?;
+    telemetry::clear()?;
+    outln!("📊 Telemetry turned off and local records deleted");
+    Ok(())
+}
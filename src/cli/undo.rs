@@ -0,0 +1,111 @@
+use std::path::Path;
+use anyhow::{Result, Context, bail};
+use dialoguer::Confirm;
+
+use crate::cli::remove::remove_subtree;
+use crate::core::journal::{Journal, JournalEntry, StackChange};
+use crate::core::lockfile::forget_stack;
+use crate::utils::claude_md_updater::ClaudeMdUpdater;
+use crate::utils::files;
+use crate::utils::terminal::is_interactive;
+
+/// Revert the project's most recent recorded operation, e.g. a checkout of the wrong stacks
+///
+/// Removes the subtrees, links and CLAUDE.md imports it added and restores the settings it merged into.
+pub async fn run(yes: bool) -> Result<()> {
+    let root = std::env::current_dir()?;
+    let mut journal = Journal::load(&root)?;
+    let entry = match journal.last() {
+        Some(entry) => entry.clone(),
+        None => {
+            outln!("ℹ️ Nothing to undo in this project");
+            return Ok(());
+        }
+    };
+
+    outln!(
+        "↩️ Last operation: {} of {} ({})",
+        entry.operation,
+        entry.stack_names().join(", "),
+        entry.recorded_at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M")
+    );
+    print_plan(&entry);
+
+    if !yes {
+        if !is_interactive() {
+            bail!("Refusing to undo the last {} without confirmation; pass --yes", entry.operation);
+        }
+
+        let confirmed = Confirm::new()
+            .with_prompt("Undo it? Changes made to these stacks since are lost")
+            .default(false)
+            .interact()?;
+
+        if !confirmed {
+            outln!("Undo cancelled.");
+            return Ok(());
+        }
+    }
+
+    for change in entry.stacks.iter().rev() {
+        undo_stack_change(&root, change).await?;
+    }
+    for path in entry.restore_backups(&root)? {
+        outln!("  🛡️ Restored {}", path.display());
+    }
+
+    journal.pop();
+    journal.save(&root)?;
+
+    outln!("✅ Undid {} of {}", entry.operation, entry.stack_names().join(", "));
+    Ok(())
+}
+
+fn print_plan(entry: &JournalEntry) {
+    for change in &entry.stacks {
+        let mut steps = Vec::new();
+        if change.added_subtree {
+            steps.push(format!("remove stacks/{}", change.stack));
+        }
+        if !change.links.is_empty() {
+            steps.push(format!("delete {} link(s)", change.links.len()));
+        }
+        if change.added_import {
+            steps.push("drop its CLAUDE.md import".to_string());
+        }
+        match steps.is_empty() {
+            true => outln!("  • {}: nothing to revert", change.stack),
+            false => outln!("  • {}: {}", change.stack, steps.join(", ")),
+        }
+    }
+    if !entry.backups.is_empty() {
+        outln!("  • restore the settings from before the {}", entry.operation);
+    }
+}
+
+async fn undo_stack_change(root: &Path, change: &StackChange) -> Result<()> {
+    outln!("🔧 Reverting {}", change.stack);
+
+    for link in &change.links {
+        let path = root.join(link);
+        // A link replaced by hand since is left alone
+        if path.is_symlink() {
+            files::remove_file(&path)
+                .with_context(|| format!("Failed to remove symlink {}", path.display()))?;
+            outln!("  🗑️ Removed symlink: {}", link.display());
+        }
+    }
+
+    if change.added_import {
+        ClaudeMdUpdater::for_project(root)?.remove_stack_import(&change.stack).await
+            .with_context(|| format!("Failed to update CLAUDE.md for stack {}", change.stack))?;
+    }
+
+    if change.added_subtree && root.join("stacks").join(&change.stack).exists() {
+        remove_subtree(&change.stack)?;
+        forget_stack(&change.stack)?;
+        outln!("  🗑️ Removed stacks/{}", change.stack);
+    }
+
+    Ok(())
+}
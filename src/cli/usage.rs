@@ -0,0 +1,61 @@
+use anyhow::Result;
+
+use crate::core::sessions::SessionRegistry;
+use crate::core::usage::{self, format_tokens, Usage};
+use crate::core::worktrees::list_worktrees;
+
+/// Show claude token usage and estimated cost per worktree of this repository
+pub async fn run(days: Option<u32>) -> Result<()> {
+    let since = days.map(|days| chrono::Utc::now() - chrono::Duration::days(days as i64));
+    let registry = SessionRegistry::load().unwrap_or_default();
+
+    let mut rows: Vec<(String, String, Usage)> = Vec::new();
+    for worktree in list_worktrees()?.into_iter().filter(|worktree| !worktree.is_bare) {
+        let usage = usage::usage_for_dir(&worktree.path, since);
+        if usage.messages == 0 {
+            continue;
+        }
+        // Prefer the task name stacks recorded when it created the worktree
+        let task = registry.sessions
+            .iter()
+            .find(|session| session.worktree == worktree.path)
+            .map(|session| session.task.clone())
+            .or_else(|| worktree.path.file_name().map(|name| name.to_string_lossy().to_string()))
+            .unwrap_or_default();
+        rows.push((task, worktree.branch_label(), usage));
+    }
+
+    let period = match days {
+        Some(days) => format!(" in the last {} day(s)", days),
+        None => String::new(),
+    };
+    if rows.is_empty() {
+        outln!("📭 No claude usage recorded for this repository's worktrees{}", period);
+        return Ok(());
+    }
+    rows.sort_by(|a, b| b.2.cost_usd.total_cmp(&a.2.cost_usd));
+
+    outln!("💸 Claude usage per worktree{}:", period);
+    outln!("  {:<24} {:<28} {:>8} {:>8} {:>8} {:>10}", "TASK", "BRANCH", "SESSIONS", "INPUT", "OUTPUT", "COST");
+    let mut total = Usage::default();
+    for (task, branch, usage) in &rows {
+        outln!(
+            "  {:<24} {:<28} {:>8} {:>8} {:>8} {:>10}",
+            task,
+            branch,
+            usage.sessions,
+            format_tokens(usage.input_tokens + usage.cache_creation_tokens + usage.cache_read_tokens),
+            format_tokens(usage.output_tokens),
+            format!("${:.2}", usage.cost_usd)
+        );
+        total.add(usage);
+    }
+    outln!(
+        "\n  Total: {} session(s), {} tokens, ${:.2}",
+        total.sessions,
+        format_tokens(total.total_tokens()),
+        total.cost_usd
+    );
+    outln!("💡 Costs are estimated from list prices unless claude recorded them");
+    Ok(())
+}
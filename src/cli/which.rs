@@ -0,0 +1,43 @@
+use anyhow::Result;
+
+use crate::core::definitions::{self, DefinitionKind, Scope};
+use crate::error::StacksError;
+
+/// Show where an agent or command comes from: project or user install, stack and symlink target
+pub async fn run(name: String) -> Result<()> {
+    let root = std::env::current_dir()?;
+    let user_dir = definitions::user_claude_dir();
+    let found = definitions::find(&name, &root, user_dir.as_deref());
+    if found.is_empty() {
+        return Err(StacksError::validation(format!("No agent or command named {}", name))
+            .with_hint("Agents and commands are looked up in .claude/ and ~/.claude/, and in the stacks under stacks/")
+            .into());
+    }
+
+    for definition in &found {
+        let source = match &definition.stack {
+            Some(stack) => format!("stack {}", stack),
+            None => "no stack".to_string(),
+        };
+        outln!("🔎 {} {} ({}, {})", definition.kind.as_str(), definition.name, definition.scope.as_str(), source);
+        outln!("  📄 {}", definition.path.strip_prefix(&root).unwrap_or(&definition.path).display());
+        if let Some(target) = &definition.link_target {
+            match definition.is_broken() {
+                true => outln!("  ❌ Broken symlink to {}; run 'stacks repair'", target.display()),
+                false => outln!("  🔗 Symlink to {}", target.display()),
+            }
+        }
+        if definition.scope == Scope::Unlinked {
+            outln!("  ⚠️ Not linked into .claude, so claude doesn't see it; run 'stacks repair'");
+        }
+    }
+
+    let active = |kind: DefinitionKind| found.iter().filter(|definition| definition.kind == kind && definition.scope != Scope::Unlinked).count();
+    if active(DefinitionKind::Agent) > 1 {
+        outln!("\n💡 Several agents share this name; project agents take precedence over user agents");
+    }
+    if active(DefinitionKind::Command) > 1 {
+        outln!("\n💡 Several commands share this name; invoke the stack-prefixed one (e.g. /<stack>_{}) to be explicit", name.trim_start_matches('/'));
+    }
+    Ok(())
+}
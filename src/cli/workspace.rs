@@ -0,0 +1,80 @@
+use std::process::Command;
+use anyhow::{Result, Context};
+
+use crate::core::workspace::{Workspace, MEMBER_ENV, WORKSPACE_FILE};
+use crate::error::StacksError;
+use crate::utils::logging::CommandLogExt;
+
+/// Run the current command in every member of the workspace instead of the current directory
+///
+/// Each member gets its own `stacks` process with the same arguments minus `--workspace`,
+/// so per-repository config and hooks apply as usual. `args` is the command line after alias
+/// expansion, so an alias that adds `--workspace` isn't expanded again in the members.
+pub async fn run_in_members(args: &[String]) -> Result<()> {
+    let args: Vec<String> = args.iter().skip(1).filter(|arg| *arg != "--workspace").cloned().collect();
+    for_each_member(&Workspace::current()?, &args)
+}
+
+/// Install the same stacks into every member of the workspace
+///
+/// Groups are expanded once at the workspace root, so every member receives the same stacks.
+/// Without stacks or groups, the `stacks` list of the workspace manifest is used.
+pub async fn checkout(stacks: Vec<String>, groups: Vec<String>, review: bool) -> Result<()> {
+    let workspace = Workspace::current()?;
+    let mut names = stacks;
+    if !groups.is_empty() {
+        for member in crate::config::expand_groups(&crate::config::stack_groups()?, &groups)? {
+            if !names.contains(&member) {
+                names.push(member);
+            }
+        }
+    }
+    if names.is_empty() {
+        names = workspace.stacks.clone();
+    }
+    if names.is_empty() {
+        return Err(StacksError::validation("No stacks to check out in the workspace")
+            .with_hint(format!("Name the stacks, use --group, or list them as stacks = [...] in {}", WORKSPACE_FILE))
+            .into());
+    }
+
+    outln!("👥 Checking out {} in {} member(s)", names.join(", "), workspace.members.len());
+    let mut args = vec!["checkout".to_string()];
+    args.extend(names);
+    if review {
+        args.push("--review".to_string());
+    }
+    for_each_member(&workspace, &args)
+}
+
+fn for_each_member(workspace: &Workspace, args: &[String]) -> Result<()> {
+    // A member inside the workspace would find the root manifest again and run itself forever
+    if std::env::var_os(MEMBER_ENV).is_some() {
+        return Err(StacksError::validation("--workspace can't be used inside a workspace member's command")
+            .with_hint("Check that no alias used with --workspace adds --workspace again")
+            .into());
+    }
+    let executable = std::env::current_exe().context("Failed to locate the stacks executable")?;
+    let members = workspace.member_dirs()?;
+
+    let mut failed = Vec::new();
+    for (member, dir) in &members {
+        outln!("\n📂 {}", member);
+        // Members may prompt (pickers, --review), so they keep the terminal
+        let status = Command::new(&executable)
+            .args(args)
+            .env(MEMBER_ENV, "1")
+            .current_dir(dir)
+            .logged_status()
+            .with_context(|| format!("Failed to run stacks in {}", dir.display()))?;
+        if !status.success() {
+            failed.push(member.clone());
+        }
+    }
+
+    if !failed.is_empty() {
+        anyhow::bail!("{} of {} member(s) failed: {}", failed.len(), members.len(), failed.join(", "));
+    }
+    outln!("\n✅ Done in {} member(s)", members.len());
+    Ok(())
+}
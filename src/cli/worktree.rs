@@ -1,11 +1,22 @@
-use std::process::Command;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 use anyhow::{Result, Context};
 use dialoguer::{Input, Select, Confirm};
 
-use crate::utils::dependency_check::check_dependencies;
+use crate::cli::attach;
+use crate::core::sessions::{label_target, record_session, task_label};
+use crate::core::transcripts;
+use crate::utils::dependency_check::{require_all, tmux_server_running, Dependency};
 use crate::config::{load_config, TmuxStrategy, InTmuxBehavior};
-use crate::core::permission_generator::PermissionGenerator;
+use crate::core::default_branch::{default_branch_start_point, project_default_branch};
+use crate::core::container;
+use crate::core::sandbox;
+use crate::core::permission_generator::{protected_paths, PermissionGenerator, PermissionPreset, PermissionTemplate};
+use crate::error::{not_a_repository, StacksError};
+use crate::utils::process::Process;
+use crate::utils::task_name;
+use crate::utils::claude_md_updater::{ClaudeMdUpdater, LOCAL_CLAUDE_MD};
+use crate::utils::files;
+use crate::utils::terminal::{is_interactive, require_interactive};
 
 #[derive(Debug, Clone)]
 pub struct WorktreeConfig {
@@ -15,8 +26,49 @@ pub struct WorktreeConfig {
     pub tmux_session: String,
     pub tmux_strategy: TmuxStrategy,
     pub navigation_command: Option<String>,
+    /// Built-in permission template chosen with --permissions; overrides project and config templates
+    pub permission_preset: Option<PermissionPreset>,
+    /// Run claude inside the configured OS-level sandbox
+    pub sandbox: bool,
+    /// Run claude in a container with only the worktree writable
+    pub container: bool,
+    /// Record each claude pane's output to .stacks/logs/<task>/
+    pub record: bool,
+    /// Shell command each tmux pane runs to start claude
+    pub claude_command: String,
+    /// Per-pane commands from the launch profiles, in the order panes are created
+    pub pane_commands: Vec<String>,
 }
 
+impl WorktreeConfig {
+    pub fn new(task_name: String, branch_strategy: BranchStrategy, location: PathBuf, tmux_session: String, tmux_strategy: TmuxStrategy) -> Self {
+        Self {
+            task_name,
+            branch_strategy,
+            location,
+            tmux_session,
+            tmux_strategy,
+            navigation_command: None,
+            permission_preset: None,
+            sandbox: false,
+            container: false,
+            record: false,
+            claude_command: CLAUDE_COMMAND.to_string(),
+            pane_commands: Vec::new(),
+        }
+    }
+
+    /// Shell command the claude pane at `index` runs; `claude_command` when no profile applies
+    pub fn pane_command(&self, index: usize) -> &str {
+        self.pane_commands.get(index).map(String::as_str).unwrap_or(&self.claude_command)
+    }
+}
+
+pub(crate) const CLAUDE_COMMAND: &str = "claude --permission-mode acceptEdits";
+
+/// Most claude panes a tmux strategy creates
+const CLAUDE_PANES: usize = 4;
+
 #[derive(Debug, Clone)]
 pub enum BranchStrategy {
     NewFromCurrent,
@@ -25,56 +77,106 @@ pub enum BranchStrategy {
     NewFromRemote(String),
 }
 
-pub async fn run() -> Result<()> {
-    println!("🔍 Checking dependencies...");
-    check_dependencies().context("Dependency check failed")?;
+impl BranchStrategy {
+    /// Branch for `git worktree add` and the point to create it from;
+    /// no start point means the branch already exists
+    pub(crate) fn resolve(&self, task_name: &str) -> Result<(String, Option<String>)> {
+        let branch_name = format!("feature-{}", task_name);
+        match self {
+            BranchStrategy::NewFromCurrent => Ok((branch_name, Some("HEAD".to_string()))),
+            BranchStrategy::NewFromMain => {
+                Process::git()
+                    .args(["fetch", "origin"])
+                    .output()
+                    .context("Failed to fetch from origin")?;
+                let default_branch = project_default_branch(load_config()?.default_branch.as_deref());
+                match default_branch_start_point(&default_branch) {
+                    Some(start_point) => Ok((branch_name, Some(start_point))),
+                    None => anyhow::bail!(
+                        "Default branch '{}' not found locally or on origin. Set it with 'stacks config set default-branch <branch>'",
+                        default_branch
+                    ),
+                }
+            }
+            BranchStrategy::ExistingBranch(branch) => Ok((branch.clone(), None)),
+            BranchStrategy::NewFromRemote(remote_branch) => Ok((branch_name, Some(remote_branch.clone()))),
+        }
+    }
+}
+
+/// Create a feature worktree and its tmux session, asking for the task and how to set it up
+///
+/// With `task` and no terminal, nothing is asked: the branch comes from the current one and the
+/// location, session and layout take their defaults.
+pub async fn run(task: Option<String>, permissions: Option<String>, sandbox: bool, container: bool, record: bool) -> Result<()> {
+    let permission_preset = permissions.as_deref().map(str::parse::<PermissionPreset>).transpose()?;
+    if task.is_none() {
+        require_interactive("stacks worktree", "Pass the task with --task <name>; everything else then takes its default")?;
+    }
+
+    require_all(&[Dependency::Git, Dependency::Tmux, Dependency::Claude])?;
     
     // Check if we're in a git repository
-    let git_status = Command::new("git")
-        .args(["status", "--porcelain"])
-        .output()
-        .context("Failed to check git status")?;
-
-    if !git_status.status.success() {
-        anyhow::bail!("Not in a git repository. Please run this command from a git repository.");
+    if !Process::git().args(["status", "--porcelain"]).read_only().succeeds() {
+        return Err(not_a_repository().into());
     }
 
     // Get current branch and repo info
     let current_branch = get_current_branch()?;
     let repo_name = get_repo_name()?;
     
-    println!("✅ Git repository detected (current branch: {})", current_branch);
+    outln!("✅ Git repository detected (current branch: {})", current_branch);
 
     // Load config and interactive configuration
     let app_config = load_config()?;
-    let config = gather_worktree_config(&current_branch, &repo_name, &app_config).await?;
+    let interactive = is_interactive();
+    let mut config = gather_worktree_config(task, interactive, &current_branch, &repo_name, &app_config).await?;
+    config.permission_preset = permission_preset;
+    config.sandbox = sandbox;
+    config.container = container;
+    config.record = record || app_config.record_transcripts;
     
     // Show configuration summary
-    println!("\n📋 Configuration Summary:");
-    println!("  Task: {}", config.task_name);
-    println!("  Branch Strategy: {:?}", config.branch_strategy);
-    println!("  Location: {}", config.location.display());
-    println!("  Tmux Session: {}", config.tmux_session);
-    println!("  Tmux Strategy: {}", config.tmux_strategy.description());
-
-    let should_proceed = Confirm::new()
-        .with_prompt("Proceed with worktree creation?")
-        .default(true)
-        .interact()?;
+    outln!("\n📋 Configuration Summary:");
+    outln!("  Task: {}", config.task_name);
+    outln!("  Branch Strategy: {:?}", config.branch_strategy);
+    outln!("  Location: {}", config.location.display());
+    outln!("  Tmux Session: {}", config.tmux_session);
+    outln!("  Tmux Strategy: {}", config.tmux_strategy.description());
+    if let Some(preset) = &config.permission_preset {
+        outln!("  Permissions: {} - {}", preset.as_str(), preset.description());
+    }
+    if config.sandbox {
+        outln!("  Sandbox: {}", app_config.sandbox_command.as_deref().unwrap_or(sandbox::DEFAULT_SANDBOX));
+    }
+    if config.container {
+        outln!("  Container: {}", app_config.container_image.as_deref().unwrap_or("from devcontainer.json"));
+    }
+    if config.record {
+        outln!("  Transcripts: .stacks/logs/{}/", config.task_name);
+    }
+
+    let should_proceed = match interactive {
+        true => Confirm::new()
+            .with_prompt("Proceed with worktree creation?")
+            .default(true)
+            .interact()?,
+        false => true,
+    };
 
     if !should_proceed {
-        println!("Worktree creation cancelled.");
+        outln!("Worktree creation cancelled.");
         return Ok(());
     }
 
     // Execute the worktree creation process
     let final_config = execute_worktree_creation(&config, &current_branch).await?;
 
-    println!("\n🎉 Worktree setup complete!");
+    outln!("\n🎉 Worktree setup complete!");
     
     // Show navigation command or provide options
     if let Some(nav_command) = &final_config.navigation_command {
-        println!("💡 Navigation: {}", nav_command);
+        outln!("💡 Navigation: {}", nav_command);
     } else {
         show_navigation_options(&final_config).await?;
     }
@@ -82,54 +184,34 @@ pub async fn run() -> Result<()> {
     Ok(())
 }
 
-async fn gather_worktree_config(current_branch: &str, repo_name: &str, app_config: &crate::config::StacksConfig) -> Result<WorktreeConfig> {
+async fn gather_worktree_config(
+    task: Option<String>,
+    interactive: bool,
+    current_branch: &str,
+    repo_name: &str,
+    app_config: &crate::config::StacksConfig,
+) -> Result<WorktreeConfig> {
     // Get task name
-    let raw_task_name: String = Input::new()
-        .with_prompt("Task name")
-        .interact_text()?;
-    
-    // Replace spaces with hyphens for safe usage in branch names, paths, and tmux sessions
-    let task_name = raw_task_name.replace(' ', "-");
+    let raw_task_name: String = match task {
+        Some(task) => task,
+        None => Input::new()
+            .with_prompt("Task name")
+            .interact_text()?,
+    };
     
-    // Show transformation if spaces were replaced
-    if task_name != raw_task_name {
-        println!("📝 Task name normalized: '{}' → '{}'", raw_task_name, task_name);
-    }
+    // Slugify for safe usage in branch names, paths, and tmux sessions
+    let task_name = task_name::sanitize_reporting(&raw_task_name)?;
+    let default_location = format!("../{}-{}", repo_name, task_name);
+    let default_session = format!("{}-{}", repo_name, task_name);
 
-    // Branch strategy selection
-    let branch_strategies = vec![
-        format!("Create new branch from current ({})", current_branch),
-        "Create new branch from main/master".to_string(),
-        "Use existing branch".to_string(),
-        "Create new branch from remote".to_string(),
-    ];
+    if !interactive {
+        outln!("🤖 No terminal to ask on; branching from {} with the default location and session", current_branch);
+        return Ok(WorktreeConfig::new(task_name, BranchStrategy::NewFromCurrent, PathBuf::from(default_location), default_session, app_config.tmux_strategy.clone()));
+    }
 
-    let branch_selection = Select::new()
-        .with_prompt("Select branch strategy")
-        .items(&branch_strategies)
-        .default(0)
-        .interact()?;
-
-    let branch_strategy = match branch_selection {
-        0 => BranchStrategy::NewFromCurrent,
-        1 => BranchStrategy::NewFromMain,
-        2 => {
-            let branch: String = Input::new()
-                .with_prompt("Existing branch name")
-                .interact_text()?;
-            BranchStrategy::ExistingBranch(branch)
-        }
-        3 => {
-            let remote_branch: String = Input::new()
-                .with_prompt("Remote branch name (e.g., origin/feature-branch)")
-                .interact_text()?;
-            BranchStrategy::NewFromRemote(remote_branch)
-        }
-        _ => unreachable!(),
-    };
+    let branch_strategy = select_branch_strategy(current_branch)?;
 
     // Worktree location suggestions
-    let default_location = format!("../{}-{}", repo_name, task_name);
     let location_options = vec![
         format!("{} (recommended)", default_location),
         format!("../worktrees/{}", task_name),
@@ -160,7 +242,6 @@ async fn gather_worktree_config(current_branch: &str, repo_name: &str, app_confi
     };
 
     // Tmux session configuration
-    let default_session = format!("{}-{}", repo_name, task_name);
     let tmux_session: String = Input::new()
         .with_prompt("Tmux session name")
         .default(default_session)
@@ -190,14 +271,44 @@ async fn gather_worktree_config(current_branch: &str, repo_name: &str, app_confi
         app_config.tmux_strategy.clone()
     };
 
-    Ok(WorktreeConfig {
-        task_name,
-        branch_strategy,
-        location,
-        tmux_session,
-        tmux_strategy,
-        navigation_command: None,
-    })
+    Ok(WorktreeConfig::new(task_name, branch_strategy, location, tmux_session, tmux_strategy))
+}
+
+/// Ask how the task's branch should be created
+pub(crate) fn select_branch_strategy(current_branch: &str) -> Result<BranchStrategy> {
+    // Branch strategy selection
+    let branch_strategies = vec![
+        format!("Create new branch from current ({})", current_branch),
+        "Create new branch from the default branch".to_string(),
+        "Use existing branch".to_string(),
+        "Create new branch from remote".to_string(),
+    ];
+
+    let branch_selection = Select::new()
+        .with_prompt("Select branch strategy")
+        .items(&branch_strategies)
+        .default(0)
+        .interact()?;
+
+    let branch_strategy = match branch_selection {
+        0 => BranchStrategy::NewFromCurrent,
+        1 => BranchStrategy::NewFromMain,
+        2 => {
+            let branch: String = Input::new()
+                .with_prompt("Existing branch name")
+                .interact_text()?;
+            BranchStrategy::ExistingBranch(branch)
+        }
+        3 => {
+            let remote_branch: String = Input::new()
+                .with_prompt("Remote branch name (e.g., origin/feature-branch)")
+                .interact_text()?;
+            BranchStrategy::NewFromRemote(remote_branch)
+        }
+        _ => unreachable!(),
+    };
+
+    Ok(branch_strategy)
 }
 
 async fn execute_worktree_creation(config: &WorktreeConfig, current_branch: &str) -> Result<WorktreeConfig> {
@@ -208,103 +319,124 @@ async fn execute_worktree_creation(config: &WorktreeConfig, current_branch: &str
             
             // Check if we're already on the target branch
             if current_branch == target_branch_name {
-                println!("📍 Already on target branch {}, using it for worktree...", target_branch_name);
+                outln!("📍 Already on target branch {}, using it for worktree...", target_branch_name);
                 target_branch_name
             } else {
-                println!("🌱 Creating branch {} from current branch...", target_branch_name);
+                outln!("🌱 Creating branch {} from current branch...", target_branch_name);
                 
-                let output = Command::new("git")
+                Process::git()
                     .args(["checkout", "-b", &target_branch_name])
-                    .output()
+                    .run()
                     .context("Failed to create new branch")?;
 
-                if !output.status.success() {
-                    let error = String::from_utf8_lossy(&output.stderr);
-                    anyhow::bail!("Failed to create branch: {}", error);
-                }
-
                 target_branch_name
             }
         }
         BranchStrategy::NewFromMain => {
             let branch_name = format!("feature-{}", config.task_name);
-            println!("🌱 Creating branch {} from main/master...", branch_name);
             
             // First, fetch latest changes
-            Command::new("git")
+            Process::git()
                 .args(["fetch", "origin"])
                 .output()
                 .context("Failed to fetch from origin")?;
 
-            // Try main first, then master
-            let main_branch = if branch_exists("origin/main") {
-                "origin/main"
-            } else if branch_exists("origin/master") {
-                "origin/master"
-            } else {
-                anyhow::bail!("Neither origin/main nor origin/master found");
+            let default_branch = project_default_branch(load_config()?.default_branch.as_deref());
+            let start_point = match default_branch_start_point(&default_branch) {
+                Some(start_point) => start_point,
+                None => anyhow::bail!(
+                    "Default branch '{}' not found locally or on origin. Set it with 'stacks config set default-branch <branch>'",
+                    default_branch
+                ),
             };
+            outln!("🌱 Creating branch {} from {}...", branch_name, start_point);
 
-            let output = Command::new("git")
-                .args(["checkout", "-b", &branch_name, main_branch])
-                .output()
+            Process::git()
+                .args(["checkout", "-b", &branch_name, &start_point])
+                .run()
                 .context("Failed to create branch from main")?;
 
-            if !output.status.success() {
-                let error = String::from_utf8_lossy(&output.stderr);
-                anyhow::bail!("Failed to create branch: {}", error);
-            }
-
             branch_name
         }
         BranchStrategy::ExistingBranch(branch) => {
-            println!("🔄 Using existing branch {}...", branch);
+            outln!("🔄 Using existing branch {}...", branch);
             branch.clone()
         }
         BranchStrategy::NewFromRemote(remote_branch) => {
             let branch_name = format!("feature-{}", config.task_name);
-            println!("🌱 Creating branch {} from {}...", branch_name, remote_branch);
+            outln!("🌱 Creating branch {} from {}...", branch_name, remote_branch);
             
-            let output = Command::new("git")
+            Process::git()
                 .args(["checkout", "-b", &branch_name, remote_branch])
-                .output()
+                .run()
                 .context("Failed to create branch from remote")?;
 
-            if !output.status.success() {
-                let error = String::from_utf8_lossy(&output.stderr);
-                anyhow::bail!("Failed to create branch: {}", error);
-            }
-
             branch_name
         }
     };
 
     // Create the worktree
-    println!("🏗️ Creating worktree at {}...", config.location.display());
-    let output = Command::new("git")
-        .args(["worktree", "add", config.location.to_str().unwrap(), &branch_name])
-        .output()
-        .context("Failed to create git worktree")?;
-
-    if !output.status.success() {
-        let error = String::from_utf8_lossy(&output.stderr);
-        anyhow::bail!("Failed to create worktree: {}", error);
+    outln!("🏗️ Creating worktree at {}...", config.location.display());
+    let worktree_add = Process::git().args(["worktree", "add", config.location.to_str().unwrap(), &branch_name]);
+    let output = worktree_add.output().context("Failed to create git worktree")?;
+
+    if !output.success() {
+        return Err(StacksError::git("Failed to create worktree")
+            .with_command(worktree_add.describe(), output.stderr)
+            .with_hint("Pick another branch or location, or remove the stale worktree with `git worktree prune`")
+            .into());
     }
 
     // Set up automatic permissions for the feature branch
     setup_feature_permissions(config).await?;
+    describe_worktree_scope(&config.task_name, &branch_name, &config.location);
+
+    let mut config = config.clone();
+    let app_config = load_config()?;
+    config.pane_commands = (0..CLAUDE_PANES)
+        .map(|index| app_config.launch.command_for_pane(&config.claude_command, index))
+        .collect::<Result<_>>()?;
+    if config.sandbox {
+        config.pane_commands = config.pane_commands
+            .iter()
+            .map(|command| sandbox::wrap_command(app_config.sandbox_command.as_deref(), &config.location, command))
+            .collect::<Result<_>>()?;
+        outln!("🔒 Claude will run sandboxed to {}", config.location.display());
+    }
+    if config.container {
+        let image = container::resolve_image(&config.location, app_config.container_image.as_deref())?;
+        let main_checkout = std::env::current_dir()?;
+        config.pane_commands = config.pane_commands
+            .iter()
+            .enumerate()
+            .map(|(index, command)| {
+                let name = format!("stacks-{}-{}", config.task_name, index + 1);
+                container::wrap_command(&image, &name, &config.location, &main_checkout, command)
+            })
+            .collect::<Result<_>>()?;
+        outln!("📦 Claude will run in {} containers with only {} writable", image, config.location.display());
+    }
+    let config = &config;
 
     // Check if user is already in tmux
     let in_tmux = is_in_tmux()?;
     
     // Set up tmux session with selected strategy
-    println!("🖥️ Setting up tmux session {} with {} strategy{}...", 
+    outln!("🖥️ Setting up tmux session {} with {} strategy{}...", 
         config.tmux_session, 
         config.tmux_strategy.as_str(),
         if in_tmux { " (already in tmux)" } else { "" }
     );
     
     let nav_command = setup_tmux_session(config, in_tmux).await?;
+
+    if config.record {
+        match transcripts::start_recording(&std::env::current_dir()?, &config.task_name, &config.location) {
+            Ok(0) => outln!("⚠️ No claude panes found to record"),
+            Ok(panes) => outln!("📼 Recording {} pane(s) to .stacks/logs/{}/", panes, config.task_name),
+            Err(error) => outln!("⚠️ Failed to start recording transcripts: {:#}", error),
+        }
+    }
     
     let mut result_config = config.clone();
     result_config.navigation_command = nav_command;
@@ -313,7 +445,17 @@ async fn execute_worktree_creation(config: &WorktreeConfig, current_branch: &str
 }
 
 fn is_in_tmux() -> Result<bool> {
-    Ok(std::env::var("TMUX").is_ok())
+    if std::env::var("TMUX").is_err() {
+        return Ok(false);
+    }
+    // $TMUX outlives its server, e.g. in a shell restored after tmux was killed
+    match tmux_server_running() {
+        true => Ok(true),
+        false => {
+            outln!("⚠️ $TMUX is set but its tmux server isn't running; creating a new session instead");
+            Ok(false)
+        }
+    }
 }
 
 fn get_current_tmux_session() -> Result<Option<String>> {
@@ -321,17 +463,14 @@ fn get_current_tmux_session() -> Result<Option<String>> {
         return Ok(None);
     }
     
-    let output = Command::new("tmux")
+    let output = Process::tmux()
         .args(["display-message", "-p", "#S"])
+        .read_only()
         .output()
         .context("Failed to get current tmux session")?;
         
-    if output.status.success() {
-        let session = String::from_utf8(output.stdout)
-            .context("Invalid UTF-8 in session name")?
-            .trim()
-            .to_string();
-        Ok(Some(session))
+    if output.success() {
+        Ok(Some(output.stdout.trim().to_string()))
     } else {
         Ok(None)
     }
@@ -354,6 +493,9 @@ async fn setup_tmux_session(config: &WorktreeConfig, in_tmux: bool) -> Result<Op
             InTmuxBehavior::NewSession => {
                 // Create new session as normal
             }
+            InTmuxBehavior::Ask if !is_interactive() => {
+                // Nobody to ask; a new session leaves the current one as it is
+            }
             InTmuxBehavior::Ask => {
                 // Ask user what to do
                 let options = vec![
@@ -375,25 +517,25 @@ async fn setup_tmux_session(config: &WorktreeConfig, in_tmux: bool) -> Result<Op
     }
 
     // Check if target session already exists
-    let session_exists = Command::new("tmux")
+    let session_exists = Process::tmux()
         .args(["has-session", "-t", &config.tmux_session])
-        .output()
-        .map(|output| output.status.success())
-        .unwrap_or(false);
+        .read_only()
+        .succeeds();
 
     if session_exists {
-        let should_kill = Confirm::new()
+        let should_kill = is_interactive() && Confirm::new()
             .with_prompt(format!("Tmux session '{}' already exists. Kill and recreate?", config.tmux_session))
             .default(false)
             .interact()?;
 
         if should_kill {
-            Command::new("tmux")
+            Process::tmux()
                 .args(["kill-session", "-t", &config.tmux_session])
-                .output()
+                .run()
                 .context("Failed to kill existing tmux session")?;
         } else {
-            println!("Using existing tmux session.");
+            outln!("Using existing tmux session.");
+            record_session(&config.task_name, &config.tmux_session, &worktree_path);
             let nav_cmd = if in_tmux {
                 format!("tmux switch-client -t {}", config.tmux_session)
             } else {
@@ -438,24 +580,28 @@ async fn setup_tmux_session(config: &WorktreeConfig, in_tmux: bool) -> Result<Op
             }
         }
     };
+    label_target(&config.tmux_session, &task_label(&config.task_name, &worktree_path));
+    record_session(&config.task_name, &config.tmux_session, &worktree_path);
 
     Ok(Some(nav_cmd))
 }
 
 async fn setup_in_existing_session(config: &WorktreeConfig, worktree_path: &PathBuf, current_session: &str) -> Result<Option<String>> {
     // Find next available window number
-    let output = Command::new("tmux")
+    let output = Process::tmux()
         .args(["list-windows", "-t", current_session, "-F", "#{window_index}"])
-        .output()
+        .read_only()
+        .run()
         .context("Failed to list tmux windows")?;
     
-    let existing_windows: Vec<u32> = String::from_utf8(output.stdout)
-        .context("Invalid UTF-8 in window list")?
+    let existing_windows: Vec<u32> = output.stdout
         .lines()
         .filter_map(|line| line.parse().ok())
         .collect();
     
     let start_window = existing_windows.iter().max().unwrap_or(&0) + 1;
+    record_session(&config.task_name, &format!("{}:{}", current_session, start_window), worktree_path);
+    let label = task_label(&config.task_name, worktree_path);
     
     match config.tmux_strategy {
         TmuxStrategy::SeparateSessions | TmuxStrategy::MultipleWindows => {
@@ -466,18 +612,19 @@ async fn setup_in_existing_session(config: &WorktreeConfig, worktree_path: &Path
                 let target = format!("{}:{}", current_session, window_num);
                 let context_msg = format!("Failed to create window {}", window_num);
                 
-                Command::new("tmux")
+                Process::tmux()
                     .args([
                         "new-window", "-t", &target,
                         "-n", &window_name,
                         "-c", worktree_path.to_str().unwrap(),
-                        "claude", "--permission-mode", "acceptEdits"
+                        config.pane_command(i as usize)
                     ])
-                    .output()
+                    .run()
                     .context(context_msg)?;
+                label_target(&target, &label);
             }
             
-            println!("  ✅ Created 4 new windows in current session '{}'", current_session);
+            outln!("  ✅ Created 4 new windows in current session '{}'", current_session);
             Ok(Some(format!("tmux select-window -t {}:{}", current_session, start_window)))
         }
         TmuxStrategy::QuadSplit => {
@@ -486,49 +633,50 @@ async fn setup_in_existing_session(config: &WorktreeConfig, worktree_path: &Path
             let window_name = format!("{}-quad", config.task_name);
             
             // Create window with first pane
-            Command::new("tmux")
+            Process::tmux()
                 .args([
                     "new-window", "-t", &format!("{}:{}", current_session, window_num),
                     "-n", &window_name,
                     "-c", worktree_path.to_str().unwrap(),
-                    "claude", "--permission-mode", "acceptEdits"
+                    config.pane_command(0)
                 ])
-                .output()
+                .run()
                 .context("Failed to create quad split window")?;
                 
             let window_target = format!("{}:{}", current_session, window_num);
             
             // Split vertically first (left/right)
-            Command::new("tmux")
+            Process::tmux()
                 .args([
                     "split-window", "-h", "-t", &window_target,
                     "-c", worktree_path.to_str().unwrap(),
-                    "claude", "--permission-mode", "acceptEdits"
+                    config.pane_command(1)
                 ])
-                .output()
+                .run()
                 .context("Failed to split window vertically")?;
 
             // Split left pane horizontally (top/bottom)
-            Command::new("tmux")
+            Process::tmux()
                 .args([
                     "split-window", "-v", "-t", &format!("{}.0", window_target),
                     "-c", worktree_path.to_str().unwrap(),
-                    "claude", "--permission-mode", "acceptEdits"
+                    config.pane_command(2)
                 ])
-                .output()
+                .run()
                 .context("Failed to split left pane horizontally")?;
 
             // Split right pane horizontally (top/bottom)  
-            Command::new("tmux")
+            Process::tmux()
                 .args([
                     "split-window", "-v", "-t", &format!("{}.1", window_target),
                     "-c", worktree_path.to_str().unwrap(),
-                    "claude", "--permission-mode", "acceptEdits"
+                    config.pane_command(3)
                 ])
-                .output()
+                .run()
                 .context("Failed to split right pane horizontally")?;
                 
-            println!("  ✅ Created quad split window in current session '{}'", current_session);
+            label_target(&window_target, &label);
+            outln!("  ✅ Created quad split window in current session '{}'", current_session);
             Ok(Some(format!("tmux select-window -t {}", window_target)))
         }
         TmuxStrategy::HorizontalSplit => {
@@ -537,14 +685,14 @@ async fn setup_in_existing_session(config: &WorktreeConfig, worktree_path: &Path
             let window_name = format!("{}-horizontal", config.task_name);
             
             // Create window with first pane
-            Command::new("tmux")
+            Process::tmux()
                 .args([
                     "new-window", "-t", &format!("{}:{}", current_session, window_num),
                     "-n", &window_name,
                     "-c", worktree_path.to_str().unwrap(),
-                    "claude", "--permission-mode", "acceptEdits"
+                    config.pane_command(0)
                 ])
-                .output()
+                .run()
                 .context("Failed to create horizontal split window")?;
                 
             let window_target = format!("{}:{}", current_session, window_num);
@@ -552,304 +700,225 @@ async fn setup_in_existing_session(config: &WorktreeConfig, worktree_path: &Path
             // Create 3 more horizontal panes (4 total)
             for i in 1..4 {
                 let context_msg = format!("Failed to create pane {}", i);
-                Command::new("tmux")
+                Process::tmux()
                     .args([
                         "split-window", "-v", "-t", &window_target,
                         "-c", worktree_path.to_str().unwrap(),
-                        "claude", "--permission-mode", "acceptEdits"
+                        config.pane_command(i)
                     ])
-                    .output()
+                    .run()
                     .context(context_msg)?;
             }
             
-            println!("  ✅ Created horizontal split window in current session '{}'", current_session);
+            label_target(&window_target, &label);
+            outln!("  ✅ Created horizontal split window in current session '{}'", current_session);
             Ok(Some(format!("tmux select-window -t {}", window_target)))
         }
     }
 }
 
 async fn show_navigation_options(config: &WorktreeConfig) -> Result<()> {
-    // Get list of all sessions and windows
-    let output = Command::new("tmux")
-        .args(["list-sessions", "-F", "#{session_name}"])
-        .output()
-        .context("Failed to list tmux sessions")?;
-    
-    let sessions: Vec<String> = String::from_utf8(output.stdout)
-        .context("Invalid UTF-8 in session list")?
-        .lines()
-        .map(|s| s.to_string())
-        .collect();
-        
-    if sessions.is_empty() {
-        println!("💡 No tmux sessions available. Start with: tmux attach -t {}", config.tmux_session);
-        return Ok(());
-    }
-    
-    // Get all windows for all sessions
-    let mut navigation_options = Vec::new();
-    
-    for session in &sessions {
-        let output = Command::new("tmux")
-            .args(["list-windows", "-t", session, "-F", "#{session_name}:#{window_index} #{window_name}"])
-            .output()
-            .context("Failed to list windows")?;
-            
-        let windows: Vec<String> = String::from_utf8(output.stdout)
-            .context("Invalid UTF-8 in window list")?
-            .lines()
-            .map(|s| s.to_string())
-            .collect();
-            
-        navigation_options.extend(windows);
-    }
-    
-    if navigation_options.is_empty() {
-        println!("💡 Navigation: tmux attach -t {}", config.tmux_session);
-        return Ok(());
-    }
-    
-    // Use skim for fuzzy selection
-    use skim::prelude::*;
-    use std::io::Cursor;
-    
-    let options = navigation_options.join("\n");
-    let item_reader = SkimItemReader::default();
-    let items = item_reader.of_bufread(Cursor::new(options));
-    
-    let skim_options = SkimOptionsBuilder::default()
-        .height(Some("40%"))
-        .prompt(Some("Navigate to tmux window: "))
-        .build()
-        .unwrap();
-        
-    let selected_items = Skim::run_with(&skim_options, Some(items))
-        .map(|out| out.selected_items)
-        .unwrap_or_else(Vec::new);
-    
-    if let Some(item) = selected_items.first() {
-        let selected = item.output().to_string();
-        let target = selected.split(' ').next().unwrap_or(&selected);
-        
-        let nav_command = if is_in_tmux()? {
-            if target.contains(':') {
-                format!("tmux select-window -t {}", target)
-            } else {
-                format!("tmux switch-client -t {}", target)
-            }
-        } else {
-            if target.contains(':') {
-                let session = target.split(':').next().unwrap();
-                format!("tmux attach -t {} \\; select-window -t {}", session, target)
-            } else {
-                format!("tmux attach -t {}", target)
-            }
-        };
-        
-        println!("💡 Navigation: {}", nav_command);
-        
-        // Optionally execute the command
-        let should_navigate = Confirm::new()
-            .with_prompt("Execute navigation command now?")
-            .default(true)
-            .interact()?;
-            
-        if should_navigate {
-            let parts: Vec<&str> = nav_command.split(' ').collect();
-            if parts.len() >= 2 {
-                Command::new(parts[0])
-                    .args(&parts[1..])
-                    .status()
-                    .context("Failed to execute navigation command")?;
-            }
-        }
+    let should_navigate = is_interactive() && Confirm::new()
+        .with_prompt("Pick a tmux session to jump to now?")
+        .default(true)
+        .interact()?;
+
+    if should_navigate {
+        attach::run(Some(config.task_name.clone())).await
     } else {
-        println!("💡 Navigation: tmux attach -t {}", config.tmux_session);
+        outln!("💡 Navigation: stacks attach {}", config.task_name);
+        Ok(())
     }
-    
-    Ok(())
 }
 
 async fn setup_separate_sessions(config: &WorktreeConfig, worktree_path: &PathBuf) -> Result<()> {
     // Create session with first window in the worktree directory
-    Command::new("tmux")
+    Process::tmux()
         .args([
             "new-session", "-d", "-s", &config.tmux_session,
             "-c", worktree_path.to_str().unwrap()
         ])
-        .output()
+        .run()
         .context("Failed to create tmux session")?;
 
     // Split the window vertically and start Claude Code in the right pane
-    Command::new("tmux")
+    Process::tmux()
         .args([
             "split-window", "-h", "-t", &format!("{}:0", config.tmux_session),
             "-c", worktree_path.to_str().unwrap(),
-            "claude", "--permission-mode", "acceptEdits"
+            config.pane_command(0)
         ])
-        .output()
+        .run()
         .context("Failed to split tmux window and start Claude Code")?;
 
     // Select the left pane (development pane)
-    Command::new("tmux")
+    Process::tmux()
         .args(["select-pane", "-t", &format!("{}:0.0", config.tmux_session)])
-        .output()
+        .run()
         .context("Failed to select tmux pane")?;
 
-    println!("  ✅ Tmux session '{}' created with separate sessions layout", config.tmux_session);
+    outln!("  ✅ Tmux session '{}' created with separate sessions layout", config.tmux_session);
     Ok(())
 }
 
 async fn setup_quad_split(config: &WorktreeConfig, worktree_path: &PathBuf) -> Result<()> {
     // Create session with first window in the worktree directory
-    Command::new("tmux")
+    Process::tmux()
         .args([
             "new-session", "-d", "-s", &config.tmux_session,
             "-c", worktree_path.to_str().unwrap()
         ])
-        .output()
+        .run()
         .context("Failed to create tmux session")?;
 
     // Split vertically first (left/right)
-    Command::new("tmux")
+    Process::tmux()
         .args([
             "split-window", "-h", "-t", &format!("{}:0", config.tmux_session),
             "-c", worktree_path.to_str().unwrap(),
-            "claude", "--permission-mode", "acceptEdits"
+            config.pane_command(0)
         ])
-        .output()
+        .run()
         .context("Failed to split window vertically")?;
 
     // Split left pane horizontally (top/bottom)
-    Command::new("tmux")
+    Process::tmux()
         .args([
             "split-window", "-v", "-t", &format!("{}:0.0", config.tmux_session),
             "-c", worktree_path.to_str().unwrap(),
-            "claude", "--permission-mode", "acceptEdits"
+            config.pane_command(1)
         ])
-        .output()
+        .run()
         .context("Failed to split left pane horizontally")?;
 
     // Split right pane horizontally (top/bottom)
-    Command::new("tmux")
+    Process::tmux()
         .args([
             "split-window", "-v", "-t", &format!("{}:0.1", config.tmux_session),
             "-c", worktree_path.to_str().unwrap(),
-            "claude", "--permission-mode", "acceptEdits"
+            config.pane_command(2)
         ])
-        .output()
+        .run()
         .context("Failed to split right pane horizontally")?;
 
     // Select the first pane (top-left)
-    Command::new("tmux")
+    Process::tmux()
         .args(["select-pane", "-t", &format!("{}:0.0", config.tmux_session)])
-        .output()
+        .run()
         .context("Failed to select tmux pane")?;
 
-    println!("  ✅ Tmux session '{}' created with 2x2 quad split layout", config.tmux_session);
+    outln!("  ✅ Tmux session '{}' created with 2x2 quad split layout", config.tmux_session);
     Ok(())
 }
 
 async fn setup_horizontal_split(config: &WorktreeConfig, worktree_path: &PathBuf) -> Result<()> {
     // Create session with first window in the worktree directory
-    Command::new("tmux")
+    Process::tmux()
         .args([
             "new-session", "-d", "-s", &config.tmux_session,
             "-c", worktree_path.to_str().unwrap()
         ])
-        .output()
+        .run()
         .context("Failed to create tmux session")?;
 
     // Create 3 more horizontal panes (4 total)
     for i in 1..4 {
         let target = format!("{}:0", config.tmux_session);
         let context_msg = format!("Failed to create pane {}", i);
-        Command::new("tmux")
+        Process::tmux()
             .args([
                 "split-window", "-v", "-t", &target,
                 "-c", worktree_path.to_str().unwrap(),
-                "claude", "--permission-mode", "acceptEdits"
+                config.pane_command(i - 1)
             ])
-            .output()
+            .run()
             .context(context_msg)?;
     }
 
     // Select the first pane (top)
-    Command::new("tmux")
+    Process::tmux()
         .args(["select-pane", "-t", &format!("{}:0.0", config.tmux_session)])
-        .output()
+        .run()
         .context("Failed to select tmux pane")?;
 
-    println!("  ✅ Tmux session '{}' created with 4 horizontal panes layout", config.tmux_session);
+    outln!("  ✅ Tmux session '{}' created with 4 horizontal panes layout", config.tmux_session);
     Ok(())
 }
 
 async fn setup_multiple_windows(config: &WorktreeConfig, worktree_path: &PathBuf) -> Result<()> {
     // Create session with first window
-    Command::new("tmux")
+    Process::tmux()
         .args([
             "new-session", "-d", "-s", &config.tmux_session,
             "-c", worktree_path.to_str().unwrap(),
-            "claude", "--permission-mode", "acceptEdits"
+            config.pane_command(0)
         ])
-        .output()
+        .run()
         .context("Failed to create tmux session")?;
 
     // Create 3 more windows (4 total)
     for i in 1..4 {
         let target = format!("{}:{}", config.tmux_session, i);
         let context_msg = format!("Failed to create window {}", i);
-        Command::new("tmux")
+        Process::tmux()
             .args([
                 "new-window", "-t", &target,
                 "-c", worktree_path.to_str().unwrap(),
-                "claude", "--permission-mode", "acceptEdits"
+                config.pane_command(i)
             ])
-            .output()
+            .run()
             .context(context_msg)?;
     }
 
     // Select the first window
-    Command::new("tmux")
+    Process::tmux()
         .args(["select-window", "-t", &format!("{}:0", config.tmux_session)])
-        .output()
+        .run()
         .context("Failed to select tmux window")?;
 
-    println!("  ✅ Tmux session '{}' created with 4 windows layout", config.tmux_session);
+    outln!("  ✅ Tmux session '{}' created with 4 windows layout", config.tmux_session);
     Ok(())
 }
 
-fn get_current_branch() -> Result<String> {
-    let output = Command::new("git")
-        .args(["rev-parse", "--abbrev-ref", "HEAD"])
-        .output()
-        .context("Failed to get current branch")?;
-
-    if !output.status.success() {
-        anyhow::bail!("Failed to determine current branch");
+/// Tell every claude started in the worktree what it is for: a generated section in its
+/// CLAUDE.local.md with the task, branch, the main checkout it must not touch and how to clean up
+pub(crate) fn describe_worktree_scope(task_name: &str, branch: &str, worktree: &Path) {
+    let result = std::env::current_dir().map_err(anyhow::Error::from).and_then(|main_dir| {
+        let body = format!(
+            "## Worktree scope\n\n\
+             This is a git worktree for the task **{}** on branch `{}`.\n\n\
+             - Work only inside this worktree; commit to `{}`.\n\
+             - The main checkout at `{}` is read-only from here: don't edit, commit or run git commands there.\n\
+             - When the task is done, run `stacks cleanup` from the main checkout to push stack changes and tidy up the worktree.",
+            task_name,
+            branch,
+            branch,
+            main_dir.display()
+        );
+        ClaudeMdUpdater::for_checkout(worktree).set_worktree_scope(&body)
+    });
+    if let Err(error) = result {
+        outln!("⚠️ Could not describe the worktree scope in {}: {:#}", LOCAL_CLAUDE_MD, error);
     }
+}
 
-    let branch = String::from_utf8(output.stdout)
-        .context("Invalid UTF-8 in branch name")?
-        .trim()
-        .to_string();
+pub(crate) fn get_current_branch() -> Result<String> {
+    let output = Process::git()
+        .args(["rev-parse", "--abbrev-ref", "HEAD"])
+        .read_only()
+        .run()
+        .context("Failed to determine current branch")?;
 
-    Ok(branch)
+    Ok(output.stdout.trim().to_string())
 }
 
-fn get_repo_name() -> Result<String> {
-    let output = Command::new("git")
+pub(crate) fn get_repo_name() -> Result<String> {
+    let output = Process::git()
         .args(["rev-parse", "--show-toplevel"])
-        .output()
-        .context("Failed to get repository root")?;
-
-    if !output.status.success() {
-        anyhow::bail!("Failed to determine repository root");
-    }
-
-    let repo_path_string = String::from_utf8(output.stdout)
-        .context("Invalid UTF-8 in repository path")?;
-    let repo_path = repo_path_string.trim();
+        .read_only()
+        .run()
+        .context("Failed to determine repository root")?;
+    let repo_path = output.stdout.trim();
 
     let repo_name = std::path::Path::new(repo_path)
         .file_name()
@@ -860,17 +929,9 @@ fn get_repo_name() -> Result<String> {
     Ok(repo_name)
 }
 
-fn branch_exists(branch_name: &str) -> bool {
-    Command::new("git")
-        .args(["rev-parse", "--verify", branch_name])
-        .output()
-        .map(|output| output.status.success())
-        .unwrap_or(false)
-}
-
 /// Set up automatic permissions that protect the main directory while allowing full access to the feature directory
-async fn setup_feature_permissions(config: &WorktreeConfig) -> Result<()> {
-    println!("🛡️ Setting up automatic permissions for feature branch...");
+pub(crate) async fn setup_feature_permissions(config: &WorktreeConfig) -> Result<()> {
+    outln!("🛡️ Setting up automatic permissions for feature branch...");
     
     // Get the current working directory (main project directory)
     let current_dir = std::env::current_dir()
@@ -879,8 +940,19 @@ async fn setup_feature_permissions(config: &WorktreeConfig) -> Result<()> {
     // The feature directory is the worktree location
     let feature_dir = config.location.clone();
     
+    let app_config = load_config()?;
+
+    // Rules come from the --permissions preset, .stacks/permissions.toml, the configured template, or the built-in default
+    let permission_template = match config.permission_preset {
+        Some(preset) => preset.template(),
+        None => PermissionTemplate::resolve(&current_dir, app_config.permissions.template.clone())?,
+    };
+    
     // Create permission generator
-    let permission_generator = PermissionGenerator::new(current_dir.clone(), feature_dir);
+    let permission_generator = PermissionGenerator::new(current_dir.clone(), feature_dir)
+        .with_template(permission_template)
+        .with_protected_paths(protected_paths(&app_config.permissions.protected_paths))
+        .with_container(config.container);
     
     // Apply permissions to the feature directory's .claude/settings.local.json
     let feature_settings_path = config.location.join(".claude").join("settings.local.json");
@@ -888,17 +960,18 @@ async fn setup_feature_permissions(config: &WorktreeConfig) -> Result<()> {
     // Ensure the .claude directory exists in the feature directory
     let claude_dir = config.location.join(".claude");
     if !claude_dir.exists() {
-        tokio::fs::create_dir_all(&claude_dir).await
+        files::create_dir_all(&claude_dir)
             .with_context(|| format!("Failed to create .claude directory in {}", config.location.display()))?;
     }
     
     permission_generator.apply_to_local_settings(&feature_settings_path).await
         .context("Failed to apply feature permissions")?;
     
-    println!("  ✅ Permissions configured:");
-    println!("    • Full access to: {}", config.location.display());
-    println!("    • Read-only access to: {}", current_dir.display());
-    println!("    • Settings saved to: {}", feature_settings_path.display());
+    outln!("  ✅ Permissions configured:");
+    outln!("    • Full access to: {}", config.location.display());
+    outln!("    • Read-only access to: {}", current_dir.display());
+    outln!("    • Settings saved to: {}", feature_settings_path.display());
     
     Ok(())
-}
\ No newline at end of file
+}
+
@@ -1,16 +1,681 @@
-use std::path::PathBuf;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
 use serde::{Deserialize, Serialize};
 use anyhow::{Result, Context};
 use dirs::home_dir;
 
+use crate::core::hooks::HooksConfig;
+use crate::core::notifications::NotificationsConfig;
+use crate::core::launch::LaunchConfig;
+use crate::core::signatures::{SignaturePolicy, SignaturesConfig};
+use crate::core::permission_generator::PermissionTemplate;
+use crate::error::StacksError;
+use crate::utils::fuzzy::FuzzyFinder;
+
+/// Environment variable selecting the active profile (set by the global --profile flag)
+pub const PROFILE_ENV: &str = "STACKS_PROFILE";
+
+/// A parsed config with the path, modification time and size its file had when it was read
+type ParsedConfig = (PathBuf, Option<SystemTime>, u64, StacksConfig);
+
+/// The config file as last parsed in this process; commands load the config many times and
+/// parsing it each time adds up
+static PARSED_CONFIG: Mutex<Option<ParsedConfig>> = Mutex::new(None);
+
+/// Schema version written to config.toml; bump it and add a step to MIGRATIONS when the format changes
+pub const CONFIG_VERSION: u32 = 2;
+
 #[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
 pub struct StacksConfig {
+    pub version: u32,
     pub tmux_strategy: TmuxStrategy,
     pub prompt_for_strategy: bool,
     pub in_tmux_behavior: InTmuxBehavior,
+    /// Whether plain `stacks` creates a worktree and tmux session; when false it installs
+    /// stacks into the current checkout like `stacks checkout`
+    pub use_worktrees: bool,
+    /// Check once a day for stack and tool updates (opt-in)
+    #[serde(default)]
+    pub update_checks: bool,
+    /// Record each command's name, duration and error class for the maintainers (opt-in); see `stacks telemetry`
+    #[serde(default)]
+    pub telemetry: bool,
+    /// Stack registry as `owner/repo` or `owner/repo@branch`; the default registry when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub registry: Option<String>,
+    /// OAuth app `stacks auth login` signs in with; it must have device flow enabled
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub github_client_id: Option<String>,
+    /// URL (or path) of the organization policy every command is checked against
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub policy_url: Option<String>,
+    /// Registry mirror (directory or URL) written by `stacks registry mirror`; used instead of GitHub
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub registry_mirror: Option<String>,
+    /// Project default branch for new feature branches; detected from origin when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default_branch: Option<String>,
+    /// How stack instructions appear in CLAUDE.md; `.stacks/config.toml` can override it per project
+    #[serde(default)]
+    pub claude_md_mode: ClaudeMdMode,
+    /// Path of the template for a CLAUDE.md created from scratch; the built-in template when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub claude_md_template: Option<String>,
+    /// Sandbox for `stacks worktree --sandbox`: bwrap, firejail, or a command template
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sandbox_command: Option<String>,
+    /// Image `stacks worktree --container` runs claude in when the worktree has no devcontainer.json image
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub container_image: Option<String>,
+    /// Attempts for GitHub requests and git fetches before giving up on transient failures
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub network_retries: Option<u32>,
+    /// Size in megabytes the cache directory is kept under
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cache_max_mb: Option<u32>,
+    /// Panes the checkout wizard puts in one tmux window before it opens a new window
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub max_panes_per_window: Option<u32>,
+    /// Hours without activity after which `stacks gc` offers to close a task's tmux session
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub idle_session_hours: Option<u32>,
+    /// Fuzzy finder stack and session pickers use
+    #[serde(default)]
+    pub fuzzy_finder: FuzzyFinder,
+    /// Leave emoji and box drawing out of output, like --plain
+    #[serde(default)]
+    pub plain_output: bool,
+    /// Record each claude pane's output under `.stacks/logs/<task>/`
+    #[serde(default)]
+    pub record_transcripts: bool,
+    /// Add a Stop hook to the project's claude settings that runs `stacks push --quiet`
+    #[serde(default)]
+    pub auto_push: bool,
+    /// Permission rules written into feature worktrees
+    #[serde(default, skip_serializing_if = "PermissionsConfig::is_empty")]
+    pub permissions: PermissionsConfig,
+    /// Where to announce that a long checkout, pull or cleanup finished
+    #[serde(default, skip_serializing_if = "NotificationsConfig::is_empty")]
+    pub notifications: NotificationsConfig,
+    /// Which keys stacks must be signed with before they're checked out
+    #[serde(default, skip_serializing_if = "SignaturesConfig::is_empty")]
+    pub signatures: SignaturesConfig,
+    /// Model, permission mode and flags claude starts with in each tmux pane
+    #[serde(default, skip_serializing_if = "LaunchConfig::is_empty")]
+    pub launch: LaunchConfig,
+    /// Named bundles of stacks (`[groups]`), checked out together with `--group`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub groups: BTreeMap<String, Vec<String>>,
+    /// Named kickoff prompts for new worktrees (`[prompts]`), using {task} and {stacks}
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub prompts: BTreeMap<String, String>,
+    /// Command shorthands (`[aliases]`), e.g. `st = "status --porcelain"`
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub aliases: BTreeMap<String, String>,
+    /// Named profiles (`[profile.work]`) overriding the settings above
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub profile: BTreeMap<String, ProfileConfig>,
+    /// Keys this version doesn't know about, kept so saving never drops them
+    #[serde(flatten)]
+    pub extra: toml::Table,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+/// `[permissions]` section of config.toml
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct PermissionsConfig {
+    /// Custom allow/deny template (`[permissions.template]`); the built-in template when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub template: Option<PermissionTemplate>,
+    /// Scope stack Bash grants to the stack's scripts and record them so `stacks remove` can retract them
+    #[serde(default)]
+    pub scope_stack_grants: bool,
+    /// Extra paths every generated worktree denies writes to, besides ~/.ssh and the registry clone
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub protected_paths: Vec<String>,
+}
+
+impl PermissionsConfig {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Per-profile overrides; unset keys fall back to the top-level settings
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct ProfileConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tmux_strategy: Option<TmuxStrategy>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt_for_strategy: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub in_tmux_behavior: Option<InTmuxBehavior>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub update_checks: Option<bool>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub registry: Option<String>,
+}
+
+impl StacksConfig {
+    /// Settings with a profile's overrides applied on top of the top-level settings
+    pub fn with_profile(&self, name: &str) -> Result<StacksConfig> {
+        let profile = self.profile.get(name)
+            .with_context(|| format!("Unknown profile: {} (defined profiles: {})", name, self.profile_names()))?;
+
+        let mut effective = self.clone();
+        if let Some(tmux_strategy) = &profile.tmux_strategy {
+            effective.tmux_strategy = tmux_strategy.clone();
+        }
+        if let Some(prompt_for_strategy) = profile.prompt_for_strategy {
+            effective.prompt_for_strategy = prompt_for_strategy;
+        }
+        if let Some(in_tmux_behavior) = &profile.in_tmux_behavior {
+            effective.in_tmux_behavior = in_tmux_behavior.clone();
+        }
+        if let Some(update_checks) = profile.update_checks {
+            effective.update_checks = update_checks;
+        }
+        if profile.registry.is_some() {
+            effective.registry = profile.registry.clone();
+        }
+        Ok(effective)
+    }
+
+    /// Store the settings of `updated` that differ from the top-level ones as overrides of a profile
+    ///
+    /// Fails, leaving the config as it was, when `updated` also changes a setting profiles can't override.
+    pub fn set_profile_overrides(&mut self, name: &str, updated: &StacksConfig) -> Result<()> {
+        let existing = self.profile.get(name).cloned().unwrap_or_default();
+        let profile = ProfileConfig {
+            tmux_strategy: override_value(existing.tmux_strategy, &self.tmux_strategy, &updated.tmux_strategy),
+            prompt_for_strategy: override_value(existing.prompt_for_strategy, &self.prompt_for_strategy, &updated.prompt_for_strategy),
+            in_tmux_behavior: override_value(existing.in_tmux_behavior, &self.in_tmux_behavior, &updated.in_tmux_behavior),
+            update_checks: override_value(existing.update_checks, &self.update_checks, &updated.update_checks),
+            registry: override_value(existing.registry.map(Some), &self.registry, &updated.registry).flatten(),
+        };
+        let mut candidate = self.clone();
+        candidate.profile.insert(name.to_string(), profile);
+
+        // Whatever the profile can't hold would be silently lost on save
+        let mut stored = candidate.with_profile(name)?;
+        stored.profile = updated.profile.clone();
+        let lost: Vec<&str> = CONFIG_KEYS.iter()
+            .filter(|key| key.get(&stored) != key.get(updated))
+            .map(|key| key.name)
+            .collect();
+        let serialize = |config: &StacksConfig| toml::to_string(config).context("Failed to serialize config");
+        if !lost.is_empty() || serialize(&stored)? != serialize(updated)? {
+            let what = match lost.is_empty() {
+                true => "These settings".to_string(),
+                false => lost.join(", "),
+            };
+            return Err(StacksError::validation(format!("{} can't be set per profile (profile '{}' is active)", what, name))
+                .with_hint("Profiles override tmux-strategy, prompt-for-strategy, in-tmux-behavior, update-checks and registry; run without --profile to change the others")
+                .into());
+        }
+        *self = candidate;
+        Ok(())
+    }
+
+    fn profile_names(&self) -> String {
+        if self.profile.is_empty() {
+            "none".to_string()
+        } else {
+            self.profile.keys().cloned().collect::<Vec<_>>().join(", ")
+        }
+    }
+}
+
+/// Keep an existing override (updated to the new value), or add one when the value differs from the base
+fn override_value<T: Clone + PartialEq>(existing: Option<T>, base: &T, updated: &T) -> Option<T> {
+    if existing.is_some() || updated != base {
+        Some(updated.clone())
+    } else {
+        None
+    }
+}
+
+/// Type of a config value, used for validation and for listing allowed values
+pub enum ConfigValueKind {
+    Bool,
+    /// One of a fixed set of values, each with a description
+    Choice(fn() -> Vec<(&'static str, &'static str)>),
+    /// Free-form text; `default` or an empty value unsets it
+    OptionalText,
+    /// A whole number in the inclusive range; `default` or an empty value unsets it
+    Number(u32, u32),
+}
+
+/// A settable config key: its CLI name, description, type and accessors
+pub struct ConfigKey {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub kind: ConfigValueKind,
+    get: fn(&StacksConfig) -> String,
+    set: fn(&mut StacksConfig, &str) -> Result<()>,
+}
+
+impl ConfigKey {
+    pub fn get(&self, config: &StacksConfig) -> String {
+        (self.get)(config)
+    }
+
+    /// Validate and apply a value
+    pub fn set(&self, config: &mut StacksConfig, value: &str) -> Result<()> {
+        (self.set)(config, value)
+    }
+
+    /// Check a value without applying it anywhere
+    pub fn validate(&self, value: &str) -> Result<()> {
+        self.set(&mut StacksConfig::default(), value)
+    }
+
+    /// Allowed values for display, e.g. "true, false"
+    pub fn allowed_values(&self) -> String {
+        match &self.kind {
+            ConfigValueKind::Bool => "true, false".to_string(),
+            ConfigValueKind::Choice(options) => options().iter().map(|(value, _)| *value).collect::<Vec<_>>().join(", "),
+            ConfigValueKind::OptionalText => "any value, or 'default' to unset".to_string(),
+            ConfigValueKind::Number(min, max) => format!("{} to {}, or 'default' to unset", min, max),
+        }
+    }
+}
+
+fn parse_bool(value: &str) -> Result<bool> {
+    match value {
+        "true" | "on" | "yes" | "enabled" => Ok(true),
+        "false" | "off" | "no" | "disabled" => Ok(false),
+        _ => anyhow::bail!("Invalid boolean value: {} (expected true or false)", value),
+    }
+}
+
+fn optional_text(value: &str) -> Option<String> {
+    let value = value.trim();
+    if value.is_empty() || value == "default" {
+        None
+    } else {
+        Some(value.to_string())
+    }
+}
+
+fn optional_number(value: &str, min: u32, max: u32) -> Result<Option<u32>> {
+    let value = match optional_text(value) {
+        Some(value) => value,
+        None => return Ok(None),
+    };
+    match value.parse::<u32>() {
+        Ok(number) if (min..=max).contains(&number) => Ok(Some(number)),
+        _ => anyhow::bail!("Expected a number from {} to {}, got '{}'", min, max, value),
+    }
+}
+
+/// Every key accepted by `config get`/`config set`
+pub const CONFIG_KEYS: &[ConfigKey] = &[
+    ConfigKey {
+        name: "tmux-strategy",
+        description: "How tmux sessions are laid out for new worktrees",
+        kind: ConfigValueKind::Choice(|| TmuxStrategy::all().iter().map(|s| (s.as_str(), s.description())).collect()),
+        get: |config| config.tmux_strategy.as_str().to_string(),
+        set: |config, value| {
+            config.tmux_strategy = TmuxStrategy::from_str(value)?;
+            Ok(())
+        },
+    },
+    ConfigKey {
+        name: "prompt-strategy",
+        description: "Ask which tmux strategy to use each time",
+        kind: ConfigValueKind::Bool,
+        get: |config| config.prompt_for_strategy.to_string(),
+        set: |config, value| {
+            config.prompt_for_strategy = parse_bool(value)?;
+            Ok(())
+        },
+    },
+    ConfigKey {
+        name: "in-tmux-behavior",
+        description: "What to do when stacks runs inside an existing tmux session",
+        kind: ConfigValueKind::Choice(|| InTmuxBehavior::all().iter().map(|b| (b.as_str(), b.description())).collect()),
+        get: |config| config.in_tmux_behavior.as_str().to_string(),
+        set: |config, value| {
+            config.in_tmux_behavior = InTmuxBehavior::from_str(value)?;
+            Ok(())
+        },
+    },
+    ConfigKey {
+        name: "use-worktrees",
+        description: "Run the worktree and tmux wizard for plain 'stacks'; when false, install stacks into the current checkout",
+        kind: ConfigValueKind::Bool,
+        get: |config| config.use_worktrees.to_string(),
+        set: |config, value| {
+            config.use_worktrees = parse_bool(value)?;
+            Ok(())
+        },
+    },
+    ConfigKey {
+        name: "claude-md-mode",
+        description: "How stack instructions are added to CLAUDE.md",
+        kind: ConfigValueKind::Choice(|| ClaudeMdMode::all().iter().map(|m| (m.as_str(), m.description())).collect()),
+        get: |config| config.claude_md_mode.as_str().to_string(),
+        set: |config, value| {
+            config.claude_md_mode = value.parse()?;
+            Ok(())
+        },
+    },
+    ConfigKey {
+        name: "claude-md-template",
+        description: "Template file for a new CLAUDE.md, using {project}, {commands} and {stacks}",
+        kind: ConfigValueKind::OptionalText,
+        get: |config| config.claude_md_template.clone().unwrap_or_default(),
+        set: |config, value| {
+            config.claude_md_template = optional_text(value);
+            Ok(())
+        },
+    },
+    ConfigKey {
+        name: "update-checks",
+        description: "Check once a day for stack and tool updates",
+        kind: ConfigValueKind::Bool,
+        get: |config| config.update_checks.to_string(),
+        set: |config, value| {
+            config.update_checks = parse_bool(value)?;
+            Ok(())
+        },
+    },
+    ConfigKey {
+        name: "telemetry",
+        description: "Record command names, durations and error classes (no arguments or paths) for the maintainers",
+        kind: ConfigValueKind::Bool,
+        get: |config| config.telemetry.to_string(),
+        set: |config, value| {
+            config.telemetry = parse_bool(value)?;
+            Ok(())
+        },
+    },
+    ConfigKey {
+        name: "scope-stack-permissions",
+        description: "Record which stack granted each permission so 'stacks remove' can retract it",
+        kind: ConfigValueKind::Bool,
+        get: |config| config.permissions.scope_stack_grants.to_string(),
+        set: |config, value| {
+            config.permissions.scope_stack_grants = parse_bool(value)?;
+            Ok(())
+        },
+    },
+    ConfigKey {
+        name: "auto-push",
+        description: "Push stack edits upstream when a claude session stops (takes effect on the next checkout or repair)",
+        kind: ConfigValueKind::Bool,
+        get: |config| config.auto_push.to_string(),
+        set: |config, value| {
+            config.auto_push = parse_bool(value)?;
+            Ok(())
+        },
+    },
+    ConfigKey {
+        name: "registry",
+        description: "Stack registry as owner/repo or owner/repo@branch",
+        kind: ConfigValueKind::OptionalText,
+        get: |config| config.registry.clone().unwrap_or_default(),
+        set: |config, value| {
+            let registry = optional_text(value);
+            if let Some(spec) = &registry {
+                crate::core::remote_stack_manager::StackRepository::from_spec(spec)?;
+            }
+            config.registry = registry;
+            Ok(())
+        },
+    },
+    ConfigKey {
+        name: "github-client-id",
+        description: "Client ID of the GitHub OAuth app (with device flow enabled) used by 'stacks auth login'",
+        kind: ConfigValueKind::OptionalText,
+        get: |config| config.github_client_id.clone().unwrap_or_default(),
+        set: |config, value| {
+            config.github_client_id = optional_text(value);
+            Ok(())
+        },
+    },
+    ConfigKey {
+        name: "policy-url",
+        description: "URL or path of the organization policy (allowed registries, forbidden grants, required stacks)",
+        kind: ConfigValueKind::OptionalText,
+        get: |config| config.policy_url.clone().unwrap_or_default(),
+        set: |config, value| {
+            config.policy_url = optional_text(value);
+            Ok(())
+        },
+    },
+    ConfigKey {
+        name: "registry-mirror",
+        description: "Directory or URL of a registry mirror to discover and check out stacks from instead of GitHub",
+        kind: ConfigValueKind::OptionalText,
+        get: |config| config.registry_mirror.clone().unwrap_or_default(),
+        set: |config, value| {
+            config.registry_mirror = optional_text(value);
+            Ok(())
+        },
+    },
+    ConfigKey {
+        name: "default-branch",
+        description: "Branch new feature branches start from (detected from origin/HEAD when unset)",
+        kind: ConfigValueKind::OptionalText,
+        get: |config| config.default_branch.clone().unwrap_or_default(),
+        set: |config, value| {
+            config.default_branch = optional_text(value);
+            Ok(())
+        },
+    },
+    ConfigKey {
+        name: "sandbox-command",
+        description: "Sandbox for 'stacks worktree --sandbox': bwrap, firejail, or a template using {worktree}, {git_dir}, {home}, {claude_bin}, {claude_dir} and {command}",
+        kind: ConfigValueKind::OptionalText,
+        get: |config| config.sandbox_command.clone().unwrap_or_default(),
+        set: |config, value| {
+            let sandbox_command = optional_text(value);
+            if let Some(template) = &sandbox_command {
+                crate::core::sandbox::validate_template(template)?;
+            }
+            config.sandbox_command = sandbox_command;
+            Ok(())
+        },
+    },
+    ConfigKey {
+        name: "container-image",
+        description: "Image 'stacks worktree --container' runs claude in when the worktree has no devcontainer.json image",
+        kind: ConfigValueKind::OptionalText,
+        get: |config| config.container_image.clone().unwrap_or_default(),
+        set: |config, value| {
+            config.container_image = optional_text(value);
+            Ok(())
+        },
+    },
+    ConfigKey {
+        name: "network-retries",
+        description: "Attempts for GitHub requests and git fetches before a transient network failure is reported",
+        kind: ConfigValueKind::Number(1, 10),
+        get: |config| config.network_retries.map(|attempts| attempts.to_string()).unwrap_or_default(),
+        set: |config, value| {
+            config.network_retries = optional_number(value, 1, 10)?;
+            Ok(())
+        },
+    },
+    ConfigKey {
+        name: "max-panes-per-window",
+        description: "Claude panes the checkout wizard adds to a tmux window before opening a new one (4 by default)",
+        kind: ConfigValueKind::Number(1, 16),
+        get: |config| config.max_panes_per_window.map(|panes| panes.to_string()).unwrap_or_default(),
+        set: |config, value| {
+            config.max_panes_per_window = optional_number(value, 1, 16)?;
+            Ok(())
+        },
+    },
+    ConfigKey {
+        name: "idle-session-hours",
+        description: "Hours without tmux activity after which 'stacks gc' offers to close a task's session (24 by default)",
+        kind: ConfigValueKind::Number(1, 720),
+        get: |config| config.idle_session_hours.map(|hours| hours.to_string()).unwrap_or_default(),
+        set: |config, value| {
+            config.idle_session_hours = optional_number(value, 1, 720)?;
+            Ok(())
+        },
+    },
+    ConfigKey {
+        name: "fuzzy-finder",
+        description: "Fuzzy finder for picking stacks and sessions: the built-in one, or an installed fzf",
+        kind: ConfigValueKind::Choice(|| FuzzyFinder::all().iter().map(|f| (f.as_str(), f.description())).collect()),
+        get: |config| config.fuzzy_finder.as_str().to_string(),
+        set: |config, value| {
+            config.fuzzy_finder = value.parse()?;
+            Ok(())
+        },
+    },
+    ConfigKey {
+        name: "plain-output",
+        description: "Print without emoji and box drawing, like --plain (e.g. for screen readers or logs)",
+        kind: ConfigValueKind::Bool,
+        get: |config| config.plain_output.to_string(),
+        set: |config, value| {
+            config.plain_output = parse_bool(value)?;
+            Ok(())
+        },
+    },
+    ConfigKey {
+        name: "record-transcripts",
+        description: "Record the output of claude panes created by 'stacks worktree' (see 'stacks logs')",
+        kind: ConfigValueKind::Bool,
+        get: |config| config.record_transcripts.to_string(),
+        set: |config, value| {
+            config.record_transcripts = parse_bool(value)?;
+            Ok(())
+        },
+    },
+    ConfigKey {
+        name: "notify-desktop",
+        description: "Desktop notification when a long checkout, pull or cleanup finishes",
+        kind: ConfigValueKind::Bool,
+        get: |config| config.notifications.desktop.to_string(),
+        set: |config, value| {
+            config.notifications.desktop = parse_bool(value)?;
+            Ok(())
+        },
+    },
+    ConfigKey {
+        name: "notify-tmux",
+        description: "tmux message when a long checkout, pull or cleanup finishes",
+        kind: ConfigValueKind::Bool,
+        get: |config| config.notifications.tmux.to_string(),
+        set: |config, value| {
+            config.notifications.tmux = parse_bool(value)?;
+            Ok(())
+        },
+    },
+    ConfigKey {
+        name: "notify-webhook",
+        description: "URL that receives a JSON POST when a long checkout, pull or cleanup finishes",
+        kind: ConfigValueKind::OptionalText,
+        get: |config| config.notifications.webhook.clone().unwrap_or_default(),
+        set: |config, value| {
+            let webhook = optional_text(value);
+            if let Some(url) = &webhook {
+                if !url.starts_with("https://") && !url.starts_with("http://") {
+                    anyhow::bail!("Webhook must be an http(s) URL, got '{}'", url);
+                }
+            }
+            config.notifications.webhook = webhook;
+            Ok(())
+        },
+    },
+    ConfigKey {
+        name: "notify-after-seconds",
+        description: "Only notify about operations that took at least this long (default 30)",
+        kind: ConfigValueKind::Number(0, 86400),
+        get: |config| config.notifications.min_seconds.map(|seconds| seconds.to_string()).unwrap_or_default(),
+        set: |config, value| {
+            config.notifications.min_seconds = optional_number(value, 0, 86400)?;
+            Ok(())
+        },
+    },
+    ConfigKey {
+        name: "signature-policy",
+        description: "Whether checkout verifies that stacks are signed by a trusted key",
+        kind: ConfigValueKind::Choice(|| SignaturePolicy::all().iter().map(|p| (p.as_str(), p.description())).collect()),
+        get: |config| config.signatures.policy.as_str().to_string(),
+        set: |config, value| {
+            config.signatures.policy = value.parse()?;
+            Ok(())
+        },
+    },
+    ConfigKey {
+        name: "signature-trusted-keys",
+        description: "Comma-separated GPG key fingerprints trusted to sign stacks",
+        kind: ConfigValueKind::OptionalText,
+        get: |config| config.signatures.trusted_keys.join(","),
+        set: |config, value| {
+            config.signatures.trusted_keys = optional_text(value)
+                .map(|keys| keys.split(',').map(|key| key.trim().to_string()).filter(|key| !key.is_empty()).collect())
+                .unwrap_or_default();
+            Ok(())
+        },
+    },
+    ConfigKey {
+        name: "signature-allowed-signers",
+        description: "SSH allowed-signers file for stacks signed with SSH keys",
+        kind: ConfigValueKind::OptionalText,
+        get: |config| config.signatures.allowed_signers.clone().unwrap_or_default(),
+        set: |config, value| {
+            config.signatures.allowed_signers = optional_text(value);
+            Ok(())
+        },
+    },
+    ConfigKey {
+        name: "launch-panes",
+        description: "Comma-separated launch profile per claude pane, e.g. plan,build,build,build (profiles go in [launch.profiles.<name>])",
+        kind: ConfigValueKind::OptionalText,
+        get: |config| config.launch.panes.join(","),
+        set: |config, value| {
+            let panes: Vec<String> = optional_text(value)
+                .map(|panes| panes.split(',').map(|pane| pane.trim().to_string()).filter(|pane| !pane.is_empty()).collect())
+                .unwrap_or_default();
+            if let Some(unknown) = panes.iter().find(|pane| !config.launch.profiles.contains_key(*pane)) {
+                anyhow::bail!("Unknown launch profile '{}'; define it as [launch.profiles.{}] in the config file first", unknown, unknown);
+            }
+            config.launch.panes = panes;
+            Ok(())
+        },
+    },
+    ConfigKey {
+        name: "cache-max-mb",
+        description: "Size in megabytes above which the oldest cached files are removed",
+        kind: ConfigValueKind::Number(1, 10240),
+        get: |config| config.cache_max_mb.map(|megabytes| megabytes.to_string()).unwrap_or_default(),
+        set: |config, value| {
+            config.cache_max_mb = optional_number(value, 1, 10240)?;
+            Ok(())
+        },
+    },
+];
+
+/// Look up a config key by its CLI name
+pub fn find_key(name: &str) -> Result<&'static ConfigKey> {
+    CONFIG_KEYS.iter()
+        .find(|key| key.name == name)
+        .with_context(|| format!(
+            "Unknown config key: {}. Valid keys: {}",
+            name,
+            CONFIG_KEYS.iter().map(|key| key.name).collect::<Vec<_>>().join(", ")
+        ))
+}
+
+/// Profile selected via --profile or STACKS_PROFILE, if any
+pub fn active_profile() -> Option<String> {
+    std::env::var(PROFILE_ENV)
+        .ok()
+        .filter(|name| !name.trim().is_empty())
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub enum InTmuxBehavior {
     #[serde(rename = "new-windows")]
     NewWindows,
@@ -20,7 +685,16 @@ pub enum InTmuxBehavior {
     Ask,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub enum ClaudeMdMode {
+    #[default]
+    #[serde(rename = "import")]
+    Import,
+    #[serde(rename = "embed")]
+    Embed,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
 pub enum TmuxStrategy {
     #[serde(rename = "separate-sessions")]
     SeparateSessions,
@@ -35,14 +709,53 @@ pub enum TmuxStrategy {
 impl Default for StacksConfig {
     fn default() -> Self {
         Self {
+            version: CONFIG_VERSION,
             tmux_strategy: TmuxStrategy::SeparateSessions,
             prompt_for_strategy: false,
             in_tmux_behavior: InTmuxBehavior::NewWindows,
+            use_worktrees: true,
+            update_checks: false,
+            telemetry: false,
+            registry: None,
+            github_client_id: None,
+            policy_url: None,
+            registry_mirror: None,
+            default_branch: None,
+            claude_md_mode: ClaudeMdMode::default(),
+            claude_md_template: None,
+            sandbox_command: None,
+            container_image: None,
+            network_retries: None,
+            max_panes_per_window: None,
+            idle_session_hours: None,
+            cache_max_mb: None,
+            fuzzy_finder: FuzzyFinder::default(),
+            plain_output: false,
+            record_transcripts: false,
+            auto_push: false,
+            permissions: PermissionsConfig::default(),
+            notifications: NotificationsConfig::default(),
+            launch: LaunchConfig::default(),
+            signatures: SignaturesConfig::default(),
+            groups: BTreeMap::new(),
+            prompts: BTreeMap::new(),
+            aliases: BTreeMap::new(),
+            profile: BTreeMap::new(),
+            extra: toml::Table::new(),
         }
     }
 }
 
 impl TmuxStrategy {
+    pub fn all() -> [TmuxStrategy; 4] {
+        [
+            TmuxStrategy::SeparateSessions,
+            TmuxStrategy::QuadSplit,
+            TmuxStrategy::HorizontalSplit,
+            TmuxStrategy::MultipleWindows,
+        ]
+    }
+
     pub fn as_str(&self) -> &'static str {
         match self {
             TmuxStrategy::SeparateSessions => "separate-sessions",
@@ -62,6 +775,14 @@ impl TmuxStrategy {
         }
     }
 
+    /// tmux layout that keeps panes added to a window evenly sized
+    pub fn layout(&self) -> &'static str {
+        match self {
+            TmuxStrategy::HorizontalSplit => "even-vertical",
+            TmuxStrategy::SeparateSessions | TmuxStrategy::QuadSplit | TmuxStrategy::MultipleWindows => "tiled",
+        }
+    }
+
     pub fn description(&self) -> &'static str {
         match self {
             TmuxStrategy::SeparateSessions => "Separate tmux sessions (current default)",
@@ -80,7 +801,17 @@ pub fn get_config_path() -> Result<PathBuf> {
     Ok(config_dir.join("config.toml"))
 }
 
+/// Effective settings: the config file with the active profile applied
 pub fn load_config() -> Result<StacksConfig> {
+    let config = load_raw_config()?;
+    match active_profile() {
+        Some(name) => config.with_profile(&name),
+        None => Ok(config),
+    }
+}
+
+/// The config file as written, without applying a profile; use this when modifying and saving
+pub fn load_raw_config() -> Result<StacksConfig> {
     let config_path = get_config_path()?;
     
     if !config_path.exists() {
@@ -89,37 +820,186 @@ pub fn load_config() -> Result<StacksConfig> {
         return Ok(default_config);
     }
 
+    if let Some(config) = parsed_config(&config_path) {
+        return Ok(config);
+    }
+
     let content = std::fs::read_to_string(&config_path)
         .context("Failed to read config file")?;
     
-    // Try parsing as current config format first
-    if let Ok(config) = toml::from_str::<StacksConfig>(&content) {
-        return Ok(config);
+    // Never replace a config we can't read: the user's settings would be lost
+    let mut table: toml::Table = toml::from_str(&content)
+        .with_context(|| format!(
+            "Failed to parse {}. Fix the file or run 'stacks config reset'",
+            config_path.display()
+        ))?;
+    
+    let version = detect_version(&table);
+    if version > CONFIG_VERSION {
+        anyhow::bail!(
+            "{} was written by a newer version of stacks (config version {}, supported {}). Please upgrade stacks.",
+            config_path.display(), version, CONFIG_VERSION
+        );
     }
     
-    // If that fails, try parsing as legacy format and migrate
-    if let Ok(legacy_config) = toml::from_str::<LegacyConfig>(&content) {
-        println!("Migrating config to new format...");
-        let migrated_config = StacksConfig {
-            tmux_strategy: legacy_config.tmux_strategy,
-            prompt_for_strategy: legacy_config.prompt_for_strategy,
-            in_tmux_behavior: InTmuxBehavior::NewWindows, // Default for migration
-        };
-        save_config(&migrated_config)?;
-        return Ok(migrated_config);
+    if version < CONFIG_VERSION {
+        let backup_path = config_path.with_extension(format!("toml.v{}.bak", version));
+        std::fs::copy(&config_path, &backup_path)
+            .with_context(|| format!("Failed to back up config to {}", backup_path.display()))?;
+        
+        migrate(&mut table, version)?;
+        let migrated = toml::to_string_pretty(&table)
+            .context("Failed to serialize migrated config")?;
+        std::fs::write(&config_path, migrated)
+            .context("Failed to write migrated config file")?;
+        eoutln!(
+            "Migrated config from version {} to {} (backup: {})",
+            version, CONFIG_VERSION, backup_path.display()
+        );
     }
     
-    // If all parsing fails, create default config
-    println!("Config file format not recognized, creating new default config...");
-    let default_config = StacksConfig::default();
-    save_config(&default_config)?;
-    Ok(default_config)
+    let config: StacksConfig = toml::Value::Table(table).try_into()
+        .with_context(|| format!("Invalid settings in {}", config_path.display()))?;
+    remember_parsed_config(&config_path, &config);
+    Ok(config)
 }
 
-#[derive(Deserialize)]
-struct LegacyConfig {
-    pub tmux_strategy: TmuxStrategy,
-    pub prompt_for_strategy: bool,
+/// The config parsed earlier in this run, if the file hasn't changed since
+fn parsed_config(path: &Path) -> Option<StacksConfig> {
+    let metadata = std::fs::metadata(path).ok()?;
+    let parsed = PARSED_CONFIG.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    match &*parsed {
+        Some((parsed_path, modified, len, config))
+            if parsed_path == path && *modified == metadata.modified().ok() && *len == metadata.len() => Some(config.clone()),
+        _ => None,
+    }
+}
+
+fn remember_parsed_config(path: &Path, config: &StacksConfig) {
+    if let Ok(metadata) = std::fs::metadata(path) {
+        *PARSED_CONFIG.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) =
+            Some((path.to_path_buf(), metadata.modified().ok(), metadata.len(), config.clone()));
+    }
+}
+
+/// A migration step from version `from` to `from + 1`, operating on the raw TOML so unknown keys survive
+struct Migration {
+    from: u32,
+    description: &'static str,
+    apply: fn(&mut toml::Table),
+}
+
+const MIGRATIONS: &[Migration] = &[
+    Migration {
+        from: 0,
+        description: "add in_tmux_behavior",
+        apply: |table| {
+            table.entry("in_tmux_behavior")
+                .or_insert_with(|| toml::Value::String(InTmuxBehavior::NewWindows.as_str().to_string()));
+        },
+    },
+    Migration {
+        from: 1,
+        description: "add schema version",
+        apply: |_| {},
+    },
+];
+
+/// Config files before version 2 have no version key: version 0 predates in_tmux_behavior
+fn detect_version(table: &toml::Table) -> u32 {
+    match table.get("version").and_then(|version| version.as_integer()) {
+        Some(version) => version.max(0) as u32,
+        None if table.contains_key("in_tmux_behavior") => 1,
+        None => 0,
+    }
+}
+
+fn migrate(table: &mut toml::Table, from: u32) -> Result<()> {
+    for version in from..CONFIG_VERSION {
+        let migration = MIGRATIONS.iter()
+            .find(|migration| migration.from == version)
+            .with_context(|| format!("No config migration from version {}", version))?;
+        (migration.apply)(table);
+        table.insert("version".to_string(), toml::Value::Integer(i64::from(version + 1)));
+        eoutln!("  • config v{} → v{}: {}", version, version + 1, migration.description);
+    }
+    Ok(())
+}
+
+/// Config file contents to share with a team (`stacks config export`)
+pub fn export_config() -> Result<String> {
+    let config = load_raw_config()?;
+    toml::to_string_pretty(&config).context("Failed to serialize config")
+}
+
+/// Read shared settings from a file path or an http(s) URL
+pub async fn read_import_source(source: &str) -> Result<String> {
+    if source.starts_with("http://") || source.starts_with("https://") {
+        let response = reqwest::get(source)
+            .await
+            .with_context(|| format!("Failed to download {}", source))?;
+        if !response.status().is_success() {
+            anyhow::bail!("Failed to download {}: {}", source, response.status());
+        }
+        response.text().await.with_context(|| format!("Failed to read {}", source))
+    } else {
+        std::fs::read_to_string(source).with_context(|| format!("Failed to read {}", source))
+    }
+}
+
+/// Validate imported settings and merge them over the current config; returns the merged config
+/// and the keys that change
+pub fn merge_imported_config(current: &StacksConfig, content: &str) -> Result<(StacksConfig, Vec<String>)> {
+    let mut imported: toml::Table = toml::from_str(content)
+        .context("Imported settings are not valid TOML")?;
+    
+    // A shared snippet without a version key is current settings, not a pre-versioning config
+    // file, so the migrations that add defaults must not override the user's own values
+    let version = match imported.contains_key("version") {
+        true => detect_version(&imported),
+        false => CONFIG_VERSION,
+    };
+    if version > CONFIG_VERSION {
+        anyhow::bail!("Imported settings use config version {}, this stacks supports {}. Please upgrade stacks.", version, CONFIG_VERSION);
+    }
+    migrate(&mut imported, version)?;
+    
+    // Validate on its own first so errors point at the imported file
+    let _: StacksConfig = toml::Value::Table(imported.clone()).try_into()
+        .context("Imported settings are invalid")?;
+    
+    let mut merged = match toml::Value::try_from(current).context("Failed to serialize config")? {
+        toml::Value::Table(table) => table,
+        _ => toml::Table::new(),
+    };
+    let mut changed = Vec::new();
+    merge_table(&mut merged, imported, "", &mut changed);
+    
+    let merged: StacksConfig = toml::Value::Table(merged).try_into()
+        .context("Merged settings are invalid")?;
+    Ok((merged, changed))
+}
+
+/// Deep-merge `overlay` into `base`, recording the dotted paths of values that change
+fn merge_table(base: &mut toml::Table, overlay: toml::Table, prefix: &str, changed: &mut Vec<String>) {
+    for (key, value) in overlay {
+        let path = if prefix.is_empty() { key.clone() } else { format!("{}.{}", prefix, key) };
+        match (base.get_mut(&key), value) {
+            (Some(toml::Value::Table(base_table)), toml::Value::Table(overlay_table)) => {
+                merge_table(base_table, overlay_table, &path, changed);
+            }
+            (Some(existing), value) => {
+                if *existing != value && key != "version" {
+                    changed.push(path);
+                }
+                *existing = value;
+            }
+            (None, value) => {
+                changed.push(path);
+                base.insert(key, value);
+            }
+        }
+    }
 }
 
 pub fn save_config(config: &StacksConfig) -> Result<()> {
@@ -129,21 +1009,46 @@ pub fn save_config(config: &StacksConfig) -> Result<()> {
     
     std::fs::write(&config_path, content)
         .context("Failed to write config file")?;
+    // A write within the file system's timestamp resolution could look unchanged
+    *PARSED_CONFIG.lock().unwrap_or_else(|poisoned| poisoned.into_inner()) = None;
     
     Ok(())
 }
 
+/// Modify settings and save them; with an active profile, the changes go into that profile
+///
+/// Nothing is saved when `updater` fails, or when it changes settings the active profile can't override.
 pub fn update_config<F>(updater: F) -> Result<StacksConfig> 
 where
-    F: FnOnce(&mut StacksConfig),
+    F: FnOnce(&mut StacksConfig) -> Result<()>,
 {
-    let mut config = load_config()?;
-    updater(&mut config);
-    save_config(&config)?;
-    Ok(config)
+    let mut config = load_raw_config()?;
+    
+    match active_profile() {
+        Some(name) => {
+            let mut effective = if config.profile.contains_key(&name) {
+                config.with_profile(&name)?
+            } else {
+                config.clone()
+            };
+            updater(&mut effective)?;
+            config.set_profile_overrides(&name, &effective)?;
+            save_config(&config)?;
+            Ok(effective)
+        }
+        None => {
+            updater(&mut config)?;
+            save_config(&config)?;
+            Ok(config)
+        }
+    }
 }
 
 impl InTmuxBehavior {
+    pub fn all() -> [InTmuxBehavior; 3] {
+        [InTmuxBehavior::NewWindows, InTmuxBehavior::NewSession, InTmuxBehavior::Ask]
+    }
+
     pub fn as_str(&self) -> &'static str {
         match self {
             InTmuxBehavior::NewWindows => "new-windows",
@@ -168,4 +1073,328 @@ impl InTmuxBehavior {
             InTmuxBehavior::Ask => "Ask what to do when already in tmux",
         }
     }
-}
\ No newline at end of file
+}
+
+impl ClaudeMdMode {
+    pub fn all() -> [ClaudeMdMode; 2] {
+        [ClaudeMdMode::Import, ClaudeMdMode::Embed]
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ClaudeMdMode::Import => "import",
+            ClaudeMdMode::Embed => "embed",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            ClaudeMdMode::Import => "Reference each stack's CLAUDE.md with an @-import",
+            ClaudeMdMode::Embed => "Copy each stack's CLAUDE.md inline (refreshed on pull)",
+        }
+    }
+}
+
+impl std::str::FromStr for ClaudeMdMode {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "import" => Ok(ClaudeMdMode::Import),
+            "embed" => Ok(ClaudeMdMode::Embed),
+            _ => anyhow::bail!("Invalid CLAUDE.md mode: {}", s),
+        }
+    }
+}
+
+/// Project settings file, committed with the project and overriding the user config
+pub const PROJECT_CONFIG_FILE: &str = ".stacks/config.toml";
+
+/// Settings a project can pin in `.stacks/config.toml`
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct ProjectConfig {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub claude_md_mode: Option<ClaudeMdMode>,
+    /// Commands run before and after checkout, pull, push and cleanup
+    #[serde(default, skip_serializing_if = "HooksConfig::is_empty")]
+    pub hooks: HooksConfig,
+    /// Stack groups shared with everyone working on the project; they win over same-named user groups
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub groups: BTreeMap<String, Vec<String>>,
+}
+
+/// Load `.stacks/config.toml` from the current directory, or empty settings if it doesn't exist
+pub fn load_project_config() -> Result<ProjectConfig> {
+    let path = PathBuf::from(PROJECT_CONFIG_FILE);
+    if !path.exists() {
+        return Ok(ProjectConfig::default());
+    }
+
+    let content = std::fs::read_to_string(&path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    toml::from_str(&content)
+        .with_context(|| format!("Failed to parse {}", path.display()))
+}
+
+/// CLAUDE.md mode for the current project: `.stacks/config.toml`, then the user config
+pub fn claude_md_mode() -> Result<ClaudeMdMode> {
+    match load_project_config()?.claude_md_mode {
+        Some(mode) => Ok(mode),
+        None => Ok(load_config()?.claude_md_mode),
+    }
+}
+
+/// Stack groups from the user config and `.stacks/config.toml`, the project's taking precedence
+pub fn stack_groups() -> Result<BTreeMap<String, Vec<String>>> {
+    let mut groups = load_config()?.groups;
+    groups.extend(load_project_config()?.groups);
+    Ok(groups)
+}
+
+/// Member stacks of the named groups, in order and without duplicates
+pub fn expand_groups(groups: &BTreeMap<String, Vec<String>>, names: &[String]) -> Result<Vec<String>> {
+    let mut stacks: Vec<String> = Vec::new();
+    for name in names {
+        let members = groups.get(name).with_context(|| format!(
+            "Unknown stack group: {} (defined groups: {})",
+            name,
+            if groups.is_empty() { "none".to_string() } else { groups.keys().cloned().collect::<Vec<_>>().join(", ") }
+        ))?;
+        for member in members {
+            if !stacks.contains(member) {
+                stacks.push(member.clone());
+            }
+        }
+    }
+    Ok(stacks)
+}
+
+/// Replace an alias used as the command with its expansion, keeping the arguments around it
+///
+/// The command is the first argument that isn't an option or an option's value (`value_options`
+/// lists the options that take one). Built-in commands win over aliases of the same name, and
+/// expansions aren't expanded again.
+pub fn expand_alias(
+    args: Vec<String>,
+    aliases: &BTreeMap<String, String>,
+    commands: &[String],
+    value_options: &[String],
+) -> Result<Vec<String>> {
+    let mut index = 1;
+    while let Some(arg) = args.get(index) {
+        match arg.starts_with('-') {
+            true if value_options.contains(arg) => index += 2,
+            true => index += 1,
+            false => break,
+        }
+    }
+    let expansion = match args.get(index) {
+        Some(name) if !commands.contains(name) => match aliases.get(name) {
+            Some(expansion) => expansion,
+            None => return Ok(args),
+        },
+        _ => return Ok(args),
+    };
+
+    let words = split_words(expansion)
+        .with_context(|| format!("Invalid alias {} = \"{}\"", args[index], expansion))?;
+    let mut expanded = args[..index].to_vec();
+    expanded.extend(words);
+    expanded.extend(args[index + 1..].iter().cloned());
+    Ok(expanded)
+}
+
+/// Split on whitespace, keeping single- or double-quoted text together
+fn split_words(text: &str) -> Result<Vec<String>> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut quote: Option<char> = None;
+    for c in text.chars() {
+        match (quote, c) {
+            (Some(open), c) if c == open => quote = None,
+            (Some(_), c) => word.get_or_insert_with(String::new).push(c),
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                word.get_or_insert_with(String::new);
+            }
+            (None, c) if c.is_whitespace() => words.extend(word.take()),
+            (None, c) => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    if quote.is_some() {
+        anyhow::bail!("unterminated quote");
+    }
+    words.extend(word);
+    Ok(words)
+}
+
+/// Content of the configured CLAUDE.md template, if one is set
+pub fn claude_md_template() -> Result<Option<String>> {
+    let path = match load_config()?.claude_md_template {
+        Some(path) => path,
+        None => return Ok(None),
+    };
+
+    let path = match (path.strip_prefix("~/"), home_dir()) {
+        (Some(rest), Some(home)) => home.join(rest),
+        _ => PathBuf::from(&path),
+    };
+    std::fs::read_to_string(&path)
+        .map(Some)
+        .with_context(|| format!("Failed to read CLAUDE.md template {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_profile_falls_back_to_top_level() {
+        let content = r#"
+tmux_strategy = "separate-sessions"
+prompt_for_strategy = false
+in_tmux_behavior = "new-windows"
+
+[profile.work]
+tmux_strategy = "quad-split"
+registry = "acme/stacks"
+"#;
+        let config: StacksConfig = toml::from_str(content).unwrap();
+        let work = config.with_profile("work").unwrap();
+
+        assert_eq!(work.tmux_strategy, TmuxStrategy::QuadSplit);
+        assert_eq!(work.registry.as_deref(), Some("acme/stacks"));
+        assert_eq!(work.in_tmux_behavior, InTmuxBehavior::NewWindows);
+        assert!(config.with_profile("personal").is_err());
+    }
+
+    #[test]
+    fn test_expand_alias() {
+        let aliases = BTreeMap::from([
+            ("st".to_string(), "status --porcelain".to_string()),
+            ("wip".to_string(), "push -m 'work in progress'".to_string()),
+            ("status".to_string(), "pull".to_string()),
+        ]);
+        let commands = vec!["status".to_string(), "push".to_string()];
+        let value_options = vec!["--profile".to_string()];
+        let expand = |args: &[&str]| {
+            let args = args.iter().map(|arg| arg.to_string()).collect();
+            expand_alias(args, &aliases, &commands, &value_options).unwrap()
+        };
+
+        assert_eq!(expand(&["stacks", "st", "-w"]), vec!["stacks", "status", "--porcelain", "-w"]);
+        assert_eq!(expand(&["stacks", "--profile", "st", "wip"]), vec!["stacks", "--profile", "st", "push", "-m", "work in progress"]);
+        assert_eq!(expand(&["stacks", "-v", "status"]), vec!["stacks", "-v", "status"]);
+        assert_eq!(expand(&["stacks", "checkout", "st"]), vec!["stacks", "checkout", "st"]);
+        assert_eq!(expand(&["stacks"]), vec!["stacks"]);
+        assert!(expand_alias(vec!["stacks".into(), "bad".into()], &BTreeMap::from([("bad".to_string(), "push -m 'oops".to_string())]), &commands, &value_options).is_err());
+    }
+
+    #[test]
+    fn test_config_keys_validate_values() {
+        let mut config = StacksConfig::default();
+
+        find_key("tmux-strategy").unwrap().set(&mut config, "quad-split").unwrap();
+        assert_eq!(config.tmux_strategy, TmuxStrategy::QuadSplit);
+        assert_eq!(find_key("tmux-strategy").unwrap().get(&config), "quad-split");
+
+        assert!(find_key("update-checks").unwrap().validate("maybe").is_err());
+        assert!(find_key("registry").unwrap().validate("not-a-registry").is_err());
+        assert!(find_key("no-such-key").is_err());
+
+        find_key("registry").unwrap().set(&mut config, "acme/stacks").unwrap();
+        find_key("registry").unwrap().set(&mut config, "default").unwrap();
+        assert_eq!(config.registry, None);
+
+        find_key("network-retries").unwrap().set(&mut config, "5").unwrap();
+        assert_eq!(config.network_retries, Some(5));
+        assert!(find_key("network-retries").unwrap().validate("0").is_err());
+        assert!(find_key("network-retries").unwrap().validate("many").is_err());
+    }
+
+    #[test]
+    fn test_migrate_legacy_config_keeps_unknown_keys() {
+        let mut table: toml::Table = toml::from_str(r#"
+tmux_strategy = "quad-split"
+prompt_for_strategy = true
+custom_key = "kept"
+"#).unwrap();
+
+        assert_eq!(detect_version(&table), 0);
+        migrate(&mut table, 0).unwrap();
+        assert_eq!(detect_version(&table), CONFIG_VERSION);
+
+        let config: StacksConfig = toml::Value::Table(table).try_into().unwrap();
+        assert_eq!(config.tmux_strategy, TmuxStrategy::QuadSplit);
+        assert_eq!(config.in_tmux_behavior, InTmuxBehavior::NewWindows);
+        assert_eq!(config.extra.get("custom_key").and_then(|v| v.as_str()), Some("kept"));
+
+        let saved = toml::to_string_pretty(&config).unwrap();
+        assert!(saved.contains("custom_key = \"kept\""));
+        assert!(saved.contains("version = 2"));
+    }
+
+    #[test]
+    fn test_merge_imported_config() {
+        let current = StacksConfig::default();
+        let imported = r#"
+registry = "acme/stacks"
+tmux_strategy = "separate-sessions"
+
+[profile.work]
+tmux_strategy = "quad-split"
+"#;
+
+        let (merged, changed) = merge_imported_config(&current, imported).unwrap();
+        assert_eq!(merged.registry.as_deref(), Some("acme/stacks"));
+        assert_eq!(merged.profile["work"].tmux_strategy, Some(TmuxStrategy::QuadSplit));
+        let mut changed = changed;
+        changed.sort();
+        assert_eq!(changed, vec!["profile", "registry"]);
+
+        assert!(merge_imported_config(&current, "tmux_strategy = \"bogus\"").is_err());
+    }
+
+    #[test]
+    fn test_merge_imported_aliases_keep_in_tmux_behavior() {
+        let current = StacksConfig { in_tmux_behavior: InTmuxBehavior::Ask, ..Default::default() };
+
+        let (merged, changed) = merge_imported_config(&current, "[aliases]\nst = \"status\"\n").unwrap();
+        assert_eq!(merged.in_tmux_behavior, InTmuxBehavior::Ask);
+        assert_eq!(merged.aliases["st"], "status");
+        assert_eq!(changed, vec!["aliases"]);
+    }
+
+    #[test]
+    fn test_set_profile_overrides_only_stores_differences() {
+        let mut config = StacksConfig::default();
+        let mut updated = config.clone();
+        updated.in_tmux_behavior = InTmuxBehavior::Ask;
+
+        config.set_profile_overrides("work", &updated).unwrap();
+
+        let profile = &config.profile["work"];
+        assert_eq!(profile.in_tmux_behavior, Some(InTmuxBehavior::Ask));
+        assert_eq!(profile.tmux_strategy, None);
+        assert_eq!(config.in_tmux_behavior, InTmuxBehavior::NewWindows);
+
+        // Settings outside the profile table are refused rather than dropped
+        let mut updated = config.with_profile("work").unwrap();
+        updated.telemetry = true;
+        let error = config.set_profile_overrides("work", &updated).unwrap_err();
+        assert!(error.to_string().contains("telemetry"));
+        assert!(!config.telemetry);
+        assert_eq!(config.profile["work"].in_tmux_behavior, Some(InTmuxBehavior::Ask));
+    }
+
+    #[test]
+    fn test_expand_groups() {
+        let config: StacksConfig = toml::from_str(
+            "[groups]\nbackend = [\"ruff-lint\", \"pytest\"]\ndb = [\"pytest\", \"postgres-mcp\"]\n"
+        ).unwrap();
+
+        let stacks = expand_groups(&config.groups, &["backend".to_string(), "db".to_string()]).unwrap();
+        assert_eq!(stacks, vec!["ruff-lint", "pytest", "postgres-mcp"]);
+        assert!(expand_groups(&config.groups, &["frontend".to_string()]).is_err());
+    }
+}
@@ -0,0 +1,313 @@
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+use anyhow::{Result, Context};
+use serde::{Deserialize, Serialize};
+use walkdir::WalkDir;
+
+use crate::config::load_config;
+
+/// Registry indexes, one JSON file per `owner/repo@branch`
+const REGISTRY_DIR: &str = "registries";
+
+/// Log files written by `--log-file`
+const LOG_DIR: &str = "logs";
+
+/// Cache size in megabytes above which the oldest entries are evicted, when `cache-max-mb` is unset
+pub const DEFAULT_MAX_MB: u32 = 200;
+
+/// A registry index younger than this is listed without asking the registry
+const INDEX_FRESH_FOR: Duration = Duration::from_secs(15 * 60);
+
+/// Entries untouched for this long are removed by `stacks cache prune`
+const STALE_AFTER: Duration = Duration::from_secs(30 * 24 * 60 * 60);
+
+/// Per-user cache directory for stacks (e.g. ~/.cache/claude-stacks)
+pub fn cache_root() -> Result<PathBuf> {
+    Ok(dirs::cache_dir()
+        .context("Failed to get cache directory")?
+        .join("claude-stacks"))
+}
+
+/// What a cached file is for
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CacheKind {
+    RegistryIndex,
+    Log,
+    Other,
+}
+
+impl CacheKind {
+    pub fn all() -> [CacheKind; 3] {
+        [CacheKind::RegistryIndex, CacheKind::Log, CacheKind::Other]
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            CacheKind::RegistryIndex => "registry indexes",
+            CacheKind::Log => "logs",
+            CacheKind::Other => "other",
+        }
+    }
+
+    fn of(relative: &Path) -> CacheKind {
+        match relative.components().next().and_then(|first| first.as_os_str().to_str()) {
+            Some(REGISTRY_DIR) => CacheKind::RegistryIndex,
+            Some(LOG_DIR) => CacheKind::Log,
+            _ => CacheKind::Other,
+        }
+    }
+}
+
+/// A file in the cache directory
+#[derive(Debug, Clone)]
+pub struct CacheEntry {
+    pub path: PathBuf,
+    pub kind: CacheKind,
+    pub size: u64,
+    pub modified: SystemTime,
+}
+
+impl CacheEntry {
+    pub fn is_stale(&self, now: SystemTime) -> bool {
+        // Small bookkeeping files like the update-check stamp are rewritten as needed
+        self.kind != CacheKind::Other
+            && now.duration_since(self.modified).map(|age| age > STALE_AFTER).unwrap_or(false)
+    }
+}
+
+/// Stacks that a registry listed when it was last reached
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RegistryIndex {
+    pub registry: String,
+    pub fetched_at: chrono::DateTime<chrono::Utc>,
+    pub stacks: Vec<IndexedStack>,
+}
+
+impl RegistryIndex {
+    /// Recent enough that discovery uses it instead of listing the registry again
+    pub fn is_fresh(&self, now: chrono::DateTime<chrono::Utc>) -> bool {
+        now.signed_duration_since(self.fetched_at)
+            .to_std()
+            .is_ok_and(|age| age < INDEX_FRESH_FOR)
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct IndexedStack {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+}
+
+fn index_path(root: &Path, registry: &str) -> PathBuf {
+    let file_name: String = registry
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '.' { c } else { '_' })
+        .collect();
+    root.join(REGISTRY_DIR).join(format!("{}.json", file_name))
+}
+
+/// Cached index of a registry, if it was ever reached
+pub fn load_registry_index(registry: &str) -> Option<RegistryIndex> {
+    let content = std::fs::read_to_string(index_path(&cache_root().ok()?, registry)).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+/// Remember a registry's stacks, then keep the cache under its size cap
+pub fn save_registry_index(index: &RegistryIndex) -> Result<()> {
+    let root = cache_root()?;
+    let path = index_path(&root, &index.registry);
+    std::fs::create_dir_all(root.join(REGISTRY_DIR))
+        .context("Failed to create registry index directory")?;
+    std::fs::write(&path, serde_json::to_string_pretty(index)?)
+        .with_context(|| format!("Failed to write {}", path.display()))?;
+
+    enforce_size_cap(&root, max_bytes())?;
+    Ok(())
+}
+
+/// Configured size cap in bytes
+pub fn max_bytes() -> u64 {
+    let megabytes = load_config()
+        .ok()
+        .and_then(|config| config.cache_max_mb)
+        .unwrap_or(DEFAULT_MAX_MB);
+    u64::from(megabytes) * 1024 * 1024
+}
+
+/// Every file in the cache, oldest first
+pub fn entries(root: &Path) -> Vec<CacheEntry> {
+    let mut entries: Vec<CacheEntry> = WalkDir::new(root)
+        .min_depth(1)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file())
+        .filter_map(|entry| {
+            let metadata = entry.metadata().ok()?;
+            let relative = entry.path().strip_prefix(root).ok()?;
+            Some(CacheEntry {
+                kind: CacheKind::of(relative),
+                path: entry.path().to_path_buf(),
+                size: metadata.len(),
+                modified: metadata.modified().unwrap_or(SystemTime::UNIX_EPOCH),
+            })
+        })
+        .collect();
+    entries.sort_by_key(|entry| entry.modified);
+    entries
+}
+
+/// Remove stale entries, then the oldest ones until the cache fits in `max_bytes`
+///
+/// Returns the removed entries.
+pub fn prune(root: &Path, now: SystemTime, max_bytes: u64) -> Result<Vec<CacheEntry>> {
+    let mut removed = Vec::new();
+    let mut kept = Vec::new();
+    for entry in entries(root) {
+        if entry.is_stale(now) {
+            remove_entry(&entry)?;
+            removed.push(entry);
+        } else {
+            kept.push(entry);
+        }
+    }
+
+    let mut total: u64 = kept.iter().map(|entry| entry.size).sum();
+    for entry in kept {
+        if total <= max_bytes {
+            break;
+        }
+        remove_entry(&entry)?;
+        total -= entry.size;
+        removed.push(entry);
+    }
+
+    Ok(removed)
+}
+
+fn enforce_size_cap(root: &Path, max_bytes: u64) -> Result<()> {
+    let total: u64 = entries(root).iter().map(|entry| entry.size).sum();
+    if total > max_bytes {
+        let removed = prune(root, SystemTime::now(), max_bytes)?;
+        tracing::debug!(removed = removed.len(), "cache exceeded its size cap");
+    }
+    Ok(())
+}
+
+fn remove_entry(entry: &CacheEntry) -> Result<()> {
+    match std::fs::remove_file(&entry.path) {
+        Ok(()) => Ok(()),
+        Err(error) if error.kind() == std::io::ErrorKind::NotFound => Ok(()),
+        Err(error) => Err(error).with_context(|| format!("Failed to remove {}", entry.path.display())),
+    }
+}
+
+/// Remove everything in the cache, keeping the directory itself; returns the bytes freed
+pub fn clear(root: &Path) -> Result<u64> {
+    let freed = entries(root).iter().map(|entry| entry.size).sum();
+    if !root.exists() {
+        return Ok(0);
+    }
+    for child in std::fs::read_dir(root).context("Failed to read cache directory")? {
+        let path = child?.path();
+        if path.is_dir() {
+            std::fs::remove_dir_all(&path)
+        } else {
+            std::fs::remove_file(&path)
+        }
+        .with_context(|| format!("Failed to remove {}", path.display()))?;
+    }
+    Ok(freed)
+}
+
+/// `1.5 MB`-style size for display
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KB", "MB", "GB"];
+    let mut size = bytes as f64;
+    let mut unit = 0;
+    while size >= 1024.0 && unit < UNITS.len() - 1 {
+        size /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} {}", bytes, UNITS[0])
+    } else {
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn write(root: &Path, relative: &str, size: usize) -> PathBuf {
+        let path = root.join(relative);
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(&path, vec![b'x'; size]).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_entries_are_classified() {
+        let dir = TempDir::new().unwrap();
+        write(dir.path(), "registries/acme_stacks_main.json", 10);
+        write(dir.path(), "logs/stacks.log.2026-01-01", 20);
+        write(dir.path(), "last-update-check", 5);
+
+        let mut kinds: Vec<&str> = entries(dir.path()).iter().map(|entry| entry.kind.as_str()).collect();
+        kinds.sort();
+        assert_eq!(kinds, vec!["logs", "other", "registry indexes"]);
+        assert_eq!(index_path(dir.path(), "acme/stacks@main"), dir.path().join("registries/acme_stacks_main.json"));
+    }
+
+    #[test]
+    fn test_registry_index_is_fresh() {
+        let now = chrono::Utc::now();
+        let index = RegistryIndex { registry: "acme/stacks@main".to_string(), fetched_at: now - chrono::Duration::minutes(5), stacks: Vec::new() };
+        assert!(index.is_fresh(now));
+        assert!(!index.is_fresh(now + chrono::Duration::hours(1)));
+        // A clock that went backwards doesn't make an index fresh forever
+        assert!(!index.is_fresh(now - chrono::Duration::hours(1)));
+    }
+
+    #[test]
+    fn test_prune_removes_stale_then_oldest() {
+        let dir = TempDir::new().unwrap();
+        write(dir.path(), "registries/a.json", 100);
+        std::thread::sleep(Duration::from_millis(20));
+        write(dir.path(), "registries/b.json", 100);
+        write(dir.path(), "last-update-check", 5);
+
+        // Nothing is stale yet and everything fits
+        assert!(prune(dir.path(), SystemTime::now(), 1024).unwrap().is_empty());
+
+        // Over the cap, the oldest entry goes first
+        let removed = prune(dir.path(), SystemTime::now(), 150).unwrap();
+        assert_eq!(removed.len(), 1);
+        assert!(removed[0].path.ends_with("a.json"));
+
+        // Far in the future the remaining index is stale, but the stamp file is kept
+        let later = SystemTime::now() + STALE_AFTER * 2;
+        let removed = prune(dir.path(), later, u64::MAX).unwrap();
+        assert_eq!(removed.len(), 1);
+        assert!(dir.path().join("last-update-check").exists());
+    }
+
+    #[test]
+    fn test_clear_keeps_directory() {
+        let dir = TempDir::new().unwrap();
+        write(dir.path(), "registries/a.json", 100);
+        write(dir.path(), "last-update-check", 5);
+        assert_eq!(clear(dir.path()).unwrap(), 105);
+        assert!(dir.path().exists());
+        assert!(entries(dir.path()).is_empty());
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(1536), "1.5 KB");
+        assert_eq!(format_size(3 * 1024 * 1024), "3.0 MB");
+    }
+}
@@ -0,0 +1,154 @@
+use std::path::{Path, PathBuf};
+use anyhow::{Result, Context, bail};
+use serde_json::Value;
+
+use super::git::Repo;
+use crate::utils::process::{shell_quote, Process};
+
+/// Container runtime `--container` starts claude with
+pub const RUNTIME: &str = "docker";
+
+/// Where a devcontainer definition may live in the worktree, in order of precedence
+const DEVCONTAINER_FILES: [&str; 2] = [".devcontainer/devcontainer.json", ".devcontainer.json"];
+
+/// Host paths a worktree's container sees, each at the same path inside
+#[derive(Debug, Clone, PartialEq)]
+pub struct ContainerMounts {
+    /// Mounted read-write; claude's working directory
+    pub worktree: PathBuf,
+    /// Mounted read-only
+    pub main_checkout: PathBuf,
+    /// The shared .git directory, read-write so commits from the worktree land
+    pub git_dir: PathBuf,
+    /// Home directory, for claude's ~/.claude and ~/.claude.json only
+    pub home: PathBuf,
+}
+
+/// Image for a worktree's claude: the `image` of its devcontainer.json, else `container-image`
+///
+/// Devcontainers that only define a `build` need an image built from them first.
+pub fn resolve_image(worktree: &Path, configured: Option<&str>) -> Result<String> {
+    for file in DEVCONTAINER_FILES {
+        let path = worktree.join(file);
+        if !path.is_file() {
+            continue;
+        }
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        let definition: Value = serde_json::from_str(&strip_line_comments(&content))
+            .with_context(|| format!("Failed to parse {}", path.display()))?;
+        if let Some(image) = definition["image"].as_str() {
+            return Ok(image.to_string());
+        }
+        if configured.is_none() {
+            bail!(
+                "{} has no image. Build one from it and set it with 'stacks config set container-image <image>'",
+                path.display()
+            );
+        }
+    }
+    match configured {
+        Some(image) => Ok(image.to_string()),
+        None => bail!("No container image: add a .devcontainer/devcontainer.json with an image, or run 'stacks config set container-image <image>'"),
+    }
+}
+
+/// devcontainer.json allows `//` comments, which serde_json doesn't
+fn strip_line_comments(content: &str) -> String {
+    content.lines()
+        .filter(|line| !line.trim_start().starts_with("//"))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Wrap a shell command so it runs in a fresh container named `name`, confined to `worktree`
+pub fn wrap_command(image: &str, name: &str, worktree: &Path, main_checkout: &Path, command: &str) -> Result<String> {
+    if !Process::new(RUNTIME).arg("--version").read_only().succeeds() {
+        bail!("{} not found. Install it to run claude in containers", RUNTIME);
+    }
+
+    let worktree = worktree.canonicalize()
+        .with_context(|| format!("Failed to resolve worktree path {}", worktree.display()))?;
+    let main_checkout = main_checkout.canonicalize()
+        .with_context(|| format!("Failed to resolve main checkout {}", main_checkout.display()))?;
+    let repo = Repo::discover(&worktree)?;
+    let git_dir = repo.common_dir().canonicalize()
+        .with_context(|| format!("Failed to resolve git directory {}", repo.common_dir().display()))?;
+    let home = dirs::home_dir().context("Could not find home directory")?;
+
+    let mounts = ContainerMounts { worktree, main_checkout, git_dir, home };
+    Ok(render_command(image, name, &mounts, owner(&mounts.worktree), command))
+}
+
+/// User and group owning `path`, so files claude creates in the container stay the user's
+#[cfg(unix)]
+fn owner(path: &Path) -> Option<(u32, u32)> {
+    use std::os::unix::fs::MetadataExt;
+    std::fs::metadata(path).ok().map(|metadata| (metadata.uid(), metadata.gid()))
+}
+
+#[cfg(not(unix))]
+fn owner(_path: &Path) -> Option<(u32, u32)> {
+    None
+}
+
+fn render_command(image: &str, name: &str, mounts: &ContainerMounts, user: Option<(u32, u32)>, command: &str) -> String {
+    let bind = |path: &Path, mode: &str| {
+        let path = path.to_string_lossy();
+        format!("-v {}", shell_quote(&format!("{}:{}{}", path, path, mode)))
+    };
+    let mut parts = vec![
+        format!("{} run --rm -it --name {}", RUNTIME, shell_quote(name)),
+        bind(&mounts.main_checkout, ":ro"),
+        // Later mounts shadow the read-only checkout where they're nested inside it
+        bind(&mounts.git_dir, ""),
+        bind(&mounts.worktree, ""),
+        bind(&mounts.home.join(".claude"), ""),
+        bind(&mounts.home.join(".claude.json"), ""),
+        format!("-e HOME={}", shell_quote(&mounts.home.to_string_lossy())),
+        format!("-w {}", shell_quote(&mounts.worktree.to_string_lossy())),
+    ];
+    if let Some((uid, gid)) = user {
+        parts.push(format!("-u {}:{}", uid, gid));
+    }
+    parts.push(shell_quote(image));
+    parts.push(format!("sh -c {}", shell_quote(command)));
+    parts.join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_render_command() {
+        let mounts = ContainerMounts {
+            worktree: PathBuf::from("/work/app-auth"),
+            main_checkout: PathBuf::from("/work/app"),
+            git_dir: PathBuf::from("/work/app/.git"),
+            home: PathBuf::from("/home/dev"),
+        };
+        let command = render_command("node:20", "stacks-auth-1", &mounts, Some((1000, 1000)), "claude --permission-mode acceptEdits");
+
+        assert!(command.starts_with("docker run --rm -it --name stacks-auth-1 -v /work/app:/work/app:ro -v /work/app/.git:/work/app/.git "));
+        assert!(command.contains("-v /work/app-auth:/work/app-auth "));
+        assert!(command.contains("-w /work/app-auth -u 1000:1000 node:20"));
+        assert!(command.ends_with("sh -c 'claude --permission-mode acceptEdits'"));
+    }
+
+    #[test]
+    fn test_resolve_image() {
+        let temp_dir = TempDir::new().unwrap();
+        let worktree = temp_dir.path();
+        assert!(resolve_image(worktree, None).is_err());
+        assert_eq!(resolve_image(worktree, Some("node:20")).unwrap(), "node:20");
+
+        std::fs::create_dir_all(worktree.join(".devcontainer")).unwrap();
+        std::fs::write(
+            worktree.join(".devcontainer/devcontainer.json"),
+            "{\n  // the team image\n  \"image\": \"ghcr.io/acme/dev:latest\"\n}\n",
+        ).unwrap();
+        assert_eq!(resolve_image(worktree, Some("node:20")).unwrap(), "ghcr.io/acme/dev:latest");
+    }
+}
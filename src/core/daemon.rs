@@ -0,0 +1,114 @@
+use std::path::PathBuf;
+use anyhow::{Result, Context};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::git::Repo;
+
+/// Written next to the repository's git data so every worktree reads the same file
+const STATUS_FILE: &str = "stacks-daemon-status.json";
+
+/// One stack as the daemon last saw it
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct StackHealth {
+    pub name: String,
+    /// `UpstreamStatus::describe`, e.g. "2 commits behind upstream"
+    pub upstream: String,
+    pub outdated: bool,
+    /// Missing or broken symlinks
+    pub drifted: bool,
+}
+
+/// Result of the daemon's latest pass, for `stacks status` and shell prompts
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DaemonStatus {
+    pub pid: u32,
+    pub checked_at: DateTime<Utc>,
+    pub interval_secs: u64,
+    pub stacks: Vec<StackHealth>,
+}
+
+impl DaemonStatus {
+    pub fn outdated(&self) -> Vec<&StackHealth> {
+        self.stacks.iter().filter(|stack| stack.outdated).collect()
+    }
+
+    pub fn drifted(&self) -> Vec<&StackHealth> {
+        self.stacks.iter().filter(|stack| stack.drifted).collect()
+    }
+
+    /// Whether the daemon has checked recently enough for the file to be trusted
+    pub fn is_fresh(&self, now: DateTime<Utc>) -> bool {
+        let allowance = chrono::Duration::seconds((self.interval_secs * 2) as i64);
+        now - self.checked_at <= allowance
+    }
+
+    /// Stacks that are outdated now but weren't in `previous`, so each update is announced once
+    pub fn newly_outdated(&self, previous: Option<&DaemonStatus>) -> Vec<&StackHealth> {
+        self.outdated()
+            .into_iter()
+            .filter(|stack| {
+                !previous
+                    .map(|previous| previous.stacks.iter().any(|old| old.name == stack.name && old.outdated))
+                    .unwrap_or(false)
+            })
+            .collect()
+    }
+}
+
+pub fn status_path() -> Result<PathBuf> {
+    Ok(Repo::current()?.common_dir().join(STATUS_FILE))
+}
+
+/// The daemon's latest status for this repository, if it has run
+pub fn load_status() -> Option<DaemonStatus> {
+    let content = std::fs::read_to_string(status_path().ok()?).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+pub fn save_status(status: &DaemonStatus) -> Result<()> {
+    let path = status_path()?;
+    std::fs::write(&path, serde_json::to_string_pretty(status)?)
+        .with_context(|| format!("Failed to write {}", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stack(name: &str, outdated: bool) -> StackHealth {
+        StackHealth {
+            name: name.to_string(),
+            upstream: String::new(),
+            outdated,
+            drifted: false,
+        }
+    }
+
+    fn status(stacks: Vec<StackHealth>) -> DaemonStatus {
+        DaemonStatus {
+            pid: 1,
+            checked_at: Utc::now(),
+            interval_secs: 60,
+            stacks,
+        }
+    }
+
+    #[test]
+    fn test_newly_outdated_announces_each_update_once() {
+        let first = status(vec![stack("lint", true), stack("docs", false)]);
+        let names: Vec<&str> = first.newly_outdated(None).iter().map(|stack| stack.name.as_str()).collect();
+        assert_eq!(names, vec!["lint"]);
+
+        let second = status(vec![stack("lint", true), stack("docs", true)]);
+        let names: Vec<&str> = second.newly_outdated(Some(&first)).iter().map(|stack| stack.name.as_str()).collect();
+        assert_eq!(names, vec!["docs"]);
+    }
+
+    #[test]
+    fn test_is_fresh() {
+        let status = status(Vec::new());
+        assert!(status.is_fresh(Utc::now()));
+        assert!(!status.is_fresh(Utc::now() + chrono::Duration::seconds(121)));
+    }
+}
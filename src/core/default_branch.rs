@@ -0,0 +1,52 @@
+use super::git::Repo;
+
+/// Default branch of the current project
+///
+/// Resolution order: the configured override, origin's HEAD (`refs/remotes/origin/HEAD`),
+/// the first existing common branch name, git's `init.defaultBranch`, and finally `main`.
+pub fn project_default_branch(configured: Option<&str>) -> String {
+    if let Some(branch) = configured.map(str::trim).filter(|branch| !branch.is_empty()) {
+        return branch.to_string();
+    }
+
+    let repo = match Repo::current() {
+        Ok(repo) => repo,
+        Err(_) => return global_default_branch(),
+    };
+
+    if let Some(target) = repo.symbolic_target("refs/remotes/origin/HEAD") {
+        return target.trim_start_matches("refs/remotes/origin/").to_string();
+    }
+
+    for candidate in ["main", "master", "trunk", "develop"] {
+        if repo.ref_exists(&format!("refs/remotes/origin/{}", candidate)) || repo.ref_exists(&format!("refs/heads/{}", candidate)) {
+            return candidate.to_string();
+        }
+    }
+
+    repo.config_string("init.defaultBranch")
+        .filter(|branch| !branch.trim().is_empty())
+        .unwrap_or_else(|| "main".to_string())
+}
+
+/// `init.defaultBranch` from the user's git config, or `main`, outside a repository
+fn global_default_branch() -> String {
+    git2::Config::open_default()
+        .and_then(|config| config.get_string("init.defaultBranch"))
+        .ok()
+        .filter(|branch| !branch.trim().is_empty())
+        .unwrap_or_else(|| "main".to_string())
+}
+
+/// Ref to branch new work from: origin's copy of the default branch when present, else the local one
+pub fn default_branch_start_point(branch: &str) -> Option<String> {
+    let repo = Repo::current().ok()?;
+    let remote = format!("origin/{}", branch);
+    if repo.ref_exists(&format!("refs/remotes/{}", remote)) {
+        Some(remote)
+    } else if repo.ref_exists(&format!("refs/heads/{}", branch)) {
+        Some(branch.to_string())
+    } else {
+        None
+    }
+}
@@ -0,0 +1,302 @@
+use std::path::{Path, PathBuf};
+use walkdir::WalkDir;
+
+/// Whether a definition is a subagent or a slash command
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum DefinitionKind {
+    Agent,
+    Command,
+}
+
+impl DefinitionKind {
+    pub fn all() -> [DefinitionKind; 2] {
+        [DefinitionKind::Agent, DefinitionKind::Command]
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DefinitionKind::Agent => "agent",
+            DefinitionKind::Command => "command",
+        }
+    }
+
+    fn dir(&self) -> &'static str {
+        match self {
+            DefinitionKind::Agent => "agents",
+            DefinitionKind::Command => "commands",
+        }
+    }
+}
+
+/// Where claude picks a definition up from
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Scope {
+    /// The project's .claude directory
+    Project,
+    /// ~/.claude (or $CLAUDE_CONFIG_DIR), shared by every project
+    User,
+    /// Inside stacks/<name> but not linked into .claude, so claude doesn't see it
+    Unlinked,
+}
+
+impl Scope {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Scope::Project => "project",
+            Scope::User => "user",
+            Scope::Unlinked => "unlinked",
+        }
+    }
+}
+
+/// An agent or command file matching a name
+#[derive(Debug, Clone, PartialEq)]
+pub struct Definition {
+    pub kind: DefinitionKind,
+    pub scope: Scope,
+    /// Name claude knows it by: the path under agents/ or commands/ without `.md`
+    pub name: String,
+    pub path: PathBuf,
+    /// Where the file points when it is a symlink
+    pub link_target: Option<PathBuf>,
+    /// Stack the definition comes from, if any
+    pub stack: Option<String>,
+}
+
+impl Definition {
+    /// Whether a symlinked definition points at a file that no longer exists
+    pub fn is_broken(&self) -> bool {
+        self.link_target.is_some() && !self.path.exists()
+    }
+}
+
+/// Claude's user-level configuration directory
+pub fn user_claude_dir() -> Option<PathBuf> {
+    match std::env::var_os("CLAUDE_CONFIG_DIR") {
+        Some(dir) => Some(PathBuf::from(dir)),
+        None => dirs::home_dir().map(|home| home.join(".claude")),
+    }
+}
+
+/// Every agent and command called `query` visible from `project_root`, project definitions first
+///
+/// `query` may be given with or without `.md`, a leading `/` or the `<stack>_` prefix links get.
+pub fn find(query: &str, project_root: &Path, user_dir: Option<&Path>) -> Vec<Definition> {
+    let query = query.trim_start_matches('/').trim_end_matches(".md");
+    let stacks = installed_stacks(project_root);
+    let mut found: Vec<Definition> = visible(project_root, user_dir)
+        .into_iter()
+        .filter(|definition| matches(query, &definition.name, definition.stack.as_deref()))
+        .collect();
+
+    // Stack files that should have been linked into the project but weren't
+    for stack in &stacks {
+        let stack_claude_dir = project_root.join("stacks").join(stack).join(".claude");
+        for kind in DefinitionKind::all() {
+            for (name, path) in definitions_in(&stack_claude_dir.join(kind.dir())) {
+                if name != query && format!("{}_{}", stack, name) != query {
+                    continue;
+                }
+                let linked = found.iter().any(|definition| {
+                    definition.scope == Scope::Project && definition.stack.as_deref() == Some(stack.as_str()) && definition.path.exists()
+                });
+                if !linked {
+                    found.push(Definition { kind, scope: Scope::Unlinked, name, path, link_target: None, stack: Some(stack.clone()) });
+                }
+            }
+        }
+    }
+    found
+}
+
+/// Every agent and command in the project's and the user's .claude directories, project ones
+/// first, each with the stack it comes from
+pub fn visible(project_root: &Path, user_dir: Option<&Path>) -> Vec<Definition> {
+    let stacks = installed_stacks(project_root);
+    let mut found = Vec::new();
+
+    let mut scopes = vec![(Scope::Project, project_root.join(".claude"))];
+    if let Some(user_dir) = user_dir {
+        scopes.push((Scope::User, user_dir.to_path_buf()));
+    }
+    for (scope, claude_dir) in scopes {
+        for kind in DefinitionKind::all() {
+            for (name, path) in definitions_in(&claude_dir.join(kind.dir())) {
+                let link_target = std::fs::read_link(&path).ok();
+                let stack = link_target.as_deref().and_then(stack_of_target).or_else(|| prefix_stack(&name, &stacks));
+                found.push(Definition { kind, scope, name, path, link_target, stack });
+            }
+        }
+    }
+    found
+}
+
+/// `name` and `description` from the YAML frontmatter of an agent or command
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Frontmatter {
+    pub name: Option<String>,
+    pub description: Option<String>,
+}
+
+impl Frontmatter {
+    /// Frontmatter of `content`; empty when it has none or it isn't valid YAML
+    pub fn parse(content: &str) -> Self {
+        let mut lines = content.lines();
+        if lines.next().map(str::trim_end) != Some("---") {
+            return Self::default();
+        }
+        let yaml: Vec<&str> = lines.take_while(|line| line.trim_end() != "---").collect();
+        let value: serde_yaml::Value = match serde_yaml::from_str(&yaml.join("\n")) {
+            Ok(value) => value,
+            Err(_) => return Self::default(),
+        };
+        let field = |key: &str| value.get(key)
+            .and_then(|field| field.as_str())
+            .map(|field| field.trim().to_string())
+            .filter(|field| !field.is_empty());
+        Self { name: field("name"), description: field("description") }
+    }
+}
+
+/// What claude says a definition does: the frontmatter description, or else the first line of
+/// its body, as claude shows for commands without one
+pub fn description(content: &str) -> Option<String> {
+    if let Some(description) = Frontmatter::parse(content).description {
+        return Some(description);
+    }
+    let body = match content.strip_prefix("---") {
+        Some(rest) => rest.split_once("\n---").map(|(_, body)| body).unwrap_or(""),
+        None => content,
+    };
+    body.lines()
+        .map(|line| line.trim().trim_start_matches('#').trim())
+        .find(|line| !line.is_empty())
+        .map(str::to_string)
+}
+
+fn matches(query: &str, name: &str, stack: Option<&str>) -> bool {
+    if name == query {
+        return true;
+    }
+    match stack {
+        Some(stack) => name.strip_prefix(stack).and_then(|rest| rest.strip_prefix('_')) == Some(query),
+        None => false,
+    }
+}
+
+/// `.md` files under `dir` as (name, path), the name being the relative path without extension
+fn definitions_in(dir: &Path) -> Vec<(String, PathBuf)> {
+    if !dir.is_dir() {
+        return Vec::new();
+    }
+    WalkDir::new(dir)
+        .min_depth(1)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| !e.file_type().is_dir())
+        .filter(|e| e.path().extension().map(|extension| extension == "md").unwrap_or(false))
+        .filter_map(|e| {
+            let relative = e.path().strip_prefix(dir).ok()?.with_extension("");
+            Some((relative.to_string_lossy().replace('\\', "/"), e.path().to_path_buf()))
+        })
+        .collect()
+}
+
+fn installed_stacks(project_root: &Path) -> Vec<String> {
+    WalkDir::new(project_root.join("stacks"))
+        .min_depth(1)
+        .max_depth(1)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_dir())
+        .map(|e| e.file_name().to_string_lossy().to_string())
+        .collect()
+}
+
+/// Stack a link points into, from the `stacks/<name>/` part of its target
+fn stack_of_target(target: &Path) -> Option<String> {
+    let components: Vec<String> = target.components().map(|c| c.as_os_str().to_string_lossy().to_string()).collect();
+    components
+        .windows(2)
+        .find(|pair| pair[0] == "stacks")
+        .map(|pair| pair[1].clone())
+}
+
+/// Stack whose `<stack>_` prefix a definition name carries; the longest wins
+fn prefix_stack(name: &str, stacks: &[String]) -> Option<String> {
+    let file_name = name.rsplit('/').next().unwrap_or(name);
+    stacks
+        .iter()
+        .filter(|stack| file_name.starts_with(&format!("{}_", stack)))
+        .max_by_key(|stack| stack.len())
+        .cloned()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    fn write(path: &Path) {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, "---\nname: test\n---\n").unwrap();
+    }
+
+    #[test]
+    fn test_stack_of_target() {
+        assert_eq!(stack_of_target(Path::new("../../stacks/lint/.claude/agents/reviewer.md")).as_deref(), Some("lint"));
+        assert_eq!(stack_of_target(Path::new("/home/me/agents/reviewer.md")), None);
+        assert_eq!(prefix_stack("ts-lint_reviewer", &["ts".to_string(), "ts-lint".to_string()]).as_deref(), Some("ts-lint"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_find() {
+        let project = TempDir::new().unwrap();
+        let user = TempDir::new().unwrap();
+        let root = project.path();
+
+        write(&root.join("stacks/lint/.claude/agents/reviewer.md"));
+        write(&root.join("stacks/lint/.claude/commands/fix.md"));
+        std::fs::create_dir_all(root.join(".claude/agents")).unwrap();
+        std::os::unix::fs::symlink("../../stacks/lint/.claude/agents/reviewer.md", root.join(".claude/agents/lint_reviewer.md")).unwrap();
+        write(&user.path().join("agents/reviewer.md"));
+
+        let found = find("reviewer", root, Some(user.path()));
+        assert_eq!(found.len(), 2);
+        assert_eq!(found[0].scope, Scope::Project);
+        assert_eq!(found[0].name, "lint_reviewer");
+        assert_eq!(found[0].stack.as_deref(), Some("lint"));
+        assert!(!found[0].is_broken());
+        assert_eq!(found[1].scope, Scope::User);
+        assert_eq!(found[1].stack, None);
+
+        let found = find("/lint_fix", root, Some(user.path()));
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].kind, DefinitionKind::Command);
+        assert_eq!(found[0].scope, Scope::Unlinked);
+
+        assert!(find("missing", root, None).is_empty());
+
+        let all = visible(root, Some(user.path()));
+        assert_eq!(all.len(), 2);
+        assert!(all.iter().all(|definition| definition.kind == DefinitionKind::Agent));
+    }
+
+    #[test]
+    fn test_frontmatter_and_description() {
+        let agent = "---\nname: reviewer\ndescription: Reviews diffs for lint issues\ntools: Read, Grep\n---\nYou review code.\n";
+        assert_eq!(Frontmatter::parse(agent), Frontmatter {
+            name: Some("reviewer".to_string()),
+            description: Some("Reviews diffs for lint issues".to_string()),
+        });
+        assert_eq!(description(agent).as_deref(), Some("Reviews diffs for lint issues"));
+
+        assert_eq!(description("---\nallowed-tools: Bash(git:*)\n---\n\n# Fix lint errors\nRun eslint.").as_deref(), Some("Fix lint errors"));
+        assert_eq!(description("Run the test suite and fix failures").as_deref(), Some("Run the test suite and fix failures"));
+        assert_eq!(Frontmatter::parse("---\nname: [unclosed\n---\n"), Frontmatter::default());
+        assert_eq!(description(""), None);
+    }
+}
@@ -0,0 +1,162 @@
+use std::fmt;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, OnceLock};
+use serde::Serialize;
+
+/// Something a core operation did or found, for the front end to show
+///
+/// Core modules report these instead of printing, so the CLI, the library API and tests can each
+/// decide what to do with them. `Display` gives the line the CLI prints; serialized, the variant is
+/// the `type` field, e.g. `{"type": "settings_merged", "stack": "ts-lint"}`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Event {
+    /// A stack's subtree is being added to the project
+    SubtreeAdding { stack: String, repo_url: String, branch: String, path: Option<String> },
+    SubtreeAdded { stack: String },
+    /// The stack's directory already existed, so nothing was downloaded
+    StackAlreadyPresent { stack: String },
+    /// The registry couldn't be reached and its cached listing was used
+    RegistryCacheUsed { registry: String, fetched_at: chrono::DateTime<chrono::Utc> },
+    SymlinkCreated { link: PathBuf },
+    /// A link was removed; stale links pointed at a file that no longer exists
+    SymlinkRemoved { link: PathBuf, stale: bool },
+    SettingsMerged { stack: String },
+    AutoPushHookChanged { enabled: bool },
+    ClaudeMdSectionAdded { stack: String, embedded: bool },
+    ClaudeMdSectionRemoved { stack: String },
+    HookStarted { hook: String, command: String },
+    HookOutput { line: String },
+    HookFailed { hook: String, reason: String },
+    PolicyWarning { message: String },
+    /// The organization policy couldn't be fetched and the copy from the last run was used
+    PolicyCacheUsed { location: String },
+    /// A stack's signature was checked; `trusted` is false for unsigned or untrusted stacks
+    SignatureChecked { description: String, trusted: bool },
+}
+
+impl fmt::Display for Event {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Event::SubtreeAdding { stack, repo_url, branch, path: None } => {
+                write!(f, "  📥 Adding {} as subtree from {} ({})", stack, repo_url, branch)
+            }
+            Event::SubtreeAdding { stack, repo_url, branch, path: Some(path) } => {
+                write!(f, "  📥 Adding {} as subtree from {} ({}, {})", stack, repo_url, branch, path)
+            }
+            Event::SubtreeAdded { stack } => write!(f, "  ✅ Successfully added {} as subtree", stack),
+            Event::StackAlreadyPresent { stack } => write!(f, "  📦 Stack already exists: {}", stack),
+            Event::RegistryCacheUsed { registry, fetched_at } => write!(
+                f,
+                "⚠️ Could not reach {}, using the stack list cached {}",
+                registry,
+                fetched_at.with_timezone(&chrono::Local).format("%Y-%m-%d %H:%M")
+            ),
+            Event::SymlinkCreated { link } => write!(f, "  📎 Created symlink: {}", link.display()),
+            Event::SymlinkRemoved { link, stale: false } => write!(f, "  🗑️ Removed symlink: {}", link.display()),
+            Event::SymlinkRemoved { link, stale: true } => write!(f, "  🗑️ Removed stale symlink: {}", link.display()),
+            Event::SettingsMerged { stack } => write!(f, "  ⚙️ Merged settings from stack {}", stack),
+            Event::AutoPushHookChanged { enabled: true } => {
+                write!(f, "  🔁 Added the auto-push hook: stack edits are pushed when a claude session stops")
+            }
+            Event::AutoPushHookChanged { enabled: false } => write!(f, "  🔁 Removed the auto-push hook"),
+            Event::ClaudeMdSectionAdded { stack, embedded: false } => {
+                write!(f, "  📝 Added import to CLAUDE.md: @stacks/{}/CLAUDE.md", stack)
+            }
+            Event::ClaudeMdSectionAdded { stack, embedded: true } => write!(f, "  📝 Embedded {} instructions in CLAUDE.md", stack),
+            Event::ClaudeMdSectionRemoved { stack } => write!(f, "  📝 Removed import from CLAUDE.md: @stacks/{}/CLAUDE.md", stack),
+            Event::HookStarted { hook, command } => write!(f, "🪝 {}: {}", hook, command),
+            Event::HookOutput { line } => write!(f, "    {}", line),
+            Event::HookFailed { hook, reason } => write!(f, "  ⚠️ Hook {} failed: {}", hook, reason),
+            Event::PolicyWarning { message } => write!(f, "⚠️ Policy: {}", message),
+            Event::PolicyCacheUsed { location } => {
+                write!(f, "⚠️ Could not reach the organization policy at {}; using the copy from the last run", location)
+            }
+            Event::SignatureChecked { description, trusted: true } => write!(f, "  🔏 {}", description),
+            Event::SignatureChecked { description, trusted: false } => write!(f, "  ⚠️ {}", description),
+        }
+    }
+}
+
+/// Receives the events of core operations
+pub trait Reporter: Send + Sync {
+    fn report(&self, event: &Event);
+}
+
+/// Prints each event as a line of CLI output
+#[derive(Debug, Default, Clone, Copy)]
+pub struct ConsoleReporter;
+
+impl Reporter for ConsoleReporter {
+    fn report(&self, event: &Event) {
+        outln!("{}", event);
+    }
+}
+
+/// Keeps every event, e.g. to assert on in tests or to show once an operation is done
+#[derive(Debug, Default)]
+pub struct CollectingReporter {
+    events: Mutex<Vec<Event>>,
+}
+
+impl CollectingReporter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn events(&self) -> Vec<Event> {
+        self.events.lock().map(|events| events.clone()).unwrap_or_default()
+    }
+}
+
+impl Reporter for CollectingReporter {
+    fn report(&self, event: &Event) {
+        if let Ok(mut events) = self.events.lock() {
+            events.push(event.clone());
+        }
+    }
+}
+
+static REPORTER: OnceLock<Arc<dyn Reporter>> = OnceLock::new();
+
+/// Replace the console as the reporter of every core operation in this process
+///
+/// Can be set once, before the first operation; returns false if a reporter was already set.
+/// Managers built `with_reporter` use theirs instead.
+pub fn set_reporter(reporter: Arc<dyn Reporter>) -> bool {
+    REPORTER.set(reporter).is_ok()
+}
+
+/// The process-wide reporter: the one set with `set_reporter`, or the console
+pub fn default_reporter() -> Arc<dyn Reporter> {
+    REPORTER.get_or_init(|| Arc::new(ConsoleReporter)).clone()
+}
+
+/// Report `event` to the process-wide reporter, for core functions without a manager of their own
+pub fn report(event: Event) {
+    default_reporter().report(&event);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_events_render_as_cli_lines() {
+        let event = Event::SubtreeAdding {
+            stack: "ts-lint".to_string(),
+            repo_url: "https://github.com/acme/ts-lint".to_string(),
+            branch: "main".to_string(),
+            path: None,
+        };
+        assert_eq!(event.to_string(), "  📥 Adding ts-lint as subtree from https://github.com/acme/ts-lint (main)");
+        assert_eq!(
+            Event::SymlinkRemoved { link: PathBuf::from(".claude/agents/ts-lint-fix.md"), stale: true }.to_string(),
+            "  🗑️ Removed stale symlink: .claude/agents/ts-lint-fix.md"
+        );
+
+        let collector = CollectingReporter::new();
+        collector.report(&Event::SettingsMerged { stack: "ts-lint".to_string() });
+        assert_eq!(collector.events(), vec![Event::SettingsMerged { stack: "ts-lint".to_string() }]);
+    }
+}
@@ -0,0 +1,475 @@
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use git2::{Cred, CredentialType, Direction, ErrorCode, Remote, RemoteCallbacks, Repository, Status, StatusOptions};
+use super::auth;
+use crate::utils::logging::CommandLogExt;
+
+/// Errors from git operations, independent of git's locale-dependent messages
+#[derive(Debug)]
+pub enum GitError {
+    /// The directory is not inside a git repository
+    NotARepository(PathBuf),
+    /// A ref, revision, or path doesn't exist
+    NotFound(String),
+    /// The remote could not be reached or rejected our credentials
+    Remote { url: String, message: String },
+    /// The git executable could not be started
+    Spawn(std::io::Error),
+    /// A git subcommand exited unsuccessfully
+    Command { args: Vec<String>, code: Option<i32>, stderr: String },
+    /// Any other error reported by libgit2
+    Library(git2::Error),
+}
+
+impl fmt::Display for GitError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GitError::NotARepository(path) => write!(f, "{} is not in a git repository", path.display()),
+            GitError::NotFound(what) => write!(f, "{} not found", what),
+            GitError::Remote { url, message } => write!(f, "Failed to reach {}: {}", url, message),
+            GitError::Spawn(error) => write!(f, "Failed to run git: {}", error),
+            GitError::Command { args, code, stderr } => {
+                write!(f, "git {} failed", args.join(" "))?;
+                if let Some(code) = code {
+                    write!(f, " (exit code {})", code)?;
+                }
+                if !stderr.trim().is_empty() {
+                    write!(f, ": {}", stderr.trim())?;
+                }
+                Ok(())
+            }
+            GitError::Library(error) => write!(f, "{}", error.message()),
+        }
+    }
+}
+
+impl std::error::Error for GitError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            GitError::Spawn(error) => Some(error),
+            GitError::Library(error) => Some(error),
+            _ => None,
+        }
+    }
+}
+
+impl From<git2::Error> for GitError {
+    fn from(error: git2::Error) -> Self {
+        match error.code() {
+            ErrorCode::NotFound => GitError::NotFound(error.message().to_string()),
+            _ => GitError::Library(error),
+        }
+    }
+}
+
+pub type GitResult<T> = std::result::Result<T, GitError>;
+
+/// A repository opened through libgit2
+pub struct Repo {
+    inner: Repository,
+}
+
+impl Repo {
+    /// Open the repository containing `dir`
+    pub fn discover(dir: &Path) -> GitResult<Self> {
+        match Repository::discover(dir) {
+            Ok(inner) => Ok(Self { inner }),
+            Err(error) if error.code() == ErrorCode::NotFound => Err(GitError::NotARepository(dir.to_path_buf())),
+            Err(error) => Err(error.into()),
+        }
+    }
+
+    /// Open the repository containing the current directory
+    pub fn current() -> GitResult<Self> {
+        let dir = std::env::current_dir().map_err(GitError::Spawn)?;
+        Self::discover(&dir)
+    }
+
+    /// The checkout's own git directory (`.git/worktrees/<name>` in a linked worktree)
+    pub fn git_dir(&self) -> &Path {
+        self.inner.path()
+    }
+
+    /// The git directory shared by all worktrees
+    ///
+    /// git2 0.18 has no `commondir()`, so a linked worktree's is read from its `commondir` file.
+    pub fn common_dir(&self) -> PathBuf {
+        let git_dir = self.inner.path();
+        if !self.inner.is_worktree() {
+            return git_dir.to_path_buf();
+        }
+        match std::fs::read_to_string(git_dir.join("commondir")) {
+            Ok(common) => git_dir.join(common.trim()),
+            Err(_) => git_dir.to_path_buf(),
+        }
+    }
+
+    /// Root of the working tree; None for bare repositories
+    pub fn workdir(&self) -> Option<&Path> {
+        self.inner.workdir()
+    }
+
+    /// Whether this checkout is a linked worktree rather than the main one
+    pub fn is_linked_worktree(&self) -> bool {
+        self.inner.is_worktree()
+    }
+
+    pub fn ref_exists(&self, name: &str) -> bool {
+        self.inner.find_reference(name).is_ok()
+    }
+
+    /// Target of a symbolic ref, e.g. `refs/remotes/origin/main` for `refs/remotes/origin/HEAD`
+    pub fn symbolic_target(&self, name: &str) -> Option<String> {
+        self.inner.find_reference(name).ok()?.symbolic_target().map(str::to_string)
+    }
+
+    /// A string value from the repository's config, including global and system config
+    pub fn config_string(&self, key: &str) -> Option<String> {
+        self.inner.config().ok()?.get_string(key).ok()
+    }
+
+    /// Full sha of HEAD; None in a repository without commits
+    pub fn head_commit(&self) -> Option<String> {
+        self.inner.head().ok()?.peel_to_commit().ok().map(|commit| commit.id().to_string())
+    }
+
+    /// Resolve a revision (branch, ref, or sha) to a full commit sha
+    pub fn resolve_commit(&self, revision: &str) -> GitResult<String> {
+        let object = self.inner.revparse_single(revision)
+            .map_err(|_| GitError::NotFound(format!("revision '{}'", revision)))?;
+        Ok(object.peel_to_commit()?.id().to_string())
+    }
+
+    /// Whether a merge is waiting to be concluded (MERGE_HEAD exists)
+    pub fn merge_in_progress(&self) -> bool {
+        self.git_dir().join("MERGE_HEAD").exists()
+    }
+
+    /// Uncommitted changes under `pathspec` (relative to the repository root; empty for the
+    /// whole tree), including untracked files, as (status, path) pairs
+    pub fn changes(&self, pathspec: &str) -> GitResult<Vec<(Status, String)>> {
+        let mut options = StatusOptions::new();
+        options
+            .include_untracked(true)
+            .recurse_untracked_dirs(true)
+            .include_ignored(false);
+        if !pathspec.is_empty() {
+            options.pathspec(pathspec);
+        }
+
+        let statuses = self.inner.statuses(Some(&mut options))?;
+        Ok(statuses
+            .iter()
+            .filter(|entry| entry.status() != Status::CURRENT)
+            .filter_map(|entry| Some((entry.status(), entry.path()?.to_string())))
+            .collect())
+    }
+
+    /// Uncommitted changes inside `dir`, which may be anywhere in the working tree
+    pub fn changes_in(&self, dir: &Path) -> GitResult<Vec<(Status, String)>> {
+        let workdir = self.workdir()
+            .and_then(|workdir| workdir.canonicalize().ok())
+            .ok_or_else(|| GitError::NotFound("working tree".to_string()))?;
+        let dir = dir.canonicalize().map_err(|_| GitError::NotFound(dir.display().to_string()))?;
+        let relative = dir.strip_prefix(&workdir).unwrap_or(Path::new(""));
+        self.changes(&relative.to_string_lossy())
+    }
+
+    /// Whether git ignores `path`, which needn't exist yet
+    pub fn is_ignored(&self, path: &Path) -> bool {
+        let (dir, name) = match (path.parent(), path.file_name()) {
+            (Some(dir), Some(name)) => (if dir.as_os_str().is_empty() { Path::new(".") } else { dir }, name),
+            _ => return false,
+        };
+        let workdir = match self.workdir().and_then(|workdir| workdir.canonicalize().ok()) {
+            Some(workdir) => workdir,
+            None => return false,
+        };
+        match dir.canonicalize() {
+            Ok(dir) => match dir.join(name).strip_prefix(&workdir) {
+                Ok(relative) => self.inner.is_path_ignored(relative).unwrap_or(false),
+                Err(_) => false,
+            },
+            Err(_) => false,
+        }
+    }
+
+    /// Ignore `pattern` in this clone only by adding it to info/exclude, once
+    pub fn exclude(&self, pattern: &str) -> std::io::Result<()> {
+        let exclude_path = self.common_dir().join("info").join("exclude");
+        if let Some(info_dir) = exclude_path.parent() {
+            std::fs::create_dir_all(info_dir)?;
+        }
+
+        let mut exclude = std::fs::read_to_string(&exclude_path).unwrap_or_default();
+        if exclude.lines().any(|line| line.trim() == pattern) {
+            return Ok(());
+        }
+        if !exclude.is_empty() && !exclude.ends_with('\n') {
+            exclude.push('\n');
+        }
+        exclude.push_str(pattern);
+        exclude.push('\n');
+        std::fs::write(&exclude_path, exclude)
+    }
+
+    pub fn has_changes(&self, pathspec: &str) -> GitResult<bool> {
+        Ok(!self.changes(pathspec)?.is_empty())
+    }
+
+    /// Paths currently in an unmerged (conflicted) state
+    pub fn conflicted_files(&self) -> GitResult<Vec<String>> {
+        Ok(self.changes("")?
+            .into_iter()
+            .filter(|(status, _)| status.contains(Status::CONFLICTED))
+            .map(|(_, path)| path)
+            .collect())
+    }
+
+    /// Latest commit reachable from HEAD whose message has a line accepted by `matches`,
+    /// as its sha and full message
+    pub fn find_commit_message(&self, matches: impl Fn(&str) -> bool) -> GitResult<Option<(String, String)>> {
+        self.find_recent_commit_message(matches, usize::MAX)
+    }
+
+    /// Like `find_commit_message`, looking at no more than the latest `depth` commits
+    pub fn find_recent_commit_message(&self, matches: impl Fn(&str) -> bool, depth: usize) -> GitResult<Option<(String, String)>> {
+        // An unborn HEAD has no history to search; libgit2 reports pushing it as a generic error
+        if self.head_commit().is_none() {
+            return Ok(None);
+        }
+        let mut walk = self.inner.revwalk()?;
+        walk.push_head()?;
+
+        for oid in walk.take(depth) {
+            let commit = self.inner.find_commit(oid?)?;
+            let message = commit.message().unwrap_or_default();
+            if message.lines().any(&matches) {
+                return Ok(Some((commit.id().to_string(), message.to_string())));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Non-merge commits after `since` up to HEAD that change anything under `path`
+    pub fn count_commits_touching(&self, since: &str, path: &str) -> GitResult<usize> {
+        let since = self.inner.revparse_single(since)
+            .map_err(|_| GitError::NotFound(format!("revision '{}'", since)))?
+            .id();
+        let path = Path::new(path);
+
+        let mut walk = self.inner.revwalk()?;
+        walk.push_head()?;
+        walk.hide(since)?;
+
+        let mut count = 0;
+        for oid in walk {
+            let commit = self.inner.find_commit(oid?)?;
+            if commit.parent_count() > 1 {
+                continue;
+            }
+            let entry_at = |tree: git2::Tree| tree.get_path(path).ok().map(|entry| entry.id());
+            let current = entry_at(commit.tree()?);
+            let previous = match commit.parent(0) {
+                Ok(parent) => entry_at(parent.tree()?),
+                Err(_) => None,
+            };
+            if current != previous {
+                count += 1;
+            }
+        }
+        Ok(count)
+    }
+}
+
+/// Two-letter status code as shown by `git status --porcelain`, e.g. ` M` or `??`
+pub fn porcelain_code(status: Status) -> String {
+    if status.contains(Status::CONFLICTED) {
+        return "UU".to_string();
+    }
+    if status.contains(Status::WT_NEW) && !status.intersects(Status::INDEX_NEW | Status::INDEX_MODIFIED) {
+        return "??".to_string();
+    }
+
+    let index = if status.contains(Status::INDEX_NEW) {
+        'A'
+    } else if status.contains(Status::INDEX_MODIFIED) {
+        'M'
+    } else if status.contains(Status::INDEX_DELETED) {
+        'D'
+    } else if status.contains(Status::INDEX_RENAMED) {
+        'R'
+    } else if status.contains(Status::INDEX_TYPECHANGE) {
+        'T'
+    } else {
+        ' '
+    };
+    let worktree = if status.contains(Status::WT_MODIFIED) {
+        'M'
+    } else if status.contains(Status::WT_DELETED) {
+        'D'
+    } else if status.contains(Status::WT_RENAMED) {
+        'R'
+    } else if status.contains(Status::WT_TYPECHANGE) {
+        'T'
+    } else {
+        ' '
+    };
+    format!("{}{}", index, worktree)
+}
+
+/// A branch or tag advertised by a remote, as listed by `git ls-remote`
+#[derive(Debug, Clone, PartialEq)]
+pub struct RemoteRef {
+    pub name: String,
+    pub commit: String,
+    /// Where a symbolic ref such as HEAD points
+    pub symref_target: Option<String>,
+}
+
+/// List a remote's refs without a local repository, authenticating like the git CLI does
+pub fn list_remote(url: &str) -> GitResult<Vec<RemoteRef>> {
+    let remote_error = |error: git2::Error| GitError::Remote {
+        url: url.to_string(),
+        message: error.message().to_string(),
+    };
+
+    let mut remote = Remote::create_detached(url).map_err(remote_error)?;
+    let connection = remote
+        .connect_auth(Direction::Fetch, Some(credential_callbacks()), None)
+        .map_err(remote_error)?;
+
+    let refs = connection.list().map_err(remote_error)?;
+    Ok(refs
+        .iter()
+        .map(|head| RemoteRef {
+            name: head.name().to_string(),
+            commit: head.oid().to_string(),
+            symref_target: head.symref_target().map(str::to_string),
+        })
+        .collect())
+}
+
+/// Credentials from the ssh agent, the token from `stacks auth login` (github.com over HTTPS, as
+/// git commands get it) or the configured credential helper
+///
+/// libgit2 asks again after each rejected credential, so every kind is offered once; an agent
+/// key the server refuses would otherwise be retried forever.
+fn credential_callbacks<'a>() -> RemoteCallbacks<'a> {
+    let mut callbacks = RemoteCallbacks::new();
+    let mut tried = CredentialType::empty();
+    let mut token_tried = false;
+    callbacks.credentials(move |url, username, allowed| {
+        let untried = allowed - tried;
+        if untried.contains(CredentialType::SSH_KEY) {
+            tried |= CredentialType::SSH_KEY;
+            return Cred::ssh_key_from_agent(username.unwrap_or("git"));
+        }
+        if untried.contains(CredentialType::USER_PASS_PLAINTEXT) && !token_tried && url.starts_with(auth::GITHUB_HTTPS) {
+            token_tried = true;
+            if let Some(token) = auth::stored_token() {
+                return Cred::userpass_plaintext("x-access-token", &token);
+            }
+        }
+        if untried.contains(CredentialType::USER_PASS_PLAINTEXT) {
+            tried |= CredentialType::USER_PASS_PLAINTEXT;
+            let config = git2::Config::open_default()?;
+            return Cred::credential_helper(&config, url, username);
+        }
+        if !tried.contains(CredentialType::DEFAULT) {
+            tried |= CredentialType::DEFAULT;
+            return Cred::default();
+        }
+        Err(git2::Error::from_str(&format!("authentication to {} failed", url)))
+    });
+    callbacks
+}
+
+/// Run a git subcommand in `dir` and return its stdout
+///
+/// Only for operations libgit2 doesn't implement, such as `git subtree`, stashing, and
+/// worktree management; callers get the exit code and stderr as a typed error.
+pub fn run(dir: &Path, args: &[&str]) -> GitResult<String> {
+    let output = Command::new("git")
+        .current_dir(dir)
+        .args(args)
+        .logged_output()
+        .map_err(GitError::Spawn)?;
+
+    if !output.status.success() {
+        return Err(GitError::Command {
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+            code: output.status.code(),
+            stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+        });
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn git(dir: &Path, args: &[&str]) {
+        run(dir, &[&["-c", "user.name=test", "-c", "user.email=test@example.com"][..], args].concat()).unwrap();
+    }
+
+    #[test]
+    fn test_repository_queries() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let root = temp_dir.path();
+        git(root, &["init", "-q", "-b", "main"]);
+
+        let repo = Repo::discover(root).unwrap();
+        assert_eq!(repo.head_commit(), None);
+        assert_eq!(repo.find_commit_message(|_| true).unwrap(), None);
+
+        std::fs::create_dir_all(root.join("stacks/lint")).unwrap();
+        std::fs::write(root.join("stacks/lint/CLAUDE.md"), "lint").unwrap();
+        assert!(repo.has_changes("stacks/lint").unwrap());
+        assert!(!repo.has_changes("stacks/other").unwrap());
+        let changes = repo.changes_in(&root.join("stacks/lint")).unwrap();
+        assert_eq!(changes.len(), 1);
+        assert_eq!(porcelain_code(changes[0].0), "??");
+        assert_eq!(changes[0].1, "stacks/lint/CLAUDE.md");
+
+        git(root, &["add", "."]);
+        git(root, &["commit", "-q", "-m", "Add lint\n\ngit-subtree-dir: stacks/lint"]);
+        let squash = repo.head_commit().unwrap();
+        assert!(!repo.has_changes("stacks/lint").unwrap());
+        assert!(repo.ref_exists("refs/heads/main"));
+        assert_eq!(repo.resolve_commit("main").unwrap(), squash);
+
+        std::fs::write(root.join("stacks/lint/CLAUDE.md"), "lint, changed").unwrap();
+        std::fs::write(root.join("README.md"), "readme").unwrap();
+        git(root, &["add", "."]);
+        git(root, &["commit", "-q", "-m", "Local changes"]);
+
+        let (sha, message) = repo
+            .find_commit_message(|line| line == "git-subtree-dir: stacks/lint")
+            .unwrap()
+            .unwrap();
+        assert_eq!(sha, squash);
+        assert!(message.starts_with("Add lint"));
+        assert_eq!(repo.find_recent_commit_message(|line| line == "git-subtree-dir: stacks/lint", 1).unwrap(), None);
+        assert!(repo.find_recent_commit_message(|line| line == "git-subtree-dir: stacks/lint", 2).unwrap().is_some());
+        assert_eq!(repo.count_commits_touching(&squash, "stacks/lint").unwrap(), 1);
+        assert_eq!(repo.count_commits_touching(&squash, "stacks/other").unwrap(), 0);
+        assert!(!repo.merge_in_progress());
+        assert!(!repo.is_linked_worktree());
+    }
+
+    #[test]
+    fn test_typed_errors() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+
+        assert!(matches!(Repo::discover(temp_dir.path()), Err(GitError::NotARepository(_))));
+        match run(temp_dir.path(), &["rev-parse", "--git-dir"]) {
+            Err(GitError::Command { code, .. }) => assert_eq!(code, Some(128)),
+            other => panic!("expected a command error, got {:?}", other.map(|_| ())),
+        }
+    }
+}
@@ -0,0 +1,260 @@
+use std::collections::BTreeMap;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use super::events::{self, Event};
+use crate::config::load_project_config;
+use crate::error::StacksError;
+use crate::utils::process::Process;
+
+/// Lifecycle operation a hook runs around
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Operation {
+    Checkout,
+    Pull,
+    Push,
+    Cleanup,
+}
+
+impl Operation {
+    pub fn all() -> [Operation; 4] {
+        [Operation::Checkout, Operation::Pull, Operation::Push, Operation::Cleanup]
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Operation::Checkout => "checkout",
+            Operation::Pull => "pull",
+            Operation::Push => "push",
+            Operation::Cleanup => "cleanup",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Phase {
+    Pre,
+    Post,
+}
+
+impl Phase {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Phase::Pre => "pre",
+            Phase::Post => "post",
+        }
+    }
+}
+
+/// What happens when a hook command fails
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+pub enum HookFailure {
+    /// Print a warning and carry on
+    #[default]
+    #[serde(rename = "warn")]
+    Warn,
+    /// Stop: a failed pre-hook cancels the operation, a failed post-hook fails the command
+    #[serde(rename = "abort")]
+    Abort,
+}
+
+/// One command or a list of commands
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(untagged)]
+pub enum HookCommands {
+    One(String),
+    Many(Vec<String>),
+}
+
+impl HookCommands {
+    pub fn commands(&self) -> Vec<&str> {
+        match self {
+            HookCommands::One(command) => vec![command.as_str()],
+            HookCommands::Many(commands) => commands.iter().map(String::as_str).collect(),
+        }
+    }
+}
+
+/// `[hooks]` section of `.stacks/config.toml`
+///
+/// ```toml
+/// [hooks]
+/// on-failure = "abort"
+/// post-pull = "npm install"
+/// post-push = ["./scripts/notify.sh", "echo pushed $STACKS_HOOK_STACKS"]
+/// ```
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct HooksConfig {
+    #[serde(default, rename = "on-failure")]
+    pub on_failure: HookFailure,
+    /// `pre-<operation>`/`post-<operation>` to shell commands
+    #[serde(flatten)]
+    pub commands: BTreeMap<String, HookCommands>,
+}
+
+impl HooksConfig {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Commands configured for an operation and phase
+    pub fn commands_for(&self, phase: Phase, operation: Operation) -> Vec<&str> {
+        self.commands
+            .get(&hook_name(phase, operation))
+            .map(HookCommands::commands)
+            .unwrap_or_default()
+    }
+
+    /// Reject hook names that no operation would ever run
+    pub fn validate(&self) -> Result<()> {
+        let known: Vec<String> = Operation::all()
+            .iter()
+            .flat_map(|operation| [hook_name(Phase::Pre, *operation), hook_name(Phase::Post, *operation)])
+            .collect();
+        for name in self.commands.keys() {
+            if !known.contains(name) {
+                return Err(StacksError::validation(format!("Unknown hook '{}' in [hooks]", name))
+                    .with_hint(format!("Valid hooks: {}", known.join(", ")))
+                    .into());
+            }
+        }
+        Ok(())
+    }
+}
+
+/// `pre-pull`, `post-checkout`, ...
+pub fn hook_name(phase: Phase, operation: Operation) -> String {
+    format!("{}-{}", phase.as_str(), operation.as_str())
+}
+
+/// What an operation is doing, passed to its hooks as `STACKS_HOOK_*` environment variables
+#[derive(Debug, Clone)]
+pub struct HookContext {
+    pub operation: Operation,
+    pub stacks: Vec<String>,
+    env: Vec<(String, String)>,
+}
+
+impl HookContext {
+    pub fn new(operation: Operation) -> Self {
+        Self {
+            operation,
+            stacks: Vec::new(),
+            env: Vec::new(),
+        }
+    }
+
+    pub fn with_stacks(mut self, stacks: Vec<String>) -> Self {
+        self.stacks = stacks;
+        self
+    }
+
+    /// Extra variable, exported as `STACKS_HOOK_<NAME>`
+    pub fn with_env(mut self, name: &str, value: impl Into<String>) -> Self {
+        self.env.push((format!("STACKS_HOOK_{}", name.to_uppercase()), value.into()));
+        self
+    }
+
+    fn variables(&self, phase: Phase) -> Vec<(String, String)> {
+        let project_root = std::env::current_dir()
+            .map(|dir| dir.display().to_string())
+            .unwrap_or_default();
+        let mut variables = vec![
+            ("STACKS_HOOK".to_string(), hook_name(phase, self.operation)),
+            ("STACKS_HOOK_OPERATION".to_string(), self.operation.as_str().to_string()),
+            ("STACKS_HOOK_PHASE".to_string(), phase.as_str().to_string()),
+            ("STACKS_HOOK_STACKS".to_string(), self.stacks.join(",")),
+            ("STACKS_HOOK_PROJECT_ROOT".to_string(), project_root),
+        ];
+        variables.extend(self.env.iter().cloned());
+        variables
+    }
+}
+
+/// Run the project's hooks for `phase` of an operation
+pub fn run(phase: Phase, context: &HookContext) -> Result<()> {
+    let hooks = load_project_config()?.hooks;
+    hooks.validate()?;
+    run_hooks(&hooks, phase, context)
+}
+
+fn run_hooks(hooks: &HooksConfig, phase: Phase, context: &HookContext) -> Result<()> {
+    let name = hook_name(phase, context.operation);
+    for command in hooks.commands_for(phase, context.operation) {
+        events::report(Event::HookStarted { hook: name.clone(), command: command.to_string() });
+
+        let (shell, flag) = shell();
+        let mut process = Process::new(shell).args([flag, command]);
+        for (key, value) in context.variables(phase) {
+            process = process.env(key, value);
+        }
+        let output = process.output()?;
+        for line in output.stdout.lines().chain(output.stderr.lines()) {
+            events::report(Event::HookOutput { line: line.to_string() });
+        }
+        if output.success() {
+            continue;
+        }
+
+        let reason = match output.code {
+            Some(code) => format!("`{}` exited with status {}", command, code),
+            None => format!("`{}` was killed by a signal", command),
+        };
+        match hooks.on_failure {
+            HookFailure::Warn => events::report(Event::HookFailed { hook: name.clone(), reason }),
+            HookFailure::Abort => {
+                return Err(StacksError::validation(format!("Hook {} failed", name))
+                    .with_reason(reason)
+                    .with_hint("Fix the hook command in .stacks/config.toml, or set `on-failure = \"warn\"` under [hooks]")
+                    .into());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Shell and its run-a-command flag
+fn shell() -> (&'static str, &'static str) {
+    if cfg!(windows) { ("cmd", "/C") } else { ("sh", "-c") }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(content: &str) -> HooksConfig {
+        toml::from_str(content).unwrap()
+    }
+
+    #[test]
+    fn test_parse_hooks() {
+        let hooks = parse(r#"
+on-failure = "abort"
+pre-pull = "echo one"
+post-push = ["echo two", "echo three"]
+"#);
+        assert_eq!(hooks.on_failure, HookFailure::Abort);
+        assert_eq!(hooks.commands_for(Phase::Pre, Operation::Pull), vec!["echo one"]);
+        assert_eq!(hooks.commands_for(Phase::Post, Operation::Push), vec!["echo two", "echo three"]);
+        assert!(hooks.commands_for(Phase::Post, Operation::Checkout).is_empty());
+        assert!(hooks.validate().is_ok());
+
+        assert!(parse("post-pul = \"typo\"").validate().is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_failures_warn_or_abort() {
+        let context = HookContext::new(Operation::Pull)
+            .with_stacks(vec!["lint".to_string(), "docs".to_string()]);
+
+        let passing = parse(r#"pre-pull = "test \"$STACKS_HOOK_STACKS\" = lint,docs && test \"$STACKS_HOOK\" = pre-pull""#);
+        assert!(run_hooks(&passing, Phase::Pre, &context).is_ok());
+
+        let failing = parse("pre-pull = \"exit 1\"");
+        assert!(run_hooks(&failing, Phase::Pre, &context).is_ok());
+
+        let aborting = HooksConfig { on_failure: HookFailure::Abort, ..failing };
+        assert!(run_hooks(&aborting, Phase::Pre, &context).is_err());
+    }
+}
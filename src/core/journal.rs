@@ -0,0 +1,231 @@
+use std::path::{Path, PathBuf};
+use anyhow::{Result, Context};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::git::Repo;
+use super::settings_merger::GRANTS_FILE;
+use crate::utils::files;
+
+/// Undoable operations of a project, most recent last
+pub const JOURNAL_FILE: &str = ".stacks/journal.json";
+
+/// Older entries are dropped past this many
+const MAX_ENTRIES: usize = 10;
+
+/// Files an operation may rewrite wholesale, relative to the project root
+fn backed_up_files() -> Vec<PathBuf> {
+    let claude_dir = Path::new(".claude");
+    vec![claude_dir.join(".local-settings.json"), claude_dir.join(GRANTS_FILE)]
+}
+
+/// A project file as it was before an operation
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct FileBackup {
+    /// Relative to the project root
+    pub path: PathBuf,
+    /// None when the file didn't exist, so undoing deletes it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub content: Option<String>,
+}
+
+/// What an operation changed for one stack
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct StackChange {
+    pub stack: String,
+    /// The stack's subtree (and its stacks.lock entry) was added by the operation
+    #[serde(default)]
+    pub added_subtree: bool,
+    /// Agent and command links the operation created, relative to the project root
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub links: Vec<PathBuf>,
+    /// The operation added the stack's CLAUDE.md import or section
+    #[serde(default)]
+    pub added_import: bool,
+}
+
+impl StackChange {
+    pub fn new(stack: &str) -> Self {
+        Self { stack: stack.to_string(), ..Self::default() }
+    }
+}
+
+/// One undoable operation, e.g. a checkout of several stacks
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct JournalEntry {
+    pub id: String,
+    pub operation: String,
+    pub recorded_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub backups: Vec<FileBackup>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub stacks: Vec<StackChange>,
+}
+
+impl JournalEntry {
+    /// Start an entry for `operation` in the project at `root`, backing up the files it may rewrite
+    pub fn begin(root: &Path, operation: &str) -> Result<Self> {
+        let recorded_at = Utc::now();
+        let backups = backed_up_files()
+            .into_iter()
+            .map(|path| {
+                let full_path = root.join(&path);
+                let content = match full_path.exists() {
+                    true => Some(std::fs::read_to_string(&full_path)
+                        .with_context(|| format!("Failed to back up {}", full_path.display()))?),
+                    false => None,
+                };
+                Ok(FileBackup { path, content })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        Ok(Self {
+            id: recorded_at.format("%Y%m%dT%H%M%S%.3f").to_string(),
+            operation: operation.to_string(),
+            recorded_at,
+            backups,
+            stacks: Vec::new(),
+        })
+    }
+
+    /// Put the backed-up files back as they were before the operation
+    pub fn restore_backups(&self, root: &Path) -> Result<Vec<PathBuf>> {
+        let mut restored = Vec::new();
+        for backup in &self.backups {
+            let path = root.join(&backup.path);
+            let current = std::fs::read_to_string(&path).ok();
+            if current == backup.content {
+                continue;
+            }
+            match &backup.content {
+                Some(content) => files::write(&path, content)
+                    .with_context(|| format!("Failed to restore {}", path.display()))?,
+                None => files::remove_file(&path)
+                    .with_context(|| format!("Failed to remove {}", path.display()))?,
+            }
+            restored.push(backup.path.clone());
+        }
+        Ok(restored)
+    }
+
+    pub fn stack_names(&self) -> Vec<String> {
+        self.stacks.iter().map(|change| change.stack.clone()).collect()
+    }
+}
+
+/// The project's undoable operations, kept out of git
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct Journal {
+    #[serde(default, rename = "entry")]
+    pub entries: Vec<JournalEntry>,
+}
+
+impl Journal {
+    pub fn path(root: &Path) -> PathBuf {
+        root.join(JOURNAL_FILE)
+    }
+
+    /// The project's journal, or an empty one if nothing was recorded yet
+    pub fn load(root: &Path) -> Result<Self> {
+        let path = Self::path(root);
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse {}", path.display()))
+    }
+
+    pub fn save(&self, root: &Path) -> Result<()> {
+        let path = Self::path(root);
+        if let Some(parent) = path.parent() {
+            files::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        if let Ok(repo) = Repo::discover(root) {
+            // Backups can hold personal settings; subtree operations also need a clean working tree
+            if let Err(error) = repo.exclude(&format!("/{}", JOURNAL_FILE)) {
+                tracing::debug!("failed to exclude the journal from git: {}", error);
+            }
+        }
+        files::write(&path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Add or update `entry` and save the journal, dropping the oldest entries past the limit
+    ///
+    /// Operations record their entry after each step, so a failure halfway stays undoable.
+    pub fn record(root: &Path, entry: &JournalEntry) -> Result<()> {
+        let mut journal = Self::load(root)?;
+        journal.upsert(entry.clone());
+        journal.save(root)
+    }
+
+    fn upsert(&mut self, entry: JournalEntry) {
+        match self.entries.iter_mut().find(|existing| existing.id == entry.id) {
+            Some(existing) => *existing = entry,
+            None => self.entries.push(entry),
+        }
+        let excess = self.entries.len().saturating_sub(MAX_ENTRIES);
+        self.entries.drain(..excess);
+    }
+
+    pub fn last(&self) -> Option<&JournalEntry> {
+        self.entries.last()
+    }
+
+    /// Forget the most recent entry once it has been undone
+    pub fn pop(&mut self) -> Option<JournalEntry> {
+        self.entries.pop()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_backups_restore_files() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        std::fs::create_dir_all(root.join(".claude")).unwrap();
+        let settings = root.join(".claude/.local-settings.json");
+        std::fs::write(&settings, "{}").unwrap();
+
+        let entry = JournalEntry::begin(root, "checkout").unwrap();
+        std::fs::write(&settings, r#"{"permissions":{"allow":["Bash(npm:*)"]}}"#).unwrap();
+        std::fs::write(root.join(".claude").join(GRANTS_FILE), "{}").unwrap();
+
+        let restored = entry.restore_backups(root).unwrap();
+        assert_eq!(restored.len(), 2);
+        assert_eq!(std::fs::read_to_string(&settings).unwrap(), "{}");
+        assert!(!root.join(".claude").join(GRANTS_FILE).exists());
+        assert!(entry.restore_backups(root).unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_record_updates_and_trims() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+
+        let mut entry = JournalEntry::begin(root, "checkout").unwrap();
+        Journal::record(root, &entry).unwrap();
+        entry.stacks.push(StackChange::new("ts-lint"));
+        Journal::record(root, &entry).unwrap();
+
+        let journal = Journal::load(root).unwrap();
+        assert_eq!(journal.entries.len(), 1);
+        assert_eq!(journal.last().unwrap().stack_names(), vec!["ts-lint"]);
+
+        let mut journal = Journal::default();
+        for index in 0..MAX_ENTRIES + 2 {
+            let mut entry = JournalEntry::begin(root, "checkout").unwrap();
+            entry.id = format!("entry-{}", index);
+            journal.upsert(entry);
+        }
+        assert_eq!(journal.entries.len(), MAX_ENTRIES);
+        assert_eq!(journal.entries[0].id, "entry-2");
+        assert_eq!(journal.pop().unwrap().id, format!("entry-{}", MAX_ENTRIES + 1));
+    }
+}
@@ -0,0 +1,173 @@
+use std::collections::BTreeMap;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+use crate::error::StacksError;
+use crate::utils::process::shell_quote;
+
+/// Values claude accepts for --permission-mode
+pub const PERMISSION_MODES: [&str; 4] = ["default", "acceptEdits", "plan", "bypassPermissions"];
+
+/// How claude is started in one pane: its model, permission mode and any extra flags
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct LaunchProfile {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub model: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub permission_mode: Option<String>,
+    /// Further claude flags, e.g. ["--add-dir", "../shared"]
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub args: Vec<String>,
+}
+
+impl LaunchProfile {
+    /// `command` with the profile's flags right after `claude`, replacing flags it sets already
+    ///
+    /// The model and args are shell-quoted, since panes run the command through a shell.
+    /// Commands that don't start with claude (e.g. a custom wrapper) are left alone.
+    pub fn apply(&self, command: &str) -> String {
+        let rest = match command.strip_prefix("claude") {
+            Some(rest) if rest.is_empty() || rest.starts_with(' ') => rest,
+            _ => return command.to_string(),
+        };
+        let mut rest = rest.to_string();
+        let mut flags = Vec::new();
+        if let Some(model) = &self.model {
+            rest = remove_flag(&rest, "--model");
+            flags.push(format!("--model {}", shell_quote(model)));
+        }
+        if let Some(mode) = &self.permission_mode {
+            rest = remove_flag(&rest, "--permission-mode");
+            flags.push(format!("--permission-mode {}", mode));
+        }
+        flags.extend(self.args.iter().map(|arg| shell_quote(arg)));
+        match flags.is_empty() {
+            true => command.to_string(),
+            false => format!("claude {}{}", flags.join(" "), rest),
+        }
+    }
+}
+
+/// Remove `flag <value>` and `flag=<value>` from a command line
+fn remove_flag(command: &str, flag: &str) -> String {
+    let joined = format!("{}=", flag);
+    let mut words = command.split(' ');
+    let mut kept = Vec::new();
+    while let Some(word) = words.next() {
+        if word == flag {
+            words.next();
+        } else if !word.starts_with(&joined) {
+            kept.push(word);
+        }
+    }
+    kept.join(" ")
+}
+
+/// `[launch]` section of config.toml: named profiles and which one each claude pane uses
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct LaunchConfig {
+    /// Profile per claude pane, in the order panes are created; panes beyond the list use the last one
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub panes: Vec<String>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub profiles: BTreeMap<String, LaunchProfile>,
+}
+
+impl LaunchConfig {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    /// Profile of the pane at `index`, if any is assigned
+    pub fn profile_for_pane(&self, index: usize) -> Result<Option<&LaunchProfile>> {
+        let name = match self.panes.get(index).or(self.panes.last()) {
+            Some(name) => name,
+            None => return Ok(None),
+        };
+        let profile = match self.profiles.get(name) {
+            Some(profile) => profile,
+            None => {
+                return Err(StacksError::validation(format!("Unknown launch profile: {}", name))
+                    .with_hint("Define it as [launch.profiles.<name>] in the config file, or fix launch-panes")
+                    .into())
+            }
+        };
+        if let Some(mode) = &profile.permission_mode {
+            if !PERMISSION_MODES.contains(&mode.as_str()) {
+                return Err(StacksError::validation(format!("Launch profile {} has an unknown permission mode: {}", name, mode))
+                    .with_hint(format!("Use one of {}", PERMISSION_MODES.join(", ")))
+                    .into());
+            }
+        }
+        Ok(Some(profile))
+    }
+
+    /// Claude command for the pane at `index`, starting from the shared `command`
+    pub fn command_for_pane(&self, command: &str, index: usize) -> Result<String> {
+        Ok(match self.profile_for_pane(index)? {
+            Some(profile) => profile.apply(command),
+            None => command.to_string(),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> LaunchConfig {
+        toml::from_str(
+            r#"
+panes = ["plan", "build"]
+
+[profiles.plan]
+model = "opus"
+permission_mode = "plan"
+
+[profiles.build]
+model = "sonnet"
+args = ["--verbose"]
+"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_apply() {
+        let plan = &config().profiles["plan"];
+        assert_eq!(
+            plan.apply("claude --permission-mode acceptEdits"),
+            "claude --model opus --permission-mode plan"
+        );
+        assert_eq!(
+            plan.apply("claude --permission-mode acceptEdits \"fix the login bug\""),
+            "claude --model opus --permission-mode plan \"fix the login bug\""
+        );
+        assert_eq!(plan.apply("claude"), "claude --model opus --permission-mode plan");
+        assert_eq!(plan.apply("claude --model=haiku --verbose"), "claude --model opus --permission-mode plan --verbose");
+        assert_eq!(plan.apply("my-claude-wrapper"), "my-claude-wrapper");
+
+        let shared = LaunchProfile {
+            model: Some("claude-opus-4-1; rm -rf ~".to_string()),
+            args: vec!["--add-dir".to_string(), "../shared docs".to_string()],
+            ..Default::default()
+        };
+        assert_eq!(
+            shared.apply("claude"),
+            "claude --model 'claude-opus-4-1; rm -rf ~' --add-dir '../shared docs'"
+        );
+    }
+
+    #[test]
+    fn test_command_for_pane() {
+        let config = config();
+        let base = "claude --permission-mode acceptEdits";
+        assert_eq!(config.command_for_pane(base, 0).unwrap(), "claude --model opus --permission-mode plan");
+        assert_eq!(config.command_for_pane(base, 1).unwrap(), "claude --model sonnet --verbose --permission-mode acceptEdits");
+        assert_eq!(config.command_for_pane(base, 3).unwrap(), "claude --model sonnet --verbose --permission-mode acceptEdits");
+        assert_eq!(LaunchConfig::default().command_for_pane(base, 0).unwrap(), base);
+
+        let broken = LaunchConfig { panes: vec!["missing".to_string()], ..Default::default() };
+        assert!(broken.command_for_pane(base, 0).is_err());
+    }
+}
@@ -0,0 +1,175 @@
+use std::path::{Path, PathBuf};
+use anyhow::{Result, Context};
+use serde::{Deserialize, Serialize};
+
+use super::remote_stack_manager::StackMetadata;
+use super::upstream;
+use crate::utils::process::Process;
+use crate::utils::files;
+
+pub const LOCKFILE_NAME: &str = "stacks.lock";
+
+/// Project-level record of where each checked-out stack came from
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct StacksLock {
+    #[serde(default, rename = "stack")]
+    pub stacks: Vec<StackMetadata>,
+}
+
+impl StacksLock {
+    /// Load stacks.lock from the current directory, or an empty lock if it doesn't exist
+    pub fn load() -> Result<Self> {
+        Self::load_from(&lockfile_path()?)
+    }
+
+    pub fn load_from(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        toml::from_str(&content)
+            .with_context(|| format!("Failed to parse {}", path.display()))
+    }
+
+    pub fn save(&self) -> Result<()> {
+        self.save_to(&lockfile_path()?)
+    }
+
+    pub fn save_to(&self, path: &Path) -> Result<()> {
+        let content = toml::to_string_pretty(self)
+            .context("Failed to serialize stacks.lock")?;
+        let content = format!("# This file is generated by stacks. Do not edit it by hand.\n\n{}", content);
+        files::write(path, content)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    pub fn get(&self, stack_name: &str) -> Option<&StackMetadata> {
+        self.stacks.iter().find(|stack| stack.stack_name == stack_name)
+    }
+
+    /// Insert or replace a stack entry, keeping entries sorted by name
+    pub fn upsert(&mut self, metadata: StackMetadata) {
+        self.stacks.retain(|stack| stack.stack_name != metadata.stack_name);
+        self.stacks.push(metadata);
+        self.stacks.sort_by(|a, b| a.stack_name.cmp(&b.stack_name));
+    }
+
+    pub fn remove(&mut self, stack_name: &str) -> Option<StackMetadata> {
+        let index = self.stacks.iter().position(|stack| stack.stack_name == stack_name)?;
+        Some(self.stacks.remove(index))
+    }
+}
+
+pub fn lockfile_path() -> Result<PathBuf> {
+    Ok(std::env::current_dir()?.join(LOCKFILE_NAME))
+}
+
+/// Resolve where a stack comes from: stacks.lock, then legacy per-stack metadata, then naming convention
+pub fn resolve_stack_metadata(stack_name: &str) -> StackMetadata {
+    resolve_stack_metadata_in(Path::new("."), stack_name)
+}
+
+/// Like `resolve_stack_metadata`, for a project checked out at `project_root` (e.g. another worktree)
+pub fn resolve_stack_metadata_in(project_root: &Path, stack_name: &str) -> StackMetadata {
+    recorded_stack_metadata_in(project_root, stack_name)
+        .unwrap_or_else(|| StackMetadata::from_convention(stack_name))
+}
+
+/// Where a stack comes from according to stacks.lock or legacy metadata; None when only the naming convention knows
+pub fn recorded_stack_metadata(stack_name: &str) -> Option<StackMetadata> {
+    recorded_stack_metadata_in(Path::new("."), stack_name)
+}
+
+fn recorded_stack_metadata_in(project_root: &Path, stack_name: &str) -> Option<StackMetadata> {
+    if let Ok(lock) = StacksLock::load_from(&project_root.join(LOCKFILE_NAME)) {
+        if let Some(metadata) = lock.get(stack_name) {
+            return Some(metadata.clone());
+        }
+    }
+
+    let legacy_path = project_root.join("stacks").join(stack_name).join(".stack-metadata.json");
+    std::fs::read_to_string(legacy_path)
+        .ok()
+        .and_then(|content| serde_json::from_str(&content).ok())
+}
+
+/// Record a stack in stacks.lock (with its installed upstream commit) and commit the lockfile
+pub fn record_stack(mut metadata: StackMetadata) -> Result<()> {
+    metadata.commit = upstream::installed_commit(&metadata.stack_name)?;
+
+    let mut lock = StacksLock::load()?;
+    lock.upsert(metadata.clone());
+    lock.save()?;
+
+    commit_lockfile(&format!("chore(stacks): lock {}", metadata.stack_name))
+}
+
+/// Refresh the recorded upstream commit of an already-installed stack
+pub fn update_installed_commit(stack_name: &str) -> Result<()> {
+    record_stack(resolve_stack_metadata(stack_name))
+}
+
+/// Drop a removed stack from stacks.lock and commit the lockfile
+pub fn forget_stack(stack_name: &str) -> Result<()> {
+    let mut lock = StacksLock::load()?;
+    if lock.remove(stack_name).is_none() {
+        return Ok(());
+    }
+    lock.save()?;
+
+    commit_lockfile(&format!("chore(stacks): unlock {}", stack_name))
+}
+
+/// Commit stacks.lock on its own so subtree operations keep a clean working tree
+fn commit_lockfile(message: &str) -> Result<()> {
+    Process::git()
+        .args(["add", LOCKFILE_NAME])
+        .run()
+        .context("Failed to stage stacks.lock")?;
+
+    let unchanged = Process::git()
+        .args(["diff", "--cached", "--quiet", "--", LOCKFILE_NAME])
+        .read_only()
+        .output()
+        .context("Failed to check stacks.lock changes")?
+        .success();
+
+    if unchanged {
+        return Ok(());
+    }
+
+    Process::git()
+        .args(["commit", "-m", message, "--", LOCKFILE_NAME])
+        .run()
+        .context("Failed to commit stacks.lock")?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_lockfile_roundtrip_sorted() {
+        let temp_dir = TempDir::new().unwrap();
+        let path = temp_dir.path().join(LOCKFILE_NAME);
+
+        let mut lock = StacksLock::default();
+        lock.upsert(StackMetadata::from_convention("zeta-stack"));
+        lock.upsert(StackMetadata::from_convention("alpha-stack"));
+        lock.upsert(StackMetadata::from_convention("zeta-stack"));
+        lock.save_to(&path).unwrap();
+
+        let loaded = StacksLock::load_from(&path).unwrap();
+        let names: Vec<&str> = loaded.stacks.iter().map(|s| s.stack_name.as_str()).collect();
+        assert_eq!(names, vec!["alpha-stack", "zeta-stack"]);
+        assert_eq!(
+            loaded.get("alpha-stack").unwrap().source_repo,
+            "git@github.com:csaben/alpha-stack.git"
+        );
+    }
+}
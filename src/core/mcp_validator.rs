@@ -1,10 +1,19 @@
 use std::collections::HashMap;
-use std::process::Command;
 use anyhow::{Result, Context};
 use serde_json::Value;
 
+use super::stack_manager::Stack;
+use crate::utils::process::Process;
+
 pub struct McpValidator;
 
+/// A server from `claude mcp list`; `healthy` is None when the output has no health result
+#[derive(Debug, Clone, PartialEq)]
+pub struct InstalledMcpServer {
+    pub name: String,
+    pub healthy: Option<bool>,
+}
+
 #[derive(Debug, Clone)]
 pub struct McpServer {
     pub name: String,
@@ -34,6 +43,12 @@ impl McpValidator {
         Ok(missing_servers)
     }
 
+    /// MCP servers referenced in the project's settings, installed or not
+    pub async fn required_servers(&self) -> Result<Vec<McpServer>> {
+        let settings = self.load_merged_settings().await?;
+        self.extract_mcp_servers_from_settings(&settings)
+    }
+
     /// Load and merge all settings files to get the complete MCP configuration
     async fn load_merged_settings(&self) -> Result<Value> {
         let mut merged = serde_json::Value::Object(serde_json::Map::new());
@@ -139,32 +154,41 @@ impl McpValidator {
 
     /// Get list of currently installed MCP servers
     async fn get_installed_mcp_servers(&self) -> Result<Vec<String>> {
-        let output = Command::new("claude")
+        Ok(self.list_installed_servers().await?
+            .into_iter()
+            .map(|server| server.name)
+            .collect())
+    }
+
+    /// Installed MCP servers with the health reported by `claude mcp list`
+    pub async fn list_installed_servers(&self) -> Result<Vec<InstalledMcpServer>> {
+        let output = Process::claude()
             .args(["mcp", "list"])
+            .read_only()
             .output()
             .context("Failed to run 'claude mcp list'")?;
 
-        if !output.status.success() {
+        if !output.success() {
             // If claude mcp list fails, assume no servers are installed
             return Ok(Vec::new());
         }
 
-        let output_str = String::from_utf8_lossy(&output.stdout);
-        let servers: Vec<String> = output_str
-            .lines()
-            .filter_map(|line| {
-                // Parse the output format of `claude mcp list`
-                // This is a simplified parser - may need adjustment based on actual format
-                if line.trim().is_empty() || line.starts_with("No servers") {
-                    None
-                } else {
-                    // Extract server name (assuming first word is the name)
-                    line.split_whitespace().next().map(|s| s.to_string())
-                }
-            })
-            .collect();
+        Ok(parse_mcp_list(&output.stdout))
+    }
 
-        Ok(servers)
+    /// MCP servers a single stack's settings require
+    pub async fn stack_required_servers(&self, stack: &Stack) -> Result<Vec<McpServer>> {
+        let settings_path = stack.claude_dir.join(".local-settings.json");
+        if !settings_path.exists() {
+            return Ok(Vec::new());
+        }
+
+        let content = tokio::fs::read_to_string(&settings_path)
+            .await
+            .with_context(|| format!("Failed to read {}", settings_path.display()))?;
+        let settings: Value = serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse {}", settings_path.display()))?;
+        self.extract_mcp_servers_from_settings(&settings)
     }
 
     /// Generate installation commands for missing MCP servers
@@ -214,7 +238,7 @@ impl McpValidator {
     }
 
     /// Static helper for merging JSON values
-    fn merge_json_static(target: &mut Value, source: Value) {
+    pub(crate) fn merge_json_static(target: &mut Value, source: Value) {
         match (target, source) {
             (Value::Object(target_map), Value::Object(source_map)) => {
                 for (key, value) in source_map {
@@ -236,4 +260,51 @@ impl McpValidator {
             (target_val, source_val) => *target_val = source_val,
         }
     }
-}
\ No newline at end of file
+}
+
+/// Parse `claude mcp list` output, e.g. `postgres: npx -y @modelcontextprotocol/server-postgres - ✓ Connected`
+fn parse_mcp_list(output: &str) -> Vec<InstalledMcpServer> {
+    output
+        .lines()
+        .map(|line| line.trim())
+        .filter(|line| !line.is_empty() && !line.starts_with("No ") && !line.starts_with("Checking"))
+        .filter_map(|line| {
+            let (name, rest) = line.split_once(':').unwrap_or((line, ""));
+            let name = name.trim();
+            if name.is_empty() || name.contains(char::is_whitespace) {
+                return None;
+            }
+
+            let healthy = if rest.contains('✗') || rest.contains("Failed") {
+                Some(false)
+            } else if rest.contains('✓') || rest.contains("Connected") {
+                Some(true)
+            } else {
+                None
+            };
+
+            Some(InstalledMcpServer { name: name.to_string(), healthy })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_mcp_list() {
+        let output = "Checking MCP server health...\n\n\
+postgres: npx -y @modelcontextprotocol/server-postgres postgresql://localhost/db - ✓ Connected\n\
+sentry: https://mcp.sentry.dev/mcp (HTTP) - ✗ Failed to connect\n\
+redis\n";
+
+        let servers = parse_mcp_list(output);
+        assert_eq!(servers, vec![
+            InstalledMcpServer { name: "postgres".to_string(), healthy: Some(true) },
+            InstalledMcpServer { name: "sentry".to_string(), healthy: Some(false) },
+            InstalledMcpServer { name: "redis".to_string(), healthy: None },
+        ]);
+        assert!(parse_mcp_list("No MCP servers configured. Use `claude mcp add` to add a server.\n").is_empty());
+    }
+}
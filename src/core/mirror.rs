@@ -0,0 +1,240 @@
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use anyhow::{Result, Context};
+use serde::{Deserialize, Serialize};
+
+use super::cache;
+use crate::error::StacksError;
+use crate::utils::process::Process;
+
+/// Index of a mirror, at its root
+pub const INDEX_FILE: &str = "index.json";
+
+/// Mirrored stacks live in cache_root/mirror when the mirror is served over HTTP
+const DOWNLOAD_DIR: &str = "mirror";
+
+/// Sent as a bearer token when reading from or uploading to an HTTP mirror
+const TOKEN_ENV: &str = "STACKS_MIRROR_TOKEN";
+
+/// Time limit for a single mirror transfer; bundles can be large
+const TRANSFER_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Snapshot of a registry written by `stacks registry mirror`
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MirrorIndex {
+    /// `owner/repo@branch` of the mirrored registry
+    pub registry: String,
+    pub created_at: chrono::DateTime<chrono::Utc>,
+    pub stacks: Vec<MirroredStack>,
+}
+
+impl MirrorIndex {
+    pub fn get(&self, name: &str) -> Option<&MirroredStack> {
+        self.stacks.iter().find(|stack| stack.name == name)
+    }
+}
+
+/// A stack of the mirror, kept as a git bundle so subtree adds and pulls still have its history
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MirroredStack {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    /// Branch the bundle was taken from
+    pub branch: String,
+    pub commit: String,
+    /// Bundle path relative to the mirror root
+    pub bundle: String,
+}
+
+impl MirroredStack {
+    pub fn bundle_path(name: &str) -> String {
+        format!("stacks/{}.bundle", name)
+    }
+}
+
+/// Where a mirror is kept: a directory (or file:// URL), or an artifact store reached over HTTP
+#[derive(Debug, Clone, PartialEq)]
+pub enum MirrorLocation {
+    Dir(PathBuf),
+    Http(String),
+}
+
+impl MirrorLocation {
+    pub fn parse(location: &str) -> Self {
+        match location.starts_with("https://") || location.starts_with("http://") {
+            true => MirrorLocation::Http(location.trim_end_matches('/').to_string()),
+            false => MirrorLocation::Dir(PathBuf::from(location.strip_prefix("file://").unwrap_or(location))),
+        }
+    }
+
+    pub fn describe(&self) -> String {
+        match self {
+            MirrorLocation::Dir(dir) => dir.display().to_string(),
+            MirrorLocation::Http(url) => url.clone(),
+        }
+    }
+
+    fn url(base: &str, relative: &str) -> String {
+        format!("{}/{}", base, relative)
+    }
+
+    fn request(client: &reqwest::Client, method: reqwest::Method, url: &str) -> reqwest::RequestBuilder {
+        let request = client.request(method, url).timeout(TRANSFER_TIMEOUT);
+        match std::env::var(TOKEN_ENV) {
+            Ok(token) if !token.is_empty() => request.bearer_auth(token),
+            _ => request,
+        }
+    }
+
+    /// Read a file of the mirror
+    pub async fn read(&self, relative: &str) -> Result<Vec<u8>> {
+        match self {
+            MirrorLocation::Dir(dir) => {
+                let path = dir.join(relative);
+                std::fs::read(&path).with_context(|| format!("Failed to read {}", path.display()))
+            }
+            MirrorLocation::Http(base) => {
+                let url = Self::url(base, relative);
+                let response = Self::request(&reqwest::Client::new(), reqwest::Method::GET, &url)
+                    .send()
+                    .await
+                    .and_then(|response| response.error_for_status())
+                    .map_err(|error| StacksError::network(format!("Failed to download {}", url)).with_reason(error.to_string()))?;
+                Ok(response.bytes().await.with_context(|| format!("Failed to download {}", url))?.to_vec())
+            }
+        }
+    }
+
+    /// Write a file to the mirror; HTTP mirrors receive a PUT, as artifact stores expect
+    pub async fn write(&self, relative: &str, content: Vec<u8>) -> Result<()> {
+        match self {
+            MirrorLocation::Dir(dir) => {
+                let path = dir.join(relative);
+                if let Some(parent) = path.parent() {
+                    std::fs::create_dir_all(parent)
+                        .with_context(|| format!("Failed to create {}", parent.display()))?;
+                }
+                std::fs::write(&path, content).with_context(|| format!("Failed to write {}", path.display()))
+            }
+            MirrorLocation::Http(base) => {
+                let url = Self::url(base, relative);
+                Self::request(&reqwest::Client::new(), reqwest::Method::PUT, &url)
+                    .body(content)
+                    .send()
+                    .await
+                    .and_then(|response| response.error_for_status())
+                    .map_err(|error| StacksError::network(format!("Failed to upload {}", url)).with_reason(error.to_string()))?;
+                Ok(())
+            }
+        }
+    }
+
+    pub async fn load_index(&self) -> Result<MirrorIndex> {
+        let content = self.read(INDEX_FILE).await.map_err(|error| {
+            StacksError::validation(format!("No registry mirror at {}", self.describe()))
+                .with_reason(format!("{:#}", error))
+                .with_hint("Create one with 'stacks registry mirror <dir|URL>', or unset registry-mirror")
+        })?;
+        serde_json::from_slice(&content)
+            .with_context(|| format!("Failed to parse the mirror index at {}", self.describe()))
+    }
+
+    /// Local path of a mirrored stack's bundle, downloading it first from an HTTP mirror
+    pub async fn bundle(&self, stack: &MirroredStack) -> Result<PathBuf> {
+        match self {
+            MirrorLocation::Dir(dir) => Ok(dir.join(&stack.bundle)),
+            MirrorLocation::Http(_) => {
+                let path = cache::cache_root()?.join(DOWNLOAD_DIR).join(format!("{}.bundle", stack.name));
+                let content = self.read(&stack.bundle).await?;
+                if let Some(parent) = path.parent() {
+                    std::fs::create_dir_all(parent)
+                        .with_context(|| format!("Failed to create {}", parent.display()))?;
+                }
+                std::fs::write(&path, content).with_context(|| format!("Failed to write {}", path.display()))?;
+                Ok(path)
+            }
+        }
+    }
+}
+
+/// Bundle `branch` of `repo_url` (its default branch when `None`) into `bundle`
+///
+/// Returns the bundled branch and commit.
+pub fn create_bundle(repo_url: &str, branch: Option<&str>, bundle: &Path) -> Result<(String, String)> {
+    let work_dir = cache::cache_root()?.join(DOWNLOAD_DIR).join("work");
+    if work_dir.exists() {
+        std::fs::remove_dir_all(&work_dir).context("Failed to clear the mirror work directory")?;
+    }
+    std::fs::create_dir_all(work_dir.parent().unwrap_or(&work_dir)).context("Failed to create the mirror work directory")?;
+
+    let mut clone = Process::git().args(["clone", "--bare", "--quiet"]);
+    if let Some(branch) = branch {
+        clone = clone.args(["--branch", branch]);
+    }
+    clone.arg(repo_url).arg(&work_dir).run()
+        .map_err(|error| StacksError::git(format!("Failed to clone {}", repo_url)).with_reason(format!("{:#}", error)))?;
+
+    let result: Result<(String, String)> = (|| {
+        let branch = match branch {
+            Some(branch) => branch.to_string(),
+            None => Process::git().args(["symbolic-ref", "--short", "HEAD"]).current_dir(&work_dir).read_only().output()?
+                .stdout.trim().to_string(),
+        };
+        let commit = Process::git().args(["rev-parse", &format!("refs/heads/{}", branch)]).current_dir(&work_dir).read_only().output()?
+            .stdout.trim().to_string();
+        if let Some(parent) = bundle.parent() {
+            std::fs::create_dir_all(parent).with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        Process::git()
+            .args(["bundle", "create", "--quiet"])
+            .arg(bundle)
+            .arg(format!("refs/heads/{}", branch))
+            .current_dir(&work_dir)
+            .run()
+            .with_context(|| format!("Failed to bundle {}", repo_url))?;
+        Ok((branch, commit))
+    })();
+    std::fs::remove_dir_all(&work_dir).ok();
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_location() {
+        assert_eq!(MirrorLocation::parse("/srv/stacks-mirror"), MirrorLocation::Dir(PathBuf::from("/srv/stacks-mirror")));
+        assert_eq!(MirrorLocation::parse("file:///srv/mirror"), MirrorLocation::Dir(PathBuf::from("/srv/mirror")));
+        assert_eq!(
+            MirrorLocation::parse("https://artifacts.example.com/stacks/"),
+            MirrorLocation::Http("https://artifacts.example.com/stacks".to_string())
+        );
+    }
+
+    #[tokio::test]
+    async fn test_index_round_trip() {
+        let dir = std::env::temp_dir().join(format!("stacks-mirror-test-{}", std::process::id()));
+        let location = MirrorLocation::Dir(dir.clone());
+        let index = MirrorIndex {
+            registry: "csaben/claude-code-stacks@main".to_string(),
+            created_at: chrono::Utc::now(),
+            stacks: vec![MirroredStack {
+                name: "ts-lint-stack".to_string(),
+                description: Some("Lint TypeScript".to_string()),
+                branch: "main".to_string(),
+                commit: "abc123".to_string(),
+                bundle: MirroredStack::bundle_path("ts-lint-stack"),
+            }],
+        };
+        location.write(INDEX_FILE, serde_json::to_vec_pretty(&index).unwrap()).await.unwrap();
+
+        let loaded = location.load_index().await.unwrap();
+        let stack = loaded.get("ts-lint-stack").unwrap();
+        assert_eq!(stack.bundle, "stacks/ts-lint-stack.bundle");
+        assert_eq!(location.bundle(stack).await.unwrap(), dir.join("stacks/ts-lint-stack.bundle"));
+        assert!(loaded.get("missing").is_none());
+        std::fs::remove_dir_all(&dir).ok();
+    }
+}
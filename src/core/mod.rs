@@ -3,4 +3,38 @@ pub mod remote_stack_manager;
 pub mod symlink_manager;
 pub mod settings_merger;
 pub mod mcp_validator;
-pub mod permission_generator;
\ No newline at end of file
+pub mod permission_generator;
+pub mod stack_manifest;
+pub mod upstream;
+pub mod lockfile;
+pub mod update_check;
+pub mod worktrees;
+pub mod default_branch;
+pub mod sandbox;
+pub mod git;
+pub mod cache;
+pub mod hooks;
+pub mod daemon;
+pub mod sessions;
+pub mod transcripts;
+pub mod usage;
+pub mod notifications;
+pub mod prompts;
+pub mod session_manifest;
+pub mod stack_set;
+pub mod policy;
+pub mod review;
+pub mod signatures;
+pub mod auth;
+pub mod mirror;
+pub mod definitions;
+pub mod workspace;
+pub mod launch;
+pub mod container;
+pub mod journal;
+pub mod events;
+pub mod registry_client;
+pub mod schema;
+pub mod rpc;
+pub mod telemetry;
+pub mod search;
\ No newline at end of file
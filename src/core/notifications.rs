@@ -0,0 +1,132 @@
+use std::time::{Duration, Instant};
+use serde::{Deserialize, Serialize};
+
+use crate::utils::process::Process;
+
+/// Operations shorter than this finish while the user is still watching
+const DEFAULT_MIN_SECONDS: u32 = 30;
+
+/// `[notifications]` section of config.toml: where to announce finished long operations
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct NotificationsConfig {
+    /// Desktop notification (osascript on macOS, notify-send elsewhere)
+    #[serde(default)]
+    pub desktop: bool,
+    /// `tmux display-message` in the current client
+    #[serde(default)]
+    pub tmux: bool,
+    /// URL that receives a JSON POST (Slack-compatible `text` field)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub webhook: Option<String>,
+    /// Only notify when the operation took at least this many seconds
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub min_seconds: Option<u32>,
+}
+
+impl NotificationsConfig {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+
+    pub fn is_enabled(&self) -> bool {
+        self.desktop || self.tmux || self.webhook.is_some()
+    }
+
+    fn min_duration(&self) -> Duration {
+        Duration::from_secs(self.min_seconds.unwrap_or(DEFAULT_MIN_SECONDS) as u64)
+    }
+}
+
+/// One-line summary, e.g. "✅ stacks pull finished in 2m 05s"
+pub fn summary(operation: &str, elapsed: Duration, error: Option<&anyhow::Error>) -> String {
+    let seconds = elapsed.as_secs();
+    let took = match seconds {
+        0..=59 => format!("{}s", seconds),
+        _ => format!("{}m {:02}s", seconds / 60, seconds % 60),
+    };
+    match error {
+        None => format!("✅ stacks {} finished in {}", operation, took),
+        Some(error) => format!("❌ stacks {} failed after {}: {}", operation, took, error),
+    }
+}
+
+/// Announce the end of an operation on every configured channel, if it ran long enough
+///
+/// Notifications are best-effort: a failing channel is logged and never fails the command.
+pub async fn operation_finished(config: &NotificationsConfig, operation: &str, started: Instant, result: &anyhow::Result<()>) {
+    let elapsed = started.elapsed();
+    if !config.is_enabled() || elapsed < config.min_duration() {
+        return;
+    }
+    let message = summary(operation, elapsed, result.as_ref().err());
+
+    if config.desktop {
+        desktop("stacks", &message);
+    }
+    if config.tmux && std::env::var_os("TMUX").is_some() {
+        if let Err(error) = Process::tmux().args(["display-message", "-d", "5000", &message]).output() {
+            tracing::debug!("tmux notification failed: {:#}", error);
+        }
+    }
+    if let Some(url) = &config.webhook {
+        let payload = serde_json::json!({
+            "text": message,
+            "operation": operation,
+            "success": result.is_ok(),
+            "elapsed_secs": elapsed.as_secs(),
+        });
+        let response = reqwest::Client::new()
+            .post(url)
+            .json(&payload)
+            .timeout(Duration::from_secs(10))
+            .send()
+            .await;
+        match response {
+            Ok(response) if !response.status().is_success() => {
+                tracing::debug!("webhook notification returned {}", response.status())
+            }
+            Err(error) => tracing::debug!("webhook notification failed: {}", error),
+            Ok(_) => {}
+        }
+    }
+}
+
+/// Best-effort desktop notification; does nothing where no notifier is available
+pub fn desktop(title: &str, body: &str) {
+    let process = if cfg!(target_os = "macos") {
+        let script = format!(
+            "display notification \"{}\" with title \"{}\"",
+            body.replace('"', "'"),
+            title.replace('"', "'")
+        );
+        Process::new("osascript").args(["-e", &script])
+    } else {
+        Process::new("notify-send").args([title, body])
+    };
+    if let Err(error) = process.timeout(Duration::from_secs(5)).output() {
+        tracing::debug!("desktop notification failed: {:#}", error);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_summary() {
+        assert_eq!(summary("pull", Duration::from_secs(125), None), "✅ stacks pull finished in 2m 05s");
+        let error = anyhow::anyhow!("merge conflict");
+        assert_eq!(summary("cleanup", Duration::from_secs(42), Some(&error)), "❌ stacks cleanup failed after 42s: merge conflict");
+    }
+
+    #[test]
+    fn test_enabled_and_threshold() {
+        let config = NotificationsConfig::default();
+        assert!(!config.is_enabled());
+        assert_eq!(config.min_duration(), Duration::from_secs(30));
+
+        let config = NotificationsConfig { webhook: Some("https://example.com/hook".to_string()), min_seconds: Some(0), ..Default::default() };
+        assert!(config.is_enabled());
+        assert_eq!(config.min_duration(), Duration::ZERO);
+    }
+}
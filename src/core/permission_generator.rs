@@ -1,12 +1,216 @@
 use std::path::{Path, PathBuf};
 use anyhow::{Result, Context};
+use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 
+use super::remote_stack_manager::RemoteStackManager;
+use crate::utils::files;
+
+/// Project file that overrides the configured permission template
+pub const PROJECT_TEMPLATE_FILE: &str = ".stacks/permissions.toml";
+
+/// Allow/deny rules for feature worktrees; `{main}` and `{feature}` expand to the
+/// main project and feature worktree paths
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PermissionTemplate {
+    #[serde(default)]
+    pub allow: Vec<String>,
+    #[serde(default)]
+    pub deny: Vec<String>,
+}
+
+impl Default for PermissionTemplate {
+    /// Full access to the feature worktree, read-only access to the main directory
+    fn default() -> Self {
+        Self {
+            allow: rules(&[
+                "Read({main}/*)",
+                "Read({feature}/*)",
+                "Bash(cd:{main})",
+                "Bash(cd:{feature})",
+                "Bash(git:*)",
+                "Bash(stacks:cleanup)",
+                "Bash(touch:*)",
+                "Bash(mkdir:*)",
+                "Bash(echo:*)",
+                "Bash(cat:*)",
+                "Bash(vim:*)",
+                "Bash(nano:*)",
+                "Bash(cp:*)",
+                "Bash(mv:*)",
+                "Bash(rm:*)",
+            ]),
+            deny: rules(&[
+                "Write({main}/*)",
+                "Edit({main}/*)",
+                "MultiEdit({main}/*)",
+                "DeleteFile({main}/*)",
+                "Bash(rm:{main}/*)",
+                "Bash(mv:{main}/*)",
+                "Bash(cp:*/{main}/*)",
+            ]),
+        }
+    }
+}
+
+/// Owned copies of built-in rules
+fn rules(rules: &[&str]) -> Vec<String> {
+    rules.iter().map(|rule| rule.to_string()).collect()
+}
+
+impl PermissionTemplate {
+    /// Template for a project: `.stacks/permissions.toml`, then the configured template, then the default
+    pub fn resolve(project_root: &Path, configured: Option<PermissionTemplate>) -> Result<Self> {
+        let project_file = project_root.join(PROJECT_TEMPLATE_FILE);
+        if project_file.exists() {
+            let content = std::fs::read_to_string(&project_file)
+                .with_context(|| format!("Failed to read {}", project_file.display()))?;
+            return toml::from_str(&content)
+                .with_context(|| format!("Failed to parse {}", project_file.display()));
+        }
+
+        Ok(configured.unwrap_or_default())
+    }
+
+    fn render(rules: &[String], main: &str, feature: &str) -> Vec<String> {
+        rules.iter()
+            .map(|rule| rule.replace("{main}", main).replace("{feature}", feature))
+            .collect()
+    }
+}
+
+/// Built-in templates selectable with `stacks worktree --permissions <preset>`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PermissionPreset {
+    Strict,
+    Standard,
+    Permissive,
+}
+
+impl PermissionPreset {
+    pub fn all() -> [PermissionPreset; 3] {
+        [PermissionPreset::Strict, PermissionPreset::Standard, PermissionPreset::Permissive]
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PermissionPreset::Strict => "strict",
+            PermissionPreset::Standard => "standard",
+            PermissionPreset::Permissive => "permissive",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            PermissionPreset::Strict => "Only git and build tools run without asking, file-moving and network commands are denied, and anything else asks first",
+            PermissionPreset::Standard => "Common shell commands allowed, deletes limited to the feature worktree",
+            PermissionPreset::Permissive => "Any rm/mv/cp allowed outside the main directory (the default)",
+        }
+    }
+
+    pub fn template(&self) -> PermissionTemplate {
+        match self {
+            PermissionPreset::Permissive => PermissionTemplate::default(),
+            PermissionPreset::Standard => PermissionTemplate {
+                allow: rules(&[
+                    "Read({main}/*)",
+                    "Read({feature}/*)",
+                    "Bash(cd:{main})",
+                    "Bash(cd:{feature})",
+                    "Bash(git:*)",
+                    "Bash(stacks:cleanup)",
+                    "Bash(touch:*)",
+                    "Bash(mkdir:*)",
+                    "Bash(echo:*)",
+                    "Bash(cat:*)",
+                    "Bash(cp:{feature}/*)",
+                    "Bash(mv:{feature}/*)",
+                    "Bash(rm:{feature}/*)",
+                ]),
+                deny: PermissionTemplate::default().deny,
+            },
+            PermissionPreset::Strict => {
+                let mut deny = PermissionTemplate::default().deny;
+                deny.extend(rules(&[
+                    "Bash(rm:*)",
+                    "Bash(mv:*)",
+                    "Bash(cp:*)",
+                    "Bash(chmod:*)",
+                    "Bash(chown:*)",
+                    "Bash(sudo:*)",
+                    "Bash(curl:*)",
+                    "Bash(wget:*)",
+                ]));
+
+                PermissionTemplate {
+                    allow: rules(&[
+                        "Read({main}/*)",
+                        "Read({feature}/*)",
+                        "Bash(git:*)",
+                        "Bash(cargo:*)",
+                        "Bash(npm:*)",
+                        "Bash(pnpm:*)",
+                        "Bash(yarn:*)",
+                        "Bash(make:*)",
+                        "Bash(go:*)",
+                    ]),
+                    deny,
+                }
+            }
+        }
+    }
+}
+
+impl std::str::FromStr for PermissionPreset {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "strict" => Ok(PermissionPreset::Strict),
+            "standard" => Ok(PermissionPreset::Standard),
+            "permissive" => Ok(PermissionPreset::Permissive),
+            _ => anyhow::bail!("Invalid permission preset: {}", s),
+        }
+    }
+}
+
+/// Paths every generated worktree denies writes to, plus `permissions.protected_paths` from config
+///
+/// `~` at the start of a configured path expands to the home directory.
+pub fn protected_paths(configured: &[String]) -> Vec<PathBuf> {
+    let home = dirs::home_dir();
+    let mut paths = Vec::new();
+
+    if let Some(home) = &home {
+        paths.push(home.join(".ssh"));
+    }
+    // The registry clone is shared by every project on the machine
+    if let Ok(manager) = RemoteStackManager::new() {
+        paths.push(manager.cache_dir().to_path_buf());
+    }
+
+    for path in configured {
+        let expanded = match (path.strip_prefix("~/"), &home) {
+            (Some(rest), Some(home)) => home.join(rest),
+            _ if path == "~" => home.clone().unwrap_or_else(|| PathBuf::from(path)),
+            _ => PathBuf::from(path),
+        };
+        if !paths.contains(&expanded) {
+            paths.push(expanded);
+        }
+    }
+
+    paths
+}
+
 /// Generates permission configurations for feature branch worktrees
 /// that protect the main directory while allowing full access to feature directories
 pub struct PermissionGenerator {
     main_directory: PathBuf,
     feature_directory: PathBuf,
+    template: PermissionTemplate,
+    protected_paths: Vec<PathBuf>,
+    container: bool,
 }
 
 impl PermissionGenerator {
@@ -14,11 +218,31 @@ impl PermissionGenerator {
         Self {
             main_directory,
             feature_directory,
+            template: PermissionTemplate::default(),
+            protected_paths: Vec::new(),
+            container: false,
         }
     }
 
-    /// Generate the permission configuration JSON that protects main directory
-    /// while allowing full access to feature directory
+    pub fn with_template(mut self, template: PermissionTemplate) -> Self {
+        self.template = template;
+        self
+    }
+
+    /// Additional paths to deny writes to, on top of the main directory
+    pub fn with_protected_paths(mut self, protected_paths: Vec<PathBuf>) -> Self {
+        self.protected_paths = protected_paths;
+        self
+    }
+
+    /// Claude runs in a container that mounts only the checkouts, so any shell command may run
+    /// and paths outside them need no rules
+    pub fn with_container(mut self, container: bool) -> Self {
+        self.container = container;
+        self
+    }
+
+    /// Generate the permission configuration JSON from the template
     pub fn generate_permission_config(&self) -> Result<Value> {
         let main_path = self.main_directory.canonicalize()
             .context("Failed to resolve main directory path")?;
@@ -30,41 +254,44 @@ impl PermissionGenerator {
         let feature_path_str = feature_path.to_str()
             .context("Feature directory path contains invalid UTF-8")?;
 
+        let mut deny = PermissionTemplate::render(&self.template.deny, main_path_str, feature_path_str);
+        let protected_paths: &[PathBuf] = match self.container {
+            true => &[],
+            false => &self.protected_paths,
+        };
+        for path in protected_paths {
+            let path = path.display();
+            for rule in [
+                format!("Write({}/*)", path),
+                format!("Edit({}/*)", path),
+                format!("MultiEdit({}/*)", path),
+                format!("Bash(rm:{}/*)", path),
+            ] {
+                if !deny.contains(&rule) {
+                    deny.push(rule);
+                }
+            }
+        }
+
+        let mut allow = PermissionTemplate::render(&self.template.allow, main_path_str, feature_path_str);
+        if self.container && !allow.iter().any(|rule| rule == "Bash") {
+            allow.push("Bash".to_string());
+        }
+
         let config = json!({
             "permissions": {
-                "allow": [
-                    format!("Read({}/*)", main_path_str),
-                    format!("Read({}/*)", feature_path_str),
-                    format!("Bash(cd:{})", main_path_str),
-                    format!("Bash(cd:{})", feature_path_str),
-                    "Bash(git:*)",
-                    "Bash(stacks:cleanup)",
-                    "Bash(touch:*)",
-                    "Bash(mkdir:*)",
-                    "Bash(echo:*)",
-                    "Bash(cat:*)",
-                    "Bash(vim:*)",
-                    "Bash(nano:*)",
-                    "Bash(cp:*)",
-                    "Bash(mv:*)",
-                    "Bash(rm:*)"
-                ],
-                "deny": [
-                    format!("Write({}/*)", main_path_str),
-                    format!("Edit({}/*)", main_path_str),
-                    format!("MultiEdit({}/*)", main_path_str),
-                    format!("DeleteFile({}/*)", main_path_str),
-                    format!("Bash(rm:{}/*)", main_path_str),
-                    format!("Bash(mv:{}/*)", main_path_str),
-                    format!("Bash(cp:*/{}/*)", main_path_str)
-                ]
+                "allow": allow,
+                "deny": deny
             }
         });
 
         Ok(config)
     }
 
-    /// Generate permission config and merge it into existing settings
+    /// Add the generated rules to existing settings, replacing rules from a previous run
+    ///
+    /// Rules this generator added are recorded in a marker file next to the settings so
+    /// `revoke_generated_permissions` can remove exactly those, leaving user-authored rules alone.
     pub async fn apply_to_local_settings(&self, settings_path: &Path) -> Result<()> {
         let permission_config = self.generate_permission_config()?;
 
@@ -78,59 +305,155 @@ impl PermissionGenerator {
         } else {
             // Create directory if it doesn't exist
             if let Some(parent) = settings_path.parent() {
-                tokio::fs::create_dir_all(parent).await
+                files::create_dir_all(parent)
                     .with_context(|| format!("Failed to create directory {}", parent.display()))?;
             }
             json!({})
         };
 
-        // Merge the permission config into existing settings
-        deep_merge(&mut existing_settings, permission_config);
+        // Drop what a previous run generated so template changes don't leave stale rules behind
+        if let Some(previous) = GeneratedRules::load(settings_path).await? {
+            previous.remove_from(&mut existing_settings);
+        }
+
+        let mut generated = GeneratedRules::default();
+        for (list, added) in [("allow", &mut generated.allow), ("deny", &mut generated.deny)] {
+            let rules = permission_config["permissions"][list].as_array().cloned().unwrap_or_default();
+            for rule in rules {
+                if let Some(rule) = rule.as_str() {
+                    // Rules the user already has stay theirs and are not recorded
+                    if add_rule(&mut existing_settings, list, rule)? {
+                        added.push(rule.to_string());
+                    }
+                }
+            }
+        }
 
         // Write back to file
         let content = serde_json::to_string_pretty(&existing_settings)
             .context("Failed to serialize settings")?;
         
-        tokio::fs::write(settings_path, content).await
+        files::write(settings_path, content)
             .with_context(|| format!("Failed to write settings to {}", settings_path.display()))?;
 
+        generated.save(settings_path).await?;
+
         Ok(())
     }
 }
 
-/// Deep merge two JSON values, with the second value taking precedence for permissions
-fn deep_merge(target: &mut Value, source: Value) {
-    match (target, source) {
-        (Value::Object(target_map), Value::Object(source_map)) => {
-            for (key, value) in source_map {
-                match target_map.get_mut(&key) {
-                    Some(target_value) => {
-                        // For permissions, we want to merge arrays
-                        if key == "permissions" {
-                            deep_merge(target_value, value);
-                        } else {
-                            // For other keys, source takes precedence
-                            *target_value = value;
-                        }
-                    }
-                    None => {
-                        // Insert new key-value pair
-                        target_map.insert(key, value);
-                    }
+/// Marker file recording the rules written into a settings file, next to that file
+pub const GENERATED_MARKER_FILE: &str = ".stacks-permissions.json";
+
+/// Rules added to a settings file by the generator
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct GeneratedRules {
+    allow: Vec<String>,
+    deny: Vec<String>,
+}
+
+impl GeneratedRules {
+    fn marker_path(settings_path: &Path) -> PathBuf {
+        settings_path.with_file_name(GENERATED_MARKER_FILE)
+    }
+
+    async fn load(settings_path: &Path) -> Result<Option<Self>> {
+        let marker_path = Self::marker_path(settings_path);
+        if !marker_path.exists() {
+            return Ok(None);
+        }
+
+        let content = tokio::fs::read_to_string(&marker_path).await
+            .with_context(|| format!("Failed to read {}", marker_path.display()))?;
+        let rules = serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse {}", marker_path.display()))?;
+        Ok(Some(rules))
+    }
+
+    async fn save(&self, settings_path: &Path) -> Result<()> {
+        let marker_path = Self::marker_path(settings_path);
+        let content = serde_json::to_string_pretty(self)
+            .context("Failed to serialize generated permissions")?;
+        files::write(&marker_path, content)
+            .with_context(|| format!("Failed to write {}", marker_path.display()))
+    }
+
+    fn len(&self) -> usize {
+        self.allow.len() + self.deny.len()
+    }
+
+    /// Remove these rules from settings, dropping lists and the permissions object once empty
+    fn remove_from(&self, settings: &mut Value) {
+        let permissions = match settings.get_mut("permissions").and_then(Value::as_object_mut) {
+            Some(permissions) => permissions,
+            None => return,
+        };
+
+        for (list, generated) in [("allow", &self.allow), ("deny", &self.deny)] {
+            if let Some(rules) = permissions.get_mut(list).and_then(Value::as_array_mut) {
+                rules.retain(|rule| !rule.as_str().is_some_and(|rule| generated.iter().any(|g| g == rule)));
+                if rules.is_empty() {
+                    permissions.remove(list);
                 }
             }
         }
-        (Value::Array(target_array), Value::Array(source_array)) => {
-            // For permission arrays, replace entirely to avoid duplicates
-            *target_array = source_array;
-        }
-        (target_val, source_val) => {
-            // For primitive values, source takes precedence
-            *target_val = source_val;
+
+        if permissions.is_empty() {
+            if let Some(settings) = settings.as_object_mut() {
+                settings.remove("permissions");
+            }
         }
     }
 }
 
+/// Append a rule to `permissions.<list>` unless present; returns whether it was added
+fn add_rule(settings: &mut Value, list: &str, rule: &str) -> Result<bool> {
+    let settings = settings.as_object_mut()
+        .context("Settings file is not a JSON object")?;
+    let permissions = settings.entry("permissions")
+        .or_insert_with(|| json!({}))
+        .as_object_mut()
+        .context("\"permissions\" in settings is not an object")?;
+    let rules = permissions.entry(list)
+        .or_insert_with(|| json!([]))
+        .as_array_mut()
+        .with_context(|| format!("\"permissions.{}\" in settings is not an array", list))?;
+
+    if rules.iter().any(|existing| existing.as_str() == Some(rule)) {
+        return Ok(false);
+    }
+    rules.push(Value::String(rule.to_string()));
+    Ok(true)
+}
+
+/// Remove the rules the generator wrote into a settings file; returns how many were removed
+pub async fn revoke_generated_permissions(settings_path: &Path) -> Result<usize> {
+    let generated = match GeneratedRules::load(settings_path).await? {
+        Some(generated) => generated,
+        None => return Ok(0),
+    };
+
+    if settings_path.exists() {
+        let content = tokio::fs::read_to_string(settings_path).await
+            .with_context(|| format!("Failed to read settings from {}", settings_path.display()))?;
+        let mut settings: Value = serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse JSON in {}", settings_path.display()))?;
+
+        generated.remove_from(&mut settings);
+
+        let content = serde_json::to_string_pretty(&settings)
+            .context("Failed to serialize settings")?;
+        files::write(settings_path, content)
+            .with_context(|| format!("Failed to write settings to {}", settings_path.display()))?;
+    }
+
+    let marker_path = GeneratedRules::marker_path(settings_path);
+    files::remove_file(&marker_path)
+        .with_context(|| format!("Failed to remove {}", marker_path.display()))?;
+
+    Ok(generated.len())
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -170,4 +493,114 @@ mod tests {
         // Should allow git operations
         assert!(allow_rules.iter().any(|v| v.as_str().unwrap() == "Bash(git:*)"));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_presets() {
+        assert_eq!(PermissionPreset::Permissive.template(), PermissionTemplate::default());
+
+        let standard = PermissionPreset::Standard.template();
+        assert!(!standard.allow.contains(&"Bash(rm:*)".to_string()));
+        assert!(standard.allow.contains(&"Bash(rm:{feature}/*)".to_string()));
+
+        let strict = PermissionPreset::Strict.template();
+        assert!(strict.allow.contains(&"Bash(git:*)".to_string()));
+        assert!(strict.allow.contains(&"Bash(cargo:*)".to_string()));
+        assert!(strict.deny.contains(&"Bash(rm:*)".to_string()));
+
+        for preset in PermissionPreset::all() {
+            assert_eq!(preset.as_str().parse::<PermissionPreset>().unwrap(), preset);
+        }
+    }
+
+    #[test]
+    fn test_project_template_placeholders() {
+        let temp_main = TempDir::new().unwrap();
+        let temp_feature = TempDir::new().unwrap();
+        std::fs::create_dir_all(temp_main.path().join(".stacks")).unwrap();
+        std::fs::write(
+            temp_main.path().join(PROJECT_TEMPLATE_FILE),
+            "allow = [\"Edit({feature}/*)\"]\ndeny = [\"Write({main}/*)\"]\n",
+        ).unwrap();
+
+        let template = PermissionTemplate::resolve(temp_main.path(), None).unwrap();
+        let config = PermissionGenerator::new(temp_main.path().to_path_buf(), temp_feature.path().to_path_buf())
+            .with_template(template)
+            .generate_permission_config()
+            .unwrap();
+
+        let feature = temp_feature.path().canonicalize().unwrap();
+        let main = temp_main.path().canonicalize().unwrap();
+        assert_eq!(config["permissions"]["allow"][0], format!("Edit({}/*)", feature.display()));
+        assert_eq!(config["permissions"]["deny"][0], format!("Write({}/*)", main.display()));
+        assert_eq!(config["permissions"]["allow"].as_array().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_protected_paths_are_denied() {
+        let temp_main = TempDir::new().unwrap();
+        let temp_feature = TempDir::new().unwrap();
+
+        let paths = protected_paths(&["/srv/secrets".to_string(), "~/.aws".to_string()]);
+        assert!(paths.contains(&PathBuf::from("/srv/secrets")));
+        if let Some(home) = dirs::home_dir() {
+            assert!(paths.contains(&home.join(".ssh")));
+            assert!(paths.contains(&home.join(".aws")));
+        }
+
+        let config = PermissionGenerator::new(temp_main.path().to_path_buf(), temp_feature.path().to_path_buf())
+            .with_protected_paths(paths)
+            .generate_permission_config()
+            .unwrap();
+
+        let deny_rules = config["permissions"]["deny"].as_array().unwrap();
+        assert!(deny_rules.iter().any(|rule| rule == "Write(/srv/secrets/*)"));
+        assert!(deny_rules.iter().any(|rule| rule == "Bash(rm:/srv/secrets/*)"));
+    }
+
+    #[test]
+    fn test_container_permissions() {
+        let temp_main = TempDir::new().unwrap();
+        let temp_feature = TempDir::new().unwrap();
+
+        let config = PermissionGenerator::new(temp_main.path().to_path_buf(), temp_feature.path().to_path_buf())
+            .with_protected_paths(vec![PathBuf::from("/srv/secrets")])
+            .with_container(true)
+            .generate_permission_config()
+            .unwrap();
+
+        assert!(config["permissions"]["allow"].as_array().unwrap().iter().any(|rule| rule == "Bash"));
+        let deny_rules = config["permissions"]["deny"].as_array().unwrap();
+        assert!(!deny_rules.iter().any(|rule| rule == "Write(/srv/secrets/*)"));
+    }
+
+    #[tokio::test]
+    async fn test_revoke_keeps_user_rules() {
+        let temp_main = TempDir::new().unwrap();
+        let temp_feature = TempDir::new().unwrap();
+        let settings_path = temp_feature.path().join(".claude").join("settings.local.json");
+        std::fs::create_dir_all(settings_path.parent().unwrap()).unwrap();
+        std::fs::write(
+            &settings_path,
+            r#"{"model": "opus", "permissions": {"allow": ["Bash(git:*)", "Bash(npm test)"]}}"#,
+        ).unwrap();
+
+        let generator = PermissionGenerator::new(temp_main.path().to_path_buf(), temp_feature.path().to_path_buf());
+        generator.apply_to_local_settings(&settings_path).await.unwrap();
+        // Applying twice must not duplicate rules
+        generator.apply_to_local_settings(&settings_path).await.unwrap();
+
+        let applied: Value = serde_json::from_str(&std::fs::read_to_string(&settings_path).unwrap()).unwrap();
+        let allow = applied["permissions"]["allow"].as_array().unwrap();
+        assert_eq!(allow.iter().filter(|rule| *rule == "Bash(git:*)").count(), 1);
+        assert!(allow.iter().any(|rule| rule == "Bash(rm:*)"));
+
+        let removed = revoke_generated_permissions(&settings_path).await.unwrap();
+        assert!(removed > 0);
+
+        let revoked: Value = serde_json::from_str(&std::fs::read_to_string(&settings_path).unwrap()).unwrap();
+        assert_eq!(revoked["model"], "opus");
+        assert_eq!(revoked["permissions"]["allow"], json!(["Bash(git:*)", "Bash(npm test)"]));
+        assert!(revoked["permissions"].get("deny").is_none());
+        assert!(!settings_path.with_file_name(GENERATED_MARKER_FILE).exists());
+    }
+}
@@ -0,0 +1,378 @@
+use std::path::Path;
+use std::time::Duration;
+use anyhow::{Result, Context};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::sync::OnceCell;
+
+use super::cache;
+use super::events::{self, Event};
+use super::stack_manager::Stack;
+use crate::config::load_config;
+use crate::error::StacksError;
+use crate::utils::process::Process;
+
+/// Last policy fetched, used when the policy URL can't be reached
+const CACHE_FILE: &str = "policy.toml";
+
+/// Fetched once per run; None when no policy is configured
+static POLICY: OnceCell<Option<Policy>> = OnceCell::const_new();
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// Refuse the operation
+    #[default]
+    Error,
+    /// Go ahead, but say what the policy objects to
+    Warn,
+}
+
+/// Organization policy, published by administrators at the `policy-url` of the config
+///
+/// ```toml
+/// [registries]
+/// allowed = ["acme/claude-stacks", "acme/*"]
+///
+/// [permissions]
+/// forbidden = ["Bash(rm:*)", "Bash(sudo *)"]
+///
+/// [stacks]
+/// required = ["security-baseline"]
+/// severity = "warn"
+///
+/// [telemetry]
+/// endpoint = "https://stacks-telemetry.acme.dev/v1/records"
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Policy {
+    #[serde(default)]
+    pub registries: RegistryRules,
+    #[serde(default)]
+    pub permissions: PermissionRules,
+    #[serde(default)]
+    pub stacks: StackRules,
+    #[serde(default)]
+    pub telemetry: TelemetryRules,
+}
+
+/// Registries and repositories stacks may come from, as `owner/repo` or `owner/*`; any when empty
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct RegistryRules {
+    #[serde(default)]
+    pub allowed: Vec<String>,
+    #[serde(default)]
+    pub severity: Severity,
+}
+
+/// Permission rules no stack may grant; `*` matches anything
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PermissionRules {
+    #[serde(default)]
+    pub forbidden: Vec<String>,
+    #[serde(default)]
+    pub severity: Severity,
+}
+
+/// Stacks every project must have installed
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct StackRules {
+    #[serde(default)]
+    pub required: Vec<String>,
+    #[serde(default)]
+    pub severity: Severity,
+}
+
+/// Where the telemetry of users who turned it on is uploaded; kept locally only when unset
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct TelemetryRules {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub endpoint: Option<String>,
+}
+
+/// Something the policy objects to
+#[derive(Debug, Clone, PartialEq)]
+pub struct Violation {
+    pub severity: Severity,
+    pub message: String,
+}
+
+impl Policy {
+    /// The configured policy, fetched on first use; None when `policy-url` is unset
+    pub async fn current() -> Result<Option<&'static Policy>> {
+        let policy = POLICY
+            .get_or_try_init(|| async {
+                match load_config()?.policy_url {
+                    Some(url) => fetch(&url).await.map(Some),
+                    None => Ok::<_, anyhow::Error>(None),
+                }
+            })
+            .await?;
+        Ok(policy.as_ref())
+    }
+
+    pub fn parse(content: &str) -> Result<Self> {
+        toml::from_str(content).context("Failed to parse the organization policy")
+    }
+
+    /// Whether stacks may come from the `owner/repo` repository
+    pub fn check_registry(&self, owner: &str, repo: &str) -> Option<Violation> {
+        let rules = &self.registries;
+        if rules.allowed.is_empty() {
+            return None;
+        }
+        let repository = format!("{}/{}", owner, repo);
+        let allowed = rules.allowed.iter().any(|pattern| wildcard_match(&pattern.to_lowercase(), &repository.to_lowercase()));
+        match allowed {
+            true => None,
+            false => Some(Violation {
+                severity: rules.severity,
+                message: format!("{} is not an allowed stack source (allowed: {})", repository, rules.allowed.join(", ")),
+            }),
+        }
+    }
+
+    /// Forbidden rules among the permission rules `origin` grants
+    ///
+    /// A grant is forbidden when a forbidden pattern covers it, or when it is broader than one:
+    /// `Bash` or `Bash(*)` grants everything `Bash(rm:*)` forbids.
+    pub fn check_grants(&self, origin: &str, grants: &[String]) -> Vec<Violation> {
+        let rules = &self.permissions;
+        grants
+            .iter()
+            .filter_map(|grant| {
+                let forbidden = rules.forbidden.iter().find(|pattern| rule_covers(pattern, grant) || rule_covers(grant, pattern))?;
+                Some(Violation {
+                    severity: rules.severity,
+                    message: match forbidden == grant {
+                        true => format!("{} grants forbidden permission {}", origin, grant),
+                        false => format!("{} grants {}, forbidden by {}", origin, grant, forbidden),
+                    },
+                })
+            })
+            .collect()
+    }
+
+    /// Forbidden rules in the settings a stack merges into the project
+    pub fn check_stack(&self, stack: &Stack) -> Result<Vec<Violation>> {
+        let grants = allowed_rules(&stack.claude_dir.join(".local-settings.json"))?;
+        Ok(self.check_grants(&format!("Stack {}", stack.name), &grants))
+    }
+
+    /// Required stacks that aren't in `installed`
+    pub fn check_required(&self, installed: &[String]) -> Vec<Violation> {
+        self.stacks.required
+            .iter()
+            .filter(|name| !installed.contains(name))
+            .map(|name| Violation {
+                severity: self.stacks.severity,
+                message: format!("Required stack {} is not installed; run 'stacks checkout {}'", name, name),
+            })
+            .collect()
+    }
+}
+
+/// Print warnings and fail on the first error, so the caller stops before changing anything
+pub fn enforce(violations: Vec<Violation>) -> Result<()> {
+    let mut errors = Vec::new();
+    for violation in violations {
+        match violation.severity {
+            Severity::Warn => events::report(Event::PolicyWarning { message: violation.message }),
+            Severity::Error => errors.push(violation.message),
+        }
+    }
+    match errors.is_empty() {
+        true => Ok(()),
+        false => Err(StacksError::validation(format!("Blocked by organization policy: {}", errors.join("; ")))
+            .with_hint("Ask your administrator, or see the policy at the configured policy-url")
+            .into()),
+    }
+}
+
+/// Check stacks from the `owner/repo` repository against the configured policy, if any
+pub async fn enforce_registry(owner: &str, repo: &str) -> Result<()> {
+    match Policy::current().await? {
+        Some(policy) => enforce(policy.check_registry(owner, repo).into_iter().collect()),
+        None => Ok(()),
+    }
+}
+
+/// Check the grants a stack's settings add against the configured policy, if any
+pub async fn enforce_stack(stack: &Stack) -> Result<()> {
+    match Policy::current().await? {
+        Some(policy) => enforce(policy.check_stack(stack)?),
+        None => Ok(()),
+    }
+}
+
+/// Check the grants of a stack fetched at `commit`, before its subtree is added
+pub async fn enforce_commit(stack_name: &str, commit: &str) -> Result<()> {
+    let policy = match Policy::current().await? {
+        Some(policy) => policy,
+        None => return Ok(()),
+    };
+    let settings = Process::git()
+        .args(["show", &format!("{}:.claude/.local-settings.json", commit)])
+        .read_only()
+        .output()?;
+    let grants = match settings.success() {
+        true => parse_allowed_rules(&settings.stdout)
+            .with_context(|| format!("Failed to parse .claude/.local-settings.json of {}", stack_name))?,
+        false => Vec::new(),
+    };
+    enforce(policy.check_grants(&format!("Stack {}", stack_name), &grants))
+}
+
+/// `permissions.allow` rules of a settings file; none when the file doesn't exist
+pub fn allowed_rules(path: &Path) -> Result<Vec<String>> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Failed to read {}", path.display()))?;
+    parse_allowed_rules(&content).with_context(|| format!("Failed to parse {}", path.display()))
+}
+
+fn parse_allowed_rules(content: &str) -> Result<Vec<String>> {
+    let settings: Value = serde_json::from_str(content)?;
+    Ok(settings
+        .pointer("/permissions/allow")
+        .and_then(Value::as_array)
+        .map(|rules| rules.iter().filter_map(Value::as_str).map(str::to_string).collect())
+        .unwrap_or_default())
+}
+
+/// Fetch the policy from a URL or read it from a path, falling back to the last fetched copy
+async fn fetch(location: &str) -> Result<Policy> {
+    let cache_path = cache::cache_root()?.join(CACHE_FILE);
+    if !location.starts_with("https://") && !location.starts_with("http://") {
+        let path = location.strip_prefix("file://").unwrap_or(location);
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read the organization policy at {}", path))?;
+        return Policy::parse(&content);
+    }
+
+    let response = reqwest::Client::new()
+        .get(location)
+        .timeout(Duration::from_secs(10))
+        .send()
+        .await
+        .and_then(|response| response.error_for_status());
+    let content = match response {
+        Ok(response) => response.text().await.ok(),
+        Err(error) => {
+            tracing::debug!("failed to fetch policy from {}: {}", location, error);
+            None
+        }
+    };
+    match content {
+        Some(content) => {
+            let policy = Policy::parse(&content)?;
+            if let Some(parent) = cache_path.parent() {
+                std::fs::create_dir_all(parent).ok();
+            }
+            if let Err(error) = std::fs::write(&cache_path, &content) {
+                tracing::debug!("failed to cache policy: {}", error);
+            }
+            Ok(policy)
+        }
+        None => match std::fs::read_to_string(&cache_path) {
+            Ok(content) => {
+                events::report(Event::PolicyCacheUsed { location: location.to_string() });
+                Policy::parse(&content)
+            }
+            Err(_) => Err(StacksError::network(format!("Could not fetch the organization policy from {}", location))
+                .with_hint("Commands are checked against the policy, so it must be reachable at least once")
+                .into()),
+        },
+    }
+}
+
+/// Match `text` against `pattern`, where `*` stands for any run of characters
+/// Whether the permission rule `pattern` allows everything `rule` does; a bare tool name covers
+/// all of that tool's rules
+fn rule_covers(pattern: &str, rule: &str) -> bool {
+    wildcard_match(pattern, rule) || (!pattern.contains('(') && rule.starts_with(&format!("{}(", pattern)))
+}
+
+fn wildcard_match(pattern: &str, text: &str) -> bool {
+    let parts: Vec<&str> = pattern.split('*').collect();
+    if parts.len() == 1 {
+        return pattern == text;
+    }
+    let (first, last) = (parts[0], parts[parts.len() - 1]);
+    if !text.starts_with(first) || !text[first.len()..].ends_with(last) {
+        return false;
+    }
+    let mut rest = &text[first.len()..text.len() - last.len()];
+    for part in &parts[1..parts.len() - 1] {
+        match rest.find(part) {
+            Some(index) => rest = &rest[index + part.len()..],
+            None => return false,
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const POLICY: &str = r#"
+[registries]
+allowed = ["acme/claude-stacks", "acme-tools/*"]
+
+[permissions]
+forbidden = ["Bash(rm:*)", "Bash(sudo*"]
+
+[stacks]
+required = ["security-baseline"]
+severity = "warn"
+"#;
+
+    #[test]
+    fn test_wildcard_match() {
+        assert!(wildcard_match("Bash(rm:*)", "Bash(rm:*)"));
+        assert!(wildcard_match("Bash(rm:*)", "Bash(rm:-rf /tmp)"));
+        assert!(!wildcard_match("Bash(rm:*)", "Bash(rmdir:*)"));
+        assert!(wildcard_match("a*b*c", "aXbYc"));
+        assert!(!wildcard_match("a*b*c", "acb"));
+        assert!(wildcard_match("exact", "exact"));
+    }
+
+    #[test]
+    fn test_policy_checks() {
+        let policy = Policy::parse(POLICY).unwrap();
+
+        assert_eq!(policy.check_registry("acme", "claude-stacks"), None);
+        assert_eq!(policy.check_registry("ACME-tools", "lint"), None);
+        let violation = policy.check_registry("someone", "stacks").unwrap();
+        assert_eq!(violation.severity, Severity::Error);
+
+        let grants = vec!["Bash(rm:-rf build)".to_string(), "Bash(npm test)".to_string(), "Bash(sudo apt install)".to_string()];
+        let violations = policy.check_grants("Stack lint", &grants);
+        assert_eq!(violations.len(), 2);
+        assert_eq!(violations[0].message, "Stack lint grants Bash(rm:-rf build), forbidden by Bash(rm:*)");
+
+        // Grants broader than a forbidden pattern include it
+        for grant in ["Bash", "Bash(*)", "Bash(r*)"] {
+            let violations = policy.check_grants("Stack lint", &[grant.to_string()]);
+            assert_eq!(violations.len(), 1, "{} should be forbidden", grant);
+            assert_eq!(violations[0].message, format!("Stack lint grants {}, forbidden by Bash(rm:*)", grant));
+        }
+        assert!(policy.check_grants("Stack lint", &["Bash(npm:*)".to_string(), "Read".to_string()]).is_empty());
+
+        let violations = policy.check_required(&["lint".to_string()]);
+        assert_eq!(violations.len(), 1);
+        assert_eq!(violations[0].severity, Severity::Warn);
+        assert!(policy.check_required(&["security-baseline".to_string()]).is_empty());
+    }
+
+    #[test]
+    fn test_enforce() {
+        let warning = Violation { severity: Severity::Warn, message: "required stack missing".to_string() };
+        assert!(enforce(vec![warning.clone()]).is_ok());
+        let error = Violation { severity: Severity::Error, message: "forbidden grant".to_string() };
+        assert!(enforce(vec![warning, error]).is_err());
+    }
+}
@@ -0,0 +1,103 @@
+use std::collections::BTreeMap;
+use std::path::PathBuf;
+use anyhow::{Result, Context};
+
+use crate::error::StacksError;
+
+/// Where the kickoff prompt of a new worktree comes from
+#[derive(Debug, Clone)]
+pub enum PromptSource {
+    /// A markdown or text file
+    File(PathBuf),
+    /// A template from the `[prompts]` section of config.toml
+    Named(String),
+}
+
+impl PromptSource {
+    /// The raw template text, before interpolation
+    pub fn load(&self, templates: &BTreeMap<String, String>) -> Result<String> {
+        match self {
+            PromptSource::File(path) => std::fs::read_to_string(path)
+                .with_context(|| format!("Failed to read prompt file {}", path.display())),
+            PromptSource::Named(name) => match templates.get(name) {
+                Some(template) => Ok(template.clone()),
+                None => {
+                    let known = match templates.is_empty() {
+                        true => "none defined".to_string(),
+                        false => templates.keys().cloned().collect::<Vec<_>>().join(", "),
+                    };
+                    Err(StacksError::validation(format!("Unknown prompt template: {} ({})", name, known))
+                        .with_hint("Define templates in the [prompts] section of the config file")
+                        .into())
+                }
+            },
+        }
+    }
+}
+
+/// Replace `{name}` placeholders with their values; unknown placeholders are left as written
+pub fn render(template: &str, variables: &[(&str, &str)]) -> String {
+    let mut rendered = template.to_string();
+    for (name, value) in variables {
+        rendered = rendered.replace(&format!("{{{}}}", name), value);
+    }
+    rendered
+}
+
+/// Shell command that starts claude with `prompt` in a tmux pane
+///
+/// Anything beyond a short single-line prompt is written to a file and read back by the shell,
+/// so quotes and newlines survive `tmux send-keys`.
+pub fn claude_command(prompt: &str, task: &str) -> Result<String> {
+    let prompt = prompt.trim();
+    if prompt.is_empty() || prompt == "claude" {
+        return Ok("claude".to_string());
+    }
+    if !prompt.contains(['\n', '"', '\'', '$', '`', '\\']) {
+        return Ok(format!("claude \"{}\"", prompt));
+    }
+
+    let path = prompt_file_path(task);
+    std::fs::write(&path, prompt)
+        .with_context(|| format!("Failed to write prompt to {}", path.display()))?;
+    Ok(format!("claude \"$(cat '{}')\"", path.display()))
+}
+
+fn prompt_file_path(task: &str) -> PathBuf {
+    let name: String = task
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() || c == '-' || c == '_' { c } else { '-' })
+        .collect();
+    std::env::temp_dir().join(format!("stacks-prompt-{}.md", name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render() {
+        let template = "Implement {task} using {stacks}.\nKeep {unknown} as is.";
+        assert_eq!(
+            render(template, &[("task", "auth"), ("stacks", "ts-lint-stack, pytest")]),
+            "Implement auth using ts-lint-stack, pytest.\nKeep {unknown} as is."
+        );
+    }
+
+    #[test]
+    fn test_claude_command() {
+        assert_eq!(claude_command("claude", "auth").unwrap(), "claude");
+        assert_eq!(claude_command("fix the login bug", "auth").unwrap(), "claude \"fix the login bug\"");
+
+        let command = claude_command("Step 1: read \"README\"\nStep 2: fix", "auth/x").unwrap();
+        assert!(command.starts_with("claude \"$(cat '"));
+        assert!(command.contains("stacks-prompt-auth-x.md"));
+    }
+
+    #[test]
+    fn test_unknown_template() {
+        let templates = BTreeMap::from([("review".to_string(), "Review {task}".to_string())]);
+        assert_eq!(PromptSource::Named("review".to_string()).load(&templates).unwrap(), "Review {task}");
+        assert!(PromptSource::Named("missing".to_string()).load(&templates).is_err());
+    }
+}
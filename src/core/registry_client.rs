@@ -0,0 +1,334 @@
+use std::path::PathBuf;
+use anyhow::{Result, Context};
+use futures::future::{BoxFuture, FutureExt};
+use reqwest::header::HeaderMap;
+use reqwest::StatusCode;
+
+use super::remote_stack_manager::{github_token, http_client, GitHubFile, StackRepository};
+use crate::error::StacksError;
+use crate::utils::retry::{is_transient_http, RetryPolicy};
+
+/// Set (by `--mock-registry`) to a fixture directory that stands in for the registry
+pub const MOCK_REGISTRY_ENV: &str = "STACKS_MOCK_REGISTRY";
+
+const GITHUB_API: &str = "https://api.github.com";
+const GITHUB_RAW: &str = "https://raw.githubusercontent.com";
+
+/// Entries requested per page of a directory listing; GitHub's maximum
+const PAGE_SIZE: usize = 100;
+
+/// How stacks are listed and their files read from a registry
+///
+/// GitHub in normal use; tests point a `GitHubClient` at a mock server, and `--mock-registry`
+/// reads a fixture directory instead.
+pub trait RegistryClient: Send + Sync {
+    /// Names of the directories under `stacks/` on the registry branch
+    fn list_stacks<'a>(&'a self, repository: &'a StackRepository) -> BoxFuture<'a, Result<Vec<String>>>;
+
+    /// Content of a stack's file, e.g. its CLAUDE.md; None when the file doesn't exist
+    fn stack_file<'a>(&'a self, repository: &'a StackRepository, stack: &'a str, file: &'a str) -> BoxFuture<'a, Result<Option<String>>>;
+
+    /// Repository to check `stack` out from, when the client knows better than the naming convention
+    fn stack_repo_url(&self, _stack: &str) -> Option<String> {
+        None
+    }
+}
+
+/// The registry client for this process: the fixture directory of `--mock-registry`, else GitHub
+pub fn from_env(retry: RetryPolicy) -> Box<dyn RegistryClient> {
+    match std::env::var_os(MOCK_REGISTRY_ENV) {
+        Some(dir) => Box::new(FixtureRegistry::new(PathBuf::from(dir))),
+        None => Box::new(GitHubClient::new(retry)),
+    }
+}
+
+/// Reads the registry through the GitHub contents API and raw file downloads
+pub struct GitHubClient {
+    retry: RetryPolicy,
+    api_base: String,
+    raw_base: String,
+}
+
+impl GitHubClient {
+    pub fn new(retry: RetryPolicy) -> Self {
+        Self {
+            retry,
+            api_base: GITHUB_API.to_string(),
+            raw_base: GITHUB_RAW.to_string(),
+        }
+    }
+
+    /// Talk to another server than GitHub, e.g. a mock server in tests or GitHub Enterprise
+    pub fn with_base_urls(mut self, api_base: &str, raw_base: &str) -> Self {
+        self.api_base = api_base.trim_end_matches('/').to_string();
+        self.raw_base = raw_base.trim_end_matches('/').to_string();
+        self
+    }
+
+    /// GET `url`, authenticated when a token is available, retrying transient failures
+    async fn get(&self, what: &str, url: &str) -> Result<reqwest::Response> {
+        let response = self.retry
+            .run(what, || {
+                let request = http_client()
+                    .get(url)
+                    .header("User-Agent", "claude-stacks-cli")
+                    .header("Accept", "application/vnd.github+json");
+                let request = match github_token() {
+                    Some(token) => request.bearer_auth(token),
+                    None => request,
+                };
+                request.send()
+            }, is_transient_http)
+            .await?;
+        match rate_limit_error(response.status(), response.headers()) {
+            Some(error) => Err(error.into()),
+            None => Ok(response),
+        }
+    }
+
+    async fn list_stacks_pages(&self, repository: &StackRepository) -> Result<Vec<String>> {
+        let mut url = Some(format!(
+            "{}/repos/{}/{}/contents/stacks?ref={}&per_page={}",
+            self.api_base, repository.owner, repository.repo, repository.branch, PAGE_SIZE
+        ));
+        let mut names = Vec::new();
+        while let Some(page_url) = url.take() {
+            let response = self.get("Listing stacks", &page_url).await
+                .context("Failed to fetch stacks from GitHub API")?;
+
+            if response.status() == StatusCode::NOT_FOUND {
+                return Err(StacksError::validation(format!(
+                    "Registry {}/{} has no stacks directory on branch {}",
+                    repository.owner, repository.repo, repository.branch
+                ))
+                .with_hint("Check the registry setting; a private registry also needs GITHUB_TOKEN or 'stacks auth login'")
+                .into());
+            }
+            if !response.status().is_success() {
+                return Err(StacksError::network(format!("GitHub API request failed with status: {}", response.status()))
+                    .with_hint(format!(
+                        "Check that {}/{} exists and has a `stacks` directory on branch {}",
+                        repository.owner, repository.repo, repository.branch
+                    ))
+                    .into());
+            }
+
+            url = response.headers()
+                .get(reqwest::header::LINK)
+                .and_then(|link| link.to_str().ok())
+                .and_then(next_page);
+            let files: Vec<GitHubFile> = response
+                .json()
+                .await
+                .context("Failed to parse GitHub API response")?;
+            names.extend(files.into_iter().filter(|file| file.file_type == "dir").map(|file| file.name));
+        }
+        Ok(names)
+    }
+
+    async fn fetch_stack_file(&self, repository: &StackRepository, stack: &str, file: &str) -> Result<Option<String>> {
+        let url = format!(
+            "{}/{}/{}/{}/stacks/{}/{}",
+            self.raw_base, repository.owner, repository.repo, repository.branch, stack, file
+        );
+        let response = self.get("Fetching stack file", &url).await?;
+        match response.status() {
+            StatusCode::NOT_FOUND => Ok(None),
+            status if status.is_success() => Ok(Some(response.text().await?)),
+            status => anyhow::bail!("Fetching stacks/{}/{} failed with status: {}", stack, file, status),
+        }
+    }
+}
+
+impl RegistryClient for GitHubClient {
+    fn list_stacks<'a>(&'a self, repository: &'a StackRepository) -> BoxFuture<'a, Result<Vec<String>>> {
+        self.list_stacks_pages(repository).boxed()
+    }
+
+    fn stack_file<'a>(&'a self, repository: &'a StackRepository, stack: &'a str, file: &'a str) -> BoxFuture<'a, Result<Option<String>>> {
+        self.fetch_stack_file(repository, stack, file).boxed()
+    }
+}
+
+/// A registry laid out on disk as `<root>/stacks/<name>/...`, for offline development and tests
+///
+/// Stack directories that are git repositories, bare or not, are checked out from there; others
+/// fall back to the registry's naming convention.
+pub struct FixtureRegistry {
+    root: PathBuf,
+}
+
+impl FixtureRegistry {
+    pub fn new(root: PathBuf) -> Self {
+        Self { root }
+    }
+}
+
+impl RegistryClient for FixtureRegistry {
+    fn list_stacks<'a>(&'a self, _repository: &'a StackRepository) -> BoxFuture<'a, Result<Vec<String>>> {
+        async move {
+            let stacks_dir = self.root.join("stacks");
+            let entries = std::fs::read_dir(&stacks_dir)
+                .with_context(|| format!("Failed to read mock registry {}", stacks_dir.display()))?;
+            let mut names: Vec<String> = entries
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| entry.path().is_dir())
+                .map(|entry| entry.file_name().to_string_lossy().to_string())
+                .collect();
+            names.sort();
+            Ok(names)
+        }
+        .boxed()
+    }
+
+    fn stack_file<'a>(&'a self, _repository: &'a StackRepository, stack: &'a str, file: &'a str) -> BoxFuture<'a, Result<Option<String>>> {
+        async move {
+            let path = self.root.join("stacks").join(stack).join(file);
+            match path.is_file() {
+                true => Ok(Some(std::fs::read_to_string(&path)
+                    .with_context(|| format!("Failed to read {}", path.display()))?)),
+                false => Ok(None),
+            }
+        }
+        .boxed()
+    }
+
+    fn stack_repo_url(&self, stack: &str) -> Option<String> {
+        let path = self.root.join("stacks").join(stack);
+        match path.join(".git").exists() || path.join("HEAD").is_file() {
+            true => Some(path.to_string_lossy().to_string()),
+            false => None,
+        }
+    }
+}
+
+/// GitHub's answer when the rate limit is used up: 403 or 429 with no requests remaining
+fn rate_limit_error(status: StatusCode, headers: &HeaderMap) -> Option<StacksError> {
+    let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+    let exhausted = header("x-ratelimit-remaining") == Some("0");
+    match (status, exhausted) {
+        (StatusCode::FORBIDDEN, true) | (StatusCode::TOO_MANY_REQUESTS, _) => {}
+        _ => return None,
+    }
+
+    let resets = header("x-ratelimit-reset")
+        .and_then(|reset| reset.parse::<i64>().ok())
+        .and_then(|reset| chrono::DateTime::from_timestamp(reset, 0));
+    let error = StacksError::network("GitHub API rate limit exceeded")
+        .with_hint("Set GITHUB_TOKEN or run 'stacks auth login' for a higher limit");
+    Some(match resets {
+        Some(resets) => error.with_reason(format!(
+            "The limit resets at {}",
+            resets.with_timezone(&chrono::Local).format("%H:%M")
+        )),
+        None => error,
+    })
+}
+
+/// URL of the `rel="next"` page in a Link header
+fn next_page(link: &str) -> Option<String> {
+    link.split(',').find_map(|part| {
+        let (url, params) = part.split_once(';')?;
+        params
+            .split(';')
+            .any(|param| param.trim() == "rel=\"next\"")
+            .then(|| url.trim().trim_start_matches('<').trim_end_matches('>').to_string())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+    use wiremock::matchers::{method, path, query_param};
+    use wiremock::{Mock, MockServer, ResponseTemplate};
+
+    fn repository() -> StackRepository {
+        StackRepository::from_spec("acme/stacks@main").unwrap()
+    }
+
+    fn client(server: &MockServer) -> GitHubClient {
+        GitHubClient::new(RetryPolicy::default().with_attempts(1))
+            .with_base_urls(&server.uri(), &format!("{}/raw", server.uri()))
+    }
+
+    fn dir(name: &str) -> serde_json::Value {
+        json!({ "name": name, "path": format!("stacks/{}", name), "type": "dir" })
+    }
+
+    #[test]
+    fn test_next_page() {
+        let link = r#"<https://api.github.com/repositories/1/contents/stacks?page=2>; rel="next", <https://api.github.com/repositories/1/contents/stacks?page=3>; rel="last""#;
+        assert_eq!(next_page(link).as_deref(), Some("https://api.github.com/repositories/1/contents/stacks?page=2"));
+        assert_eq!(next_page(r#"<https://api.github.com/x?page=1>; rel="prev""#), None);
+    }
+
+    #[tokio::test]
+    async fn test_list_stacks_follows_pages() {
+        let server = MockServer::start().await;
+        // Mounted first, so the second page wins over the first-page mock below
+        Mock::given(method("GET"))
+            .and(path("/repos/acme/stacks/contents/stacks"))
+            .and(query_param("page", "2"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([dir("ts-lint")])))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/repos/acme/stacks/contents/stacks"))
+            .respond_with(ResponseTemplate::new(200)
+                .insert_header("link", format!(r#"<{}/repos/acme/stacks/contents/stacks?ref=main&page=2>; rel="next""#, server.uri()).as_str())
+                .set_body_json(json!([dir("api-docs"), { "name": "README.md", "path": "stacks/README.md", "type": "file" }])))
+            .mount(&server)
+            .await;
+
+        let names = client(&server).list_stacks(&repository()).await.unwrap();
+        assert_eq!(names, vec!["api-docs", "ts-lint"]);
+    }
+
+    #[tokio::test]
+    async fn test_missing_registry_and_files() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(404))
+            .mount(&server)
+            .await;
+
+        let error = client(&server).list_stacks(&repository()).await.unwrap_err();
+        assert_eq!(crate::error::classify(&error), crate::error::ErrorKind::Validation);
+        assert_eq!(client(&server).stack_file(&repository(), "ts-lint", "CLAUDE.md").await.unwrap(), None);
+    }
+
+    #[tokio::test]
+    async fn test_rate_limit_is_reported() {
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .respond_with(ResponseTemplate::new(403)
+                .insert_header("x-ratelimit-remaining", "0")
+                .insert_header("x-ratelimit-reset", "1760000000"))
+            .mount(&server)
+            .await;
+
+        let error = client(&server).list_stacks(&repository()).await.unwrap_err();
+        assert_eq!(crate::error::classify(&error), crate::error::ErrorKind::Network);
+        let detail = error.chain().find_map(|cause| cause.downcast_ref::<StacksError>()).unwrap();
+        assert_eq!(detail.message, "GitHub API rate limit exceeded");
+        assert!(detail.reason.as_deref().unwrap().starts_with("The limit resets at"));
+    }
+
+    #[tokio::test]
+    async fn test_fixture_registry() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        std::fs::create_dir_all(temp_dir.path().join("stacks/ts-lint")).unwrap();
+        std::fs::write(temp_dir.path().join("stacks/ts-lint/CLAUDE.md"), "# Description: Lint TypeScript\n").unwrap();
+
+        let registry = FixtureRegistry::new(temp_dir.path().to_path_buf());
+        assert_eq!(registry.list_stacks(&repository()).await.unwrap(), vec!["ts-lint"]);
+        assert!(registry.stack_file(&repository(), "ts-lint", "CLAUDE.md").await.unwrap().unwrap().contains("Lint"));
+        assert_eq!(registry.stack_repo_url("ts-lint"), None);
+
+        let bare_repo = temp_dir.path().join("stacks/ts-format");
+        std::fs::create_dir_all(&bare_repo).unwrap();
+        std::fs::write(bare_repo.join("HEAD"), "ref: refs/heads/main\n").unwrap();
+        assert_eq!(registry.stack_repo_url("ts-format"), Some(bare_repo.to_string_lossy().to_string()));
+    }
+}
@@ -1,10 +1,44 @@
 use std::path::{Path, PathBuf};
 use std::process::Command;
+use std::sync::{Arc, OnceLock};
 use anyhow::{Result, Context, bail};
 use serde::{Deserialize, Serialize};
-use dirs;
+use futures::stream::{self, StreamExt};
 
+use super::auth;
+use super::cache::{self, IndexedStack, RegistryIndex};
+use super::events::{default_reporter, Event, Reporter};
+use super::lockfile;
+use super::mirror::MirrorLocation;
+use super::registry_client::{self, RegistryClient};
+use super::policy;
+use super::upstream;
 use super::stack_manager::Stack;
+use crate::config::load_config;
+use crate::error::{classify, ErrorKind, StacksError};
+use crate::utils::retry::{is_transient_git, is_transient_git_process, is_transient_http, RetryPolicy};
+use crate::utils::logging::CommandLogExt;
+use crate::utils::process::Process;
+
+/// Time limit for a single HTTP request before it counts as a transient failure
+const REQUEST_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(30);
+
+/// HTTP client shared by every request of the process, built on first use
+///
+/// Building one loads the system's TLS roots, which takes longer than a discovery served from the
+/// cached registry index; commands that never go online skip it.
+pub fn http_client() -> reqwest::Client {
+    static CLIENT: OnceLock<reqwest::Client> = OnceLock::new();
+    CLIENT
+        .get_or_init(|| reqwest::Client::builder()
+            .timeout(REQUEST_TIMEOUT)
+            .build()
+            .unwrap_or_default())
+        .clone()
+}
+
+/// Stack descriptions fetched at once during discovery
+const DESCRIPTION_FETCH_CONCURRENCY: usize = 8;
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct GitHubFile {
@@ -14,6 +48,21 @@ pub struct GitHubFile {
     pub file_type: String,
 }
 
+#[derive(Debug, Clone, Deserialize)]
+pub struct GitHubOwner {
+    pub login: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct GitHubRepository {
+    pub name: String,
+    pub owner: GitHubOwner,
+    pub ssh_url: String,
+    pub clone_url: String,
+    pub html_url: String,
+    pub default_branch: String,
+}
+
 #[derive(Debug, Clone)]
 pub struct StackRepository {
     pub owner: String,
@@ -29,6 +78,205 @@ pub struct StackMetadata {
     pub source_branch: String,
     pub stack_name: String,
     pub original_path: String,
+    /// Directory of the stack inside its source repository; the repository root when unset
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_path: Option<String>,
+    /// Upstream commit currently installed in the project
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commit: Option<String>,
+    /// Release tag the stack is pinned to (e.g. v1.2.0); pulls fetch the tag instead of `source_branch`
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub source_tag: Option<String>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct GitHubRelease {
+    pub tag_name: String,
+    pub html_url: String,
+}
+
+/// Result of the code search API, with the text around each match
+#[derive(Debug, Clone, Deserialize)]
+pub struct GitHubCodeSearch {
+    pub items: Vec<GitHubCodeHit>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct GitHubCodeHit {
+    /// Path of the file in the repository
+    pub path: String,
+    #[serde(default)]
+    pub text_matches: Vec<GitHubTextMatch>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct GitHubTextMatch {
+    /// Lines of the file around the match
+    pub fragment: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct GitHubCommitDetails {
+    pub message: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct GitHubCommit {
+    pub sha: String,
+    pub commit: GitHubCommitDetails,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct GitHubComparison {
+    pub ahead_by: usize,
+    pub behind_by: usize,
+    #[serde(default)]
+    pub commits: Vec<GitHubCommit>,
+}
+
+impl StackMetadata {
+    /// Metadata derived from the naming convention of one repository per stack
+    pub fn from_convention(stack_name: &str) -> Self {
+        let repository = StackRepository::default();
+        Self {
+            source_repo: format!("git@github.com:{}/{}.git", repository.owner, stack_name),
+            source_owner: repository.owner,
+            source_name: stack_name.to_string(),
+            source_branch: repository.branch,
+            stack_name: stack_name.to_string(),
+            original_path: format!("stacks/{}", stack_name),
+            source_path: None,
+            commit: None,
+            source_tag: None,
+        }
+    }
+
+    /// Full name of the upstream ref: the pinned release tag, or the tracked branch
+    pub fn source_ref(&self) -> String {
+        match &self.source_tag {
+            Some(tag) => format!("refs/tags/{}", tag),
+            None => format!("refs/heads/{}", self.source_branch),
+        }
+    }
+
+    /// The pinned tag or the tracked branch, for display
+    pub fn describe_ref(&self) -> String {
+        match &self.source_tag {
+            Some(tag) => format!("tag {}", tag),
+            None => self.source_branch.clone(),
+        }
+    }
+}
+
+impl StackRepository {
+    /// Parse a registry spec: `owner/repo` or `owner/repo@branch`
+    pub fn from_spec(spec: &str) -> Result<Self> {
+        let (path, branch) = match spec.split_once('@') {
+            Some((path, branch)) => (path, branch.to_string()),
+            None => (spec, "main".to_string()),
+        };
+
+        match path.split_once('/') {
+            Some((owner, repo)) if !owner.is_empty() && !repo.is_empty() && !repo.contains('/') && !branch.is_empty() => {
+                Ok(Self { owner: owner.to_string(), repo: repo.to_string(), branch })
+            }
+            _ => bail!("Invalid registry '{}' (expected owner/repo or owner/repo@branch)", spec),
+        }
+    }
+}
+
+/// A stack named on the command line by its own repository rather than a registry name:
+/// `https://github.com/owner/repo(/tree/branch/path)`, `git@github.com:owner/repo.git`, or
+/// `owner/repo(#branch)(:path)`
+#[derive(Debug, Clone, PartialEq)]
+pub struct StackSource {
+    pub owner: String,
+    pub repo: String,
+    /// Clone URL; HTTPS when the stack was given as an HTTPS URL, SSH otherwise
+    pub url: String,
+    /// Branch to track; the repository's default branch when unset
+    pub branch: Option<String>,
+    /// Directory of the stack inside the repository; the repository root when unset
+    pub path: Option<String>,
+}
+
+impl StackSource {
+    /// None when `spec` looks like a plain registry stack name
+    pub fn parse(spec: &str) -> Option<Self> {
+        let spec = spec.trim();
+        if let Some(rest) = spec.strip_prefix("https://github.com/").or_else(|| spec.strip_prefix("http://github.com/")) {
+            let segments: Vec<&str> = rest.trim_end_matches('/').split('/').collect();
+            let (owner, repo) = match segments.as_slice() {
+                [owner, repo, ..] if !owner.is_empty() && !repo.is_empty() => {
+                    (owner.to_string(), repo.trim_end_matches(".git").to_string())
+                }
+                _ => return None,
+            };
+            // Only /tree/<branch>[/<path>] is meaningful past owner/repo
+            let (branch, path) = match segments.get(2..) {
+                Some(["tree", branch, path @ ..]) if !branch.is_empty() => {
+                    (Some(branch.to_string()), Some(path.join("/")).filter(|path| !path.is_empty()))
+                }
+                Some([]) | None => (None, None),
+                Some(_) => return None,
+            };
+            return Some(Self { url: format!("https://github.com/{}/{}.git", owner, repo), owner, repo, branch, path });
+        }
+
+        if spec.starts_with("git@github.com:") || spec.starts_with("ssh://git@github.com/") {
+            let (owner, repo) = parse_github_repo(spec)?;
+            return Some(Self { url: format!("git@github.com:{}/{}.git", owner, repo), owner, repo, branch: None, path: None });
+        }
+
+        // owner/repo, optionally followed by #branch, #branch:path or #:path
+        let (repository, reference) = match spec.split_once('#') {
+            Some((repository, reference)) => (repository, Some(reference)),
+            None => (spec, None),
+        };
+        let (owner, repo) = repository.split_once('/')?;
+        let valid = |part: &str| !part.is_empty() && part.chars().all(|c| c.is_ascii_alphanumeric() || "-_.".contains(c));
+        if !valid(owner) || !valid(repo) {
+            return None;
+        }
+        let (branch, path) = match reference.map(|reference| reference.split_once(':').unwrap_or((reference, ""))) {
+            Some((branch, path)) => (
+                Some(branch.to_string()).filter(|branch| !branch.is_empty()),
+                Some(path.trim_matches('/').to_string()).filter(|path| !path.is_empty()),
+            ),
+            None => (None, None),
+        };
+        Some(Self {
+            url: format!("git@github.com:{}/{}.git", owner, repo),
+            owner: owner.to_string(),
+            repo: repo.to_string(),
+            branch,
+            path,
+        })
+    }
+
+    /// Directory name under stacks/: the last path segment, or the repository name
+    pub fn stack_name(&self) -> String {
+        self.path
+            .as_deref()
+            .and_then(|path| path.rsplit('/').next())
+            .unwrap_or(&self.repo)
+            .to_string()
+    }
+
+    /// e.g. "acme/stacks (branch dev, path stacks/lint)"
+    pub fn describe(&self) -> String {
+        let details: Vec<String> = [
+            self.branch.as_ref().map(|branch| format!("branch {}", branch)),
+            self.path.as_ref().map(|path| format!("path {}", path)),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        match details.is_empty() {
+            true => format!("{}/{}", self.owner, self.repo),
+            false => format!("{}/{} ({})", self.owner, self.repo, details.join(", ")),
+        }
+    }
 }
 
 impl Default for StackRepository {
@@ -41,77 +289,169 @@ impl Default for StackRepository {
     }
 }
 
+/// The stacks of a cached registry index, as checked out under `stacks_dir`
+fn stacks_from_index(index: RegistryIndex, stacks_dir: &Path) -> Vec<Stack> {
+    index.stacks
+        .into_iter()
+        .map(|indexed| {
+            let mut stack = Stack::new(indexed.name.clone(), stacks_dir.join(&indexed.name));
+            stack.description = indexed.description;
+            stack
+        })
+        .collect()
+}
+
 pub struct RemoteStackManager {
     pub repository: StackRepository,
-    #[allow(dead_code)]
     cache_dir: PathBuf,
-    client: reqwest::Client,
+    retry: RetryPolicy,
+    /// Registry mirror to use instead of GitHub, from `registry-mirror`
+    mirror: Option<MirrorLocation>,
+    /// Lists the registry's stacks and reads their files
+    registry: Box<dyn RegistryClient>,
+    reporter: Arc<dyn Reporter>,
 }
 
 impl RemoteStackManager {
     pub fn new() -> Result<Self> {
-        let cache_dir = dirs::cache_dir()
-            .context("Failed to get cache directory")?
-            .join("claude-stacks");
+        let cache_dir = cache::cache_root()?;
         
         std::fs::create_dir_all(&cache_dir)
             .context("Failed to create cache directory")?;
 
+        let retry = RetryPolicy::from_config();
+
         Ok(Self {
             repository: StackRepository::default(),
             cache_dir,
-            client: reqwest::Client::new(),
+            registry: registry_client::from_env(retry.clone()),
+            retry,
+            mirror: load_config().ok()
+                .and_then(|config| config.registry_mirror)
+                .map(|location| MirrorLocation::parse(&location)),
+            reporter: default_reporter(),
         })
     }
 
-    #[allow(dead_code)]
+    /// Manager for a configured registry spec, or the default registry
+    pub fn for_registry(registry: Option<&str>) -> Result<Self> {
+        match registry {
+            Some(spec) => Self::with_repository(StackRepository::from_spec(spec)?),
+            None => Self::new(),
+        }
+    }
+
     pub fn with_repository(repository: StackRepository) -> Result<Self> {
         let mut manager = Self::new()?;
         manager.repository = repository;
         Ok(manager)
     }
 
-    /// Discover available stacks from the GitHub repository
-    pub async fn discover_remote_stacks(&self) -> Result<Vec<Stack>> {
-        let url = format!(
-            "https://api.github.com/repos/{}/{}/contents/stacks?ref={}",
-            self.repository.owner, self.repository.repo, self.repository.branch
-        );
+    /// Talk to the registry itself even when a mirror is configured, e.g. to refresh the mirror
+    pub fn without_mirror(mut self) -> Self {
+        self.mirror = None;
+        self
+    }
 
-        let response = self.client
-            .get(&url)
-            .header("User-Agent", "claude-stacks-cli")
-            .send()
-            .await
-            .context("Failed to fetch stacks from GitHub API")?;
+    pub fn with_reporter(mut self, reporter: Arc<dyn Reporter>) -> Self {
+        self.reporter = reporter;
+        self
+    }
 
-        if !response.status().is_success() {
-            bail!("GitHub API request failed with status: {}", response.status());
-        }
+    /// List and read the registry through `registry` instead of GitHub, e.g. a mock server in tests
+    pub fn with_registry_client(mut self, registry: impl RegistryClient + 'static) -> Self {
+        self.registry = Box::new(registry);
+        self
+    }
 
-        let files: Vec<GitHubFile> = response
-            .json()
-            .await
-            .context("Failed to parse GitHub API response")?;
+    /// `owner/repo@branch` of the registry, as used for its cached index
+    pub(crate) fn registry_spec(&self) -> String {
+        format!("{}/{}@{}", self.repository.owner, self.repository.repo, self.repository.branch)
+    }
+
+    /// Discover available stacks from the GitHub repository
+    ///
+    /// The listing is cached: a listing fetched in the last few minutes is used as is, so the
+    /// picker opens without waiting on GitHub, and when GitHub can't be reached the last listing
+    /// is used instead.
+    pub async fn discover_remote_stacks(&self) -> Result<Vec<Stack>> {
+        self.discover(true).await
+    }
 
-        let mut stacks = Vec::new();
+    /// Discover available stacks from the GitHub repository, ignoring a recent cached listing
+    ///
+    /// For when the listing must be current, e.g. a stack named on the command line that the
+    /// cached listing doesn't have yet.
+    pub async fn refresh_remote_stacks(&self) -> Result<Vec<Stack>> {
+        self.discover(false).await
+    }
 
-        for file in files {
-            if file.file_type == "dir" {
-                let stack_name = file.name.clone();
-                let local_path = std::env::current_dir()?.join("stacks").join(&stack_name);
-                
-                // Create a stack object for the remote stack
-                let mut stack = Stack::new(stack_name, local_path);
-                
-                // Always fetch description from remote CLAUDE.md (don't rely on local cache)
-                if let Some(description) = self.fetch_stack_description(&file.name).await? {
-                    stack.description = Some(description);
+    async fn discover(&self, use_fresh_index: bool) -> Result<Vec<Stack>> {
+        policy::enforce_registry(&self.repository.owner, &self.repository.repo).await?;
+        let stacks_dir = std::env::current_dir()?.join("stacks");
+        if let Some(mirror) = &self.mirror {
+            let index = mirror.load_index().await?;
+            return Ok(index.stacks
+                .into_iter()
+                .map(|mirrored| {
+                    let mut stack = Stack::new(mirrored.name.clone(), stacks_dir.join(&mirrored.name));
+                    stack.description = mirrored.description;
+                    stack
+                })
+                .collect());
+        }
+        if use_fresh_index {
+            if let Some(index) = cache::load_registry_index(&self.registry_spec()).filter(|index| index.is_fresh(chrono::Utc::now())) {
+                tracing::debug!(registry = %index.registry, fetched_at = %index.fetched_at, "listing stacks from the cached registry index");
+                return Ok(stacks_from_index(index, &stacks_dir));
+            }
+        }
+        let error = match self.fetch_remote_stacks(&stacks_dir).await {
+            Ok(stacks) => {
+                let index = RegistryIndex {
+                    registry: self.registry_spec(),
+                    fetched_at: chrono::Utc::now(),
+                    stacks: stacks.iter()
+                        .map(|stack| IndexedStack { name: stack.name.clone(), description: stack.description.clone() })
+                        .collect(),
+                };
+                if let Err(error) = cache::save_registry_index(&index) {
+                    tracing::debug!("failed to cache registry index: {:#}", error);
                 }
-                
-                stacks.push(stack);
+                return Ok(stacks);
             }
+            Err(error) => error,
+        };
+
+        if classify(&error) != ErrorKind::Network {
+            return Err(error);
         }
+        let index = match cache::load_registry_index(&self.registry_spec()) {
+            Some(index) if !index.stacks.is_empty() => index,
+            _ => return Err(error),
+        };
+        self.reporter.report(&Event::RegistryCacheUsed {
+            registry: self.registry_spec(),
+            fetched_at: index.fetched_at,
+        });
+        Ok(stacks_from_index(index, &stacks_dir))
+    }
+
+    async fn fetch_remote_stacks(&self, stacks_dir: &Path) -> Result<Vec<Stack>> {
+        let stack_names = self.registry.list_stacks(&self.repository).await?;
+
+        // Always fetch descriptions from the remote CLAUDE.md (don't rely on local cache),
+        // a few at a time so large repositories don't wait on one request after another
+        let mut stacks: Vec<Stack> = stream::iter(stack_names)
+            .map(|stack_name| async move {
+                let description = self.fetch_stack_description(&stack_name).await.ok().flatten();
+                let mut stack = Stack::new(stack_name.clone(), stacks_dir.join(&stack_name));
+                stack.description = description;
+                stack
+            })
+            .buffer_unordered(DESCRIPTION_FETCH_CONCURRENCY)
+            .collect()
+            .await;
 
         if stacks.is_empty() {
             bail!("No stacks found in repository {}/{}", self.repository.owner, self.repository.repo);
@@ -123,73 +463,323 @@ impl RemoteStackManager {
 
     /// Fetch the description from a stack's CLAUDE.md file
     async fn fetch_stack_description(&self, stack_name: &str) -> Result<Option<String>> {
+        let content = match self.registry.stack_file(&self.repository, stack_name, "CLAUDE.md").await {
+            Ok(Some(content)) => content,
+            _ => return Ok(None), // Ignore errors for description fetching
+        };
+
+        // Extract description from CLAUDE.md
+        for line in content.lines() {
+            if line.starts_with("# Description:") {
+                return Ok(Some(line.trim_start_matches("# Description:").trim().to_string()));
+            }
+        }
+        Ok(None)
+    }
+
+    /// Send a request built by `build`, retrying timeouts, dropped connections and server errors
+    async fn send(&self, what: &str, build: impl Fn() -> reqwest::RequestBuilder) -> reqwest::Result<reqwest::Response> {
+        self.retry.run(what, || build().send(), is_transient_http).await
+    }
+
+    /// GET request to the GitHub API, authenticated when a token is available
+    fn github_get(&self, url: &str) -> reqwest::RequestBuilder {
+        let request = http_client()
+            .get(url)
+            .header("User-Agent", "claude-stacks-cli")
+            .header("Accept", "application/vnd.github+json");
+        match github_token() {
+            Some(token) => request.bearer_auth(token),
+            None => request,
+        }
+    }
+
+    /// Compare two commits of a repository (commits in `head` not in `base`)
+    pub async fn compare_commits(&self, owner: &str, repo: &str, base: &str, head: &str) -> Result<GitHubComparison> {
         let url = format!(
-            "https://raw.githubusercontent.com/{}/{}/{}/stacks/{}/CLAUDE.md",
-            self.repository.owner, self.repository.repo, self.repository.branch, stack_name
+            "https://api.github.com/repos/{}/{}/compare/{}...{}",
+            owner, repo, base, head
         );
 
-        let response = self.client
-            .get(&url)
+        let response = self
+            .send("Comparing commits", || self.github_get(&url))
+            .await
+            .context("Failed to compare commits via GitHub API")?;
+
+        if !response.status().is_success() {
+            bail!("GitHub compare request for {}/{} failed with status: {}", owner, repo, response.status());
+        }
+
+        response
+            .json()
+            .await
+            .context("Failed to parse GitHub compare response")
+    }
+
+    /// Latest published release of a repository, if it has any
+    pub async fn latest_release(&self, owner: &str, repo: &str) -> Result<Option<GitHubRelease>> {
+        let url = format!("https://api.github.com/repos/{}/{}/releases/latest", owner, repo);
+
+        let response = self
+            .send("Fetching latest release", || self.github_get(&url))
+            .await
+            .context("Failed to fetch latest release from GitHub API")?;
+
+        if response.status() == reqwest::StatusCode::NOT_FOUND {
+            return Ok(None);
+        }
+        if !response.status().is_success() {
+            bail!("GitHub release request for {}/{} failed with status: {}", owner, repo, response.status());
+        }
+
+        let release = response
+            .json()
+            .await
+            .context("Failed to parse GitHub release response")?;
+        Ok(Some(release))
+    }
+
+    /// Search the registry's `stacks/` directory for `text` with GitHub code search
+    ///
+    /// Code search needs a token and matches words, not regular expressions; it only covers the
+    /// registry's default branch.
+    pub async fn search_code(&self, text: &str) -> Result<GitHubCodeSearch> {
+        if self.mirror.is_some() {
+            return Err(StacksError::validation("Registry search needs GitHub, but a registry mirror is configured")
+                .with_hint("Search installed stacks only by leaving out --remote")
+                .into());
+        }
+        let token = github_token().ok_or_else(|| StacksError::validation("GitHub code search needs a token")
+            .with_hint("Run 'stacks auth login' or set GITHUB_TOKEN"))?;
+        let query = format!("{} repo:{}/{} path:stacks", text, self.repository.owner, self.repository.repo);
+
+        let response = self
+            .send("Searching the registry", || http_client()
+                .get("https://api.github.com/search/code")
+                .query(&[("q", query.as_str()), ("per_page", "100")])
+                .header("User-Agent", "claude-stacks-cli")
+                .header("Accept", "application/vnd.github.text-match+json")
+                .bearer_auth(&token))
+            .await
+            .map_err(|e| StacksError::network(format!("Failed to search {}: {}", self.registry_spec(), e)))?;
+
+        if !response.status().is_success() {
+            return Err(StacksError::network(format!("GitHub code search failed with status: {}", response.status())).into());
+        }
+        response
+            .json()
+            .await
+            .context("Failed to parse GitHub code search response")
+    }
+
+    /// Fork a repository into the authenticated user's account
+    pub async fn fork_repository(&self, owner: &str, repo: &str, token: &str) -> Result<GitHubRepository> {
+        let url = format!("https://api.github.com/repos/{}/{}/forks", owner, repo);
+
+        let response = http_client()
+            .post(&url)
             .header("User-Agent", "claude-stacks-cli")
+            .header("Accept", "application/vnd.github+json")
+            .bearer_auth(token)
             .send()
-            .await;
+            .await
+            .context("Failed to request fork from GitHub API")?;
 
-        match response {
-            Ok(resp) if resp.status().is_success() => {
-                let content = resp.text().await?;
-                
-                // Extract description from CLAUDE.md
-                for line in content.lines() {
-                    if line.starts_with("# Description:") {
-                        return Ok(Some(line.trim_start_matches("# Description:").trim().to_string()));
-                    }
-                }
-                Ok(None)
+        if !response.status().is_success() {
+            bail!("GitHub fork request for {}/{} failed with status: {}", owner, repo, response.status());
+        }
+
+        let fork: GitHubRepository = response
+            .json()
+            .await
+            .context("Failed to parse GitHub fork response")?;
+
+        // Forks are created asynchronously; wait until the repository is reachable
+        for _ in 0..15 {
+            let check = http_client()
+                .get(format!("https://api.github.com/repos/{}/{}", fork.owner.login, fork.name))
+                .header("User-Agent", "claude-stacks-cli")
+                .bearer_auth(token)
+                .send()
+                .await;
+
+            if matches!(check, Ok(ref resp) if resp.status().is_success()) {
+                return Ok(fork);
+            }
+            tokio::time::sleep(std::time::Duration::from_secs(2)).await;
+        }
+
+        bail!("Fork {}/{} was requested but did not become available in time", fork.owner.login, fork.name)
+    }
+
+    /// Add a stack as a git subtree, at `pinned` when its signature was checked or its content reviewed
+    pub async fn add_stack_subtree(&self, stack_name: &str, pinned: Option<&str>) -> Result<PathBuf> {
+        let (repo_url, branch) = self.stack_source(stack_name).await?;
+        self.add_subtree(stack_name, &repo_url, branch, None, pinned).await
+    }
+
+    /// Where a registry stack is checked out from: its repository, or its bundle in the mirror
+    ///
+    /// The branch is only known for mirrored stacks; repositories are checked out at their default branch.
+    pub async fn stack_source(&self, stack_name: &str) -> Result<(String, Option<String>)> {
+        let mirror = match &self.mirror {
+            Some(mirror) => mirror,
+            None => {
+                let url = self.registry.stack_repo_url(stack_name).unwrap_or_else(|| self.stack_repo_url(stack_name));
+                return Ok((url, None));
+            }
+        };
+        let index = mirror.load_index().await?;
+        let mirrored = match index.get(stack_name) {
+            Some(mirrored) => mirrored,
+            None => {
+                return Err(StacksError::validation(format!("Stack {} is not in the registry mirror at {}", stack_name, mirror.describe()))
+                    .with_hint("Refresh the mirror with 'stacks registry mirror'")
+                    .into())
+            }
+        };
+        let bundle = mirror.bundle(mirrored).await?;
+        Ok((bundle.to_string_lossy().to_string(), Some(mirrored.branch.clone())))
+    }
+
+    /// Repository a registry stack is checked out from
+    pub fn stack_repo_url(&self, stack_name: &str) -> String {
+        // Map stack names to their specific repositories
+        match stack_name {
+            "ts-lint-stack" => "git@github.com:csaben/ts-lint-stack.git".to_string(),
+            "stackstack" => "git@github.com:csaben/stackstack.git".to_string(),
+            _ => {
+                // For other stacks, assume they're in separate repositories following the pattern
+                format!("git@github.com:{}/{}.git", self.repository.owner, stack_name)
             }
-            _ => Ok(None), // Ignore errors for description fetching
         }
     }
 
-    /// Add a stack as a git subtree
-    pub async fn add_stack_subtree(&self, stack_name: &str) -> Result<PathBuf> {
+    /// Add a stack given by URL or `owner/repo` as a git subtree
+    pub async fn add_stack_from_source(&self, source: &StackSource, pinned: Option<&str>) -> Result<PathBuf> {
+        self.add_subtree(&source.stack_name(), &source.url, source.branch.clone(), source.path.as_deref(), pinned).await
+    }
+
+    /// Add a stack under `stack_name` from a recorded source, e.g. an entry of `stacks export`
+    pub async fn add_stack_from_repo(&self, stack_name: &str, repo_url: &str, branch: &str, path: Option<&str>, pinned: Option<&str>) -> Result<PathBuf> {
+        self.add_subtree(stack_name, repo_url, Some(branch.to_string()), path, pinned).await
+    }
+
+    /// Add `repo_url` (or its `path` subdirectory) at stacks/<stack_name> and record it in stacks.lock
+    ///
+    /// With `pinned`, the branch must still be at that commit, the one whose signature was checked or
+    /// whose content was reviewed.
+    ///
+    /// Stacks the organization policy forbids are refused before their subtree is added.
+    async fn add_subtree(&self, stack_name: &str, repo_url: &str, branch: Option<String>, path: Option<&str>, pinned: Option<&str>) -> Result<PathBuf> {
         let stack_path = std::env::current_dir()?.join("stacks").join(stack_name);
         
         // Check if already exists
         if stack_path.exists() {
-            println!("  📦 Stack already exists: {}", stack_name);
+            self.reporter.report(&Event::StackAlreadyPresent { stack: stack_name.to_string() });
             return Ok(stack_path);
         }
         
-        // Map stack names to their specific repositories
-        let repo_url = match stack_name {
-            "ts-lint-stack" => "git@github.com:csaben/ts-lint-stack.git".to_string(),
-            "stackstack" => "git@github.com:csaben/stackstack.git".to_string(),
-            _ => {
-                // For other stacks, assume they're in separate repositories following the pattern
-                format!("git@github.com:{}/{}.git", self.repository.owner, stack_name)
-            }
+        // A release tag (e.g. `owner/repo#v1.2.0`) pins the stack; pushes still go to the default branch
+        let tag = branch.clone().filter(|name| upstream::is_remote_tag(repo_url, name));
+        // Stacks track the repository's default branch, which isn't always main
+        let branch = branch.filter(|_| tag.is_none()).unwrap_or_else(|| {
+            upstream::remote_default_branch(repo_url)
+                .ok()
+                .flatten()
+                .unwrap_or_else(|| "main".to_string())
+        });
+        let (fetch_ref, described_ref) = match &tag {
+            Some(tag) => (format!("refs/tags/{}", tag), tag.clone()),
+            None => (format!("refs/heads/{}", branch), branch.clone()),
         };
+        let prefix = format!("stacks/{}", stack_name);
         
-        println!("  📥 Adding {} as subtree from {}", stack_name, repo_url);
-        
-        // Add as git subtree
-        let subtree_output = Command::new("git")
-            .args([
-                "subtree", "add", 
-                "--prefix", &format!("stacks/{}", stack_name),
-                &repo_url,
-                "main",
-                "--squash"
-            ])
+        self.reporter.report(&Event::SubtreeAdding {
+            stack: stack_name.to_string(),
+            repo_url: repo_url.to_string(),
+            branch: described_ref.clone(),
+            path: path.map(str::to_string),
+        });
+        // Fetch first and add the exact commit, so the installed content is the one that was checked.
+        // Only FETCH_HEAD changes, so this also runs with --dry-run.
+        let fetch = self.retry
+            .run_blocking("Fetching stack", || {
+                Process::git()
+                    .env("LC_ALL", "C")
+                    .envs(auth::git_auth_env(repo_url))
+                    .args(["fetch", "--quiet", "--no-tags", repo_url, &fetch_ref])
+                    .read_only()
+                    .output()
+            }, is_transient_git_process)
+            .context("Failed to execute git fetch")?;
+        if !fetch.success() {
+            bail!("Failed to fetch {} ({}): {}", repo_url, described_ref, fetch.stderr.trim());
+        }
+        let fetched = Process::git()
+            .args(["rev-parse", "FETCH_HEAD^{commit}"])
+            .read_only()
+            .run()
+            .context("Failed to resolve the fetched commit")?
+            .stdout
+            .trim()
+            .to_string();
+        if let Some(pinned) = pinned {
+            if pinned != fetched {
+                return Err(StacksError::validation(format!(
+                    "{} moved upstream after it was checked (checked {}, fetched {})",
+                    stack_name, pinned, fetched
+                ))
+                .with_hint("Run the checkout again to check the new commit")
+                .into());
+            }
+        }
+        let commit = match path {
+            None => fetched,
+            Some(path) => {
+                // A stack inside a larger repository: split its directory's history out of the fetched commit
+                let split = Process::git()
+                    .args(["subtree", "split", "--prefix", path, &fetched])
+                    .read_only()
+                    .output()
+                    .context("Failed to execute git subtree split")?;
+                if !split.success() {
+                    bail!("Path '{}' not found on {} of {}: {}", path, described_ref, repo_url, split.stderr.trim());
+                }
+                split.stdout.trim().to_string()
+            }
+        };
+        policy::enforce_commit(stack_name, &commit).await?;
+        let subtree_output = Process::git()
+            .args(["subtree", "add", "--prefix", &prefix, &commit, "--squash"])
             .output()
             .context("Failed to execute git subtree add")?;
             
-        if !subtree_output.status.success() {
-            let error = String::from_utf8_lossy(&subtree_output.stderr);
-            bail!("Git subtree add failed: {}", error);
+        if !subtree_output.success() {
+            bail!("Git subtree add failed: {}", subtree_output.stderr);
         }
         
-        println!("  ✅ Successfully added {} as subtree", stack_name);
+        self.reporter.report(&Event::SubtreeAdded { stack: stack_name.to_string() });
+        
+        // Record the source so pull/status/push work without per-stack metadata
+        let metadata = StackMetadata {
+            source_repo: repo_url.to_string(),
+            source_owner: parse_github_repo(repo_url)
+                .map(|(owner, _)| owner)
+                .unwrap_or_else(|| self.repository.owner.clone()),
+            source_name: parse_github_repo(repo_url)
+                .map(|(_, repo)| repo)
+                .unwrap_or_else(|| stack_name.to_string()),
+            source_branch: branch,
+            stack_name: stack_name.to_string(),
+            original_path: prefix.clone(),
+            source_path: path.map(str::to_string),
+            commit: None,
+            source_tag: tag,
+        };
+        lockfile::record_stack(metadata)
+            .context("Failed to record stack in stacks.lock")?;
+        
         Ok(stack_path)
     }
 
@@ -203,7 +793,7 @@ impl RemoteStackManager {
         if stack_path.exists() {
             let stack = Stack::new(stack_name.to_string(), stack_path.clone());
             if stack.is_valid() {
-                println!("  📦 Using existing stack: {}", stack_name);
+                outln!("  📦 Using existing stack: {}", stack_name);
                 return Ok(stack_path);
             }
             
@@ -217,7 +807,7 @@ impl RemoteStackManager {
         std::fs::create_dir_all(&stacks_dir)
             .context("Failed to create stacks directory")?;
 
-        println!("  ⬇️ Checking out stack: {}", stack_name);
+        outln!("  ⬇️ Checking out stack: {}", stack_name);
         
         // Use sparse checkout to get only the specific stack
         self.git_clone_stack(stack_name).await
@@ -238,14 +828,14 @@ impl RemoteStackManager {
         }
         
         // Clone the full repository to a temporary location
-        println!("  📦 Cloning repository...");
-        let clone_output = Command::new("git")
-            .args([
-                "clone",
-                &ssh_url,
-                temp_path.to_str().unwrap(),
-            ])
-            .output()
+        outln!("  📦 Cloning repository...");
+        let clone_output = self.retry
+            .run_blocking("Cloning repository", || {
+                Command::new("git")
+                    .env("LC_ALL", "C")
+                    .args(["clone", &ssh_url, temp_path.to_str().unwrap()])
+                    .logged_output()
+            }, is_transient_git)
             .context("Failed to execute git clone")?;
 
         if !clone_output.status.success() {
@@ -258,7 +848,7 @@ impl RemoteStackManager {
             bail!("Stack '{}' not found in repository", stack_name);
         }
         
-        println!("  📁 Extracting stack content...");
+        outln!("  📁 Extracting stack content...");
         std::fs::create_dir_all(&final_stack_path)?;
         self.copy_dir_all(&source_stack_path, &final_stack_path)?;
         
@@ -266,7 +856,7 @@ impl RemoteStackManager {
         let git_init_output = Command::new("git")
             .current_dir(&final_stack_path)
             .args(["init"])
-            .output()
+            .logged_output()
             .context("Failed to initialize git repository")?;
             
         if !git_init_output.status.success() {
@@ -277,7 +867,7 @@ impl RemoteStackManager {
         let remote_output = Command::new("git")
             .current_dir(&final_stack_path)
             .args(["remote", "add", "origin", &ssh_url])
-            .output()
+            .logged_output()
             .context("Failed to add remote origin")?;
             
         if !remote_output.status.success() {
@@ -285,10 +875,14 @@ impl RemoteStackManager {
         }
         
         // Fetch from origin
-        let fetch_output = Command::new("git")
-            .current_dir(&final_stack_path)
-            .args(["fetch", "origin"])
-            .output()
+        let fetch_output = self.retry
+            .run_blocking("Fetching from origin", || {
+                Command::new("git")
+                    .current_dir(&final_stack_path)
+                    .env("LC_ALL", "C")
+                    .args(["fetch", "origin"])
+                    .logged_output()
+            }, is_transient_git)
             .context("Failed to fetch from origin")?;
             
         if !fetch_output.status.success() {
@@ -299,7 +893,7 @@ impl RemoteStackManager {
         let branch_output = Command::new("git")
             .current_dir(&final_stack_path)
             .args(["checkout", "-b", &self.repository.branch, &format!("origin/{}", self.repository.branch)])
-            .output()
+            .logged_output()
             .context("Failed to checkout branch")?;
             
         if !branch_output.status.success() {
@@ -317,10 +911,13 @@ impl RemoteStackManager {
             source_branch: self.repository.branch.clone(),
             stack_name: stack_name.to_string(),
             original_path: format!("stacks/{}", stack_name),
+            source_path: None,
+            commit: None,
+            source_tag: None,
         };
 
         self.save_stack_metadata(&final_stack_path, &metadata)?;
-        println!("  📋 Stack initialized as independent git repository");
+        outln!("  📋 Stack initialized as independent git repository");
 
         Ok(())
     }
@@ -331,10 +928,10 @@ impl RemoteStackManager {
         let git_dir = std::env::current_dir()?.join(".git");
         
         if !git_dir.exists() {
-            println!("  🎯 Initializing git repository...");
+            outln!("  🎯 Initializing git repository...");
             let init_output = Command::new("git")
                 .args(["init"])
-                .output()
+                .logged_output()
                 .context("Failed to initialize git repository")?;
                 
             if !init_output.status.success() {
@@ -344,11 +941,11 @@ impl RemoteStackManager {
             // Set up initial commit if no commits exist
             let log_output = Command::new("git")
                 .args(["log", "--oneline", "-1"])
-                .output();
+                .logged_output();
                 
             if log_output.is_err() || !log_output.unwrap().status.success() {
                 // Create initial commit
-                println!("  📝 Creating initial commit...");
+                outln!("  📝 Creating initial commit...");
                 
                 // Create a README if it doesn't exist
                 let readme_path = std::env::current_dir()?.join("README.md");
@@ -358,7 +955,7 @@ impl RemoteStackManager {
                 
                 let add_output = Command::new("git")
                     .args(["add", "."])
-                    .output()
+                    .logged_output()
                     .context("Failed to add files")?;
                     
                 if !add_output.status.success() {
@@ -367,7 +964,7 @@ impl RemoteStackManager {
                 
                 let commit_output = Command::new("git")
                     .args(["commit", "-m", "feat: initial commit with stacks setup"])
-                    .output()
+                    .logged_output()
                     .context("Failed to create initial commit")?;
                     
                 if !commit_output.status.success() {
@@ -412,23 +1009,10 @@ impl RemoteStackManager {
     }
 
     /// Get the cache directory path
-    #[allow(dead_code)]
     pub fn cache_dir(&self) -> &Path {
         &self.cache_dir
     }
 
-    /// Clear the entire stack cache
-    #[allow(dead_code)]
-    pub fn clear_cache(&self) -> Result<()> {
-        if self.cache_dir.exists() {
-            std::fs::remove_dir_all(&self.cache_dir)
-                .context("Failed to clear stack cache")?;
-            std::fs::create_dir_all(&self.cache_dir)
-                .context("Failed to recreate cache directory")?;
-        }
-        Ok(())
-    }
-
     /// Update cached stack (re-download)
     #[allow(dead_code)]
     pub async fn update_stack(&self, stack_name: &str) -> Result<PathBuf> {
@@ -445,6 +1029,52 @@ impl RemoteStackManager {
     }
 }
 
+/// Find a GitHub token from the environment, `stacks auth login`, or the gh CLI
+pub fn github_token() -> Option<String> {
+    for var in ["GITHUB_TOKEN", "GH_TOKEN"] {
+        if let Ok(token) = std::env::var(var) {
+            if !token.trim().is_empty() {
+                return Some(token.trim().to_string());
+            }
+        }
+    }
+
+    if let Some(token) = super::auth::stored_token() {
+        return Some(token);
+    }
+
+    let output = Command::new("gh")
+        .args(["auth", "token"])
+        .output()
+        .ok()?;
+
+    if output.status.success() {
+        let token = String::from_utf8_lossy(&output.stdout).trim().to_string();
+        if !token.is_empty() {
+            return Some(token);
+        }
+    }
+
+    None
+}
+
+/// Extract (owner, repo) from a GitHub SSH or HTTPS URL
+pub fn parse_github_repo(url: &str) -> Option<(String, String)> {
+    let path = url
+        .strip_prefix("git@github.com:")
+        .or_else(|| url.strip_prefix("ssh://git@github.com/"))
+        .or_else(|| url.strip_prefix("https://github.com/"))
+        .or_else(|| url.strip_prefix("http://github.com/"))?;
+
+    let path = path.trim_end_matches('/');
+    let path = path.strip_suffix(".git").unwrap_or(path);
+    let mut parts = path.split('/');
+    let owner = parts.next().filter(|s| !s.is_empty())?;
+    let repo = parts.next().filter(|s| !s.is_empty())?;
+
+    Some((owner.to_string(), repo.to_string()))
+}
+
 /// Fallback to local stacks directory for development/testing
 #[allow(dead_code)]
 pub async fn discover_local_stacks() -> Result<Vec<Stack>> {
@@ -456,23 +1086,96 @@ pub async fn discover_local_stacks() -> Result<Vec<Stack>> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_registry_spec() {
+        let repository = StackRepository::from_spec("acme/team-stacks@trunk").unwrap();
+        assert_eq!(repository.owner, "acme");
+        assert_eq!(repository.repo, "team-stacks");
+        assert_eq!(repository.branch, "trunk");
+
+        assert_eq!(StackRepository::from_spec("acme/team-stacks").unwrap().branch, "main");
+        assert!(StackRepository::from_spec("acme").is_err());
+    }
+
     #[tokio::test]
     async fn test_remote_stack_discovery() {
-        let manager = RemoteStackManager::new().unwrap();
-        
-        // This test requires internet access and the actual repository
-        // In a real scenario, you'd mock the HTTP client
-        match manager.discover_remote_stacks().await {
-            Ok(stacks) => {
-                assert!(!stacks.is_empty());
-                println!("Found {} stacks", stacks.len());
-                for stack in stacks {
-                    println!("  - {}: {:?}", stack.name, stack.description);
-                }
-            }
-            Err(e) => {
-                println!("Failed to discover remote stacks (expected in CI): {}", e);
-            }
-        }
+        use crate::core::registry_client::GitHubClient;
+        use wiremock::matchers::{method, path};
+        use wiremock::{Mock, MockServer, ResponseTemplate};
+
+        let server = MockServer::start().await;
+        Mock::given(method("GET"))
+            .and(path("/repos/acme/offline-stacks/contents/stacks"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!([
+                { "name": "ts-lint", "path": "stacks/ts-lint", "type": "dir" },
+                { "name": "api-docs", "path": "stacks/api-docs", "type": "dir" },
+            ])))
+            .mount(&server)
+            .await;
+        Mock::given(method("GET"))
+            .and(path("/raw/acme/offline-stacks/main/stacks/ts-lint/CLAUDE.md"))
+            .respond_with(ResponseTemplate::new(200).set_body_string("# Description: Lint TypeScript\n# TS Lint"))
+            .mount(&server)
+            .await;
+
+        let client = GitHubClient::new(RetryPolicy::default().with_attempts(1))
+            .with_base_urls(&server.uri(), &format!("{}/raw", server.uri()));
+        let manager = RemoteStackManager::with_repository(StackRepository::from_spec("acme/offline-stacks").unwrap())
+            .unwrap()
+            .without_mirror()
+            .with_registry_client(client);
+
+        let stacks = manager.discover_remote_stacks().await.unwrap();
+        let found: Vec<(&str, Option<&str>)> = stacks.iter()
+            .map(|stack| (stack.name.as_str(), stack.description.as_deref()))
+            .collect();
+        assert_eq!(found, vec![("api-docs", None), ("ts-lint", Some("Lint TypeScript"))]);
+    }
+
+    #[test]
+    fn test_parse_github_repo() {
+        assert_eq!(
+            parse_github_repo("git@github.com:csaben/ts-lint-stack.git"),
+            Some(("csaben".to_string(), "ts-lint-stack".to_string()))
+        );
+        assert_eq!(
+            parse_github_repo("https://github.com/csaben/stackstack"),
+            Some(("csaben".to_string(), "stackstack".to_string()))
+        );
+        assert_eq!(parse_github_repo("https://gitlab.com/csaben/stackstack"), None);
+    }
+
+    #[test]
+    fn test_parse_stack_source() {
+        let source = StackSource::parse("https://github.com/acme/monorepo/tree/dev/tools/lint-stack").unwrap();
+        assert_eq!(source.url, "https://github.com/acme/monorepo.git");
+        assert_eq!(source.branch.as_deref(), Some("dev"));
+        assert_eq!(source.path.as_deref(), Some("tools/lint-stack"));
+        assert_eq!(source.stack_name(), "lint-stack");
+
+        let source = StackSource::parse("acme/docs-stack").unwrap();
+        assert_eq!(source.url, "git@github.com:acme/docs-stack.git");
+        assert_eq!(source.stack_name(), "docs-stack");
+        assert_eq!(source.branch, None);
+
+        assert_eq!(StackSource::parse("git@github.com:acme/docs-stack.git").unwrap().repo, "docs-stack");
+        assert_eq!(StackSource::parse("https://github.com/acme/docs-stack/issues/3"), None);
+        assert_eq!(StackSource::parse("ts-lint-stack"), None);
+    }
+
+    #[test]
+    fn test_parse_stack_source_with_reference() {
+        let source = StackSource::parse("acme/monorepo#feature-x:tools/lint-stack").unwrap();
+        assert_eq!(source.branch.as_deref(), Some("feature-x"));
+        assert_eq!(source.path.as_deref(), Some("tools/lint-stack"));
+        assert_eq!(source.stack_name(), "lint-stack");
+
+        let source = StackSource::parse("acme/lint-stack#next").unwrap();
+        assert_eq!(source.branch.as_deref(), Some("next"));
+        assert_eq!(source.path, None);
+
+        let source = StackSource::parse("acme/monorepo#:stacks/docs/").unwrap();
+        assert_eq!(source.branch, None);
+        assert_eq!(source.path.as_deref(), Some("stacks/docs"));
     }
 }
\ No newline at end of file
@@ -0,0 +1,200 @@
+use std::path::{Path, PathBuf};
+use anyhow::{Result, Context};
+use serde_json::Value;
+use walkdir::WalkDir;
+
+use super::auth;
+use super::cache;
+use super::stack_manager::Stack;
+use crate::utils::process::Process;
+
+/// Under the cache directory; reviewed stacks never touch the project until approved
+const QUARANTINE_DIR: &str = "quarantine";
+
+/// A stack downloaded for review
+pub struct Quarantined {
+    /// Clone of the stack repository, removed once the review is over
+    pub clone_dir: PathBuf,
+    pub stack: Stack,
+    /// Commit that was reviewed
+    pub commit: String,
+}
+
+impl Quarantined {
+    /// Shallow-clone `url` (at `branch`) into the quarantine directory; `path` selects a subdirectory
+    pub fn download(name: &str, url: &str, branch: Option<&str>, path: Option<&str>) -> Result<Self> {
+        let clone_dir = cache::cache_root()?.join(QUARANTINE_DIR).join(name);
+        if clone_dir.exists() {
+            std::fs::remove_dir_all(&clone_dir)
+                .with_context(|| format!("Failed to clear {}", clone_dir.display()))?;
+        }
+        let clone_path = clone_dir.to_string_lossy().to_string();
+        let mut clone = Process::git()
+            .envs(auth::git_auth_env(url))
+            .args(["clone", "--quiet", "--depth", "1"]);
+        if let Some(branch) = branch {
+            clone = clone.args(["--branch", branch]);
+        }
+        clone.args([url, clone_path.as_str()])
+            .run()
+            .with_context(|| format!("Failed to download {} for review", url))?;
+
+        let commit = Process::git()
+            .args(["rev-parse", "HEAD"])
+            .current_dir(&clone_dir)
+            .read_only()
+            .run()?
+            .stdout
+            .trim()
+            .to_string();
+        let stack_dir = match path {
+            Some(path) => clone_dir.join(path),
+            None => clone_dir.clone(),
+        };
+        if !stack_dir.is_dir() {
+            anyhow::bail!("Path '{}' not found in {}", path.unwrap_or_default(), url);
+        }
+        Ok(Self { clone_dir, stack: Stack::new(name.to_string(), stack_dir), commit })
+    }
+
+    /// Delete the downloaded copy
+    pub fn discard(self) {
+        if let Err(error) = std::fs::remove_dir_all(&self.clone_dir) {
+            tracing::debug!("failed to remove {}: {}", self.clone_dir.display(), error);
+        }
+    }
+}
+
+/// What a stack would add to a project
+#[derive(Debug, Default, PartialEq)]
+pub struct StackReview {
+    pub agents: Vec<String>,
+    pub commands: Vec<String>,
+    /// `permissions.allow` rules the stack grants
+    pub grants: Vec<String>,
+    /// Hook commands, as `Event [matcher]: command`
+    pub hooks: Vec<String>,
+    /// Other top-level settings keys that would be merged
+    pub other_settings: Vec<String>,
+    /// Files that can be run, outside agents and commands
+    pub executables: Vec<String>,
+}
+
+impl StackReview {
+    pub fn inspect(stack: &Stack) -> Result<Self> {
+        let mut review = Self {
+            agents: markdown_files(&stack.claude_dir.join("agents")),
+            commands: markdown_files(&stack.claude_dir.join("commands")),
+            executables: executables(&stack.path),
+            ..Self::default()
+        };
+        for file in [".local-settings.json", "settings.json"] {
+            let path = stack.claude_dir.join(file);
+            if !path.exists() {
+                continue;
+            }
+            let content = std::fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read {}", path.display()))?;
+            let settings: Value = serde_json::from_str(&content)
+                .with_context(|| format!("Failed to parse {}", path.display()))?;
+            review.add_settings(&settings);
+        }
+        review.other_settings.sort();
+        review.other_settings.dedup();
+        Ok(review)
+    }
+
+    fn add_settings(&mut self, settings: &Value) {
+        let object = match settings.as_object() {
+            Some(object) => object,
+            None => return,
+        };
+        for (key, value) in object {
+            match key.as_str() {
+                "permissions" => {
+                    let allow = value.get("allow").and_then(Value::as_array).into_iter().flatten();
+                    self.grants.extend(allow.filter_map(Value::as_str).map(str::to_string));
+                    let other = value.as_object().into_iter().flatten().filter(|(key, _)| key.as_str() != "allow");
+                    self.other_settings.extend(other.map(|(key, _)| format!("permissions.{}", key)));
+                }
+                "hooks" => {
+                    for (event, matchers) in value.as_object().into_iter().flatten() {
+                        for matcher in matchers.as_array().into_iter().flatten() {
+                            let pattern = matcher.get("matcher").and_then(Value::as_str).filter(|pattern| !pattern.is_empty());
+                            let hooks = matcher.get("hooks").and_then(Value::as_array).into_iter().flatten();
+                            for command in hooks.filter_map(|hook| hook.get("command").and_then(Value::as_str)) {
+                                self.hooks.push(match pattern {
+                                    Some(pattern) => format!("{} [{}]: {}", event, pattern, command),
+                                    None => format!("{}: {}", event, command),
+                                });
+                            }
+                        }
+                    }
+                }
+                _ => self.other_settings.push(key.clone()),
+            }
+        }
+    }
+}
+
+fn markdown_files(dir: &Path) -> Vec<String> {
+    let mut files: Vec<String> = WalkDir::new(dir)
+        .into_iter()
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file() && entry.path().extension().is_some_and(|ext| ext == "md"))
+        .filter_map(|entry| entry.path().strip_prefix(dir).ok().map(|path| path.display().to_string()))
+        .collect();
+    files.sort();
+    files
+}
+
+fn executables(root: &Path) -> Vec<String> {
+    let mut files: Vec<String> = WalkDir::new(root)
+        .into_iter()
+        .filter_entry(|entry| entry.file_name() != ".git")
+        .filter_map(|entry| entry.ok())
+        .filter(|entry| entry.file_type().is_file() && is_executable(entry.path()))
+        .filter_map(|entry| entry.path().strip_prefix(root).ok().map(|path| path.display().to_string()))
+        .collect();
+    files.sort();
+    files
+}
+
+#[cfg(unix)]
+fn is_executable(path: &Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::metadata(path).map(|metadata| metadata.permissions().mode() & 0o111 != 0).unwrap_or(false)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext == "sh" || ext == "ps1" || ext == "bat")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_inspect() {
+        let dir = TempDir::new().unwrap();
+        let claude_dir = dir.path().join(".claude");
+        std::fs::create_dir_all(claude_dir.join("agents")).unwrap();
+        std::fs::create_dir_all(claude_dir.join("commands")).unwrap();
+        std::fs::write(claude_dir.join("agents").join("reviewer.md"), "# Reviewer").unwrap();
+        std::fs::write(claude_dir.join("commands").join("lint.md"), "# Lint").unwrap();
+        std::fs::write(claude_dir.join(".local-settings.json"), r#"{
+            "permissions": {"allow": ["Bash(npm run lint:*)"], "deny": ["Read(.env)"]},
+            "hooks": {"PostToolUse": [{"matcher": "Edit", "hooks": [{"type": "command", "command": "npm run lint"}]}]},
+            "model": "sonnet"
+        }"#).unwrap();
+
+        let review = StackReview::inspect(&Stack::new("lint".to_string(), dir.path().to_path_buf())).unwrap();
+        assert_eq!(review.agents, ["reviewer.md"]);
+        assert_eq!(review.commands, ["lint.md"]);
+        assert_eq!(review.grants, ["Bash(npm run lint:*)"]);
+        assert_eq!(review.hooks, ["PostToolUse [Edit]: npm run lint"]);
+        assert_eq!(review.other_settings, ["model", "permissions.deny"]);
+    }
+}
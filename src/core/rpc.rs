@@ -0,0 +1,155 @@
+use std::io::Write;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+
+use super::events::{Event, Reporter};
+use crate::error::{self, StacksError};
+
+/// JSON-RPC 2.0 error codes
+pub const PARSE_ERROR: i64 = -32700;
+pub const INVALID_REQUEST: i64 = -32600;
+pub const METHOD_NOT_FOUND: i64 = -32601;
+pub const INVALID_PARAMS: i64 = -32602;
+/// A stacks operation failed; `data` carries the error kind and hint
+pub const OPERATION_FAILED: i64 = -32000;
+
+/// A request, or a notification when it has no id
+#[derive(Debug, Clone, Deserialize, PartialEq)]
+pub struct Request {
+    pub jsonrpc: String,
+    #[serde(default)]
+    pub id: Option<Value>,
+    pub method: String,
+    #[serde(default)]
+    pub params: Value,
+}
+
+impl Request {
+    /// The params as `T`; missing params read as an empty object
+    pub fn params<T: serde::de::DeserializeOwned>(&self) -> Result<T, RpcError> {
+        let params = match &self.params {
+            Value::Null => json!({}),
+            params => params.clone(),
+        };
+        serde_json::from_value(params)
+            .map_err(|e| RpcError::new(INVALID_PARAMS, format!("Invalid params for {}: {}", self.method, e)))
+    }
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct RpcError {
+    pub code: i64,
+    pub message: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub data: Option<Value>,
+}
+
+impl RpcError {
+    pub fn new(code: i64, message: impl Into<String>) -> Self {
+        Self { code, message: message.into(), data: None }
+    }
+
+    /// A failed operation, with the kind and hint the CLI would show
+    pub fn from_error(error: &anyhow::Error) -> Self {
+        let hint = error.chain()
+            .find_map(|cause| cause.downcast_ref::<StacksError>())
+            .and_then(|detail| detail.hint.clone());
+        Self {
+            code: OPERATION_FAILED,
+            message: format!("{:#}", error),
+            data: Some(json!({ "kind": error::classify(error).as_str(), "hint": hint })),
+        }
+    }
+}
+
+/// Parse one line of input; a line that isn't a request gives the error response to send instead
+pub fn parse_request(line: &str) -> Result<Request, Value> {
+    let value: Value = serde_json::from_str(line)
+        .map_err(|e| error_response(Value::Null, RpcError::new(PARSE_ERROR, e.to_string())))?;
+    let id = value.get("id").cloned().unwrap_or(Value::Null);
+    let request: Request = serde_json::from_value(value)
+        .map_err(|e| error_response(id.clone(), RpcError::new(INVALID_REQUEST, e.to_string())))?;
+    if request.jsonrpc != "2.0" {
+        return Err(error_response(id, RpcError::new(INVALID_REQUEST, "Only JSON-RPC 2.0 is supported")));
+    }
+    Ok(request)
+}
+
+pub fn result_response(id: Value, result: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "result": result })
+}
+
+pub fn error_response(id: Value, error: RpcError) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": error })
+}
+
+pub fn notification(method: &str, params: Value) -> Value {
+    json!({ "jsonrpc": "2.0", "method": method, "params": params })
+}
+
+/// `stacks/event`: the event as data, and the line the CLI would print for it
+pub fn event_notification(event: &Event) -> Value {
+    notification("stacks/event", json!({ "event": event, "message": event.to_string() }))
+}
+
+/// Write one message as a line on stdout
+///
+/// Responses, events and output all go through here, so their lines never interleave.
+pub fn send(message: &Value) {
+    let mut stdout = std::io::stdout().lock();
+    if writeln!(stdout, "{}", message).and_then(|_| stdout.flush()).is_err() {
+        tracing::debug!("failed to write a JSON-RPC message; the client went away");
+    }
+}
+
+/// Sends every event to the client as a `stacks/event` notification
+#[derive(Debug, Default, Clone, Copy)]
+pub struct RpcReporter;
+
+impl Reporter for RpcReporter {
+    fn report(&self, event: &Event) {
+        send(&event_notification(event));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_request() {
+        let request = parse_request(r#"{"jsonrpc":"2.0","id":1,"method":"stacks/status"}"#).unwrap();
+        assert_eq!(request.id, Some(json!(1)));
+        assert_eq!(request.method, "stacks/status");
+
+        let response = parse_request("{not json").unwrap_err();
+        assert_eq!(response["error"]["code"], PARSE_ERROR);
+        assert_eq!(response["id"], Value::Null);
+
+        let response = parse_request(r#"{"jsonrpc":"1.0","id":"a","method":"stacks/list"}"#).unwrap_err();
+        assert_eq!(response["error"]["code"], INVALID_REQUEST);
+        assert_eq!(response["id"], "a");
+
+        #[derive(Debug, Deserialize)]
+        struct Checkout {
+            stacks: Vec<String>,
+        }
+        let request = parse_request(r#"{"jsonrpc":"2.0","id":2,"method":"stacks/checkout"}"#).unwrap();
+        assert_eq!(request.params::<Checkout>().unwrap_err().code, INVALID_PARAMS);
+        let request = parse_request(r#"{"jsonrpc":"2.0","id":2,"method":"stacks/checkout","params":{"stacks":["ts-lint"]}}"#).unwrap();
+        assert_eq!(request.params::<Checkout>().unwrap().stacks, vec!["ts-lint"]);
+    }
+
+    #[test]
+    fn test_event_notification() {
+        let notification = event_notification(&Event::SettingsMerged { stack: "ts-lint".to_string() });
+        assert_eq!(notification["method"], "stacks/event");
+        assert_eq!(notification["params"]["event"], json!({ "type": "settings_merged", "stack": "ts-lint" }));
+        assert_eq!(notification["params"]["message"], "  ⚙️ Merged settings from stack ts-lint");
+
+        let error = anyhow::Error::from(StacksError::validation("Unknown stack nope").with_hint("Run 'stacks checkout'"));
+        let rpc_error = RpcError::from_error(&error);
+        assert_eq!(rpc_error.code, OPERATION_FAILED);
+        assert_eq!(rpc_error.data.unwrap()["hint"], "Run 'stacks checkout'");
+    }
+}
@@ -0,0 +1,199 @@
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use anyhow::{Result, Context, bail};
+
+use super::git::Repo;
+use crate::utils::dependency_check::find_in_path;
+use crate::utils::process::shell_quote;
+
+/// Sandbox used by `--sandbox` when `sandbox-command` is not configured
+pub const DEFAULT_SANDBOX: &str = "bwrap";
+
+/// Built-in command templates, selectable by name in `sandbox-command`
+///
+/// Both leave the system readable but hide the home directory (SSH keys, cloud credentials, shell
+/// history) except Claude's own state and, read-only, the directories claude runs from. Only the
+/// worktree, the repository's git directory and Claude's state are writable.
+const BUILTIN_SANDBOXES: [(&str, &str); 2] = [
+    (
+        "bwrap",
+        "bwrap --ro-bind / / --dev /dev --proc /proc --tmpfs /tmp --tmpfs {home} \
+--ro-bind-try {claude_bin} {claude_bin} --ro-bind-try {claude_dir} {claude_dir} \
+--bind {worktree} {worktree} --bind {git_dir} {git_dir} \
+--bind-try {home}/.claude {home}/.claude --bind-try {home}/.claude.json {home}/.claude.json \
+--chdir {worktree} -- {command}",
+    ),
+    (
+        "firejail",
+        "firejail --quiet --noprofile --read-only=/ \
+--whitelist={home}/.claude --whitelist={home}/.claude.json --whitelist={worktree} --whitelist={git_dir} \
+--whitelist={claude_bin} --whitelist={claude_dir} --read-only={claude_bin} --read-only={claude_dir} \
+--read-write={worktree} --read-write={git_dir} \
+--read-write={home}/.claude --read-write={home}/.claude.json -- {command}",
+    ),
+];
+
+/// Check a `sandbox-command` value: a built-in name or a template containing `{command}`
+pub fn validate_template(template: &str) -> Result<()> {
+    if builtin_template(template).is_none() && !template.contains("{command}") {
+        bail!(
+            "Sandbox command must be one of {} or a template containing {{command}}",
+            BUILTIN_SANDBOXES.iter().map(|(name, _)| *name).collect::<Vec<_>>().join(", ")
+        );
+    }
+    Ok(())
+}
+
+fn builtin_template(name: &str) -> Option<&'static str> {
+    BUILTIN_SANDBOXES.iter()
+        .find(|(builtin, _)| *builtin == name)
+        .map(|(_, template)| *template)
+}
+
+/// Wrap a shell command so it runs inside the configured sandbox, confined to `worktree`
+pub fn wrap_command(configured: Option<&str>, worktree: &Path, command: &str) -> Result<String> {
+    let template = configured.unwrap_or(DEFAULT_SANDBOX);
+    validate_template(template)?;
+    let template = builtin_template(template).unwrap_or(template);
+
+    let program = template.split_whitespace().next().unwrap_or_default();
+    if Command::new(program).arg("--version").output().is_err() {
+        bail!(
+            "Sandbox program '{}' not found. Install it or set another with 'stacks config set sandbox-command <bwrap|firejail|template>'",
+            program
+        );
+    }
+
+    let worktree = worktree.canonicalize()
+        .with_context(|| format!("Failed to resolve worktree path {}", worktree.display()))?;
+    let git_dir = git_common_dir(&worktree)?;
+    let home = dirs::home_dir().context("Could not find home directory")?;
+    let (claude_bin, claude_dir) = claude_dirs(&home);
+
+    Ok(render_template(
+        template,
+        &worktree.to_string_lossy(),
+        &git_dir.to_string_lossy(),
+        &home.to_string_lossy(),
+        [&claude_bin.to_string_lossy(), &claude_dir.to_string_lossy()],
+        command,
+    ))
+}
+
+/// Directory of the claude on PATH and the one it resolves to, for installs under home that the
+/// sandbox would hide: ~/.local/bin from the native installer, or an npm or nvm prefix
+///
+/// Claude's state directory, which the sandbox exposes anyway, stands in for a directory outside
+/// home or a claude that isn't found, so templates need no conditionals.
+fn claude_dirs(home: &Path) -> (PathBuf, PathBuf) {
+    let homes = [Some(home.to_path_buf()), home.canonicalize().ok()];
+    let under_home = |dir: Option<&Path>| {
+        dir.filter(|dir| homes.iter().flatten().any(|home| dir.starts_with(home)))
+            .map(Path::to_path_buf)
+            .unwrap_or_else(|| home.join(".claude"))
+    };
+    let executable = find_in_path("claude");
+    let resolved = executable.as_ref().and_then(|path| path.canonicalize().ok());
+    (
+        under_home(executable.as_deref().and_then(Path::parent)),
+        under_home(resolved.as_deref().and_then(Path::parent)),
+    )
+}
+
+/// Fill `{worktree}`, `{git_dir}`, `{home}`, `{claude_bin}` and `{claude_dir}` (shell-quoted) and
+/// `{command}` (as-is) into a template
+fn render_template(template: &str, worktree: &str, git_dir: &str, home: &str, claude: [&str; 2], command: &str) -> String {
+    // {home} is usually followed by a path suffix, so quote the whole word it appears in
+    template.split(' ')
+        .map(|word| {
+            if word == "{command}" {
+                return command.to_string();
+            }
+            if !word.contains('{') {
+                return word.to_string();
+            }
+            let (flag, value) = match word.split_once('=') {
+                Some((flag, value)) if flag.starts_with('-') => (format!("{}=", flag), value),
+                _ => (String::new(), word),
+            };
+            let value = value
+                .replace("{worktree}", worktree)
+                .replace("{git_dir}", git_dir)
+                .replace("{claude_bin}", claude[0])
+                .replace("{claude_dir}", claude[1])
+                .replace("{home}", home);
+            format!("{}{}", flag, shell_quote(&value))
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// The repository's shared .git directory, which commits from a worktree write to
+fn git_common_dir(worktree: &Path) -> Result<PathBuf> {
+    let repo = Repo::discover(worktree)?;
+    repo.common_dir().canonicalize()
+        .with_context(|| format!("Failed to resolve git directory {}", repo.common_dir().display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_template() {
+        let rendered = render_template(
+            builtin_template("firejail").unwrap(),
+            "/work/my project",
+            "/work/repo/.git",
+            "/home/dev",
+            ["/usr/bin", "/usr/lib/claude"],
+            "claude --permission-mode acceptEdits",
+        );
+
+        assert!(rendered.starts_with("firejail --quiet"));
+        assert!(rendered.contains("--read-write='/work/my project'"));
+        assert!(rendered.contains("--read-write=/home/dev/.claude.json"));
+        assert!(rendered.ends_with("-- claude --permission-mode acceptEdits"));
+    }
+
+    #[test]
+    fn test_builtin_sandboxes_hide_home() {
+        let claude = ["/home/dev/.local/bin", "/home/dev/.local/share/claude/versions"];
+        let render = |name| render_template(builtin_template(name).unwrap(), "/home/dev/project", "/home/dev/project/.git", "/home/dev", claude, "claude");
+
+        // Home is replaced by an empty tmpfs before anything under it is bound back in
+        let bwrap = render("bwrap");
+        let hidden = bwrap.find("--tmpfs /home/dev ").unwrap();
+        assert!(hidden < bwrap.find("--bind /home/dev/project ").unwrap());
+        assert!(hidden < bwrap.find("--bind-try /home/dev/.claude ").unwrap());
+
+        // Claude's own install under home is bound back read-only
+        assert!(hidden < bwrap.find("--ro-bind-try /home/dev/.local/bin /home/dev/.local/bin ").unwrap());
+        assert!(bwrap.contains("--ro-bind-try /home/dev/.local/share/claude/versions /home/dev/.local/share/claude/versions "));
+
+        // Firejail hides everything under home that isn't whitelisted
+        let firejail = render("firejail");
+        assert!(firejail.contains("--whitelist=/home/dev/.claude "));
+        assert!(firejail.contains("--whitelist=/home/dev/project "));
+        assert!(firejail.contains("--whitelist=/home/dev/.local/bin --whitelist=/home/dev/.local/share/claude/versions "));
+        assert!(firejail.contains("--read-only=/home/dev/.local/bin "));
+
+        for rendered in [bwrap, firejail] {
+            assert!(!rendered.contains(".ssh"));
+            assert!(!rendered.contains("--bind /home/dev /home/dev"));
+        }
+    }
+
+    #[test]
+    fn test_claude_dirs_outside_home_fall_back_to_claude_state() {
+        let home = Path::new("/nonexistent-home");
+        assert_eq!(claude_dirs(home), (home.join(".claude"), home.join(".claude")));
+    }
+
+    #[test]
+    fn test_validate_template() {
+        assert!(validate_template("bwrap").is_ok());
+        assert!(validate_template("nsjail --cwd {worktree} -- {command}").is_ok());
+        assert!(validate_template("nsjail --cwd {worktree}").is_err());
+    }
+}
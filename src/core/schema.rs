@@ -0,0 +1,274 @@
+use std::path::PathBuf;
+use anyhow::Result;
+use schemars::schema::RootSchema;
+use schemars::{schema_for, JsonSchema};
+use serde::{Deserialize, Serialize};
+
+use crate::core::upstream::UpstreamStatus;
+use crate::utils::build_info;
+use crate::utils::dependency_check::DependencyReport;
+
+/// Version of every `--json` output; bumped when a field is removed, renamed or changes meaning
+///
+/// Fields may be added without a bump, so consumers should ignore the ones they don't know.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// The machine-readable outputs, by the name `stacks schema` knows them under
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Output {
+    Status,
+    Outdated,
+    Verify,
+    Doctor,
+    Version,
+}
+
+impl Output {
+    pub fn all() -> [Output; 5] {
+        [Output::Status, Output::Outdated, Output::Verify, Output::Doctor, Output::Version]
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Output::Status => "status",
+            Output::Outdated => "outdated",
+            Output::Verify => "ci-verify",
+            Output::Doctor => "doctor",
+            Output::Version => "version",
+        }
+    }
+
+    /// The command whose `--json` flag produces this output
+    pub fn command(&self) -> &'static str {
+        match self {
+            Output::Status => "stacks status --json",
+            Output::Outdated => "stacks outdated --json",
+            Output::Verify => "stacks ci verify --json",
+            Output::Doctor => "stacks doctor --json",
+            Output::Version => "stacks --version --json",
+        }
+    }
+
+    pub fn schema(&self) -> RootSchema {
+        let mut schema = match self {
+            Output::Status => schema_for!(StatusOutput),
+            Output::Outdated => schema_for!(OutdatedOutput),
+            Output::Verify => schema_for!(VerifyOutput),
+            Output::Doctor => schema_for!(DoctorOutput),
+            Output::Version => schema_for!(VersionOutput),
+        };
+        let metadata = schema.schema.metadata();
+        metadata.title = Some(format!("stacks {} output, schema version {}", self.as_str(), SCHEMA_VERSION));
+        metadata.description = Some(format!("Output of '{}'", self.command()));
+        schema
+    }
+}
+
+impl std::str::FromStr for Output {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        Self::all()
+            .into_iter()
+            .find(|output| output.as_str() == s)
+            .ok_or_else(|| anyhow::anyhow!("Unknown output: {}", s))
+    }
+}
+
+/// Print a machine-readable output on stdout
+pub fn print<T: Serialize>(output: &T) -> Result<()> {
+    println!("{}", serde_json::to_string_pretty(output)?);
+    Ok(())
+}
+
+/// Whether a worktree or a stack has uncommitted changes
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeState {
+    Clean,
+    /// Uncommitted changes; `dirty` or `modified` in the porcelain output
+    Changed,
+    /// git status couldn't be read
+    Error,
+}
+
+/// `stacks status --json`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct StatusOutput {
+    pub schema_version: u32,
+    pub worktrees: Vec<WorktreeStatus>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct WorktreeStatus {
+    pub path: PathBuf,
+    /// None outside a repository with worktrees, or for a detached HEAD
+    pub branch: Option<String>,
+    /// Commit checked out; None outside a repository with worktrees
+    pub head: Option<String>,
+    pub state: ChangeState,
+    /// Number of changed files; None when the state is `error`
+    pub changes: Option<usize>,
+    pub stacks: Vec<StackStatus>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct StackStatus {
+    pub name: String,
+    pub state: ChangeState,
+    /// Number of changed files; None when the state is `error`
+    pub changes: Option<usize>,
+    /// Commits to the stack not yet pushed upstream
+    pub local_commits: usize,
+    /// The stack's symlinks resolve and its CLAUDE.md import is present
+    pub healthy: bool,
+}
+
+/// `stacks outdated --json`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct OutdatedOutput {
+    pub schema_version: u32,
+    pub stacks: Vec<StackUpdate>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct StackUpdate {
+    pub name: String,
+    pub installed_commit: Option<String>,
+    pub latest_commit: Option<String>,
+    pub commits_behind: Option<usize>,
+    /// Subject of the latest upstream commit
+    pub latest_message: Option<String>,
+    pub outdated: bool,
+    /// What the table of `stacks outdated` shows, e.g. "3 commits behind upstream"
+    pub description: String,
+}
+
+impl StackUpdate {
+    pub fn new(name: &str, status: &UpstreamStatus) -> Self {
+        Self {
+            name: name.to_string(),
+            installed_commit: status.installed_commit.clone(),
+            latest_commit: status.remote_commit.clone(),
+            commits_behind: status.commits_behind,
+            latest_message: status.latest_message.clone(),
+            outdated: status.is_outdated(),
+            description: status.describe(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize, JsonSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    Error,
+    Warning,
+}
+
+/// Something wrong with the committed stack setup
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct Problem {
+    pub severity: Severity,
+    /// Repository-relative file the problem is in
+    pub file: Option<String>,
+    pub message: String,
+}
+
+impl Problem {
+    pub fn error(file: Option<&str>, message: impl Into<String>) -> Self {
+        Self { severity: Severity::Error, file: file.map(str::to_string), message: message.into() }
+    }
+
+    pub fn warning(file: Option<&str>, message: impl Into<String>) -> Self {
+        Self { severity: Severity::Warning, file: file.map(str::to_string), message: message.into() }
+    }
+}
+
+/// `stacks ci verify --json`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct VerifyOutput {
+    pub schema_version: u32,
+    /// Stacks checked out under stacks/
+    pub stacks: Vec<String>,
+    pub problems: Vec<Problem>,
+    /// No problem is an error; warnings don't fail verification
+    pub passed: bool,
+}
+
+/// `stacks doctor --json`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct DoctorOutput {
+    pub schema_version: u32,
+    pub dependencies: Vec<DependencyReport>,
+}
+
+/// `stacks --version --json`: the build, for install tooling and bug reports
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, JsonSchema)]
+pub struct VersionOutput {
+    pub schema_version: u32,
+    pub name: String,
+    pub version: String,
+    /// Short commit hash, or "unknown" when built outside a git checkout
+    pub git_sha: String,
+    /// UTC date as YYYY-MM-DD
+    pub build_date: String,
+    /// Target triple, e.g. aarch64-apple-darwin
+    pub target: String,
+}
+
+impl VersionOutput {
+    pub fn current() -> Self {
+        Self {
+            schema_version: SCHEMA_VERSION,
+            name: "stacks".to_string(),
+            version: build_info::VERSION.to_string(),
+            git_sha: build_info::GIT_SHA.to_string(),
+            build_date: build_info::BUILD_DATE.to_string(),
+            target: build_info::TARGET.to_string(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_every_output_is_versioned() {
+        for output in Output::all() {
+            assert_eq!(output.as_str().parse::<Output>().unwrap(), output);
+
+            let schema = serde_json::to_value(output.schema()).unwrap();
+            let required = schema["required"].as_array().unwrap();
+            assert!(required.contains(&serde_json::json!("schema_version")), "{} has no schema_version", output.as_str());
+            assert!(schema["description"].as_str().unwrap().contains(output.command()));
+        }
+        assert!("list".parse::<Output>().is_err());
+    }
+
+    #[test]
+    fn test_status_output_round_trips() {
+        let output = StatusOutput {
+            schema_version: SCHEMA_VERSION,
+            worktrees: vec![WorktreeStatus {
+                path: PathBuf::from("/work/app"),
+                branch: Some("main".to_string()),
+                head: Some("4e1f0c2a9b".to_string()),
+                state: ChangeState::Changed,
+                changes: Some(2),
+                stacks: vec![StackStatus {
+                    name: "ts-lint".to_string(),
+                    state: ChangeState::Clean,
+                    changes: Some(0),
+                    local_commits: 1,
+                    healthy: true,
+                }],
+            }],
+        };
+
+        let json = serde_json::to_value(&output).unwrap();
+        assert_eq!(json["worktrees"][0]["state"], "changed");
+        assert_eq!(json["worktrees"][0]["stacks"][0]["local_commits"], 1);
+        assert_eq!(serde_json::from_value::<StatusOutput>(json).unwrap(), output);
+    }
+}
@@ -0,0 +1,212 @@
+use std::path::{Path, PathBuf};
+use anyhow::Result;
+use regex::{Regex, RegexBuilder};
+use walkdir::WalkDir;
+
+use super::remote_stack_manager::GitHubCodeSearch;
+use crate::error::StacksError;
+
+/// What a stack file is to claude
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SourceKind {
+    /// The stack's CLAUDE.md, imported into the project's
+    Instructions,
+    Agent,
+    Command,
+}
+
+impl SourceKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SourceKind::Instructions => "instructions",
+            SourceKind::Agent => "agent",
+            SourceKind::Command => "command",
+        }
+    }
+
+    /// Kind of the file at `relative`, a path inside a stack; None for files claude doesn't read
+    pub fn of_path(relative: &str) -> Option<Self> {
+        let relative = relative.replace('\\', "/");
+        if relative == "CLAUDE.md" {
+            return Some(SourceKind::Instructions);
+        }
+        if !relative.ends_with(".md") {
+            return None;
+        }
+        match relative.split_once('/') {
+            Some((".claude", rest)) if rest.starts_with("agents/") => Some(SourceKind::Agent),
+            Some((".claude", rest)) if rest.starts_with("commands/") => Some(SourceKind::Command),
+            _ => None,
+        }
+    }
+}
+
+/// A line of a stack file that matches the pattern
+#[derive(Debug, Clone, PartialEq)]
+pub struct Match {
+    pub stack: String,
+    pub kind: SourceKind,
+    /// Path inside the stack, e.g. `.claude/agents/reviewer.md`
+    pub file: String,
+    /// 1-based; None for registry matches, which come as fragments without line numbers
+    pub line: Option<usize>,
+    pub text: String,
+}
+
+/// The pattern to search for: a regular expression, or literal text with `fixed`
+pub fn pattern(text: &str, fixed: bool, ignore_case: bool) -> Result<Regex> {
+    let source = match fixed {
+        true => regex::escape(text),
+        false => text.to_string(),
+    };
+    RegexBuilder::new(&source)
+        .case_insensitive(ignore_case)
+        .build()
+        .map_err(|e| StacksError::validation(format!("Invalid pattern {}: {}", text, e))
+            .with_hint("Pass -F to search for the text as written")
+            .into())
+}
+
+/// Files claude reads from the stack at `stack_dir`: its CLAUDE.md, agents and commands
+fn stack_files(stack_dir: &Path) -> Vec<(String, SourceKind, PathBuf)> {
+    let mut files = Vec::new();
+    if stack_dir.join("CLAUDE.md").is_file() {
+        files.push(("CLAUDE.md".to_string(), SourceKind::Instructions, stack_dir.join("CLAUDE.md")));
+    }
+    let walker = WalkDir::new(stack_dir.join(".claude"))
+        .min_depth(1)
+        .sort_by_file_name()
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file());
+    for entry in walker {
+        let relative = match entry.path().strip_prefix(stack_dir) {
+            Ok(relative) => relative.to_string_lossy().replace('\\', "/"),
+            Err(_) => continue,
+        };
+        if let Some(kind) = SourceKind::of_path(&relative) {
+            files.push((relative, kind, entry.path().to_path_buf()));
+        }
+    }
+    files
+}
+
+/// Matching lines in the stacks installed under `project_root/stacks`, by stack name then file
+pub fn search_installed(project_root: &Path, pattern: &Regex) -> Vec<Match> {
+    let mut stacks: Vec<String> = std::fs::read_dir(project_root.join("stacks"))
+        .map(|entries| entries
+            .filter_map(|entry| entry.ok())
+            .filter(|entry| entry.path().is_dir())
+            .map(|entry| entry.file_name().to_string_lossy().to_string())
+            .collect())
+        .unwrap_or_default();
+    stacks.sort();
+
+    let mut matches = Vec::new();
+    for stack in stacks {
+        for (file, kind, path) in stack_files(&project_root.join("stacks").join(&stack)) {
+            let content = match std::fs::read_to_string(&path) {
+                Ok(content) => content,
+                Err(_) => continue,
+            };
+            for (index, line) in content.lines().enumerate() {
+                if pattern.is_match(line) {
+                    matches.push(Match { stack: stack.clone(), kind, file: file.clone(), line: Some(index + 1), text: line.trim().to_string() });
+                }
+            }
+        }
+    }
+    matches
+}
+
+/// Matching lines of a registry code search, leaving out stacks in `skip` and files claude doesn't read
+///
+/// The search matched words; `pattern` picks the lines of each fragment that really match.
+pub fn registry_matches(search: &GitHubCodeSearch, pattern: &Regex, skip: &[String]) -> Vec<Match> {
+    let mut matches = Vec::new();
+    for hit in &search.items {
+        let (stack, file) = match hit.path.strip_prefix("stacks/").and_then(|rest| rest.split_once('/')) {
+            Some((stack, file)) => (stack.to_string(), file.to_string()),
+            None => continue,
+        };
+        let kind = match SourceKind::of_path(&file) {
+            Some(kind) if !skip.contains(&stack) => kind,
+            _ => continue,
+        };
+        for fragment in &hit.text_matches {
+            for line in fragment.fragment.lines().filter(|line| pattern.is_match(line)) {
+                let text = line.trim().to_string();
+                if !matches.iter().any(|found: &Match| found.stack == stack && found.file == file && found.text == text) {
+                    matches.push(Match { stack: stack.clone(), kind, file: file.clone(), line: None, text });
+                }
+            }
+        }
+    }
+    matches.sort_by(|a, b| a.stack.cmp(&b.stack).then(a.file.cmp(&b.file)));
+    matches
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::core::remote_stack_manager::{GitHubCodeHit, GitHubTextMatch};
+    use tempfile::TempDir;
+
+    fn write(path: &Path, content: &str) {
+        std::fs::create_dir_all(path.parent().unwrap()).unwrap();
+        std::fs::write(path, content).unwrap();
+    }
+
+    #[test]
+    fn test_source_kind_of_path() {
+        assert_eq!(SourceKind::of_path("CLAUDE.md"), Some(SourceKind::Instructions));
+        assert_eq!(SourceKind::of_path(".claude/agents/review/strict.md"), Some(SourceKind::Agent));
+        assert_eq!(SourceKind::of_path(".claude/commands/fix-lint.md"), Some(SourceKind::Command));
+        assert_eq!(SourceKind::of_path(".claude/.local-settings.json"), None);
+        assert_eq!(SourceKind::of_path("docs/CLAUDE.md"), None);
+    }
+
+    #[test]
+    fn test_search_installed() {
+        let dir = TempDir::new().unwrap();
+        let stacks = dir.path().join("stacks");
+        write(&stacks.join("ts-lint/CLAUDE.md"), "# Description: Lint TypeScript\nNever use `any`.\n");
+        write(&stacks.join("ts-lint/.claude/agents/reviewer.md"), "---\nname: reviewer\n---\nReject code that uses ANY type.\n");
+        write(&stacks.join("ts-lint/scripts/lint.sh"), "echo any\n");
+        write(&stacks.join("api-docs/.claude/commands/docs.md"), "Document any public function.\n");
+
+        let found = search_installed(dir.path(), &pattern("any", false, false).unwrap());
+        let found: Vec<(&str, &str, Option<usize>)> = found.iter().map(|m| (m.stack.as_str(), m.file.as_str(), m.line)).collect();
+        assert_eq!(found, vec![
+            ("api-docs", ".claude/commands/docs.md", Some(1)),
+            ("ts-lint", "CLAUDE.md", Some(2)),
+        ]);
+
+        let found = search_installed(dir.path(), &pattern("any type", true, true).unwrap());
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].kind, SourceKind::Agent);
+        assert_eq!(found[0].text, "Reject code that uses ANY type.");
+
+        assert!(pattern("(unclosed", false, false).is_err());
+        assert!(pattern("(unclosed", true, false).is_ok());
+    }
+
+    #[test]
+    fn test_registry_matches() {
+        let hit = |path: &str, fragment: &str| GitHubCodeHit {
+            path: path.to_string(),
+            text_matches: vec![GitHubTextMatch { fragment: fragment.to_string() }],
+        };
+        let search = GitHubCodeSearch {
+            items: vec![
+                hit("stacks/py-test/.claude/agents/tester.md", "Run pytest with -x\nthen report"),
+                hit("stacks/py-test/README.md", "pytest docs"),
+                hit("stacks/ts-lint/CLAUDE.md", "pytest is not used here"),
+            ],
+        };
+        let found = registry_matches(&search, &pattern("pytest", false, false).unwrap(), &["ts-lint".to_string()]);
+        assert_eq!(found.len(), 1);
+        assert_eq!((found[0].stack.as_str(), found[0].kind, found[0].line), ("py-test", SourceKind::Agent, None));
+        assert_eq!(found[0].text, "Run pytest with -x");
+    }
+}
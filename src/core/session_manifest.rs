@@ -0,0 +1,139 @@
+use std::path::{Path, PathBuf};
+use anyhow::{Result, Context};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::core::git::Repo;
+use crate::utils::files;
+
+/// Written into each worktree the checkout wizard creates
+pub const MANIFEST_FILE: &str = ".stacks/session.json";
+
+/// What the checkout wizard set up in a worktree, for resuming, cleaning up and reporting on it
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SessionManifest {
+    pub task: String,
+    pub branch: String,
+    pub stacks: Vec<String>,
+    /// Kickoff prompt after interpolation; none when claude was started without one
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub prompt: Option<String>,
+    /// tmux pane id claude was started in
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub tmux_target: Option<String>,
+    pub created_at: DateTime<Utc>,
+    /// When claude was sent its command
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub started_at: Option<DateTime<Utc>>,
+}
+
+impl SessionManifest {
+    pub fn new(task: &str, branch: &str, stacks: Vec<String>) -> Self {
+        Self {
+            task: task.to_string(),
+            branch: branch.to_string(),
+            stacks,
+            prompt: None,
+            tmux_target: None,
+            created_at: Utc::now(),
+            started_at: None,
+        }
+    }
+
+    pub fn path(worktree: &Path) -> PathBuf {
+        worktree.join(MANIFEST_FILE)
+    }
+
+    /// The worktree's manifest, if the checkout wizard created it
+    pub fn load(worktree: &Path) -> Result<Option<Self>> {
+        let path = Self::path(worktree);
+        if !path.exists() {
+            return Ok(None);
+        }
+        let content = std::fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        serde_json::from_str(&content)
+            .map(Some)
+            .with_context(|| format!("Failed to parse {}", path.display()))
+    }
+
+    pub fn save(&self, worktree: &Path) -> Result<()> {
+        let path = Self::path(worktree);
+        if let Some(parent) = path.parent() {
+            files::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        if let Ok(repo) = Repo::discover(worktree) {
+            // The manifest describes this checkout only; keep it out of the task's commits
+            if let Err(error) = repo.exclude(&format!("/{}", MANIFEST_FILE)) {
+                tracing::debug!("failed to exclude the session manifest from git: {}", error);
+            }
+        }
+        files::write(&path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Summary block printed once the worktree is ready
+    pub fn summary(&self, worktree: &Path) -> String {
+        let stacks = match self.stacks.is_empty() {
+            true => "none (vanilla Claude)".to_string(),
+            false => self.stacks.join(", "),
+        };
+        let mut lines = vec![
+            format!("📋 Session {}", self.task),
+            format!("  Worktree: {}", worktree.display()),
+            format!("  Branch:   {}", self.branch),
+            format!("  Stacks:   {}", stacks),
+        ];
+        if let Some(target) = &self.tmux_target {
+            lines.push(format!("  tmux:     {}", target));
+        }
+        if let Some(prompt) = &self.prompt {
+            lines.push(format!("  Prompt:   {}", first_line(prompt, 60)));
+        }
+        lines.push(format!("  Manifest: {}", MANIFEST_FILE));
+        lines.join("\n")
+    }
+}
+
+/// First line of `text`, shortened to `max` characters
+fn first_line(text: &str, max: usize) -> String {
+    let line = text.lines().next().unwrap_or_default().trim();
+    let multiline = text.trim().lines().nth(1).is_some();
+    match line.chars().count() > max {
+        true => format!("{}…", line.chars().take(max).collect::<String>()),
+        false if multiline => format!("{}…", line),
+        false => line.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_save_and_load() {
+        let dir = TempDir::new().unwrap();
+        assert_eq!(SessionManifest::load(dir.path()).unwrap(), None);
+
+        let mut manifest = SessionManifest::new("fix-login", "feature-fix-login", vec!["ts-lint-stack".to_string()]);
+        manifest.prompt = Some("Fix the login bug".to_string());
+        manifest.tmux_target = Some("%12".to_string());
+        manifest.save(dir.path()).unwrap();
+
+        assert!(dir.path().join(".stacks").join("session.json").exists());
+        assert_eq!(SessionManifest::load(dir.path()).unwrap(), Some(manifest));
+    }
+
+    #[test]
+    fn test_summary() {
+        let mut manifest = SessionManifest::new("fix-login", "feature-fix-login", Vec::new());
+        manifest.prompt = Some("Step 1: read the code\nStep 2: fix it".to_string());
+
+        let summary = manifest.summary(Path::new("../app-fix-login"));
+        assert!(summary.contains("  Stacks:   none (vanilla Claude)"));
+        assert!(summary.contains("  Prompt:   Step 1: read the code…"));
+        assert!(!summary.contains("tmux:"));
+    }
+}
@@ -0,0 +1,289 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use anyhow::{Result, Context};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::session_manifest::SessionManifest;
+use crate::utils::dependency_check::{tmux_supports, TMUX_PANE_OPTIONS};
+use crate::utils::process::Process;
+use crate::utils::files;
+
+const REGISTRY_FILE: &str = "sessions.json";
+
+/// A tmux session, window or pane created for a task
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct SessionRecord {
+    pub task: String,
+    /// tmux target: `session`, `session:window` or a pane id like `%12`
+    pub target: String,
+    pub worktree: PathBuf,
+    pub created_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_attached: Option<DateTime<Utc>>,
+}
+
+/// Every tmux target stacks has created, across projects
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct SessionRegistry {
+    #[serde(default)]
+    pub sessions: Vec<SessionRecord>,
+}
+
+impl SessionRegistry {
+    pub fn path() -> Result<PathBuf> {
+        Ok(dirs::data_local_dir()
+            .context("Failed to get local data directory")?
+            .join("claude-stacks")
+            .join(REGISTRY_FILE))
+    }
+
+    pub fn load() -> Result<Self> {
+        Self::load_from(&Self::path()?)
+    }
+
+    fn load_from(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse {}", path.display()))
+    }
+
+    pub fn save(&self) -> Result<()> {
+        self.save_to(&Self::path()?)
+    }
+
+    fn save_to(&self, path: &Path) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            files::create_dir_all(parent)
+                .with_context(|| format!("Failed to create {}", parent.display()))?;
+        }
+        files::write(path, serde_json::to_string_pretty(self)?)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Add a target, replacing an older record of the same target
+    pub fn record(&mut self, task: &str, target: &str, worktree: &Path) {
+        self.sessions.retain(|session| session.target != target);
+        self.sessions.push(SessionRecord {
+            task: task.to_string(),
+            target: target.to_string(),
+            worktree: worktree.to_path_buf(),
+            created_at: Utc::now(),
+            last_attached: None,
+        });
+    }
+
+    /// Drop records whose tmux target no longer exists; returns how many were dropped
+    pub fn retain_live(&mut self, live_targets: &HashSet<String>) -> usize {
+        let before = self.sessions.len();
+        self.sessions.retain(|session| live_targets.contains(&session.target));
+        before - self.sessions.len()
+    }
+
+    pub fn mark_attached(&mut self, target: &str) {
+        if let Some(session) = self.sessions.iter_mut().find(|session| session.target == target) {
+            session.last_attached = Some(Utc::now());
+        }
+    }
+
+    /// Most recently used first
+    pub fn sorted(&self) -> Vec<&SessionRecord> {
+        let mut sessions: Vec<&SessionRecord> = self.sessions.iter().collect();
+        sessions.sort_by_key(|session| std::cmp::Reverse(session.last_attached.unwrap_or(session.created_at)));
+        sessions
+    }
+}
+
+/// Remember a tmux target created for a task; failures only cost the `stacks attach` entry
+pub fn record_session(task: &str, target: &str, worktree: &Path) {
+    let result = SessionRegistry::load().and_then(|mut registry| {
+        registry.record(task, target, worktree);
+        registry.save()
+    });
+    if let Err(error) = result {
+        tracing::debug!("failed to record tmux session {}: {:#}", target, error);
+    }
+}
+
+/// A tmux pane with the targets it belongs to and its window's last activity
+#[derive(Debug, Clone, PartialEq)]
+pub struct PaneActivity {
+    pub session: String,
+    /// `session:index`
+    pub window: String,
+    pub pane: String,
+    /// Unix time of the window's last output or input
+    pub activity: i64,
+}
+
+/// Every tmux pane with its window's last activity
+pub fn pane_activity() -> Vec<PaneActivity> {
+    let output = Process::tmux()
+        .args(["list-panes", "-a", "-F", "#{session_name}\t#{session_name}:#{window_index}\t#{pane_id}\t#{window_activity}"])
+        .read_only()
+        .output();
+    match output {
+        Ok(output) if output.success() => output.stdout
+            .lines()
+            .filter_map(|line| {
+                let fields: Vec<&str> = line.split('\t').collect();
+                match fields.as_slice() {
+                    [session, window, pane, activity] => Some(PaneActivity {
+                        session: session.to_string(),
+                        window: window.to_string(),
+                        pane: pane.to_string(),
+                        activity: activity.parse().ok()?,
+                    }),
+                    _ => None,
+                }
+            })
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+/// Latest activity of any pane in a session, window or pane target
+pub fn last_activity(target: &str, panes: &[PaneActivity]) -> Option<DateTime<Utc>> {
+    let latest = panes.iter()
+        .filter(|pane| pane.session == target || pane.window == target || pane.pane == target)
+        .map(|pane| pane.activity)
+        .max()?;
+    DateTime::from_timestamp(latest, 0)
+}
+
+/// `{task}:{stacks}` label for a task's tmux windows and panes, e.g. `auth:ts-lint+rust-dev`
+///
+/// Stacks come from the session manifest, else from the worktree's stacks/ directory.
+pub fn task_label(task: &str, worktree: &Path) -> String {
+    let mut stacks = match SessionManifest::load(worktree) {
+        Ok(Some(manifest)) => manifest.stacks,
+        _ => std::fs::read_dir(worktree.join("stacks"))
+            .map(|entries| entries
+                .filter_map(|entry| entry.ok())
+                .filter(|entry| entry.path().is_dir())
+                .map(|entry| entry.file_name().to_string_lossy().to_string())
+                .collect())
+            .unwrap_or_default(),
+    };
+    stacks.sort();
+    match stacks.is_empty() {
+        true => task.to_string(),
+        false => format!("{}:{}", task, stacks.join("+")),
+    }
+}
+
+/// Name the windows of a tmux target after `label` and title its panes with it
+///
+/// Pane borders show the label, so identical claude panes of different tasks can be told apart.
+/// A lone pane id (split into someone else's window) only gets its own title.
+/// Failures only cost the label.
+pub fn label_target(target: &str, label: &str) {
+    let result: Result<()> = (|| {
+        let panes: Vec<(Option<String>, String)> = match target.starts_with('%') {
+            true => vec![(None, target.to_string())],
+            false => {
+                // A bare session name covers all of its windows
+                let mut list = Process::tmux().arg("list-panes");
+                if !target.contains(':') {
+                    list = list.arg("-s");
+                }
+                list.args(["-t", target, "-F", "#{session_name}:#{window_index}\t#{pane_id}"])
+                    .read_only()
+                    .run()?
+                    .stdout
+                    .lines()
+                    .filter_map(|line| line.split_once('\t'))
+                    .map(|(window, pane)| (Some(window.to_string()), pane.to_string()))
+                    .collect()
+            }
+        };
+
+        let mut windows: Vec<&str> = panes.iter().filter_map(|(window, _)| window.as_deref()).collect();
+        windows.dedup();
+        for window in windows {
+            Process::tmux().args(["rename-window", "-t", window, label]).run()?;
+            Process::tmux().args(["set-option", "-w", "-t", window, "pane-border-status", "top"]).run()?;
+            Process::tmux()
+                .args(["set-option", "-w", "-t", window, "pane-border-format", PANE_BORDER_FORMAT])
+                .run()?;
+        }
+        for (_, pane) in &panes {
+            Process::tmux().args(["select-pane", "-t", pane, "-T", label]).run()?;
+            // claude retitles its pane; the border reads this option instead where tmux has pane options
+            if tmux_supports(TMUX_PANE_OPTIONS) {
+                Process::tmux().args(["set-option", "-p", "-t", pane, "@stacks_label", label]).run()?;
+            }
+        }
+        Ok(())
+    })();
+    if let Err(error) = result {
+        tracing::debug!("failed to label tmux target {}: {:#}", target, error);
+    }
+}
+
+/// Border of panes stacks labelled: the label, or the pane title elsewhere
+const PANE_BORDER_FORMAT: &str = " #{pane_index}: #{?@stacks_label,#{@stacks_label},#{pane_title}} ";
+
+/// Every session, window (`session:index`) and pane id tmux currently has
+pub fn live_targets() -> HashSet<String> {
+    let output = Process::tmux()
+        .args(["list-panes", "-a", "-F", "#{session_name}\t#{session_name}:#{window_index}\t#{pane_id}"])
+        .read_only()
+        .output();
+    match output {
+        Ok(output) if output.success() => output.stdout
+            .lines()
+            .flat_map(|line| line.split('\t').map(str::to_string).collect::<Vec<_>>())
+            .collect(),
+        _ => HashSet::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_record_replaces_and_prunes() {
+        let dir = TempDir::new().unwrap();
+        let path = dir.path().join(REGISTRY_FILE);
+
+        let mut registry = SessionRegistry::load_from(&path).unwrap();
+        registry.record("auth", "auth-session", Path::new("/tmp/auth"));
+        registry.record("docs", "main:3", Path::new("/tmp/docs"));
+        registry.record("auth-retry", "auth-session", Path::new("/tmp/auth"));
+        assert_eq!(registry.sessions.len(), 2);
+        registry.save_to(&path).unwrap();
+
+        let mut registry = SessionRegistry::load_from(&path).unwrap();
+        assert_eq!(registry.sessions[1].task, "auth-retry");
+
+        registry.mark_attached("main:3");
+        assert_eq!(registry.sorted()[0].target, "main:3");
+
+        let live: HashSet<String> = ["main".to_string(), "main:3".to_string()].into_iter().collect();
+        assert_eq!(registry.retain_live(&live), 1);
+        assert_eq!(registry.sessions.len(), 1);
+    }
+
+    #[test]
+    fn test_last_activity() {
+        let pane = |window: &str, pane: &str, activity: i64| PaneActivity {
+            session: "auth".to_string(),
+            window: window.to_string(),
+            pane: pane.to_string(),
+            activity,
+        };
+        let panes = vec![pane("auth:0", "%1", 100), pane("auth:0", "%2", 100), pane("auth:1", "%3", 300)];
+
+        assert_eq!(last_activity("auth", &panes).unwrap().timestamp(), 300);
+        assert_eq!(last_activity("auth:0", &panes).unwrap().timestamp(), 100);
+        assert_eq!(last_activity("%3", &panes).unwrap().timestamp(), 300);
+        assert!(last_activity("docs", &panes).is_none());
+    }
+}
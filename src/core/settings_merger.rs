@@ -1,23 +1,182 @@
-use std::path::PathBuf;
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
 use std::fs;
+use std::sync::Arc;
 use anyhow::{Result, Context};
+use serde::{Deserialize, Serialize};
 use serde_json::{Value, Map};
 
+use super::events::{default_reporter, Event, Reporter};
 use super::stack_manager::Stack;
+use crate::utils::files;
+
+/// Ledger of the permission rules each stack added, next to the local settings file
+pub const GRANTS_FILE: &str = ".stacks-grants.json";
+
+/// Command of the Stop hook `auto-push` adds; the hook is recognized by it when removed again
+pub const AUTO_PUSH_COMMAND: &str = "cd \"$CLAUDE_PROJECT_DIR\" && stacks push --quiet";
+
+/// Permission rules one stack added to the local settings
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StackGrants {
+    #[serde(default)]
+    pub allow: Vec<String>,
+    #[serde(default)]
+    pub deny: Vec<String>,
+}
 
 pub struct SettingsMerger {
     local_settings_path: PathBuf,
+    scope_grants: bool,
+    auto_push: bool,
+    reporter: Arc<dyn Reporter>,
 }
 
 impl SettingsMerger {
     pub fn new() -> Self {
         Self {
             local_settings_path: PathBuf::from(".claude/.local-settings.json"),
+            scope_grants: false,
+            auto_push: false,
+            reporter: default_reporter(),
+        }
+    }
+
+    /// Scope stack Bash grants to the stack's own scripts and record which stack added each rule
+    pub fn with_scoped_grants(mut self, scope_grants: bool) -> Self {
+        self.scope_grants = scope_grants;
+        self
+    }
+
+    /// Keep the auto-push Stop hook in the local settings in line with the `auto-push` config key
+    pub fn with_auto_push(mut self, auto_push: bool) -> Self {
+        self.auto_push = auto_push;
+        self
+    }
+
+    pub fn with_reporter(mut self, reporter: Arc<dyn Reporter>) -> Self {
+        self.reporter = reporter;
+        self
+    }
+
+    fn grants_path(&self) -> PathBuf {
+        self.local_settings_path.with_file_name(GRANTS_FILE)
+    }
+
+    fn load_grants(&self) -> Result<BTreeMap<String, StackGrants>> {
+        let path = self.grants_path();
+        if !path.exists() {
+            return Ok(BTreeMap::new());
         }
+
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("Failed to parse {}", path.display()))
+    }
+
+    fn save_grants(&self, grants: &BTreeMap<String, StackGrants>) -> Result<()> {
+        let path = self.grants_path();
+        if grants.is_empty() {
+            if path.exists() {
+                files::remove_file(&path)
+                    .with_context(|| format!("Failed to remove {}", path.display()))?;
+            }
+            return Ok(());
+        }
+
+        let content = serde_json::to_string_pretty(grants)
+            .context("Failed to serialize stack grants")?;
+        files::write(&path, content)
+            .with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Remove the permission rules recorded for a stack, keeping rules another stack also granted
+    ///
+    /// Returns the number of rules removed; stacks merged without scoped grants have none recorded.
+    pub async fn retract_stack_grants(&self, stack_name: &str) -> Result<usize> {
+        let mut ledger = self.load_grants()?;
+        let grants = match ledger.remove(stack_name) {
+            Some(grants) => grants,
+            None => return Ok(0),
+        };
+
+        let mut removed = 0;
+        if self.local_settings_path.exists() {
+            let content = tokio::fs::read_to_string(&self.local_settings_path)
+                .await
+                .with_context(|| format!("Failed to read local settings from {}", self.local_settings_path.display()))?;
+            let mut local_settings: Value = serde_json::from_str(&content)
+                .with_context(|| format!("Failed to parse JSON in {}", self.local_settings_path.display()))?;
+
+            for (list, rules) in [("allow", &grants.allow), ("deny", &grants.deny)] {
+                let still_granted = |rule: &str| ledger.values().any(|other| {
+                    let other_rules = if list == "allow" { &other.allow } else { &other.deny };
+                    other_rules.iter().any(|other_rule| other_rule == rule)
+                });
+
+                if let Some(existing) = local_settings.pointer_mut(&format!("/permissions/{}", list)).and_then(Value::as_array_mut) {
+                    let before = existing.len();
+                    existing.retain(|rule| match rule.as_str() {
+                        Some(rule) => !rules.iter().any(|granted| granted == rule) || still_granted(rule),
+                        None => true,
+                    });
+                    removed += before - existing.len();
+                }
+            }
+
+            let merged_content = serde_json::to_string_pretty(&local_settings)
+                .context("Failed to serialize local settings")?;
+            files::write(&self.local_settings_path, merged_content)
+                .with_context(|| format!("Failed to write local settings to {}", self.local_settings_path.display()))?;
+        }
+
+        self.save_grants(&ledger)?;
+        Ok(removed)
+    }
+
+    /// Add or remove the Stop hook that pushes stack edits when a claude session stops
+    ///
+    /// `stacks push --quiet` only pushes stacks with changes under stacks/ and prints nothing
+    /// otherwise, so the hook is cheap when the session didn't touch a stack.
+    /// Returns whether the settings changed.
+    pub fn set_auto_push(&self, enabled: bool) -> Result<bool> {
+        let mut local_settings: Value = match self.local_settings_path.exists() {
+            true => {
+                let content = fs::read_to_string(&self.local_settings_path)
+                    .with_context(|| format!("Failed to read local settings from {}", self.local_settings_path.display()))?;
+                serde_json::from_str(&content)
+                    .with_context(|| format!("Failed to parse JSON in {}", self.local_settings_path.display()))?
+            }
+            false if enabled => Value::Object(Map::new()),
+            false => return Ok(false),
+        };
+
+        let changed = match enabled {
+            true => add_auto_push_hook(&mut local_settings),
+            false => remove_auto_push_hook(&mut local_settings),
+        };
+        if !changed {
+            return Ok(false);
+        }
+
+        if let Some(parent) = self.local_settings_path.parent() {
+            files::create_dir_all(parent)
+                .with_context(|| format!("Failed to create directory {}", parent.display()))?;
+        }
+        let content = serde_json::to_string_pretty(&local_settings)
+            .context("Failed to serialize local settings")?;
+        files::write(&self.local_settings_path, content)
+            .with_context(|| format!("Failed to write local settings to {}", self.local_settings_path.display()))?;
+        Ok(true)
     }
 
     /// Merge settings from a stack into the local settings file
     pub async fn merge_stack_settings(&self, stack: &Stack) -> Result<()> {
+        if self.set_auto_push(self.auto_push)? {
+            self.reporter.report(&Event::AutoPushHookChanged { enabled: self.auto_push });
+        }
+
         let stack_settings_path = stack.claude_dir.join(".local-settings.json");
         
         if !stack_settings_path.exists() {
@@ -30,9 +189,13 @@ impl SettingsMerger {
             .await
             .with_context(|| format!("Failed to read stack settings from {}", stack_settings_path.display()))?;
         
-        let stack_settings: Value = serde_json::from_str(&stack_settings_content)
+        let mut stack_settings: Value = serde_json::from_str(&stack_settings_content)
             .with_context(|| format!("Failed to parse JSON in {}", stack_settings_path.display()))?;
 
+        if self.scope_grants {
+            scope_bash_grants(&mut stack_settings, &stack.path);
+        }
+
         // Read existing local settings or create empty object
         let mut local_settings = if self.local_settings_path.exists() {
             let local_content = tokio::fs::read_to_string(&self.local_settings_path)
@@ -44,12 +207,19 @@ impl SettingsMerger {
         } else {
             // Ensure parent directory exists
             if let Some(parent) = self.local_settings_path.parent() {
-                fs::create_dir_all(parent)
+                files::create_dir_all(parent)
                     .with_context(|| format!("Failed to create directory {}", parent.display()))?;
             }
             Value::Object(Map::new())
         };
 
+        // Rules the stack adds (not ones already present) are recorded as its grants
+        let added = if self.scope_grants {
+            Some(new_permission_rules(&local_settings, &stack_settings))
+        } else {
+            None
+        };
+
         // Merge stack settings into local settings
         deep_merge(&mut local_settings, stack_settings);
 
@@ -57,15 +227,126 @@ impl SettingsMerger {
         let merged_content = serde_json::to_string_pretty(&local_settings)
             .context("Failed to serialize merged settings")?;
         
-        tokio::fs::write(&self.local_settings_path, merged_content)
-            .await
+        files::write(&self.local_settings_path, merged_content)
             .with_context(|| format!("Failed to write merged settings to {}", self.local_settings_path.display()))?;
 
-        println!("  ⚙️ Merged settings from stack {}", stack.name);
+        if let Some(added) = added {
+            let mut ledger = self.load_grants()?;
+            let grants = ledger.entry(stack.name.clone()).or_default();
+            for rule in added.allow {
+                if !grants.allow.contains(&rule) {
+                    grants.allow.push(rule);
+                }
+            }
+            for rule in added.deny {
+                if !grants.deny.contains(&rule) {
+                    grants.deny.push(rule);
+                }
+            }
+            self.save_grants(&ledger)?;
+        }
+
+        self.reporter.report(&Event::SettingsMerged { stack: stack.name.clone() });
         Ok(())
     }
 }
 
+fn is_auto_push_entry(entry: &Value) -> bool {
+    entry["hooks"].as_array().is_some_and(|hooks| {
+        hooks.iter().any(|hook| hook["command"].as_str() == Some(AUTO_PUSH_COMMAND))
+    })
+}
+
+/// Append the auto-push entry to `hooks.Stop` unless it's there already
+fn add_auto_push_hook(settings: &mut Value) -> bool {
+    if settings.pointer("/hooks/Stop").and_then(Value::as_array).is_some_and(|entries| entries.iter().any(is_auto_push_entry)) {
+        return false;
+    }
+    let entry = serde_json::json!({
+        "hooks": [{ "type": "command", "command": AUTO_PUSH_COMMAND }]
+    });
+    deep_merge(settings, serde_json::json!({ "hooks": { "Stop": [entry] } }));
+    true
+}
+
+/// Drop the auto-push entry from `hooks.Stop`, and the hook lists it leaves empty
+fn remove_auto_push_hook(settings: &mut Value) -> bool {
+    let hooks = match settings.get_mut("hooks").and_then(Value::as_object_mut) {
+        Some(hooks) => hooks,
+        None => return false,
+    };
+    let stop = match hooks.get_mut("Stop").and_then(Value::as_array_mut) {
+        Some(stop) => stop,
+        None => return false,
+    };
+    let before = stop.len();
+    stop.retain(|entry| !is_auto_push_entry(entry));
+    if stop.len() == before {
+        return false;
+    }
+    if stop.is_empty() {
+        hooks.remove("Stop");
+    }
+    if hooks.is_empty() {
+        if let Some(settings) = settings.as_object_mut() {
+            settings.remove("hooks");
+        }
+    }
+    true
+}
+
+/// Rewrite `Bash(./script...)` grants that name a script shipped in the stack to its path under the stack
+///
+/// This keeps a stack's grant from allowing a same-named script elsewhere in the project.
+fn scope_bash_grants(stack_settings: &mut Value, stack_path: &Path) {
+    let stack_dir = stack_path.to_string_lossy().trim_end_matches('/').to_string();
+
+    for list in ["allow", "deny"] {
+        let rules = match stack_settings.pointer_mut(&format!("/permissions/{}", list)).and_then(Value::as_array_mut) {
+            Some(rules) => rules,
+            None => continue,
+        };
+
+        for rule in rules.iter_mut() {
+            let scoped = match rule.as_str().and_then(|rule| rule.strip_prefix("Bash(./")) {
+                Some(rest) => {
+                    let script = rest.split([':', ' ', ')']).next().unwrap_or(rest);
+                    if stack_path.join(script).is_file() {
+                        Some(format!("Bash({}/{}", stack_dir, rest))
+                    } else {
+                        None
+                    }
+                }
+                None => None,
+            };
+
+            if let Some(scoped) = scoped {
+                *rule = Value::String(scoped);
+            }
+        }
+    }
+}
+
+/// Permission rules in `source` that `target` doesn't already contain
+fn new_permission_rules(target: &Value, source: &Value) -> StackGrants {
+    let new_rules = |list: &str| -> Vec<String> {
+        let existing = target.pointer(&format!("/permissions/{}", list)).and_then(Value::as_array);
+        source.pointer(&format!("/permissions/{}", list))
+            .and_then(Value::as_array)
+            .map(|rules| rules.iter()
+                .filter_map(Value::as_str)
+                .filter(|rule| !existing.is_some_and(|existing| existing.iter().any(|e| e.as_str() == Some(*rule))))
+                .map(|rule| rule.to_string())
+                .collect())
+            .unwrap_or_default()
+    };
+
+    StackGrants {
+        allow: new_rules("allow"),
+        deny: new_rules("deny"),
+    }
+}
+
 /// Deep merge two JSON values, with the second value taking precedence
 fn deep_merge(target: &mut Value, source: Value) {
     match (target, source) {
@@ -102,6 +383,7 @@ fn deep_merge(target: &mut Value, source: Value) {
 mod tests {
     use super::*;
     use serde_json::json;
+    use crate::core::events::CollectingReporter;
 
     #[test]
     fn test_deep_merge_objects() {
@@ -141,4 +423,67 @@ mod tests {
         
         assert_eq!(target, json!([1, 2, 3, 4, 5]));
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_scoped_grants_are_retracted() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let stack_path = temp_dir.path().join("stacks").join("lint");
+        std::fs::create_dir_all(stack_path.join(".claude")).unwrap();
+        std::fs::write(stack_path.join("check.sh"), "#!/bin/sh\n").unwrap();
+        std::fs::write(
+            stack_path.join(".claude").join(".local-settings.json"),
+            r#"{"permissions": {"allow": ["Bash(./check.sh:*)", "Bash(npm run lint)", "Bash(git:*)"]}}"#,
+        ).unwrap();
+
+        let local_settings_path = temp_dir.path().join(".claude").join(".local-settings.json");
+        std::fs::create_dir_all(local_settings_path.parent().unwrap()).unwrap();
+        std::fs::write(&local_settings_path, r#"{"permissions": {"allow": ["Bash(git:*)"]}}"#).unwrap();
+
+        let reporter = Arc::new(CollectingReporter::new());
+        let merger = SettingsMerger {
+            local_settings_path: local_settings_path.clone(),
+            scope_grants: true,
+            auto_push: false,
+            reporter: reporter.clone(),
+        };
+        let stack = Stack::new("lint".to_string(), stack_path.clone());
+        merger.merge_stack_settings(&stack).await.unwrap();
+        assert_eq!(reporter.events(), vec![Event::SettingsMerged { stack: "lint".to_string() }]);
+
+        let merged: Value = serde_json::from_str(&std::fs::read_to_string(&local_settings_path).unwrap()).unwrap();
+        let scoped = format!("Bash({}/check.sh:*)", stack_path.display());
+        assert!(merged["permissions"]["allow"].as_array().unwrap().iter().any(|rule| rule == &json!(scoped)));
+
+        let removed = merger.retract_stack_grants("lint").await.unwrap();
+        assert_eq!(removed, 2);
+
+        let retracted: Value = serde_json::from_str(&std::fs::read_to_string(&local_settings_path).unwrap()).unwrap();
+        // The user's own git grant predates the stack and stays
+        assert_eq!(retracted["permissions"]["allow"], json!(["Bash(git:*)"]));
+        assert!(!local_settings_path.with_file_name(GRANTS_FILE).exists());
+    }
+
+    #[test]
+    fn test_auto_push_hook() {
+        let mut settings = json!({
+            "hooks": {
+                "Stop": [{ "hooks": [{ "type": "command", "command": "say done" }] }]
+            }
+        });
+
+        assert!(add_auto_push_hook(&mut settings));
+        assert!(!add_auto_push_hook(&mut settings));
+        let stop = settings["hooks"]["Stop"].as_array().unwrap();
+        assert_eq!(stop.len(), 2);
+        assert_eq!(stop[1]["hooks"][0]["command"], json!(AUTO_PUSH_COMMAND));
+
+        assert!(remove_auto_push_hook(&mut settings));
+        assert!(!remove_auto_push_hook(&mut settings));
+        assert_eq!(settings["hooks"]["Stop"].as_array().unwrap().len(), 1);
+
+        let mut only = json!({});
+        add_auto_push_hook(&mut only);
+        remove_auto_push_hook(&mut only);
+        assert_eq!(only, json!({}));
+    }
+}
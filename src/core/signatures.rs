@@ -0,0 +1,297 @@
+use std::path::Path;
+use anyhow::{Result, Context};
+use serde::{Deserialize, Serialize};
+
+use super::auth;
+use super::cache;
+use super::events::{self, Event};
+use crate::error::StacksError;
+use crate::utils::process::{Process, ProcessOutput};
+
+/// Bare clones used for verification, under the cache directory
+const VERIFY_DIR: &str = "verify";
+
+/// What to do with a stack whose tip isn't signed by a trusted key
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SignaturePolicy {
+    /// Don't check signatures
+    #[default]
+    Ignore,
+    /// Check, and warn about unsigned or untrusted stacks
+    Warn,
+    /// Refuse to check out unsigned or untrusted stacks
+    Require,
+}
+
+impl SignaturePolicy {
+    pub fn all() -> [SignaturePolicy; 3] {
+        [SignaturePolicy::Ignore, SignaturePolicy::Warn, SignaturePolicy::Require]
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SignaturePolicy::Ignore => "ignore",
+            SignaturePolicy::Warn => "warn",
+            SignaturePolicy::Require => "require",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            SignaturePolicy::Ignore => "Don't check stack signatures",
+            SignaturePolicy::Warn => "Warn when a stack isn't signed by a trusted key",
+            SignaturePolicy::Require => "Refuse stacks that aren't signed by a trusted key",
+        }
+    }
+}
+
+impl std::str::FromStr for SignaturePolicy {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "ignore" => Ok(SignaturePolicy::Ignore),
+            "warn" => Ok(SignaturePolicy::Warn),
+            "require" => Ok(SignaturePolicy::Require),
+            _ => anyhow::bail!("Invalid signature policy: {}", s),
+        }
+    }
+}
+
+/// `[signatures]` section of config.toml: which keys stacks must be signed with
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq)]
+pub struct SignaturesConfig {
+    #[serde(default)]
+    pub policy: SignaturePolicy,
+    /// GPG key fingerprints (or long key ids) trusted to sign stacks
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub trusted_keys: Vec<String>,
+    /// SSH allowed-signers file (see `gpg.ssh.allowedSignersFile` in git-config) for SSH-signed stacks
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub allowed_signers: Option<String>,
+}
+
+impl SignaturesConfig {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+/// Result of checking the commit a stack would be checked out at
+#[derive(Debug, Clone, PartialEq)]
+pub enum Verification {
+    /// Signed by a trusted key, on a tag pointing at the commit or on the commit itself
+    Trusted { commit: String, signed: String, signer: String },
+    /// Signed, but not by a trusted key
+    Untrusted { commit: String, signer: String },
+    Unsigned { commit: String },
+}
+
+impl Verification {
+    /// The commit that was checked
+    pub fn commit(&self) -> &str {
+        match self {
+            Verification::Trusted { commit, .. } | Verification::Untrusted { commit, .. } | Verification::Unsigned { commit } => commit,
+        }
+    }
+
+    pub fn describe(&self, stack_name: &str) -> String {
+        match self {
+            Verification::Trusted { commit, signed, signer } => {
+                format!("{} at {} is signed by {} ({})", stack_name, short(commit), signer, signed)
+            }
+            Verification::Untrusted { commit, signer } => {
+                format!("{} at {} is signed by {}, which is not a trusted key", stack_name, short(commit), signer)
+            }
+            Verification::Unsigned { commit } => {
+                format!("{} at {} has no signed tag or commit signature", stack_name, short(commit))
+            }
+        }
+    }
+}
+
+/// Check the signatures on the tip of `branch` (the default branch when None) of `url`
+///
+/// The repository is cloned bare, without file contents, into the cache; tags pointing at the
+/// tip are tried first, then the commit's own signature.
+pub fn verify_remote(name: &str, url: &str, branch: Option<&str>, config: &SignaturesConfig) -> Result<Verification> {
+    let clone_dir = cache::cache_root()?.join(VERIFY_DIR).join(name);
+    if clone_dir.exists() {
+        std::fs::remove_dir_all(&clone_dir)
+            .with_context(|| format!("Failed to clear {}", clone_dir.display()))?;
+    }
+    let clone_path = clone_dir.to_string_lossy().to_string();
+    let mut clone = Process::git()
+        .envs(auth::git_auth_env(url))
+        .args(["clone", "--quiet", "--bare", "--filter=blob:none"]);
+    if let Some(branch) = branch {
+        clone = clone.args(["--branch", branch]);
+    }
+    clone.args([url, clone_path.as_str()])
+        .run()
+        .with_context(|| format!("Failed to fetch {} to verify its signatures", url))?;
+
+    let result = verify_clone(&clone_dir, config);
+    if let Err(error) = std::fs::remove_dir_all(&clone_dir) {
+        tracing::debug!("failed to remove {}: {}", clone_dir.display(), error);
+    }
+    result
+}
+
+fn verify_clone(dir: &Path, config: &SignaturesConfig) -> Result<Verification> {
+    let git = || {
+        let mut git = Process::git().current_dir(dir).read_only();
+        if let Some(signers) = &config.allowed_signers {
+            git = git.args(["-c", &format!("gpg.ssh.allowedSignersFile={}", signers)]);
+        }
+        git
+    };
+    let commit = git().args(["rev-parse", "HEAD"]).run()?.stdout.trim().to_string();
+    let tags = git().args(["tag", "--points-at", "HEAD"]).run()?.stdout;
+
+    let mut untrusted = None;
+    let candidates = tags.lines().map(|tag| (format!("tag {}", tag), "verify-tag", tag.to_string()))
+        .chain(std::iter::once(("commit signature".to_string(), "verify-commit", commit.clone())));
+    for (signed, command, object) in candidates {
+        let output = git().args([command, "--raw", object.as_str()]).output()?;
+        match check_signature(&output, config) {
+            Signature::Trusted(signer) => return Ok(Verification::Trusted { commit, signed, signer }),
+            Signature::Untrusted(signer) => untrusted = untrusted.or(Some(signer)),
+            Signature::None => {}
+        }
+    }
+    Ok(match untrusted {
+        Some(signer) => Verification::Untrusted { commit, signer },
+        None => Verification::Unsigned { commit },
+    })
+}
+
+#[derive(Debug, PartialEq)]
+enum Signature {
+    Trusted(String),
+    Untrusted(String),
+    None,
+}
+
+/// Interpret `git verify-tag/verify-commit --raw`: GPG status lines, or git's SSH messages
+fn check_signature(output: &ProcessOutput, config: &SignaturesConfig) -> Signature {
+    let status = &output.stderr;
+    if status.contains("[GNUPG:]") {
+        // VALIDSIG <fingerprint> ... <primary key fingerprint>
+        let fingerprints: Vec<&str> = status
+            .lines()
+            .filter_map(|line| line.strip_prefix("[GNUPG:] VALIDSIG "))
+            .flat_map(|rest| {
+                let fields: Vec<&str> = rest.split_whitespace().collect();
+                [fields.first().copied(), fields.last().copied()]
+            })
+            .flatten()
+            .collect();
+        let signer = match fingerprints.first() {
+            Some(fingerprint) => fingerprint.to_string(),
+            None => match status.lines().find_map(|line| line.strip_prefix("[GNUPG:] ERRSIG ")) {
+                Some(rest) => rest.split_whitespace().next().unwrap_or("an unknown key").to_string(),
+                None => return Signature::None,
+            },
+        };
+        let trusted = output.success() && fingerprints.iter().any(|fingerprint| {
+            config.trusted_keys.iter().any(|key| {
+                let key = key.replace(' ', "").to_uppercase();
+                !key.is_empty() && fingerprint.to_uppercase().ends_with(&key)
+            })
+        });
+        return match trusted {
+            true => Signature::Trusted(signer),
+            false => Signature::Untrusted(signer),
+        };
+    }
+
+    // SSH signatures: git checks the signer against the allowed-signers file itself
+    match status.lines().find(|line| line.starts_with("Good \"git\" signature")) {
+        Some(line) if output.success() && config.allowed_signers.is_some() => {
+            let signer = line.split(" for ").nth(1).and_then(|rest| rest.split(" with ").next()).unwrap_or(line);
+            Signature::Trusted(signer.to_string())
+        }
+        Some(line) => Signature::Untrusted(line.to_string()),
+        None if status.contains("No principal matched") || status.contains("allowedSignersFile") => {
+            Signature::Untrusted("an SSH key outside the allowed signers".to_string())
+        }
+        None => Signature::None,
+    }
+}
+
+/// Verify a stack before checkout per the configured policy; does nothing when signatures are ignored
+///
+/// Returns the checked commit, which the checkout must install rather than fetching the branch anew.
+pub fn check(name: &str, url: &str, branch: Option<&str>, config: &SignaturesConfig) -> Result<Option<String>> {
+    if config.policy == SignaturePolicy::Ignore {
+        return Ok(None);
+    }
+    let verification = verify_remote(name, url, branch, config)?;
+    enforce(name, &verification, config.policy)?;
+    Ok(Some(verification.commit().to_string()))
+}
+
+/// Report the verification, and refuse the stack when the policy requires a trusted signature
+pub fn enforce(stack_name: &str, verification: &Verification, policy: SignaturePolicy) -> Result<()> {
+    match (verification, policy) {
+        (Verification::Trusted { .. }, _) => {
+            events::report(Event::SignatureChecked { description: verification.describe(stack_name), trusted: true });
+            Ok(())
+        }
+        (_, SignaturePolicy::Require) => Err(StacksError::validation(format!("Refusing {}", verification.describe(stack_name)))
+            .with_hint("Trust the signer with 'stacks config set signature-trusted-keys', or set signature-policy to warn")
+            .into()),
+        _ => {
+            events::report(Event::SignatureChecked { description: verification.describe(stack_name), trusted: false });
+            Ok(())
+        }
+    }
+}
+
+fn short(commit: &str) -> &str {
+    &commit[..commit.len().min(7)]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn output(code: i32, stderr: &str) -> ProcessOutput {
+        ProcessOutput { code: Some(code), stdout: String::new(), stderr: stderr.to_string() }
+    }
+
+    #[test]
+    fn test_gpg_signatures() {
+        let config = SignaturesConfig {
+            trusted_keys: vec!["ABCD 1234 EF56 7890".to_string()],
+            ..Default::default()
+        };
+        let good = "[GNUPG:] NEWSIG\n[GNUPG:] GOODSIG 1234EF567890 Stack Maintainer\n[GNUPG:] VALIDSIG 0000111122223333ABCD1234EF567890 2024-01-01 1704067200 0 4 0 1 10 00 0000111122223333ABCD1234EF567890\n";
+        assert_eq!(check_signature(&output(0, good), &config), Signature::Trusted("0000111122223333ABCD1234EF567890".to_string()));
+
+        let other = SignaturesConfig { trusted_keys: vec!["FFFF".to_string()], ..Default::default() };
+        assert!(matches!(check_signature(&output(0, good), &other), Signature::Untrusted(_)));
+
+        let missing_key = "[GNUPG:] NEWSIG\n[GNUPG:] ERRSIG 1234EF567890 1 10 00 1704067200 9 -\n[GNUPG:] NO_PUBKEY 1234EF567890\n";
+        assert_eq!(check_signature(&output(1, missing_key), &config), Signature::Untrusted("1234EF567890".to_string()));
+    }
+
+    #[test]
+    fn test_ssh_and_missing_signatures() {
+        let config = SignaturesConfig { allowed_signers: Some("/etc/stacks/allowed_signers".to_string()), ..Default::default() };
+        let good = "Good \"git\" signature for maintainer@example.com with ED25519 key SHA256:abc\n";
+        assert_eq!(check_signature(&output(0, good), &config), Signature::Trusted("maintainer@example.com".to_string()));
+
+        let unsigned = "error: no signature found\n";
+        assert_eq!(check_signature(&output(1, unsigned), &config), Signature::None);
+    }
+
+    #[test]
+    fn test_enforce() {
+        let unsigned = Verification::Unsigned { commit: "abc1234def".to_string() };
+        assert!(enforce("lint", &unsigned, SignaturePolicy::Warn).is_ok());
+        assert!(enforce("lint", &unsigned, SignaturePolicy::Require).is_err());
+    }
+}
@@ -0,0 +1,141 @@
+use std::path::Path;
+use anyhow::{Result, Context, bail};
+
+use crate::utils::files;
+
+pub const MANIFEST_FILE: &str = "stack.toml";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReleaseLevel {
+    Major,
+    Minor,
+    Patch,
+}
+
+impl ReleaseLevel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ReleaseLevel::Major => "major",
+            ReleaseLevel::Minor => "minor",
+            ReleaseLevel::Patch => "patch",
+        }
+    }
+}
+
+impl std::str::FromStr for ReleaseLevel {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "major" => Ok(ReleaseLevel::Major),
+            "minor" => Ok(ReleaseLevel::Minor),
+            "patch" => Ok(ReleaseLevel::Patch),
+            _ => bail!("Invalid release level: {} (expected major, minor or patch)", s),
+        }
+    }
+}
+
+/// Read the `[stack] version` from a stack's stack.toml, if present
+pub fn read_version(stack_path: &Path) -> Result<Option<String>> {
+    let manifest_path = stack_path.join(MANIFEST_FILE);
+    if !manifest_path.exists() {
+        return Ok(None);
+    }
+
+    let content = std::fs::read_to_string(&manifest_path)
+        .with_context(|| format!("Failed to read {}", manifest_path.display()))?;
+    let table: toml::Table = toml::from_str(&content)
+        .with_context(|| format!("Failed to parse {}", manifest_path.display()))?;
+
+    Ok(table.get("stack")
+        .and_then(|stack| stack.get("version"))
+        .and_then(|version| version.as_str())
+        .map(|version| version.to_string()))
+}
+
+/// Write the `[stack] version` to stack.toml, keeping any other keys intact
+pub fn write_version(stack_path: &Path, stack_name: &str, version: &str) -> Result<()> {
+    let manifest_path = stack_path.join(MANIFEST_FILE);
+
+    let mut table: toml::Table = if manifest_path.exists() {
+        let content = std::fs::read_to_string(&manifest_path)
+            .with_context(|| format!("Failed to read {}", manifest_path.display()))?;
+        toml::from_str(&content)
+            .with_context(|| format!("Failed to parse {}", manifest_path.display()))?
+    } else {
+        toml::Table::new()
+    };
+
+    let stack = table.entry("stack")
+        .or_insert_with(|| toml::Value::Table(toml::Table::new()));
+    let stack = stack.as_table_mut()
+        .context("The [stack] entry in stack.toml must be a table")?;
+
+    stack.entry("name")
+        .or_insert_with(|| toml::Value::String(stack_name.to_string()));
+    stack.insert("version".to_string(), toml::Value::String(version.to_string()));
+
+    let content = toml::to_string_pretty(&table)
+        .context("Failed to serialize stack.toml")?;
+    files::write(&manifest_path, content)
+        .with_context(|| format!("Failed to write {}", manifest_path.display()))?;
+
+    Ok(())
+}
+
+/// Bump a `major.minor.patch` version string by the given level
+pub fn bump_version(version: &str, level: ReleaseLevel) -> Result<String> {
+    let trimmed = version.trim().trim_start_matches('v');
+    let parts: Vec<u64> = trimmed
+        .split('.')
+        .map(|part| part.parse::<u64>())
+        .collect::<std::result::Result<_, _>>()
+        .with_context(|| format!("Invalid version '{}' in stack.toml", version))?;
+
+    if parts.len() != 3 {
+        bail!("Invalid version '{}' in stack.toml (expected major.minor.patch)", version);
+    }
+
+    let (major, minor, patch) = (parts[0], parts[1], parts[2]);
+    let bumped = match level {
+        ReleaseLevel::Major => (major + 1, 0, 0),
+        ReleaseLevel::Minor => (major, minor + 1, 0),
+        ReleaseLevel::Patch => (major, minor, patch + 1),
+    };
+
+    Ok(format!("{}.{}.{}", bumped.0, bumped.1, bumped.2))
+}
+
+/// Tag name used for a released stack version; usable as a pinned ref
+pub fn release_tag(version: &str) -> String {
+    format!("v{}", version)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_bump_version() {
+        assert_eq!(bump_version("1.2.3", ReleaseLevel::Patch).unwrap(), "1.2.4");
+        assert_eq!(bump_version("1.2.3", ReleaseLevel::Minor).unwrap(), "1.3.0");
+        assert_eq!(bump_version("v1.2.3", ReleaseLevel::Major).unwrap(), "2.0.0");
+        assert!(bump_version("1.2", ReleaseLevel::Patch).is_err());
+    }
+
+    #[test]
+    fn test_write_version_preserves_other_keys() {
+        let temp_dir = TempDir::new().unwrap();
+        std::fs::write(
+            temp_dir.path().join(MANIFEST_FILE),
+            "[stack]\nname = \"lint\"\nversion = \"0.1.0\"\ndescription = \"Linting\"\n",
+        ).unwrap();
+
+        write_version(temp_dir.path(), "lint", "0.2.0").unwrap();
+
+        assert_eq!(read_version(temp_dir.path()).unwrap(), Some("0.2.0".to_string()));
+        let content = std::fs::read_to_string(temp_dir.path().join(MANIFEST_FILE)).unwrap();
+        assert!(content.contains("description = \"Linting\""));
+    }
+}
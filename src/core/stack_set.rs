@@ -0,0 +1,167 @@
+use std::path::Path;
+use anyhow::{Result, Context};
+use serde::{Deserialize, Serialize};
+
+use super::lockfile::StacksLock;
+use super::mcp_validator::McpServer;
+use super::stack_manifest;
+
+/// A project's stack setup in a form teammates can reproduce with `stacks apply`
+#[derive(Debug, Default, Clone, Serialize, Deserialize, PartialEq)]
+pub struct StackSet {
+    #[serde(default, rename = "stack")]
+    pub stacks: Vec<StackEntry>,
+    #[serde(default, rename = "mcp", skip_serializing_if = "Vec::is_empty")]
+    pub mcp_servers: Vec<McpEntry>,
+    /// The project's `.claude/settings.json`, merged into the teammate's on apply
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub settings: Option<toml::Table>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct StackEntry {
+    pub name: String,
+    /// Repository URL the stack was checked out from
+    pub source: String,
+    pub branch: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub path: Option<String>,
+    /// Upstream commit installed when the set was exported
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub commit: Option<String>,
+    /// Version from the stack's stack.toml
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+}
+
+/// An MCP server the stacks need, as `claude mcp add` takes it
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+pub struct McpEntry {
+    pub name: String,
+    pub transport: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>,
+}
+
+impl StackSet {
+    /// Collect the stacks recorded in `project_root`'s stacks.lock, with the given MCP servers
+    pub fn collect(project_root: &Path, lock: &StacksLock, mcp_servers: &[McpServer]) -> Result<Self> {
+        let stacks = lock.stacks
+            .iter()
+            .map(|metadata| StackEntry {
+                name: metadata.stack_name.clone(),
+                source: metadata.source_repo.clone(),
+                // A pinned stack is re-added from its tag, which checking out recognizes as one
+                branch: metadata.source_tag.clone().unwrap_or_else(|| metadata.source_branch.clone()),
+                path: metadata.source_path.clone(),
+                commit: metadata.commit.clone(),
+                version: stack_manifest::read_version(&project_root.join(&metadata.original_path)).ok().flatten(),
+            })
+            .collect();
+
+        let mcp_servers = mcp_servers
+            .iter()
+            .map(|server| McpEntry {
+                name: server.name.clone(),
+                transport: server.transport.clone(),
+                command: server.command.clone(),
+                url: server.url.clone(),
+            })
+            .collect();
+
+        let settings_path = project_root.join(".claude").join("settings.json");
+        let settings = match settings_path.exists() {
+            true => {
+                let content = std::fs::read_to_string(&settings_path)
+                    .with_context(|| format!("Failed to read {}", settings_path.display()))?;
+                let json: serde_json::Value = serde_json::from_str(&content)
+                    .with_context(|| format!("Failed to parse {}", settings_path.display()))?;
+                Some(toml::Table::try_from(json)
+                    .with_context(|| format!("{} can't be exported (TOML has no null values)", settings_path.display()))?)
+            }
+            false => None,
+        };
+
+        Ok(Self { stacks, mcp_servers, settings })
+    }
+
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        toml::from_str(&content)
+            .with_context(|| format!("Failed to parse stack set {}", path.display()))
+    }
+
+    pub fn to_toml(&self) -> Result<String> {
+        let content = toml::to_string_pretty(self).context("Failed to serialize the stack set")?;
+        Ok(format!("# Stack set exported by `stacks export`; reproduce it with `stacks apply <file>`\n\n{}", content))
+    }
+}
+
+impl McpEntry {
+    /// Arguments for `claude`; none when the entry lacks what its transport needs
+    pub fn add_args(&self) -> Option<Vec<String>> {
+        let mut args = vec!["mcp".to_string(), "add".to_string()];
+        match (self.transport.as_str(), &self.url, &self.command) {
+            ("http" | "sse", Some(url), _) => {
+                args.extend(["--transport".to_string(), self.transport.clone(), self.name.clone(), url.clone()]);
+            }
+            ("stdio", _, Some(command)) => {
+                args.extend([self.name.clone(), "--".to_string()]);
+                args.extend(command.split_whitespace().map(str::to_string));
+            }
+            _ => return None,
+        }
+        Some(args)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let mut settings = toml::Table::new();
+        settings.insert("model".to_string(), toml::Value::String("sonnet".to_string()));
+        let set = StackSet {
+            stacks: vec![StackEntry {
+                name: "lint".to_string(),
+                source: "git@github.com:acme/tools.git".to_string(),
+                branch: "dev".to_string(),
+                path: Some("stacks/lint".to_string()),
+                commit: Some("abc1234".to_string()),
+                version: None,
+            }],
+            mcp_servers: vec![McpEntry {
+                name: "sentry".to_string(),
+                transport: "http".to_string(),
+                command: None,
+                url: Some("https://mcp.sentry.dev/mcp".to_string()),
+            }],
+            settings: Some(settings),
+        };
+
+        let parsed: StackSet = toml::from_str(&set.to_toml().unwrap()).unwrap();
+        assert_eq!(parsed, set);
+    }
+
+    #[test]
+    fn test_mcp_add_args() {
+        let server = McpEntry {
+            name: "postgres".to_string(),
+            transport: "stdio".to_string(),
+            command: Some("npx -y @modelcontextprotocol/server-postgres".to_string()),
+            url: None,
+        };
+        assert_eq!(
+            server.add_args().unwrap(),
+            ["mcp", "add", "postgres", "--", "npx", "-y", "@modelcontextprotocol/server-postgres"]
+        );
+
+        let incomplete = McpEntry { transport: "http".to_string(), command: None, ..server };
+        assert_eq!(incomplete.add_args(), None);
+    }
+}
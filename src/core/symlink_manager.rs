@@ -1,54 +1,83 @@
 use std::path::{Path, PathBuf};
 use std::fs;
-#[cfg(unix)]
-use std::os::unix::fs as unix_fs;
-#[cfg(windows)]
-use std::os::windows::fs as windows_fs;
+use std::sync::Arc;
 use anyhow::{Result, Context};
 use walkdir::WalkDir;
 
+use super::events::{default_reporter, Event, Reporter};
 use super::stack_manager::Stack;
+use crate::utils::files;
+
+/// Result of checking a stack's agent/command symlinks
+#[derive(Debug, Default)]
+pub struct SymlinkHealth {
+    pub linked: usize,
+    /// Expected links that don't exist
+    pub missing: Vec<PathBuf>,
+    /// Links whose source file is gone
+    pub broken: Vec<PathBuf>,
+}
+
+impl SymlinkHealth {
+    pub fn is_healthy(&self) -> bool {
+        self.missing.is_empty() && self.broken.is_empty()
+    }
+}
 
 pub struct SymlinkManager {
     claude_dir: PathBuf,
+    reporter: Arc<dyn Reporter>,
 }
 
 impl SymlinkManager {
     pub fn new() -> Self {
-        Self {
-            claude_dir: PathBuf::from(".claude"),
-        }
+        Self::with_claude_dir(PathBuf::from(".claude"))
+    }
+
+    /// Manage the .claude directory of a project other than the current directory
+    pub fn with_claude_dir(claude_dir: PathBuf) -> Self {
+        Self { claude_dir, reporter: default_reporter() }
+    }
+
+    pub fn with_reporter(mut self, reporter: Arc<dyn Reporter>) -> Self {
+        self.reporter = reporter;
+        self
     }
 
     /// Create symlinks for all relevant files in a stack
-    pub async fn create_symlinks_for_stack(&self, stack: &Stack) -> Result<()> {
+    ///
+    /// Returns the links created or replaced; links that were already correct aren't included.
+    pub async fn create_symlinks_for_stack(&self, stack: &Stack) -> Result<Vec<PathBuf>> {
         // Ensure .claude directory exists
         self.ensure_claude_dir_exists()?;
 
+        let mut created = Vec::new();
+
         // Create symlinks for agents
         if stack.has_agents() {
-            self.create_symlinks_for_subdir(stack, "agents").await?;
+            created.extend(self.create_symlinks_for_subdir(stack, "agents").await?);
         }
 
         // Create symlinks for commands
         if stack.has_commands() {
-            self.create_symlinks_for_subdir(stack, "commands").await?;
+            created.extend(self.create_symlinks_for_subdir(stack, "commands").await?);
         }
 
-        Ok(())
+        Ok(created)
     }
 
     /// Create symlinks for a subdirectory (agents or commands)
-    async fn create_symlinks_for_subdir(&self, stack: &Stack, subdir: &str) -> Result<()> {
+    async fn create_symlinks_for_subdir(&self, stack: &Stack, subdir: &str) -> Result<Vec<PathBuf>> {
         let source_dir = stack.claude_dir.join(subdir);
         let target_dir = self.claude_dir.join(subdir);
+        let mut created = Vec::new();
 
         if !source_dir.exists() {
-            return Ok(());
+            return Ok(created);
         }
 
         // Ensure target directory exists
-        fs::create_dir_all(&target_dir)
+        files::create_dir_all(&target_dir)
             .with_context(|| format!("Failed to create directory {}", target_dir.display()))?;
 
         // Walk through source directory and create symlinks
@@ -64,25 +93,21 @@ impl SymlinkManager {
 
             // Create parent directories if needed
             if let Some(parent) = target_file.parent() {
-                fs::create_dir_all(parent)
+                files::create_dir_all(parent)
                     .with_context(|| format!("Failed to create parent directory for {}", target_file.display()))?;
             }
 
-            self.create_symlink_with_prefix(source_file, &target_file, &stack.name).await?;
+            created.extend(self.create_symlink_with_prefix(source_file, &target_file, &stack.name).await?);
         }
 
-        Ok(())
+        Ok(created)
     }
 
     /// Create a symlink with stack name prefix to avoid conflicts
-    async fn create_symlink_with_prefix(&self, source: &Path, target: &Path, stack_name: &str) -> Result<()> {
+    async fn create_symlink_with_prefix(&self, source: &Path, target: &Path, stack_name: &str) -> Result<Option<PathBuf>> {
         // Generate target path with stack prefix
-        let filename = target.file_name()
-            .and_then(|name| name.to_str())
+        let prefixed_target = prefixed_link_path(target, stack_name)
             .context("Invalid filename")?;
-        
-        let prefixed_filename = format!("{}_{}", stack_name, filename);
-        let prefixed_target = target.with_file_name(prefixed_filename);
 
         // Check if symlink already exists
         if prefixed_target.exists() {
@@ -94,11 +119,11 @@ impl SymlinkManager {
                 
                 if canonical_source == canonical_existing {
                     // Already correctly linked
-                    return Ok(());
+                    return Ok(None);
                 }
                 
                 // Remove existing symlink
-                fs::remove_file(&prefixed_target)
+                files::remove_file(&prefixed_target)
                     .with_context(|| format!("Failed to remove existing symlink {}", prefixed_target.display()))?;
             } else {
                 anyhow::bail!("Target file {} already exists and is not a symlink", prefixed_target.display());
@@ -132,65 +157,128 @@ impl SymlinkManager {
             source.to_path_buf()
         };
             
-        #[cfg(unix)]
-        unix_fs::symlink(&relative_source, &prefixed_target)
+        files::symlink(&relative_source, &prefixed_target)
             .with_context(|| format!("Failed to create symlink from {} to {}", 
                 relative_source.display(), prefixed_target.display()))?;
-                
-        #[cfg(windows)]
-        {
-            // On Windows, determine if source is a file or directory and use appropriate function
-            if relative_source.is_dir() {
-                windows_fs::symlink_dir(&relative_source, &prefixed_target)
-            } else {
-                windows_fs::symlink_file(&relative_source, &prefixed_target)
-            }
-            .with_context(|| format!("Failed to create symlink from {} to {}", 
-                relative_source.display(), prefixed_target.display()))?;
-        }
 
-        println!("  📎 Created symlink: {}", prefixed_target.display());
-        Ok(())
+        self.reporter.report(&Event::SymlinkCreated { link: prefixed_target.clone() });
+        Ok(Some(prefixed_target))
     }
 
     /// Ensure the .claude directory exists
     fn ensure_claude_dir_exists(&self) -> Result<()> {
         if !self.claude_dir.exists() {
-            fs::create_dir_all(&self.claude_dir)
+            files::create_dir_all(&self.claude_dir)
                 .with_context(|| format!("Failed to create .claude directory at {}", self.claude_dir.display()))?;
         }
         Ok(())
     }
 
     /// Remove symlinks for a specific stack
-    #[allow(dead_code)]
     pub async fn remove_stack_symlinks(&self, stack_name: &str) -> Result<()> {
-        let dirs_to_check = ["agents", "commands"];
-        
-        for dir in &dirs_to_check {
+        for link in self.stack_symlinks(stack_name) {
+            files::remove_file(&link)
+                .with_context(|| format!("Failed to remove symlink {}", link.display()))?;
+            self.reporter.report(&Event::SymlinkRemoved { link, stale: false });
+        }
+
+        Ok(())
+    }
+
+    /// Remove a stack's symlinks whose source file no longer exists
+    pub async fn prune_stale_symlinks(&self, stack_name: &str) -> Result<usize> {
+        let mut pruned = 0;
+
+        for link in self.stack_symlinks(stack_name) {
+            // exists() follows the link, so a dangling symlink reports false
+            if !link.exists() {
+                files::remove_file(&link)
+                    .with_context(|| format!("Failed to remove stale symlink {}", link.display()))?;
+                self.reporter.report(&Event::SymlinkRemoved { link, stale: true });
+                pruned += 1;
+            }
+        }
+
+        Ok(pruned)
+    }
+
+    /// Check that every agent/command of a stack is linked and that its links resolve
+    pub fn check_stack_symlinks(&self, stack: &Stack) -> SymlinkHealth {
+        let mut health = SymlinkHealth::default();
+        let mut expected = Vec::new();
+
+        for subdir in ["agents", "commands"] {
+            let source_dir = stack.claude_dir.join(subdir);
+            if !source_dir.exists() {
+                continue;
+            }
+
+            for entry in WalkDir::new(&source_dir)
+                .min_depth(1)
+                .into_iter()
+                .filter_map(|e| e.ok())
+                .filter(|e| e.file_type().is_file())
+            {
+                let relative_path = match entry.path().strip_prefix(&source_dir) {
+                    Ok(relative_path) => relative_path,
+                    Err(_) => continue,
+                };
+                let link = match prefixed_link_path(&self.claude_dir.join(subdir).join(relative_path), &stack.name) {
+                    Some(link) => link,
+                    None => continue,
+                };
+
+                // exists() follows the link, so a dangling symlink reports false
+                if !link.is_symlink() {
+                    health.missing.push(link.clone());
+                } else if !link.exists() {
+                    health.broken.push(link.clone());
+                } else {
+                    health.linked += 1;
+                }
+                expected.push(link);
+            }
+        }
+
+        // Links left behind for files the stack no longer has
+        for link in self.stack_symlinks(&stack.name) {
+            if !expected.contains(&link) && !link.exists() {
+                health.broken.push(link);
+            }
+        }
+
+        health
+    }
+
+    /// Find all symlinks in .claude/agents and .claude/commands created for a stack
+    fn stack_symlinks(&self, stack_name: &str) -> Vec<PathBuf> {
+        let prefix = format!("{}_", stack_name);
+        let mut links = Vec::new();
+
+        for dir in ["agents", "commands"] {
             let search_dir = self.claude_dir.join(dir);
             if !search_dir.exists() {
                 continue;
             }
 
-            // Find and remove symlinks with the stack prefix
             for entry in WalkDir::new(&search_dir)
                 .min_depth(1)
                 .into_iter()
                 .filter_map(|e| e.ok())
-                .filter(|e| e.file_type().is_file() && e.path().is_symlink())
+                .filter(|e| e.path_is_symlink())
             {
-                let filename = entry.file_name().to_string_lossy();
-                let prefix = format!("{}_", stack_name);
-                
-                if filename.starts_with(&prefix) {
-                    fs::remove_file(entry.path())
-                        .with_context(|| format!("Failed to remove symlink {}", entry.path().display()))?;
-                    println!("  🗑️ Removed symlink: {}", entry.path().display());
+                if entry.file_name().to_string_lossy().starts_with(&prefix) {
+                    links.push(entry.path().to_path_buf());
                 }
             }
         }
 
-        Ok(())
+        links
     }
+}
+
+/// Link path for a stack file: the target file name prefixed with the stack name
+fn prefixed_link_path(target: &Path, stack_name: &str) -> Option<PathBuf> {
+    let filename = target.file_name()?.to_str()?;
+    Some(target.with_file_name(format!("{}_{}", stack_name, filename)))
 }
\ No newline at end of file
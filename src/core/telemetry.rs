@@ -0,0 +1,244 @@
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+use anyhow::{Result, Context};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::cache;
+use super::policy::Policy;
+use crate::config::load_config;
+use crate::error;
+
+/// Recorded commands, one JSON object per line
+const RECORDS_FILE: &str = "telemetry.jsonl";
+
+/// Timestamp of the newest record uploaded to the organization's endpoint
+const UPLOADED_FILE: &str = "telemetry-uploaded";
+
+/// The oldest records are dropped beyond this many
+const MAX_RECORDS: usize = 1000;
+
+/// One finished command; nothing in it identifies the user, the machine or the project
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Record {
+    pub timestamp: DateTime<Utc>,
+    /// Subcommand as typed without its arguments, e.g. "ci verify"; "default" for plain `stacks`
+    pub command: String,
+    pub duration_ms: u64,
+    /// Error kind of a failure, e.g. "network"; None when the command succeeded
+    pub error: Option<String>,
+    pub version: String,
+    pub os: String,
+}
+
+impl Record {
+    pub fn new(command: &str, duration: Duration, result: &Result<()>) -> Self {
+        Self {
+            timestamp: Utc::now(),
+            command: command.to_string(),
+            duration_ms: duration.as_millis() as u64,
+            error: result.as_ref().err().map(|error| error::classify(error).as_str().to_string()),
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            os: std::env::consts::OS.to_string(),
+        }
+    }
+}
+
+/// How often one command ran and failed
+#[derive(Debug, Clone, PartialEq)]
+pub struct CommandStats {
+    pub command: String,
+    pub runs: usize,
+    pub failures: usize,
+    pub total_ms: u64,
+}
+
+impl CommandStats {
+    pub fn failure_rate(&self) -> f64 {
+        self.failures as f64 / self.runs as f64
+    }
+
+    pub fn average(&self) -> Duration {
+        Duration::from_millis(self.total_ms / self.runs as u64)
+    }
+}
+
+pub fn records_path() -> Result<PathBuf> {
+    Ok(cache::cache_root()?.join(RECORDS_FILE))
+}
+
+fn uploaded_path() -> Result<PathBuf> {
+    Ok(cache::cache_root()?.join(UPLOADED_FILE))
+}
+
+/// Records in `path`, oldest first; lines that don't parse are skipped
+pub fn load(path: &Path) -> Vec<Record> {
+    std::fs::read_to_string(path)
+        .unwrap_or_default()
+        .lines()
+        .filter_map(|line| serde_json::from_str(line).ok())
+        .collect()
+}
+
+/// Add `record` to the file at `path`, keeping the newest MAX_RECORDS
+pub fn append(path: &Path, record: Record) -> Result<()> {
+    let mut records = load(path);
+    records.push(record);
+    let skip = records.len().saturating_sub(MAX_RECORDS);
+
+    let mut content = String::new();
+    for record in &records[skip..] {
+        content.push_str(&serde_json::to_string(record)?);
+        content.push('\n');
+    }
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    std::fs::write(path, content).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Runs and failures per command, most failures first
+pub fn summarize(records: &[Record]) -> Vec<CommandStats> {
+    let mut stats: Vec<CommandStats> = Vec::new();
+    for record in records {
+        let index = match stats.iter().position(|stats| stats.command == record.command) {
+            Some(index) => index,
+            None => {
+                stats.push(CommandStats { command: record.command.clone(), runs: 0, failures: 0, total_ms: 0 });
+                stats.len() - 1
+            }
+        };
+        let entry = &mut stats[index];
+        entry.runs += 1;
+        entry.failures += usize::from(record.error.is_some());
+        entry.total_ms += record.duration_ms;
+    }
+    stats.sort_by(|a, b| b.failures.cmp(&a.failures).then(b.runs.cmp(&a.runs)).then(a.command.cmp(&b.command)));
+    stats
+}
+
+/// Records not uploaded yet, if the oldest of them waited a day; uploads go out at most daily
+fn due_for_upload(records: &[Record], uploaded_through: Option<DateTime<Utc>>, now: DateTime<Utc>) -> Vec<Record> {
+    let pending: Vec<Record> = records
+        .iter()
+        .filter(|record| uploaded_through.is_none_or(|through| record.timestamp > through))
+        .cloned()
+        .collect();
+    match pending.first() {
+        Some(oldest) if now.signed_duration_since(oldest.timestamp) >= chrono::Duration::days(1) => pending,
+        _ => Vec::new(),
+    }
+}
+
+/// Record a finished command when telemetry is turned on, then upload pending records if the
+/// organization policy names an endpoint
+///
+/// Best-effort: failures are logged and never fail the command.
+pub async fn command_finished(command: &str, started: Instant, result: &Result<()>) {
+    if !load_config().is_ok_and(|config| config.telemetry) {
+        return;
+    }
+    if let Err(error) = record_and_upload(Record::new(command, started.elapsed(), result)).await {
+        tracing::debug!("telemetry failed: {:#}", error);
+    }
+}
+
+async fn record_and_upload(record: Record) -> Result<()> {
+    let path = records_path()?;
+    append(&path, record)?;
+
+    let endpoint = match Policy::current().await?.and_then(|policy| policy.telemetry.endpoint.as_ref()) {
+        Some(endpoint) => endpoint,
+        None => return Ok(()),
+    };
+    let uploaded_through = std::fs::read_to_string(uploaded_path()?)
+        .ok()
+        .and_then(|content| DateTime::parse_from_rfc3339(content.trim()).ok())
+        .map(|time| time.with_timezone(&Utc));
+    let pending = due_for_upload(&load(&path), uploaded_through, Utc::now());
+    let newest = match pending.last() {
+        Some(record) => record.timestamp,
+        None => return Ok(()),
+    };
+
+    reqwest::Client::new()
+        .post(endpoint)
+        .json(&serde_json::json!({ "records": pending }))
+        .timeout(Duration::from_secs(5))
+        .send()
+        .await?
+        .error_for_status()?;
+    std::fs::write(uploaded_path()?, newest.to_rfc3339()).context("Failed to record the telemetry upload")
+}
+
+/// Forget everything recorded on this machine
+pub fn clear() -> Result<()> {
+    for path in [records_path()?, uploaded_path()?] {
+        if path.exists() {
+            std::fs::remove_file(&path).with_context(|| format!("Failed to remove {}", path.display()))?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::StacksError;
+
+    fn record(command: &str, hours_ago: i64, error: Option<&str>) -> Record {
+        Record {
+            timestamp: Utc::now() - chrono::Duration::hours(hours_ago),
+            command: command.to_string(),
+            duration_ms: 1000,
+            error: error.map(str::to_string),
+            version: "0.1.0".to_string(),
+            os: "linux".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_record_keeps_only_the_error_kind() {
+        let failed: Result<()> = Err(StacksError::network("GET https://api.github.com/repos/acme/private failed").into());
+        let record = Record::new("pull", Duration::from_millis(2500), &failed);
+        assert_eq!(record.error.as_deref(), Some("network"));
+        assert_eq!(record.duration_ms, 2500);
+        assert!(!serde_json::to_string(&record).unwrap().contains("acme"));
+
+        assert_eq!(Record::new("status", Duration::ZERO, &Ok(())).error, None);
+    }
+
+    #[test]
+    fn test_append_and_summarize() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let path = temp_dir.path().join(RECORDS_FILE);
+        append(&path, record("status", 3, None)).unwrap();
+        append(&path, record("pull", 2, Some("git"))).unwrap();
+        append(&path, record("pull", 1, None)).unwrap();
+        std::fs::write(&path, format!("{}not json\n", std::fs::read_to_string(&path).unwrap())).unwrap();
+
+        let records = load(&path);
+        assert_eq!(records.len(), 3);
+        let stats = summarize(&records);
+        assert_eq!(stats[0].command, "pull");
+        assert_eq!((stats[0].runs, stats[0].failures), (2, 1));
+        assert_eq!(stats[0].failure_rate(), 0.5);
+        assert_eq!(stats[1].average(), Duration::from_secs(1));
+
+        for _ in 0..MAX_RECORDS {
+            append(&path, record("status", 0, None)).unwrap();
+        }
+        assert_eq!(load(&path).len(), MAX_RECORDS);
+    }
+
+    #[test]
+    fn test_due_for_upload() {
+        let now = Utc::now();
+        let records = vec![record("status", 30, None), record("pull", 2, None)];
+        assert_eq!(due_for_upload(&records, None, now).len(), 2);
+        assert_eq!(due_for_upload(&records[1..], None, now), Vec::new());
+
+        let uploaded_through = Some(records[0].timestamp);
+        assert_eq!(due_for_upload(&records, uploaded_through, now), Vec::new());
+    }
+}
@@ -0,0 +1,148 @@
+use std::path::{Path, PathBuf};
+use anyhow::{Result, Context};
+use walkdir::WalkDir;
+
+use super::git::Repo;
+use crate::utils::process::Process;
+
+/// Transcripts live under the project root, one directory per task
+const LOGS_DIR: &str = ".stacks/logs";
+
+pub fn logs_root(project_root: &Path) -> PathBuf {
+    project_root.join(LOGS_DIR)
+}
+
+pub fn task_dir(project_root: &Path, task: &str) -> PathBuf {
+    logs_root(project_root).join(task)
+}
+
+/// Create a task's transcript directory, keeping the logs directory out of git
+pub fn create_task_dir(project_root: &Path, task: &str) -> Result<PathBuf> {
+    let dir = task_dir(project_root, task);
+    std::fs::create_dir_all(&dir)
+        .with_context(|| format!("Failed to create {}", dir.display()))?;
+    if let Ok(repo) = Repo::discover(project_root) {
+        // Transcripts are personal and can be large; keep them out of commits
+        if let Err(error) = repo.exclude(&format!("/{}/", LOGS_DIR)) {
+            tracing::debug!("failed to exclude transcripts from git: {}", error);
+        }
+    }
+    Ok(dir)
+}
+
+/// Pipe the output of every tmux pane running in `worktree` to `.stacks/logs/<task>/pane-<id>.log`
+///
+/// Returns the number of panes now recorded. Panes that already pipe somewhere are left alone.
+pub fn start_recording(project_root: &Path, task: &str, worktree: &Path) -> Result<usize> {
+    let dir = create_task_dir(project_root, task)?;
+
+    let worktree = worktree.canonicalize().unwrap_or_else(|_| worktree.to_path_buf());
+    let output = Process::tmux()
+        .args(["list-panes", "-a", "-F", "#{pane_id}\t#{pane_current_path}\t#{pane_pipe}"])
+        .read_only()
+        .run()
+        .context("Failed to list tmux panes")?;
+
+    let mut recorded = 0;
+    for (pane_id, path, piped) in output.stdout.lines().filter_map(parse_pane_line) {
+        if piped || Path::new(path) != worktree {
+            continue;
+        }
+        let log_file = dir.join(format!("pane-{}.log", pane_id.trim_start_matches('%')));
+        Process::tmux()
+            .args(["pipe-pane", "-o", "-t", pane_id])
+            .arg(format!("cat >> '{}'", log_file.display()))
+            .run()
+            .with_context(|| format!("Failed to record tmux pane {}", pane_id))?;
+        recorded += 1;
+    }
+    Ok(recorded)
+}
+
+fn parse_pane_line(line: &str) -> Option<(&str, &str, bool)> {
+    let mut fields = line.split('\t');
+    let pane_id = fields.next()?;
+    let path = fields.next()?;
+    let piped = fields.next() == Some("1");
+    Some((pane_id, path, piped))
+}
+
+/// Tasks with recorded transcripts, sorted by name
+pub fn tasks(project_root: &Path) -> Vec<String> {
+    let mut tasks: Vec<String> = WalkDir::new(logs_root(project_root))
+        .min_depth(1)
+        .max_depth(1)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_dir())
+        .map(|e| e.file_name().to_string_lossy().to_string())
+        .collect();
+    tasks.sort();
+    tasks
+}
+
+/// Transcript files of a task, sorted by name
+pub fn transcript_files(project_root: &Path, task: &str) -> Vec<PathBuf> {
+    let mut files: Vec<PathBuf> = WalkDir::new(task_dir(project_root, task))
+        .min_depth(1)
+        .max_depth(1)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.file_type().is_file())
+        .map(|e| e.path().to_path_buf())
+        .collect();
+    files.sort();
+    files
+}
+
+/// Drop terminal escape sequences and carriage returns so transcripts read as plain text
+pub fn strip_ansi(line: &str) -> String {
+    let mut plain = String::with_capacity(line.len());
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '\x1b' => match chars.next() {
+                // CSI: parameters until a final byte in @..~
+                Some('[') => {
+                    for next in chars.by_ref() {
+                        if ('@'..='~').contains(&next) {
+                            break;
+                        }
+                    }
+                }
+                // OSC: until BEL or ST
+                Some(']') => {
+                    while let Some(next) = chars.next() {
+                        if next == '\x07' || (next == '\x1b' && chars.peek() == Some(&'\\')) {
+                            chars.next_if_eq(&'\\');
+                            break;
+                        }
+                    }
+                }
+                _ => {}
+            },
+            '\r' => {}
+            _ => plain.push(c),
+        }
+    }
+    plain
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strip_ansi() {
+        assert_eq!(strip_ansi("\x1b[1;32m✓ tests pass\x1b[0m\r"), "✓ tests pass");
+        assert_eq!(strip_ansi("\x1b]0;claude\x07Editing src/main.rs"), "Editing src/main.rs");
+        assert_eq!(strip_ansi("plain"), "plain");
+    }
+
+    #[test]
+    fn test_parse_pane_line() {
+        assert_eq!(parse_pane_line("%3\t/work/auth\t0"), Some(("%3", "/work/auth", false)));
+        assert_eq!(parse_pane_line("%4\t/work/auth\t1"), Some(("%4", "/work/auth", true)));
+        assert_eq!(parse_pane_line("garbage"), None);
+    }
+}
@@ -0,0 +1,146 @@
+use std::path::Path;
+use anyhow::{Result, Context};
+use chrono::{DateTime, Duration, Utc};
+use walkdir::WalkDir;
+
+use super::lockfile::resolve_stack_metadata;
+use super::remote_stack_manager::{GitHubRelease, RemoteStackManager};
+use super::upstream::{check_upstream, UpstreamStatus};
+
+const STAMP_FILE: &str = "last-update-check";
+const TOOL_OWNER: &str = "csaben";
+const TOOL_REPO: &str = "claude-code-stacks";
+
+/// Updates found by a scheduled check
+#[derive(Debug, Default)]
+pub struct UpdateNotice {
+    pub tool_release: Option<GitHubRelease>,
+    pub outdated_stacks: Vec<(String, UpstreamStatus)>,
+}
+
+impl UpdateNotice {
+    pub fn is_empty(&self) -> bool {
+        self.tool_release.is_none() && self.outdated_stacks.is_empty()
+    }
+
+    /// Print the notice to stderr so it never mixes with command output
+    pub fn print(&self) {
+        if self.is_empty() {
+            return;
+        }
+
+        eoutln!();
+        if let Some(release) = &self.tool_release {
+            eoutln!(
+                "💡 stacks {} is available (installed: {}) - {}",
+                release.tag_name.trim_start_matches('v'),
+                env!("CARGO_PKG_VERSION"),
+                release.html_url
+            );
+        }
+        for (name, status) in &self.outdated_stacks {
+            eoutln!("💡 Stack {} is {}. Run 'stacks pull {}' to update.", name, status.describe(), name);
+        }
+        eoutln!("   (disable these checks with 'stacks config set update-checks false')");
+    }
+}
+
+/// Run the update check if the last one was more than a day ago
+///
+/// The timestamp is recorded before checking, so a failed or slow check is not retried until tomorrow.
+pub async fn check_if_due() -> Result<Option<UpdateNotice>> {
+    let manager = RemoteStackManager::new()?;
+    let stamp_path = manager.cache_dir().join(STAMP_FILE);
+
+    if !is_due(&stamp_path, Utc::now()) {
+        return Ok(None);
+    }
+    std::fs::write(&stamp_path, Utc::now().to_rfc3339())
+        .context("Failed to record update check time")?;
+
+    let mut notice = UpdateNotice::default();
+
+    if let Ok(Some(release)) = manager.latest_release(TOOL_OWNER, TOOL_REPO).await {
+        if is_newer_version(env!("CARGO_PKG_VERSION"), &release.tag_name) {
+            notice.tool_release = Some(release);
+        }
+    }
+
+    let stacks_dir = std::env::current_dir()?.join("stacks");
+    if stacks_dir.exists() {
+        for entry in WalkDir::new(&stacks_dir)
+            .min_depth(1)
+            .max_depth(1)
+            .into_iter()
+            .filter_map(|e| e.ok())
+            .filter(|e| e.file_type().is_dir())
+        {
+            let stack_name = entry.file_name().to_string_lossy().to_string();
+            let metadata = resolve_stack_metadata(&stack_name);
+            if let Ok(status) = check_upstream(&manager, &metadata).await {
+                if status.is_outdated() {
+                    notice.outdated_stacks.push((stack_name, status));
+                }
+            }
+        }
+    }
+
+    Ok(Some(notice))
+}
+
+fn is_due(stamp_path: &Path, now: DateTime<Utc>) -> bool {
+    let last_check = std::fs::read_to_string(stamp_path)
+        .ok()
+        .and_then(|content| DateTime::parse_from_rfc3339(content.trim()).ok());
+
+    match last_check {
+        Some(last_check) => now.signed_duration_since(last_check) >= Duration::days(1),
+        None => true,
+    }
+}
+
+/// Whether `latest` (e.g. "v0.2.0") is a newer `major.minor.patch` than `current`
+fn is_newer_version(current: &str, latest: &str) -> bool {
+    fn parse(version: &str) -> Option<Vec<u64>> {
+        version.trim()
+            .trim_start_matches('v')
+            .split('.')
+            .map(|part| part.parse().ok())
+            .collect()
+    }
+
+    match (parse(current), parse(latest)) {
+        (Some(current), Some(latest)) => latest > current,
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_is_newer_version() {
+        assert!(is_newer_version("0.1.0", "v0.2.0"));
+        assert!(is_newer_version("0.1.9", "0.1.10"));
+        assert!(!is_newer_version("0.2.0", "v0.2.0"));
+        assert!(!is_newer_version("0.2.0", "v0.1.5"));
+        assert!(!is_newer_version("0.1.0", "nightly"));
+    }
+
+    #[test]
+    fn test_is_due_once_per_day() {
+        let temp_dir = TempDir::new().unwrap();
+        let stamp_path = temp_dir.path().join(STAMP_FILE);
+        let now = Utc::now();
+
+        assert!(is_due(&stamp_path, now));
+
+        std::fs::write(&stamp_path, (now - Duration::hours(2)).to_rfc3339()).unwrap();
+        assert!(!is_due(&stamp_path, now));
+
+        std::fs::write(&stamp_path, (now - Duration::hours(25)).to_rfc3339()).unwrap();
+        assert!(is_due(&stamp_path, now));
+    }
+}
@@ -0,0 +1,331 @@
+use std::path::Path;
+use anyhow::Result;
+
+use super::git::{self, Repo};
+use super::remote_stack_manager::{RemoteStackManager, StackMetadata, parse_github_repo};
+
+/// How an installed subtree stack compares to its upstream branch
+#[derive(Debug, Clone, Default)]
+pub struct UpstreamStatus {
+    pub installed_commit: Option<String>,
+    pub remote_commit: Option<String>,
+    pub commits_behind: Option<usize>,
+    pub latest_message: Option<String>,
+    /// The stack is a directory of its repository, so only `stacks pull` can tell if it's behind
+    pub from_subdirectory: bool,
+}
+
+impl UpstreamStatus {
+    pub fn is_outdated(&self) -> bool {
+        match (&self.installed_commit, &self.remote_commit) {
+            (Some(installed), Some(remote)) => installed != remote && self.commits_behind != Some(0),
+            _ => false,
+        }
+    }
+
+    /// Short human-readable description, e.g. "3 commits behind upstream"
+    pub fn describe(&self) -> String {
+        match (&self.installed_commit, &self.remote_commit) {
+            _ if self.from_subdirectory => "unknown (directory of its repository; run 'stacks pull' to update)".to_string(),
+            (None, _) => "unknown (no subtree commit recorded)".to_string(),
+            (_, None) => "unknown (upstream unreachable)".to_string(),
+            _ if !self.is_outdated() => "up to date".to_string(),
+            _ => match self.commits_behind {
+                Some(1) => "1 commit behind upstream".to_string(),
+                Some(count) => format!("{} commits behind upstream", count),
+                None => "behind upstream".to_string(),
+            },
+        }
+    }
+}
+
+/// Most commits searched for a stack's squash commit on paths shell prompts and status lines
+/// redraw from; a stack added further back than that reports no local commits there
+pub const PROMPT_HISTORY_DEPTH: usize = 1000;
+
+/// Upstream commit the stack subtree was last added or merged from
+///
+/// `git subtree` records it as a `git-subtree-split:` trailer on its squash commits.
+pub fn installed_commit(stack_name: &str) -> Result<Option<String>> {
+    installed_commit_in(Path::new("."), stack_name)
+}
+
+/// Like `installed_commit`, for the repository checked out at `root` (e.g. another worktree)
+pub fn installed_commit_in(root: &Path, stack_name: &str) -> Result<Option<String>> {
+    Ok(subtree_squash_commit(root, stack_name, usize::MAX)?
+        .and_then(|(_, message)| parse_subtree_split(&message)))
+}
+
+/// Commits touching the stack since it was last added or pulled, i.e. local changes to it
+///
+/// The squash commit is looked for in the latest `depth` commits only.
+pub fn local_commits_in(root: &Path, stack_name: &str, depth: usize) -> Result<usize> {
+    let squash_commit = match subtree_squash_commit(root, stack_name, depth)? {
+        Some((sha, _)) => sha,
+        None => return Ok(0),
+    };
+
+    let repo = match Repo::discover(root) {
+        Ok(repo) => repo,
+        Err(_) => return Ok(0),
+    };
+    Ok(repo.count_commits_touching(&squash_commit, &format!("stacks/{}", stack_name)).unwrap_or(0))
+}
+
+/// Latest squash commit `git subtree` created for the stack within `depth` commits, with its message
+fn subtree_squash_commit(root: &Path, stack_name: &str, depth: usize) -> Result<Option<(String, String)>> {
+    let prefix = format!("stacks/{}", stack_name);
+    let repo = match Repo::discover(root) {
+        Ok(repo) => repo,
+        Err(_) => return Ok(None),
+    };
+    Ok(repo.find_recent_commit_message(|line| {
+        line.trim()
+            .strip_prefix("git-subtree-dir:")
+            .is_some_and(|dir| dir.trim().trim_end_matches('/') == prefix)
+    }, depth)?)
+}
+
+fn parse_subtree_split(message: &str) -> Option<String> {
+    message
+        .lines()
+        .find_map(|line| line.trim().strip_prefix("git-subtree-split:"))
+        .map(|sha| sha.trim().to_string())
+        .filter(|sha| !sha.is_empty())
+}
+
+/// What a pull would bring into a stack: upstream commits and CHANGELOG.md additions
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct IncomingChanges {
+    /// Short sha and subject, newest first
+    pub commits: Vec<(String, String)>,
+    /// Lines added to the stack's CHANGELOG.md
+    pub changelog: Vec<String>,
+    /// The installed commit isn't in the fetched history, so `commits` are only the latest ones
+    pub partial: bool,
+}
+
+/// Most commits listed when the installed commit isn't part of the upstream history
+const RECENT_COMMITS: &str = "-10";
+
+/// Changes between the installed commit and a fetched upstream ref, read from the repository at `root`
+pub fn incoming_changes(root: &Path, installed: Option<&str>, upstream_ref: &str) -> Result<IncomingChanges> {
+    // A stack installed from a commit the upstream no longer has (e.g. after a force push) can
+    // only be compared against the upstream's latest commits
+    let known = installed.filter(|commit| git::run(root, &["merge-base", "--is-ancestor", commit, upstream_ref]).is_ok());
+    let range = known.map(|commit| format!("{}..{}", commit, upstream_ref));
+    let log_args = match &range {
+        Some(range) => vec!["log", "--format=%h%x09%s", range.as_str()],
+        None => vec!["log", "--format=%h%x09%s", RECENT_COMMITS, upstream_ref],
+    };
+    let commits = git::run(root, &log_args)?
+        .lines()
+        .filter_map(|line| line.split_once('\t'))
+        .map(|(sha, subject)| (sha.to_string(), subject.to_string()))
+        .collect();
+
+    let changelog = match known {
+        Some(commit) => git::run(root, &["diff", "--no-color", commit, upstream_ref, "--", "CHANGELOG.md"])
+            .map(|diff| changelog_additions(&diff))
+            .unwrap_or_default(),
+        None => Vec::new(),
+    };
+
+    Ok(IncomingChanges { commits, changelog, partial: known.is_none() })
+}
+
+/// Lines a unified diff adds, without the `+` markers and blank lines
+fn changelog_additions(diff: &str) -> Vec<String> {
+    diff.lines()
+        .filter(|line| !line.starts_with("+++"))
+        .filter_map(|line| line.strip_prefix('+'))
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.to_string())
+        .collect()
+}
+
+/// Current head commit of a remote branch, like `git ls-remote`
+pub fn remote_head(repo_url: &str, branch: &str) -> Result<Option<String>> {
+    remote_commit(repo_url, &format!("refs/heads/{}", branch))
+}
+
+/// Commit a remote ref (e.g. `refs/tags/v1.2.0`) points to; annotated tags are peeled
+pub fn remote_commit(repo_url: &str, ref_name: &str) -> Result<Option<String>> {
+    let refs = match git::list_remote(repo_url) {
+        Ok(refs) => refs,
+        Err(_) => return Ok(None),
+    };
+
+    let peeled = format!("{}^{{}}", ref_name);
+    let commit = |name: &str| refs.iter()
+        .find(|remote_ref| remote_ref.name == name)
+        .map(|remote_ref| remote_ref.commit.clone());
+    Ok(commit(&peeled).or_else(|| commit(ref_name)))
+}
+
+/// Default branch of a remote repository (where its HEAD points)
+pub fn remote_default_branch(repo_url: &str) -> Result<Option<String>> {
+    let refs = match git::list_remote(repo_url) {
+        Ok(refs) => refs,
+        Err(_) => return Ok(None),
+    };
+
+    Ok(default_branch(&refs))
+}
+
+fn default_branch(refs: &[git::RemoteRef]) -> Option<String> {
+    refs.iter()
+        .find(|remote_ref| remote_ref.name == "HEAD")
+        .and_then(|remote_ref| remote_ref.symref_target.as_deref())
+        .and_then(|target| target.strip_prefix("refs/heads/").map(str::to_string))
+}
+
+/// Whether `name` is a tag, and not a branch, of the remote repository
+pub fn is_remote_tag(repo_url: &str, name: &str) -> bool {
+    let refs = match git::list_remote(repo_url) {
+        Ok(refs) => refs,
+        Err(_) => return false,
+    };
+    let exists = |ref_name: String| refs.iter().any(|remote_ref| remote_ref.name == ref_name);
+    exists(format!("refs/tags/{}", name)) && !exists(format!("refs/heads/{}", name))
+}
+
+/// The remote's default branch when the remote reports that `branch` doesn't exist
+///
+/// None when the branch exists, or when the remote can't be listed to tell.
+pub fn missing_branch_fallback(repo_url: &str, branch: &str) -> Option<String> {
+    let refs = git::list_remote(repo_url).ok()?;
+    let name = format!("refs/heads/{}", branch);
+    if refs.iter().any(|remote_ref| remote_ref.name == name) {
+        return None;
+    }
+    default_branch(&refs).filter(|default| default != branch)
+}
+
+/// Compare an installed stack against its upstream branch without modifying the repository
+pub async fn check_upstream(manager: &RemoteStackManager, metadata: &StackMetadata) -> Result<UpstreamStatus> {
+    compare_upstream(manager, metadata, installed_commit(&metadata.stack_name)?).await
+}
+
+/// Compare a known installed commit against the stack's upstream branch
+pub async fn compare_upstream(
+    manager: &RemoteStackManager,
+    metadata: &StackMetadata,
+    installed_commit: Option<String>,
+) -> Result<UpstreamStatus> {
+    // The subtree of a stack that is a directory of its repository holds split commits, which
+    // only a fetch and split (as in `stacks pull`) can compare
+    if metadata.source_path.is_some() {
+        return Ok(UpstreamStatus { installed_commit, from_subdirectory: true, ..Default::default() });
+    }
+
+    let mut status = UpstreamStatus {
+        installed_commit,
+        remote_commit: remote_commit(&metadata.source_repo, &metadata.source_ref())?,
+        ..Default::default()
+    };
+
+    let (installed, remote) = match (&status.installed_commit, &status.remote_commit) {
+        (Some(installed), Some(remote)) if installed != remote => (installed.clone(), remote.clone()),
+        (Some(_), Some(_)) => {
+            status.commits_behind = Some(0);
+            return Ok(status);
+        }
+        _ => return Ok(status),
+    };
+
+    // Commit counts and messages come from the GitHub API; other hosts only get the head comparison
+    if let Some((owner, repo)) = parse_github_repo(&metadata.source_repo) {
+        if let Ok(comparison) = manager.compare_commits(&owner, &repo, &installed, &remote).await {
+            status.commits_behind = Some(comparison.ahead_by);
+            status.latest_message = comparison.commits
+                .last()
+                .and_then(|commit| commit.commit.message.lines().next())
+                .map(|line| line.to_string());
+        }
+    }
+
+    Ok(status)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_subtree_split() {
+        let message = "Squashed 'stacks/ts-lint-stack/' content from commit 1a2b3c4\n\ngit-subtree-dir: stacks/ts-lint-stack\ngit-subtree-split: 1a2b3c4d5e6f\n";
+        assert_eq!(parse_subtree_split(message), Some("1a2b3c4d5e6f".to_string()));
+        assert_eq!(parse_subtree_split("Regular commit message"), None);
+    }
+
+    #[test]
+    fn test_remote_head_of_local_repository() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let root = temp_dir.path();
+        git::run(root, &["init", "-q", "-b", "main"]).unwrap();
+        git::run(root, &["-c", "user.name=test", "-c", "user.email=test@example.com", "commit", "-q", "--allow-empty", "-m", "initial"]).unwrap();
+
+        let url = root.to_str().unwrap();
+        let head = Repo::discover(root).unwrap().head_commit();
+        assert_eq!(remote_head(url, "main").unwrap(), head);
+        assert_eq!(remote_head(url, "missing").unwrap(), None);
+        assert_eq!(remote_head(&root.join("nowhere").display().to_string(), "main").unwrap(), None);
+
+        git::run(root, &["tag", "v1.0.0"]).unwrap();
+        assert_eq!(remote_commit(url, "refs/tags/v1.0.0").unwrap(), head);
+        assert!(is_remote_tag(url, "v1.0.0"));
+        assert!(!is_remote_tag(url, "main"));
+        assert_eq!(missing_branch_fallback(url, "main"), None);
+        assert_eq!(missing_branch_fallback(url, "master"), Some("main".to_string()));
+    }
+
+    #[test]
+    fn test_incoming_changes() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let root = temp_dir.path();
+        let commit = |message: &str| {
+            git::run(root, &["add", "-A"]).unwrap();
+            git::run(root, &["-c", "user.name=test", "-c", "user.email=test@example.com", "commit", "-q", "-m", message]).unwrap();
+            Repo::discover(root).unwrap().head_commit().unwrap()
+        };
+        git::run(root, &["init", "-q", "-b", "main"]).unwrap();
+        std::fs::write(root.join("CHANGELOG.md"), "# Changelog\n\n## 1.0\n- First release\n").unwrap();
+        let installed = commit("Release 1.0");
+        std::fs::write(root.join("CLAUDE.md"), "Lint everything\n").unwrap();
+        commit("Lint everything");
+        std::fs::write(root.join("CHANGELOG.md"), "# Changelog\n\n## 1.1\n- Stricter linting\n\n## 1.0\n- First release\n").unwrap();
+        commit("Release 1.1");
+
+        let changes = incoming_changes(root, Some(&installed), "HEAD").unwrap();
+        let subjects: Vec<&str> = changes.commits.iter().map(|(_, subject)| subject.as_str()).collect();
+        assert_eq!(subjects, vec!["Release 1.1", "Lint everything"]);
+        assert_eq!(changes.changelog, vec!["## 1.1", "- Stricter linting"]);
+        assert!(!changes.partial);
+
+        let unknown = incoming_changes(root, Some("0123456789abcdef0123456789abcdef01234567"), "HEAD").unwrap();
+        assert_eq!(unknown.commits.len(), 3);
+        assert!(unknown.changelog.is_empty());
+        assert!(unknown.partial);
+    }
+
+    #[test]
+    fn test_describe_upstream_status() {
+        let status = UpstreamStatus {
+            installed_commit: Some("abc".to_string()),
+            remote_commit: Some("def".to_string()),
+            commits_behind: Some(3),
+            ..Default::default()
+        };
+        assert!(status.is_outdated());
+        assert_eq!(status.describe(), "3 commits behind upstream");
+
+        let current = UpstreamStatus {
+            installed_commit: Some("abc".to_string()),
+            remote_commit: Some("abc".to_string()),
+            ..Default::default()
+        };
+        assert!(!current.is_outdated());
+        assert_eq!(current.describe(), "up to date");
+    }
+}
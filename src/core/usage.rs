@@ -0,0 +1,199 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use walkdir::WalkDir;
+
+/// Token counts and cost of the claude sessions run in one directory
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Usage {
+    pub sessions: usize,
+    pub messages: usize,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cache_creation_tokens: u64,
+    pub cache_read_tokens: u64,
+    pub cost_usd: f64,
+}
+
+impl Usage {
+    pub fn total_tokens(&self) -> u64 {
+        self.input_tokens + self.output_tokens + self.cache_creation_tokens + self.cache_read_tokens
+    }
+
+    pub fn add(&mut self, other: &Usage) {
+        self.sessions += other.sessions;
+        self.messages += other.messages;
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+        self.cache_creation_tokens += other.cache_creation_tokens;
+        self.cache_read_tokens += other.cache_read_tokens;
+        self.cost_usd += other.cost_usd;
+    }
+}
+
+/// List prices in USD per million tokens: input, output, cache write, cache read
+fn pricing(model: &str) -> (f64, f64, f64, f64) {
+    if model.contains("opus") {
+        (15.0, 75.0, 18.75, 1.5)
+    } else if model.contains("haiku") {
+        (0.8, 4.0, 1.0, 0.08)
+    } else {
+        (3.0, 15.0, 3.75, 0.3)
+    }
+}
+
+/// Where Claude Code keeps its session transcripts, one directory per working directory
+pub fn claude_projects_dir() -> Option<PathBuf> {
+    match std::env::var_os("CLAUDE_CONFIG_DIR") {
+        Some(dir) => Some(PathBuf::from(dir).join("projects")),
+        None => dirs::home_dir().map(|home| home.join(".claude").join("projects")),
+    }
+}
+
+/// Claude Code names a project's transcript directory after its path with every
+/// non-alphanumeric character replaced by '-'
+pub fn project_dir_name(dir: &Path) -> String {
+    dir.to_string_lossy()
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '-' })
+        .collect()
+}
+
+/// Usage of every claude session started in `dir`, counting only messages after `since`
+pub fn usage_for_dir(dir: &Path, since: Option<DateTime<Utc>>) -> Usage {
+    let mut usage = Usage::default();
+    let projects = match claude_projects_dir() {
+        Some(projects) => projects,
+        None => return usage,
+    };
+    let dir = dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf());
+
+    let transcripts = WalkDir::new(projects.join(project_dir_name(&dir)))
+        .min_depth(1)
+        .max_depth(1)
+        .into_iter()
+        .filter_map(|e| e.ok())
+        .filter(|e| e.path().extension().map(|ext| ext == "jsonl").unwrap_or(false));
+    for entry in transcripts {
+        match std::fs::read_to_string(entry.path()) {
+            Ok(content) => {
+                let session = parse_transcript(&content, since);
+                if session.messages > 0 {
+                    usage.add(&Usage { sessions: 1, ..session });
+                }
+            }
+            Err(error) => tracing::debug!("failed to read {}: {}", entry.path().display(), error),
+        }
+    }
+    usage
+}
+
+/// Sum the usage of the assistant messages in one session transcript
+///
+/// Streaming writes one line per content block, each repeating the message's usage, so
+/// messages are counted once per id. A recorded `costUSD` wins over the price estimate.
+pub fn parse_transcript(content: &str, since: Option<DateTime<Utc>>) -> Usage {
+    let mut usage = Usage::default();
+    let mut seen = HashSet::new();
+
+    for line in content.lines() {
+        let entry: Value = match serde_json::from_str(line) {
+            Ok(entry) => entry,
+            Err(_) => continue,
+        };
+        if entry["type"] != "assistant" {
+            continue;
+        }
+        let message = &entry["message"];
+        let tokens = &message["usage"];
+        if !tokens.is_object() {
+            continue;
+        }
+        if let Some(id) = message["id"].as_str() {
+            if !seen.insert(id.to_string()) {
+                continue;
+            }
+        }
+        if let Some(since) = since {
+            let at = entry["timestamp"].as_str().and_then(|at| DateTime::parse_from_rfc3339(at).ok());
+            if at.map(|at| at < since).unwrap_or(false) {
+                continue;
+            }
+        }
+
+        let count = |key: &str| tokens[key].as_u64().unwrap_or(0);
+        let input = count("input_tokens");
+        let output = count("output_tokens");
+        let cache_creation = count("cache_creation_input_tokens");
+        let cache_read = count("cache_read_input_tokens");
+
+        let cost = match entry["costUSD"].as_f64() {
+            Some(cost) => cost,
+            None => {
+                let (input_price, output_price, write_price, read_price) =
+                    pricing(message["model"].as_str().unwrap_or(""));
+                (input as f64 * input_price
+                    + output as f64 * output_price
+                    + cache_creation as f64 * write_price
+                    + cache_read as f64 * read_price)
+                    / 1_000_000.0
+            }
+        };
+
+        usage.messages += 1;
+        usage.input_tokens += input;
+        usage.output_tokens += output;
+        usage.cache_creation_tokens += cache_creation;
+        usage.cache_read_tokens += cache_read;
+        usage.cost_usd += cost;
+    }
+    usage
+}
+
+/// 1234567 -> "1.2M"
+pub fn format_tokens(tokens: u64) -> String {
+    match tokens {
+        0..=999 => tokens.to_string(),
+        1_000..=999_999 => format!("{:.1}k", tokens as f64 / 1_000.0),
+        _ => format!("{:.1}M", tokens as f64 / 1_000_000.0),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_project_dir_name() {
+        assert_eq!(project_dir_name(Path::new("/home/me/work/app.auth")), "-home-me-work-app-auth");
+    }
+
+    #[test]
+    fn test_parse_transcript_dedupes_and_prices() {
+        let content = [
+            r#"{"type":"user","message":{"content":"hi"}}"#,
+            r#"{"type":"assistant","timestamp":"2026-01-02T10:00:00Z","message":{"id":"m1","model":"claude-sonnet-4","usage":{"input_tokens":1000000,"output_tokens":100000}}}"#,
+            r#"{"type":"assistant","timestamp":"2026-01-02T10:00:00Z","message":{"id":"m1","model":"claude-sonnet-4","usage":{"input_tokens":1000000,"output_tokens":100000}}}"#,
+            r#"{"type":"assistant","timestamp":"2026-01-01T10:00:00Z","costUSD":0.5,"message":{"id":"m2","usage":{"input_tokens":10,"cache_read_input_tokens":20}}}"#,
+            "not json",
+        ]
+        .join("\n");
+
+        let usage = parse_transcript(&content, None);
+        assert_eq!(usage.messages, 2);
+        assert_eq!(usage.input_tokens, 1_000_010);
+        assert_eq!(usage.cache_read_tokens, 20);
+        assert!((usage.cost_usd - 5.0).abs() < 1e-9);
+
+        let since = DateTime::parse_from_rfc3339("2026-01-02T00:00:00Z").unwrap().with_timezone(&Utc);
+        assert_eq!(parse_transcript(&content, Some(since)).messages, 1);
+    }
+
+    #[test]
+    fn test_format_tokens() {
+        assert_eq!(format_tokens(999), "999");
+        assert_eq!(format_tokens(12_300), "12.3k");
+        assert_eq!(format_tokens(4_560_000), "4.6M");
+    }
+}
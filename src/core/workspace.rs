@@ -0,0 +1,109 @@
+use std::path::{Path, PathBuf};
+use anyhow::{Result, Context};
+use serde::{Deserialize, Serialize};
+
+use crate::error::StacksError;
+
+/// Manifest at the root of a meta-repository that lists its member repositories
+pub const WORKSPACE_FILE: &str = "stacks-workspace.toml";
+
+/// Set in the `stacks` processes run for each member, which must not fan out again
+pub const MEMBER_ENV: &str = "STACKS_WORKSPACE_MEMBER";
+
+/// A meta-repository whose member repositories are managed together with `--workspace`
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
+pub struct Workspace {
+    /// Directory containing the manifest
+    #[serde(skip)]
+    pub root: PathBuf,
+    /// Member repositories, relative to the workspace root
+    #[serde(default)]
+    pub members: Vec<String>,
+    /// Stacks `stacks checkout --workspace` installs into every member when none are given
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub stacks: Vec<String>,
+}
+
+impl Workspace {
+    pub fn parse(content: &str, root: &Path) -> Result<Self> {
+        let mut workspace: Workspace = toml::from_str(content)
+            .with_context(|| format!("Failed to parse {}", root.join(WORKSPACE_FILE).display()))?;
+        workspace.root = root.to_path_buf();
+        if workspace.members.is_empty() {
+            return Err(StacksError::validation(format!("{} lists no members", root.join(WORKSPACE_FILE).display()))
+                .with_hint("Add the member repositories, e.g. members = [\"api\", \"web\"]")
+                .into());
+        }
+        Ok(workspace)
+    }
+
+    /// The workspace `start` is in: the nearest ancestor with a workspace manifest
+    pub fn find(start: &Path) -> Result<Option<Self>> {
+        for dir in start.ancestors() {
+            let path = dir.join(WORKSPACE_FILE);
+            if path.is_file() {
+                let content = std::fs::read_to_string(&path)
+                    .with_context(|| format!("Failed to read {}", path.display()))?;
+                return Self::parse(&content, dir).map(Some);
+            }
+        }
+        Ok(None)
+    }
+
+    /// The workspace of the current directory, or an error explaining how to create one
+    pub fn current() -> Result<Self> {
+        match Self::find(&std::env::current_dir()?)? {
+            Some(workspace) => Ok(workspace),
+            None => Err(StacksError::validation(format!("No {} found in this directory or its parents", WORKSPACE_FILE))
+                .with_hint(format!("Create {} at the root of the meta-repository with members = [\"app-a\", \"app-b\"]", WORKSPACE_FILE))
+                .into()),
+        }
+    }
+
+    /// Member directories, checking that each one exists
+    pub fn member_dirs(&self) -> Result<Vec<(String, PathBuf)>> {
+        self.members
+            .iter()
+            .map(|member| {
+                let dir = self.root.join(member);
+                match dir.is_dir() {
+                    true => Ok((member.clone(), dir)),
+                    false => Err(StacksError::validation(format!("Workspace member {} does not exist", member))
+                        .with_hint(format!("Clone it to {} or remove it from {}", dir.display(), WORKSPACE_FILE))
+                        .into()),
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::TempDir;
+
+    #[test]
+    fn test_parse() {
+        let workspace = Workspace::parse("members = [\"api\", \"web\"]\nstacks = [\"ts-lint-stack\"]\n", Path::new("/work/meta")).unwrap();
+        assert_eq!(workspace.members, vec!["api", "web"]);
+        assert_eq!(workspace.stacks, vec!["ts-lint-stack"]);
+        assert_eq!(workspace.root, PathBuf::from("/work/meta"));
+
+        assert!(Workspace::parse("members = []\n", Path::new("/work/meta")).is_err());
+    }
+
+    #[test]
+    fn test_find_from_member() {
+        let temp_dir = TempDir::new().unwrap();
+        let root = temp_dir.path();
+        std::fs::write(root.join(WORKSPACE_FILE), "members = [\"api\", \"missing\"]\n").unwrap();
+        std::fs::create_dir_all(root.join("api/src")).unwrap();
+
+        let workspace = Workspace::find(&root.join("api/src")).unwrap().unwrap();
+        assert_eq!(workspace.root, root);
+        assert!(workspace.member_dirs().is_err());
+
+        let other = TempDir::new().unwrap();
+        assert!(Workspace::find(other.path()).unwrap().is_none());
+    }
+}
@@ -0,0 +1,169 @@
+use std::path::{Path, PathBuf};
+use anyhow::{Result, Context};
+
+use super::git::Repo;
+use crate::utils::process::Process;
+
+/// A git worktree of the current repository, as reported by `git worktree list`
+#[derive(Debug, Clone, PartialEq)]
+pub struct WorktreeInfo {
+    pub path: PathBuf,
+    pub head: Option<String>,
+    /// Checked-out branch without the refs/heads/ prefix; None when detached
+    pub branch: Option<String>,
+    pub is_main: bool,
+    pub is_bare: bool,
+    pub is_locked: bool,
+    pub is_prunable: bool,
+}
+
+impl WorktreeInfo {
+    pub fn branch_label(&self) -> String {
+        match (&self.branch, &self.head) {
+            (Some(branch), _) => branch.clone(),
+            (None, Some(head)) => format!("detached at {}", head.chars().take(7).collect::<String>()),
+            (None, None) => "unknown".to_string(),
+        }
+    }
+
+    pub fn has_stacks(&self) -> bool {
+        self.path.join("stacks").is_dir()
+    }
+
+    /// Whether this worktree contains the given directory
+    pub fn contains(&self, dir: &Path) -> bool {
+        match (self.path.canonicalize(), dir.canonicalize()) {
+            (Ok(worktree), Ok(dir)) => dir.starts_with(worktree),
+            _ => false,
+        }
+    }
+}
+
+/// All worktrees of the repository containing the current directory; the main worktree comes first
+pub fn list_worktrees() -> Result<Vec<WorktreeInfo>> {
+    let output = Process::git()
+        .args(["worktree", "list", "--porcelain"])
+        .read_only()
+        .run()
+        .context("Failed to list git worktrees")?;
+
+    Ok(parse_worktree_list(&output.stdout))
+}
+
+/// Worktrees other than the one containing the current directory that have checked-out stacks
+pub fn other_worktrees_with_stacks() -> Result<Vec<WorktreeInfo>> {
+    let current_dir = std::env::current_dir()?;
+    Ok(list_worktrees()?
+        .into_iter()
+        .filter(|worktree| !worktree.is_bare && !worktree.is_prunable)
+        .filter(|worktree| !worktree.contains(&current_dir))
+        .filter(|worktree| worktree.has_stacks())
+        .collect())
+}
+
+/// Whether `dir` is in a linked worktree (created with `git worktree add`) rather than the main checkout
+pub fn is_linked_worktree(dir: &Path) -> bool {
+    Repo::discover(dir)
+        .map(|repo| repo.is_linked_worktree())
+        .unwrap_or(false)
+}
+
+fn parse_worktree_list(output: &str) -> Vec<WorktreeInfo> {
+    let mut worktrees = Vec::new();
+
+    // Records are separated by blank lines; each starts with a `worktree <path>` line
+    for record in output.split("\n\n") {
+        let mut worktree: Option<WorktreeInfo> = None;
+
+        for line in record.lines() {
+            let (key, value) = match line.split_once(' ') {
+                Some((key, value)) => (key, value),
+                None => (line, ""),
+            };
+
+            if key == "worktree" {
+                worktree = Some(WorktreeInfo {
+                    path: PathBuf::from(value),
+                    head: None,
+                    branch: None,
+                    is_main: worktrees.is_empty(),
+                    is_bare: false,
+                    is_locked: false,
+                    is_prunable: false,
+                });
+                continue;
+            }
+
+            if let Some(worktree) = worktree.as_mut() {
+                match key {
+                    "HEAD" => worktree.head = Some(value.to_string()),
+                    "branch" => {
+                        worktree.branch = Some(value.trim_start_matches("refs/heads/").to_string())
+                    }
+                    "bare" => worktree.is_bare = true,
+                    "locked" => worktree.is_locked = true,
+                    "prunable" => worktree.is_prunable = true,
+                    _ => {}
+                }
+            }
+        }
+
+        if let Some(worktree) = worktree {
+            worktrees.push(worktree);
+        }
+    }
+
+    worktrees
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::process::Command;
+
+    #[test]
+    fn test_parse_worktree_list() {
+        let output = "worktree /home/user/project\nHEAD 1a2b3c4d5e6f\nbranch refs/heads/main\n\n\
+worktree /home/user/project-feature\nHEAD 9f8e7d6c5b4a\nbranch refs/heads/feature-auth\nlocked\n\n\
+worktree /home/user/project-detached\nHEAD abcdef123456\ndetached\nprunable gitdir file points to non-existent location\n";
+
+        let worktrees = parse_worktree_list(output);
+        assert_eq!(worktrees.len(), 3);
+
+        assert!(worktrees[0].is_main);
+        assert_eq!(worktrees[0].branch.as_deref(), Some("main"));
+
+        assert!(!worktrees[1].is_main);
+        assert!(worktrees[1].is_locked);
+        assert_eq!(worktrees[1].branch_label(), "feature-auth");
+
+        assert_eq!(worktrees[2].branch, None);
+        assert!(worktrees[2].is_prunable);
+        assert_eq!(worktrees[2].branch_label(), "detached at abcdef1");
+    }
+
+    #[test]
+    fn test_is_linked_worktree() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        let main = temp_dir.path().join("main");
+        let feature = temp_dir.path().join("feature");
+        std::fs::create_dir_all(&main).unwrap();
+
+        let git = |dir: &Path, args: &[&str]| {
+            let status = Command::new("git")
+                .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+                .args(args)
+                .current_dir(dir)
+                .status()
+                .unwrap();
+            assert!(status.success());
+        };
+        git(&main, &["init", "-q"]);
+        git(&main, &["commit", "-q", "--allow-empty", "-m", "initial"]);
+        git(&main, &["worktree", "add", "-q", "-b", "feature", feature.to_str().unwrap()]);
+
+        assert!(!is_linked_worktree(&main));
+        assert!(is_linked_worktree(&feature));
+        assert!(!is_linked_worktree(temp_dir.path()));
+    }
+}
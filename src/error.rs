@@ -0,0 +1,283 @@
+use std::fmt;
+
+use crate::core::git::GitError;
+
+/// Broad category of a failure, which decides the process exit code and the default fix hint
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ErrorKind {
+    /// Anything not covered by a more specific kind
+    Other,
+    /// Bad input or configuration: an unknown stack, key, or value
+    Validation,
+    /// A git operation failed
+    Git,
+    /// GitHub or a git remote could not be reached
+    Network,
+    /// tmux is missing or a tmux command failed
+    Tmux,
+    /// The user cancelled a prompt
+    Aborted,
+}
+
+impl ErrorKind {
+    pub fn all() -> Vec<ErrorKind> {
+        vec![
+            ErrorKind::Other,
+            ErrorKind::Validation,
+            ErrorKind::Git,
+            ErrorKind::Network,
+            ErrorKind::Tmux,
+            ErrorKind::Aborted,
+        ]
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ErrorKind::Other => "error",
+            ErrorKind::Validation => "validation",
+            ErrorKind::Git => "git",
+            ErrorKind::Network => "network",
+            ErrorKind::Tmux => "tmux",
+            ErrorKind::Aborted => "aborted",
+        }
+    }
+
+    /// Process exit code; 2 matches clap's usage errors and 130 a shell's Ctrl-C
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            ErrorKind::Other => 1,
+            ErrorKind::Validation => 2,
+            ErrorKind::Git => 3,
+            ErrorKind::Network => 4,
+            ErrorKind::Tmux => 5,
+            ErrorKind::Aborted => 130,
+        }
+    }
+
+    /// Fix hint for errors that don't carry their own
+    fn default_hint(&self) -> Option<&'static str> {
+        match self {
+            ErrorKind::Other | ErrorKind::Aborted => None,
+            ErrorKind::Validation => Some("Check the arguments against `stacks --help` and `stacks config show`"),
+            ErrorKind::Git => Some("Run the command from inside a git repository and check `git status` for an operation in progress"),
+            ErrorKind::Network => Some("Check your connection; for private or rate-limited repositories set GITHUB_TOKEN"),
+            ErrorKind::Tmux => Some("Make sure tmux is installed and on PATH; `tmux ls` shows running sessions"),
+        }
+    }
+}
+
+/// An error with its category, cause, and a suggested fix
+///
+/// Wrap it in anyhow as usual (`return Err(StacksError::git("...").into())`); `report` finds
+/// it anywhere in the chain.
+#[derive(Debug)]
+pub struct StacksError {
+    pub kind: ErrorKind,
+    pub message: String,
+    pub reason: Option<String>,
+    pub hint: Option<String>,
+    /// External command that failed, with its stderr
+    pub command: Option<(String, String)>,
+}
+
+impl StacksError {
+    pub fn new(kind: ErrorKind, message: impl Into<String>) -> Self {
+        Self {
+            kind,
+            message: message.into(),
+            reason: None,
+            hint: None,
+            command: None,
+        }
+    }
+
+    pub fn validation(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::Validation, message)
+    }
+
+    pub fn git(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::Git, message)
+    }
+
+    pub fn network(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::Network, message)
+    }
+
+    pub fn tmux(message: impl Into<String>) -> Self {
+        Self::new(ErrorKind::Tmux, message)
+    }
+
+    pub fn with_reason(mut self, reason: impl Into<String>) -> Self {
+        self.reason = Some(reason.into());
+        self
+    }
+
+    pub fn with_hint(mut self, hint: impl Into<String>) -> Self {
+        self.hint = Some(hint.into());
+        self
+    }
+
+    pub fn with_command(mut self, command: impl Into<String>, stderr: impl Into<String>) -> Self {
+        self.command = Some((command.into(), stderr.into()));
+        self
+    }
+}
+
+impl fmt::Display for StacksError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for StacksError {}
+
+/// Ends a command with a non-zero exit code after it printed its own result, e.g.
+/// `status --porcelain` finding changes; `report` passes the code on without printing anything
+#[derive(Debug)]
+pub struct ExitStatus(pub i32);
+
+impl fmt::Display for ExitStatus {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "exit status {}", self.0)
+    }
+}
+
+impl std::error::Error for ExitStatus {}
+
+/// The command was run outside a git repository
+pub fn not_a_repository() -> StacksError {
+    StacksError::git("Not in a git repository")
+        .with_reason("stacks keeps stacks as git subtrees of the current project")
+        .with_hint("Run this command from inside a git repository, or create one with `git init`")
+}
+
+/// Category of an error, from the first classifiable error in its chain
+pub fn classify(error: &anyhow::Error) -> ErrorKind {
+    for cause in error.chain() {
+        if let Some(error) = cause.downcast_ref::<StacksError>() {
+            return error.kind;
+        }
+        if let Some(error) = cause.downcast_ref::<GitError>() {
+            return match error {
+                GitError::Remote { .. } => ErrorKind::Network,
+                _ => ErrorKind::Git,
+            };
+        }
+        if cause.downcast_ref::<reqwest::Error>().is_some() {
+            return ErrorKind::Network;
+        }
+        if let Some(dialoguer::Error::IO(io_error)) = cause.downcast_ref::<dialoguer::Error>() {
+            if io_error.kind() == std::io::ErrorKind::Interrupted {
+                return ErrorKind::Aborted;
+            }
+        }
+    }
+    ErrorKind::Other
+}
+
+/// The "what happened / why / how to fix" sections for an error
+pub fn render(error: &anyhow::Error) -> String {
+    let kind = classify(error);
+    let detail = error.chain().find_map(|cause| cause.downcast_ref::<StacksError>());
+
+    let mut lines = vec![format!("❌ {}", error)];
+
+    // Why: the error's own reason, else the rest of the anyhow chain
+    let causes: Vec<String> = error.chain().skip(1).map(|cause| cause.to_string()).collect();
+    match detail.and_then(|detail| detail.reason.clone()) {
+        Some(reason) => lines.push(format!("   Why: {}", reason)),
+        None if !causes.is_empty() => lines.push(format!("   Why: {}", causes.join(": "))),
+        None => {}
+    }
+
+    let command = detail.and_then(|detail| detail.command.clone()).or_else(|| {
+        error.chain().find_map(|cause| match cause.downcast_ref::<GitError>() {
+            Some(GitError::Command { args, stderr, .. }) => Some((format!("git {}", args.join(" ")), stderr.clone())),
+            _ => None,
+        })
+    });
+    if let Some((command, stderr)) = command {
+        lines.push(format!("   Command: {}", command));
+        for line in stderr.trim().lines() {
+            lines.push(format!("   │ {}", line));
+        }
+    }
+
+    let hint = detail
+        .and_then(|detail| detail.hint.clone())
+        .or_else(|| kind.default_hint().map(str::to_string));
+    if let Some(hint) = hint {
+        lines.push(format!("   Fix: {}", hint));
+    }
+
+    lines.join("\n")
+}
+
+/// Print an error to stderr and return the exit code for it
+pub fn report(error: &anyhow::Error) -> i32 {
+    if let Some(ExitStatus(code)) = error.downcast_ref::<ExitStatus>() {
+        return *code;
+    }
+    let kind = classify(error);
+    tracing::debug!(kind = kind.as_str(), "command failed: {:#}", error);
+    if kind == ErrorKind::Aborted {
+        eoutln!("Cancelled.");
+    } else {
+        eoutln!("{}", render(error));
+    }
+    kind.exit_code()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    #[test]
+    fn test_exit_codes_are_distinct() {
+        let mut codes: Vec<i32> = ErrorKind::all().iter().map(ErrorKind::exit_code).collect();
+        codes.sort();
+        codes.dedup();
+        assert_eq!(codes.len(), ErrorKind::all().len());
+        assert!(!codes.contains(&0));
+    }
+
+    #[test]
+    fn test_exit_status_passes_code_through() {
+        assert_eq!(report(&anyhow::Error::new(ExitStatus(1))), 1);
+    }
+
+    #[test]
+    fn test_classify_through_context() {
+        let error = Err::<(), _>(StacksError::tmux("Failed to create session"))
+            .context("Failed to set up worktree")
+            .unwrap_err();
+        assert_eq!(classify(&error), ErrorKind::Tmux);
+        assert_eq!(classify(&anyhow::anyhow!("plain")), ErrorKind::Other);
+
+        let git_error = anyhow::Error::new(GitError::Command {
+            args: vec!["worktree".to_string(), "add".to_string()],
+            code: Some(128),
+            stderr: "fatal: 'feature' is already checked out".to_string(),
+        });
+        assert_eq!(classify(&git_error), ErrorKind::Git);
+        let rendered = render(&git_error);
+        assert!(rendered.contains("Command: git worktree add"));
+        assert!(rendered.contains("│ fatal: 'feature' is already checked out"));
+        assert!(rendered.contains("Fix:"));
+    }
+
+    #[test]
+    fn test_render_sections() {
+        let error = anyhow::Error::new(
+            StacksError::validation("Unknown stack 'lnt'")
+                .with_reason("No stack with that name is checked out")
+                .with_hint("Run `stacks status` to list checked-out stacks"),
+        );
+        let rendered = render(&error);
+        assert_eq!(
+            rendered,
+            "❌ Unknown stack 'lnt'\n   Why: No stack with that name is checked out\n   Fix: Run `stacks status` to list checked-out stacks"
+        );
+    }
+}
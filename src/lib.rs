@@ -0,0 +1,51 @@
+//! Manage Claude Code workflow stacks: reusable agents, commands, settings and CLAUDE.md
+//! instructions, checked out into projects as git subtrees.
+//!
+//! The `stacks` binary is a thin layer over this crate, and editor plugins or other front ends
+//! can embed the same functionality:
+//!
+//! - [`RemoteStackManager`] discovers registry stacks and adds them to a project;
+//! - [`SymlinkManager`] links a stack's agents and commands into `.claude/`;
+//! - [`SettingsMerger`] merges a stack's settings into the project's local settings;
+//! - [`ClaudeMdUpdater`] adds and removes stack instructions in CLAUDE.md;
+//! - [`discover_stacks`] lists the stacks checked out in the current project.
+//!
+//! The managers report what they do as typed [`Event`]s to a [`Reporter`]. By default that is the
+//! console, which prints each event the way the CLI does. Give a manager its own reporter with
+//! `with_reporter`, or replace the console for the whole process with [`set_reporter`]:
+//!
+//! ```no_run
+//! use std::sync::Arc;
+//!
+//! let reporter = Arc::new(stacks::CollectingReporter::new());
+//! let symlinks = stacks::SymlinkManager::new().with_reporter(reporter.clone());
+//! ```
+//!
+//! Everything else the library prints can be redirected with [`set_output_sink`]:
+//!
+//! ```no_run
+//! stacks::set_output_sink(|stream, line| eprintln!("[{:?}] {}", stream, line));
+//! ```
+//!
+//! Errors are `anyhow` errors; those meant for users downcast to [`StacksError`], which carries a
+//! kind and an optional hint.
+
+// First, so its output macros are visible in every other module
+#[macro_use]
+pub mod utils;
+pub mod cli;
+pub mod core;
+pub mod config;
+pub mod error;
+
+pub use crate::config::{load_config, StacksConfig};
+pub use crate::core::events::{set_reporter, CollectingReporter, ConsoleReporter, Event, Reporter};
+pub use crate::core::journal::{Journal, JournalEntry};
+pub use crate::core::lockfile::StacksLock;
+pub use crate::core::remote_stack_manager::{RemoteStackManager, StackMetadata, StackSource};
+pub use crate::core::settings_merger::SettingsMerger;
+pub use crate::core::stack_manager::{discover_stacks, Stack};
+pub use crate::core::symlink_manager::SymlinkManager;
+pub use crate::error::{ErrorKind, StacksError};
+pub use crate::utils::claude_md_updater::ClaudeMdUpdater;
+pub use crate::utils::output::{set_sink as set_output_sink, Stream as OutputStream};
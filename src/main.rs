@@ -1,19 +1,63 @@
-use clap::{Parser, Subcommand};
+use std::time::Duration;
+use clap::{CommandFactory, FromArgMatches, Parser, Subcommand};
 use anyhow::Result;
 
-mod cli;
-mod core;
-mod utils;
-mod config;
-
-use cli::{checkout, push, status, pull, worktree, sync, cleanup};
-use config::{StacksConfig, TmuxStrategy, InTmuxBehavior};
+use stacks::{outln, cli, config, core, error, utils};
+use stacks::cli::{checkout, push, status, pull, worktree, sync, cleanup, outdated, repair, permissions, remove, doctor, cache, daemon, attach, logs, usage, run, ci, stack_set, auth, registry, which, workspace, open, statusline, gc, remote, env, undo, schema, serve, query, telemetry, grep, inventory};
 
 #[derive(Parser)]
 #[command(name = "stacks")]
 #[command(about = "A CLI tool for managing Claude Code workflow stacks")]
-#[command(version = "0.1.0")]
+#[command(disable_version_flag = true)]
 struct Cli {
+    /// Print the version, commit, build date and target
+    #[arg(short = 'V', long)]
+    version: bool,
+    /// With --version: print the build information as JSON
+    #[arg(long, requires = "version")]
+    json: bool,
+    /// Config profile to use (overrides the STACKS_PROFILE environment variable)
+    #[arg(long, global = true, value_name = "NAME")]
+    profile: Option<String>,
+    /// Show each git and tmux command with its duration (-v), or all diagnostics (-vv)
+    #[arg(short, long, global = true, action = clap::ArgAction::Count)]
+    verbose: u8,
+    /// Also write detailed logs to a daily rotating file in the cache directory
+    #[arg(long, global = true)]
+    log_file: bool,
+    /// Print the commands and file changes that would be made instead of making them
+    #[arg(long, global = true)]
+    dry_run: bool,
+    /// Color diagnostics and progress output: auto (off for NO_COLOR, dumb terminals and pipes), always or never
+    #[arg(long, global = true, value_name = "WHEN", default_value = "auto", value_parser = ["auto", "always", "never"])]
+    color: String,
+    /// Leave emoji and box drawing out of output, e.g. for logs and dumb terminals
+    #[arg(long, global = true)]
+    plain: bool,
+    /// Development: list and read registry stacks from a fixture directory (DIR/stacks/<name>) instead of GitHub
+    #[arg(long, global = true, hide = true, value_name = "DIR")]
+    mock_registry: Option<std::path::PathBuf>,
+    /// Without a command: install stacks into the current checkout instead of creating a worktree (also 'stacks checkout --no-worktree')
+    #[arg(long)]
+    no_worktree: bool,
+    /// Without a command: start each new worktree's claude with the prompt in this file
+    #[arg(long, value_name = "FILE", conflicts_with = "prompt_template")]
+    prompt_file: Option<std::path::PathBuf>,
+    /// Without a command: start each new worktree's claude with a template from [prompts] in the config
+    #[arg(long, value_name = "NAME")]
+    prompt_template: Option<String>,
+    /// Without a command: branch new worktrees from the default branch instead of asking
+    #[arg(long, group = "branch_strategy")]
+    from_default_branch: bool,
+    /// Without a command: base new worktrees on this remote branch (e.g. origin/release) instead of asking
+    #[arg(long, value_name = "REMOTE_BRANCH", group = "branch_strategy")]
+    from_remote: Option<String>,
+    /// Without a command: check out this existing branch in the new worktree instead of asking
+    #[arg(long, value_name = "BRANCH", group = "branch_strategy")]
+    existing_branch: Option<String>,
+    /// Without a command: branch new worktrees from the current branch instead of asking
+    #[arg(long, group = "branch_strategy")]
+    from_current: bool,
     #[command(subcommand)]
     command: Option<Commands>,
 }
@@ -21,11 +65,24 @@ struct Cli {
 #[derive(Subcommand)]
 enum Commands {
     /// Check out one or more stacks for use in the current project
-    #[command(name = "checkout")]
+    #[command(name = "checkout", visible_alias = "co")]
     Checkout {
-        /// GitHub URL or stack name to checkout directly
+        /// Registry stack names, owner/repo specs, or GitHub URLs (https://github.com/owner/repo[/tree/branch/path]);
+        /// opens the picker when none are given
         #[arg(value_name = "STACK_URL_OR_NAME")]
-        stack: Option<String>,
+        stacks: Vec<String>,
+        /// Also check out the stacks of a group from [groups] in the config or .stacks/config.toml
+        #[arg(short, long = "group", value_name = "GROUP")]
+        groups: Vec<String>,
+        /// Download each stack to quarantine and show its agents, commands, grants and hooks before installing it
+        #[arg(long)]
+        review: bool,
+        /// Install into the current checkout; without stacks, skip the worktree and tmux wizard
+        #[arg(long, conflicts_with = "workspace")]
+        no_worktree: bool,
+        /// Install the stacks into every member of the workspace (stacks-workspace.toml)
+        #[arg(long)]
+        workspace: bool,
     },
     /// Push changes in stacks back to source repositories
     #[command(name = "push")]
@@ -36,37 +93,409 @@ enum Commands {
         /// Commit message for the changes
         #[arg(short, long)]
         message: Option<String>,
+        /// Push to a fork of the stack repository and print the pull request URL
+        #[arg(long)]
+        fork: bool,
+        /// Bump the version in stack.toml and tag the stack repository (major, minor, patch)
+        #[arg(long, value_name = "LEVEL", value_parser = ["major", "minor", "patch"])]
+        release: Option<String>,
+        /// Never prompt and print nothing when no stack changed (used by the auto-push hook)
+        #[arg(short, long)]
+        quiet: bool,
     },
     /// Show git status of all checked-out stacks
-    #[command(name = "status")]
-    Status,
+    #[command(name = "status", visible_alias = "st")]
+    Status {
+        /// Keep refreshing the report on an interval or when worktrees change
+        #[arg(short, long)]
+        watch: bool,
+        /// Seconds between refreshes in watch mode
+        #[arg(long, value_name = "SECONDS", default_value_t = 10, requires = "watch")]
+        interval: u64,
+        /// Tab-separated output for scripts; exits 0 when clean, 1 when stacks have changes, 2 on errors
+        #[arg(long, conflicts_with = "watch")]
+        porcelain: bool,
+        /// The porcelain report as JSON (see 'stacks schema status'), with the same exit codes
+        #[arg(long, conflicts_with_all = ["watch", "porcelain"])]
+        json: bool,
+        /// Report on every member of the workspace (stacks-workspace.toml)
+        #[arg(long, conflicts_with_all = ["watch", "porcelain", "json"])]
+        workspace: bool,
+    },
     /// Update stacks from source repositories
     #[command(name = "pull")]
     Pull {
         /// Stack name to update (optional - updates all if not specified)
+        #[arg(value_name = "STACK_NAME", conflicts_with_all = ["continue_pull", "abort"])]
+        stack_name: Option<String>,
+        /// Finish a pull that stopped on merge conflicts
+        #[arg(long = "continue", conflicts_with = "abort")]
+        continue_pull: bool,
+        /// Abandon a pull that stopped on merge conflicts
+        #[arg(long)]
+        abort: bool,
+        /// Only show the upstream commits and CHANGELOG.md additions the pull would bring in
+        #[arg(long, requires = "stack_name", conflicts_with = "workspace")]
+        preview: bool,
+        /// Update the stacks of every member of the workspace (stacks-workspace.toml)
+        #[arg(long)]
+        workspace: bool,
+    },
+    /// Show which checked-out stacks have upstream updates (read-only)
+    Outdated {
+        /// Print every stack as JSON (see 'stacks schema outdated')
+        #[arg(long)]
+        json: bool,
+    },
+    /// Restore missing symlinks, settings and CLAUDE.md imports for stacks
+    Repair {
+        /// Stack name to repair (optional - repairs all if not specified)
         #[arg(value_name = "STACK_NAME")]
         stack_name: Option<String>,
     },
+    /// Check the project for inconsistencies (runs all checks unless one is selected)
+    Doctor {
+        /// Check that CLAUDE.md references every installed stack exactly once
+        #[arg(long)]
+        claude_md: bool,
+        /// Check that git, tmux, claude and ssh are installed and new enough
+        #[arg(long)]
+        dependencies: bool,
+        /// Repair the problems found
+        #[arg(long)]
+        fix: bool,
+        /// Print the dependency checks as JSON (see 'stacks schema doctor')
+        #[arg(long, conflicts_with_all = ["claude_md", "fix"])]
+        json: bool,
+    },
+    /// Remove a checked-out stack and retract what it added to the project
+    Remove {
+        /// Stack name to remove
+        #[arg(value_name = "STACK_NAME")]
+        stack_name: String,
+        /// Remove without asking for confirmation
+        #[arg(short, long)]
+        yes: bool,
+    },
+    /// Revert the last checkout: its subtrees, links, CLAUDE.md imports and settings changes
+    Undo {
+        /// Undo without asking for confirmation
+        #[arg(short, long)]
+        yes: bool,
+    },
     /// Manage git worktrees with tmux integration
-    Worktree,
+    #[command(visible_alias = "wt")]
+    Worktree {
+        /// Task name; without a terminal the remaining choices take their defaults instead of being asked
+        #[arg(long, value_name = "NAME")]
+        task: Option<String>,
+        /// Permission preset for the new worktree (strict, standard, permissive)
+        #[arg(long, value_name = "PRESET", value_parser = ["strict", "standard", "permissive"])]
+        permissions: Option<String>,
+        /// Launch claude inside an OS-level sandbox (see 'stacks config set sandbox-command')
+        #[arg(long)]
+        sandbox: bool,
+        /// Run each claude in a container (devcontainer.json image or container-image) with the main checkout read-only
+        #[arg(long, conflicts_with = "sandbox")]
+        container: bool,
+        /// Record each claude pane's output to .stacks/logs/<task>/ (see 'stacks logs')
+        #[arg(long)]
+        record: bool,
+        /// Create the worktree and tmux session on another machine over ssh
+        #[arg(long, value_name = "USER@HOST", conflicts_with_all = ["permissions", "sandbox", "container", "record"])]
+        remote: Option<String>,
+        /// Clone of the repository on the remote host (~/<repo> by default)
+        #[arg(long, value_name = "DIR", requires = "remote")]
+        remote_dir: Option<String>,
+    },
     /// Sync MCP server configurations from docker-compose and other sources
     Sync,
     /// Clean up worktrees by pushing stacks, removing symlinks, and cleaning CLAUDE.md
-    Cleanup,
+    Cleanup {
+        /// Clean up the worktrees of every member of the workspace (stacks-workspace.toml)
+        #[arg(long)]
+        workspace: bool,
+    },
+    /// Manage permissions generated for feature worktrees
+    Permissions {
+        #[command(subcommand)]
+        command: PermissionsCommands,
+    },
     /// Manage configuration settings
     Config {
         #[command(subcommand)]
         command: ConfigCommands,
     },
+    /// Fuzzy-pick a tmux session created by stacks and switch or attach to it
+    #[command(visible_alias = "a")]
+    Attach {
+        /// Initial search text, e.g. a task name
+        #[arg(value_name = "QUERY")]
+        query: Option<String>,
+    },
+    /// Jump into the tmux session of an existing task, recreating it if it was closed
+    Open {
+        /// Task name the worktree was created for
+        #[arg(value_name = "TASK")]
+        task: String,
+        /// Restart claude in panes where it exited
+        #[arg(long)]
+        relaunch: bool,
+    },
+    /// Print a one-line worktree summary for tmux's status-right
+    Statusline {
+        /// Directory to describe, e.g. '#{pane_current_path}'; the current directory by default
+        #[arg(long, value_name = "DIR")]
+        path: Option<std::path::PathBuf>,
+    },
+    /// Answer quick questions about a worktree for shell prompts and status lines, without the network
+    Query {
+        /// What to ask: the current task, or the checked-out, dirty or outdated stacks
+        #[arg(value_name = "QUERY", value_parser = ["current-task", "stacks", "dirty-stacks", "outdated-stacks"])]
+        query: String,
+        /// Print how many stacks match instead of their names
+        #[arg(long)]
+        count: bool,
+        /// Worktree to ask about, e.g. '#{pane_current_path}'; the current directory by default
+        #[arg(long, value_name = "DIR")]
+        path: Option<std::path::PathBuf>,
+    },
+    /// Periodically check stacks for upstream updates and broken symlinks
+    Daemon {
+        /// Seconds between checks (at least 60)
+        #[arg(long, default_value = "900")]
+        interval: u64,
+        /// Check once and exit
+        #[arg(long)]
+        once: bool,
+        /// Post a desktop notification when a stack update appears
+        #[arg(long)]
+        notify: bool,
+        /// Restore missing or broken symlinks, settings and CLAUDE.md imports automatically
+        #[arg(long)]
+        repair: bool,
+        /// Run in a detached tmux window instead of the foreground
+        #[arg(long)]
+        tmux: bool,
+    },
+    /// Close idle tmux sessions of tasks with clean worktrees, and optionally remove the worktrees
+    Gc {
+        /// Hours without activity before a session counts as idle (idle-session-hours, 24 by default)
+        #[arg(long, value_name = "HOURS")]
+        idle_hours: Option<u32>,
+        /// Close every idle session and remove its worktree without asking
+        #[arg(short, long)]
+        yes: bool,
+    },
+    /// Inspect and clean the cache directory
+    Cache {
+        #[command(subcommand)]
+        command: CacheCommands,
+    },
+    /// Opt-in usage telemetry: command names, durations and error classes, never arguments or paths
+    Telemetry {
+        #[command(subcommand)]
+        command: TelemetryCommands,
+    },
+    /// Show or search claude transcripts recorded with 'stacks worktree --record'
+    Logs {
+        /// Task whose transcripts to show (lists recorded tasks when omitted)
+        #[arg(value_name = "TASK")]
+        task: Option<String>,
+        /// Keep printing new output as it is recorded
+        #[arg(short, long)]
+        follow: bool,
+        /// Only show lines containing this text (case-insensitive)
+        #[arg(long, value_name = "TEXT")]
+        grep: Option<String>,
+        /// Number of trailing lines to show per pane
+        #[arg(short = 'n', long, default_value = "50")]
+        lines: usize,
+    },
+    /// Run claude headlessly on a prompt in a new worktree per task
+    Run {
+        /// Task name; repeat to queue several tasks
+        #[arg(long = "task", value_name = "NAME", required = true)]
+        tasks: Vec<String>,
+        /// File containing the prompt given to each task
+        #[arg(long, value_name = "FILE")]
+        prompt_file: std::path::PathBuf,
+        /// Number of tasks to run at the same time
+        #[arg(short, long, default_value = "2")]
+        jobs: usize,
+        /// Revision new task branches start from
+        #[arg(long, value_name = "REV", default_value = "HEAD")]
+        base: String,
+        /// Permission preset for the new worktrees (strict, standard, permissive)
+        #[arg(long, value_name = "PRESET", value_parser = ["strict", "standard", "permissive"])]
+        permissions: Option<String>,
+    },
+    /// Checks for continuous integration
+    Ci {
+        #[command(subcommand)]
+        command: CiCommands,
+    },
+    /// Show claude token usage and estimated cost per worktree
+    Usage {
+        /// Only count usage from the last N days
+        #[arg(long, value_name = "N")]
+        days: Option<u32>,
+    },
+    /// Sign in to GitHub for private registries and stacks
+    #[command(name = "auth")]
+    Auth {
+        #[command(subcommand)]
+        command: AuthCommands,
+    },
+    /// Write the project's stacks, MCP servers and settings to a shareable manifest
+    #[command(name = "export")]
+    Export {
+        /// File to write; prints the manifest when omitted
+        #[arg(short, long, value_name = "FILE")]
+        output: Option<std::path::PathBuf>,
+    },
+    /// Reproduce a manifest from 'stacks export': check out its stacks, merge settings, add MCP servers
+    #[command(name = "apply")]
+    Apply {
+        /// Manifest written by 'stacks export'
+        #[arg(value_name = "MANIFEST")]
+        file: std::path::PathBuf,
+    },
+    /// Print the resolved configuration, dependencies, registry, paths and project state for bug reports
+    Env,
+    /// Show which stack, install scope and file an agent or command comes from
+    #[command(name = "which")]
+    Which {
+        /// Agent or command name, e.g. code-reviewer or /fix-lint
+        #[arg(value_name = "NAME")]
+        name: String,
+    },
+    /// List every agent claude can use in this project, grouped by the stack it comes from
+    Agents,
+    /// List every slash command claude offers in this project, grouped by the stack it comes from
+    Commands,
+    /// Search the installed stacks' CLAUDE.md files, agents and commands, showing which stack each match is from
+    #[command(name = "grep")]
+    Grep {
+        /// Regular expression to search for
+        #[arg(value_name = "PATTERN")]
+        pattern: String,
+        /// Match case-insensitively
+        #[arg(short, long)]
+        ignore_case: bool,
+        /// Search for the pattern as plain text rather than a regular expression
+        #[arg(short = 'F', long)]
+        fixed_strings: bool,
+        /// Also search the registry's stacks that aren't installed, with GitHub code search (needs a token)
+        #[arg(long)]
+        remote: bool,
+    },
+    /// Manage the stack registry
+    #[command(name = "registry")]
+    Registry {
+        #[command(subcommand)]
+        command: RegistryCommands,
+    },
+    /// Serve stacks to editor extensions over JSON-RPC: list, checkout, status and attach info
+    Serve {
+        /// Talk JSON-RPC 2.0 on stdin and stdout, one message per line
+        #[arg(long, required = true)]
+        stdio: bool,
+    },
+    /// Print the JSON Schema of the --json outputs, for tooling that parses them
+    Schema {
+        /// Output to describe; prints all of them, keyed by name, when omitted
+        #[arg(value_name = "OUTPUT", value_parser = ["status", "outdated", "ci-verify", "doctor", "version"])]
+        output: Option<String>,
+    },
+}
+
+#[derive(Subcommand)]
+enum RegistryCommands {
+    /// Snapshot the registry index and its stacks into a directory or artifact store, for air-gapped use
+    Mirror {
+        /// Directory, file:// URL, or http(s) URL that accepts PUT uploads
+        #[arg(value_name = "DIR|URL")]
+        dest: String,
+    },
+}
+
+#[derive(Subcommand)]
+enum AuthCommands {
+    /// Sign in with GitHub's device flow and store the token in the system keyring
+    Login {
+        /// GitHub OAuth app client ID (defaults to github-client-id from the config)
+        #[arg(long, value_name = "ID")]
+        client_id: Option<String>,
+        /// Space-separated OAuth scopes to request
+        #[arg(long, value_name = "SCOPES")]
+        scopes: Option<String>,
+    },
+    /// Remove the stored token
+    Logout,
+    /// Show which token is used and whose it is
+    Status,
+}
+
+#[derive(Subcommand)]
+enum CacheCommands {
+    /// Show the cache size, cached registry indexes, and stale entries
+    Info,
+    /// Remove everything in the cache
+    Clear,
+    /// Remove stale entries and the oldest ones above the size cap
+    Prune,
+}
+
+#[derive(Subcommand)]
+enum TelemetryCommands {
+    /// Show whether telemetry is on, where it goes, and the failure rates recorded so far
+    Status,
+    /// Start recording
+    On,
+    /// Stop recording and delete the records kept on this machine
+    Off,
+}
+
+#[derive(Subcommand)]
+enum CiCommands {
+    /// Fail when stacks.lock, symlinks, settings or CLAUDE.md are inconsistent
+    /// (emits GitHub Actions annotations when run there)
+    Verify {
+        /// Skip comparing stacks with upstream
+        #[arg(long)]
+        offline: bool,
+        /// Treat outdated stacks as errors
+        #[arg(long)]
+        strict: bool,
+        /// Print the problems as JSON (see 'stacks schema ci-verify'); progress goes to stderr
+        #[arg(long)]
+        json: bool,
+    },
+}
+
+#[derive(Subcommand)]
+enum PermissionsCommands {
+    /// Remove generated permission rules, keeping user-authored settings
+    Revoke {
+        /// Worktree whose .claude/settings.local.json to clean (defaults to the current directory)
+        #[arg(value_name = "WORKTREE")]
+        worktree: Option<std::path::PathBuf>,
+    },
 }
 
 #[derive(Subcommand)]
 enum ConfigCommands {
     /// Show current configuration
     Show,
+    /// Print the value of a configuration key
+    Get {
+        /// Setting key (see 'stacks config show' for all keys)
+        key: String,
+    },
     /// Set configuration value
     Set {
-        /// Setting key (tmux-strategy, prompt-strategy, in-tmux-behavior)
+        /// Setting key (see 'stacks config show' for all keys)
         key: String,
         /// Setting value
         value: String,
@@ -75,252 +504,254 @@ enum ConfigCommands {
     Edit,
     /// Reset configuration to defaults
     Reset,
+    /// Print the configuration file, e.g. to share as team defaults
+    Export,
+    /// Merge settings from a file or URL into the configuration
+    Import {
+        /// Path or http(s) URL of a TOML file produced by 'stacks config export'
+        source: String,
+        /// Apply without asking for confirmation
+        #[arg(short, long)]
+        yes: bool,
+    },
 }
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    let cli = Cli::parse();
+    let args = expand_aliases(std::env::args().collect())?;
+    let matches = Cli::command().get_matches_from(&args);
+    let cli = Cli::from_arg_matches(&matches).unwrap_or_else(|error| error.exit());
 
-    match cli.command {
-        Some(Commands::Checkout { stack }) => {
-            checkout::run_with_stack(stack).await
+    // Answered before the config is applied, so install tooling can always ask what it installed
+    if cli.version {
+        match cli.json {
+            true => core::schema::print(&core::schema::VersionOutput::current())?,
+            false => println!("stacks {}", utils::build_info::LONG_VERSION),
         }
-        Some(Commands::Push { stack_name, message }) => {
-            push::run(stack_name, message).await
+        return Ok(());
+    }
+
+    // Config is loaded in many places; the environment carries the selected profile to all of them
+    if let Some(profile) = &cli.profile {
+        std::env::set_var(config::PROFILE_ENV, profile);
+    }
+    if let Some(dir) = &cli.mock_registry {
+        std::env::set_var(core::registry_client::MOCK_REGISTRY_ENV, dir);
+    }
+
+    let plain = cli.plain || config::load_config().is_ok_and(|config| config.plain_output);
+    utils::output::init(cli.color.parse()?, plain);
+    let log_guard = utils::logging::init(cli.verbose, cli.log_file)?;
+    utils::process::set_dry_run(cli.dry_run);
+
+    // Scheduled update check runs alongside the command; its notice is printed afterwards
+    let update_check = if update_checks_enabled(&cli.command) {
+        Some(tokio::spawn(core::update_check::check_if_due()))
+    } else {
+        None
+    };
+
+    let notify_operation = long_operation(&cli.command);
+    let telemetry_command = records_telemetry(&cli.command).then(|| command_path(&matches));
+    let started = std::time::Instant::now();
+
+    let prompt = match (cli.prompt_file, cli.prompt_template) {
+        (Some(path), _) => Some(core::prompts::PromptSource::File(path)),
+        (None, Some(name)) => Some(core::prompts::PromptSource::Named(name)),
+        (None, None) => None,
+    };
+    let branch_strategy = match (cli.from_current, cli.from_default_branch, cli.from_remote, cli.existing_branch) {
+        (true, ..) => Some(cli::worktree::BranchStrategy::NewFromCurrent),
+        (_, true, ..) => Some(cli::worktree::BranchStrategy::NewFromMain),
+        (_, _, Some(remote_branch), _) => Some(cli::worktree::BranchStrategy::NewFromRemote(remote_branch)),
+        (_, _, _, Some(branch)) => Some(cli::worktree::BranchStrategy::ExistingBranch(branch)),
+        _ => None,
+    };
+    let result = match cli.command {
+        Some(Commands::Checkout { stacks, groups, review, workspace: true, .. }) => {
+            workspace::checkout(stacks, groups, review).await
         }
-        Some(Commands::Status) => {
-            status::run().await
+        // Without stacks, `stacks checkout` is the default command
+        Some(Commands::Checkout { stacks, groups, review: false, no_worktree, .. }) if stacks.is_empty() && groups.is_empty() => {
+            default_checkout(no_worktree || cli.no_worktree, prompt, branch_strategy).await
         }
-        Some(Commands::Pull { stack_name }) => {
-            pull::run(stack_name).await
+        Some(Commands::Checkout { stacks, groups, review, .. }) => {
+            checkout::run_with_stack(stacks, groups, review).await
         }
-        Some(Commands::Worktree) => worktree::run().await,
-        Some(Commands::Sync) => sync::run().await,
-        Some(Commands::Cleanup) => cleanup::run().await,
-        Some(Commands::Config { command }) => handle_config_command(command).await,
-        None => {
-            // Default behavior - run checkout command
-            checkout::run().await
+        Some(Commands::Push { stack_name, message, fork, release, quiet }) => {
+            push::run(stack_name, message, fork, release, quiet).await
         }
-    }
-}
-
-async fn handle_config_command(command: ConfigCommands) -> Result<()> {
-    match command {
-        ConfigCommands::Show => {
-            show_config().await?;
+        Some(Commands::Status { workspace: true, .. } | Commands::Pull { workspace: true, .. } | Commands::Cleanup { workspace: true }) => {
+            workspace::run_in_members(&args).await
+        }
+        Some(Commands::Status { watch, interval, porcelain, json, .. }) => {
+            status::run(watch, interval, porcelain, json).await
+        }
+        Some(Commands::Pull { stack_name, continue_pull, abort, preview, .. }) => {
+            pull::run(stack_name, continue_pull, abort, preview).await
+        }
+        Some(Commands::Outdated { json }) => outdated::run(json).await,
+        Some(Commands::Repair { stack_name }) => repair::run(stack_name).await,
+        Some(Commands::Doctor { claude_md, dependencies, fix, json }) => doctor::run(claude_md, dependencies, fix, json).await,
+        Some(Commands::Remove { stack_name, yes }) => remove::run(stack_name, yes).await,
+        Some(Commands::Undo { yes }) => undo::run(yes).await,
+        Some(Commands::Worktree { remote: Some(host), remote_dir, task, .. }) => remote::create_worktree(host, remote_dir, task).await,
+        Some(Commands::Worktree { task, permissions, sandbox, container, record, .. }) => worktree::run(task, permissions, sandbox, container, record).await,
+        Some(Commands::Sync) => sync::run().await,
+        Some(Commands::Cleanup { .. }) => cleanup::run().await,
+        Some(Commands::Permissions { command: PermissionsCommands::Revoke { worktree } }) => {
+            permissions::revoke(worktree).await
         }
-        ConfigCommands::Edit => {
-            interactive_config_editor().await?;
+        Some(Commands::Config { command }) => match command {
+            ConfigCommands::Show => cli::config::show().await,
+            ConfigCommands::Edit => cli::config::edit().await,
+            ConfigCommands::Get { key } => cli::config::get(&key),
+            ConfigCommands::Set { key, value } => cli::config::set(&key, &value),
+            ConfigCommands::Export => cli::config::export(),
+            ConfigCommands::Import { source, yes } => cli::config::import(&source, yes).await,
+            ConfigCommands::Reset => cli::config::reset(),
+        },
+        Some(Commands::Attach { query }) => attach::run(query).await,
+        Some(Commands::Open { task, relaunch }) => open::run(task, relaunch).await,
+        Some(Commands::Statusline { path }) => statusline::run(path).await,
+        Some(Commands::Query { query, count, path }) => query::run(query, count, path).await,
+        Some(Commands::Daemon { interval, once, notify, repair, tmux }) => {
+            daemon::run(interval, once, notify, repair, tmux).await
         }
-        ConfigCommands::Set { key, value } => {
-            match key.as_str() {
-                "tmux-strategy" => {
-                    let strategy = TmuxStrategy::from_str(&value)?;
-                    config::update_config(|config| {
-                        config.tmux_strategy = strategy;
-                    })?;
-                    println!("Set tmux-strategy to: {}", value);
-                }
-                "prompt-strategy" => {
-                    let prompt = value.parse::<bool>()
-                        .map_err(|_| anyhow::anyhow!("Invalid boolean value: {}", value))?;
-                    config::update_config(|config| {
-                        config.prompt_for_strategy = prompt;
-                    })?;
-                    println!("Set prompt-strategy to: {}", prompt);
-                }
-                "in-tmux-behavior" => {
-                    let behavior = InTmuxBehavior::from_str(&value)?;
-                    config::update_config(|config| {
-                        config.in_tmux_behavior = behavior;
-                    })?;
-                    println!("Set in-tmux-behavior to: {}", value);
-                }
-                _ => anyhow::bail!("Unknown config key: {}. Valid keys: tmux-strategy, prompt-strategy, in-tmux-behavior", key),
-            }
+        Some(Commands::Gc { idle_hours, yes }) => gc::run(idle_hours, yes).await,
+        Some(Commands::Cache { command }) => match command {
+            CacheCommands::Info => cache::info().await,
+            CacheCommands::Clear => cache::clear().await,
+            CacheCommands::Prune => cache::prune().await,
+        },
+        Some(Commands::Telemetry { command }) => match command {
+            TelemetryCommands::Status => telemetry::status().await,
+            TelemetryCommands::On => telemetry::on().await,
+            TelemetryCommands::Off => telemetry::off().await,
+        },
+        Some(Commands::Logs { task, follow, grep, lines }) => logs::run(task, follow, grep, lines).await,
+        Some(Commands::Usage { days }) => usage::run(days).await,
+        Some(Commands::Auth { command }) => match command {
+            AuthCommands::Login { client_id, scopes } => auth::login(client_id, scopes).await,
+            AuthCommands::Logout => auth::logout().await,
+            AuthCommands::Status => auth::status().await,
+        },
+        Some(Commands::Export { output }) => stack_set::export(output).await,
+        Some(Commands::Apply { file }) => stack_set::apply(file).await,
+        Some(Commands::Env) => env::run().await,
+        Some(Commands::Which { name }) => which::run(name).await,
+        Some(Commands::Agents) => inventory::agents().await,
+        Some(Commands::Commands) => inventory::commands().await,
+        Some(Commands::Grep { pattern, ignore_case, fixed_strings, remote }) => grep::run(pattern, ignore_case, fixed_strings, remote).await,
+        Some(Commands::Registry { command }) => match command {
+            RegistryCommands::Mirror { dest } => registry::mirror(dest).await,
+        },
+        Some(Commands::Ci { command: CiCommands::Verify { offline, strict, json } }) => ci::verify(offline, strict, json).await,
+        Some(Commands::Schema { output }) => schema::run(output),
+        Some(Commands::Serve { .. }) => serve::run().await,
+        Some(Commands::Run { tasks, prompt_file, jobs, base, permissions }) => {
+            run::run(tasks, prompt_file, jobs, base, permissions).await
         }
-        ConfigCommands::Reset => {
-            let default_config = StacksConfig::default();
-            config::save_config(&default_config)?;
-            println!("Configuration reset to defaults");
+        None => default_checkout(cli.no_worktree, prompt, branch_strategy).await,
+    };
+
+    if let Some(operation) = notify_operation {
+        let notifications = config::load_config().map(|config| config.notifications).unwrap_or_default();
+        core::notifications::operation_finished(&notifications, operation, started, &result).await;
+    }
+
+    if let Some(command) = telemetry_command {
+        let _ = tokio::time::timeout(Duration::from_secs(5), core::telemetry::command_finished(&command, started, &result)).await;
+    }
+
+    if let Some(handle) = update_check {
+        if let Ok(Ok(Ok(Some(notice)))) = tokio::time::timeout(Duration::from_secs(3), handle).await {
+            notice.print();
         }
     }
+
+    if let Err(error) = result {
+        let code = error::report(&error);
+        // exit skips destructors; the guard flushes buffered log lines, including the failure
+        drop(log_guard);
+        std::process::exit(code);
+    }
     Ok(())
 }
 
-async fn show_config() -> Result<()> {
-    let config = config::load_config()?;
-    let config_path = config::get_config_path()?;
-    
-    println!("📋 Current Configuration");
-    println!("═══════════════════════");
-    
-    println!("\n🎯 Tmux Strategy: {} ({})", 
-        config.tmux_strategy.as_str(), 
-        config.tmux_strategy.description()
-    );
-    println!("   Options:");
-    for strategy in [
-        TmuxStrategy::SeparateSessions,
-        TmuxStrategy::QuadSplit,
-        TmuxStrategy::HorizontalSplit,
-        TmuxStrategy::MultipleWindows,
-    ] {
-        let marker = if strategy.as_str() == config.tmux_strategy.as_str() { "→" } else { " " };
-        println!("   {} {}: {}", marker, strategy.as_str(), strategy.description());
+/// Default checkout: pick stacks for a new worktree with the wizard, or install them into the
+/// current checkout with --no-worktree or use-worktrees = false
+async fn default_checkout(
+    no_worktree: bool,
+    prompt: Option<core::prompts::PromptSource>,
+    branch_strategy: Option<cli::worktree::BranchStrategy>,
+) -> Result<()> {
+    let use_worktrees = config::load_config().map(|config| config.use_worktrees).unwrap_or(true);
+    match no_worktree || !use_worktrees {
+        true => checkout::run_with_stack(Vec::new(), Vec::new(), false).await,
+        false => checkout::run(prompt, branch_strategy).await,
     }
-    
-    println!("\n🔄 Prompt Strategy: {}", if config.prompt_for_strategy { "enabled" } else { "disabled" });
-    println!("   • enabled: Ask which tmux strategy to use each time");
-    println!("   • disabled: Use default tmux strategy without asking");
-    
-    println!("\n🖥️  In-Tmux Behavior: {} ({})", 
-        config.in_tmux_behavior.as_str(),
-        config.in_tmux_behavior.description()
-    );
-    println!("   Options:");
-    for behavior in [
-        InTmuxBehavior::NewWindows,
-        InTmuxBehavior::NewSession,
-        InTmuxBehavior::Ask,
-    ] {
-        let marker = if behavior.as_str() == config.in_tmux_behavior.as_str() { "→" } else { " " };
-        println!("   {} {}: {}", marker, behavior.as_str(), behavior.description());
+}
+
+/// Commands slow enough to announce when they finish (see `[notifications]` in the config)
+fn long_operation(command: &Option<Commands>) -> Option<&'static str> {
+    match command {
+        None | Some(Commands::Checkout { .. }) => Some("checkout"),
+        Some(Commands::Pull { .. }) => Some("pull"),
+        Some(Commands::Cleanup { .. }) => Some("cleanup"),
+        Some(Commands::Run { .. }) => Some("run"),
+        _ => None,
     }
-    
-    println!("\n📁 Config file: {}", config_path.display());
-    
-    Ok(())
 }
 
-async fn interactive_config_editor() -> Result<()> {
-    use dialoguer::{Select, Confirm};
-    
-    let mut config = config::load_config()?;
-    let mut changes_made = false;
-    
-    loop {
-        println!("\n📋 Interactive Configuration Editor");
-        println!("══════════════════════════════════");
-        
-        let options = vec![
-            format!("Tmux Strategy: {} ({})", config.tmux_strategy.as_str(), config.tmux_strategy.description()),
-            format!("Prompt Strategy: {}", if config.prompt_for_strategy { "enabled" } else { "disabled" }),
-            format!("In-Tmux Behavior: {} ({})", config.in_tmux_behavior.as_str(), config.in_tmux_behavior.description()),
-            "💾 Save and Exit".to_string(),
-            "❌ Exit without Saving".to_string(),
-        ];
-        
-        let selection = Select::new()
-            .with_prompt("Select setting to modify")
-            .items(&options)
-            .default(0)
-            .interact()?;
-            
-        match selection {
-            0 => {
-                // Tmux Strategy
-                let strategies = vec![
-                    TmuxStrategy::SeparateSessions,
-                    TmuxStrategy::QuadSplit,
-                    TmuxStrategy::HorizontalSplit,
-                    TmuxStrategy::MultipleWindows,
-                ];
-                
-                let strategy_options: Vec<String> = strategies.iter()
-                    .map(|s| format!("{}: {}", s.as_str(), s.description()))
-                    .collect();
-                
-                let current_index = strategies.iter().position(|s| s.as_str() == config.tmux_strategy.as_str()).unwrap_or(0);
-                
-                let selected_strategy = Select::new()
-                    .with_prompt("Choose tmux strategy")
-                    .items(&strategy_options)
-                    .default(current_index)
-                    .interact()?;
-                    
-                if strategies[selected_strategy].as_str() != config.tmux_strategy.as_str() {
-                    config.tmux_strategy = strategies[selected_strategy].clone();
-                    changes_made = true;
-                    println!("✅ Updated tmux strategy to: {}", config.tmux_strategy.as_str());
-                }
-            }
-            1 => {
-                // Prompt Strategy
-                let prompt_options = vec![
-                    "disabled: Use default strategy without asking",
-                    "enabled: Ask which strategy to use each time",
-                ];
-                
-                let current_index = if config.prompt_for_strategy { 1 } else { 0 };
-                
-                let selected = Select::new()
-                    .with_prompt("Choose prompt behavior")
-                    .items(&prompt_options)
-                    .default(current_index)
-                    .interact()?;
-                    
-                let new_value = selected == 1;
-                if new_value != config.prompt_for_strategy {
-                    config.prompt_for_strategy = new_value;
-                    changes_made = true;
-                    println!("✅ Updated prompt strategy to: {}", if new_value { "enabled" } else { "disabled" });
-                }
-            }
-            2 => {
-                // In-Tmux Behavior
-                let behaviors = vec![
-                    InTmuxBehavior::NewWindows,
-                    InTmuxBehavior::NewSession,
-                    InTmuxBehavior::Ask,
-                ];
-                
-                let behavior_options: Vec<String> = behaviors.iter()
-                    .map(|b| format!("{}: {}", b.as_str(), b.description()))
-                    .collect();
-                
-                let current_index = behaviors.iter().position(|b| b.as_str() == config.in_tmux_behavior.as_str()).unwrap_or(0);
-                
-                let selected_behavior = Select::new()
-                    .with_prompt("Choose in-tmux behavior")
-                    .items(&behavior_options)
-                    .default(current_index)
-                    .interact()?;
-                    
-                if behaviors[selected_behavior].as_str() != config.in_tmux_behavior.as_str() {
-                    config.in_tmux_behavior = behaviors[selected_behavior].clone();
-                    changes_made = true;
-                    println!("✅ Updated in-tmux behavior to: {}", config.in_tmux_behavior.as_str());
-                }
-            }
-            3 => {
-                // Save and Exit
-                if changes_made {
-                    config::save_config(&config)?;
-                    println!("💾 Configuration saved successfully!");
-                } else {
-                    println!("No changes to save.");
-                }
-                break;
-            }
-            4 => {
-                // Exit without Saving
-                if changes_made {
-                    let confirm_exit = Confirm::new()
-                        .with_prompt("You have unsaved changes. Exit anyway?")
-                        .default(false)
-                        .interact()?;
-                    if !confirm_exit {
-                        continue;
-                    }
-                }
-                println!("Configuration editor exited without saving.");
-                break;
-            }
-            _ => unreachable!(),
-        }
+/// Commands recorded by telemetry; prompt and status line queries run too often to be worth recording
+fn records_telemetry(command: &Option<Commands>) -> bool {
+    !matches!(command, Some(Commands::Statusline { .. } | Commands::Query { .. } | Commands::Serve { .. } | Commands::Telemetry { .. }))
+}
+
+/// The subcommands that ran, e.g. "ci verify", without arguments; "default" for plain `stacks`
+fn command_path(matches: &clap::ArgMatches) -> String {
+    let mut names = Vec::new();
+    let mut current = matches;
+    while let Some((name, sub_matches)) = current.subcommand() {
+        names.push(name);
+        current = sub_matches;
     }
-    
-    Ok(())
+    match names.is_empty() {
+        true => "default".to_string(),
+        false => names.join(" "),
+    }
+}
+
+fn update_checks_enabled(command: &Option<Commands>) -> bool {
+    if matches!(command, Some(Commands::Config { .. } | Commands::Cache { .. } | Commands::Daemon { .. } | Commands::Ci { .. } | Commands::Auth { .. } | Commands::Schema { .. } | Commands::Serve { .. } | Commands::Statusline { .. } | Commands::Query { .. } | Commands::Telemetry { .. })) {
+        return false;
+    }
+    config::load_config()
+        .map(|config| config.update_checks)
+        .unwrap_or(false)
+}
+
+/// Expand a user alias from `[aliases]` in the config before clap sees the arguments
+fn expand_aliases(args: Vec<String>) -> Result<Vec<String>> {
+    // A broken config is reported by the command that loads it
+    let aliases = match config::load_config() {
+        Ok(config) if !config.aliases.is_empty() => config.aliases,
+        _ => return Ok(args),
+    };
+    let command = Cli::command();
+    let commands: Vec<String> = command.get_subcommands()
+        .flat_map(|subcommand| std::iter::once(subcommand.get_name()).chain(subcommand.get_all_aliases()))
+        .chain(std::iter::once("help"))
+        .map(str::to_string)
+        .collect();
+    let value_options: Vec<String> = command.get_arguments()
+        .filter(|arg| arg.get_action().takes_values())
+        .flat_map(|arg| {
+            let long = arg.get_long().map(|long| format!("--{}", long));
+            let short = arg.get_short().map(|short| format!("-{}", short));
+            long.into_iter().chain(short)
+        })
+        .collect();
+    config::expand_alias(args, &aliases, &commands, &value_options)
 }
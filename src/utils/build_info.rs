@@ -0,0 +1,26 @@
+//! What this binary is and how it was built, embedded by build.rs
+
+pub const VERSION: &str = env!("CARGO_PKG_VERSION");
+/// Short commit hash, or "unknown" when built outside a git checkout
+pub const GIT_SHA: &str = env!("STACKS_GIT_SHA");
+/// UTC date as YYYY-MM-DD
+pub const BUILD_DATE: &str = env!("STACKS_BUILD_DATE");
+/// Target triple, e.g. aarch64-apple-darwin
+pub const TARGET: &str = env!("STACKS_TARGET");
+
+/// `stacks --version`: "0.1.0 (4e1f0c2a9b3d 2025-06-01) x86_64-unknown-linux-gnu"
+pub const LONG_VERSION: &str = concat!(
+    env!("CARGO_PKG_VERSION"), " (", env!("STACKS_GIT_SHA"), " ", env!("STACKS_BUILD_DATE"), ") ", env!("STACKS_TARGET")
+);
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_long_version() {
+        assert!(LONG_VERSION.starts_with(VERSION));
+        assert!(LONG_VERSION.ends_with(TARGET));
+        assert_eq!(BUILD_DATE.len(), 10);
+    }
+}
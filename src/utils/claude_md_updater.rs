@@ -1,308 +1,862 @@
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 use anyhow::{Result, Context};
 
+use crate::config::ClaudeMdMode;
+use crate::core::events::{default_reporter, Event, Reporter};
+use crate::core::git::Repo;
+use crate::core::worktrees::is_linked_worktree;
+use crate::utils::project_info::ProjectInfo;
+use crate::utils::files;
+
+/// Untracked instructions file Claude reads alongside CLAUDE.md
+pub const LOCAL_CLAUDE_MD: &str = "CLAUDE.local.md";
+
+/// Template for a CLAUDE.md created from scratch, unless `claude-md-template` points at another
+///
+/// `{project}` is the project name, `{commands}` a section of detected build/test/lint commands
+/// and `{stacks}` where the first stack section goes.
+pub const DEFAULT_TEMPLATE: &str = "# {project}\n\n{commands}\n{stacks}\n";
+
+/// Line closing a stack's managed section
+const SECTION_END: &str = "<!-- stacks:end -->";
+
+/// Lines around the generated description of a feature worktree's scope
+const WORKTREE_BEGIN: &str = "<!-- stacks:worktree:begin -->";
+const WORKTREE_END: &str = "<!-- stacks:worktree:end -->";
+
+/// Demarcation line older versions wrote above all stack imports
+const LEGACY_DEMARCATION: &str = "----";
+
 pub struct ClaudeMdUpdater {
     claude_md_path: PathBuf,
+    mode: ClaudeMdMode,
+    template: Option<String>,
+    reporter: Arc<dyn Reporter>,
 }
 
 impl ClaudeMdUpdater {
     pub fn new() -> Self {
+        Self::with_path(PathBuf::from("CLAUDE.md"))
+    }
+
+    /// Manage a CLAUDE.md other than the one in the current directory
+    pub fn with_path(claude_md_path: PathBuf) -> Self {
         Self {
-            claude_md_path: PathBuf::from("CLAUDE.md"),
+            claude_md_path,
+            mode: ClaudeMdMode::Import,
+            template: None,
+            reporter: default_reporter(),
         }
     }
 
-    /// Add an import statement for a stack to CLAUDE.md with demarcation line
-    pub async fn add_stack_import_with_demarcation(&self, stack_name: &str) -> Result<()> {
-        let import_line = format!("@stacks/{}/CLAUDE.md", stack_name);
-        
-        if self.claude_md_path.exists() {
-            let content = tokio::fs::read_to_string(&self.claude_md_path)
-                .await
-                .with_context(|| format!("Failed to read {}", self.claude_md_path.display()))?;
-            
-            // Check if the import already exists
-            if content.contains(&import_line) {
-                return Ok(()); // Already imported
-            }
+    pub fn with_reporter(mut self, reporter: Arc<dyn Reporter>) -> Self {
+        self.reporter = reporter;
+        self
+    }
 
-            let updated_content = self.insert_stack_import_with_demarcation(&content, &import_line);
-            
-            tokio::fs::write(&self.claude_md_path, updated_content)
-                .await
-                .with_context(|| format!("Failed to write {}", self.claude_md_path.display()))?;
+    /// Updater for the checkout at `root`: its CLAUDE.md, or CLAUDE.local.md in a linked worktree
+    ///
+    /// A worktree's CLAUDE.md is the tracked file shared with the main checkout, so stacks installed
+    /// in the worktree are described in its own untracked CLAUDE.local.md instead and never leak
+    /// back into main when the branch is merged.
+    pub fn for_checkout(root: &Path) -> Self {
+        let file_name = if is_linked_worktree(root) { LOCAL_CLAUDE_MD } else { "CLAUDE.md" };
+        Self::with_path(root.join(file_name))
+    }
+
+    /// Updater for the checkout at `root` with the configured mode and template
+    pub fn for_project(root: &Path) -> Result<Self> {
+        Ok(Self::for_checkout(root)
+            .with_mode(crate::config::claude_md_mode()?)
+            .with_template(crate::config::claude_md_template()?))
+    }
+
+    /// Template used when CLAUDE.md doesn't exist yet; None uses `DEFAULT_TEMPLATE`
+    pub fn with_template(mut self, template: Option<String>) -> Self {
+        self.template = template;
+        self
+    }
+
+    /// Content for a new file: the template filled in, with the first stack's section
+    fn render_new_file(&self, stack_name: &str, body: &str) -> String {
+        let root = match self.claude_md_path.parent() {
+            Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+            _ => PathBuf::from("."),
+        };
+        let project = ProjectInfo::detect(&root);
+        let template = self.template.as_deref().unwrap_or(DEFAULT_TEMPLATE);
+
+        let rendered = template
+            .replace("{project}", &project.name)
+            .replace("{commands}", &project.commands_section());
+
+        if rendered.contains("{stacks}") {
+            let section = format!("{}\n{}\n{}", section_begin(stack_name), body.trim_end(), SECTION_END);
+            let lines: Vec<&str> = rendered.lines().collect();
+            tidy(&lines).replace("{stacks}", &section)
         } else {
-            // Create new CLAUDE.md with demarcation
-            let content = format!("# Project Instructions\n\n----\n\nSee {}.\n", import_line);
-            tokio::fs::write(&self.claude_md_path, content)
-                .await
-                .with_context(|| format!("Failed to create {}", self.claude_md_path.display()))?;
+            insert_stack_section(&rendered, stack_name, body)
         }
-        
-        Ok(())
     }
 
-    /// Add an import statement for a stack to CLAUDE.md
+    /// Import stack instructions by reference or embed them inline
+    pub fn with_mode(mut self, mode: ClaudeMdMode) -> Self {
+        self.mode = mode;
+        self
+    }
+
+    /// Check whether CLAUDE.md imports or embeds a stack's CLAUDE.md
+    pub async fn has_stack_import(&self, stack_name: &str) -> Result<bool> {
+        if !self.claude_md_path.exists() {
+            return Ok(false);
+        }
+
+        let content = tokio::fs::read_to_string(&self.claude_md_path)
+            .await
+            .with_context(|| format!("Failed to read {}", self.claude_md_path.display()))?;
+        Ok(content.contains(&import_reference(stack_name)) || content.contains(&section_begin(stack_name)))
+    }
+
+    fn is_local_file(&self) -> bool {
+        self.claude_md_path.file_name().is_some_and(|name| name == LOCAL_CLAUDE_MD)
+    }
+
+    /// Section body for a stack: an @-import, or its CLAUDE.md verbatim in embed mode
+    async fn section_body(&self, stack_name: &str) -> Result<String> {
+        match self.mode {
+            ClaudeMdMode::Import => Ok(format!("See {}.", import_reference(stack_name))),
+            ClaudeMdMode::Embed => {
+                let stack_claude_md = self.claude_md_path
+                    .parent()
+                    .map(|dir| dir.to_path_buf())
+                    .unwrap_or_default()
+                    .join("stacks")
+                    .join(stack_name)
+                    .join("CLAUDE.md");
+                tokio::fs::read_to_string(&stack_claude_md)
+                    .await
+                    .with_context(|| format!("Failed to read {} to embed", stack_claude_md.display()))
+            }
+        }
+    }
+
+    /// Add a stack's section (`<!-- stacks:begin name -->` … `<!-- stacks:end -->`) with its instructions
+    ///
+    /// In embed mode an existing section is refreshed with the stack's current CLAUDE.md.
     pub async fn add_stack_import(&self, stack_name: &str) -> Result<()> {
-        let import_line = format!("@stacks/{}/CLAUDE.md", stack_name);
-        
+        let body = self.section_body(stack_name).await?;
+
         if self.claude_md_path.exists() {
             let content = tokio::fs::read_to_string(&self.claude_md_path)
                 .await
                 .with_context(|| format!("Failed to read {}", self.claude_md_path.display()))?;
-            
-            // Check if the import already exists
-            if content.contains(&import_line) {
-                return Ok(()); // Already imported
+
+            let updated_content = insert_stack_section(&content, stack_name, &body);
+            if updated_content == content {
+                return Ok(());
             }
 
-            // Find the best place to insert the import
-            let updated_content = self.insert_stack_import(&content, &import_line);
-            
-            tokio::fs::write(&self.claude_md_path, updated_content)
-                .await
+            files::write(&self.claude_md_path, updated_content)
                 .with_context(|| format!("Failed to write to {}", self.claude_md_path.display()))?;
         } else {
-            // Create new CLAUDE.md with the import
-            let content = format!("# Project Instructions\n\nSee {} for additional instructions.\n", import_line);
-            tokio::fs::write(&self.claude_md_path, content)
-                .await
-                .with_context(|| format!("Failed to create {}", self.claude_md_path.display()))?;
-        }
-
-        println!("  📝 Added import to CLAUDE.md: {}", import_line);
-        Ok(())
-    }
-
-    /// Insert the stack import in an appropriate location
-    fn insert_stack_import(&self, content: &str, import_line: &str) -> String {
-        let lines: Vec<&str> = content.lines().collect();
-        let mut result_lines = Vec::new();
-        let mut import_inserted = false;
-
-        // Look for existing stack imports section or create one
-        for (i, line) in lines.iter().enumerate() {
-            result_lines.push(line.to_string());
-            
-            // If we find existing stack imports, insert after them
-            if line.starts_with("@stacks/") && !import_inserted {
-                // Find the end of the stack imports block
-                let mut j = i + 1;
-                while j < lines.len() && (lines[j].starts_with("@stacks/") || lines[j].trim().is_empty()) {
-                    result_lines.push(lines[j].to_string());
-                    j += 1;
-                }
-                
-                // Insert our import
-                result_lines.push(import_line.to_string());
-                import_inserted = true;
-                
-                // Skip the lines we already added
-                for k in (i + 1)..j {
-                    if k < lines.len() {
-                        // Already added above
-                    }
-                }
-                continue;
-            }
-            
-            // If we haven't found imports yet and we're at the end of the header section,
-            // insert the import
-            if !import_inserted && 
-               (line.trim().is_empty() && 
-                i > 0 && 
-                !lines[i-1].trim().is_empty() && 
-                !lines[i-1].starts_with("#")) {
-                result_lines.push("".to_string()); // Empty line before imports
-                result_lines.push(format!("See {} for additional stack instructions.", import_line));
-                result_lines.push("".to_string()); // Empty line after imports
-                import_inserted = true;
+            if self.is_local_file() {
+                exclude_from_git(&self.claude_md_path)?;
             }
-        }
 
-        // If we still haven't inserted it, add it at the end
-        if !import_inserted {
-            if !result_lines.is_empty() && !result_lines.last().unwrap().is_empty() {
-                result_lines.push("".to_string());
-            }
-            result_lines.push("".to_string());
-            result_lines.push(format!("See {} for additional stack instructions.", import_line));
+            // Create a new CLAUDE.md from the template with the stack's section
+            let content = self.render_new_file(stack_name, &body);
+            files::write(&self.claude_md_path, content)
+                .with_context(|| format!("Failed to create {}", self.claude_md_path.display()))?;
         }
 
-        result_lines.join("\n")
+        self.reporter.report(&Event::ClaudeMdSectionAdded {
+            stack: stack_name.to_string(),
+            embedded: self.mode == ClaudeMdMode::Embed,
+        });
+        Ok(())
     }
 
-    /// Remove a stack import from CLAUDE.md
-    #[allow(dead_code)]
+    /// Remove a stack's section (and any legacy import line) from CLAUDE.md
     pub async fn remove_stack_import(&self, stack_name: &str) -> Result<()> {
         if !self.claude_md_path.exists() {
             return Ok(()); // Nothing to remove
         }
 
-        let import_line = format!("@stacks/{}/CLAUDE.md", stack_name);
         let content = tokio::fs::read_to_string(&self.claude_md_path)
             .await
             .with_context(|| format!("Failed to read {}", self.claude_md_path.display()))?;
 
-        let lines: Vec<&str> = content.lines().collect();
-        let filtered_lines: Vec<String> = lines
-            .iter()
-            .filter(|line| !line.contains(&import_line))
-            .map(|line| line.to_string())
-            .collect();
+        if !content.contains(&section_begin(stack_name)) && !content.contains(&import_reference(stack_name)) {
+            return Ok(()); // Leave files without the stack untouched
+        }
 
-        let updated_content = filtered_lines.join("\n");
-        
-        tokio::fs::write(&self.claude_md_path, updated_content)
-            .await
+        let updated_content = remove_stack_section(&content, stack_name);
+
+        files::write(&self.claude_md_path, updated_content)
             .with_context(|| format!("Failed to write to {}", self.claude_md_path.display()))?;
 
-        println!("  📝 Removed import from CLAUDE.md: {}", import_line);
+        self.reporter.report(&Event::ClaudeMdSectionRemoved { stack: stack_name.to_string() });
         Ok(())
     }
 
-    /// Insert stack import with demarcation line handling
-    pub fn insert_stack_import_with_demarcation(&self, content: &str, import_line: &str) -> String {
-        const DEMARCATION: &str = "----";
-        
-        // Check if demarcation line exists
-        if let Some(_demarcation_pos) = content.find(DEMARCATION) {
-            // Find the position after the demarcation line
-            let lines: Vec<&str> = content.lines().collect();
-            let mut result_lines = Vec::new();
-            
-            for line in &lines {
-                result_lines.push(line.to_string());
-                
-                if line.trim() == DEMARCATION {
-                    // Add empty line then the import
-                    result_lines.push("".to_string());
-                    result_lines.push(format!("See {}.", import_line));
+    /// Check the stack sections against the installed stacks
+    pub async fn check(&self, installed: &[String]) -> Result<Vec<ClaudeMdIssue>> {
+        let content = if self.claude_md_path.exists() {
+            tokio::fs::read_to_string(&self.claude_md_path)
+                .await
+                .with_context(|| format!("Failed to read {}", self.claude_md_path.display()))?
+        } else {
+            String::new()
+        };
+
+        Ok(check_stack_sections(&content, installed))
+    }
+
+    /// Replace all stack sections and imports with one section per installed stack, in order
+    pub async fn rebuild(&self, installed: &[String]) -> Result<()> {
+        let mut rebuilt = if self.claude_md_path.exists() {
+            let content = tokio::fs::read_to_string(&self.claude_md_path)
+                .await
+                .with_context(|| format!("Failed to read {}", self.claude_md_path.display()))?;
+            remove_all_stack_sections(&content)
+        } else {
+            match installed.first() {
+                Some(first) => self.render_new_file(first, &self.section_body(first).await?),
+                None => return Ok(()),
+            }
+        };
+
+        for stack_name in installed {
+            let body = self.section_body(stack_name).await?;
+            rebuilt = insert_stack_section(&rebuilt, stack_name, &body);
+        }
+
+        files::write(&self.claude_md_path, rebuilt)
+            .with_context(|| format!("Failed to write {}", self.claude_md_path.display()))?;
+
+        Ok(())
+    }
+
+    /// Write the section describing the worktree's task and boundaries, replacing an earlier one
+    pub fn set_worktree_scope(&self, body: &str) -> Result<()> {
+        let content = match self.claude_md_path.exists() {
+            true => std::fs::read_to_string(&self.claude_md_path)
+                .with_context(|| format!("Failed to read {}", self.claude_md_path.display()))?,
+            false => {
+                if self.is_local_file() {
+                    exclude_from_git(&self.claude_md_path)?;
                 }
+                String::new()
             }
-            
-            result_lines.join("\n")
-        } else {
-            // No demarcation line exists, add it with the import
-            format!("{}\n\n{}\n\nSee {}.\n", content.trim(), DEMARCATION, import_line)
+        };
+
+        let updated_content = insert_worktree_section(&content, body);
+        if updated_content == content {
+            return Ok(());
         }
+        files::write(&self.claude_md_path, updated_content)
+            .with_context(|| format!("Failed to write {}", self.claude_md_path.display()))
     }
 
-    /// Remove all imports below demarcation line (used in cleanup)
-    pub async fn cleanup_demarcated_imports(&self) -> Result<()> {
-        const DEMARCATION: &str = "----";
-        
+    /// Remove every stack section, keeping content users added around them (used in cleanup)
+    pub async fn remove_all_stack_sections(&self) -> Result<()> {
         if !self.claude_md_path.exists() {
             return Ok(()); // Nothing to clean
         }
-        
+
         let content = tokio::fs::read_to_string(&self.claude_md_path)
             .await
             .with_context(|| format!("Failed to read {}", self.claude_md_path.display()))?;
-        
-        if let Some(_demarcation_pos) = content.find(DEMARCATION) {
-            let lines: Vec<&str> = content.lines().collect();
-            let mut result_lines = Vec::new();
-            
-            for line in &lines {
-                if line.trim() == DEMARCATION {
-                    result_lines.push(line.to_string());
-                    break; // Stop here, removing everything after demarcation
-                } else {
-                    result_lines.push(line.to_string());
-                }
-            }
-            
-            let cleaned_content = result_lines.join("\n");
-            
-            tokio::fs::write(&self.claude_md_path, cleaned_content)
-                .await
-                .with_context(|| format!("Failed to write cleaned {}", self.claude_md_path.display()))?;
+
+        if !content.contains("<!-- stacks:") && !content.contains("@stacks/") {
+            return Ok(()); // Leave files without stack sections untouched
         }
-        
+
+        let cleaned_content = remove_all_stack_sections(&content);
+
+        files::write(&self.claude_md_path, cleaned_content)
+            .with_context(|| format!("Failed to write cleaned {}", self.claude_md_path.display()))?;
+
         Ok(())
     }
 }
 
+/// Keep a CLAUDE.local.md out of commits via the repository's info/exclude, unless already ignored
+fn exclude_from_git(path: &Path) -> Result<()> {
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir.to_path_buf(),
+        _ => PathBuf::from("."),
+    };
+
+    let repo = match Repo::discover(&dir) {
+        Ok(repo) => repo,
+        // Not a git checkout; nothing to exclude from
+        Err(_) => return Ok(()),
+    };
+    if repo.is_ignored(&dir.join(LOCAL_CLAUDE_MD)) {
+        return Ok(());
+    }
+
+    repo.exclude(&format!("/{}", LOCAL_CLAUDE_MD))
+        .context("Failed to update the repository's info/exclude")
+}
+
+fn import_reference(stack_name: &str) -> String {
+    format!("@stacks/{}/CLAUDE.md", stack_name)
+}
+
+fn section_begin(stack_name: &str) -> String {
+    format!("<!-- stacks:begin {} -->", stack_name)
+}
+
+/// Line range `[begin, end]` of a stack's section, if present and closed
+fn find_section(lines: &[&str], stack_name: &str) -> Option<(usize, usize)> {
+    let begin_marker = section_begin(stack_name);
+    let begin = lines.iter().position(|line| line.trim() == begin_marker)?;
+    let end = lines[begin..].iter().position(|line| line.trim() == SECTION_END)? + begin;
+    Some((begin, end))
+}
+
+/// Add or replace a stack's section, keeping all sections together and sorted by stack name
+///
+/// The result is normalized, so inserting is idempotent and doesn't depend on the order stacks
+/// were added in. A legacy import line for the stack is replaced by its section.
+pub fn insert_stack_section(content: &str, stack_name: &str, body: &str) -> String {
+    let legacy_import = import_reference(stack_name);
+    let lines = retain_outside_sections(content, |line| !is_legacy_import_line(line, &legacy_import));
+    let section = format!("{}\n{}\n{}", section_begin(stack_name), body.trim_end(), SECTION_END);
+
+    let sections = closed_sections(&lines);
+    let (before, after): (&[&str], &[&str]) = match find_section(&lines, stack_name) {
+        Some((begin, end)) => (&lines[..begin], &lines[end + 1..]),
+        None => match sections.iter().find(|(name, _, _)| name.as_str() > stack_name) {
+            // Before the first section that sorts after this stack
+            Some((_, begin, _)) => (&lines[..*begin], &lines[*begin..]),
+            None => match sections.last() {
+                // After the last section
+                Some((_, _, end)) => (&lines[..=*end], &lines[end + 1..]),
+                // No sections yet: at the end of the file
+                None => (&lines[..], &[]),
+            },
+        },
+    };
+
+    let mut result: Vec<&str> = before.to_vec();
+    if before.last().is_some_and(|line| !line.trim().is_empty()) {
+        result.push("");
+    }
+    result.extend(section.lines());
+    if !after.is_empty() && !after[0].trim().is_empty() {
+        result.push("");
+    }
+    result.extend_from_slice(after);
+
+    tidy(&result)
+}
+
+/// `(stack name, begin, end)` line ranges of the well-formed sections, in file order
+fn closed_sections(lines: &[&str]) -> Vec<(String, usize, usize)> {
+    lines.iter()
+        .enumerate()
+        .filter_map(|(index, line)| {
+            let name = section_name(line)?;
+            let end = closing_marker(lines, index)?;
+            Some((name.to_string(), index, end))
+        })
+        .collect()
+}
+
+/// Lines of the content, dropping lines outside sections that `keep` rejects
+fn retain_outside_sections(content: &str, keep: impl Fn(&str) -> bool) -> Vec<&str> {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut result = Vec::new();
+    let mut index = 0;
+
+    while index < lines.len() {
+        if section_name(lines[index]).is_some() {
+            if let Some(end) = closing_marker(&lines, index) {
+                result.extend_from_slice(&lines[index..=end]);
+                index = end + 1;
+                continue;
+            }
+        }
+        if keep(lines[index]) {
+            result.push(lines[index]);
+        }
+        index += 1;
+    }
+
+    result
+}
+
+/// Remove a stack's section and any import line older versions wrote for it
+pub fn remove_stack_section(content: &str, stack_name: &str) -> String {
+    let legacy_import = import_reference(stack_name);
+    let mut lines = retain_outside_sections(content, |line| !is_legacy_import_line(line, &legacy_import));
+    if let Some((begin, end)) = find_section(&lines, stack_name) {
+        lines.drain(begin..=end);
+    }
+
+    tidy(&lines)
+}
+
+/// Add or replace the worktree scope section; a new one goes at the end of the file
+pub fn insert_worktree_section(content: &str, body: &str) -> String {
+    let section = format!("{}\n{}\n{}", WORKTREE_BEGIN, body.trim_end(), WORKTREE_END);
+    let lines: Vec<&str> = content.lines().collect();
+    let mut result: Vec<&str> = Vec::new();
+    match find_worktree_section(&lines) {
+        Some((begin, end)) => {
+            result.extend_from_slice(&lines[..begin]);
+            result.extend(section.lines());
+            result.extend_from_slice(&lines[end + 1..]);
+        }
+        None => {
+            result.extend_from_slice(&lines);
+            result.push("");
+            result.extend(section.lines());
+        }
+    }
+    tidy(&result)
+}
+
+/// Remove the worktree scope section, if any
+pub fn remove_worktree_section(content: &str) -> String {
+    let mut lines: Vec<&str> = content.lines().collect();
+    if let Some((begin, end)) = find_worktree_section(&lines) {
+        lines.drain(begin..=end);
+    }
+    tidy(&lines)
+}
+
+fn find_worktree_section(lines: &[&str]) -> Option<(usize, usize)> {
+    let begin = lines.iter().position(|line| line.trim() == WORKTREE_BEGIN)?;
+    let end = lines[begin..].iter().position(|line| line.trim() == WORKTREE_END)? + begin;
+    Some((begin, end))
+}
+
+/// Remove all stack sections, the worktree scope and legacy imports, leaving the rest of the file intact
+pub fn remove_all_stack_sections(content: &str) -> String {
+    let content = remove_worktree_section(content);
+    let all_lines: Vec<&str> = content.lines().collect();
+    let mut lines: Vec<&str> = Vec::new();
+    let mut index = 0;
+
+    while index < all_lines.len() {
+        let line = all_lines[index];
+        if section_name(line).is_some() {
+            // A section runs to its end marker; an unclosed begin marker is dropped on its own
+            // so a damaged file never loses the content after it
+            match closing_marker(&all_lines, index) {
+                Some(end) => index = end + 1,
+                None => index += 1,
+            }
+            continue;
+        }
+        if line.trim() != SECTION_END && !is_legacy_import_line(line, "@stacks/") {
+            lines.push(line);
+        }
+        index += 1;
+    }
+
+    // The legacy demarcation only separated stack imports; drop it once nothing follows it
+    while let Some(last) = lines.iter().rposition(|line| !line.trim().is_empty()) {
+        if lines[last].trim() == LEGACY_DEMARCATION {
+            lines.truncate(last);
+        } else {
+            break;
+        }
+    }
+
+    tidy(&lines)
+}
+
+/// Stack name of a `<!-- stacks:begin name -->` line
+fn section_name(line: &str) -> Option<&str> {
+    line.trim()
+        .strip_prefix("<!-- stacks:begin ")
+        .and_then(|rest| rest.strip_suffix(" -->"))
+        .map(str::trim)
+}
+
+/// Index of the end marker closing the section that begins at `begin`, unless another section begins first
+fn closing_marker(lines: &[&str], begin: usize) -> Option<usize> {
+    for (offset, line) in lines[begin + 1..].iter().enumerate() {
+        if line.trim() == SECTION_END {
+            return Some(begin + 1 + offset);
+        }
+        if section_name(line).is_some() {
+            return None;
+        }
+    }
+    None
+}
+
+/// Stack name referenced by a legacy `See @stacks/<name>/CLAUDE.md.` line
+fn legacy_import_name(line: &str) -> Option<&str> {
+    let trimmed = line.trim();
+    let rest = trimmed.strip_prefix("See ").unwrap_or(trimmed).strip_prefix("@stacks/")?;
+    rest.split_once("/CLAUDE.md").map(|(name, _)| name)
+}
+
+/// A problem with the stack sections of a CLAUDE.md
+#[derive(Debug, Clone, PartialEq)]
+pub enum ClaudeMdIssue {
+    /// An installed stack has no import or section
+    Missing(String),
+    /// An installed stack is imported more than once
+    Duplicate(String, usize),
+    /// An import or section for a stack that isn't in stacks/
+    Orphaned(String),
+    /// A begin marker without its end marker (1-based line)
+    UnclosedSection(String, usize),
+    /// An end marker without a begin marker (1-based line)
+    StrayEnd(usize),
+}
+
+impl ClaudeMdIssue {
+    pub fn describe(&self) -> String {
+        match self {
+            ClaudeMdIssue::Missing(name) => format!("Stack {} is installed but not referenced in CLAUDE.md", name),
+            ClaudeMdIssue::Duplicate(name, count) => format!("Stack {} is referenced {} times", name, count),
+            ClaudeMdIssue::Orphaned(name) => format!("CLAUDE.md references stack {}, but stacks/{} does not exist", name, name),
+            ClaudeMdIssue::UnclosedSection(name, line) => format!("Section for {} at line {} has no end marker", name, line),
+            ClaudeMdIssue::StrayEnd(line) => format!("End marker at line {} has no matching begin marker", line),
+        }
+    }
+}
+
+/// Check that every installed stack is referenced exactly once and the markers are well formed
+pub fn check_stack_sections(content: &str, installed: &[String]) -> Vec<ClaudeMdIssue> {
+    let lines: Vec<&str> = content.lines().collect();
+    let mut issues = Vec::new();
+    let mut references: Vec<String> = Vec::new();
+    let mut open_section: Option<usize> = None;
+
+    for (index, line) in lines.iter().enumerate() {
+        if let Some(name) = section_name(line) {
+            if closing_marker(&lines, index).is_none() {
+                issues.push(ClaudeMdIssue::UnclosedSection(name.to_string(), index + 1));
+            } else {
+                references.push(name.to_string());
+                open_section = Some(index);
+            }
+        } else if line.trim() == SECTION_END {
+            if open_section.take().is_none() {
+                issues.push(ClaudeMdIssue::StrayEnd(index + 1));
+            }
+        } else if open_section.is_none() {
+            if let Some(name) = legacy_import_name(line) {
+                references.push(name.to_string());
+            }
+        }
+    }
+
+    for name in installed {
+        match references.iter().filter(|reference| *reference == name).count() {
+            0 => issues.push(ClaudeMdIssue::Missing(name.clone())),
+            1 => {}
+            count => issues.push(ClaudeMdIssue::Duplicate(name.clone(), count)),
+        }
+    }
+
+    let mut orphaned: Vec<&String> = references.iter().filter(|reference| !installed.contains(reference)).collect();
+    orphaned.sort();
+    orphaned.dedup();
+    issues.extend(orphaned.into_iter().map(|name| ClaudeMdIssue::Orphaned(name.clone())));
+
+    issues
+}
+
+/// `See @stacks/<name>/CLAUDE.md…` lines written before sections existed
+fn is_legacy_import_line(line: &str, import: &str) -> bool {
+    let trimmed = line.trim();
+    (trimmed.starts_with("See @stacks/") || trimmed.starts_with("@stacks/")) && trimmed.contains(import)
+}
+
+/// Join lines, collapsing runs of blank lines outside sections and ending with a single newline
+fn tidy(lines: &[&str]) -> String {
+    let mut result: Vec<&str> = Vec::new();
+    let mut in_section = false;
+    for &line in lines {
+        if section_name(line).is_some() {
+            in_section = true;
+        } else if line.trim() == SECTION_END {
+            in_section = false;
+        } else if !in_section && line.trim().is_empty() && result.last().is_none_or(|last| last.trim().is_empty()) {
+            continue;
+        }
+        result.push(line);
+    }
+    while result.last().is_some_and(|last| last.trim().is_empty()) {
+        result.pop();
+    }
+
+    if result.is_empty() {
+        String::new()
+    } else {
+        format!("{}\n", result.join("\n"))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
     #[test]
-    fn test_insert_stack_import() {
-        let updater = ClaudeMdUpdater::new();
-        
+    fn test_insert_stack_section() {
         let content = "# My Project\n\nThis is my project.\n\n## Features\n\n- Feature 1\n";
-        let import_line = "@stacks/linting/CLAUDE.md";
-        
-        let result = updater.insert_stack_import(content, import_line);
-        
-        assert!(result.contains(import_line));
+
+        let result = insert_stack_section(content, "linting", "See @stacks/linting/CLAUDE.md.");
+
+        assert!(result.starts_with(content));
+        assert!(result.contains("<!-- stacks:begin linting -->\nSee @stacks/linting/CLAUDE.md.\n<!-- stacks:end -->"));
+    }
+
+    #[test]
+    fn test_replace_existing_section() {
+        let content = "# My Project\n\n<!-- stacks:begin testing -->\nold\n<!-- stacks:end -->\n\n## Notes\n";
+
+        let result = insert_stack_section(content, "testing", "new");
+
+        assert!(result.contains("<!-- stacks:begin testing -->\nnew\n<!-- stacks:end -->\n\n## Notes"));
+        assert!(!result.contains("old"));
     }
-    
+
     #[test]
-    fn test_insert_with_existing_imports() {
-        let updater = ClaudeMdUpdater::new();
-        
-        let content = "# My Project\n\n@stacks/testing/CLAUDE.md\n\n## Features\n";
-        let import_line = "@stacks/linting/CLAUDE.md";
-        
-        let result = updater.insert_stack_import(content, import_line);
-        
-        assert!(result.contains("@stacks/testing/CLAUDE.md"));
-        assert!(result.contains("@stacks/linting/CLAUDE.md"));
+    fn test_remove_all_sections_keeps_legacy_notes() {
+        let content = "# My Project\n\n----\n\nSee @stacks/old-stack/CLAUDE.md.\n\nMy own note.\n";
+
+        let result = remove_all_stack_sections(content);
+
+        assert!(!result.contains("@stacks/old-stack"));
+        assert!(result.contains("My own note."));
+        assert!(result.contains(LEGACY_DEMARCATION));
+    }
+
+    #[test]
+    fn test_worktree_section() {
+        let content = "# Notes\n\nMy own note.\n";
+        let added = insert_worktree_section(content, "Task: auth");
+        assert_eq!(added, format!("# Notes\n\nMy own note.\n\n{}\nTask: auth\n{}\n", WORKTREE_BEGIN, WORKTREE_END));
+
+        let replaced = insert_worktree_section(&added, "Task: billing");
+        assert!(replaced.contains("Task: billing"));
+        assert!(!replaced.contains("Task: auth"));
+        assert_eq!(insert_worktree_section(&replaced, "Task: billing"), replaced);
+
+        assert_eq!(remove_all_stack_sections(&replaced), content);
+        assert_eq!(insert_worktree_section("", "Task: auth"), format!("{}\nTask: auth\n{}\n", WORKTREE_BEGIN, WORKTREE_END));
+    }
+
+    #[test]
+    fn test_check_stack_sections() {
+        let installed = vec!["lint".to_string(), "test".to_string(), "docs".to_string()];
+        let content = "# Project\n\n\
+<!-- stacks:begin lint -->\nSee @stacks/lint/CLAUDE.md.\n<!-- stacks:end -->\n\n\
+See @stacks/lint/CLAUDE.md.\n\n\
+<!-- stacks:begin gone -->\nSee @stacks/gone/CLAUDE.md.\n<!-- stacks:end -->\n\n\
+<!-- stacks:begin test -->\nunclosed\n\n\
+<!-- stacks:end -->\n";
+
+        let issues = check_stack_sections(content, &installed);
+
+        assert!(issues.contains(&ClaudeMdIssue::Duplicate("lint".to_string(), 2)));
+        assert!(issues.contains(&ClaudeMdIssue::Missing("docs".to_string())));
+        assert!(issues.contains(&ClaudeMdIssue::Orphaned("gone".to_string())));
+        assert!(!issues.contains(&ClaudeMdIssue::Missing("test".to_string())));
+        assert_eq!(issues.len(), 3);
+
+        let healthy = remove_all_stack_sections(content);
+        assert!(check_stack_sections(&healthy, &[]).is_empty());
+    }
+
+    #[test]
+    fn test_unclosed_section_keeps_following_content() {
+        let content = "# Project\n\n<!-- stacks:begin lint -->\nSee @stacks/lint/CLAUDE.md.\n\n## Notes\n\nKeep me.\n";
+
+        assert_eq!(
+            check_stack_sections(content, &["lint".to_string()]),
+            vec![ClaudeMdIssue::UnclosedSection("lint".to_string(), 3)]
+        );
+
+        let cleaned = remove_all_stack_sections(content);
+        assert!(cleaned.contains("Keep me."));
+        assert!(!cleaned.contains("stacks:begin"));
+    }
+
+    /// Every ordering of `items`
+    fn permutations(items: &[&'static str]) -> Vec<Vec<&'static str>> {
+        if items.len() <= 1 {
+            return vec![items.to_vec()];
+        }
+        let mut result = Vec::new();
+        for (index, item) in items.iter().enumerate() {
+            let mut rest = items.to_vec();
+            rest.remove(index);
+            for mut permutation in permutations(&rest) {
+                permutation.insert(0, item);
+                result.push(permutation);
+            }
+        }
+        result
+    }
+
+    fn section_order(content: &str) -> Vec<String> {
+        content.lines().filter_map(section_name).map(str::to_string).collect()
+    }
+
+    const BASE_DOCUMENTS: [&str; 5] = [
+        "",
+        "# Project\n",
+        "# Project\n\nIntro.\n\n## Notes\n\nKeep these.\n",
+        "# Project\n\n\n\nIntro without trailing newline",
+        "# Project\n\n----\n\nSee @stacks/beta/CLAUDE.md.\n\nMy note.\n",
+    ];
+
+    #[test]
+    fn test_insert_order_does_not_matter() {
+        let stacks = ["gamma", "alpha", "beta", "delta"];
+
+        for base in BASE_DOCUMENTS {
+            let results: Vec<String> = permutations(&stacks)
+                .into_iter()
+                .map(|order| order.iter().fold(base.to_string(), |content, stack| {
+                    insert_stack_section(&content, stack, &format!("See {}.", import_reference(stack)))
+                }))
+                .collect();
+
+            for result in &results {
+                assert_eq!(result, &results[0], "insertion order changed the result for {:?}", base);
+            }
+            assert_eq!(section_order(&results[0]), vec!["alpha", "beta", "delta", "gamma"]);
+            assert_eq!(results[0].matches("@stacks/beta/CLAUDE.md").count(), 1, "legacy import should become a section");
+        }
+    }
+
+    #[test]
+    fn test_insert_and_remove_are_idempotent() {
+        let stacks = ["alpha", "beta", "gamma"];
+
+        for base in BASE_DOCUMENTS {
+            let normalized = tidy(&base.lines().collect::<Vec<_>>());
+            let mut content = base.to_string();
+            for stack in stacks {
+                content = insert_stack_section(&content, stack, &format!("See {}.", import_reference(stack)));
+            }
+
+            for stack in stacks {
+                let body = format!("See {}.", import_reference(stack));
+                let again = insert_stack_section(&content, stack, &body);
+                assert_eq!(again, content, "re-inserting {} changed {:?}", stack, base);
+                assert_eq!(insert_stack_section(&again, stack, &body), again);
+            }
+
+            for order in permutations(&stacks) {
+                let removed = order.iter().fold(content.clone(), |content, stack| remove_stack_section(&content, stack));
+                assert_eq!(remove_stack_section(&removed, "alpha"), removed);
+                if !base.contains("@stacks/") {
+                    assert_eq!(removed, normalized, "removing all stacks should restore {:?}", base);
+                }
+            }
+        }
     }
 
     #[tokio::test]
-    async fn test_demarcation_and_cleanup() {
+    async fn test_sections_and_cleanup() {
         use tempfile::tempdir;
         use std::fs;
-        
+
         // Create a temporary directory for testing
         let temp_dir = tempdir().expect("Failed to create temp dir");
         let claude_md_path = temp_dir.path().join("CLAUDE.md");
-        
+
         // Create a custom updater with the temp path
-        let updater = ClaudeMdUpdater {
-            claude_md_path: claude_md_path.clone(),
-        };
-        
-        // Initial content with some existing content above the demarcation
+        let updater = ClaudeMdUpdater::with_path(claude_md_path.clone());
+
+        // Initial content with some existing project info
         let initial_content = "# My Project\n\nThis is important project info.\n\n## Setup\n\nSome setup instructions.";
         fs::write(&claude_md_path, initial_content).expect("Failed to write initial content");
-        
-        // Add first stack with demarcation
-        updater.add_stack_import_with_demarcation("ts-lint-stack").await.unwrap();
-        
+
+        updater.add_stack_import("ts-lint-stack").await.unwrap();
+
         let content_after_first = fs::read_to_string(&claude_md_path).unwrap();
-        assert!(content_after_first.contains("----"), "Demarcation line should be added");
+        assert!(content_after_first.contains("<!-- stacks:begin ts-lint-stack -->"), "Section should be added");
         assert!(content_after_first.contains("See @stacks/ts-lint-stack/CLAUDE.md"), "First stack import should be added");
         assert!(content_after_first.contains("This is important project info"), "Original content should be preserved");
-        
-        // Add second stack with demarcation
-        updater.add_stack_import_with_demarcation("stack-2").await.unwrap();
-        
+
+        // The user adds notes after checkout, then a second stack is added
+        fs::write(&claude_md_path, format!("{}\n## My notes\n\nKeep these.\n", content_after_first)).unwrap();
+        updater.add_stack_import("stack-2").await.unwrap();
+        updater.add_stack_import("stack-2").await.unwrap();
+
         let content_after_second = fs::read_to_string(&claude_md_path).unwrap();
         assert!(content_after_second.contains("See @stacks/ts-lint-stack/CLAUDE.md"), "First stack should still be there");
-        assert!(content_after_second.contains("See @stacks/stack-2/CLAUDE.md"), "Second stack should be added");
-        
-        // Count demarcation lines - should only be one
-        let demarcation_count = content_after_second.matches("----").count();
-        assert_eq!(demarcation_count, 1, "Should only have one demarcation line");
-        
-        // Now test cleanup
-        updater.cleanup_demarcated_imports().await.unwrap();
-        
+        assert_eq!(content_after_second.matches("<!-- stacks:begin stack-2 -->").count(), 1, "Adding twice is a no-op");
+
+        // Removing one stack leaves the other and the notes
+        updater.remove_stack_import("ts-lint-stack").await.unwrap();
+        let content_after_remove = fs::read_to_string(&claude_md_path).unwrap();
+        assert!(!content_after_remove.contains("ts-lint-stack"));
+        assert!(content_after_remove.contains("@stacks/stack-2/CLAUDE.md"));
+        assert!(content_after_remove.contains("Keep these."));
+
+        // Cleanup removes the sections but not the user's notes
+        updater.remove_all_stack_sections().await.unwrap();
+
         let content_after_cleanup = fs::read_to_string(&claude_md_path).unwrap();
         assert!(content_after_cleanup.contains("This is important project info"), "Original content should be preserved after cleanup");
-        assert!(content_after_cleanup.contains("----"), "Demarcation line should remain");
-        assert!(!content_after_cleanup.contains("@stacks/ts-lint-stack/CLAUDE.md"), "Stack imports should be removed");
-        assert!(!content_after_cleanup.contains("@stacks/stack-2/CLAUDE.md"), "Stack imports should be removed");
-        
-        // Content after demarcation should be gone
-        let lines: Vec<&str> = content_after_cleanup.lines().collect();
-        let demarcation_index = lines.iter().position(|&line| line.trim() == "----").unwrap();
-        assert_eq!(lines.len(), demarcation_index + 1, "Nothing should exist after demarcation line");
+        assert!(content_after_cleanup.contains("Keep these."), "User notes should survive cleanup");
+        assert!(!content_after_cleanup.contains("@stacks/"), "Stack imports should be removed");
+        assert!(!content_after_cleanup.contains("stacks:begin"), "Markers should be removed");
     }
-}
 
+    #[tokio::test]
+    async fn test_new_file_from_template() {
+        let temp_dir = tempfile::tempdir().unwrap();
+        let claude_md_path = temp_dir.path().join("CLAUDE.md");
+        std::fs::write(temp_dir.path().join("Cargo.toml"), "[package]\nname = \"widget\"\n").unwrap();
+
+        let updater = ClaudeMdUpdater::with_path(claude_md_path.clone())
+            .with_template(Some("# {project}\n\n{commands}\n## Stacks\n\n{stacks}\n\n## Notes\n".to_string()));
+        updater.add_stack_import("lint").await.unwrap();
+        updater.add_stack_import("docs").await.unwrap();
+
+        let content = std::fs::read_to_string(&claude_md_path).unwrap();
+        assert!(content.starts_with("# widget\n\n## Commands\n\n- Build: `cargo build`\n"));
+        // Later stacks join the first one, sorted by name, rather than going after the template's trailing sections
+        assert!(content.contains(
+            "## Stacks\n\n<!-- stacks:begin docs -->\nSee @stacks/docs/CLAUDE.md.\n<!-- stacks:end -->\n\n\
+<!-- stacks:begin lint -->\nSee @stacks/lint/CLAUDE.md.\n<!-- stacks:end -->\n\n## Notes\n"
+        ));
+    }
+
+    #[tokio::test]
+    async fn test_embed_mode_refreshes_section() {
+        use std::fs;
+
+        let temp_dir = tempfile::tempdir().unwrap();
+        let claude_md_path = temp_dir.path().join("CLAUDE.md");
+        let stack_claude_md = temp_dir.path().join("stacks").join("lint").join("CLAUDE.md");
+        fs::create_dir_all(stack_claude_md.parent().unwrap()).unwrap();
+        fs::write(&stack_claude_md, "Run the linter before committing.\n").unwrap();
+
+        let updater = ClaudeMdUpdater::with_path(claude_md_path.clone()).with_mode(ClaudeMdMode::Embed);
+        updater.add_stack_import("lint").await.unwrap();
+
+        let content = fs::read_to_string(&claude_md_path).unwrap();
+        assert!(content.contains("<!-- stacks:begin lint -->\nRun the linter before committing.\n<!-- stacks:end -->"));
+        assert!(!content.contains("@stacks/lint"));
+        assert!(updater.has_stack_import("lint").await.unwrap());
+
+        // A pull changes the stack's instructions; the embedded copy follows
+        fs::write(&stack_claude_md, "Run the linter and formatter.\n").unwrap();
+        updater.add_stack_import("lint").await.unwrap();
+
+        let refreshed = fs::read_to_string(&claude_md_path).unwrap();
+        assert!(refreshed.contains("Run the linter and formatter."));
+        assert!(!refreshed.contains("before committing"));
+        assert_eq!(refreshed.matches("<!-- stacks:begin lint -->").count(), 1);
+    }
+}
@@ -1,33 +1,316 @@
-use std::process::Command;
-use anyhow::{Result, Context};
+use std::path::PathBuf;
+use std::sync::{Mutex, OnceLock};
+use anyhow::Result;
+use schemars::JsonSchema;
+use serde::{Deserialize, Serialize};
 
-/// Check if all required dependencies are available
-pub fn check_dependencies() -> Result<()> {
-    let deps = vec![
-        ("tmux", "tmux is required for worktree management"),
-        ("claude", "claude CLI is required for MCP operations"), 
-        ("git", "git is required for worktree operations"),
-    ];
+use crate::error::{ErrorKind, StacksError};
+use crate::utils::process::Process;
 
-    for (cmd, description) in deps {
-        check_command_exists(cmd)
-            .with_context(|| format!("{}: {}", description, cmd))?;
+/// Dependencies already found on PATH in this run
+static FOUND: Mutex<Vec<Dependency>> = Mutex::new(Vec::new());
+
+/// An external program some commands need
+///
+/// Commands check only what they use, right before using it, so e.g. `stacks status`
+/// never looks for tmux.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Dependency {
+    Git,
+    Tmux,
+    Claude,
+    Ssh,
+}
+
+impl Dependency {
+    pub fn all() -> [Dependency; 4] {
+        [Dependency::Git, Dependency::Tmux, Dependency::Claude, Dependency::Ssh]
     }
 
-    Ok(())
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Dependency::Git => "git",
+            Dependency::Tmux => "tmux",
+            Dependency::Claude => "claude",
+            Dependency::Ssh => "ssh",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            Dependency::Git => "git is required for stacks and worktree operations",
+            Dependency::Tmux => "tmux is required for worktree sessions",
+            Dependency::Claude => "claude CLI is required for MCP operations and agent sessions",
+            Dependency::Ssh => "ssh is required for worktrees on a remote host",
+        }
+    }
+
+    /// Oldest version commands work with; older installs fail `require`
+    pub fn minimum_version(&self) -> Option<Version> {
+        match self {
+            // `git worktree` features stacks relies on, like `worktree add` checking out new branches cleanly
+            Dependency::Git => Some(Version::new(2, 30)),
+            Dependency::Tmux => Some(MIN_TMUX_VERSION),
+            Dependency::Claude | Dependency::Ssh => None,
+        }
+    }
+
+    /// Version below which some features are degraded; `stacks doctor` warns about it
+    pub fn recommended_version(&self) -> Option<Version> {
+        match self {
+            Dependency::Tmux => Some(TMUX_PANE_OPTIONS),
+            _ => self.minimum_version(),
+        }
+    }
+
+    fn version_args(&self) -> &'static [&'static str] {
+        match self {
+            Dependency::Tmux => &["-V"],
+            Dependency::Ssh => &["-V"],
+            Dependency::Git | Dependency::Claude => &["--version"],
+        }
+    }
+
+    /// The installed version, asked from the program itself
+    pub fn installed_version(&self) -> Option<Version> {
+        if *self == Dependency::Tmux {
+            return tmux_version();
+        }
+        let output = Process::new(self.as_str()).args(self.version_args()).read_only().output().ok()?;
+        // ssh prints its version on stderr
+        Version::parse(&output.stdout).or_else(|| Version::parse(&output.stderr))
+    }
+
+    /// Command that installs the program on this machine
+    pub fn install_command(&self) -> String {
+        if *self == Dependency::Claude {
+            return "npm install -g @anthropic-ai/claude-code".to_string();
+        }
+        let package = match self {
+            Dependency::Ssh if cfg!(target_os = "macos") => return "ssh ships with macOS".to_string(),
+            Dependency::Ssh if find_in_path("apt-get").is_some() => "openssh-client",
+            Dependency::Ssh => "openssh",
+            _ => self.as_str(),
+        };
+        let manager = match () {
+            _ if cfg!(target_os = "macos") => "brew install",
+            _ if cfg!(windows) => "winget install",
+            _ if find_in_path("apt-get").is_some() => "sudo apt-get install",
+            _ if find_in_path("dnf").is_some() => "sudo dnf install",
+            _ if find_in_path("pacman").is_some() => "sudo pacman -S",
+            _ if find_in_path("apk").is_some() => "sudo apk add",
+            _ => "install with your package manager:",
+        };
+        format!("{} {}", manager, package)
+    }
+
+    fn error_kind(&self) -> ErrorKind {
+        match self {
+            Dependency::Git => ErrorKind::Git,
+            Dependency::Tmux => ErrorKind::Tmux,
+            Dependency::Claude | Dependency::Ssh => ErrorKind::Validation,
+        }
+    }
+}
+
+/// A `major.minor` program version; patch levels and suffixes like the `a` of tmux 3.3a are ignored
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Version {
+    pub major: u32,
+    pub minor: u32,
+}
+
+impl Version {
+    pub const fn new(major: u32, minor: u32) -> Self {
+        Self { major, minor }
+    }
+
+    /// The first `major.minor` in a `--version` line, e.g. `tmux 3.3a` or `git version 2.39.2`
+    pub fn parse(text: &str) -> Option<Self> {
+        text.split(|c: char| c.is_whitespace() || c == '-' || c == '_')
+            .find_map(|word| {
+                let mut parts = word.split('.');
+                let major = parts.next()?.parse().ok()?;
+                let minor: String = parts.next()?.chars().take_while(char::is_ascii_digit).collect();
+                Some(Self::new(major, minor.parse().ok()?))
+            })
+    }
+}
+
+impl std::fmt::Display for Version {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}.{}", self.major, self.minor)
+    }
+}
+
+/// Oldest tmux stacks drives: pane titles (`select-pane -T`) and `respawn-pane -c` need 2.6
+pub const MIN_TMUX_VERSION: Version = Version::new(2, 6);
+
+/// tmux with pane options (`set-option -p`); older versions get pane labels without them
+pub const TMUX_PANE_OPTIONS: Version = Version::new(3, 0);
+
+static TMUX_VERSION: OnceLock<Option<Version>> = OnceLock::new();
+
+/// Version of the installed tmux; None when it isn't installed or doesn't say (e.g. `tmux master`)
+pub fn tmux_version() -> Option<Version> {
+    *TMUX_VERSION.get_or_init(|| {
+        Process::new("tmux")
+            .arg("-V")
+            .read_only()
+            .output()
+            .ok()
+            .and_then(|output| Version::parse(&output.stdout))
+    })
 }
 
-/// Check if a specific command exists in PATH
-pub fn check_command_exists(command: &str) -> Result<()> {
-    let output = Command::new("which")
-        .arg(command)
-        .output()
-        .with_context(|| format!("Failed to check for {}", command))?;
+/// Whether the installed tmux has a feature added in `version`; unknown versions are assumed new
+pub fn tmux_supports(version: Version) -> bool {
+    tmux_version().is_none_or(|installed| installed >= version)
+}
 
-    if !output.status.success() {
-        anyhow::bail!("{} not found in PATH", command);
+/// Whether a tmux server is running for this user (the default socket, or $TMUX's)
+pub fn tmux_server_running() -> bool {
+    Process::tmux().arg("list-sessions").read_only().succeeds()
+}
+
+/// Whether stderr from tmux says there's no server to talk to
+pub fn is_no_server_error(stderr: &str) -> bool {
+    stderr.contains("no server running") || stderr.contains("error connecting to")
+}
+
+/// What `stacks doctor` found out about one dependency
+#[derive(Debug, Clone, Serialize, Deserialize, JsonSchema, PartialEq)]
+pub struct DependencyReport {
+    pub name: String,
+    pub path: Option<PathBuf>,
+    pub version: Option<String>,
+    pub minimum_version: Option<String>,
+    pub recommended_version: Option<String>,
+    /// Installed and at least the minimum version
+    pub ok: bool,
+    /// Installed, but older than the recommended version
+    pub outdated: bool,
+    /// How to install or upgrade it when it isn't ok or is outdated
+    pub install: Option<String>,
+}
+
+/// Look a dependency up on PATH and ask for its version
+pub fn check(dependency: Dependency) -> DependencyReport {
+    let path = find_in_path(dependency.as_str());
+    let version = path.as_ref().and_then(|_| dependency.installed_version());
+    // A version that can't be read (e.g. a tmux dev build) isn't held against it
+    let at_least = |required: Option<Version>| match (version, required) {
+        (Some(version), Some(required)) => version >= required,
+        _ => true,
+    };
+    let ok = path.is_some() && at_least(dependency.minimum_version());
+    let outdated = path.is_some() && !at_least(dependency.recommended_version());
+    DependencyReport {
+        name: dependency.as_str().to_string(),
+        path,
+        version: version.map(|version| version.to_string()),
+        minimum_version: dependency.minimum_version().map(|version| version.to_string()),
+        recommended_version: dependency.recommended_version().map(|version| version.to_string()),
+        ok,
+        outdated,
+        install: match ok && !outdated {
+            true => None,
+            false => Some(dependency.install_command()),
+        },
     }
+}
 
+/// Fail with an install hint unless `dependency` is on PATH and new enough; checked once per run
+pub fn require(dependency: Dependency) -> Result<()> {
+    let mut found = FOUND.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+    if found.contains(&dependency) {
+        return Ok(());
+    }
+
+    if find_in_path(dependency.as_str()).is_none() {
+        return Err(StacksError::new(dependency.error_kind(), format!("{} not found in PATH", dependency.as_str()))
+            .with_reason(dependency.description())
+            .with_hint(format!("Install it with: {}", dependency.install_command()))
+            .into());
+    }
+    // Only a minimum version needs the program started to ask for its version; claude takes a
+    // noticeable while to answer, and is checked on every worktree wizard start
+    if dependency.minimum_version().is_some() {
+        let report = check(dependency);
+        if !report.ok {
+            return Err(StacksError::new(dependency.error_kind(), format!("{} {} is too old", dependency.as_str(), report.version.unwrap_or_default()))
+                .with_reason(format!("stacks needs {} {} or newer", dependency.as_str(), report.minimum_version.unwrap_or_default()))
+                .with_hint(format!("Upgrade it, e.g. with: {}", dependency.install_command()))
+                .into());
+        }
+    }
+
+    found.push(dependency);
     Ok(())
 }
 
+/// Check several dependencies, reporting the first missing one
+pub fn require_all(dependencies: &[Dependency]) -> Result<()> {
+    dependencies.iter().try_for_each(|dependency| require(*dependency))
+}
+
+/// Full path of an executable on PATH, found without spawning `which`
+pub fn find_in_path(program: &str) -> Option<PathBuf> {
+    let path = std::env::var_os("PATH")?;
+    std::env::split_paths(&path)
+        .flat_map(|dir| executable_names(program).into_iter().map(move |name| dir.join(name)))
+        .find(|candidate| is_executable(candidate))
+}
+
+#[cfg(windows)]
+fn executable_names(program: &str) -> Vec<String> {
+    vec![format!("{}.exe", program), format!("{}.cmd", program), program.to_string()]
+}
+
+#[cfg(not(windows))]
+fn executable_names(program: &str) -> Vec<String> {
+    vec![program.to_string()]
+}
+
+#[cfg(unix)]
+fn is_executable(path: &std::path::Path) -> bool {
+    use std::os::unix::fs::PermissionsExt;
+    std::fs::metadata(path)
+        .map(|metadata| metadata.is_file() && metadata.permissions().mode() & 0o111 != 0)
+        .unwrap_or(false)
+}
+
+#[cfg(not(unix))]
+fn is_executable(path: &std::path::Path) -> bool {
+    path.is_file()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_in_path() {
+        // Tests run with git available, as the git module's tests need it
+        assert!(find_in_path("git").is_some());
+        assert!(find_in_path("definitely-not-a-real-program").is_none());
+        assert!(require(Dependency::Git).is_ok());
+    }
+
+    #[test]
+    fn test_parse_version() {
+        assert_eq!(Version::parse("tmux 3.3a"), Some(Version::new(3, 3)));
+        assert_eq!(Version::parse("tmux next-3.4"), Some(Version::new(3, 4)));
+        assert_eq!(Version::parse("git version 2.39.2 (Apple Git-143)"), Some(Version::new(2, 39)));
+        assert_eq!(Version::parse("tmux master"), None);
+        assert!(Version::new(2, 10) > Version::new(2, 6));
+    }
+
+    #[test]
+    fn test_check_reports_version() {
+        let git = check(Dependency::Git);
+        assert!(git.path.is_some());
+        assert!(git.version.is_some());
+        assert_eq!(git.minimum_version.as_deref(), Some("2.30"));
+    }
+}
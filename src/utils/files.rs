@@ -0,0 +1,70 @@
+use std::io;
+use std::path::Path;
+
+use crate::utils::process::is_dry_run;
+
+// Project files go through these instead of std::fs so that `--dry-run` can print each change
+// instead of making it, the way Process does for commands. Signatures match std::fs.
+
+pub fn write(path: impl AsRef<Path>, contents: impl AsRef<[u8]>) -> io::Result<()> {
+    let path = path.as_ref();
+    if is_dry_run() {
+        outln!("  🔎 Would write: {} ({} bytes)", path.display(), contents.as_ref().len());
+        return Ok(());
+    }
+    std::fs::write(path, contents)
+}
+
+pub fn create_dir_all(path: impl AsRef<Path>) -> io::Result<()> {
+    let path = path.as_ref();
+    if is_dry_run() {
+        if !path.is_dir() {
+            outln!("  🔎 Would create directory: {}", path.display());
+        }
+        return Ok(());
+    }
+    std::fs::create_dir_all(path)
+}
+
+pub fn remove_file(path: impl AsRef<Path>) -> io::Result<()> {
+    let path = path.as_ref();
+    if is_dry_run() {
+        outln!("  🔎 Would remove: {}", path.display());
+        return Ok(());
+    }
+    std::fs::remove_file(path)
+}
+
+pub fn remove_dir(path: impl AsRef<Path>) -> io::Result<()> {
+    let path = path.as_ref();
+    if is_dry_run() {
+        outln!("  🔎 Would remove directory: {}", path.display());
+        return Ok(());
+    }
+    std::fs::remove_dir(path)
+}
+
+pub fn remove_dir_all(path: impl AsRef<Path>) -> io::Result<()> {
+    let path = path.as_ref();
+    if is_dry_run() {
+        outln!("  🔎 Would remove directory: {}", path.display());
+        return Ok(());
+    }
+    std::fs::remove_dir_all(path)
+}
+
+/// Create `link` pointing at `original`; a file symlink on Windows
+pub fn symlink(original: impl AsRef<Path>, link: impl AsRef<Path>) -> io::Result<()> {
+    let (original, link) = (original.as_ref(), link.as_ref());
+    if is_dry_run() {
+        outln!("  🔎 Would link: {} -> {}", link.display(), original.display());
+        return Ok(());
+    }
+    #[cfg(unix)]
+    return std::os::unix::fs::symlink(original, link);
+    #[cfg(windows)]
+    return match original.is_dir() {
+        true => std::os::windows::fs::symlink_dir(original, link),
+        false => std::os::windows::fs::symlink_file(original, link),
+    };
+}
@@ -0,0 +1,230 @@
+use std::io::{Cursor, Write};
+use std::process::{Command, Stdio};
+use anyhow::{Result, Context};
+use serde::{Deserialize, Serialize};
+use skim::prelude::*;
+
+use crate::error::StacksError;
+use crate::utils::dependency_check::find_in_path;
+use crate::utils::terminal::is_interactive;
+
+/// Fuzzy finder interactive lists are picked from
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum FuzzyFinder {
+    /// The finder built into stacks; needs nothing installed
+    #[default]
+    Builtin,
+    /// An external `fzf` on PATH, falling back to the built-in finder without one
+    Fzf,
+}
+
+impl FuzzyFinder {
+    pub fn all() -> [FuzzyFinder; 2] {
+        [FuzzyFinder::Builtin, FuzzyFinder::Fzf]
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FuzzyFinder::Builtin => "builtin",
+            FuzzyFinder::Fzf => "fzf",
+        }
+    }
+
+    pub fn description(&self) -> &'static str {
+        match self {
+            FuzzyFinder::Builtin => "Built-in fuzzy finder (nothing to install)",
+            FuzzyFinder::Fzf => "fzf from PATH, with your FZF_DEFAULT_OPTS; the built-in finder when it's missing",
+        }
+    }
+}
+
+impl std::str::FromStr for FuzzyFinder {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "builtin" => Ok(FuzzyFinder::Builtin),
+            "fzf" => Ok(FuzzyFinder::Fzf),
+            _ => anyhow::bail!("Invalid fuzzy finder: {}", s),
+        }
+    }
+}
+
+/// One fuzzy pick from a list of lines, with the configured finder
+#[derive(Debug, Clone)]
+pub struct Picker {
+    prompt: String,
+    height: String,
+    multi: bool,
+    query: Option<String>,
+    /// How to choose without a terminal, e.g. the arguments that name the choice
+    fallback_hint: Option<String>,
+}
+
+impl Picker {
+    pub fn new(prompt: impl Into<String>) -> Self {
+        Self {
+            prompt: prompt.into(),
+            height: "40%".to_string(),
+            multi: false,
+            query: None,
+            fallback_hint: None,
+        }
+    }
+
+    pub fn with_height(mut self, height: &str) -> Self {
+        self.height = height.to_string();
+        self
+    }
+
+    /// Allow picking several lines with Tab
+    pub fn with_multi(mut self, multi: bool) -> Self {
+        self.multi = multi;
+        self
+    }
+
+    pub fn with_query(mut self, query: Option<String>) -> Self {
+        self.query = query;
+        self
+    }
+
+    pub fn with_fallback_hint(mut self, hint: impl Into<String>) -> Self {
+        self.fallback_hint = Some(hint.into());
+        self
+    }
+
+    /// The picked lines; empty when the pick was aborted
+    ///
+    /// Without a terminal, a query matching exactly one line picks it; otherwise the lines are
+    /// listed and the pick fails with the fallback hint.
+    pub fn pick(&self, lines: &[String]) -> Result<Vec<String>> {
+        if !is_interactive() {
+            return self.pick_without_terminal(lines);
+        }
+        let finder = crate::config::load_config().map(|config| config.fuzzy_finder).unwrap_or_default();
+        match finder {
+            FuzzyFinder::Fzf if find_in_path("fzf").is_some() => self.pick_with_fzf(lines),
+            FuzzyFinder::Fzf => {
+                outln!("⚠️ fzf not found in PATH; using the built-in fuzzy finder");
+                Ok(self.pick_with_skim(lines))
+            }
+            FuzzyFinder::Builtin => Ok(self.pick_with_skim(lines)),
+        }
+    }
+
+    fn pick_without_terminal(&self, lines: &[String]) -> Result<Vec<String>> {
+        if let Some(query) = &self.query {
+            let matches = matching_lines(lines, query);
+            if matches.len() == 1 {
+                return Ok(matches);
+            }
+        }
+        for line in lines {
+            outln!("  • {}", line);
+        }
+        let prompt = self.prompt.trim().trim_end_matches(':');
+        Err(StacksError::validation(format!("Can't show the picker ({}) without an interactive terminal", prompt))
+            .with_hint(self.fallback_hint.clone().unwrap_or_else(|| "Run it in a terminal".to_string()))
+            .into())
+    }
+
+    fn pick_with_skim(&self, lines: &[String]) -> Vec<String> {
+        let options = SkimOptionsBuilder::default()
+            .height(Some(self.height.as_str()))
+            .multi(self.multi)
+            .prompt(Some(self.prompt.as_str()))
+            .query(self.query.as_deref())
+            .build()
+            .unwrap();
+        let items = SkimItemReader::default().of_bufread(Cursor::new(lines.join("\n")));
+
+        match Skim::run_with(&options, Some(items)) {
+            Some(out) if !out.is_abort => out.selected_items.iter().map(|item| item.output().to_string()).collect(),
+            _ => Vec::new(),
+        }
+    }
+
+    fn pick_with_fzf(&self, lines: &[String]) -> Result<Vec<String>> {
+        let mut command = Command::new("fzf");
+        command.args(["--height", &self.height, "--prompt", &self.prompt]);
+        if self.multi {
+            command.arg("--multi");
+        }
+        if let Some(query) = &self.query {
+            command.args(["--query", query]);
+        }
+        // fzf draws on the terminal itself, so only its input and the picked lines are piped
+        let mut child = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .context("Failed to start fzf")?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(lines.join("\n").as_bytes()).context("Failed to write to fzf")?;
+        }
+        let output = child.wait_with_output().context("Failed to run fzf")?;
+
+        // fzf exits with 1 when nothing matched and 130 when the pick was aborted
+        match output.status.code() {
+            Some(0) => Ok(String::from_utf8_lossy(&output.stdout).lines().map(str::to_string).collect()),
+            Some(1) | Some(130) => Ok(Vec::new()),
+            _ => anyhow::bail!("fzf failed ({})", output.status),
+        }
+    }
+}
+
+/// First line of `pick_subset`, standing for every item
+const ALL_ITEMS: &str = "[ALL]";
+
+/// Let the user pick some of `items` (name and details), or all of them with an [ALL] line
+///
+/// Returns the picked names in the order of `items`; empty when the pick was aborted.
+pub fn pick_subset(prompt: &str, items: &[(String, String)]) -> Result<Vec<String>> {
+    let width = items.iter().map(|(name, _)| name.len()).max().unwrap_or(0);
+    let mut lines = vec![format!("{:<width$}  every one of the {} below", ALL_ITEMS, items.len(), width = width)];
+    lines.extend(items.iter().map(|(name, details)| format!("{:<width$}  {}", name, details, width = width)));
+
+    let picked = Picker::new(format!("{} (Tab to select several): ", prompt))
+        .with_height("50%")
+        .with_multi(true)
+        .pick(&lines)?;
+    let picked: Vec<&str> = picked.iter()
+        .filter_map(|line| line.split_whitespace().next())
+        .collect();
+    match picked.contains(&ALL_ITEMS) {
+        true => Ok(items.iter().map(|(name, _)| name.clone()).collect()),
+        false => Ok(items.iter()
+            .filter(|(name, _)| picked.contains(&name.as_str()))
+            .map(|(name, _)| name.clone())
+            .collect()),
+    }
+}
+
+/// Lines containing every word of `query`, ignoring case
+fn matching_lines(lines: &[String], query: &str) -> Vec<String> {
+    let words: Vec<String> = query.split_whitespace().map(str::to_lowercase).collect();
+    lines.iter()
+        .filter(|line| {
+            let line = line.to_lowercase();
+            words.iter().all(|word| line.contains(word.as_str()))
+        })
+        .cloned()
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_matching_lines() {
+        let lines = vec![
+            "app-auth:0\tauth\t/work/app-auth".to_string(),
+            "app-billing:0\tbilling\t/work/app-billing".to_string(),
+        ];
+        assert_eq!(matching_lines(&lines, "Auth"), vec![lines[0].clone()]);
+        assert_eq!(matching_lines(&lines, "app work").len(), 2);
+        assert!(matching_lines(&lines, "payments").is_empty());
+    }
+}
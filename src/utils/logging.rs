@@ -0,0 +1,137 @@
+use std::path::PathBuf;
+use std::process::{Command, ExitStatus, Output};
+use std::sync::OnceLock;
+use std::time::{Duration, Instant};
+use anyhow::{Result, Context};
+use tracing_appender::non_blocking::WorkerGuard;
+use tracing_appender::rolling::{RollingFileAppender, Rotation};
+use tracing_subscriber::filter::LevelFilter;
+use tracing_subscriber::prelude::*;
+
+use crate::core::cache::cache_root;
+
+/// Daily log files kept in the log directory
+const MAX_LOG_FILES: usize = 7;
+
+/// When `init` ran, as close to process start as diagnostics get
+static STARTED: OnceLock<Instant> = OnceLock::new();
+
+/// Time since the process started, for logging how long the startup path took
+pub fn since_start() -> Duration {
+    STARTED.get_or_init(Instant::now).elapsed()
+}
+
+/// Directory for `--log-file` logs: `<cache dir>/claude-stacks/logs`
+pub fn log_dir() -> Result<PathBuf> {
+    Ok(cache_root()?.join("logs"))
+}
+
+/// Set up diagnostics on stderr and, optionally, a rotating log file; call after `output::init`
+///
+/// Regular output stays on stdout. `verbosity` 0 only shows warnings, 1 (`-v`) adds every
+/// external command with its arguments and duration, 2 (`-vv`) adds everything else. The log
+/// file always records at the most detailed level; keep the returned guard alive until exit so
+/// buffered lines are flushed.
+pub fn init(verbosity: u8, log_to_file: bool) -> Result<Option<WorkerGuard>> {
+    STARTED.get_or_init(Instant::now);
+    let stderr_level = match verbosity {
+        0 => LevelFilter::WARN,
+        1 => LevelFilter::DEBUG,
+        _ => LevelFilter::TRACE,
+    };
+    let stderr_layer = tracing_subscriber::fmt::layer()
+        .with_writer(std::io::stderr)
+        .with_ansi(crate::utils::output::colors_enabled())
+        .with_target(false)
+        .without_time()
+        .with_filter(stderr_level);
+
+    if !log_to_file {
+        tracing_subscriber::registry().with(stderr_layer).init();
+        return Ok(None);
+    }
+
+    let dir = log_dir()?;
+    std::fs::create_dir_all(&dir)
+        .with_context(|| format!("Failed to create log directory {}", dir.display()))?;
+    let appender = RollingFileAppender::builder()
+        .rotation(Rotation::DAILY)
+        .filename_prefix("stacks")
+        .filename_suffix("log")
+        .max_log_files(MAX_LOG_FILES)
+        .build(&dir)
+        .context("Failed to open log file")?;
+    let (writer, guard) = tracing_appender::non_blocking(appender);
+    let file_layer = tracing_subscriber::fmt::layer()
+        .with_writer(writer)
+        .with_ansi(false)
+        .with_filter(LevelFilter::TRACE);
+
+    tracing_subscriber::registry().with(stderr_layer).with(file_layer).init();
+    tracing::debug!(args = ?std::env::args().collect::<Vec<_>>(), "stacks started; logging to {}", dir.display());
+    Ok(Some(guard))
+}
+
+/// `Command` runners that log the invocation, its exit status and how long it took
+pub trait CommandLogExt {
+    fn logged_output(&mut self) -> std::io::Result<Output>;
+    fn logged_status(&mut self) -> std::io::Result<ExitStatus>;
+}
+
+impl CommandLogExt for Command {
+    fn logged_output(&mut self) -> std::io::Result<Output> {
+        let started = Instant::now();
+        let result = self.output();
+        match &result {
+            Ok(output) => {
+                log_finished(self, output.status, started);
+                if !output.status.success() {
+                    tracing::trace!(stderr = %String::from_utf8_lossy(&output.stderr).trim(), "command stderr");
+                }
+            }
+            Err(error) => tracing::debug!(command = %describe(self), %error, "failed to start command"),
+        }
+        result
+    }
+
+    fn logged_status(&mut self) -> std::io::Result<ExitStatus> {
+        let started = Instant::now();
+        let result = self.status();
+        match &result {
+            Ok(status) => log_finished(self, *status, started),
+            Err(error) => tracing::debug!(command = %describe(self), %error, "failed to start command"),
+        }
+        result
+    }
+}
+
+fn log_finished(command: &Command, status: ExitStatus, started: Instant) {
+    tracing::debug!(
+        command = %describe(command),
+        dir = ?command.get_current_dir(),
+        status = ?status.code(),
+        elapsed_ms = started.elapsed().as_millis() as u64,
+        "ran command"
+    );
+}
+
+/// `program arg1 arg2`, as it would be typed
+fn describe(command: &Command) -> String {
+    std::iter::once(command.get_program())
+        .chain(command.get_args())
+        .map(|part| part.to_string_lossy().into_owned())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_describe_command() {
+        let mut command = Command::new("git");
+        command.args(["status", "--porcelain"]);
+        assert_eq!(describe(&command), "git status --porcelain");
+    }
+}
@@ -1,2 +1,13 @@
+#[macro_use]
+pub mod output;
 pub mod dependency_check;
-pub mod claude_md_updater;
\ No newline at end of file
+pub mod claude_md_updater;
+pub mod project_info;
+pub mod logging;
+pub mod retry;
+pub mod process;
+pub mod task_name;
+pub mod files;
+pub mod fuzzy;
+pub mod terminal;
+pub mod build_info;
\ No newline at end of file
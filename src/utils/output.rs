@@ -0,0 +1,203 @@
+use std::sync::OnceLock;
+use anyhow::Result;
+use is_terminal::IsTerminal;
+
+/// Print a user-facing line, plain when plain output is on
+#[macro_export]
+macro_rules! outln {
+    () => { $crate::utils::output::emit($crate::utils::output::Stream::Stdout, "", true) };
+    ($($arg:tt)*) => { $crate::utils::output::emit($crate::utils::output::Stream::Stdout, &format!($($arg)*), true) };
+}
+
+/// `outln!` without the newline
+#[macro_export]
+macro_rules! out {
+    ($($arg:tt)*) => { $crate::utils::output::emit($crate::utils::output::Stream::Stdout, &format!($($arg)*), false) };
+}
+
+/// `outln!` to stderr
+#[macro_export]
+macro_rules! eoutln {
+    () => { $crate::utils::output::emit($crate::utils::output::Stream::Stderr, "", true) };
+    ($($arg:tt)*) => { $crate::utils::output::emit($crate::utils::output::Stream::Stderr, &format!($($arg)*), true) };
+}
+
+/// Where a piece of output was headed
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Stream {
+    /// Progress and results
+    Stdout,
+    /// Messages printed alongside machine-readable output
+    Stderr,
+}
+
+type Sink = Box<dyn Fn(Stream, &str) + Send + Sync>;
+
+static SINK: OnceLock<Sink> = OnceLock::new();
+
+/// Receive all output instead of having it printed, e.g. to show progress in an editor panel
+///
+/// `sink` gets each line (or partial line from `out!`) as rendered for the output mode. It can be
+/// set once per process; returns false if a sink was already set.
+pub fn set_sink(sink: impl Fn(Stream, &str) + Send + Sync + 'static) -> bool {
+    SINK.set(Box::new(sink)).is_ok()
+}
+
+/// Print all output on stderr, keeping stdout for a machine-readable report
+///
+/// Does nothing when a sink was already set.
+pub fn redirect_to_stderr() {
+    let _ = set_sink(|_, text| eprintln!("{}", text));
+}
+
+/// Hand `text` to the sink, or print it; what the output macros expand to
+pub fn emit(stream: Stream, text: &str, newline: bool) {
+    let text = render(text);
+    if let Some(sink) = SINK.get() {
+        return sink(stream, &text);
+    }
+    match (stream, newline) {
+        (Stream::Stdout, true) => println!("{}", text),
+        (Stream::Stdout, false) => print!("{}", text),
+        (Stream::Stderr, true) => eprintln!("{}", text),
+        (Stream::Stderr, false) => eprint!("{}", text),
+    }
+}
+
+/// When to color output (--color)
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum ColorChoice {
+    /// On a terminal, unless NO_COLOR is set or TERM is dumb
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl ColorChoice {
+    pub fn all() -> [ColorChoice; 3] {
+        [ColorChoice::Auto, ColorChoice::Always, ColorChoice::Never]
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ColorChoice::Auto => "auto",
+            ColorChoice::Always => "always",
+            ColorChoice::Never => "never",
+        }
+    }
+}
+
+impl std::str::FromStr for ColorChoice {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "auto" => Ok(ColorChoice::Auto),
+            "always" => Ok(ColorChoice::Always),
+            "never" => Ok(ColorChoice::Never),
+            _ => anyhow::bail!("Invalid color choice: {}", s),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct OutputMode {
+    color: bool,
+    plain: bool,
+}
+
+static MODE: OnceLock<OutputMode> = OnceLock::new();
+
+/// Decide once how output looks; `plain` comes from --plain or the plain-output setting
+pub fn init(color: ColorChoice, plain: bool) {
+    let dumb = std::env::var("TERM").is_ok_and(|term| term == "dumb");
+    let no_color = std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+    let color = match color {
+        ColorChoice::Always => true,
+        ColorChoice::Never => false,
+        ColorChoice::Auto => !no_color && !dumb && std::io::stderr().is_terminal(),
+    };
+    let _ = MODE.set(OutputMode { color, plain: plain || dumb });
+}
+
+/// Before `init` (e.g. while the config it reads is migrated) output is left as written
+fn mode() -> OutputMode {
+    MODE.get().copied().unwrap_or(OutputMode { color: false, plain: false })
+}
+
+/// Whether diagnostics and progress bars may use ANSI colors
+pub fn colors_enabled() -> bool {
+    mode().color
+}
+
+/// Whether emoji and box drawing are left out of output
+pub fn is_plain() -> bool {
+    mode().plain
+}
+
+/// `text` as it should be printed in the current mode
+pub fn render(text: &str) -> String {
+    match is_plain() {
+        true => plain(text),
+        false => text.to_string(),
+    }
+}
+
+/// `text` without emoji, and with box drawing, bullets and arrows spelled in ASCII
+///
+/// An emoji's trailing space goes with it, so "  ✅ Done" becomes "  Done".
+pub fn plain(text: &str) -> String {
+    let mut result = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if is_emoji(c) {
+            while chars.peek().is_some_and(|next| is_emoji(*next)) {
+                chars.next();
+            }
+            if chars.peek() == Some(&' ') {
+                chars.next();
+            }
+            continue;
+        }
+        match c {
+            '─' | '━' | '═' => result.push('-'),
+            '│' | '┃' | '║' => result.push('|'),
+            '\u{2500}'..='\u{257F}' => result.push('+'),
+            '•' => result.push('-'),
+            '→' => result.push_str("->"),
+            '←' => result.push_str("<-"),
+            '…' => result.push_str("..."),
+            _ => result.push(c),
+        }
+    }
+    result
+}
+
+fn is_emoji(c: char) -> bool {
+    matches!(c,
+        '\u{1F000}'..='\u{1FAFF}'   // pictographs, emoticons, transport, flags
+        | '\u{2600}'..='\u{27BF}'   // miscellaneous symbols and dingbats: ✅ ❌ ⚠ ✏
+        | '\u{2300}'..='\u{23FF}'   // technical symbols: ⎇ ⏳ ⌛
+        | '\u{2B00}'..='\u{2BFF}'   // arrows and stars: ⬇ ⬆ ⭐
+        | '\u{2194}'..='\u{21FF}'   // ↩ and other arrows used as icons; ← and → are spelled out
+        | '\u{2139}' | '\u{25B6}' | '\u{25C0}'
+        | '\u{FE0F}' | '\u{200D}' | '\u{20E3}'
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plain() {
+        assert_eq!(plain("  ✅ 3 stack(s) up to date"), "  3 stack(s) up to date");
+        assert_eq!(plain("⚠️ fzf not found"), "fzf not found");
+        assert_eq!(plain("📂 app-auth │ ⎇ feature-auth │ ✏️ 2 dirty"), "app-auth | feature-auth | 2 dirty");
+        assert_eq!(plain("═══════"), "-------");
+        assert_eq!(plain("  • config v1 → v2"), "  - config v1 -> v2");
+        assert_eq!(plain("👨‍💻 dev"), "dev");
+        assert_eq!(plain("plain text"), "plain text");
+    }
+}
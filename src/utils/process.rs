@@ -0,0 +1,347 @@
+use std::ffi::{OsStr, OsString};
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::thread;
+use std::time::{Duration, Instant};
+use anyhow::Result;
+
+use crate::error::{ErrorKind, StacksError};
+use crate::utils::dependency_check::is_no_server_error;
+
+/// tmux commands return immediately; anything slower means the server is stuck
+const TMUX_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// How often a command with a timeout is checked for completion
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+
+static DRY_RUN: AtomicBool = AtomicBool::new(false);
+
+/// Print commands that would change something instead of running them
+pub fn set_dry_run(enabled: bool) {
+    DRY_RUN.store(enabled, Ordering::Relaxed);
+}
+
+pub fn is_dry_run() -> bool {
+    DRY_RUN.load(Ordering::Relaxed)
+}
+
+/// An external command with captured output, an optional time limit, and tracing
+///
+/// Built like `std::process::Command`, but by value so a command reads as one expression.
+/// Stdin is closed, as with `Command::output`.
+#[derive(Debug, Clone)]
+pub struct Process {
+    program: OsString,
+    args: Vec<OsString>,
+    dir: Option<PathBuf>,
+    env: Vec<(OsString, OsString)>,
+    timeout: Option<Duration>,
+    read_only: bool,
+}
+
+/// Captured result of a finished process
+#[derive(Debug, Clone, Default)]
+pub struct ProcessOutput {
+    /// Exit code; None when the process was killed by a signal
+    pub code: Option<i32>,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+impl ProcessOutput {
+    pub fn success(&self) -> bool {
+        self.code == Some(0)
+    }
+}
+
+impl Process {
+    pub fn new(program: impl AsRef<OsStr>) -> Self {
+        Self {
+            program: program.as_ref().to_os_string(),
+            args: Vec::new(),
+            dir: None,
+            env: Vec::new(),
+            timeout: None,
+            read_only: false,
+        }
+    }
+
+    pub fn git() -> Self {
+        Self::new("git")
+    }
+
+    pub fn tmux() -> Self {
+        Self::new("tmux").timeout(TMUX_TIMEOUT)
+    }
+
+    pub fn claude() -> Self {
+        Self::new("claude")
+    }
+
+    pub fn arg(mut self, arg: impl AsRef<OsStr>) -> Self {
+        self.args.push(arg.as_ref().to_os_string());
+        self
+    }
+
+    pub fn args<I, S>(mut self, args: I) -> Self
+    where
+        I: IntoIterator<Item = S>,
+        S: AsRef<OsStr>,
+    {
+        self.args.extend(args.into_iter().map(|arg| arg.as_ref().to_os_string()));
+        self
+    }
+
+    pub fn current_dir(mut self, dir: impl AsRef<Path>) -> Self {
+        self.dir = Some(dir.as_ref().to_path_buf());
+        self
+    }
+
+    pub fn env(mut self, key: impl AsRef<OsStr>, value: impl AsRef<OsStr>) -> Self {
+        self.env.push((key.as_ref().to_os_string(), value.as_ref().to_os_string()));
+        self
+    }
+
+    pub fn envs<K: AsRef<OsStr>, V: AsRef<OsStr>>(mut self, vars: impl IntoIterator<Item = (K, V)>) -> Self {
+        for (key, value) in vars {
+            self = self.env(key, value);
+        }
+        self
+    }
+
+    /// Kill the process and fail if it runs longer than `timeout`
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = Some(timeout);
+        self
+    }
+
+    /// The command only reads state, so it also runs in dry-run mode
+    pub fn read_only(mut self) -> Self {
+        self.read_only = true;
+        self
+    }
+
+    /// The same command run on `host` through ssh, in the same directory and environment
+    ///
+    /// Arguments are quoted for the remote shell; a directory starting with `~/` is relative to
+    /// the remote home. There's no time limit, since connecting may take a while.
+    pub fn over_ssh(self, host: &str) -> Self {
+        let mut script = String::new();
+        if let Some(dir) = &self.dir {
+            script.push_str(&format!("cd {} && ", shell_quote(&dir.to_string_lossy())));
+        }
+        for (key, value) in &self.env {
+            script.push_str(&format!("{}={} ", key.to_string_lossy(), shell_quote(&value.to_string_lossy())));
+        }
+        let command: Vec<String> = std::iter::once(&self.program)
+            .chain(&self.args)
+            .map(|part| shell_quote(&part.to_string_lossy()))
+            .collect();
+        script.push_str(&command.join(" "));
+
+        Self {
+            program: OsString::from("ssh"),
+            args: vec![OsString::from(host), OsString::from("--"), OsString::from(script)],
+            dir: None,
+            env: Vec::new(),
+            timeout: None,
+            read_only: self.read_only,
+        }
+    }
+
+    /// `program arg1 arg2`, as it would be typed
+    pub fn describe(&self) -> String {
+        std::iter::once(&self.program)
+            .chain(&self.args)
+            .map(|part| part.to_string_lossy().into_owned())
+            .collect::<Vec<_>>()
+            .join(" ")
+    }
+
+    /// Run to completion; fails only if the process couldn't start or timed out
+    pub fn output(&self) -> Result<ProcessOutput> {
+        if is_dry_run() && !self.read_only {
+            outln!("  🔎 Would run: {}", self.describe());
+            return Ok(ProcessOutput { code: Some(0), ..Default::default() });
+        }
+
+        let started = Instant::now();
+        let output = self.execute(started);
+        match &output {
+            Ok(output) => tracing::debug!(
+                command = %self.describe(),
+                dir = ?self.dir,
+                status = ?output.code,
+                elapsed_ms = started.elapsed().as_millis() as u64,
+                "ran command"
+            ),
+            Err(error) => tracing::debug!(command = %self.describe(), "command failed to run: {:#}", error),
+        }
+        output
+    }
+
+    /// Run to completion and fail, with the command and its stderr, unless it exits successfully
+    pub fn run(&self) -> Result<ProcessOutput> {
+        let output = self.output()?;
+        if !output.success() {
+            let reason = match output.code {
+                Some(code) => format!("exited with status {}", code),
+                None => "was killed by a signal".to_string(),
+            };
+            let mut error = self.failure(&format!("{} failed", self.program_name()))
+                .with_reason(reason);
+            if self.program.to_str() == Some("tmux") && is_no_server_error(&output.stderr) {
+                error = error.with_hint("No tmux server is running; start one with 'tmux new-session', or unset a stale $TMUX");
+            }
+            return Err(error.with_command(self.describe(), output.stderr).into());
+        }
+        Ok(output)
+    }
+
+    /// Whether the command ran and exited successfully
+    pub fn succeeds(&self) -> bool {
+        self.output().map(|output| output.success()).unwrap_or(false)
+    }
+
+    fn execute(&self, started: Instant) -> Result<ProcessOutput> {
+        let mut command = Command::new(&self.program);
+        command
+            .args(&self.args)
+            .envs(self.env.iter().map(|(key, value)| (key, value)))
+            .stdin(Stdio::null())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        if let Some(dir) = &self.dir {
+            command.current_dir(dir);
+        }
+
+        let mut child = command.spawn().map_err(|error| {
+            self.failure(&format!("Failed to run {}", self.program_name()))
+                .with_reason(error.to_string())
+                .with_hint(format!("Make sure {} is installed and on your PATH", self.program_name()))
+        })?;
+
+        // Drain both pipes while waiting so a chatty process can't block on a full pipe
+        let stdout = child.stdout.take().map(read_in_background);
+        let stderr = child.stderr.take().map(read_in_background);
+
+        let status = match self.timeout {
+            None => child.wait()?,
+            Some(limit) => loop {
+                if let Some(status) = child.try_wait()? {
+                    break status;
+                }
+                if started.elapsed() >= limit {
+                    let _ = child.kill();
+                    let _ = child.wait();
+                    return Err(self.failure(&format!("{} timed out", self.program_name()))
+                        .with_reason(format!("`{}` did not finish within {}s", self.describe(), limit.as_secs()))
+                        .into());
+                }
+                thread::sleep(POLL_INTERVAL);
+            },
+        };
+
+        let collect = |reader: Option<thread::JoinHandle<Vec<u8>>>| {
+            reader
+                .and_then(|reader| reader.join().ok())
+                .map(|bytes| String::from_utf8_lossy(&bytes).into_owned())
+                .unwrap_or_default()
+        };
+        Ok(ProcessOutput {
+            code: status.code(),
+            stdout: collect(stdout),
+            stderr: collect(stderr),
+        })
+    }
+
+    fn program_name(&self) -> String {
+        self.program.to_string_lossy().into_owned()
+    }
+
+    fn failure(&self, message: &str) -> StacksError {
+        let kind = match self.program.to_str() {
+            Some("git") => ErrorKind::Git,
+            Some("tmux") => ErrorKind::Tmux,
+            _ => ErrorKind::Other,
+        };
+        StacksError::new(kind, message)
+    }
+}
+
+/// `value` as one word for a POSIX shell; a leading `~/` stays unquoted so it still expands
+pub fn shell_quote(value: &str) -> String {
+    if let Some(rest) = value.strip_prefix("~/") {
+        return format!("~/{}", shell_quote(rest));
+    }
+    let safe = |c: char| c.is_ascii_alphanumeric() || "_@%+=:,./-".contains(c);
+    match !value.is_empty() && value.chars().all(safe) {
+        true => value.to_string(),
+        false => format!("'{}'", value.replace('\'', "'\\''")),
+    }
+}
+
+fn read_in_background(mut pipe: impl Read + Send + 'static) -> thread::JoinHandle<Vec<u8>> {
+    thread::spawn(move || {
+        let mut bytes = Vec::new();
+        let _ = pipe.read_to_end(&mut bytes);
+        bytes
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::error::classify;
+
+    #[test]
+    fn test_captures_output_and_environment() {
+        let output = Process::new("sh")
+            .args(["-c", "echo \"$GREETING\"; echo oops >&2; exit 3"])
+            .env("GREETING", "hello")
+            .output()
+            .unwrap();
+        assert_eq!(output.code, Some(3));
+        assert_eq!(output.stdout, "hello\n");
+        assert_eq!(output.stderr, "oops\n");
+        assert!(!output.success());
+    }
+
+    #[test]
+    fn test_run_reports_failures_with_command() {
+        let error = Process::git()
+            .args(["rev-parse", "--git-dir"])
+            .current_dir(std::env::temp_dir())
+            .run()
+            .unwrap_err();
+        assert_eq!(classify(&error), ErrorKind::Git);
+        let detail = error.downcast_ref::<StacksError>().unwrap();
+        assert_eq!(detail.command.as_ref().unwrap().0, "git rev-parse --git-dir");
+
+        assert!(Process::new("definitely-not-a-real-program").output().is_err());
+    }
+
+    #[test]
+    fn test_timeout_kills_the_process() {
+        let started = Instant::now();
+        let result = Process::new("sleep").arg("5").timeout(Duration::from_millis(100)).output();
+        assert!(result.is_err());
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+
+    #[test]
+    fn test_over_ssh_quotes_for_the_remote_shell() {
+        let remote = Process::tmux()
+            .args(["new-session", "-d", "-s", "app-auth", "-F", "#{pane_id}"])
+            .current_dir("~/src/my app")
+            .over_ssh("dev@box");
+        assert_eq!(
+            remote.describe(),
+            "ssh dev@box -- cd ~/'src/my app' && tmux new-session -d -s app-auth -F '#{pane_id}'"
+        );
+        assert_eq!(shell_quote("it's"), "'it'\\''s'");
+        assert_eq!(shell_quote(""), "''");
+    }
+}
@@ -0,0 +1,114 @@
+use std::path::Path;
+
+/// Facts about a project used to fill in a new CLAUDE.md
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProjectInfo {
+    pub name: String,
+    /// (purpose, command) pairs such as ("Build", "cargo build")
+    pub commands: Vec<(String, String)>,
+}
+
+impl ProjectInfo {
+    /// Detect the project name and build/test/lint commands from Cargo.toml, package.json, go.mod or a Makefile
+    pub fn detect(root: &Path) -> Self {
+        let mut name = None;
+        let mut commands = Vec::new();
+
+        if let Ok(content) = std::fs::read_to_string(root.join("Cargo.toml")) {
+            if let Ok(manifest) = toml::from_str::<toml::Table>(&content) {
+                name = manifest.get("package")
+                    .and_then(|package| package.get("name"))
+                    .and_then(|name| name.as_str())
+                    .map(str::to_string);
+            }
+            commands.push(("Build".to_string(), "cargo build".to_string()));
+            commands.push(("Test".to_string(), "cargo test".to_string()));
+            commands.push(("Lint".to_string(), "cargo clippy".to_string()));
+        }
+
+        if let Ok(content) = std::fs::read_to_string(root.join("package.json")) {
+            if let Ok(package) = serde_json::from_str::<serde_json::Value>(&content) {
+                if name.is_none() {
+                    name = package["name"].as_str().map(str::to_string);
+                }
+
+                let runner = if root.join("pnpm-lock.yaml").exists() {
+                    "pnpm"
+                } else if root.join("yarn.lock").exists() {
+                    "yarn"
+                } else {
+                    "npm"
+                };
+
+                for (script, purpose) in [("build", "Build"), ("test", "Test"), ("lint", "Lint")] {
+                    if package["scripts"].get(script).is_some() {
+                        commands.push((purpose.to_string(), format!("{} run {}", runner, script)));
+                    }
+                }
+            }
+        }
+
+        if root.join("go.mod").exists() {
+            commands.push(("Build".to_string(), "go build ./...".to_string()));
+            commands.push(("Test".to_string(), "go test ./...".to_string()));
+        }
+
+        if let Ok(content) = std::fs::read_to_string(root.join("Makefile")) {
+            for target in ["build", "test", "lint"] {
+                let has_target = content.lines().any(|line| line.starts_with(&format!("{}:", target)));
+                let purpose = format!("{}{}", target[..1].to_uppercase(), &target[1..]);
+                if has_target && !commands.iter().any(|(existing, _)| *existing == purpose) {
+                    commands.push((purpose, format!("make {}", target)));
+                }
+            }
+        }
+
+        let name = name.unwrap_or_else(|| {
+            root.canonicalize()
+                .ok()
+                .and_then(|root| root.file_name().map(|name| name.to_string_lossy().to_string()))
+                .unwrap_or_else(|| "Project".to_string())
+        });
+
+        Self { name, commands }
+    }
+
+    /// Markdown `## Commands` section, empty when nothing was detected
+    pub fn commands_section(&self) -> String {
+        if self.commands.is_empty() {
+            return String::new();
+        }
+
+        let mut section = String::from("## Commands\n\n");
+        for (purpose, command) in &self.commands {
+            section.push_str(&format!("- {}: `{}`\n", purpose, command));
+        }
+        section
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_package_json() {
+        let temp_dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(
+            temp_dir.path().join("package.json"),
+            r#"{"name": "web-app", "scripts": {"build": "vite build", "test": "vitest"}}"#,
+        ).unwrap();
+        std::fs::write(temp_dir.path().join("yarn.lock"), "").unwrap();
+        std::fs::write(temp_dir.path().join("Makefile"), "lint:\n\teslint .\n").unwrap();
+
+        let info = ProjectInfo::detect(temp_dir.path());
+
+        assert_eq!(info.name, "web-app");
+        assert_eq!(info.commands, vec![
+            ("Build".to_string(), "yarn run build".to_string()),
+            ("Test".to_string(), "yarn run test".to_string()),
+            ("Lint".to_string(), "make lint".to_string()),
+        ]);
+        assert!(info.commands_section().contains("- Test: `yarn run test`"));
+    }
+}
@@ -0,0 +1,196 @@
+use std::future::Future;
+use std::process::Output;
+use std::time::Duration;
+
+use crate::config::load_config;
+use crate::utils::process::ProcessOutput;
+
+/// Attempts per network operation when `network-retries` is unset
+pub const DEFAULT_ATTEMPTS: u32 = 3;
+
+/// git's messages for failures worth retrying; commands are run with LC_ALL=C so they're stable
+const TRANSIENT_GIT_ERRORS: &[&str] = &[
+    "Could not resolve host",
+    "Connection reset",
+    "Connection timed out",
+    "Operation timed out",
+    "Failed to connect",
+    "early EOF",
+    "RPC failed",
+    "The remote end hung up unexpectedly",
+    "returned error: 5",
+    "Temporary failure in name resolution",
+];
+
+/// How often and how patiently to retry transient network failures
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Total attempts, including the first
+    pub attempts: u32,
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            attempts: DEFAULT_ATTEMPTS,
+            base_delay: Duration::from_millis(500),
+            max_delay: Duration::from_secs(8),
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// The default policy with the configured attempt count
+    pub fn from_config() -> Self {
+        let attempts = load_config()
+            .ok()
+            .and_then(|config| config.network_retries)
+            .unwrap_or(DEFAULT_ATTEMPTS);
+        Self::default().with_attempts(attempts)
+    }
+
+    pub fn with_attempts(mut self, attempts: u32) -> Self {
+        self.attempts = attempts.max(1);
+        self
+    }
+
+    /// Wait before retrying after `failed` attempts: exponential, capped, with jitter
+    /// between half and all of it so parallel requests don't retry in lockstep
+    pub fn delay(&self, failed: u32) -> Duration {
+        let exponential = self.base_delay.saturating_mul(2u32.saturating_pow(failed.saturating_sub(1)));
+        let capped = exponential.min(self.max_delay);
+        capped.mul_f64(0.5 + jitter() * 0.5)
+    }
+
+    /// Run an async operation until it succeeds, `should_retry` rejects its result, or attempts run out
+    pub async fn run<T, E, F, Fut>(&self, what: &str, mut operation: F, should_retry: impl Fn(&Result<T, E>) -> bool) -> Result<T, E>
+    where
+        F: FnMut() -> Fut,
+        Fut: Future<Output = Result<T, E>>,
+    {
+        let mut failed = 0;
+        loop {
+            let result = operation().await;
+            failed += 1;
+            if failed >= self.attempts || !should_retry(&result) {
+                return result;
+            }
+            let delay = self.delay(failed);
+            announce_retry(what, delay, failed + 1, self.attempts);
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// Blocking version of `run`, for git commands
+    pub fn run_blocking<T, E>(&self, what: &str, mut operation: impl FnMut() -> Result<T, E>, should_retry: impl Fn(&Result<T, E>) -> bool) -> Result<T, E> {
+        let mut failed = 0;
+        loop {
+            let result = operation();
+            failed += 1;
+            if failed >= self.attempts || !should_retry(&result) {
+                return result;
+            }
+            let delay = self.delay(failed);
+            announce_retry(what, delay, failed + 1, self.attempts);
+            std::thread::sleep(delay);
+        }
+    }
+}
+
+/// Timeouts, dropped connections, rate limiting and server errors
+pub fn is_transient_http(result: &reqwest::Result<reqwest::Response>) -> bool {
+    match result {
+        Ok(response) => {
+            let status = response.status();
+            status.is_server_error() || status == reqwest::StatusCode::TOO_MANY_REQUESTS
+        }
+        Err(error) => error.is_timeout() || error.is_connect() || error.is_request(),
+    }
+}
+
+/// A git network command that failed for reasons other than the request itself
+pub fn is_transient_git(result: &std::io::Result<Output>) -> bool {
+    match result {
+        Ok(output) if !output.status.success() => is_transient_git_error(&String::from_utf8_lossy(&output.stderr)),
+        _ => false,
+    }
+}
+
+/// `is_transient_git` for a git command run through `Process`
+pub fn is_transient_git_process(result: &anyhow::Result<ProcessOutput>) -> bool {
+    match result {
+        Ok(output) if !output.success() => is_transient_git_error(&output.stderr),
+        _ => false,
+    }
+}
+
+fn is_transient_git_error(stderr: &str) -> bool {
+    TRANSIENT_GIT_ERRORS.iter().any(|message| stderr.contains(message))
+}
+
+fn announce_retry(what: &str, delay: Duration, attempt: u32, attempts: u32) {
+    outln!("  🔁 {} failed, retrying in {:.1}s (attempt {}/{})...", what, delay.as_secs_f64(), attempt, attempts);
+}
+
+/// A value in [0, 1) that varies between calls; precise randomness isn't needed
+fn jitter() -> f64 {
+    let nanos = std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|elapsed| elapsed.subsec_nanos())
+        .unwrap_or(0);
+    (nanos % 1000) as f64 / 1000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+
+    fn quick_policy(attempts: u32) -> RetryPolicy {
+        RetryPolicy {
+            attempts,
+            base_delay: Duration::from_millis(1),
+            max_delay: Duration::from_millis(2),
+        }
+    }
+
+    #[test]
+    fn test_delay_grows_and_is_capped() {
+        let policy = RetryPolicy::default();
+        assert!(policy.delay(1) <= Duration::from_millis(500));
+        assert!(policy.delay(1) >= Duration::from_millis(250));
+        assert!(policy.delay(3) >= Duration::from_millis(1000));
+        assert!(policy.delay(20) <= policy.max_delay);
+    }
+
+    #[test]
+    fn test_retries_until_success_or_attempts_run_out() {
+        let calls = Cell::new(0);
+        let result: Result<u32, &str> = quick_policy(3).run_blocking("fetch", || {
+            calls.set(calls.get() + 1);
+            if calls.get() < 2 { Err("reset") } else { Ok(calls.get()) }
+        }, |result| result.is_err());
+        assert_eq!(result, Ok(2));
+
+        calls.set(0);
+        let result: Result<u32, &str> = quick_policy(3).run_blocking("fetch", || {
+            calls.set(calls.get() + 1);
+            Err("reset")
+        }, |result| result.is_err());
+        assert_eq!(result, Err("reset"));
+        assert_eq!(calls.get(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_permanent_failures_are_not_retried() {
+        let calls = Cell::new(0);
+        let result: Result<(), &str> = quick_policy(5).run("fetch", || {
+            calls.set(calls.get() + 1);
+            async { Err("not found") }
+        }, |_| false).await;
+        assert_eq!(result, Err("not found"));
+        assert_eq!(calls.get(), 1);
+    }
+}
@@ -0,0 +1,91 @@
+use anyhow::Result;
+
+use crate::error::StacksError;
+
+/// Longest slug kept; branch names, directories and tmux targets all stay readable
+const MAX_LENGTH: usize = 64;
+
+/// Turn a task name into a slug usable as a branch suffix, directory name and tmux target
+///
+/// Spaces, slashes, dots (tmux's pane separator) and other punctuation become `-`; characters
+/// outside ASCII are dropped after the common accents are folded ("Café login" → "Cafe-login"). Names with nothing
+/// usable left are rejected instead of guessed at.
+pub fn sanitize(raw: &str) -> Result<String> {
+    let mut slug = String::new();
+    for c in raw.trim().chars() {
+        let c = fold_accent(c).unwrap_or(c);
+        match c {
+            'a'..='z' | 'A'..='Z' | '0'..='9' | '_' => slug.push(c),
+            _ if c.is_ascii() && !slug.is_empty() && !slug.ends_with('-') => slug.push('-'),
+            _ => {}
+        }
+    }
+    slug.truncate(MAX_LENGTH);
+    let slug = slug.trim_end_matches('-').to_string();
+
+    if slug.is_empty() {
+        return Err(StacksError::validation(format!("Invalid task name: '{}'", raw))
+            .with_reason("Nothing is left of it that can be used in a branch, directory or tmux session name")
+            .with_hint("Use letters, digits, '-' or '_', e.g. 'fix-login'")
+            .into());
+    }
+    Ok(slug)
+}
+
+/// Sanitize a task name and say so when it had to change
+pub fn sanitize_reporting(raw: &str) -> Result<String> {
+    let task_name = sanitize(raw)?;
+    if task_name != raw {
+        outln!("📝 Task name normalized: '{}' → '{}'", raw, task_name);
+    }
+    Ok(task_name)
+}
+
+fn fold_accent(c: char) -> Option<char> {
+    let folded = match c {
+        'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' => 'a',
+        'À' | 'Á' | 'Â' | 'Ã' | 'Ä' | 'Å' => 'A',
+        'ç' => 'c',
+        'Ç' => 'C',
+        'è' | 'é' | 'ê' | 'ë' => 'e',
+        'È' | 'É' | 'Ê' | 'Ë' => 'E',
+        'ì' | 'í' | 'î' | 'ï' => 'i',
+        'Ì' | 'Í' | 'Î' | 'Ï' => 'I',
+        'ñ' => 'n',
+        'Ñ' => 'N',
+        'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'ø' => 'o',
+        'Ò' | 'Ó' | 'Ô' | 'Õ' | 'Ö' | 'Ø' => 'O',
+        'ù' | 'ú' | 'û' | 'ü' => 'u',
+        'Ù' | 'Ú' | 'Û' | 'Ü' => 'U',
+        'ý' | 'ÿ' => 'y',
+        'Ý' => 'Y',
+        _ => return None,
+    };
+    Some(folded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sanitize() {
+        assert_eq!(sanitize("fix-login").unwrap(), "fix-login");
+        assert_eq!(sanitize("  fix the login  bug ").unwrap(), "fix-the-login-bug");
+        assert_eq!(sanitize("api/v2: auth").unwrap(), "api-v2-auth");
+        assert_eq!(sanitize("Café déjà vu").unwrap(), "Cafe-deja-vu");
+        assert_eq!(sanitize("release..1.2").unwrap(), "release-1-2");
+        assert_eq!(sanitize("-.hidden").unwrap(), "hidden");
+        assert_eq!(sanitize("emoji 🚀 launch").unwrap(), "emoji-launch");
+        assert_eq!(sanitize(&"a".repeat(100)).unwrap().len(), MAX_LENGTH);
+    }
+
+    #[test]
+    fn test_sanitize_rejects_unusable_names() {
+        assert!(sanitize("").is_err());
+        assert!(sanitize("   ").is_err());
+        assert!(sanitize("🚀🚀").is_err());
+        assert!(sanitize("日本語").is_err());
+        assert!(sanitize("/.-").is_err());
+    }
+}
@@ -0,0 +1,35 @@
+use std::time::Duration;
+use anyhow::Result;
+use is_terminal::IsTerminal;
+
+use crate::error::StacksError;
+use crate::utils::process::Process;
+
+/// Whether prompts and pickers can be shown: stdin and stdout are both terminals
+///
+/// Claude hooks, CI jobs and pipes get neither, and a prompt there would wait forever.
+pub fn is_interactive() -> bool {
+    std::io::stdin().is_terminal() && std::io::stdout().is_terminal()
+}
+
+/// Fail with `hint` when `what` would have to prompt without a terminal to prompt on
+pub fn require_interactive(what: &str, hint: impl Into<String>) -> Result<()> {
+    match is_interactive() {
+        true => Ok(()),
+        false => Err(StacksError::validation(format!("{} needs an interactive terminal", what))
+            .with_hint(hint)
+            .into()),
+    }
+}
+
+/// Open `url` in the default browser; false when no browser could be launched
+pub fn open_browser(url: &str) -> bool {
+    let opener = if cfg!(target_os = "macos") { "open" } else { "xdg-open" };
+    match Process::new(opener).arg(url).timeout(Duration::from_secs(5)).output() {
+        Ok(output) => output.success(),
+        Err(error) => {
+            tracing::debug!("failed to open the browser: {:#}", error);
+            false
+        }
+    }
+}
@@ -0,0 +1,141 @@
+//! Checkout, push, pull and cleanup against local bare repositories; see `support` for the setup.
+
+mod support;
+
+use predicates::prelude::*;
+use support::Harness;
+
+const STACK_CLAUDE_MD: &str = "# Description: Lint TypeScript\nRun the linter before committing.\n";
+
+/// A harness with the `ts-lint` stack published and checked out, and the checkout committed
+fn checked_out() -> Harness {
+    let harness = Harness::new();
+    harness.add_stack("ts-lint", &[
+        ("CLAUDE.md", STACK_CLAUDE_MD),
+        (".claude/agents/reviewer.md", "---\nname: reviewer\ndescription: Reviews lint fixes\n---\nReview.\n"),
+    ]);
+    harness
+        .stacks(&["checkout", "ts-lint"])
+        .success()
+        .stdout(predicate::str::contains("Stack ts-lint checkout complete"));
+    harness.commit_project("Check out ts-lint");
+    harness
+}
+
+#[test]
+fn test_checkout_adds_stack_as_subtree() {
+    let harness = checked_out();
+    let project = harness.project();
+
+    assert_eq!(harness.read_project_file("stacks/ts-lint/CLAUDE.md"), STACK_CLAUDE_MD);
+    assert!(project.join(".claude/agents/ts-lint_reviewer.md").is_symlink());
+    assert!(harness.read_project_file("CLAUDE.md").contains("@stacks/ts-lint/CLAUDE.md"));
+    assert!(harness.read_project_file("stacks.lock").contains(&harness.stack_repo("ts-lint").to_string_lossy().to_string()));
+
+    let log = harness.git(&project, &["log", "--format=%s"]);
+    assert!(log.contains("Squashed 'stacks/ts-lint/' content from commit"));
+
+    // The checkout is ordinary history that travels with the project
+    harness.git(&project, &["push", "--quiet", "origin", support::BRANCH]);
+    let origin = harness.path("origin.git");
+    let pushed = harness.git(&origin, &["show", &format!("{}:stacks/ts-lint/CLAUDE.md", support::BRANCH)]);
+    assert_eq!(pushed, STACK_CLAUDE_MD);
+}
+
+#[test]
+fn test_push_sends_stack_edits_upstream() {
+    let harness = checked_out();
+    let edited = format!("{}Fix every warning.\n", STACK_CLAUDE_MD);
+    harness.write_project_files(&[("stacks/ts-lint/CLAUDE.md", &edited)]);
+
+    harness
+        .stacks(&["push", "ts-lint", "--message", "require clean lint"])
+        .success()
+        .stdout(predicate::str::contains("Successfully pushed subtree changes"));
+
+    assert_eq!(harness.stack_file("ts-lint", "CLAUDE.md"), edited);
+    assert_eq!(harness.stack_log("ts-lint")[0], "feat(ts-lint): require clean lint");
+}
+
+#[test]
+fn test_push_without_changes_leaves_upstream_alone() {
+    let harness = checked_out();
+
+    harness
+        .stacks(&["push", "ts-lint"])
+        .success()
+        .stdout(predicate::str::contains("No changes detected in stack 'ts-lint'"));
+
+    assert_eq!(harness.stack_log("ts-lint"), vec!["Add ts-lint"]);
+}
+
+#[test]
+fn test_pull_merges_upstream_commits() {
+    let harness = checked_out();
+    harness.commit_to_stack("ts-lint", &[(".claude/commands/lint.md", "Run the linter.\n")], "Add lint command");
+
+    harness
+        .stacks(&["pull", "ts-lint"])
+        .success()
+        .stdout(predicate::str::contains("Stack 'ts-lint' updated successfully"));
+
+    assert_eq!(harness.read_project_file("stacks/ts-lint/.claude/commands/lint.md"), "Run the linter.\n");
+    assert!(harness.project().join(".claude/commands/ts-lint_lint.md").is_symlink());
+
+    harness
+        .stacks(&["pull", "ts-lint"])
+        .success()
+        .stdout(predicate::str::contains("Subtree is already up to date"));
+}
+
+#[test]
+fn test_pull_stops_on_conflicts_until_aborted() {
+    let harness = checked_out();
+    let ours = "# Description: Lint TypeScript\nRun the linter on save.\n";
+    harness.write_project_files(&[("stacks/ts-lint/CLAUDE.md", ours)]);
+    harness.commit_project("Lint on save");
+    harness.commit_to_stack("ts-lint", &[("CLAUDE.md", "# Description: Lint TypeScript\nRun the linter in CI.\n")], "Lint in CI");
+
+    harness
+        .stacks(&["pull", "ts-lint"])
+        .success()
+        .stdout(predicate::str::contains("Upstream changes conflict with this project's copy of 'ts-lint'"))
+        .stdout(predicate::str::contains("stacks pull --continue"));
+    assert!(harness.read_project_file("stacks/ts-lint/CLAUDE.md").contains("<<<<<<<"));
+
+    // Another pull waits for the conflict to be dealt with
+    harness
+        .stacks(&["pull", "ts-lint"])
+        .failure()
+        .stderr(predicate::str::contains("waiting for conflict resolution"));
+
+    harness
+        .stacks(&["pull", "--abort"])
+        .success()
+        .stdout(predicate::str::contains("Pull of stack 'ts-lint' aborted"));
+    assert_eq!(harness.read_project_file("stacks/ts-lint/CLAUDE.md"), ours);
+    assert_eq!(harness.git(&harness.project(), &["status", "--porcelain", "--untracked-files=no"]), "");
+}
+
+#[test]
+fn test_cleanup_needs_a_terminal() {
+    let harness = checked_out();
+
+    // The main worktree is never cleaned up
+    harness
+        .stacks(&["cleanup"])
+        .success()
+        .stdout(predicate::str::contains("No project worktrees found to clean up"));
+
+    let feature = harness.path("feature");
+    harness.git(&harness.project(), &["worktree", "add", "--quiet", "-b", "feature", &feature.to_string_lossy()]);
+    harness
+        .stacks(&["cleanup"])
+        .failure()
+        .stdout(predicate::str::contains(feature.to_string_lossy().to_string()))
+        .stderr(predicate::str::contains("stacks cleanup needs an interactive terminal"));
+
+    // Refusing happens before anything is pushed or unlinked
+    assert!(feature.join("stacks/ts-lint/CLAUDE.md").exists());
+    assert_eq!(harness.stack_log("ts-lint"), vec!["Add ts-lint"]);
+}
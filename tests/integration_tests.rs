@@ -1,4 +1,5 @@
 use std::fs;
+use std::sync::{Arc, Mutex};
 use tempfile::TempDir;
 use assert_cmd::Command;
 use predicates::prelude::*;
@@ -63,6 +64,71 @@ async fn test_stack_discovery() {
         .stdout(predicate::str::contains("Claude Code workflow stacks"));
 }
 
+#[test]
+fn test_version_describes_build() {
+    let mut cmd = Command::cargo_bin("stacks").unwrap();
+    cmd.arg("--version")
+        .assert()
+        .success()
+        .stdout(predicate::str::starts_with(format!("stacks {}", env!("CARGO_PKG_VERSION"))));
+
+    let mut cmd = Command::cargo_bin("stacks").unwrap();
+    let output = cmd.args(["--version", "--json"]).assert().success().get_output().stdout.clone();
+    let version: stacks::core::schema::VersionOutput = serde_json::from_slice(&output).expect("--version --json is not a VersionOutput");
+    assert_eq!(version, stacks::core::schema::VersionOutput::current());
+    assert!(!version.target.is_empty());
+}
+
+#[test]
+fn test_grep_shows_providing_stack() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    create_test_stacks_dir(&temp_dir).expect("Failed to create test structure");
+
+    let mut cmd = Command::cargo_bin("stacks").unwrap();
+    cmd.current_dir(temp_dir.path())
+        .args(["grep", "-i", "AGENT CONTENT"])
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("linting\n"))
+        .stdout(predicate::str::contains("agent stacks/linting/.claude/agents/linting-agent.md"))
+        .stdout(predicate::str::contains("5: Linting agent content"))
+        .stdout(predicate::str::contains("stacks/testing/.claude/agents/testing-agent.md"));
+
+    let mut cmd = Command::cargo_bin("stacks").unwrap();
+    cmd.current_dir(temp_dir.path())
+        .args(["grep", "-F", "npm run lint"])
+        .assert()
+        .code(1)
+        .stderr(predicate::str::contains("No matches for npm run lint in installed stacks"));
+}
+
+#[test]
+fn test_agents_and_commands_inventory() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let claude_dir = temp_dir.path().join(".claude");
+    fs::create_dir_all(claude_dir.join("agents")).unwrap();
+    fs::create_dir_all(claude_dir.join("commands")).unwrap();
+    fs::write(claude_dir.join("agents/reviewer.md"), "---\nname: code-reviewer\ndescription: Reviews diffs\n---\nReview the diff").unwrap();
+    fs::write(claude_dir.join("commands/fix-lint.md"), "# Fix every lint error\nRun the linter").unwrap();
+
+    let mut cmd = Command::cargo_bin("stacks").unwrap();
+    cmd.current_dir(temp_dir.path())
+        .env("CLAUDE_CONFIG_DIR", temp_dir.path().join("home"))
+        .arg("agents")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("1 agent(s) available to claude"))
+        .stdout(predicate::str::contains("code-reviewer - Reviews diffs"));
+
+    let mut cmd = Command::cargo_bin("stacks").unwrap();
+    cmd.current_dir(temp_dir.path())
+        .env("CLAUDE_CONFIG_DIR", temp_dir.path().join("home"))
+        .arg("commands")
+        .assert()
+        .success()
+        .stdout(predicate::str::contains("/fix-lint - Fix every lint error"));
+}
+
 #[tokio::test]
 async fn test_invalid_directory() {
     let temp_dir = TempDir::new().expect("Failed to create temp dir");
@@ -77,13 +143,162 @@ async fn test_invalid_directory() {
     // This is a minimal smoke test
 }
 
+/// Run git in `dir`, failing the test when git fails
+fn git(dir: &std::path::Path, args: &[&str]) {
+    let status = std::process::Command::new("git")
+        .args(args)
+        .current_dir(dir)
+        .status()
+        .expect("Failed to run git");
+    assert!(status.success(), "git {:?} failed", args);
+}
+
+/// A git repository with the test stacks, committed when `commit` is set
+fn stacks_repository(commit: bool) -> TempDir {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    git(temp_dir.path(), &["init", "--quiet"]);
+    create_test_stacks_dir(&temp_dir).expect("Failed to create test structure");
+    if commit {
+        git(temp_dir.path(), &["add", "-A"]);
+        git(temp_dir.path(), &["-c", "user.name=test", "-c", "user.email=test@example.com", "commit", "--quiet", "-m", "Add stacks"]);
+    }
+    temp_dir
+}
+
+#[test]
+fn test_status_porcelain_exit_codes() {
+    // Uncommitted stack files count as changes
+    let temp_dir = stacks_repository(false);
+    let mut cmd = Command::cargo_bin("stacks").unwrap();
+    cmd.current_dir(temp_dir.path())
+        .args(["status", "--porcelain"])
+        .assert()
+        .code(1)
+        .stdout(predicate::str::contains("\tlinting\tmodified\t"));
+    
+    let temp_dir = stacks_repository(true);
+    let mut cmd = Command::cargo_bin("stacks").unwrap();
+    cmd.current_dir(temp_dir.path())
+        .args(["status", "--porcelain"])
+        .assert()
+        .code(0)
+        .stdout(predicate::str::contains("\tlinting\tclean\t0\t"));
+}
+
+#[test]
+fn test_query_answers_from_local_state() {
+    let temp_dir = stacks_repository(false);
+    let mut cmd = Command::cargo_bin("stacks").unwrap();
+    cmd.current_dir(temp_dir.path())
+        .args(["query", "dirty-stacks", "--count"])
+        .assert()
+        .success()
+        .stdout("2\n");
+
+    let temp_dir = stacks_repository(true);
+    let mut cmd = Command::cargo_bin("stacks").unwrap();
+    cmd.current_dir(temp_dir.path())
+        .args(["query", "dirty-stacks"])
+        .assert()
+        .success()
+        .stdout("");
+    let mut cmd = Command::cargo_bin("stacks").unwrap();
+    cmd.current_dir(temp_dir.path())
+        .args(["query", "stacks"])
+        .assert()
+        .success()
+        .stdout("linting\ntesting\n");
+
+    // Not a task worktree: no answer
+    let mut cmd = Command::cargo_bin("stacks").unwrap();
+    cmd.current_dir(temp_dir.path())
+        .args(["query", "current-task"])
+        .assert()
+        .code(1)
+        .stdout("");
+}
+
+#[test]
+fn test_workspace_members_do_not_fan_out_again() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    fs::write(temp_dir.path().join("stacks-workspace.toml"), "members = [\"app\"]\n").unwrap();
+    fs::create_dir(temp_dir.path().join("app")).unwrap();
+
+    let mut cmd = Command::cargo_bin("stacks").unwrap();
+    cmd.current_dir(temp_dir.path().join("app"))
+        .env("STACKS_WORKSPACE_MEMBER", "1")
+        .args(["status", "--workspace"])
+        .assert()
+        .failure()
+        .stderr(predicate::str::contains("inside a workspace member"));
+}
+
+#[test]
+fn test_status_json_lists_worktree_stacks() {
+    let temp_dir = stacks_repository(true);
+    let mut cmd = Command::cargo_bin("stacks").unwrap();
+    let output = cmd.current_dir(temp_dir.path())
+        .args(["status", "--json"])
+        .assert()
+        .code(0)
+        .get_output()
+        .stdout
+        .clone();
+    let status: stacks::core::schema::StatusOutput = serde_json::from_slice(&output).expect("status --json is not a StatusOutput");
+    assert_eq!(status.schema_version, stacks::core::schema::SCHEMA_VERSION);
+    let stack_names: Vec<&str> = status.worktrees[0].stacks.iter().map(|stack| stack.name.as_str()).collect();
+    assert_eq!(stack_names, vec!["linting", "testing"]);
+}
+
+#[tokio::test]
+async fn test_library_output_goes_to_sink() {
+    let lines = Arc::new(Mutex::new(Vec::new()));
+    let captured = lines.clone();
+    assert!(stacks::set_output_sink(move |_, line| captured.lock().unwrap().push(line.to_string())));
+
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let updater = stacks::ClaudeMdUpdater::with_path(temp_dir.path().join("CLAUDE.md"));
+    updater.add_stack_import("linting").await.unwrap();
+
+    assert!(updater.has_stack_import("linting").await.unwrap());
+    assert!(lines.lock().unwrap().iter().any(|line| line.contains("@stacks/linting/CLAUDE.md")));
+}
+
+#[test]
+fn test_serve_answers_json_rpc() {
+    let temp_dir = TempDir::new().expect("Failed to create temp dir");
+    let input = [
+        r#"{"jsonrpc":"2.0","id":1,"method":"initialize"}"#,
+        r#"{"jsonrpc":"2.0","id":2,"method":"stacks/unknown"}"#,
+        r#"{"jsonrpc":"2.0","id":3,"method":"stacks/checkout","params":{}}"#,
+        r#"{"jsonrpc":"2.0","method":"exit"}"#,
+    ].join("\n");
+
+    let mut cmd = Command::cargo_bin("stacks").unwrap();
+    let output = cmd.current_dir(temp_dir.path())
+        .args(["serve", "--stdio"])
+        .write_stdin(input)
+        .assert()
+        .success()
+        .get_output()
+        .stdout
+        .clone();
+
+    let responses: Vec<serde_json::Value> = String::from_utf8(output).unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).expect("serve wrote a line that isn't JSON"))
+        .filter(|message: &serde_json::Value| message.get("id").is_some())
+        .collect();
+    assert_eq!(responses.len(), 3);
+    assert_eq!(responses[0]["result"]["name"], "stacks");
+    assert_eq!(responses[1]["error"]["code"], -32601);
+    assert_eq!(responses[2]["error"]["code"], -32602);
+}
+
 #[cfg(test)]
 mod unit_tests {
     use super::*;
     
-    // These tests require the main crate modules to be accessible
-    // For now, we'll focus on integration tests that test the CLI interface
-    
     #[test]
     fn test_temp_dir_creation() {
         let temp_dir = TempDir::new().expect("Failed to create temp dir");
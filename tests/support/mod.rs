@@ -0,0 +1,171 @@
+//! Offline end-to-end harness: a registry of local bare repositories and a project with a bare
+//! origin, all inside one temporary directory.
+//!
+//! The `stacks` binary runs against the registry through `--mock-registry`, with its home, config
+//! and git identity isolated from the machine running the tests.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command as StdCommand;
+use assert_cmd::assert::Assert;
+use assert_cmd::Command;
+use tempfile::TempDir;
+
+pub const BRANCH: &str = "main";
+
+pub struct Harness {
+    dir: TempDir,
+}
+
+impl Harness {
+    /// A project repository with one commit, pushed to its bare origin, and an empty registry
+    pub fn new() -> Self {
+        let harness = Self { dir: TempDir::new().expect("Failed to create temp dir") };
+        fs::create_dir_all(harness.home()).unwrap();
+        fs::create_dir_all(harness.registry().join("stacks")).unwrap();
+        fs::write(
+            harness.home().join(".gitconfig"),
+            "[user]\n\tname = Stacks Test\n\temail = test@example.com\n[init]\n\tdefaultBranch = main\n",
+        )
+        .unwrap();
+
+        let origin = harness.path("origin.git");
+        harness.git(harness.dir.path(), &["init", "--quiet", "--bare", "origin.git"]);
+        harness.git(harness.dir.path(), &["init", "--quiet", "project"]);
+        fs::write(harness.project().join("README.md"), "# Project\n").unwrap();
+        harness.commit_project("Initial commit");
+        harness.git(&harness.project(), &["remote", "add", "origin", &origin.to_string_lossy()]);
+        harness.git(&harness.project(), &["push", "--quiet", "origin", BRANCH]);
+        harness
+    }
+
+    pub fn path(&self, relative: &str) -> PathBuf {
+        self.dir.path().join(relative)
+    }
+
+    pub fn home(&self) -> PathBuf {
+        self.path("home")
+    }
+
+    /// Directory passed to `--mock-registry`
+    pub fn registry(&self) -> PathBuf {
+        self.path("registry")
+    }
+
+    pub fn project(&self) -> PathBuf {
+        self.path("project")
+    }
+
+    /// Bare repository a registry stack is checked out from and pushed to
+    pub fn stack_repo(&self, name: &str) -> PathBuf {
+        self.registry().join("stacks").join(name)
+    }
+
+    /// Publish a stack to the registry as a bare repository holding `files`
+    pub fn add_stack(&self, name: &str, files: &[(&str, &str)]) {
+        let repo = self.stack_repo(name);
+        self.git(self.dir.path(), &["init", "--quiet", "--bare", &repo.to_string_lossy()]);
+        self.commit_to_stack(name, files, &format!("Add {}", name));
+    }
+
+    /// Commit `files` to a stack's upstream, as another contributor to the stack would
+    pub fn commit_to_stack(&self, name: &str, files: &[(&str, &str)], message: &str) {
+        let work = self.path(&format!("work-{}", name));
+        match work.exists() {
+            true => {
+                self.git(&work, &["pull", "--quiet", "origin", BRANCH]);
+            }
+            false => {
+                self.git(self.dir.path(), &["clone", "--quiet", &self.stack_repo(name).to_string_lossy(), &work.to_string_lossy()]);
+            }
+        }
+        write_files(&work, files);
+        self.git(&work, &["add", "-A"]);
+        self.git(&work, &["commit", "--quiet", "-m", message]);
+        self.git(&work, &["push", "--quiet", "origin", &format!("HEAD:{}", BRANCH)]);
+    }
+
+    /// A file as it is on the stack repository's branch
+    pub fn stack_file(&self, name: &str, file: &str) -> String {
+        self.git(&self.stack_repo(name), &["show", &format!("{}:{}", BRANCH, file)])
+    }
+
+    /// Subjects of the stack repository's commits, newest first
+    pub fn stack_log(&self, name: &str) -> Vec<String> {
+        self.git(&self.stack_repo(name), &["log", "--format=%s", BRANCH])
+            .lines()
+            .map(str::to_string)
+            .collect()
+    }
+
+    /// Edit files of the project's working tree
+    pub fn write_project_files(&self, files: &[(&str, &str)]) {
+        write_files(&self.project(), files);
+    }
+
+    pub fn read_project_file(&self, file: &str) -> String {
+        fs::read_to_string(self.project().join(file)).unwrap_or_else(|_| panic!("Failed to read {}", file))
+    }
+
+    /// Commit everything in the project, as a user does after checking stacks out
+    pub fn commit_project(&self, message: &str) {
+        self.git(&self.project(), &["add", "-A"]);
+        self.git(&self.project(), &["commit", "--quiet", "-m", message]);
+    }
+
+    /// Run `stacks` in the project against the mock registry
+    pub fn stacks(&self, args: &[&str]) -> Assert {
+        Command::cargo_bin("stacks")
+            .unwrap()
+            .envs(self.environment())
+            .env_remove("GITHUB_TOKEN")
+            .current_dir(self.project())
+            .arg("--mock-registry")
+            .arg(self.registry())
+            .args(args)
+            .assert()
+    }
+
+    /// Run git in `dir` and return its output; panics when git fails
+    pub fn git(&self, dir: &Path, args: &[&str]) -> String {
+        let output = StdCommand::new("git")
+            .envs(self.environment())
+            .current_dir(dir)
+            .args(args)
+            .output()
+            .expect("Failed to run git");
+        assert!(
+            output.status.success(),
+            "git {} failed: {}",
+            args.join(" "),
+            String::from_utf8_lossy(&output.stderr)
+        );
+        String::from_utf8_lossy(&output.stdout).to_string()
+    }
+
+    /// Keep the machine's git config and stacks config out of the commands the tests run
+    fn environment(&self) -> Vec<(&'static str, PathBuf)> {
+        vec![
+            ("HOME", self.home()),
+            ("XDG_CONFIG_HOME", self.home().join(".config")),
+            ("XDG_CACHE_HOME", self.home().join(".cache")),
+            ("GIT_CONFIG_NOSYSTEM", PathBuf::from("1")),
+        ]
+    }
+}
+
+impl Default for Harness {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn write_files(root: &Path, files: &[(&str, &str)]) {
+    for (file, content) in files {
+        let path = root.join(file);
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent).unwrap();
+        }
+        fs::write(&path, content).unwrap();
+    }
+}